@@ -8,7 +8,7 @@ mod utils;
 
 use cast::{Cast, SimpleCast, TxBuilder};
 use foundry_config::Config;
-use utils::get_http_provider;
+use utils::get_provider;
 mod opts;
 use crate::{cmd::Cmd, utils::consume_config_rpc_url};
 use cast::InterfacePath;
@@ -21,7 +21,7 @@ use ethers::{
     types::{Address, NameOrAddress, U256},
 };
 use eyre::WrapErr;
-use foundry_common::fs;
+use foundry_common::{fs, provider::ProvidersManager};
 use foundry_config::Chain;
 use foundry_utils::{
     format_tokens,
@@ -274,10 +274,12 @@ async fn main() -> eyre::Result<()> {
             resend,
         } => {
             let config = Config::from(&eth);
-            let provider = get_http_provider(
+            let providers = ProvidersManager::new(config.rpc_endpoints.clone());
+            let provider = get_provider(
+                &providers,
                 &config.eth_rpc_url.unwrap_or_else(|| "http://localhost:8545".to_string()),
                 false,
-            );
+            )?;
             let chain: Chain = if let Some(chain) = eth.chain {
                 chain.into()
             } else {
@@ -290,6 +292,8 @@ async fn main() -> eyre::Result<()> {
                     WalletType::Ledger(leger) => leger.address(),
                     WalletType::Local(local) => local.address(),
                     WalletType::Trezor(trezor) => trezor.address(),
+                    WalletType::Remote(remote) => remote.address(),
+                    WalletType::Aws(aws) => aws.address(),
                 };
 
                 // prevent misconfigured hwlib from sending a transaction that defies
@@ -365,6 +369,46 @@ async fn main() -> eyre::Result<()> {
                         )
                         .await?;
                     }
+                    WalletType::Remote(signer) => {
+                        cast_send(
+                            &signer,
+                            from,
+                            to,
+                            (sig, args),
+                            tx.gas_limit,
+                            tx.gas_price,
+                            tx.priority_gas_price,
+                            tx.value,
+                            tx.nonce,
+                            chain,
+                            config.etherscan_api_key,
+                            cast_async,
+                            tx.legacy,
+                            confirmations,
+                            to_json,
+                        )
+                        .await?;
+                    }
+                    WalletType::Aws(signer) => {
+                        cast_send(
+                            &signer,
+                            from,
+                            to,
+                            (sig, args),
+                            tx.gas_limit,
+                            tx.gas_price,
+                            tx.priority_gas_price,
+                            tx.value,
+                            tx.nonce,
+                            chain,
+                            config.etherscan_api_key,
+                            cast_async,
+                            tx.legacy,
+                            confirmations,
+                            to_json,
+                        )
+                        .await?;
+                    }
                 } // Checking if signer isn't the default value
                   // 00a329c0648769A73afAc7F9381E08FB43dBEA72.
             } else if config.sender !=
@@ -689,6 +733,7 @@ async fn main() -> eyre::Result<()> {
             generate(shell, &mut Opts::command(), "cast", &mut std::io::stdout())
         }
         Subcommands::Run(cmd) => cmd.run()?,
+        Subcommands::ReplayBlock(cmd) => cmd.run()?,
         Subcommands::Rpc(cmd) => cmd.run()?.await?,
     };
     Ok(())
@@ -6,6 +6,7 @@
 //! [`foundry_config::Config`].
 
 pub mod find_block;
+pub mod replay;
 pub mod rpc;
 pub mod run;
 pub mod wallet;
@@ -0,0 +1,104 @@
+use crate::{cmd::Cmd, utils::consume_config_rpc_url};
+use clap::Parser;
+use ethers::{
+    prelude::Provider,
+    solc::utils::RuntimeOrHandle,
+    types::{Address, Bytes},
+};
+use eyre::WrapErr;
+use forge::{
+    executor::{opts::EvmOpts, Backend, ExecutorBuilder},
+    replay::BlockReplay,
+};
+use foundry_common::fs;
+use foundry_config::{find_project_root_path, Config};
+use std::{path::Path, str::FromStr};
+use yansi::Paint;
+
+#[derive(Debug, Clone, Parser)]
+pub struct ReplayBlockArgs {
+    #[clap(help = "The number of the block to replay.", value_name = "BLOCK")]
+    block: u64,
+    #[clap(short, long, env = "ETH_RPC_URL", value_name = "URL")]
+    rpc_url: Option<String>,
+    #[clap(
+        long = "override",
+        help = "Replaces the runtime code of a contract, e.g. 0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045:out/Patched.bin",
+        long_help = "Replaces the runtime code of a contract. The code is either hex encoded or a path to a file containing the hex encoded code, e.g. 0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045:out/Patched.bin",
+        value_name = "ADDRESS:CODE"
+    )]
+    overrides: Vec<String>,
+}
+
+impl Cmd for ReplayBlockArgs {
+    type Output = ();
+    fn run(self) -> eyre::Result<Self::Output> {
+        RuntimeOrHandle::new().block_on(self.replay())
+    }
+}
+
+impl ReplayBlockArgs {
+    async fn replay(self) -> eyre::Result<()> {
+        let figment = Config::figment_with_root(find_project_root_path().unwrap());
+        let mut evm_opts = figment.extract::<EvmOpts>()?;
+        let config = Config::from_provider(figment).sanitized();
+
+        let overrides =
+            self.overrides.iter().map(|s| parse_override(s)).collect::<eyre::Result<Vec<_>>>()?;
+
+        let rpc_url = consume_config_rpc_url(self.rpc_url);
+        let provider = Provider::try_from(rpc_url.as_str())?;
+
+        // Fork the state right before the block
+        evm_opts.fork_url = Some(rpc_url);
+        evm_opts.fork_block_number = Some(self.block.saturating_sub(1));
+        let env = evm_opts.evm_env().await;
+        let db = Backend::spawn(evm_opts.get_fork(&config, env.clone()));
+        let executor = ExecutorBuilder::default()
+            .with_config(env)
+            .with_spec(crate::utils::evm_spec(&config.evm_version))
+            .build(db);
+
+        let replay = overrides
+            .into_iter()
+            .fold(BlockReplay::new(executor), |replay, (address, code)| {
+                replay.with_override(address, code)
+            });
+
+        println!("Replaying block {}...", self.block);
+        let report = replay.replay(&provider, self.block).await?;
+        println!("{}", report);
+
+        if !report.matches() {
+            eyre::bail!("{}", Paint::red("Replay differs from the canonical results"))
+        }
+        Ok(())
+    }
+}
+
+/// Parses an override in the form of `<ADDRESS>:<CODE>`, where `CODE` is either hex encoded or a
+/// path to a file containing the hex encoded code
+fn parse_override(s: &str) -> eyre::Result<(Address, Bytes)> {
+    let (address, code) =
+        s.split_once(':').ok_or_else(|| eyre::eyre!("Expected <ADDRESS>:<CODE>, got `{}`", s))?;
+    let address = Address::from_str(address).wrap_err("Invalid override address")?;
+    let code = if Path::new(code).is_file() { fs::read_to_string(code)? } else { code.to_string() };
+    let code = Bytes::from_str(code.trim()).wrap_err("Invalid override code")?;
+    Ok((address, code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_override() {
+        let (address, code) =
+            parse_override("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045:0x6001").unwrap();
+        assert_eq!(address, "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045".parse().unwrap());
+        assert_eq!(code, Bytes::from(vec![0x60, 0x01]));
+
+        assert!(parse_override("0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045").is_err());
+        assert!(parse_override("vitalik:0x6001").is_err());
+    }
+}
@@ -1,14 +1,15 @@
 //! cast wallet subcommand
 
-use crate::opts::{EthereumOpts, Wallet, WalletType};
+use crate::opts::{account_keystore_path, EthereumOpts, Wallet, WalletType};
 use cast::SimpleCast;
 use clap::Parser;
 use ethers::{
     core::rand::thread_rng,
     signers::{LocalWallet, Signer},
-    types::{Address, Chain, Signature},
+    types::{transaction::eip712::TypedData, Address, Chain, Signature},
     utils::get_contract_address,
 };
+use foundry_config::Config;
 use rayon::prelude::*;
 use regex::RegexSet;
 use std::{str::FromStr, time::Instant};
@@ -70,6 +71,83 @@ pub enum WalletSubcommands {
         #[clap(flatten)]
         wallet: Wallet,
     },
+    #[clap(
+        name = "sign-typed-data",
+        visible_alias = "st",
+        about = "Sign EIP-712 typed data, given in the JSON format of `eth_signTypedData_v4`."
+    )]
+    SignTypedData {
+        #[clap(
+            help = "The typed data as JSON, or the path to a JSON file if `--from-file` is set.",
+            value_name = "DATA"
+        )]
+        data: String,
+        #[clap(long, help = "Read the typed data from the file at the given path.")]
+        from_file: bool,
+        #[clap(flatten)]
+        wallet: Wallet,
+    },
+    #[clap(
+        name = "import",
+        visible_alias = "i",
+        about = "Import a private key into an encrypted keystore in the foundry keystore directory."
+    )]
+    Import {
+        #[clap(
+            help = "The name of the account, used to select it with `--account`.",
+            value_name = "ACCOUNT_NAME"
+        )]
+        name: String,
+        #[clap(
+            long,
+            help = "The private key to import. Prompted for if neither this nor --mnemonic-path is given.",
+            conflicts_with = "mnemonic-path",
+            value_name = "RAW_PRIVATE_KEY"
+        )]
+        private_key: Option<String>,
+        #[clap(
+            long,
+            help = "Import the key from the mnemonic file at the given path.",
+            value_name = "PATH"
+        )]
+        mnemonic_path: Option<String>,
+        #[clap(
+            long,
+            help = "The index of the key to import from the mnemonic.",
+            default_value = "0",
+            value_name = "INDEX"
+        )]
+        mnemonic_index: u32,
+        #[clap(
+            long,
+            help = "Password for the keystore in cleartext. This is UNSAFE to use, by default the password is prompted for.",
+            env = "CAST_PASSWORD",
+            value_name = "PASSWORD"
+        )]
+        unsafe_password: Option<String>,
+    },
+    #[clap(
+        name = "list",
+        visible_alias = "ls",
+        about = "List the accounts in the foundry keystore directory."
+    )]
+    List,
+    #[clap(
+        name = "decrypt",
+        visible_alias = "dec",
+        about = "Decrypt the keystore of an account in the foundry keystore directory and print its private key."
+    )]
+    Decrypt {
+        #[clap(help = "The name of the account.", value_name = "ACCOUNT_NAME")]
+        name: String,
+        #[clap(
+            long,
+            help = "Password for the keystore in cleartext. This is UNSAFE to use, by default the password is prompted for.",
+            env = "CAST_PASSWORD",
+            value_name = "PASSWORD"
+        )]
+        unsafe_password: Option<String>,
+    },
     #[clap(name = "verify", visible_alias = "v", about = "Verify the signature of a message.")]
     Verify {
         #[clap(help = "The original message.", value_name = "MESSAGE")]
@@ -188,6 +266,8 @@ impl WalletSubcommands {
                     WalletType::Ledger(signer) => signer.address(),
                     WalletType::Local(signer) => signer.address(),
                     WalletType::Trezor(signer) => signer.address(),
+                    WalletType::Remote(signer) => signer.address(),
+                    WalletType::Aws(signer) => signer.address(),
                 };
                 println!("Address: {}", SimpleCast::checksum_address(&addr)?);
             }
@@ -208,9 +288,127 @@ impl WalletSubcommands {
                     WalletType::Ledger(wallet) => wallet.signer().sign_message(&message).await?,
                     WalletType::Local(wallet) => wallet.signer().sign_message(&message).await?,
                     WalletType::Trezor(wallet) => wallet.signer().sign_message(&message).await?,
+                    WalletType::Remote(wallet) => wallet.signer().sign_message(&message).await?,
+                    WalletType::Aws(wallet) => wallet.signer().sign_message(&message).await?,
                 };
                 println!("Signature: 0x{sig}");
             }
+            WalletSubcommands::SignTypedData { data, from_file, wallet } => {
+                let data = if from_file { std::fs::read_to_string(data)? } else { data };
+                let typed_data: TypedData = serde_json::from_str(&data)?;
+
+                // TODO: Figure out better way to get wallet only.
+                let wallet = EthereumOpts {
+                    wallet,
+                    rpc_url: Some("http://localhost:8545".to_string()),
+                    flashbots: false,
+                    chain: Some(Chain::Mainnet),
+                    etherscan_api_key: None,
+                }
+                .signer(0u64.into())
+                .await?
+                .unwrap();
+
+                let sig = match wallet {
+                    WalletType::Ledger(wallet) => {
+                        wallet.signer().sign_typed_data(&typed_data).await?
+                    }
+                    WalletType::Local(wallet) => {
+                        wallet.signer().sign_typed_data(&typed_data).await?
+                    }
+                    WalletType::Trezor(wallet) => {
+                        wallet.signer().sign_typed_data(&typed_data).await?
+                    }
+                    WalletType::Remote(wallet) => {
+                        wallet.signer().sign_typed_data(&typed_data).await?
+                    }
+                    WalletType::Aws(wallet) => wallet.signer().sign_typed_data(&typed_data).await?,
+                };
+                println!("Signature: 0x{sig}");
+            }
+            WalletSubcommands::Import {
+                name,
+                private_key,
+                mnemonic_path,
+                mnemonic_index,
+                unsafe_password,
+            } => {
+                let dir = Config::foundry_keystores_dir()
+                    .ok_or_else(|| eyre::eyre!("Failed to find the foundry keystore directory"))?;
+                if dir.join(&name).exists() {
+                    eyre::bail!("An account named `{}` already exists in {}", name, dir.display())
+                }
+
+                let wallet = Wallet {
+                    interactive: private_key.is_none() && mnemonic_path.is_none(),
+                    private_key,
+                    mnemonic_path,
+                    mnemonic_index,
+                    ..Default::default()
+                };
+                let wallet = wallet
+                    .private_key()
+                    .transpose()
+                    .or_else(|| wallet.mnemonic().transpose())
+                    .or_else(|| wallet.interactive().transpose())
+                    .transpose()?
+                    .expect("one key source is set");
+
+                let password = if let Some(password) = unsafe_password {
+                    password
+                } else {
+                    println!("Insert password for account `{name}`:");
+                    rpassword::read_password()?
+                };
+
+                std::fs::create_dir_all(&dir)?;
+                eth_keystore::encrypt_key(
+                    &dir,
+                    &mut thread_rng(),
+                    wallet.signer().to_bytes(),
+                    password,
+                    Some(&name),
+                )?;
+                println!(
+                    "Imported account `{}` with address {} into {}",
+                    name,
+                    SimpleCast::checksum_address(&wallet.address())?,
+                    dir.join(&name).display()
+                );
+            }
+            WalletSubcommands::List => {
+                let dir = Config::foundry_keystores_dir()
+                    .ok_or_else(|| eyre::eyre!("Failed to find the foundry keystore directory"))?;
+                let mut accounts = if dir.exists() {
+                    std::fs::read_dir(&dir)?
+                        .filter_map(|entry| entry.ok())
+                        .filter(|entry| entry.path().is_file())
+                        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                        .collect::<Vec<_>>()
+                } else {
+                    vec![]
+                };
+                accounts.sort();
+                for account in accounts {
+                    println!("{account}");
+                }
+            }
+            WalletSubcommands::Decrypt { name, unsafe_password } => {
+                let path = account_keystore_path(&name)?;
+                let password = if let Some(password) = unsafe_password {
+                    password
+                } else {
+                    println!("Insert password for account `{name}`:");
+                    rpassword::read_password()?
+                };
+
+                let wallet = LocalWallet::decrypt_keystore(path, password)?;
+                println!(
+                    "Address: {}\nPrivate Key: 0x{}",
+                    SimpleCast::checksum_address(&wallet.address())?,
+                    hex::encode(wallet.signer().to_bytes()),
+                );
+            }
             WalletSubcommands::Verify { message, signature, address } => {
                 let pubkey = Address::from_str(&address).expect("invalid pubkey provided");
                 let signature = Signature::from_str(&signature)?;
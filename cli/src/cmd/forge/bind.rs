@@ -1,7 +1,11 @@
 use crate::cmd::utils::Cmd;
 
 use clap::{Parser, ValueHint};
-use ethers::contract::MultiAbigen;
+use ethers::{
+    abi::{Abi, ParamType},
+    contract::MultiAbigen,
+    utils::keccak256,
+};
 use foundry_config::{
     figment::{
         self,
@@ -11,8 +15,13 @@ use foundry_config::{
     },
     impl_figment_convert, Config,
 };
-use serde::Serialize;
-use std::{fs, path::PathBuf};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
 
 impl_figment_convert!(BindArgs);
 
@@ -74,6 +83,13 @@ pub struct BindArgs {
     #[clap(long = "skip-cargo-toml", help = "Skip Cargo.toml consistency checks.")]
     #[serde(skip)]
     skip_cargo_toml: bool,
+
+    #[clap(
+        long,
+        help = "Also generate a `forge_harness` module with a type for every custom error, and `#[cfg(test)]` helpers to deploy the contracts in a forge `Executor`, so Rust tests can run against the contracts without a node."
+    )]
+    #[serde(skip)]
+    harness: bool,
 }
 
 impl BindArgs {
@@ -108,6 +124,10 @@ No contract artifacts found. Hint: Have you built your contracts yet? `forge bin
 
     /// Check that the existing bindings match the expected abigen output
     fn check_existing_bindings(&self) -> eyre::Result<()> {
+        if self.harness {
+            return self.check_existing_harness()
+        }
+
         let bindings = self.get_multi()?.build()?;
         println!("Checking bindings for {} contracts.", bindings.len());
         if !self.module {
@@ -125,24 +145,279 @@ No contract artifacts found. Hint: Have you built your contracts yet? `forge bin
         Ok(())
     }
 
+    /// Check that the existing bindings and harness match freshly generated ones
+    ///
+    /// The generated `lib.rs` or `mod.rs` declares the harness module, so the bindings can't be
+    /// checked by abigen. Instead, they're generated to a temporary directory and compared file by
+    /// file.
+    fn check_existing_harness(&self) -> eyre::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let expected = tmp.path().join("bindings");
+        self.write_bindings(&expected)?;
+
+        let root = self.bindings_root();
+        for entry in walkdir::WalkDir::new(&expected) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue
+            }
+            let relative = entry.path().strip_prefix(&expected)?;
+            if self.skip_cargo_toml && relative == Path::new("Cargo.toml") {
+                continue
+            }
+            let existing = fs::read_to_string(root.join(relative)).unwrap_or_default();
+            eyre::ensure!(
+                existing == fs::read_to_string(entry.path())?,
+                "The generated bindings differ from the existing bindings at {}",
+                root.join(relative).display()
+            );
+        }
+        println!("OK.");
+        Ok(())
+    }
+
     /// Generate the bindings
     fn generate_bindings(&self) -> eyre::Result<()> {
+        self.write_bindings(&self.bindings_root())
+    }
+
+    /// Write the bindings, and the harness if enabled, to `root`
+    fn write_bindings(&self, root: &Path) -> eyre::Result<()> {
         let bindings = self.get_multi()?.build()?;
         println!("Generating bindings for {} contracts", bindings.len());
         if !self.module {
             bindings.write_to_crate(
                 &self.crate_name,
                 &self.crate_version,
-                self.bindings_root(),
+                root,
                 self.single_file,
             )?;
         } else {
-            bindings.write_to_module(self.bindings_root(), self.single_file)?;
+            bindings.write_to_module(root, self.single_file)?;
+        }
+
+        if self.harness {
+            let src = if self.module { root.to_path_buf() } else { root.join("src") };
+            let artifacts = read_artifacts(&self.artifacts(), &self.bindings_root())?;
+            fs::write(src.join("forge_harness.rs"), generate_harness(&artifacts))?;
+
+            let lib = src.join(if self.module { "mod.rs" } else { "lib.rs" });
+            let mut content = fs::read_to_string(&lib)?;
+            content.push_str("pub mod forge_harness;\n");
+            fs::write(&lib, content)?;
+
+            // the deploy helpers are only available in tests
+            if !self.module {
+                let manifest = root.join("Cargo.toml");
+                let mut content = fs::read_to_string(&manifest)?;
+                content.push_str(
+                    "\n[dev-dependencies]\nfoundry-evm = { git = \"https://github.com/foundry-rs/foundry\" }\n",
+                );
+                fs::write(&manifest, content)?;
+            }
         }
         Ok(())
     }
 }
 
+/// The parts of an artifact the harness is generated from
+#[derive(Debug, Clone, Deserialize)]
+struct HarnessArtifact {
+    abi: Abi,
+    #[serde(default)]
+    bytecode: Option<HarnessBytecode>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct HarnessBytecode {
+    object: String,
+}
+
+/// Reads the artifacts in `out`, by contract name, skipping everything in the `bindings`
+/// directory and the files that are not contract artifacts
+fn read_artifacts(out: &Path, bindings: &Path) -> eyre::Result<BTreeMap<String, HarnessArtifact>> {
+    let mut artifacts = BTreeMap::new();
+    let entries = walkdir::WalkDir::new(out)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.path() != bindings);
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if !entry.file_type().is_file() ||
+            path.extension().and_then(|ext| ext.to_str()) != Some("json")
+        {
+            continue
+        }
+        let name = match path.file_stem().and_then(|name| name.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        if let Ok(artifact) = serde_json::from_str::<HarnessArtifact>(&fs::read_to_string(path)?) {
+            artifacts.entry(name).or_insert(artifact);
+        }
+    }
+    Ok(artifacts)
+}
+
+/// Generates the `forge_harness` module
+///
+/// Every contract gets a module with a type for each of its custom errors and, in tests, its
+/// creation code and a function to deploy it in a forge `Executor`.
+fn generate_harness(artifacts: &BTreeMap<String, HarnessArtifact>) -> String {
+    let mut out = String::from(
+        "//! Custom errors of the contracts, and helpers to deploy them in Rust tests\n//!\n//! This file was generated by `forge bind --harness`.\n#![allow(clippy::all)]\n",
+    );
+    for (name, artifact) in artifacts {
+        writeln!(out, "\npub mod {} {{", rust_ident(&to_snake_case(name))).unwrap();
+
+        let mut errors = artifact.abi.errors().collect::<Vec<_>>();
+        errors.dedup_by(|a, b| a.name == b.name);
+        for error in errors {
+            let types = error.inputs.iter().map(|param| param.kind.to_string()).collect::<Vec<_>>();
+            let signature = format!("{}({})", error.name, types.join(","));
+            let selector = &keccak256(signature.as_bytes())[..4];
+
+            writeln!(out, "    /// `{}`", signature).unwrap();
+            if error.inputs.is_empty() {
+                writeln!(out, "    #[derive(Clone, Debug, PartialEq, Eq)]").unwrap();
+                writeln!(out, "    pub struct {};", error.name).unwrap();
+            } else {
+                writeln!(
+                    out,
+                    "    #[derive(Clone, Debug, PartialEq, Eq, ethers::contract::EthAbiType)]"
+                )
+                .unwrap();
+                writeln!(out, "    pub struct {} {{", error.name).unwrap();
+                for (idx, param) in error.inputs.iter().enumerate() {
+                    let field = if param.name.is_empty() {
+                        format!("p{idx}")
+                    } else {
+                        rust_ident(&to_snake_case(&param.name))
+                    };
+                    writeln!(out, "        pub {}: {},", field, rust_type(&param.kind)).unwrap();
+                }
+                writeln!(out, "    }}").unwrap();
+            }
+
+            writeln!(out, "\n    impl {} {{", error.name).unwrap();
+            writeln!(out, "        /// The selector of the error").unwrap();
+            writeln!(out, "        pub const SELECTOR: [u8; 4] = {:?};\n", selector).unwrap();
+            writeln!(out, "        /// Decodes the error from revert data, or returns `None` if the revert data is another error").unwrap();
+            writeln!(out, "        pub fn decode(data: &[u8]) -> Option<Self> {{").unwrap();
+            if error.inputs.is_empty() {
+                writeln!(out, "            data.starts_with(&Self::SELECTOR).then(|| Self)")
+                    .unwrap();
+            } else {
+                writeln!(out, "            if !data.starts_with(&Self::SELECTOR) {{").unwrap();
+                writeln!(out, "                return None").unwrap();
+                writeln!(out, "            }}").unwrap();
+                writeln!(out, "            let types = {:?}", types).unwrap();
+                writeln!(out, "                .iter()").unwrap();
+                writeln!(
+                    out,
+                    "                .map(|ty| ethers::abi::param_type::Reader::read(ty))"
+                )
+                .unwrap();
+                writeln!(out, "                .collect::<Result<Vec<_>, _>>()").unwrap();
+                writeln!(out, "                .ok()?;").unwrap();
+                writeln!(
+                    out,
+                    "            let tokens = ethers::abi::decode(&types, &data[4..]).ok()?;"
+                )
+                .unwrap();
+                writeln!(out, "            ethers::abi::Detokenize::from_tokens(vec![ethers::abi::Token::Tuple(tokens)]).ok()").unwrap();
+            }
+            writeln!(out, "        }}").unwrap();
+            writeln!(out, "    }}\n").unwrap();
+        }
+
+        // contracts with unlinked libraries can't be deployed as is
+        let bytecode = artifact
+            .bytecode
+            .as_ref()
+            .map(|bytecode| bytecode.object.trim_start_matches("0x"))
+            .filter(|object| !object.is_empty() && !object.contains("__"));
+        if let Some(object) = bytecode {
+            writeln!(out, "    /// The creation code of the contract").unwrap();
+            writeln!(out, "    #[cfg(test)]").unwrap();
+            writeln!(out, "    pub const BYTECODE: &str = \"{}\";\n", object).unwrap();
+            writeln!(out, "    /// Deploys the contract from `from` in the `executor`, with the constructor `args`").unwrap();
+            writeln!(out, "    #[cfg(test)]").unwrap();
+            writeln!(out, "    pub fn deploy(").unwrap();
+            writeln!(out, "        executor: &mut foundry_evm::executor::Executor,").unwrap();
+            writeln!(out, "        from: ethers::core::types::Address,").unwrap();
+            writeln!(out, "        args: impl ethers::abi::Tokenize,").unwrap();
+            writeln!(
+                out,
+                "    ) -> Result<ethers::core::types::Address, foundry_evm::executor::EvmError> {{"
+            )
+            .unwrap();
+            writeln!(out, "        let mut code = ethers::utils::hex::decode(BYTECODE).expect(\"valid bytecode\");").unwrap();
+            writeln!(out, "        code.extend(ethers::abi::encode(&args.into_tokens()));")
+                .unwrap();
+            writeln!(
+                out,
+                "        Ok(executor.deploy(from, code.into(), 0u64.into(), None)?.address)"
+            )
+            .unwrap();
+            writeln!(out, "    }}").unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
+
+/// Returns the Rust type a value of the solidity type is decoded into
+fn rust_type(kind: &ParamType) -> String {
+    match kind {
+        ParamType::Address => "ethers::core::types::Address".to_string(),
+        ParamType::Bytes => "ethers::core::types::Bytes".to_string(),
+        ParamType::Int(_) => "ethers::core::types::I256".to_string(),
+        ParamType::Uint(_) => "ethers::core::types::U256".to_string(),
+        ParamType::Bool => "bool".to_string(),
+        ParamType::String => "String".to_string(),
+        ParamType::Array(inner) => format!("Vec<{}>", rust_type(inner)),
+        ParamType::FixedBytes(len) => format!("[u8; {len}]"),
+        ParamType::FixedArray(inner, len) => format!("[{}; {len}]", rust_type(inner)),
+        ParamType::Tuple(inner) => {
+            format!("({},)", inner.iter().map(rust_type).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+/// Converts a camel or pascal case name to snake case, e.g. `ERC20Token` to `erc20_token`
+fn to_snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::with_capacity(name.len());
+    for (idx, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && idx > 0 {
+            let prev = chars[idx - 1];
+            let next_is_lower = chars.get(idx + 1).map_or(false, |next| next.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_is_lower) {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// Returns the name as an identifier, escaping Rust keywords
+fn rust_ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else",
+        "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+        "move", "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait",
+        "true", "try", "type", "unsafe", "use", "where", "while", "yield",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
 impl Cmd for BindArgs {
     type Output = ();
 
@@ -176,3 +451,40 @@ impl Provider for BindArgs {
         Ok(Map::from([(Config::selected_profile(), dict)]))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_convert_to_snake_case() {
+        assert_eq!(to_snake_case("ERC20Token"), "erc20_token");
+        assert_eq!(to_snake_case("Counter"), "counter");
+        assert_eq!(to_snake_case("insufficientBalance"), "insufficient_balance");
+        assert_eq!(rust_ident(&to_snake_case("Type")), "type_");
+    }
+
+    #[test]
+    fn can_generate_harness() {
+        let abi: Abi = serde_json::from_str(
+            r#"[{"type":"error","name":"InsufficientBalance","inputs":[{"name":"available","type":"uint256"},{"name":"","type":"address[]"}]}]"#,
+        )
+        .unwrap();
+        let artifacts = BTreeMap::from([(
+            "MyToken".to_string(),
+            HarnessArtifact {
+                abi,
+                bytecode: Some(HarnessBytecode { object: "0x6080".to_string() }),
+            },
+        )]);
+
+        let harness = generate_harness(&artifacts);
+        assert!(harness.contains("pub mod my_token {"));
+        assert!(harness.contains("pub struct InsufficientBalance {"));
+        assert!(harness.contains("pub available: ethers::core::types::U256,"));
+        assert!(harness.contains("pub p1: Vec<ethers::core::types::Address>,"));
+        let selector = &keccak256("InsufficientBalance(uint256,address[])")[..4];
+        assert!(harness.contains(&format!("pub const SELECTOR: [u8; 4] = {:?};", selector)));
+        assert!(harness.contains("pub const BYTECODE: &str = \"6080\";"));
+    }
+}
@@ -28,7 +28,7 @@ pub struct CoreBuildArgs {
 
     #[clap(
         help_heading = "LINKER OPTIONS",
-        help = "Set pre-linked libraries.",
+        help = "Set pre-linked libraries in the form of `<path>:<name>:<address>` or `<name>:<address>`. Linked libraries are not deployed by tests and scripts.",
         long,
         env = "DAPP_LIBRARIES",
         value_name = "LIBRARIES"
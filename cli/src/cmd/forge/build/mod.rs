@@ -2,7 +2,7 @@
 
 use crate::{
     cmd::{forge::watch::WatchArgs, Cmd},
-    compile,
+    compile::{self, ProjectCompiler},
 };
 use clap::Parser;
 use ethers::solc::{Project, ProjectCompileOutput};
@@ -67,8 +67,15 @@ impl Cmd for BuildArgs {
     type Output = ProjectCompileOutput;
     fn run(self) -> eyre::Result<Self::Output> {
         let project = self.project()?;
-
-        if self.args.silent {
+        let config = Config::from(&self);
+        // compiled first, since solc exits if there are no Solidity sources
+        compile::vyper::compile(&project, self.args.silent)?;
+        compile::raw::ingest(&project, &config.raw_artifacts)?;
+
+        if !config.compiler_overrides.is_empty() {
+            let compiler = ProjectCompiler::new(self.names, self.sizes);
+            Ok(compiler.compile_with_overrides(&config, &project)?.0)
+        } else if self.args.silent {
             compile::suppress_compile(&project)
         } else {
             compile::compile(&project, self.names, self.sizes)
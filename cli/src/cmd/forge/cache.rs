@@ -1,13 +1,21 @@
 //! cache command
 
-use clap::{Parser, Subcommand};
-use std::str::FromStr;
+use clap::{Parser, Subcommand, ValueHint};
+use std::{path::PathBuf, str::FromStr};
 use strum::VariantNames;
 
-use crate::cmd::Cmd;
+use crate::{cmd::Cmd, utils};
 use cache::Cache;
-use ethers::prelude::Chain;
+use ethers::{
+    prelude::Chain,
+    types::{BlockId, BlockNumber},
+};
 use eyre::Result;
+use forge::executor::{
+    fork::{prefetch, BlockchainDb, BlockchainDbMeta},
+    opts::EvmOpts,
+};
+use foundry_common::provider::ProvidersManager;
 use foundry_config::{cache, Chain as FoundryConfigChain, Config};
 
 #[derive(Debug, Parser)]
@@ -77,12 +85,34 @@ pub struct LsArgs {
     chains: Vec<ChainOrAll>,
 }
 
+#[derive(Debug, Parser)]
+pub struct WarmArgs {
+    /// The access list file with the accounts and storage slots to fetch.
+    ///
+    /// The file holds a JSON encoded EIP-2930 access list, like the ones recorded with
+    /// `fork_prefetch` or returned by `eth_createAccessList`.
+    #[clap(value_hint = ValueHint::FilePath, value_name = "PATH")]
+    access_list: PathBuf,
+
+    /// The URL or the alias of the `rpc_endpoints` of the endpoint to fetch the state from.
+    #[clap(long, short, env = "ETH_RPC_URL", value_name = "URL")]
+    fork_url: String,
+
+    /// The block to fetch the state at.
+    ///
+    /// Defaults to the latest block.
+    #[clap(long, value_name = "BLOCK")]
+    fork_block_number: Option<u64>,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum CacheSubcommands {
     #[clap(about = "Cleans cached data from ~/.foundry.")]
     Clean(CleanArgs),
     #[clap(about = "Shows cached data from ~/.foundry.")]
     Ls(LsArgs),
+    #[clap(about = "Fetches the state of an access list into the fork cache in ~/.foundry.")]
+    Warm(WarmArgs),
 }
 
 impl Cmd for CleanArgs {
@@ -127,6 +157,39 @@ impl Cmd for LsArgs {
     }
 }
 
+impl WarmArgs {
+    pub async fn run(self) -> Result<()> {
+        let WarmArgs { access_list, fork_url, fork_block_number } = self;
+        let list = prefetch::read_access_list(&access_list)?;
+
+        let figment = Config::figment();
+        let mut evm_opts = figment.extract::<EvmOpts>()?;
+        let config = Config::from_provider(figment).sanitized();
+        let providers = ProvidersManager::new(config.rpc_endpoints);
+        let url = providers.endpoint(&fork_url)?.url;
+        let provider = utils::get_provider(&providers, &fork_url, false)?;
+
+        evm_opts.fork_block_number = fork_block_number;
+        let env = evm_opts.fork_evm_env(&url).await?;
+        let meta = BlockchainDbMeta::new(env, url);
+        let chain = meta.cfg_env.chain_id.as_u64();
+        let number = meta.block_env.number.as_u64();
+
+        // the same cache file a fork of the block uses
+        let db = BlockchainDb::new(meta, Config::foundry_block_cache_file(chain, number));
+        let block = BlockId::Number(BlockNumber::Number(number.into()));
+        prefetch::prefetch(provider, &db, &list, block).await;
+        db.cache().flush();
+
+        println!(
+            "Cached {} accounts and {} storage slots of block {number} on chain {chain}",
+            db.accounts().read().len(),
+            db.storage().read().values().map(|slots| slots.len()).sum::<usize>()
+        );
+        Ok(())
+    }
+}
+
 fn clean_chain_cache(
     chain: impl Into<FoundryConfigChain>,
     blocks: Vec<u64>,
@@ -20,10 +20,11 @@ use forge::{
     executor::{inspector::CheatsConfig, opts::EvmOpts},
     result::SuiteResult,
     trace::identifier::LocalTraceIdentifier,
-    MultiContractRunnerBuilder,
+    MultiContractRunnerBuilder, TestOptions,
 };
 use foundry_common::{evm::EvmArgs, fs};
 use foundry_config::{figment::Figment, Config};
+use globset::{Glob, GlobSetBuilder};
 use std::{collections::HashMap, path::PathBuf, sync::mpsc::channel, thread};
 
 // Loads project's figment and merges the build cli arguments into it
@@ -41,6 +42,13 @@ pub struct CoverageArgs {
     )]
     report: CoverageReportKind,
 
+    #[clap(
+        long,
+        help = "Collect coverage at the bytecode level.",
+        long_help = "Collect coverage at the bytecode level.\n\nThe project is compiled with its own optimizer settings instead of disabling the optimizer, and the executed instructions are mapped back to the sources on a best-effort basis. Use this if your project does not compile without the optimizer."
+    )]
+    bytecode: bool,
+
     #[clap(flatten, next_help_heading = "TEST FILTERING")]
     filter: Filter,
 
@@ -61,9 +69,11 @@ impl CoverageArgs {
     pub fn config_and_evm_opts(&self) -> eyre::Result<(Config, EvmOpts)> {
         // Merge all configs
         let figment: Figment = self.into();
-        let evm_opts = figment.extract()?;
+        let mut evm_opts: EvmOpts = figment.extract()?;
         let config = Config::from_provider(figment).sanitized();
 
+        // `--fork-url` can be an alias of the `rpc_endpoints`
+        evm_opts.resolve_fork_url(&config)?;
         Ok((config, evm_opts))
     }
 }
@@ -75,7 +85,7 @@ impl Cmd for CoverageArgs {
         let (config, evm_opts) = self.configure()?;
         let (project, output) = self.build(&config)?;
         p_println!(!self.opts.silent => "Analysing contracts...");
-        let (map, source_maps) = self.prepare(output.clone())?;
+        let (map, source_maps) = self.prepare(&config, output.clone())?;
 
         p_println!(!self.opts.silent => "Running tests...");
         self.collect(project, output, source_maps, map, config, evm_opts)
@@ -104,8 +114,11 @@ impl CoverageArgs {
         let project = {
             let mut project = config.ephemeral_no_artifacts_project()?;
 
-            // Disable the optimizer for more accurate source maps
-            project.solc_config.settings.optimizer.disable();
+            // Disable the optimizer for more accurate source maps, unless we are mapping hits
+            // back on a best-effort basis
+            if !self.bytecode {
+                project.solc_config.settings.optimizer.disable();
+            }
 
             project
         };
@@ -118,7 +131,11 @@ impl CoverageArgs {
     }
 
     /// Builds the coverage map.
-    fn prepare(&self, output: ProjectCompileOutput) -> eyre::Result<(CoverageMap, SourceMaps)> {
+    fn prepare(
+        &self,
+        config: &Config,
+        output: ProjectCompileOutput,
+    ) -> eyre::Result<(CoverageMap, SourceMaps)> {
         // Extract artifacts
         let (artifacts, sources) = output.into_artifacts_with_sources();
         let artifacts: HashMap<ArtifactId, CompactContractBytecode> = artifacts
@@ -160,6 +177,15 @@ impl CoverageArgs {
             })
             .collect();
 
+        // Sources excluded from coverage in the config
+        let excluded = {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in &config.coverage_exclude {
+                builder.add(Glob::new(pattern)?);
+            }
+            builder.build()?
+        };
+
         let mut map = CoverageMap::default();
         for (path, versioned_sources) in sources.0.into_iter() {
             // TODO: Make these checks robust
@@ -169,7 +195,7 @@ impl CoverageArgs {
             // are usually defined in the test files themselves.
             let is_test = path.is_sol_test();
             let is_dependency = path.starts_with("lib");
-            if is_test || is_dependency {
+            if is_test || is_dependency || excluded.is_match(&path) {
                 continue
             }
 
@@ -200,6 +226,7 @@ impl CoverageArgs {
                         .collect();
 
                     let items = Visitor::new(fs::read_to_string(&path)?, source_maps, bytecodes)
+                        .best_effort(self.bytecode)
                         .visit_ast(ast)?;
 
                     if items.is_empty() {
@@ -247,6 +274,8 @@ impl CoverageArgs {
             .sender(evm_opts.sender)
             .with_fork(evm_opts.get_fork(&config, env.clone()))
             .with_cheats_config(CheatsConfig::new(&config, &evm_opts))
+            .with_test_options(TestOptions::from(&config))
+            .with_libraries(config.parsed_libraries()?)
             .set_coverage(true)
             .build(root.clone(), output, env, evm_opts)?;
 
@@ -270,14 +299,21 @@ impl CoverageArgs {
                             Some((artifact_id, source_map, hit_map.get(&identity.address)?))
                         })
                         .for_each(|(id, source_map, hits)| {
+                            let add_hit_map = if self.bytecode {
+                                CoverageMap::add_hit_map_by_range
+                            } else {
+                                CoverageMap::add_hit_map
+                            };
+
                             // TODO: Distinguish between creation/runtime in a smart way
-                            map.add_hit_map(
+                            add_hit_map(
+                                &mut map,
                                 id.version.clone(),
                                 &source_map.0,
                                 &id.name,
                                 hits.clone(),
                             );
-                            map.add_hit_map(id.version, &source_map.1, &id.name, hits.clone())
+                            add_hit_map(&mut map, id.version, &source_map.1, &id.name, hits.clone())
                         });
                 }
             }
@@ -4,7 +4,7 @@ use crate::{
     cmd::{forge::build::CoreBuildArgs, utils, RetryArgs},
     compile,
     opts::{EthereumOpts, TransactionOpts, WalletType},
-    utils::get_http_provider,
+    utils::get_provider,
 };
 use cast::SimpleCast;
 use clap::{Parser, ValueHint};
@@ -15,10 +15,10 @@ use ethers::{
         info::ContractInfo,
         utils::{canonicalized, read_json_file},
     },
-    types::{transaction::eip2718::TypedTransaction, Chain},
+    types::{transaction::eip2718::TypedTransaction, Address, Chain},
 };
 use eyre::Context;
-use foundry_common::fs;
+use foundry_common::{fs, provider::ProvidersManager};
 use foundry_config::Config;
 use foundry_utils::parse_tokens;
 use rustc_hex::ToHex;
@@ -119,37 +119,19 @@ impl CreateArgs {
 
         // Add arguments to constructor
         let config = Config::from(&self.eth);
-        let provider = get_http_provider(
+        let providers = ProvidersManager::new(config.rpc_endpoints.clone());
+        let provider = get_provider(
+            &providers,
             config.eth_rpc_url.as_deref().unwrap_or("http://localhost:8545"),
             false,
-        );
+        )?;
         let params = match abi.constructor {
             Some(ref v) => {
-                let constructor_args =
-                    if let Some(ref constructor_args_path) = self.constructor_args_path {
-                        if !constructor_args_path.exists() {
-                            eyre::bail!(
-                                "Constructor args file \"{}\" not found",
-                                constructor_args_path.display()
-                            );
-                        }
-                        if constructor_args_path.extension() == Some(std::ffi::OsStr::new("json")) {
-                            match read_json_file(constructor_args_path) {
-                                Ok(args) => args,
-                                Err(err) => eyre::bail!(
-                                    "Constructor args file \"{}\" must encode a json array: \"{}\"",
-                                    constructor_args_path.display(),
-                                    err
-                                ),
-                            }
-                        } else {
-                            let file = fs::read_to_string(constructor_args_path)?;
-                            file.split_whitespace().map(str::to_string).collect::<Vec<String>>()
-                        }
-                    } else {
-                        self.constructor_args.clone()
-                    };
-                self.parse_constructor_args(v, &constructor_args)?
+                let constructor_args = read_constructor_args(
+                    &self.constructor_args,
+                    self.constructor_args_path.as_ref(),
+                )?;
+                parse_constructor_args(v, &constructor_args)?
             }
             None => vec![],
         };
@@ -171,6 +153,8 @@ impl CreateArgs {
                 WalletType::Ledger(signer) => self.deploy(abi, bin, params, signer).await?,
                 WalletType::Local(signer) => self.deploy(abi, bin, params, signer).await?,
                 WalletType::Trezor(signer) => self.deploy(abi, bin, params, signer).await?,
+                WalletType::Remote(signer) => self.deploy(abi, bin, params, signer).await?,
+                WalletType::Aws(signer) => self.deploy(abi, bin, params, signer).await?,
             },
             None => eyre::bail!("could not find artifact"),
         };
@@ -203,46 +187,9 @@ impl CreateArgs {
                     e
                 }
             })?;
-        let is_legacy = self.tx.legacy ||
-            Chain::try_from(chain).map(|x| Chain::is_legacy(&x)).unwrap_or_default();
+        let is_legacy = is_legacy(&self.tx, chain);
         let mut deployer = if is_legacy { deployer.legacy() } else { deployer };
-
-        // set tx value if specified
-        if let Some(value) = self.tx.value {
-            deployer.tx.set_value(value);
-        }
-
-        // fill tx first because if you target a lower gas than current base, eth_estimateGas
-        // will fail and create will fail
-        provider.fill_transaction(&mut deployer.tx, None).await?;
-
-        // set gas price if specified
-        if let Some(gas_price) = self.tx.gas_price {
-            deployer.tx.set_gas_price(gas_price);
-        }
-
-        // set gas limit if specified
-        if let Some(gas_limit) = self.tx.gas_limit {
-            deployer.tx.set_gas(gas_limit);
-        }
-
-        // set nonce if specified
-        if let Some(nonce) = self.tx.nonce {
-            deployer.tx.set_nonce(nonce);
-        }
-
-        // set priority fee if specified
-        if let Some(priority_fee) = self.tx.priority_gas_price {
-            if is_legacy {
-                panic!("there is no priority fee for legacy txs");
-            }
-            deployer.tx = match deployer.tx {
-                TypedTransaction::Eip1559(eip1559_tx_request) => TypedTransaction::Eip1559(
-                    eip1559_tx_request.max_priority_fee_per_gas(priority_fee),
-                ),
-                _ => deployer.tx,
-            };
-        }
+        fill_deployment(&*provider, &mut deployer.tx, &self.tx, is_legacy).await?;
 
         let (deployed_contract, receipt) = deployer.send_with_receipt().await?;
         let address = deployed_contract.address();
@@ -263,56 +210,218 @@ impl CreateArgs {
             return Ok(())
         }
 
-        println!("Starting contract verification...");
-        let constructor_args = if !args.is_empty() {
-            // we're passing an empty vec to the `encode_input` of the constructor because we only
-            // need the constructor arguments and the encoded input is `code + args`
-            let code = Vec::new();
-            let encoded_args = abi
-                .constructor()
-                .ok_or(eyre::eyre!("could not find constructor"))?
-                .encode_input(code, &args)?
-                .to_hex::<String>();
-            Some(encoded_args)
-        } else {
-            None
-        };
-        let num_of_optimizations =
-            if self.opts.compiler.optimize { self.opts.compiler.optimizer_runs } else { None };
-        let verify = verify::VerifyArgs {
+        verify_deployment(
+            self.contract,
             address,
-            contract: self.contract,
-            compiler_version: None,
-            constructor_args,
-            num_of_optimizations,
-            chain: chain.into(),
-            etherscan_key: self
-                .eth
-                .etherscan_api_key
-                .ok_or(eyre::eyre!("ETHERSCAN_API_KEY must be set"))?,
-            project_paths: self.opts.project_paths,
-            flatten: false,
-            force: false,
-            watch: true,
-            retry: RETRY_VERIFY_ON_CREATE,
-            libraries: vec![],
-        };
-        println!("Waiting for etherscan to detect contract deployment...");
-        verify.run().await
+            chain,
+            &abi,
+            &args,
+            &self.opts,
+            self.eth.etherscan_api_key,
+        )
+        .await
+    }
+}
+
+/// Returns whether the deployment is sent as a legacy transaction, because it's requested or the
+/// chain does not support EIP-1559
+pub fn is_legacy(tx_opts: &TransactionOpts, chain: u64) -> bool {
+    tx_opts.legacy || Chain::try_from(chain).map(|x| Chain::is_legacy(&x)).unwrap_or_default()
+}
+
+/// Fills the missing fields of a deployment transaction from the provider and applies the value,
+/// gas, nonce and fees of `tx_opts`
+pub async fn fill_deployment<M: Middleware>(
+    provider: &M,
+    tx: &mut TypedTransaction,
+    tx_opts: &TransactionOpts,
+    is_legacy: bool,
+) -> eyre::Result<()>
+where
+    M::Error: 'static,
+{
+    // set tx value if specified
+    if let Some(value) = tx_opts.value {
+        tx.set_value(value);
+    }
+
+    // fill tx first because if you target a lower gas than current base, eth_estimateGas
+    // will fail and create will fail
+    provider.fill_transaction(tx, None).await?;
+
+    // set gas price if specified
+    if let Some(gas_price) = tx_opts.gas_price {
+        tx.set_gas_price(gas_price);
+    }
+
+    // set gas limit if specified
+    if let Some(gas_limit) = tx_opts.gas_limit {
+        tx.set_gas(gas_limit);
+    }
+
+    // set nonce if specified
+    if let Some(nonce) = tx_opts.nonce {
+        tx.set_nonce(nonce);
     }
 
-    fn parse_constructor_args(
-        &self,
-        constructor: &Constructor,
-        constructor_args: &[String],
-    ) -> eyre::Result<Vec<Token>> {
-        let params = constructor
-            .inputs
-            .iter()
-            .zip(constructor_args)
-            .map(|(input, arg)| (&input.kind, arg.as_str()))
-            .collect::<Vec<_>>();
-
-        parse_tokens(params, true)
+    // set priority fee if specified
+    if let Some(priority_fee) = tx_opts.priority_gas_price {
+        if is_legacy {
+            panic!("there is no priority fee for legacy txs");
+        }
+        if let TypedTransaction::Eip1559(inner) = tx {
+            inner.max_priority_fee_per_gas = Some(priority_fee);
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies the contract deployed at `address` with the constructor `args` on etherscan
+pub async fn verify_deployment(
+    contract: ContractInfo,
+    address: Address,
+    chain: u64,
+    abi: &Abi,
+    args: &[Token],
+    opts: &CoreBuildArgs,
+    etherscan_key: Option<String>,
+) -> eyre::Result<()> {
+    println!("Starting contract verification...");
+    let constructor_args = if !args.is_empty() {
+        // we're passing an empty vec to the `encode_input` of the constructor because we only
+        // need the constructor arguments and the encoded input is `code + args`
+        let code = Vec::new();
+        let encoded_args = abi
+            .constructor()
+            .ok_or(eyre::eyre!("could not find constructor"))?
+            .encode_input(code, args)?
+            .to_hex::<String>();
+        Some(encoded_args)
+    } else {
+        None
+    };
+    let num_of_optimizations =
+        if opts.compiler.optimize { opts.compiler.optimizer_runs } else { None };
+    let verify = verify::VerifyArgs {
+        address,
+        contract,
+        compiler_version: None,
+        constructor_args,
+        num_of_optimizations,
+        chain: chain.into(),
+        etherscan_key: etherscan_key.ok_or(eyre::eyre!("ETHERSCAN_API_KEY must be set"))?,
+        project_paths: opts.project_paths.clone(),
+        flatten: false,
+        force: false,
+        watch: true,
+        retry: RETRY_VERIFY_ON_CREATE,
+        libraries: vec![],
+    };
+    println!("Waiting for etherscan to detect contract deployment...");
+    verify.run().await
+}
+
+/// Returns the constructor arguments read from `constructor_args_path`, if set, otherwise
+/// `constructor_args`
+pub fn read_constructor_args(
+    constructor_args: &[String],
+    constructor_args_path: Option<&PathBuf>,
+) -> eyre::Result<Vec<String>> {
+    let constructor_args_path = match constructor_args_path {
+        Some(path) => path,
+        None => return Ok(constructor_args.to_vec()),
+    };
+    if !constructor_args_path.exists() {
+        eyre::bail!("Constructor args file \"{}\" not found", constructor_args_path.display());
+    }
+    if constructor_args_path.extension() == Some(std::ffi::OsStr::new("json")) {
+        match read_json_file(constructor_args_path) {
+            Ok(args) => Ok(args),
+            Err(err) => eyre::bail!(
+                "Constructor args file \"{}\" must encode a json array: \"{}\"",
+                constructor_args_path.display(),
+                err
+            ),
+        }
+    } else {
+        let file = fs::read_to_string(constructor_args_path)?;
+        Ok(file.split_whitespace().map(str::to_string).collect())
+    }
+}
+
+/// Parses the constructor arguments into tokens of the constructor's input types.
+///
+/// The arguments of a constructor with multiple inputs can also be given as a single `0x` prefixed
+/// argument, the ABI-encoded arguments, e.g. as output by `cast abi-encode`.
+pub fn parse_constructor_args(
+    constructor: &Constructor,
+    constructor_args: &[String],
+) -> eyre::Result<Vec<Token>> {
+    let types = constructor.inputs.iter().map(|input| input.kind.clone()).collect::<Vec<_>>();
+    let signature = format!(
+        "constructor({})",
+        types.iter().map(ToString::to_string).collect::<Vec<_>>().join(",")
+    );
+
+    if let [encoded] = constructor_args {
+        if let (true, Some(encoded)) = (types.len() > 1, encoded.strip_prefix("0x")) {
+            let data = hex::decode(encoded)
+                .wrap_err("The ABI-encoded constructor arguments are not hex encoded")?;
+            return ethers::abi::decode(&types, &data).map_err(|err| {
+                eyre::eyre!(
+                    "Could not decode the ABI-encoded constructor arguments as the inputs of `{}`: {}",
+                    signature,
+                    err
+                )
+            })
+        }
+    }
+
+    if constructor_args.len() != types.len() {
+        eyre::bail!(
+            "`{}` takes {} arguments, but {} were provided",
+            signature,
+            types.len(),
+            constructor_args.len()
+        )
+    }
+    let params = types.iter().zip(constructor_args).map(|(kind, arg)| (kind, arg.as_str()));
+    parse_tokens(params, true)
+        .wrap_err_with(|| format!("Invalid constructor arguments for `{signature}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{Param, ParamType};
+
+    fn constructor(kinds: Vec<ParamType>) -> Constructor {
+        Constructor {
+            inputs: kinds
+                .into_iter()
+                .map(|kind| Param { name: String::new(), kind, internal_type: None })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn can_parse_constructor_args() {
+        let constructor = constructor(vec![ParamType::Address, ParamType::Uint(256)]);
+        let owner = "0x00000000000000000000000000000000000000aa";
+        let tokens =
+            parse_constructor_args(&constructor, &[owner.to_string(), "7".to_string()]).unwrap();
+        assert_eq!(tokens[1], Token::Uint(7u64.into()));
+
+        // the ABI-encoded arguments
+        let encoded = format!("0x{}", hex::encode(ethers::abi::encode(&tokens)));
+        assert_eq!(parse_constructor_args(&constructor, &[encoded]).unwrap(), tokens);
+
+        let err = parse_constructor_args(&constructor, &[owner.to_string()]).unwrap_err();
+        assert!(err.to_string().contains("takes 2 arguments, but 1 were provided"));
+        assert!(
+            parse_constructor_args(&constructor, &[owner.to_string(), "x".to_string()]).is_err()
+        );
+        assert!(parse_constructor_args(&constructor, &["0x00".to_string()]).is_err());
     }
 }
@@ -0,0 +1,334 @@
+//! Create2 command
+use super::create::{
+    fill_deployment, is_legacy, parse_constructor_args, read_constructor_args, verify_deployment,
+};
+use crate::{
+    cmd::{forge::build::CoreBuildArgs, utils},
+    compile,
+    opts::{EthereumOpts, TransactionOpts, WalletType},
+    utils::get_provider,
+};
+use cast::SimpleCast;
+use clap::{Parser, ValueHint};
+use ethers::{
+    prelude::{artifacts::BytecodeObject, Middleware},
+    solc::{info::ContractInfo, utils::canonicalized},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Eip1559TransactionRequest,
+        TransactionRequest, H256,
+    },
+    utils::{get_create2_address_from_hash, keccak256},
+};
+use foundry_common::provider::ProvidersManager;
+use foundry_config::Config;
+use rayon::prelude::*;
+use serde_json::json;
+use std::{path::PathBuf, sync::Arc};
+use tracing::log::trace;
+
+#[derive(Debug, Clone, Parser)]
+pub struct Create2Args {
+    #[clap(
+        help = "The contract identifier in the form `<path>:<contractname>`.",
+        value_name = "CONTRACT"
+    )]
+    contract: ContractInfo,
+
+    #[clap(
+        long,
+        multiple_values = true,
+        help = "The constructor arguments.",
+        name = "constructor_args",
+        conflicts_with = "constructor_args_path",
+        value_name = "ARGS"
+    )]
+    constructor_args: Vec<String>,
+
+    #[clap(
+        long,
+        help = "The path to a file containing the constructor arguments.",
+        value_hint = ValueHint::FilePath,
+        name = "constructor_args_path",
+        conflicts_with = "constructor_args",
+        value_name = "FILE"
+    )]
+    constructor_args_path: Option<PathBuf>,
+
+    #[clap(
+        long,
+        help = "The salt of the deployment. Defaults to the zero salt, or a mined salt if a vanity pattern is given.",
+        conflicts_with_all = &["starts_with", "ends_with"],
+        value_name = "SALT"
+    )]
+    salt: Option<H256>,
+
+    #[clap(
+        long,
+        help = "Mine a salt for an address starting with the given hex characters.",
+        value_name = "HEX"
+    )]
+    starts_with: Option<String>,
+
+    #[clap(
+        long,
+        help = "Mine a salt for an address ending with the given hex characters.",
+        value_name = "HEX"
+    )]
+    ends_with: Option<String>,
+
+    #[clap(
+        long,
+        help = "The address of the CREATE2 deployer.",
+        default_value = "0x4e59b44847b379578588920ca78fbf26c0b4956c",
+        value_name = "ADDRESS"
+    )]
+    deployer: Address,
+
+    #[clap(long, help = "Send the deployment transaction to the CREATE2 deployer.")]
+    broadcast: bool,
+
+    #[clap(long, help = "Verify contract after creation.", requires = "broadcast")]
+    verify: bool,
+
+    #[clap(
+        long,
+        help = "Send via `eth_sendTransaction` using the `--from` argument or `$ETH_FROM` as sender",
+        requires_all = &["from", "broadcast"]
+    )]
+    unlocked: bool,
+
+    #[clap(
+        long = "json",
+        help_heading = "DISPLAY OPTIONS",
+        help = "Print the deployment information as JSON."
+    )]
+    json: bool,
+
+    #[clap(flatten, next_help_heading = "BUILD OPTIONS")]
+    opts: CoreBuildArgs,
+
+    #[clap(flatten, next_help_heading = "TRANSACTION OPTIONS")]
+    tx: TransactionOpts,
+
+    #[clap(flatten, next_help_heading = "ETHEREUM OPTIONS")]
+    eth: EthereumOpts,
+}
+
+impl Create2Args {
+    /// Computes the CREATE2 address of the contract and deploys it if requested
+    pub async fn run(mut self) -> eyre::Result<()> {
+        let project = self.opts.project()?;
+        let mut output = if self.json || self.opts.silent {
+            compile::suppress_compile(&project)
+        } else {
+            compile::compile(&project, false, false)
+        }?;
+
+        if let Some(ref mut path) = self.contract.path {
+            // paths are absolute in the project's output
+            *path = format!("{}", canonicalized(project.root().join(&path)).display());
+        }
+
+        let (abi, bin, _) = utils::remove_contract(&mut output, &self.contract)?;
+        let mut init_code = match bin.object {
+            BytecodeObject::Bytecode(bytes) => bytes.to_vec(),
+            _ => eyre::bail!("Dynamic linking not supported in `create2` command - deploy the library contracts first, then provide the address to link at compile time"),
+        };
+
+        let args = match abi.constructor {
+            Some(ref constructor) => {
+                let constructor_args = read_constructor_args(
+                    &self.constructor_args,
+                    self.constructor_args_path.as_ref(),
+                )?;
+                let args = parse_constructor_args(constructor, &constructor_args)?;
+                init_code = constructor.encode_input(init_code, &args)?;
+                args
+            }
+            None => vec![],
+        };
+
+        let init_code_hash = H256::from(keccak256(&init_code));
+        let (salt, address) = if self.starts_with.is_some() || self.ends_with.is_some() {
+            if !self.json {
+                println!("Mining salt...");
+            }
+            mine_salt(
+                self.deployer,
+                init_code_hash,
+                self.starts_with.as_deref().unwrap_or_default(),
+                self.ends_with.as_deref().unwrap_or_default(),
+            )?
+        } else {
+            let salt = self.salt.unwrap_or_default();
+            (salt, create2_address(self.deployer, salt, init_code_hash))
+        };
+
+        let deployment = if self.broadcast {
+            // the CREATE2 deployer expects the salt followed by the init code
+            let data = [salt.as_bytes(), &init_code].concat();
+            Some(self.deploy(data).await?)
+        } else {
+            None
+        };
+        let tx_hash = deployment.map(|(tx_hash, _)| tx_hash);
+
+        if self.json {
+            let output = json!({
+                "deployer": SimpleCast::checksum_address(&self.deployer)?,
+                "salt": salt,
+                "initCodeHash": init_code_hash,
+                "address": SimpleCast::checksum_address(&address)?,
+                "transactionHash": tx_hash,
+            });
+            println!("{output}");
+        } else {
+            println!("Deployer: {}", SimpleCast::checksum_address(&self.deployer)?);
+            println!("Salt: {:?}", salt);
+            println!("Init code hash: {:?}", init_code_hash);
+            println!("Address: {}", SimpleCast::checksum_address(&address)?);
+            if let Some(tx_hash) = tx_hash {
+                println!("Transaction hash: {:?}", tx_hash);
+            }
+        }
+
+        if let (true, Some((_, chain))) = (self.verify, deployment) {
+            verify_deployment(
+                self.contract,
+                address,
+                chain,
+                &abi,
+                &args,
+                &self.opts,
+                self.eth.etherscan_api_key,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends the deployment to the CREATE2 deployer and returns the transaction hash and the chain
+    /// it was sent to
+    async fn deploy(&self, data: Vec<u8>) -> eyre::Result<(H256, u64)> {
+        let config = Config::from(&self.eth);
+        let providers = ProvidersManager::new(config.rpc_endpoints.clone());
+        let provider = get_provider(
+            &providers,
+            config.eth_rpc_url.as_deref().unwrap_or("http://localhost:8545"),
+            false,
+        )?;
+
+        if self.unlocked {
+            let sender = self.eth.wallet.from.expect("is required");
+            trace!("deploying with unlocked account={:?}", sender);
+            let provider =
+                Arc::try_unwrap(provider).expect("Only one ref; qed.").with_sender(sender);
+            return self.send(data, provider).await
+        }
+
+        let chain_id = provider.get_chainid().await?;
+        match self.eth.signer_with(chain_id, provider).await? {
+            Some(WalletType::Ledger(signer)) => self.send(data, signer).await,
+            Some(WalletType::Local(signer)) => self.send(data, signer).await,
+            Some(WalletType::Trezor(signer)) => self.send(data, signer).await,
+            Some(WalletType::Remote(signer)) => self.send(data, signer).await,
+            Some(WalletType::Aws(signer)) => self.send(data, signer).await,
+            None => eyre::bail!("could not find a signer, please provide a wallet or `--unlocked`"),
+        }
+    }
+
+    /// Sends the deployment like `forge create` does, with the same gas, fee and nonce handling
+    async fn send<M: Middleware>(&self, data: Vec<u8>, provider: M) -> eyre::Result<(H256, u64)>
+    where
+        M::Error: 'static,
+    {
+        let chain = provider.get_chainid().await?.as_u64();
+        let from = provider.default_sender().expect("no sender address set for provider");
+
+        let is_legacy = is_legacy(&self.tx, chain);
+        let mut tx: TypedTransaction = if is_legacy {
+            TransactionRequest::new().from(from).to(self.deployer).data(data).into()
+        } else {
+            Eip1559TransactionRequest::new().from(from).to(self.deployer).data(data).into()
+        };
+        fill_deployment(&provider, &mut tx, &self.tx, is_legacy).await?;
+
+        let pending_tx = provider.send_transaction(tx, None).await?;
+        let tx_hash = *pending_tx;
+        let receipt =
+            pending_tx.await?.ok_or_else(|| eyre::eyre!("Deployment {:?} was dropped", tx_hash))?;
+        if receipt.status == Some(0.into()) {
+            eyre::bail!("Deployment {:?} reverted", tx_hash)
+        }
+        Ok((tx_hash, chain))
+    }
+}
+
+/// Returns the address of a contract deployed by `deployer` with the given salt
+pub fn create2_address(deployer: Address, salt: H256, init_code_hash: H256) -> Address {
+    get_create2_address_from_hash(
+        deployer,
+        salt.as_bytes().to_vec(),
+        init_code_hash.as_bytes().to_vec(),
+    )
+}
+
+/// Returns the lowest salt for which the address of the contract, in lowercase hex, starts and
+/// ends with the given hex characters, together with the address
+pub fn mine_salt(
+    deployer: Address,
+    init_code_hash: H256,
+    starts_with: &str,
+    ends_with: &str,
+) -> eyre::Result<(H256, Address)> {
+    let starts_with = starts_with.trim_start_matches("0x").to_lowercase();
+    let ends_with = ends_with.to_lowercase();
+    if !starts_with.chars().chain(ends_with.chars()).all(|c| c.is_ascii_hexdigit()) {
+        eyre::bail!("The vanity patterns must only contain hex characters")
+    }
+    if starts_with.len() + ends_with.len() > 40 {
+        eyre::bail!("The vanity patterns are longer than an address")
+    }
+
+    (0..u64::MAX)
+        .into_par_iter()
+        .map(H256::from_low_u64_be)
+        .find_map_first(|salt| {
+            let address = create2_address(deployer, salt, init_code_hash);
+            let hex = hex::encode(address);
+            (hex.starts_with(&starts_with) && hex.ends_with(&ends_with)).then(|| (salt, address))
+        })
+        .ok_or_else(|| eyre::eyre!("Could not find a salt matching the vanity patterns"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cast::executor::inspector::DEFAULT_CREATE2_DEPLOYER;
+
+    #[test]
+    fn can_compute_create2_address() {
+        // <https://eips.ethereum.org/EIPS/eip-1014> example 5
+        let deployer = "0x00000000000000000000000000000000deadbeef".parse().unwrap();
+        let salt =
+            "0x00000000000000000000000000000000000000000000000000000000cafebabe".parse().unwrap();
+        let init_code_hash = H256::from(keccak256(hex::decode("deadbeef").unwrap()));
+        assert_eq!(
+            create2_address(deployer, salt, init_code_hash),
+            "0x60f3f640a8508fC6a86d45DF051962668E1e8AC7".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn can_mine_salt() {
+        let init_code_hash = H256::from(keccak256([0x60, 0x00]));
+        let (salt, address) =
+            mine_salt(DEFAULT_CREATE2_DEPLOYER, init_code_hash, "0xAB", "c").unwrap();
+        assert_eq!(address, create2_address(DEFAULT_CREATE2_DEPLOYER, salt, init_code_hash));
+        let hex = hex::encode(address);
+        assert!(hex.starts_with("ab") && hex.ends_with('c'));
+
+        assert!(mine_salt(DEFAULT_CREATE2_DEPLOYER, init_code_hash, "xyz", "").is_err());
+    }
+}
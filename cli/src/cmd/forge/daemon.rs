@@ -0,0 +1,279 @@
+//! Daemon command
+use crate::{
+    cmd::{forge::build::CoreBuildArgs, Cmd},
+    compile, utils,
+};
+use clap::{Parser, ValueHint};
+use ethers::{solc::utils::RuntimeOrHandle, types::Address};
+use forge::{
+    executor::{inspector::CheatsConfig, opts::EvmOpts},
+    revm,
+    trace::{identifier::LocalTraceIdentifier, CallTraceArena, CallTraceDecoderBuilder, TraceKind},
+    MultiContractRunner, MultiContractRunnerBuilder, TestOptions,
+};
+use foundry_common::evm::EvmArgs;
+use foundry_config::{figment::Figment, Config};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, path::PathBuf};
+use tracing::trace;
+
+// Loads project's figment and merges the build cli arguments into it
+foundry_config::impl_figment_convert!(DaemonArgs, opts, evm_opts);
+
+/// CLI arguments for `forge daemon`.
+#[derive(Debug, Clone, Parser)]
+pub struct DaemonArgs {
+    /// The path of the unix socket to listen on.
+    ///
+    /// Defaults to `forge.sock` in the cache directory of the project.
+    #[clap(long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    #[clap(flatten, next_help_heading = "EVM OPTIONS")]
+    evm_opts: EvmArgs,
+
+    #[clap(flatten, next_help_heading = "BUILD OPTIONS")]
+    opts: CoreBuildArgs,
+}
+
+impl Cmd for DaemonArgs {
+    type Output = ();
+
+    fn run(self) -> eyre::Result<Self::Output> {
+        trace!(target: "forge::daemon", "executing daemon command");
+
+        let figment: Figment = (&self).into();
+        let evm_opts: EvmOpts = figment.extract()?;
+        let config = Config::from_provider(figment).sanitized();
+        let socket = self.socket.unwrap_or_else(|| config.cache_path.join("forge.sock"));
+
+        let mut daemon = Daemon::new(config, evm_opts);
+        daemon.listen(socket)
+    }
+}
+
+/// Serves JSON-RPC requests for a single project, keeping the compiled project and the test
+/// runner in memory between requests.
+///
+/// Requests and responses are JSON-RPC 2.0 objects separated by newlines. The supported methods
+/// are:
+///
+/// - `compile`: (re)compiles the project and returns the identifiers of the test contracts
+/// - `runTest`: runs a single test, given by `contract` and `test`, and returns its result
+/// - `decodeTraces`: decodes the `traces` of a test result, optionally with its
+///   `labeled_addresses`, and returns them rendered as strings
+/// - `shutdown`: stops the daemon
+struct Daemon {
+    config: Config,
+    evm_opts: EvmOpts,
+    env: revm::Env,
+    /// The runner and trace identifier of the last successful build
+    build: Option<(MultiContractRunner, LocalTraceIdentifier)>,
+}
+
+impl Daemon {
+    fn new(config: Config, evm_opts: EvmOpts) -> Self {
+        let env = evm_opts.evm_env_blocking();
+        Self { config, evm_opts, env, build: None }
+    }
+
+    /// Accepts connections on the unix socket at `path` until a `shutdown` request is received.
+    ///
+    /// Connections are served one at a time.
+    #[cfg(unix)]
+    fn listen(&mut self, path: PathBuf) -> eyre::Result<()> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            os::unix::net::UnixListener,
+        };
+
+        // a stale socket of a previous daemon would make binding fail
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        println!("Listening on {}", path.display());
+
+        'serve: for stream in listener.incoming() {
+            let mut stream = stream?;
+            for line in BufReader::new(stream.try_clone()?).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue
+                }
+
+                let (response, shutdown) = self.respond(&line);
+                if let Err(err) = writeln!(stream, "{response}") {
+                    trace!(target: "forge::daemon", ?err, "client disconnected");
+                    break
+                }
+                if shutdown {
+                    break 'serve
+                }
+            }
+        }
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn listen(&mut self, _: PathBuf) -> eyre::Result<()> {
+        eyre::bail!("`forge daemon` is only supported on unix platforms")
+    }
+
+    /// Returns the response to a single request, and whether the daemon should shut down.
+    fn respond(&mut self, request: &str) -> (Value, bool) {
+        let request: Request = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(err) => return (RpcError::new(-32700, err).into_response(Value::Null), false),
+        };
+        trace!(target: "forge::daemon", method = %request.method, "handling request");
+
+        let response = match self.handle(&request.method, request.params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": request.id, "result": result }),
+            Err(err) => err.into_response(request.id),
+        };
+        (response, request.method == "shutdown")
+    }
+
+    fn handle(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "compile" => Ok(self.compile()?),
+            "runTest" => {
+                let params: RunTestParams = parse_params(params)?;
+                let (runner, _) = self.build()?;
+                let result = runner.run_test(&params.contract, &params.test)?;
+                Ok(serde_json::to_value(result).map_err(eyre::Report::from)?)
+            }
+            "decodeTraces" => {
+                let params: DecodeTracesParams = parse_params(params)?;
+                let (_, identifier) = self.build()?;
+
+                let mut decoder = CallTraceDecoderBuilder::new()
+                    .with_labels(params.labeled_addresses)
+                    .with_events(identifier.events())
+                    .build();
+                let rt = RuntimeOrHandle::new();
+                let traces = params
+                    .traces
+                    .into_iter()
+                    .map(|(_, mut trace)| {
+                        decoder.identify(&trace, identifier);
+                        rt.block_on(decoder.decode(&mut trace));
+                        trace.to_string()
+                    })
+                    .collect::<Vec<_>>();
+                Ok(traces.into())
+            }
+            "shutdown" => Ok(Value::Null),
+            _ => Err(RpcError::new(-32601, format!("Method not found: {method}"))),
+        }
+    }
+
+    /// Compiles the project and replaces the runner with one for the new build.
+    ///
+    /// If the project fails to compile, the previous build is kept.
+    fn compile(&mut self) -> eyre::Result<Value> {
+        let project = self.config.project()?;
+        let output = match compile::suppress_compile(&project) {
+            Ok(output) => output,
+            Err(err) => return Ok(json!({ "success": false, "errors": err.to_string() })),
+        };
+
+        let cfg = proptest::test_runner::Config {
+            failure_persistence: None,
+            cases: self.config.fuzz_runs,
+            max_local_rejects: self.config.fuzz_max_local_rejects,
+            max_global_rejects: self.config.fuzz_max_global_rejects,
+            ..Default::default()
+        };
+        let runner = MultiContractRunnerBuilder::default()
+            .fuzzer(proptest::test_runner::TestRunner::new(cfg))
+            .initial_balance(self.evm_opts.initial_balance)
+            .evm_spec(utils::evm_spec(&self.config.evm_version))
+            .sender(self.evm_opts.sender)
+            .with_fork(self.evm_opts.get_fork(&self.config, self.env.clone()))
+            .with_cheats_config(CheatsConfig::new(&self.config, &self.evm_opts))
+            .with_test_options(TestOptions::from(&self.config))
+            .with_libraries(self.config.parsed_libraries()?)
+            .build(&project.paths.root, output, self.env.clone(), self.evm_opts.clone())?;
+        let identifier = LocalTraceIdentifier::new(&runner.known_contracts);
+
+        let contracts = runner.contracts.keys().map(|id| id.identifier()).collect::<Vec<_>>();
+        self.build = Some((runner, identifier));
+        Ok(json!({ "success": true, "contracts": contracts }))
+    }
+
+    /// Returns the current build, compiling the project first if there is none yet.
+    fn build(&mut self) -> eyre::Result<&(MultiContractRunner, LocalTraceIdentifier)> {
+        if self.build.is_none() {
+            let output = self.compile()?;
+            if self.build.is_none() {
+                eyre::bail!(
+                    "Failed to compile the project: {}",
+                    output["errors"].as_str().unwrap_or_default()
+                )
+            }
+        }
+        Ok(self.build.as_ref().expect("is built"))
+    }
+}
+
+/// A JSON-RPC request
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct RunTestParams {
+    contract: String,
+    test: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DecodeTracesParams {
+    traces: Vec<(TraceKind, CallTraceArena)>,
+    #[serde(default)]
+    labeled_addresses: BTreeMap<Address, String>,
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|err| RpcError::new(-32602, err))
+}
+
+/// A JSON-RPC error
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self { code, message: message.to_string() }
+    }
+
+    fn into_response(self, id: Value) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": self.code, "message": self.message },
+        })
+    }
+}
+
+impl From<eyre::Report> for RpcError {
+    fn from(err: eyre::Report) -> Self {
+        Self::new(-32000, err)
+    }
+}
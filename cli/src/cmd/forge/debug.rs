@@ -49,6 +49,8 @@ impl DebugArgs {
             args: self.args,
             target_contract: self.target_contract,
             sig: self.sig,
+            constructor_args: vec![],
+            constructor_args_path: None,
             legacy: false,
             broadcast: false,
             opts: BuildArgs {
@@ -67,6 +69,12 @@ impl DebugArgs {
             json: false,
             with_gas_price: None,
             retry: RETRY_VERIFY_ON_CREATE,
+            plan: false,
+            unsigned: false,
+            access_list: false,
+            dump_state: None,
+            simulate_block: None,
+            export: vec![],
         };
         script.run_script().await
     }
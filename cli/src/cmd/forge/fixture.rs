@@ -0,0 +1,118 @@
+//! Fixture command
+use crate::{
+    cmd::{
+        forge::{build::CoreBuildArgs, test::Filter},
+        Cmd,
+    },
+    compile::ProjectCompiler,
+    utils::{self, get_provider},
+};
+use clap::Parser;
+use ethers::{
+    prelude::Middleware,
+    types::{Address, Bytes, U256},
+};
+use forge::{
+    executor::{inspector::CheatsConfig, opts::EvmOpts},
+    revm::AccountInfo,
+    MultiContractRunnerBuilder,
+};
+use foundry_common::{evm::EvmArgs, provider::ProvidersManager};
+use foundry_config::{figment::Figment, Config};
+use std::collections::BTreeMap;
+use tracing::trace;
+use yansi::Paint;
+
+// Loads project's figment and merges the build cli arguments into it
+foundry_config::impl_figment_convert!(FixtureArgs, opts, evm_opts);
+
+/// CLI arguments for `forge fixture`.
+#[derive(Debug, Clone, Parser)]
+pub struct FixtureArgs {
+    #[clap(flatten)]
+    filter: Filter,
+
+    /// The RPC endpoint of the Anvil node to push the state to.
+    #[clap(long, default_value = "http://localhost:8545", value_name = "URL")]
+    anvil_url: String,
+
+    #[clap(flatten, next_help_heading = "EVM OPTIONS")]
+    evm_opts: EvmArgs,
+
+    #[clap(flatten, next_help_heading = "BUILD OPTIONS")]
+    opts: CoreBuildArgs,
+}
+
+impl Cmd for FixtureArgs {
+    type Output = ();
+
+    fn run(self) -> eyre::Result<Self::Output> {
+        trace!(target: "forge::fixture", "executing fixture command");
+
+        let figment: Figment = (&self).into();
+        let evm_opts: EvmOpts = figment.extract()?;
+        let config = Config::from_provider(figment).sanitized();
+        let filter = self.filter.with_merged_config(&config);
+
+        let project = config.project()?;
+        let output = ProjectCompiler::default().compile(&project)?;
+
+        let env = evm_opts.evm_env_blocking();
+        let mut runner = MultiContractRunnerBuilder::default()
+            .initial_balance(evm_opts.initial_balance)
+            .evm_spec(utils::evm_spec(&config.evm_version))
+            .sender(evm_opts.sender)
+            .with_fork(evm_opts.get_fork(&config, env.clone()))
+            .with_cheats_config(CheatsConfig::new(&config, &evm_opts))
+            .build(project.paths.root, output, env, evm_opts)?;
+
+        let (id, setup, executor) = runner.setup(&filter)?;
+        if setup.setup_failed {
+            eyre::bail!(
+                "Failed to set up {}: {}",
+                id,
+                setup.reason.unwrap_or_else(|| "unknown reason".to_string())
+            )
+        }
+
+        let accounts = executor.backend().local_accounts();
+        let providers = ProvidersManager::new(config.rpc_endpoints.clone());
+        utils::block_on(push_state(&providers, &self.anvil_url, &accounts))?;
+
+        println!(
+            "{} Pushed the state of {} accounts after setting up {} to {}",
+            Paint::green("Success:"),
+            accounts.len(),
+            id,
+            self.anvil_url
+        );
+        println!("Test contract deployed at {:?}", setup.address);
+        for (address, label) in setup.labeled_addresses {
+            println!("  {label}: {address:?}");
+        }
+        Ok(())
+    }
+}
+
+/// Injects the given accounts into the node at `url` via the `anvil_set*` RPC methods
+async fn push_state(
+    providers: &ProvidersManager,
+    url: &str,
+    accounts: &BTreeMap<Address, (AccountInfo, BTreeMap<U256, U256>)>,
+) -> eyre::Result<()> {
+    let provider = get_provider(providers, url, true)?;
+    for (address, (info, storage)) in accounts {
+        trace!(target: "forge::fixture", ?address, "pushing account");
+        provider.request::<_, ()>("anvil_setBalance", (address, info.balance)).await?;
+        provider.request::<_, ()>("anvil_setNonce", (address, U256::from(info.nonce))).await?;
+        if let Some(code) = info.code.as_ref().filter(|code| !code.is_empty()) {
+            provider
+                .request::<_, ()>("anvil_setCode", (address, Bytes::from(code.clone())))
+                .await?;
+        }
+        for (slot, value) in storage {
+            provider.request::<_, ()>("anvil_setStorageAt", (address, slot, value)).await?;
+        }
+    }
+    Ok(())
+}
@@ -1,11 +1,50 @@
-use crate::cmd::{
-    forge::build::{CoreBuildArgs, ProjectPathsArgs},
-    Cmd,
+use crate::{
+    cmd::{
+        forge::build::{CoreBuildArgs, ProjectPathsArgs},
+        Cmd,
+    },
+    compile,
 };
 use clap::{Parser, ValueHint};
+use ethers::solc::{
+    artifacts::{BytecodeObject, Source},
+    AggregatedCompilerOutput, Artifact, CompilerInput, Graph, Project, ProjectPathsConfig, Solc,
+};
 use foundry_common::fs;
 use foundry_config::Config;
-use std::path::PathBuf;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use semver::Version;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+static RE_LICENSE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^[ \t]*//[ \t]*SPDX-License-Identifier:[ \t]*(?P<license>[^\n]*?)[ \t]*(\n|$)")
+        .unwrap()
+});
+static RE_PRAGMA: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^[ \t]*pragma[ \t]+(?P<name>\w+)[ \t]+(?P<value>[^;]*);[ \t]*(\n|$)").unwrap()
+});
+static RE_IMPORT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^[ \t]*import[\s][^;]*;[ \t]*(\n|$)").unwrap());
+static RE_CONTRACT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?m)^[ \t]*(abstract\s+)?(contract|interface|library)\s+(?P<name>\w+)(?P<bases>[^{]*)\{",
+    )
+    .unwrap()
+});
+static RE_IMPORT_SYMBOLS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*import\s*\{(?P<symbols>[^}]*)\}").unwrap());
+static RE_IMPORT_UNIT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"^\s*import\s+(\*|"[^"]*"|'[^']*')\s+as\s+(?P<alias>\w+)"#).unwrap());
+static RE_SYMBOL_ALIAS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*(?P<symbol>\w+)\s+as\s+(?P<alias>\w+)\s*$").unwrap());
+static RE_LIBRARY_PLACEHOLDER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"__\$[0-9a-fA-F]{34}\$__").unwrap());
 
 #[derive(Debug, Clone, Parser)]
 pub struct FlattenArgs {
@@ -22,6 +61,12 @@ pub struct FlattenArgs {
     )]
     pub output: Option<PathBuf>,
 
+    #[clap(
+        long,
+        help = "Check that the flattened contracts compile to the same bytecode as the contracts of the project."
+    )]
+    pub check: bool,
+
     #[clap(flatten, next_help_heading = "PROJECT OPTIONS")]
     project_paths: ProjectPathsArgs,
 }
@@ -29,7 +74,7 @@ pub struct FlattenArgs {
 impl Cmd for FlattenArgs {
     type Output = ();
     fn run(self) -> eyre::Result<Self::Output> {
-        let FlattenArgs { target_path, output, project_paths } = self;
+        let FlattenArgs { target_path, output, check, project_paths } = self;
 
         // flatten is a subset of `BuildArgs` so we can reuse that to get the config
         let build_args = CoreBuildArgs {
@@ -53,19 +98,598 @@ impl Cmd for FlattenArgs {
 
         let paths = config.project_paths();
         let target_path = dunce::canonicalize(target_path)?;
-        let flattened = paths
-            .flatten(&target_path)
+        let flattened = Flattened::new(&paths, &target_path)
             .map_err(|err| eyre::Error::msg(format!("Failed to flatten the file: {err}")))?;
 
+        if check {
+            flattened.check(&config.project()?, &target_path)?;
+        }
+
         match output {
             Some(output) => {
                 fs::create_dir_all(&output.parent().unwrap())?;
-                fs::write(&output, flattened)?;
+                fs::write(&output, flattened.source)?;
                 println!("Flattened file written at {}", output.display());
             }
-            None => println!("{flattened}"),
+            None => println!("{}", flattened.source),
+        };
+
+        Ok(())
+    }
+}
+
+/// A file flattened together with all files it imports
+#[derive(Debug, Clone)]
+pub struct Flattened {
+    /// The flattened source
+    pub source: String,
+    /// The files the source was flattened from
+    pub files: Vec<PathBuf>,
+}
+
+impl Flattened {
+    /// Flattens the target file and all files it imports, see [flatten_sources]
+    pub fn new(paths: &ProjectPathsConfig, target: &Path) -> eyre::Result<Self> {
+        let graph = Graph::resolve(paths)?;
+        let target = *graph
+            .files()
+            .get(target)
+            .ok_or_else(|| eyre::eyre!("{} is not part of the project", target.display()))?;
+
+        // every file comes after the files it imports, unless they import each other
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![(target, false)];
+        while let Some((idx, imports_visited)) = stack.pop() {
+            if imports_visited {
+                order.push(idx);
+            } else if visited.insert(idx) {
+                stack.push((idx, true));
+                stack.extend(graph.imported_nodes(idx).iter().rev().map(|import| (*import, false)));
+            }
+        }
+
+        let files = order.iter().map(|idx| graph.node(*idx).unpack().0.clone()).collect::<Vec<_>>();
+        let sources = files
+            .iter()
+            .map(|file| {
+                let name = file.strip_prefix(&paths.root).unwrap_or(file).display().to_string();
+                Ok((name, fs::read_to_string(file)?))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        Ok(Self { source: flatten_sources(&sources)?, files })
+    }
+
+    /// Compiles the flattened source with the compiler version and settings the project compiles
+    /// the target with, and checks that every flattened contract compiles to the same deployed
+    /// bytecode as the contract of the project, ignoring the metadata hash and library addresses.
+    pub fn check(&self, project: &Project, target: &Path) -> eyre::Result<()> {
+        let output = compile::compile_files(project, vec![target.to_path_buf()], true)?;
+        let mut version = None;
+        let mut expected = HashMap::new();
+        for (id, artifact) in output.into_artifacts() {
+            if !self.files.contains(&id.source) {
+                continue
+            }
+            if id.source == target {
+                version = Some(Version::new(id.version.major, id.version.minor, id.version.patch));
+            }
+            if let Some(object) = artifact.get_deployed_bytecode_object() {
+                expected.insert(id.name, normalize_bytecode(&object)?);
+            }
+        }
+        let version =
+            version.ok_or_else(|| eyre::eyre!("{} has no compiled contracts", target.display()))?;
+
+        let solc = if let Some(solc) = Solc::find_svm_installed_version(version.to_string())? {
+            solc
+        } else {
+            Solc::blocking_install(&version)?
         };
+        let input = CompilerInput {
+            language: "Solidity".to_string(),
+            sources: BTreeMap::from([(
+                "Flattened.sol".into(),
+                Source { content: self.source.clone() },
+            )]),
+            settings: project.solc_config.settings.clone(),
+        }
+        .normalize_evm_version(&version);
+
+        let out = solc.compile(&input)?;
+        if out.has_error() {
+            let mut output = AggregatedCompilerOutput::default();
+            output.extend(version, out);
+            eyre::bail!("Failed to compile the flattened source:\n{}", output.diagnostics(&[]))
+        }
 
+        let mut mismatches = Vec::new();
+        for (name, contract) in out.contracts.values().flat_map(|contracts| contracts.iter()) {
+            let object = contract
+                .evm
+                .as_ref()
+                .and_then(|evm| evm.deployed_bytecode.as_ref())
+                .and_then(|deployed| deployed.bytecode.as_ref())
+                .map(|bytecode| normalize_bytecode(&bytecode.object))
+                .transpose()?;
+            if object.as_ref() != expected.get(name) {
+                mismatches.push(name.as_str());
+            }
+        }
+        if !mismatches.is_empty() {
+            eyre::bail!(
+                "The flattened {} compile to different bytecode than the contracts of the project",
+                mismatches.join(", ")
+            )
+        }
+        println!("The flattened contracts compile to the same bytecode.");
         Ok(())
     }
 }
+
+/// Flattens the `(name, content)` sources, given in the order of their imports, into a single
+/// source.
+///
+/// Files are kept in the given order, except that the files of base contracts are moved before the
+/// files of the contracts that inherit from them. Since solidity resolves all other names of a
+/// source unit regardless of the order they're declared in, this is all it takes to flatten
+/// circular imports.
+///
+/// The license identifiers of the files are combined into a single one that requires all of them,
+/// and the pragmas are deduplicated. Both are sorted, so the output does not depend on the order
+/// the files are imported in.
+///
+/// Aliases of imports are resolved, i.e. `Y` of `import {X as Y} from "..."` is renamed to `X` and
+/// `A.` of `import "..." as A` is dropped. Comments and strings are left untouched.
+pub fn flatten_sources(sources: &[(String, String)]) -> eyre::Result<String> {
+    let mut licenses = BTreeSet::new();
+    let mut versions = BTreeSet::new();
+    let mut pragmas = BTreeSet::new();
+    let mut bodies = Vec::with_capacity(sources.len());
+    let mut declared = HashMap::new();
+    let mut bases = Vec::with_capacity(sources.len());
+
+    for (idx, (name, content)) in sources.iter().enumerate() {
+        // everything but the licenses is matched outside of comments and strings
+        let code = mask_comments_and_strings(content);
+        let mut removed = Vec::new();
+        for license in RE_LICENSE.captures_iter(content) {
+            licenses.insert(license["license"].to_string());
+            removed.push(license.get(0).unwrap().range());
+        }
+        for pragma in RE_PRAGMA.captures_iter(&code) {
+            let value = pragma["value"].split_whitespace().collect::<Vec<_>>().join(" ");
+            if &pragma["name"] == "solidity" {
+                versions.insert(value);
+            } else {
+                pragmas.insert(format!("{} {}", &pragma["name"], value));
+            }
+            removed.push(pragma.get(0).unwrap().range());
+        }
+        let mut aliases = Vec::new();
+        for import in RE_IMPORT.find_iter(&code) {
+            aliases.extend(import_aliases(&content[import.range()]));
+            removed.push(import.range());
+        }
+
+        let mut edits: Vec<(Range<usize>, &str)> =
+            removed.iter().map(|range| (range.clone(), "")).collect();
+        for (alias, symbol) in &aliases {
+            let re = match symbol {
+                Some(_) => Regex::new(&format!(r"\b{}\b", regex::escape(alias)))?,
+                None => Regex::new(&format!(r"\b{}\s*\.\s*", regex::escape(alias)))?,
+            };
+            for m in re.find_iter(&code) {
+                // skip members, e.g. `x.Y`, and the import statements themselves
+                let member = code[..m.start()].trim_end().ends_with('.');
+                let in_removed = removed.iter().any(|range| range.contains(&m.start()));
+                if !member && !in_removed {
+                    edits.push((m.range(), symbol.as_deref().unwrap_or("")));
+                }
+            }
+        }
+        let body = apply_edits(content, edits).trim().to_string();
+        let code = mask_comments_and_strings(&body);
+
+        let mut file_bases = BTreeSet::new();
+        for contract in RE_CONTRACT.captures_iter(&code) {
+            let contract_name = contract["name"].to_string();
+            if let Some(other) = declared.insert(contract_name.clone(), idx) {
+                if other != idx {
+                    eyre::bail!(
+                        "`{}` is declared in both {} and {}",
+                        contract_name,
+                        sources[other].0,
+                        name
+                    )
+                }
+            }
+            file_bases.extend(base_contracts(&contract["bases"]));
+        }
+        bases.push(file_bases);
+        bodies.push(body);
+    }
+
+    if pragmas.contains("abicoder v1") && pragmas.contains("abicoder v2") {
+        eyre::bail!("The files require both `pragma abicoder v1` and `pragma abicoder v2`")
+    }
+
+    // move the files of base contracts before the files that inherit from them, otherwise keep
+    // the order of the files
+    let mut dependencies = bases
+        .iter()
+        .enumerate()
+        .map(|(idx, bases)| {
+            bases
+                .iter()
+                .filter_map(|base| declared.get(base).copied())
+                .filter(|base| *base != idx)
+                .collect::<BTreeSet<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut order = Vec::with_capacity(sources.len());
+    while order.len() < sources.len() {
+        let next = (0..sources.len())
+            .filter(|idx| !order.contains(idx))
+            .find(|idx| dependencies[*idx].is_empty())
+            // circular inheritance doesn't compile anyway
+            .unwrap_or_else(|| (0..sources.len()).find(|idx| !order.contains(idx)).unwrap());
+        order.push(next);
+        dependencies.iter_mut().for_each(|deps| {
+            deps.remove(&next);
+        });
+    }
+
+    let mut out = String::new();
+    if !licenses.is_empty() {
+        let license = if licenses.len() == 1 {
+            licenses.into_iter().next().unwrap()
+        } else {
+            licenses
+                .into_iter()
+                .map(|license| if license.contains(' ') { format!("({license})") } else { license })
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        };
+        writeln!(out, "// SPDX-License-Identifier: {license}")?;
+    }
+    for version in versions {
+        writeln!(out, "pragma solidity {version};")?;
+    }
+    for pragma in pragmas {
+        writeln!(out, "pragma {pragma};")?;
+    }
+    for idx in order {
+        write!(out, "\n// {}\n\n{}\n", sources[idx].0, bodies[idx])?;
+    }
+    Ok(out)
+}
+
+/// Returns the aliases an import statement declares, as `(alias, Some(symbol))` for
+/// `import {symbol as alias} from "..."` and as `(alias, None)` for `import "..." as alias` and
+/// `import * as alias from "..."`
+fn import_aliases(import: &str) -> Vec<(String, Option<String>)> {
+    if let Some(symbols) = RE_IMPORT_SYMBOLS.captures(import) {
+        return symbols["symbols"]
+            .split(',')
+            .filter_map(|symbol| RE_SYMBOL_ALIAS.captures(symbol))
+            .map(|symbol| (symbol["alias"].to_string(), Some(symbol["symbol"].to_string())))
+            .collect()
+    }
+    RE_IMPORT_UNIT
+        .captures(import)
+        .map(|unit| vec![(unit["alias"].to_string(), None)])
+        .unwrap_or_default()
+}
+
+/// Returns the source with its comments and strings replaced by spaces, keeping the positions of
+/// everything else
+fn mask_comments_and_strings(source: &str) -> String {
+    #[derive(Clone, Copy)]
+    enum State {
+        Code,
+        LineComment,
+        BlockComment,
+        String(char),
+    }
+
+    fn mask(masked: &mut String, c: char) {
+        if c == '\n' {
+            masked.push('\n');
+        } else {
+            masked.extend(std::iter::repeat(' ').take(c.len_utf8()));
+        }
+    }
+
+    let mut masked = String::with_capacity(source.len());
+    let mut state = State::Code;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        // whether the next character belongs to the same token, e.g. `*` of `/*`
+        let mut pair = false;
+        match state {
+            State::Code => match (c, next) {
+                ('/', Some('/')) => state = State::LineComment,
+                ('/', Some('*')) => {
+                    pair = true;
+                    state = State::BlockComment
+                }
+                ('"' | '\'', _) => state = State::String(c),
+                _ => {
+                    masked.push(c);
+                    continue
+                }
+            },
+            State::LineComment if c == '\n' => state = State::Code,
+            State::BlockComment if c == '*' && next == Some('/') => {
+                pair = true;
+                state = State::Code
+            }
+            State::String(_) if c == '\\' => pair = true,
+            State::String(quote) if c == quote => state = State::Code,
+            _ => {}
+        }
+        mask(&mut masked, c);
+        if let Some(next) = chars.next_if(|_| pair) {
+            mask(&mut masked, next);
+        }
+    }
+    masked
+}
+
+/// Replaces the non-overlapping ranges of the source with the given replacements
+fn apply_edits(source: &str, mut edits: Vec<(Range<usize>, &str)>) -> String {
+    edits.sort_by_key(|(range, _)| range.start);
+    let mut out = String::with_capacity(source.len());
+    let mut pos = 0;
+    for (range, replacement) in edits {
+        if range.start < pos {
+            continue
+        }
+        out.push_str(&source[pos..range.start]);
+        out.push_str(replacement);
+        pos = range.end;
+    }
+    out.push_str(&source[pos..]);
+    out
+}
+
+/// Returns the names of the base contracts in the part of a contract definition between its name
+/// and its body, e.g. ` is A, B(1, 2), Lib.C `
+fn base_contracts(inheritance: &str) -> Vec<String> {
+    let inheritance = inheritance.trim();
+    let inheritance = match inheritance.strip_prefix("is") {
+        Some(inheritance) if inheritance.starts_with(char::is_whitespace) => inheritance,
+        _ => return Vec::new(),
+    };
+
+    // drop the constructor arguments
+    let mut depth = 0;
+    let inheritance = inheritance
+        .chars()
+        .filter(|c| {
+            match *c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    return false
+                }
+                _ => {}
+            }
+            depth == 0
+        })
+        .collect::<String>();
+
+    inheritance
+        .split(',')
+        .filter_map(|base| base.trim().rsplit('.').next())
+        .filter(|base| !base.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns the deployed bytecode without its metadata, and with zeroed library placeholders
+fn normalize_bytecode(object: &BytecodeObject) -> eyre::Result<Vec<u8>> {
+    let code = match object {
+        BytecodeObject::Bytecode(bytes) => bytes.to_vec(),
+        BytecodeObject::Unlinked(code) => {
+            let code =
+                RE_LIBRARY_PLACEHOLDER.replace_all(code.trim_start_matches("0x"), "0".repeat(40));
+            hex::decode(&*code)?
+        }
+    };
+    // the CBOR encoded metadata is followed by its length as two bytes
+    if code.len() >= 2 {
+        let len = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) as usize;
+        if len + 2 <= code.len() {
+            return Ok(code[..code.len() - len - 2].to_vec())
+        }
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_base_contracts() {
+        assert_eq!(base_contracts(" is A, B(1, f(2)), Lib.C "), vec!["A", "B", "C"]);
+        assert!(base_contracts(" ").is_empty());
+    }
+
+    #[test]
+    fn can_flatten_sources() {
+        let sources = vec![
+            (
+                "src/Token.sol".to_string(),
+                r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+import "./Vault.sol";
+
+contract Token {
+    Vault vault;
+}
+"#
+                .to_string(),
+            ),
+            (
+                "src/Vault.sol".to_string(),
+                r#"// SPDX-License-Identifier: GPL-3.0-or-later
+pragma solidity  ^0.8.10;
+pragma abicoder v2;
+import {Token} from "./Token.sol";
+import {
+    Math
+} from "./Math.sol";
+
+contract Base {}
+
+contract Vault is Base, Token {
+    function token() external returns (Token) {}
+}
+"#
+                .to_string(),
+            ),
+            (
+                "src/Math.sol".to_string(),
+                r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+library Math {}
+"#
+                .to_string(),
+            ),
+        ];
+
+        let flattened = flatten_sources(&sources).unwrap();
+        assert_eq!(
+            flattened,
+            r#"// SPDX-License-Identifier: GPL-3.0-or-later AND MIT
+pragma solidity ^0.8.0;
+pragma solidity ^0.8.10;
+pragma abicoder v2;
+
+// src/Token.sol
+
+contract Token {
+    Vault vault;
+}
+
+// src/Vault.sol
+
+contract Base {}
+
+contract Vault is Base, Token {
+    function token() external returns (Token) {}
+}
+
+// src/Math.sol
+
+library Math {}
+"#
+        );
+
+        // the file of a base contract is moved before the file that inherits from it
+        let reversed = sources.iter().rev().cloned().collect::<Vec<_>>();
+        let flattened = flatten_sources(&reversed).unwrap();
+        assert!(
+            flattened.find("contract Token").unwrap() < flattened.find("contract Base").unwrap()
+        );
+    }
+
+    #[test]
+    fn can_flatten_cyclic_imports() {
+        let sources = vec![
+            ("src/B.sol".to_string(), "import \"./A.sol\";\n\ncontract B is A {}\n".to_string()),
+            (
+                "src/A.sol".to_string(),
+                "import \"./B.sol\";\n\ncontract A {\n    B b;\n}\n".to_string(),
+            ),
+        ];
+        let flattened = flatten_sources(&sources).unwrap();
+        assert_eq!(
+            flattened,
+            "\n// src/A.sol\n\ncontract A {\n    B b;\n}\n\n// src/B.sol\n\ncontract B is A {}\n"
+        );
+    }
+
+    #[test]
+    fn can_flatten_aliased_imports() {
+        let sources = vec![
+            (
+                "src/Math.sol".to_string(),
+                "library Math {\n    function max(uint256 a, uint256 b) internal pure returns (uint256) {}\n}\n"
+                    .to_string(),
+            ),
+            ("src/Token.sol".to_string(), "contract Token {}\n".to_string()),
+            (
+                "src/Vault.sol".to_string(),
+                r#"import {Token as BaseToken} from "./Token.sol";
+import "./Math.sol" as M;
+import * as Lib from "./Math.sol";
+
+contract Vault is BaseToken {
+    // BaseToken and M.Math
+    string name = "BaseToken M.Math";
+    BaseToken token;
+
+    function max(uint256 a) external returns (uint256) {
+        return M.Math.max(a, Lib.Math.max(a, 1));
+    }
+}
+"#
+                .to_string(),
+            ),
+        ];
+
+        let flattened = flatten_sources(&sources).unwrap();
+        assert!(flattened.contains(
+            r#"// src/Vault.sol
+
+contract Vault is Token {
+    // BaseToken and M.Math
+    string name = "BaseToken M.Math";
+    Token token;
+
+    function max(uint256 a) external returns (uint256) {
+        return Math.max(a, Math.max(a, 1));
+    }
+}
+"#
+        ));
+    }
+
+    #[test]
+    fn ignores_comments_and_strings() {
+        assert_eq!(
+            mask_comments_and_strings("x = \"a;\\\"b\"; // c\n/* d\n*/ y = 'e';"),
+            "x =        ;     \n    \n   y =    ;"
+        );
+
+        // declarations and imports in comments and strings are kept as they are
+        let sources = vec![
+            (
+                "A.sol".to_string(),
+                "/*\ncontract B {}\nimport \"./B.sol\";\n*/\ncontract A {\n    string s = \"pragma solidity 0.8.0;\";\n}\n"
+                    .to_string(),
+            ),
+            ("B.sol".to_string(), "contract B {}\n".to_string()),
+        ];
+        let flattened = flatten_sources(&sources).unwrap();
+        assert!(flattened.contains("import \"./B.sol\";"));
+        assert!(flattened.contains("string s = \"pragma solidity 0.8.0;\";"));
+        assert!(!flattened.starts_with("pragma"));
+    }
+
+    #[test]
+    fn fails_on_duplicate_contracts() {
+        let sources = vec![
+            ("A.sol".to_string(), "contract A {}".to_string()),
+            ("B.sol".to_string(), "interface A {}".to_string()),
+        ];
+        assert!(flatten_sources(&sources).is_err());
+    }
+}
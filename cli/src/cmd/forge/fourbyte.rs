@@ -7,10 +7,35 @@ use clap::Parser;
 use ethers::prelude::artifacts::output_selection::ContractOutputSelection;
 use foundry_utils::selectors::{import_selectors, SelectorImportData};
 
+#[derive(Debug, Parser)]
+pub struct SelectorsArgs {
+    #[clap(subcommand)]
+    pub sub: SelectorsSubcommands,
+}
+
+#[derive(Debug, Parser)]
+pub enum SelectorsSubcommands {
+    #[clap(
+        visible_alias = "up",
+        about = "Uploads the selectors of a contract, or of all contracts of the project, to https://openchain.xyz."
+    )]
+    Upload(UploadSelectorsArgs),
+}
+
 #[derive(Debug, Clone, Parser)]
 pub struct UploadSelectorsArgs {
-    #[clap(help = "The name of the contract to upload selectors for.")]
-    pub contract: String,
+    #[clap(
+        help = "The name of the contract to upload selectors for.",
+        required_unless_present = "all"
+    )]
+    pub contract: Option<String>,
+
+    #[clap(
+        long,
+        help = "Upload the selectors of all contracts in the sources dir of the project.",
+        conflicts_with = "contract"
+    )]
+    pub all: bool,
 
     #[clap(flatten, next_help_heading = "PROJECT OPTIONS")]
     pub project_paths: ProjectPathsArgs,
@@ -19,7 +44,7 @@ pub struct UploadSelectorsArgs {
 impl UploadSelectorsArgs {
     /// Builds a contract and uploads the ABI to selector database
     pub async fn run(self) -> eyre::Result<()> {
-        let UploadSelectorsArgs { contract, project_paths } = self;
+        let UploadSelectorsArgs { contract, all, project_paths } = self;
 
         let build_args = CoreBuildArgs {
             project_paths: project_paths.clone(),
@@ -32,18 +57,28 @@ impl UploadSelectorsArgs {
 
         let project = build_args.project()?;
         let outcome = compile::suppress_compile(&project)?;
-        let found_artifact = outcome.find_first(&contract);
-        let artifact = found_artifact.ok_or_else(|| {
-            eyre::eyre!("Could not find artifact `{contract}` in the compiled artifacts")
-        })?;
+        let abis = if all {
+            outcome
+                .into_artifacts()
+                .filter(|(id, _)| id.source.starts_with(&project.paths.sources))
+                .filter_map(|(_, artifact)| artifact.abi)
+                .filter(|abi| !abi.abi.functions.is_empty() || !abi.abi.events.is_empty())
+                .collect::<Vec<_>>()
+        } else {
+            let contract = contract.unwrap_or_default();
+            let found_artifact = outcome.find_first(&contract);
+            let artifact = found_artifact.ok_or_else(|| {
+                eyre::eyre!("Could not find artifact `{contract}` in the compiled artifacts")
+            })?;
+            vec![artifact.abi.clone().ok_or(eyre::eyre!("Unable to fetch abi"))?]
+        };
 
-        let import_data = SelectorImportData::Abi(vec![artifact
-            .abi
-            .clone()
-            .ok_or(eyre::eyre!("Unable to fetch abi"))?]);
+        if abis.is_empty() {
+            eyre::bail!("No contracts with functions or events found in the sources of the project")
+        }
 
         // upload abi to selector database
-        import_selectors(import_data).await?.describe();
+        import_selectors(SelectorImportData::Abi(abis)).await?.describe();
 
         Ok(())
     }
@@ -8,13 +8,17 @@ use crate::{
 };
 use clap::Parser;
 use comfy_table::Table;
-use ethers::prelude::{
-    artifacts::output_selection::{
-        BytecodeOutputSelection, ContractOutputSelection, DeployedBytecodeOutputSelection,
-        EvmOutputSelection, EwasmOutputSelection,
+use ethers::{
+    prelude::{
+        artifacts::output_selection::{
+            BytecodeOutputSelection, ContractOutputSelection, DeployedBytecodeOutputSelection,
+            EvmOutputSelection, EwasmOutputSelection,
+        },
+        info::ContractInfo,
     },
-    info::ContractInfo,
+    types::H256,
 };
+use forge::storage_layout::compute_storage_slot;
 use serde_json::{to_value, Value};
 use std::{fmt, str::FromStr};
 
@@ -32,6 +36,13 @@ pub struct InspectArgs {
     #[clap(long, help = "Pretty print the selected field, if supported.")]
     pub pretty: bool,
 
+    #[clap(
+        long,
+        help = "Compute the storage slot of an expression like `balances[0xabc]` or `owners[1].amount` from the storage layout.",
+        value_name = "EXPRESSION"
+    )]
+    pub slot: Option<String>,
+
     /// All build arguments are supported
     #[clap(flatten)]
     build: build::CoreBuildArgs,
@@ -40,7 +51,11 @@ pub struct InspectArgs {
 impl Cmd for InspectArgs {
     type Output = ();
     fn run(self) -> eyre::Result<Self::Output> {
-        let InspectArgs { mut contract, field, build, pretty } = self;
+        let InspectArgs { mut contract, field, build, pretty, slot } = self;
+
+        if slot.is_some() && field != ContractArtifactFields::StorageLayout {
+            eyre::bail!("`--slot` can only be used with the storage layout field")
+        }
 
         // Map field to ContractOutputSelection
         let mut cos = build.compiler.extra_output;
@@ -120,7 +135,15 @@ impl Cmd for InspectArgs {
                 println!("{}", serde_json::to_string_pretty(&to_value(&artifact.gas_estimates)?)?);
             }
             ContractArtifactFields::StorageLayout => {
-                if pretty {
+                if let Some(expression) = slot {
+                    let storage_layout = artifact.storage_layout.as_ref().ok_or_else(|| {
+                        eyre::eyre!("Could not find the storage layout of `{contract}`")
+                    })?;
+                    let slot = compute_storage_slot(storage_layout, &expression)?;
+                    println!("Slot: {:?}", H256::from_uint(&slot.slot));
+                    println!("Offset: {}", slot.offset);
+                    println!("Type: {}", slot.type_label);
+                } else if pretty {
                     if let Some(storage_layout) = &artifact.storage_layout {
                         let mut table = Table::new();
                         table.set_header(vec![
@@ -43,18 +43,24 @@ pub mod cache;
 pub mod config;
 pub mod coverage;
 pub mod create;
+pub mod create2;
+pub mod daemon;
 pub mod debug;
+pub mod fixture;
 pub mod flatten;
 pub mod fmt;
 pub mod fourbyte;
 pub mod init;
 pub mod inspect;
 pub mod install;
+pub mod mutate;
 pub mod remappings;
 pub mod remove;
 pub mod script;
 pub mod snapshot;
+pub mod storage_diff;
 pub mod test;
 pub mod tree;
+pub mod upgrade_check;
 pub mod verify;
 pub mod watch;
@@ -0,0 +1,434 @@
+//! Mutate command
+use crate::{
+    cmd::{
+        forge::{build::CoreBuildArgs, test::Filter},
+        Cmd,
+    },
+    compile,
+    utils::{self, FoundryPathExt},
+};
+use clap::Parser;
+use ethers::solc::{utils::source_files, Graph, ProjectPathsConfig};
+use forge::{
+    executor::{inspector::CheatsConfig, opts::EvmOpts},
+    MultiContractRunnerBuilder, TestFilter, TestOptions,
+};
+use foundry_common::{evm::EvmArgs, fs};
+use foundry_config::{figment::Figment, Config};
+use std::{
+    collections::HashSet,
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use tracing::trace;
+use yansi::Paint;
+
+// Loads project's figment and merges the build cli arguments into it
+foundry_config::impl_figment_convert!(MutateArgs, opts, evm_opts);
+
+/// CLI arguments for `forge mutate`.
+#[derive(Debug, Clone, Parser)]
+pub struct MutateArgs {
+    #[clap(flatten)]
+    filter: Filter,
+
+    /// Only mutate source files matching the specified glob pattern.
+    #[clap(long, value_name = "GLOB")]
+    match_source: Option<globset::Glob>,
+
+    #[clap(flatten, next_help_heading = "EVM OPTIONS")]
+    evm_opts: EvmArgs,
+
+    #[clap(flatten, next_help_heading = "BUILD OPTIONS")]
+    opts: CoreBuildArgs,
+}
+
+impl Cmd for MutateArgs {
+    type Output = ();
+
+    fn run(self) -> eyre::Result<Self::Output> {
+        let figment: Figment = (&self).into();
+        let evm_opts: EvmOpts = figment.extract()?;
+        let config = Config::from_provider(figment).sanitized();
+        let filter = self.filter.with_merged_config(&config);
+        let paths = config.project_paths();
+
+        // the test suite must pass before we can measure which mutants it detects
+        let all_tests = MutantFilter { filter: &filter, test_files: None };
+        if !run_tests(&config, &evm_opts, &all_tests)? {
+            eyre::bail!("The test suite must pass before running mutation testing")
+        }
+
+        let matcher = self.match_source.as_ref().map(|glob| glob.compile_matcher());
+        let sources: Vec<_> = source_files(&config.src)
+            .into_iter()
+            .filter(|path| path.is_sol() && !path.is_sol_test())
+            .filter(|path| matcher.as_ref().map(|m| m.is_match(path)).unwrap_or(true))
+            .collect();
+
+        let mut results = Vec::new();
+        for path in sources {
+            let source = fs::read_to_string(&path)?;
+            let test_files = affected_test_files(&paths, &path)?;
+            if test_files.is_empty() {
+                trace!(target: "forge::mutate", ?path, "no tests depend on source file");
+                continue
+            }
+
+            for mutant in mutants(&path, &source) {
+                let outcome = {
+                    // restores the source file even if compiling or testing the mutant fails
+                    let _restore = RestoreOnDrop { path: &path, source: &source };
+                    fs::write(&path, mutant.apply(&source))?;
+
+                    let filter = MutantFilter { filter: &filter, test_files: Some(&test_files) };
+                    match run_tests(&config, &evm_opts, &filter) {
+                        Ok(true) => MutantOutcome::Survived,
+                        Ok(false) => MutantOutcome::Killed,
+                        Err(_) => MutantOutcome::Invalid,
+                    }
+                };
+                println!("{} {}", outcome, mutant);
+                results.push((mutant, outcome));
+            }
+        }
+
+        // make sure the artifacts match the original sources again
+        compile::suppress_compile(&config.project()?)?;
+
+        let valid = results.iter().filter(|(_, o)| *o != MutantOutcome::Invalid).count();
+        let survived: Vec<_> =
+            results.iter().filter(|(_, o)| *o == MutantOutcome::Survived).collect();
+        println!();
+        if !survived.is_empty() {
+            println!("Surviving mutants:");
+            for (mutant, _) in &survived {
+                println!("  {}", mutant);
+            }
+            println!();
+        }
+        let killed = valid - survived.len();
+        let score = if valid == 0 { 100.0 } else { 100.0 * killed as f64 / valid as f64 };
+        println!(
+            "Mutation score: {:.2}%. {} killed; {} survived; {} invalid",
+            score,
+            Paint::green(killed),
+            Paint::red(survived.len()),
+            results.len() - valid
+        );
+        Ok(())
+    }
+}
+
+/// Compiles the project and runs the tests matching the `filter`, returning whether all tests
+/// passed
+fn run_tests(config: &Config, evm_opts: &EvmOpts, filter: &impl TestFilter) -> eyre::Result<bool> {
+    let project = config.project()?;
+    let output = compile::suppress_compile(&project)?;
+
+    let fuzzer = proptest::test_runner::TestRunner::new(proptest::test_runner::Config {
+        failure_persistence: None,
+        cases: config.fuzz_runs,
+        max_local_rejects: config.fuzz_max_local_rejects,
+        max_global_rejects: config.fuzz_max_global_rejects,
+        ..Default::default()
+    });
+    let env = evm_opts.evm_env_blocking();
+    let mut runner = MultiContractRunnerBuilder::default()
+        .fuzzer(fuzzer)
+        .initial_balance(evm_opts.initial_balance)
+        .evm_spec(utils::evm_spec(&config.evm_version))
+        .sender(evm_opts.sender)
+        .with_fork(evm_opts.get_fork(config, env.clone()))
+        .with_cheats_config(CheatsConfig::new(config, evm_opts))
+        .with_test_options(TestOptions::from(config))
+        .build(project.paths.root, output, env, evm_opts.clone())?;
+
+    let results = runner.test(filter, None, true)?;
+    Ok(results.values().all(|suite| suite.test_results.values().all(|result| result.success)))
+}
+
+/// Returns the test files, relative to the project root, that import the given source file
+/// directly or transitively
+fn affected_test_files(paths: &ProjectPathsConfig, source: &Path) -> eyre::Result<HashSet<String>> {
+    let graph = Graph::resolve(paths)?;
+    Ok(graph
+        .files()
+        .iter()
+        .filter(|(path, _)| path.is_sol_test())
+        .filter(|(_, index)| {
+            graph.all_imported_nodes(**index).any(|import| graph.node(import).unpack().0 == source)
+        })
+        .map(|(path, _)| path.strip_prefix(&paths.root).unwrap_or(path).to_string_lossy().into())
+        .collect())
+}
+
+/// Restores the original content of a mutated source file when dropped
+struct RestoreOnDrop<'a> {
+    path: &'a Path,
+    source: &'a str,
+}
+
+impl<'a> Drop for RestoreOnDrop<'a> {
+    fn drop(&mut self) {
+        if let Err(err) = fs::write(self.path, self.source) {
+            eprintln!("Failed to restore {}: {}", self.path.display(), err);
+        }
+    }
+}
+
+/// A [TestFilter] that only matches the tests of the given test files
+struct MutantFilter<'a> {
+    filter: &'a Filter,
+    test_files: Option<&'a HashSet<String>>,
+}
+
+impl<'a> TestFilter for MutantFilter<'a> {
+    fn matches_test(&self, test_name: impl AsRef<str>) -> bool {
+        self.filter.matches_test(test_name)
+    }
+
+    fn matches_contract(&self, contract_name: impl AsRef<str>) -> bool {
+        self.filter.matches_contract(contract_name)
+    }
+
+    fn matches_path(&self, path: impl AsRef<str>) -> bool {
+        let path = path.as_ref();
+        self.filter.matches_path(path) &&
+            self.test_files.map(|files| files.contains(path)).unwrap_or(true)
+    }
+}
+
+/// The result of running the tests against a mutant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutantOutcome {
+    /// At least one test failed
+    Killed,
+    /// All tests passed
+    Survived,
+    /// The mutant does not compile
+    Invalid,
+}
+
+impl fmt::Display for MutantOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MutantOutcome::Killed => write!(f, "{}", Paint::green("[KILLED]  ")),
+            MutantOutcome::Survived => write!(f, "{}", Paint::red("[SURVIVED]")),
+            MutantOutcome::Invalid => write!(f, "{}", Paint::yellow("[INVALID] ")),
+        }
+    }
+}
+
+/// A single source-level mutation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mutant {
+    /// The mutated source file
+    pub path: PathBuf,
+    /// The line of the mutation, starting at 1
+    pub line: usize,
+    /// The byte range of the source that is replaced
+    pub span: Range<usize>,
+    /// The replacement for the source in `span`
+    pub replacement: String,
+    /// The original source in `span`
+    pub original: String,
+}
+
+impl Mutant {
+    /// Returns the mutated source
+    pub fn apply(&self, source: &str) -> String {
+        format!("{}{}{}", &source[..self.span.start], self.replacement, &source[self.span.end..])
+    }
+}
+
+impl fmt::Display for Mutant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: `{}` -> `{}`",
+            self.path.display(),
+            self.line,
+            self.original,
+            self.replacement
+        )
+    }
+}
+
+/// Returns the replacement of a binary operator
+fn swap_operator(op: &str) -> Option<&'static str> {
+    let swapped = match op {
+        "==" => "!=",
+        "!=" => "==",
+        "<" => ">=",
+        ">" => "<=",
+        "<=" => ">",
+        ">=" => "<",
+        "&&" => "||",
+        "||" => "&&",
+        "+" => "-",
+        "-" => "+",
+        "*" => "/",
+        "/" => "*",
+        "%" => "*",
+        _ => return None,
+    };
+    Some(swapped)
+}
+
+/// Generates all mutants of the given source.
+///
+/// The following mutations are applied:
+///   - binary operators are swapped, e.g. `+` with `-` or `<` with `>=`
+///   - the conditions of `if` and `while` statements are negated
+///   - boolean literals are flipped and decimal literals are incremented
+///
+/// Comments, string literals, pragmas and imports are never mutated.
+pub fn mutants(path: &Path, source: &str) -> Vec<Mutant> {
+    let bytes = source.as_bytes();
+    let mut mutants = Vec::new();
+    let mut push = |span: Range<usize>, replacement: String| {
+        let line = source[..span.start].matches('\n').count() + 1;
+        let original = source[span.clone()].to_string();
+        mutants.push(Mutant { path: path.to_path_buf(), line, span, replacement, original });
+    };
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if source[i..].starts_with("//") {
+            i = source[i..].find('\n').map(|n| i + n).unwrap_or(bytes.len());
+        } else if source[i..].starts_with("/*") {
+            i = source[i + 2..].find("*/").map(|n| i + n + 4).unwrap_or(bytes.len());
+        } else if c == b'"' || c == b'\'' {
+            // skip string literals, including escaped quotes
+            let mut j = i + 1;
+            while j < bytes.len() && bytes[j] != c {
+                j += if bytes[j] == b'\\' { 2 } else { 1 };
+            }
+            i = j + 1;
+        } else if c.is_ascii_alphabetic() || c == b'_' || c == b'$' {
+            let end = i + source[i..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(bytes.len() - i);
+            match &source[i..end] {
+                "pragma" | "import" => {
+                    i = source[i..].find(';').map(|n| i + n + 1).unwrap_or(bytes.len());
+                    continue
+                }
+                "true" => push(i..end, "false".to_string()),
+                "false" => push(i..end, "true".to_string()),
+                "if" | "while" => {
+                    if let Some(cond) = condition_span(source, end) {
+                        push(cond.clone(), format!("(!{})", &source[cond]));
+                    }
+                }
+                _ => {}
+            }
+            i = end;
+        } else if c.is_ascii_digit() {
+            let end = i + source[i..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(bytes.len() - i);
+            if let Ok(value) = source[i..end].parse::<u128>() {
+                if let Some(incremented) = value.checked_add(1) {
+                    push(i..end, incremented.to_string());
+                }
+            }
+            i = end;
+        } else if b"=!<>&|+-*/%^~".contains(&c) {
+            let end = i + source[i..]
+                .find(|c: char| !"=!<>&|+-*/%^~".contains(c))
+                .unwrap_or(bytes.len() - i);
+            if let Some(swapped) = swap_operator(&source[i..end]) {
+                push(i..end, swapped.to_string());
+            }
+            i = end;
+        } else {
+            // skip the whole character, which is not necessarily ASCII
+            i += source[i..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    mutants
+}
+
+/// Returns the span of the parenthesized condition following the `if` or `while` keyword that
+/// ends at `start`
+fn condition_span(source: &str, start: usize) -> Option<Range<usize>> {
+    let open = start + source[start..].find(|c: char| !c.is_whitespace())?;
+    if !source[open..].starts_with('(') {
+        return None
+    }
+    let mut depth = 0usize;
+    for (offset, c) in source[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open..open + offset + 1)
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacements(source: &str) -> Vec<(String, String)> {
+        mutants(Path::new("A.sol"), source)
+            .into_iter()
+            .map(|mutant| (mutant.original, mutant.replacement))
+            .collect()
+    }
+
+    #[test]
+    fn can_mutate_operators_and_literals() {
+        let source = r#"
+pragma solidity >=0.8.0;
+import "./B.sol";
+
+contract A {
+    // a + b
+    /* a < b */
+    function f(uint256 a, bool b) public returns (uint256) {
+        string memory s = "a - b";
+        if (a >= 10 && b) {
+            return a + 1;
+        }
+        return a;
+    }
+}
+"#;
+        assert_eq!(
+            replacements(source),
+            vec![
+                ("(a >= 10 && b)".to_string(), "(!(a >= 10 && b))".to_string()),
+                (">=".to_string(), "<".to_string()),
+                ("10".to_string(), "11".to_string()),
+                ("&&".to_string(), "||".to_string()),
+                ("+".to_string(), "-".to_string()),
+                ("1".to_string(), "2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_mutate_non_ascii_source() {
+        let source = "\u{feff}uint256 x = a\u{a0}+ b; // ≥ 1\nstring s = unicode\"\\é ≥ 1\";";
+        assert_eq!(replacements(source), vec![("+".to_string(), "-".to_string())]);
+    }
+
+    #[test]
+    fn can_apply_mutant() {
+        let source = "uint256 x = a + b;";
+        let mutant = mutants(Path::new("A.sol"), source).remove(0);
+        assert_eq!(mutant.line, 1);
+        assert_eq!(mutant.apply(source), "uint256 x = a - b;");
+    }
+}
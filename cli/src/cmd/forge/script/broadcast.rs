@@ -1,5 +1,10 @@
 use super::{
-    sequence::{ScriptSequence, TransactionWithMetadata},
+    export,
+    l1_fee::L1Fees,
+    nonce::NonceAllocator,
+    plan::ScriptPlan,
+    schedule,
+    sequence::{verify_initcode, ScriptSequence, TransactionWithMetadata},
     *,
 };
 use crate::{
@@ -7,28 +12,35 @@ use crate::{
     init_progress,
     opts::WalletType,
     update_progress,
-    utils::get_http_provider,
+    utils::{cancel_on_ctrl_c, get_provider},
 };
 use ethers::{
-    prelude::{Http, Provider, RetryClient, Signer, SignerMiddleware, TxHash},
+    prelude::{Signer, SignerMiddleware, TxHash},
     providers::Middleware,
     types::transaction::eip2718::TypedTransaction,
     utils::format_units,
 };
 use eyre::ContextCompat;
-use foundry_config::Chain;
+use foundry_common::provider::{ProvidersManager, RetryProvider};
+use foundry_config::{Chain, ChainProfile};
 use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::{cmp::min, fmt, sync::Arc};
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{atomic::Ordering, Arc},
+};
 
 impl ScriptArgs {
     /// Sends the transactions which haven't been broadcasted yet.
+    ///
+    /// The initcode of the deployments is checked against the compiled `contracts`.
     pub async fn send_transactions(
         &self,
         deployment_sequence: &mut ScriptSequence,
-        fork_url: &str,
+        provider: Arc<RetryProvider>,
+        contracts: &BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
     ) -> eyre::Result<()> {
-        let provider = get_http_provider(fork_url, true);
         let already_broadcasted = deployment_sequence.receipts.len();
 
         if already_broadcasted < deployment_sequence.transactions.len() {
@@ -42,23 +54,13 @@ impl ScriptArgs {
             let local_wallets = self.wallets.find_all(provider.clone(), required_addresses).await?;
             let chain = local_wallets.values().last().wrap_err("Error accessing local wallet when trying to send onchain transaction, did you set a private key, mnemonic or keystore?")?.chain_id();
 
-            // We only wait for a transaction receipt before sending the next transaction, if there
-            // is more than one signer. There would be no way of assuring their order
-            // otherwise. Or if the chain does not support batched transactions (eg. Arbitrum).
-            let sequential_broadcast =
-                local_wallets.len() != 1 || self.slow || !has_batch_support(chain);
+            // We only wait for a transaction receipt before sending the next transaction if the
+            // chain does not support batched transactions (eg. Arbitrum), or if asked to.
+            // Otherwise, the transactions of different signers are ordered by their batches.
+            let sequential_broadcast = self.slow || !has_batch_support(chain);
 
             // Make a one-time gas price estimation
-            let (gas_price, eip1559_fees) = {
-                match deployment_sequence.transactions.front().unwrap().typed_tx() {
-                    TypedTransaction::Legacy(_) | TypedTransaction::Eip2930(_) => {
-                        (provider.get_gas_price().await.ok(), None)
-                    }
-                    TypedTransaction::Eip1559(_) => {
-                        (None, provider.estimate_eip1559_fees(None).await.ok())
-                    }
-                }
-            };
+            let fees = estimate_fees(&provider, deployment_sequence).await;
 
             // Iterate through transactions, matching the `from` field with the associated
             // wallet. Then send the transaction. Panics if we find a unknown `from`
@@ -71,48 +73,78 @@ impl ScriptArgs {
                     let signer = local_wallets.get(&from).expect("`find_all` returned incomplete.");
 
                     let mut tx = tx.clone();
-
-                    tx.set_chain_id(chain);
-
-                    if let Some(gas_price) = self.with_gas_price {
-                        tx.set_gas_price(gas_price);
-                    } else {
-                        // fill gas price
-                        match tx {
-                            TypedTransaction::Eip2930(_) | TypedTransaction::Legacy(_) => {
-                                tx.set_gas_price(gas_price.expect("Could not get gas_price."));
-                            }
-                            TypedTransaction::Eip1559(ref mut inner) => {
-                                let eip1559_fees =
-                                    eip1559_fees.expect("Could not get eip1559 fee estimation.");
-                                inner.max_fee_per_gas = Some(eip1559_fees.0);
-                                inner.max_priority_fee_per_gas = Some(eip1559_fees.1);
-                            }
-                        }
-                    }
+                    self.fill_fees(&mut tx, chain, fees);
 
                     (tx, signer)
                 })
                 .collect::<Vec<_>>();
 
+            // Make sure we sign the exact initcode that was simulated
+            verify_initcode(
+                deployment_sequence
+                    .transactions
+                    .iter()
+                    .skip(already_broadcasted)
+                    .zip(sequence.iter().map(|(tx, _)| tx)),
+                contracts,
+            )?;
+
+            // Interrupting the broadcast stops it once the in-flight transaction (or batch of
+            // transactions) is confirmed, so the sequence can be resumed later
+            let cancelled = cancel_on_ctrl_c();
+
             let pb = init_progress!(deployment_sequence.transactions, "txes");
 
-            // We send transactions and wait for receipts in batches of 100, since some networks
-            // cannot handle more than that.
+            // We send transactions and wait for receipts in batches of at most 100, since some
+            // networks cannot handle more than that. A transaction that depends on a transaction
+            // of another signer is sent in a later batch.
             let batch_size = 100;
+            let batches = schedule::batches(
+                &deployment_sequence
+                    .transactions
+                    .iter()
+                    .skip(already_broadcasted)
+                    .collect::<Vec<_>>(),
+                batch_size,
+            );
             let mut index = 0;
 
-            for (batch_number, batch) in sequence.chunks(batch_size).map(|f| f.to_vec()).enumerate()
-            {
+            let nonces = NonceAllocator::default();
+
+            for batch in batches {
+                if cancelled.load(Ordering::Relaxed) {
+                    break
+                }
                 let mut pending_transactions = vec![];
 
                 println!(
                     "##\nSending transactions [{} - {}].",
-                    batch_number * batch_size,
-                    batch_number * batch_size + min(batch_size, batch.len()) - 1
+                    batch.start + already_broadcasted,
+                    batch.end - 1 + already_broadcasted
                 );
-                for (tx, signer) in batch.into_iter() {
-                    let tx_hash = self.send_transaction(tx, signer, sequential_broadcast, fork_url);
+
+                // The transactions of a batch are sent without waiting for each other, so the
+                // nonces of their senders are synced once per batch and allocated as they are sent
+                if !sequential_broadcast {
+                    let senders = sequence[batch.clone()]
+                        .iter()
+                        .map(|(tx, _)| *tx.from().expect("no sender"))
+                        .collect::<HashSet<_>>();
+                    for sender in senders {
+                        sync_nonce(&provider, &nonces, sender).await?;
+                    }
+                }
+
+                for (mut tx, signer) in sequence[batch].iter().cloned() {
+                    if cancelled.load(Ordering::Relaxed) {
+                        break
+                    }
+                    if sequential_broadcast {
+                        sync_nonce(&provider, &nonces, *tx.from().expect("no sender")).await?;
+                    }
+                    allocate_nonce(&nonces, &mut tx)?;
+
+                    let tx_hash = self.send_transaction(tx, signer);
 
                     if sequential_broadcast {
                         let tx_hash = tx_hash.await?;
@@ -154,6 +186,15 @@ impl ScriptArgs {
                 // Checkpoint save
                 deployment_sequence.save()?;
             }
+
+            if cancelled.load(Ordering::Relaxed) {
+                eyre::bail!(
+                    "Broadcast cancelled after {} of {} transactions. Transaction receipts written to {:?}, resume with --resume",
+                    deployment_sequence.receipts.len(),
+                    deployment_sequence.transactions.len(),
+                    deployment_sequence.path
+                )
+            }
         }
 
         println!("\n\n==========================");
@@ -164,33 +205,72 @@ impl ScriptArgs {
         Ok(())
     }
 
-    pub async fn send_transaction(
+    /// Writes the transactions which haven't been broadcasted yet unsigned, with their chain id
+    /// and fees filled, so they can be signed and sent by external infrastructure.
+    pub async fn save_unsigned_transactions(
         &self,
-        tx: TypedTransaction,
-        signer: &WalletType,
-        sequential_broadcast: bool,
-        fork_url: &str,
-    ) -> Result<TxHash, BroadcastError> {
-        let from = tx.from().expect("no sender");
+        deployment_sequence: &ScriptSequence,
+        provider: Arc<RetryProvider>,
+        chain: u64,
+    ) -> eyre::Result<()> {
+        let fees = estimate_fees(&provider, deployment_sequence).await;
 
-        if sequential_broadcast {
-            let nonce = foundry_utils::next_nonce(*from, fork_url, None).await.map_err(|_| {
-                BroadcastError::Simple("Not able to query the EOA nonce.".to_string())
-            })?;
+        let transactions = deployment_sequence
+            .typed_transactions()
+            .into_iter()
+            .skip(deployment_sequence.receipts.len())
+            .map(|tx| {
+                let mut tx = tx.clone();
+                self.fill_fees(&mut tx, chain, fees);
+                tx
+            })
+            .collect::<Vec<_>>();
 
-            let tx_nonce = tx.nonce().expect("no nonce");
+        let path = deployment_sequence.save_unsigned(&transactions)?;
 
-            if nonce != *tx_nonce {
-                return Err(BroadcastError::Simple(
-                    "EOA nonce changed unexpectedly while sending transactions.".to_string(),
-                ))
+        println!("\n\n==========================");
+        println!(
+            "\n{} UNSIGNED TRANSACTIONS written to {:?}. They need to be signed and sent in order.",
+            transactions.len(),
+            path
+        );
+        Ok(())
+    }
+
+    /// Sets the chain id and the gas price, or EIP-1559 fees, of the transaction.
+    fn fill_fees(&self, tx: &mut TypedTransaction, chain: u64, fees: EstimatedFees) {
+        let (gas_price, eip1559_fees) = fees;
+        tx.set_chain_id(chain);
+
+        if let Some(gas_price) = self.with_gas_price {
+            tx.set_gas_price(gas_price);
+        } else {
+            // fill gas price
+            match tx {
+                TypedTransaction::Eip2930(_) | TypedTransaction::Legacy(_) => {
+                    tx.set_gas_price(gas_price.expect("Could not get gas_price."));
+                }
+                TypedTransaction::Eip1559(ref mut inner) => {
+                    let eip1559_fees = eip1559_fees.expect("Could not get eip1559 fee estimation.");
+                    inner.max_fee_per_gas = Some(eip1559_fees.0);
+                    inner.max_priority_fee_per_gas = Some(eip1559_fees.1);
+                }
             }
         }
+    }
 
+    /// Signs the transaction, whose nonce was allocated, and sends it.
+    pub async fn send_transaction(
+        &self,
+        tx: TypedTransaction,
+        signer: &WalletType,
+    ) -> Result<TxHash, BroadcastError> {
         match signer {
             WalletType::Local(signer) => broadcast(signer, tx).await,
             WalletType::Ledger(signer) => broadcast(signer, tx).await,
             WalletType::Trezor(signer) => broadcast(signer, tx).await,
+            WalletType::Remote(signer) => broadcast(signer, tx).await,
+            WalletType::Aws(signer) => broadcast(signer, tx).await,
         }
     }
 
@@ -219,13 +299,20 @@ impl ScriptArgs {
 
                 let fork_url = self.evm_opts.fork_url.as_ref().unwrap().clone();
 
-                let provider = get_http_provider(&fork_url, false);
+                let providers = ProvidersManager::new(script_config.config.rpc_endpoints.clone());
+                let provider = get_provider(&providers, &fork_url, false)?;
                 let chain = provider.get_chainid().await?.as_u64();
 
                 let returns = self.get_returns(script_config, &result.returned)?;
 
                 let mut deployment_sequence = ScriptSequence::new(
-                    self.handle_chain_requirements(gas_filled_txs, provider, chain).await?,
+                    self.handle_chain_requirements(
+                        gas_filled_txs,
+                        provider,
+                        chain,
+                        script_config.config.chain_profile,
+                    )
+                    .await?,
                     returns,
                     &self.sig,
                     target,
@@ -235,8 +322,22 @@ impl ScriptArgs {
 
                 deployment_sequence.add_libraries(libraries);
 
-                if self.broadcast {
-                    self.send_transactions(&mut deployment_sequence, &fork_url).await?;
+                for format in &self.export {
+                    let path = export::export(&deployment_sequence, *format, chain)?;
+                    println!("\nTransactions exported for {:?} to {:?}", format, path);
+                }
+
+                if self.unsigned {
+                    let provider = get_provider(&providers, &fork_url, true)?;
+                    self.save_unsigned_transactions(&deployment_sequence, provider, chain).await?;
+                } else if self.broadcast {
+                    let provider = get_provider(&providers, &fork_url, true)?;
+                    self.send_transactions(
+                        &mut deployment_sequence,
+                        provider,
+                        &verify.known_contracts,
+                    )
+                    .await?;
                     if self.verify {
                         deployment_sequence.verify_contracts(verify, chain).await?;
                     }
@@ -254,25 +355,36 @@ impl ScriptArgs {
 
     /// Modify each transaction according to the specific chain requirements (transaction type
     /// and/or gas calculations).
+    ///
+    /// The gas is estimated like on the chain emulated by the chain `profile`, if any.
     async fn handle_chain_requirements(
         &self,
         txes: VecDeque<TransactionWithMetadata>,
-        provider: Arc<Provider<RetryClient<Http>>>,
+        provider: Arc<RetryProvider>,
         chain: u64,
+        profile: Option<ChainProfile>,
     ) -> eyre::Result<VecDeque<TransactionWithMetadata>> {
         let mut is_legacy = self.legacy;
         if let Chain::Named(chain) = Chain::from(chain) {
             is_legacy |= chain.is_legacy();
         };
 
+        if is_legacy && self.access_list {
+            println!(
+                "\nAccess lists are not supported by legacy transactions and won't be attached."
+            );
+        }
+
+        let gas_chain = profile.map_or(chain, |profile| profile.chain().id());
+
         let mut new_txes = VecDeque::new();
         let mut total_gas = U256::zero();
         for mut tx in txes.into_iter() {
-            tx.change_type(is_legacy);
+            tx.change_type(is_legacy, self.access_list);
 
             let typed_tx = tx.typed_tx_mut();
 
-            if has_different_gas_calc(chain) {
+            if has_different_gas_calc(gas_chain) {
                 typed_tx.set_gas(provider.estimate_gas(typed_tx).await?);
             }
 
@@ -294,14 +406,50 @@ impl ScriptArgs {
             }
         };
 
-        println!("\n==========================");
-        println!("\nEstimated total gas used for script: {}", total_gas);
-        println!(
-            "\nEstimated amount required: {} ETH",
-            format_units(total_gas.saturating_mul(per_gas), 18)
+        // rollups charge for posting the transaction data to L1. The estimate is only
+        // informational, so the broadcast goes on without it if it fails
+        let l1_fees = match L1Fees::estimate(&provider, chain, profile, &new_txes).await {
+            Ok(l1_fees) => l1_fees,
+            Err(err) => {
+                eprintln!(
+                    "{} Could not estimate the L1 fees of the transactions: {err}",
+                    Paint::yellow("Warning:").bold()
+                );
+                None
+            }
+        };
+
+        if self.plan {
+            println!(
+                "\n{}",
+                ScriptPlan {
+                    chain,
+                    transactions: &new_txes,
+                    gas_price: per_gas,
+                    l1_fees: l1_fees.as_ref()
+                }
+            );
+        }
+
+        let format_eth = |wei: U256| {
+            format_units(wei, 18)
                 .unwrap_or_else(|_| "[Could not calculate]".to_string())
                 .trim_end_matches('0')
-        );
+                .to_string()
+        };
+        println!("\n==========================");
+        println!("\nEstimated total gas used for script: {}", total_gas);
+        let mut required = total_gas.saturating_mul(per_gas);
+        if let Some(ref l1_fees) = l1_fees {
+            let included = if l1_fees.model.is_included_in_gas() {
+                "included in the gas estimate"
+            } else {
+                "charged in addition to the gas"
+            };
+            println!("\nEstimated L1 data fee: {} ETH ({})", format_eth(l1_fees.total()), included);
+            required = required.saturating_add(l1_fees.additional_total());
+        }
+        println!("\nEstimated amount required: {} ETH", format_eth(required));
         println!("\n==========================");
         Ok(new_txes)
     }
@@ -324,6 +472,37 @@ impl fmt::Display for BroadcastError {
     }
 }
 
+/// Syncs the next nonce of `sender` with its nonce on chain.
+async fn sync_nonce(
+    provider: &RetryProvider,
+    nonces: &NonceAllocator,
+    sender: Address,
+) -> Result<(), BroadcastError> {
+    let nonce = provider
+        .get_transaction_count(sender, None)
+        .await
+        .map_err(|_| BroadcastError::Simple("Not able to query the EOA nonce.".to_string()))?;
+    nonces.sync(sender, nonce);
+    Ok(())
+}
+
+/// Allocates the next nonce of the sender of the transaction, which must be the nonce it was
+/// simulated with, i.e. no other transactions were sent by the same keys since the simulation.
+fn allocate_nonce(
+    nonces: &NonceAllocator,
+    tx: &mut TypedTransaction,
+) -> Result<(), BroadcastError> {
+    let from = *tx.from().expect("no sender");
+    let nonce = nonces.allocate(from).expect("the nonce of the sender was not synced");
+    if tx.nonce().map_or(false, |simulated| *simulated != nonce) {
+        return Err(BroadcastError::Simple(
+            "EOA nonce changed unexpectedly while sending transactions.".to_string(),
+        ))
+    }
+    tx.set_nonce(nonce);
+    Ok(())
+}
+
 /// Uses the signer to submit a transaction to the network. If it fails, it tries to retrieve the
 /// transaction hash that can be used on a later run with `--resume`.
 async fn broadcast<T, U>(
@@ -370,3 +549,20 @@ where
 
     Ok(pending.tx_hash())
 }
+
+/// The gas price for legacy transactions and the max fee and priority fee for EIP-1559
+/// transactions, if they could be estimated
+type EstimatedFees = (Option<U256>, Option<(U256, U256)>);
+
+/// Makes a one-time estimation of the fees of the transactions, based on the type of the first one
+async fn estimate_fees(
+    provider: &RetryProvider,
+    deployment_sequence: &ScriptSequence,
+) -> EstimatedFees {
+    match deployment_sequence.transactions.front().unwrap().typed_tx() {
+        TypedTransaction::Legacy(_) | TypedTransaction::Eip2930(_) => {
+            (provider.get_gas_price().await.ok(), None)
+        }
+        TypedTransaction::Eip1559(_) => (None, provider.estimate_eip1559_fees(None).await.ok()),
+    }
+}
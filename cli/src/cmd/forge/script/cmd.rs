@@ -1,10 +1,11 @@
-use crate::{cmd::unwrap_contracts, utils::get_http_provider};
+use crate::{cmd::unwrap_contracts, utils::get_provider};
 
 use ethers::{
     prelude::{artifacts::CompactContractBytecode, ArtifactId, Middleware, Signer},
     types::{transaction::eip2718::TypedTransaction, U256},
 };
 use forge::executor::opts::EvmOpts;
+use foundry_common::provider::ProvidersManager;
 use foundry_config::{figment::Figment, Config};
 use tracing::trace;
 
@@ -24,6 +25,10 @@ impl ScriptArgs {
             called_function: None,
         };
 
+        // `--fork-url` can be an alias of the `rpc_endpoints`
+        script_config.evm_opts.resolve_fork_url(&script_config.config)?;
+        self.evm_opts.fork_url = script_config.evm_opts.fork_url.clone();
+
         self.maybe_load_private_key(&mut script_config)?;
         self.maybe_load_etherscan_api_key(&mut script_config)?;
 
@@ -46,7 +51,8 @@ impl ScriptArgs {
                 .expect("You must provide an RPC URL (see --fork-url).")
                 .clone();
 
-            let provider = get_http_provider(&fork_url, true);
+            let providers = ProvidersManager::new(script_config.config.rpc_endpoints.clone());
+            let provider = get_provider(&providers, &fork_url, true)?;
             let chain = provider.get_chainid().await?.as_u64();
 
             let mut deployment_sequence = ScriptSequence::load(
@@ -56,26 +62,25 @@ impl ScriptArgs {
                 chain,
             )?;
 
-            receipts::wait_for_pending(provider, &mut deployment_sequence).await?;
+            receipts::wait_for_pending(provider.clone(), &mut deployment_sequence).await?;
+
+            // We might have predeployed libraries from the broadcasting, so we need to relink the
+            // contracts with them, since their mapping is not included in the solc cache files.
+            let BuildOutput { highlevel_known_contracts, .. } = self.link(
+                build_output.project,
+                build_output.known_contracts,
+                Libraries::parse(&deployment_sequence.libraries)?,
+                script_config.config.sender, // irrelevant, since we're not creating any
+                U256::zero(),                // irrelevant, since we're not creating any
+            )?;
+            verify.known_contracts = unwrap_contracts(&highlevel_known_contracts, false);
 
             if self.resume {
-                self.send_transactions(&mut deployment_sequence, &fork_url).await?;
+                self.send_transactions(&mut deployment_sequence, provider, &verify.known_contracts)
+                    .await?;
             }
 
             if self.verify {
-                // We might have predeployed libraries from the broadcasting, so we need to relink
-                // the contracts with them, since their mapping is not included in the solc cache
-                // files.
-                let BuildOutput { highlevel_known_contracts, .. } = self.link(
-                    build_output.project,
-                    build_output.known_contracts,
-                    Libraries::parse(&deployment_sequence.libraries)?,
-                    script_config.config.sender, // irrelevant, since we're not creating any
-                    U256::zero(),                // irrelevant, since we're not creating any
-                )?;
-
-                verify.known_contracts = unwrap_contracts(&highlevel_known_contracts, false);
-
                 deployment_sequence.verify_contracts(verify, chain).await?;
             }
         } else {
@@ -3,14 +3,16 @@ use crate::{
     cmd::{forge::script::sequence::TransactionWithMetadata, needs_setup},
     utils,
 };
-use cast::executor::inspector::CheatsConfig;
+use cast::executor::inspector::{CheatsConfig, CustomPrecompiles};
 use ethers::{
     solc::artifacts::CompactContractBytecode,
-    types::{transaction::eip2718::TypedTransaction, Address, U256},
+    types::{transaction::eip2718::TypedTransaction, Address},
 };
 use forge::{
-    executor::{Backend, ExecutorBuilder},
-    trace::CallTraceDecoder,
+    executor::{genesis::write_state, Backend, ExecutorBuilder},
+    revm::db::DatabaseCommit,
+    trace::{CallTraceDecoder, RawOrDecodedCall},
+    CallKind,
 };
 use std::collections::VecDeque;
 use tracing::trace;
@@ -30,21 +32,29 @@ impl ScriptArgs {
 
         let abi = abi.expect("no ABI for contract");
         let bytecode = bytecode.expect("no bytecode for contract").object.into_bytes().unwrap();
+        let bytecode = self.init_code(&abi, bytecode)?;
 
-        let mut runner = self.prepare_runner(script_config, sender).await;
+        let mut runner = self.prepare_runner(script_config, &script_config.evm_opts, sender).await;
         let (address, mut result) = runner.setup(
             predeploy_libraries,
             bytecode,
             needs_setup(&abi),
             script_config.sender_nonce,
-            self.broadcast,
+            self.broadcast || self.unsigned,
             script_config.evm_opts.fork_url.is_none(),
         )?;
 
         let (func, calldata) = self.get_method_and_calldata(&abi)?;
         script_config.called_function = Some(func);
 
-        let script_result = runner.script(address, calldata)?;
+        let mut script_result = runner.script(address, calldata)?;
+
+        if let Some(path) = &self.dump_state {
+            if let Some(state_changeset) = script_result.state_changeset.take() {
+                runner.executor.backend_mut().commit(state_changeset);
+            }
+            write_state(path, &runner.executor.dump_allocs())?;
+        }
 
         result.success &= script_result.success;
         result.gas = script_result.gas;
@@ -76,7 +86,11 @@ impl ScriptArgs {
         decoder: &mut CallTraceDecoder,
         contracts: &BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
     ) -> eyre::Result<VecDeque<TransactionWithMetadata>> {
-        let mut runner = self.prepare_runner(script_config, script_config.evm_opts.sender).await;
+        let mut evm_opts = script_config.evm_opts.clone();
+        if let Some(block) = self.simulate_block {
+            evm_opts.set_fork_block(block);
+        }
+        let mut runner = self.prepare_runner(script_config, &evm_opts, evm_opts.sender).await;
         let mut failed = false;
 
         if script_config.evm_opts.verbosity > 3 {
@@ -99,44 +113,46 @@ impl ScriptArgs {
             .collect();
 
         let mut final_txs = VecDeque::new();
-        for tx in transactions {
-            match tx {
-                TypedTransaction::Legacy(mut tx) => {
-                    let mut result = runner
-                        .simulate(
-                            tx.from.expect(
-                                "Transaction doesn't have a `from` address at execution time",
-                            ),
-                            tx.to.clone(),
-                            tx.data.clone(),
-                            tx.value,
-                        )
-                        .expect("Internal EVM error");
-
-                    // We inflate the gas used by the transaction by x1.3 since the estimation
-                    // might be off
-                    tx.gas = Some(U256::from(result.gas * 13 / 10));
-
-                    if !result.success {
-                        failed = true;
-                    }
-
-                    if script_config.evm_opts.verbosity > 3 {
-                        for (_kind, trace) in &mut result.traces {
-                            decoder.decode(trace).await;
-                            println!("{}", trace);
-                        }
-                    }
-
-                    final_txs.push_back(TransactionWithMetadata::new(
-                        tx.into(),
-                        &result,
-                        &address_to_abi,
-                        decoder,
-                    )?);
+        // Legacy, EIP-2930 and EIP-1559 transactions are simulated alike. OP-stack deposit
+        // transactions (type `0x7e`) have no `TypedTransaction` variant in this ethers version, so
+        // scripts can't produce them.
+        for mut tx in transactions {
+            let mut result = runner
+                .simulate(
+                    *tx.from()
+                        .expect("Transaction doesn't have a `from` address at execution time"),
+                    tx.to().cloned(),
+                    tx.data().cloned(),
+                    tx.value().copied(),
+                )
+                .expect("Internal EVM error");
+
+            // the initcode of a deployment is recorded from the CREATE the executor ran, and
+            // checked against the transaction that is eventually broadcast
+            let initcode = created_initcode(&result.traces);
+
+            // We inflate the gas used by the transaction by x1.3 since the estimation
+            // might be off
+            tx.set_gas(result.gas * 13 / 10);
+
+            if !result.success {
+                failed = true;
+            }
+
+            if script_config.evm_opts.verbosity > 3 {
+                for (_kind, trace) in &mut result.traces {
+                    decoder.decode(trace).await;
+                    println!("{}", trace);
                 }
-                _ => unreachable!(),
             }
+
+            final_txs.push_back(TransactionWithMetadata::new(
+                tx,
+                initcode.as_deref(),
+                &result,
+                &address_to_abi,
+                decoder,
+            )?);
         }
 
         if failed {
@@ -147,23 +163,44 @@ impl ScriptArgs {
     }
 
     /// Creates the Runner that drives script execution
-    async fn prepare_runner(&self, script_config: &ScriptConfig, sender: Address) -> ScriptRunner {
+    async fn prepare_runner(
+        &self,
+        script_config: &ScriptConfig,
+        evm_opts: &EvmOpts,
+        sender: Address,
+    ) -> ScriptRunner {
         trace!("preparing script runner");
-        let env = script_config.evm_opts.evm_env().await;
+        let env = evm_opts.evm_env().await;
 
         // the db backend that serves all the data
-        let db =
-            Backend::spawn(script_config.evm_opts.get_fork(&script_config.config, env.clone()));
+        let db = Backend::spawn(evm_opts.get_fork(&script_config.config, env.clone()));
 
         let executor = ExecutorBuilder::default()
-            .with_cheatcodes(CheatsConfig::new(&script_config.config, &script_config.evm_opts))
+            .with_cheatcodes(CheatsConfig::new(&script_config.config, evm_opts))
             .with_config(env)
             .with_spec(utils::evm_spec(&script_config.config.evm_version))
-            .with_gas_limit(script_config.evm_opts.gas_limit())
-            .set_tracing(script_config.evm_opts.verbosity >= 3 || self.debug)
+            .with_gas_limit(evm_opts.gas_limit())
+            // the initcode of the broadcast deployments is read from the traces
+            .set_tracing(evm_opts.verbosity >= 3 || self.debug || self.broadcast)
             .set_debugger(self.debug)
+            // the access lists are also used to batch the transactions of different senders
+            .set_access_list(self.access_list || self.broadcast)
+            .with_precompiles(CustomPrecompiles::from_commands(
+                &script_config.config.__root.0,
+                &script_config.config.precompiles,
+            ))
             .build(db);
 
-        ScriptRunner::new(executor, script_config.evm_opts.initial_balance, sender)
+        ScriptRunner::new(executor, evm_opts.initial_balance, sender)
     }
 }
+
+/// Returns the initcode of the first contract created in the traces of a simulated transaction
+fn created_initcode(traces: &[(TraceKind, CallTraceArena)]) -> Option<Vec<u8>> {
+    traces.iter().flat_map(|(_, arena)| arena.arena.iter()).find_map(|node| {
+        match (&node.trace.kind, &node.trace.data) {
+            (CallKind::Create, RawOrDecodedCall::Raw(initcode)) => Some(initcode.clone()),
+            _ => None,
+        }
+    })
+}
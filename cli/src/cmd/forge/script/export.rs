@@ -0,0 +1,255 @@
+//! Exports of the simulated transactions of a script, to review them in external tools before
+//! they're executed
+use super::sequence::{ScriptSequence, TransactionWithMetadata};
+use clap::ArgEnum;
+use ethers::types::{Address, Bytes, NameOrAddress};
+use foundry_common::fs;
+use serde::Serialize;
+use std::{
+    io::BufWriter,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The formats the transactions can be exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum ExportFormat {
+    /// A bundle for the simulation API of Tenderly
+    Tenderly,
+    /// A batch file for the Transaction Builder app of Gnosis Safe
+    Gnosis,
+}
+
+impl ExportFormat {
+    fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Tenderly => "tenderly",
+            ExportFormat::Gnosis => "gnosis",
+        }
+    }
+}
+
+/// Exports the transactions of the sequence next to it, as `../[sig]-[format]-latest.json`, and
+/// returns the path they are exported to
+pub fn export(
+    sequence: &ScriptSequence,
+    format: ExportFormat,
+    chain: u64,
+) -> eyre::Result<PathBuf> {
+    let path = sequence
+        .path
+        .to_string_lossy()
+        .replace("-latest.json", &format!("-{}-latest.json", format.name()));
+    let writer = BufWriter::new(fs::create_file(&path)?);
+    match format {
+        ExportFormat::Tenderly => serde_json::to_writer_pretty(
+            writer,
+            &TenderlyBundle::new(&sequence.transactions, chain),
+        )?,
+        ExportFormat::Gnosis => {
+            serde_json::to_writer_pretty(writer, &GnosisBatch::new(&sequence.transactions, chain)?)?
+        }
+    }
+    Ok(path.into())
+}
+
+/// The body of a request to the `simulate-bundle` endpoint of Tenderly, which simulates the
+/// transactions one after the other
+#[derive(Debug, Serialize)]
+struct TenderlyBundle {
+    simulations: Vec<TenderlySimulation>,
+}
+
+#[derive(Debug, Serialize)]
+struct TenderlySimulation {
+    network_id: String,
+    from: Address,
+    /// `None` for deployments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to: Option<Address>,
+    input: Bytes,
+    gas: u64,
+    gas_price: String,
+    value: String,
+    save: bool,
+    save_if_fails: bool,
+    simulation_type: &'static str,
+}
+
+impl TenderlyBundle {
+    fn new<'a>(
+        transactions: impl IntoIterator<Item = &'a TransactionWithMetadata>,
+        chain: u64,
+    ) -> Self {
+        let simulations = transactions
+            .into_iter()
+            .map(|tx| {
+                let tx = tx.typed_tx();
+                TenderlySimulation {
+                    network_id: chain.to_string(),
+                    from: tx.from().copied().unwrap_or_default(),
+                    to: to_address(tx.to()),
+                    input: tx.data().cloned().unwrap_or_default(),
+                    gas: tx.gas().map(|gas| gas.as_u64()).unwrap_or_default(),
+                    gas_price: tx.gas_price().unwrap_or_default().to_string(),
+                    value: tx.value().copied().unwrap_or_default().to_string(),
+                    save: true,
+                    save_if_fails: true,
+                    simulation_type: "full",
+                }
+            })
+            .collect();
+        Self { simulations }
+    }
+}
+
+/// A batch file for the Transaction Builder app of Gnosis Safe
+///
+/// The transactions are executed by the safe, so they must all be sent by the address of the safe
+/// and can't deploy contracts other than through a factory, like the CREATE2 deployer.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GnosisBatch {
+    version: &'static str,
+    chain_id: String,
+    created_at: u128,
+    meta: GnosisBatchMeta,
+    transactions: Vec<GnosisTransaction>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GnosisBatchMeta {
+    name: &'static str,
+    description: String,
+    created_from_safe_address: Address,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GnosisTransaction {
+    to: Address,
+    value: String,
+    data: Bytes,
+    /// The transactions carry their calldata, so they're not described by the ABI of the called
+    /// function
+    contract_method: Option<()>,
+    contract_inputs_values: Option<()>,
+}
+
+impl GnosisBatch {
+    fn new<'a>(
+        transactions: impl IntoIterator<Item = &'a TransactionWithMetadata>,
+        chain: u64,
+    ) -> eyre::Result<Self> {
+        let mut safe = None;
+        let mut batch = Vec::new();
+        for (idx, tx) in transactions.into_iter().enumerate() {
+            let typed_tx = tx.typed_tx();
+            let from = typed_tx.from().copied().unwrap_or_default();
+            if *safe.get_or_insert(from) != from {
+                eyre::bail!(
+                    "Transaction #{} is sent by {:?}, but a Gnosis Safe batch is executed by a single safe",
+                    idx + 1,
+                    from
+                )
+            }
+            let to = match to_address(typed_tx.to()) {
+                Some(to) => to,
+                None => eyre::bail!(
+                    "Transaction #{} deploys {}, but a Gnosis Safe can only deploy contracts through a factory, e.g. with `new Contract{{salt: ..}}(..)`",
+                    idx + 1,
+                    tx.contract_name.as_deref().unwrap_or("a contract")
+                ),
+            };
+            batch.push(GnosisTransaction {
+                to,
+                value: typed_tx.value().copied().unwrap_or_default().to_string(),
+                data: typed_tx.data().cloned().unwrap_or_default(),
+                contract_method: None,
+                contract_inputs_values: None,
+            });
+        }
+
+        Ok(Self {
+            version: "1.0",
+            chain_id: chain.to_string(),
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis(),
+            meta: GnosisBatchMeta {
+                name: "Transactions Batch",
+                description: "Exported by forge script".to_string(),
+                created_from_safe_address: safe.unwrap_or_default(),
+            },
+            transactions: batch,
+        })
+    }
+}
+
+fn to_address(to: Option<&NameOrAddress>) -> Option<Address> {
+    match to {
+        Some(NameOrAddress::Address(to)) => Some(*to),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{transaction::eip2718::TypedTransaction, TransactionRequest, U256};
+
+    fn tx(from: Address, to: Option<Address>) -> TransactionWithMetadata {
+        let mut request = TransactionRequest::new()
+            .from(from)
+            .data(vec![1, 2, 3])
+            .value(U256::exp10(18))
+            .gas(100_000u64)
+            .gas_price(U256::exp10(10));
+        request.to = to.map(Into::into);
+        TransactionWithMetadata {
+            opcode: if to.is_some() { "CALL" } else { "CREATE" }.to_string(),
+            tx: TypedTransaction::Legacy(request),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_export_tenderly_bundle() {
+        let from = Address::random();
+        let to = Address::random();
+        let transactions = vec![tx(from, None), tx(from, Some(to))];
+
+        let bundle = serde_json::to_value(TenderlyBundle::new(&transactions, 1)).unwrap();
+        let simulations = bundle["simulations"].as_array().unwrap();
+        assert_eq!(simulations.len(), 2);
+        assert!(simulations[0].get("to").is_none());
+        assert_eq!(simulations[1]["to"], serde_json::json!(to));
+        assert_eq!(simulations[1]["network_id"], "1");
+        assert_eq!(simulations[1]["input"], "0x010203");
+        assert_eq!(simulations[1]["gas"], 100_000);
+        assert_eq!(simulations[1]["gas_price"], "10000000000");
+        assert_eq!(simulations[1]["value"], "1000000000000000000");
+    }
+
+    #[test]
+    fn can_export_gnosis_batch() {
+        let safe = Address::random();
+        let to = Address::random();
+
+        let batch = GnosisBatch::new(&[tx(safe, Some(to)), tx(safe, Some(to))], 5).unwrap();
+        let batch = serde_json::to_value(batch).unwrap();
+        assert_eq!(batch["chainId"], "5");
+        assert_eq!(batch["meta"]["createdFromSafeAddress"], serde_json::json!(safe));
+        let transactions = batch["transactions"].as_array().unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0]["to"], serde_json::json!(to));
+        assert_eq!(transactions[0]["data"], "0x010203");
+        assert_eq!(transactions[0]["value"], "1000000000000000000");
+        assert!(transactions[0]["contractMethod"].is_null());
+
+        // a safe can't deploy contracts directly, or send transactions of other senders
+        assert!(GnosisBatch::new(&[tx(safe, None)], 5).is_err());
+        assert!(
+            GnosisBatch::new(&[tx(safe, Some(to)), tx(Address::random(), Some(to))], 5).is_err()
+        );
+    }
+}
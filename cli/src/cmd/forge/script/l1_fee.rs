@@ -0,0 +1,198 @@
+//! Estimation of the fees rollups charge for posting the transaction data to L1
+use super::sequence::TransactionWithMetadata;
+use ethers::{
+    abi::{self, ParamType, Token},
+    providers::Middleware,
+    types::{transaction::eip2718::TypedTransaction, Address, NameOrAddress, U256},
+    utils::id,
+};
+use eyre::WrapErr;
+use foundry_common::provider::RetryProvider;
+use foundry_config::{Chain as ConfigChain, ChainProfile};
+use std::collections::VecDeque;
+
+/// The `GasPriceOracle` predeploy of Optimism
+const OPTIMISM_GAS_PRICE_ORACLE: &str = "0x420000000000000000000000000000000000000F";
+
+/// The `NodeInterface` precompile of Arbitrum
+const ARBITRUM_NODE_INTERFACE: &str = "0x00000000000000000000000000000000000000C8";
+
+/// How a rollup charges for the L1 data of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum L1FeeModel {
+    /// The L1 fee is charged on top of the L2 gas, as quoted by the `GasPriceOracle`
+    Optimism,
+    /// The L1 fee is charged as additional L2 gas, which is part of the gas estimate, as quoted by
+    /// the `NodeInterface`
+    Arbitrum,
+}
+
+impl L1FeeModel {
+    /// Returns the model of the chain, if it charges an L1 fee
+    pub fn from_chain(chain: u64) -> Option<Self> {
+        match ConfigChain::from(chain) {
+            ConfigChain::Named(chain) => match chain {
+                ethers::types::Chain::Optimism | ethers::types::Chain::OptimismKovan => {
+                    Some(L1FeeModel::Optimism)
+                }
+                ethers::types::Chain::Arbitrum | ethers::types::Chain::ArbitrumTestnet => {
+                    Some(L1FeeModel::Arbitrum)
+                }
+                _ => None,
+            },
+            ConfigChain::Id(_) => None,
+        }
+    }
+
+    /// Returns the model of the chain the profile emulates, if it charges an L1 fee
+    pub fn from_profile(profile: ChainProfile) -> Option<Self> {
+        Self::from_chain(profile.chain().id())
+    }
+
+    /// Whether the fee is part of the gas estimate of the transactions
+    pub fn is_included_in_gas(&self) -> bool {
+        matches!(self, L1FeeModel::Arbitrum)
+    }
+
+    /// Returns the L1 fee of the transaction in wei
+    async fn estimate(
+        &self,
+        provider: &RetryProvider,
+        tx: &TypedTransaction,
+    ) -> eyre::Result<U256> {
+        match self {
+            L1FeeModel::Optimism => {
+                // the oracle quotes the fee of the signed transaction from its unsigned encoding
+                let data = encode_call("getL1Fee(bytes)", &[Token::Bytes(tx.rlp().to_vec())]);
+                let output = call(provider, OPTIMISM_GAS_PRICE_ORACLE, data).await?;
+                decode_word(&output, 0)
+            }
+            L1FeeModel::Arbitrum => {
+                let (to, create) = match tx.to() {
+                    Some(NameOrAddress::Address(to)) => (*to, false),
+                    _ => (Address::zero(), true),
+                };
+                let data = encode_call(
+                    "gasEstimateL1Component(address,bool,bytes)",
+                    &[
+                        Token::Address(to),
+                        Token::Bool(create),
+                        Token::Bytes(tx.data().map(|data| data.to_vec()).unwrap_or_default()),
+                    ],
+                );
+                let output = call(provider, ARBITRUM_NODE_INTERFACE, data).await?;
+                // returns the L1 component in L2 gas and the L2 base fee
+                let gas = decode_word(&output, 0)?;
+                let base_fee = decode_word(&output, 1)?;
+                Ok(gas.saturating_mul(base_fee))
+            }
+        }
+    }
+}
+
+/// The estimated L1 fees of the transactions of a script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L1Fees {
+    pub model: L1FeeModel,
+    /// The fee of every transaction in wei, in the order of the transactions
+    pub fees: Vec<U256>,
+}
+
+impl L1Fees {
+    /// Estimates the L1 fees of the `transactions` with the oracle of the rollup.
+    ///
+    /// The rollup is the one emulated by the chain `profile`, if any, so the fees of e.g. a devnet
+    /// with its own chain id are estimated too. Returns `None` if the chain does not charge L1
+    /// fees.
+    pub async fn estimate(
+        provider: &RetryProvider,
+        chain: u64,
+        profile: Option<ChainProfile>,
+        transactions: &VecDeque<TransactionWithMetadata>,
+    ) -> eyre::Result<Option<Self>> {
+        let model = profile.map_or_else(|| L1FeeModel::from_chain(chain), L1FeeModel::from_profile);
+        let model = match model {
+            Some(model) => model,
+            None => return Ok(None),
+        };
+        let mut fees = Vec::with_capacity(transactions.len());
+        for tx in transactions {
+            fees.push(
+                model
+                    .estimate(provider, tx.typed_tx())
+                    .await
+                    .wrap_err("Failed to estimate the L1 fee of a transaction")?,
+            );
+        }
+        Ok(Some(Self { model, fees }))
+    }
+
+    /// The sum of the L1 fees of all transactions
+    pub fn total(&self) -> U256 {
+        self.fees.iter().fold(U256::zero(), |total, fee| total.saturating_add(*fee))
+    }
+
+    /// The fee of the transaction at `idx` that is not part of its gas estimate
+    pub fn additional_fee(&self, idx: usize) -> U256 {
+        if self.model.is_included_in_gas() {
+            U256::zero()
+        } else {
+            self.fees.get(idx).copied().unwrap_or_default()
+        }
+    }
+
+    /// The sum of the fees that are not part of the gas estimates
+    pub fn additional_total(&self) -> U256 {
+        if self.model.is_included_in_gas() {
+            U256::zero()
+        } else {
+            self.total()
+        }
+    }
+}
+
+fn encode_call(signature: &str, args: &[Token]) -> Vec<u8> {
+    let mut data = id(signature).to_vec();
+    data.extend(abi::encode(args));
+    data
+}
+
+async fn call(provider: &RetryProvider, to: &str, data: Vec<u8>) -> eyre::Result<Vec<u8>> {
+    let to: Address = to.parse().expect("oracle addresses are valid");
+    let tx = TypedTransaction::Legacy(ethers::types::TransactionRequest::new().to(to).data(data));
+    Ok(provider.call(&tx, None).await?.to_vec())
+}
+
+/// Decodes the `idx`th word of the output of a call as a uint
+fn decode_word(output: &[u8], idx: usize) -> eyre::Result<U256> {
+    let tokens = abi::decode(&vec![ParamType::Uint(256); idx + 1], output)
+        .wrap_err("Unexpected output of the L1 fee oracle")?;
+    Ok(tokens[idx].clone().into_uint().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_sum_l1_fees() {
+        let fees =
+            L1Fees { model: L1FeeModel::Optimism, fees: vec![U256::from(100), U256::from(200)] };
+        assert_eq!(fees.total(), U256::from(300));
+        assert_eq!(fees.additional_fee(1), U256::from(200));
+        assert_eq!(fees.additional_total(), U256::from(300));
+
+        let fees = L1Fees { model: L1FeeModel::Arbitrum, ..fees };
+        assert_eq!(fees.total(), U256::from(300));
+        assert_eq!(fees.additional_fee(1), U256::zero());
+        assert_eq!(fees.additional_total(), U256::zero());
+
+        assert_eq!(L1FeeModel::from_chain(10), Some(L1FeeModel::Optimism));
+        assert_eq!(L1FeeModel::from_chain(42161), Some(L1FeeModel::Arbitrum));
+        assert_eq!(L1FeeModel::from_chain(1), None);
+
+        assert_eq!(L1FeeModel::from_profile(ChainProfile::Optimism), Some(L1FeeModel::Optimism));
+        assert_eq!(L1FeeModel::from_profile(ChainProfile::Arbitrum), Some(L1FeeModel::Arbitrum));
+        assert_eq!(L1FeeModel::from_profile(ChainProfile::Polygon), None);
+    }
+}
@@ -1,10 +1,13 @@
 //! script command
 use crate::{
     cmd::{
-        forge::build::{BuildArgs, ProjectPathsArgs},
+        forge::{
+            build::{BuildArgs, ProjectPathsArgs},
+            create::{parse_constructor_args, read_constructor_args},
+        },
         RetryArgs,
     },
-    opts::MultiWallet,
+    opts::{cast::parse_block_id, MultiWallet},
     utils::{get_contract_name, parse_ether_value},
 };
 use clap::{Parser, ValueHint};
@@ -14,12 +17,15 @@ use ethers::{
         artifacts::{ContractBytecodeSome, Libraries},
         ArtifactId, Bytes, Project,
     },
-    types::{transaction::eip2718::TypedTransaction, Address, Log, TransactionRequest, U256},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+        Address, BlockId, BlockNumber, Log, TransactionRequest, U256,
+    },
 };
 use forge::{
     debug::DebugArena,
     decode::decode_console_logs,
-    executor::opts::EvmOpts,
+    executor::{opts::EvmOpts, StateChangeset},
     trace::{
         identifier::{EtherscanIdentifier, LocalTraceIdentifier, SignaturesIdentifier},
         CallTraceArena, CallTraceDecoder, CallTraceDecoderBuilder, TraceKind,
@@ -47,7 +53,14 @@ use ui::{TUIExitReason, Tui, Ui};
 
 mod cmd;
 mod executor;
+mod export;
+use export::ExportFormat;
+
+mod l1_fee;
+mod nonce;
+mod plan;
 mod receipts;
+mod schedule;
 mod sequence;
 
 // Loads project's figment and merges the build cli arguments into it
@@ -74,6 +87,27 @@ pub struct ScriptArgs {
     #[clap(long, short, default_value = "run()", value_name = "SIGNATURE")]
     pub sig: String,
 
+    /// The constructor arguments of the script contract.
+    #[clap(
+        long,
+        multiple_values = true,
+        conflicts_with = "constructor_args_path",
+        value_name = "ARGS"
+    )]
+    pub constructor_args: Vec<String>,
+
+    /// The path to a file containing the constructor arguments of the script contract.
+    ///
+    /// The file holds either a JSON array of the arguments, the whitespace separated arguments,
+    /// or the `0x` prefixed ABI-encoded arguments.
+    #[clap(
+        long,
+        value_hint = ValueHint::FilePath,
+        conflicts_with = "constructor_args",
+        value_name = "FILE"
+    )]
+    pub constructor_args_path: Option<PathBuf>,
+
     #[clap(
         long,
         help = "Use legacy transactions instead of EIP1559 ones. this is auto-enabled for common networks without EIP1559."
@@ -132,10 +166,70 @@ pub struct ScriptArgs {
     )]
     pub with_gas_price: Option<U256>,
 
+    /// Print a plan of the simulated transactions before broadcasting them.
+    ///
+    /// The plan lists the transactions in order with their target contracts, decoded function
+    /// calls, values and estimated gas, as well as the estimated total cost. Requires a RPC URL.
+    #[clap(long)]
+    pub plan: bool,
+
+    /// Attach EIP-2930 access lists to the broadcasted transactions.
+    ///
+    /// The access lists contain the accounts and storage slots accessed by the transactions in the
+    /// simulation. They're not attached to legacy transactions.
+    #[clap(long)]
+    pub access_list: bool,
+
+    /// Write the simulated transactions unsigned instead of broadcasting them.
+    ///
+    /// Senders of `vm.broadcast(address)` and `vm.startBroadcast(address)` don't need a wallet,
+    /// they are impersonated in the simulation. The transactions are written with their nonces,
+    /// gas limits and fees filled next to the broadcast logs, to be signed by external
+    /// infrastructure. Requires a RPC URL.
+    #[clap(long, conflicts_with = "broadcast")]
+    pub unsigned: bool,
+
+    /// Write the state after running the script to a file.
+    ///
+    /// The state is written in the state format of anvil, so it can be loaded with
+    /// `anvil --load-state` or `vm.loadAllocs`.
+    #[clap(long, value_name = "PATH")]
+    pub dump_state: Option<PathBuf>,
+
+    /// The block to simulate the transactions on-chain against: `latest`, `pending` or a block
+    /// number.
+    ///
+    /// Simulating against `pending` includes the transactions in the mempool of the endpoint, so
+    /// the simulation and its gas estimates match the state the transactions will likely be
+    /// executed on. Defaults to the block of the fork.
+    #[clap(
+        long,
+        requires = "fork-url",
+        parse(try_from_str = parse_block_number),
+        value_name = "BLOCK"
+    )]
+    pub simulate_block: Option<BlockNumber>,
+
+    /// Export the simulated transactions to review them in external tools before executing them.
+    ///
+    /// `tenderly` exports a bundle for the simulation API of Tenderly, `gnosis` a batch file for
+    /// the Transaction Builder of Gnosis Safe, whose transactions must all be sent by the safe.
+    /// The exports are written next to the broadcast logs. Requires a RPC URL.
+    #[clap(long, arg_enum, multiple_occurrences = true, value_name = "FORMAT")]
+    pub export: Vec<ExportFormat>,
+
     #[clap(flatten, help = "Allows to use retry arguments for contract verification")]
     pub retry: RetryArgs,
 }
 
+/// Parses a block number or tag, block hashes are not supported
+fn parse_block_number(s: &str) -> eyre::Result<BlockNumber> {
+    match parse_block_id(s)? {
+        BlockId::Number(number) => Ok(number),
+        BlockId::Hash(_) => eyre::bail!("Expected a block number or tag, found a block hash"),
+    }
+}
+
 // === impl ScriptArgs ===
 
 impl ScriptArgs {
@@ -308,22 +402,15 @@ impl ScriptArgs {
 
         if let Some(txs) = transactions {
             if !predeploy_libraries.is_empty() {
-                for tx in txs.iter() {
-                    match tx {
-                        TypedTransaction::Legacy(tx) => {
-                            if tx.to.is_none() {
-                                let sender = tx.from.expect("no sender");
-                                if let Some(ns) = new_sender {
-                                    if sender != ns {
-                                        println!("You have more than one deployer who could predeploy libraries. Using `--sender` instead.");
-                                        return Ok(None)
-                                    }
-                                } else if sender != evm_opts.sender {
-                                    new_sender = Some(sender);
-                                }
-                            }
+                for tx in txs.iter().filter(|tx| tx.to().is_none()) {
+                    let sender = *tx.from().expect("no sender");
+                    if let Some(ns) = new_sender {
+                        if sender != ns {
+                            println!("You have more than one deployer who could predeploy libraries. Using `--sender` instead.");
+                            return Ok(None)
                         }
-                        _ => unreachable!(),
+                    } else if sender != evm_opts.sender {
+                        new_sender = Some(sender);
                     }
                 }
             }
@@ -376,6 +463,23 @@ impl ScriptArgs {
         }
     }
 
+    /// Returns the init code of the script contract, its `bytecode` followed by its encoded
+    /// constructor arguments, which are validated against the constructor of its `abi`
+    pub fn init_code(&self, abi: &Abi, bytecode: Bytes) -> eyre::Result<Bytes> {
+        let args =
+            read_constructor_args(&self.constructor_args, self.constructor_args_path.as_ref())?;
+        match abi.constructor {
+            Some(ref constructor) => {
+                let tokens = parse_constructor_args(constructor, &args)?;
+                Ok(constructor.encode_input(bytecode.to_vec(), &tokens)?.into())
+            }
+            None if !args.is_empty() => {
+                eyre::bail!("The script contract has no constructor, but constructor arguments were provided")
+            }
+            None => Ok(bytecode),
+        }
+    }
+
     pub fn get_method_and_calldata(&self, abi: &Abi) -> eyre::Result<(Function, Bytes)> {
         let (func, data) = match self.sig.strip_prefix("0x") {
             Some(calldata) => (
@@ -410,6 +514,8 @@ pub struct ScriptResult {
     pub transactions: Option<VecDeque<TypedTransaction>>,
     pub returned: bytes::Bytes,
     pub address: Option<Address>,
+    pub state_changeset: Option<StateChangeset>,
+    pub access_list: Option<AccessList>,
 }
 
 #[derive(Serialize, Deserialize)]
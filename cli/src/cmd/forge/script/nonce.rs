@@ -0,0 +1,77 @@
+//! Allocation of the nonces of the broadcasted transactions
+//!
+//! The transactions of a batch are signed and sent concurrently, possibly by remote signers, so
+//! their nonces are handed out by a shared allocator instead of being read from the chain by each
+//! of them.
+use ethers::types::{Address, U256};
+use std::{collections::HashMap, sync::Mutex};
+
+/// Hands out the nonces of the senders of a broadcast, every nonce exactly once
+#[derive(Debug, Default)]
+pub struct NonceAllocator {
+    next: Mutex<HashMap<Address, U256>>,
+}
+
+impl NonceAllocator {
+    /// Sets the next nonce of `sender`, usually its nonce on chain.
+    pub fn sync(&self, sender: Address, nonce: U256) {
+        self.next.lock().unwrap().insert(sender, nonce);
+    }
+
+    /// Returns the next nonce of `sender` and advances it, or `None` if `sender` was never synced.
+    pub fn allocate(&self, sender: Address) -> Option<U256> {
+        let mut next = self.next.lock().unwrap();
+        let nonce = next.get_mut(&sender)?;
+        let allocated = *nonce;
+        *nonce += U256::one();
+        Some(allocated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashSet, sync::Arc};
+
+    #[test]
+    fn allocates_consecutive_nonces_per_sender() {
+        let alice = Address::repeat_byte(1);
+        let bob = Address::repeat_byte(2);
+        let nonces = NonceAllocator::default();
+        nonces.sync(alice, 5.into());
+        nonces.sync(bob, 0.into());
+
+        assert_eq!(nonces.allocate(alice), Some(5.into()));
+        assert_eq!(nonces.allocate(bob), Some(0.into()));
+        assert_eq!(nonces.allocate(alice), Some(6.into()));
+        assert_eq!(nonces.allocate(Address::repeat_byte(3)), None);
+
+        // syncing again starts over from the nonce on chain
+        nonces.sync(alice, 10.into());
+        assert_eq!(nonces.allocate(alice), Some(10.into()));
+    }
+
+    #[test]
+    fn allocates_unique_nonces_concurrently() {
+        let sender = Address::repeat_byte(1);
+        let nonces = Arc::new(NonceAllocator::default());
+        nonces.sync(sender, 3.into());
+
+        let handles = (0..8)
+            .map(|_| {
+                let nonces = nonces.clone();
+                std::thread::spawn(move || {
+                    (0..100).map(|_| nonces.allocate(sender).unwrap()).collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let allocated = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .map(|nonce| nonce.as_u64())
+            .collect::<HashSet<_>>();
+
+        assert_eq!(allocated, (3..803).collect());
+    }
+}
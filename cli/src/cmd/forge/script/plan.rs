@@ -0,0 +1,188 @@
+//! Human readable plan of the transactions of a script
+use super::{l1_fee::L1Fees, sequence::TransactionWithMetadata};
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
+use ethers::{
+    types::{NameOrAddress, U256},
+    utils::format_units,
+};
+use foundry_config::Chain;
+use std::{collections::VecDeque, fmt};
+
+/// The transactions of a script in the order they will be broadcast, with their estimated costs
+pub struct ScriptPlan<'a> {
+    /// The chain the transactions are sent to
+    pub chain: u64,
+    /// The simulated transactions, with their gas limits set
+    pub transactions: &'a VecDeque<TransactionWithMetadata>,
+    /// The price per gas the costs are estimated with
+    pub gas_price: U256,
+    /// The L1 data fees of the transactions, if the chain is a rollup that charges them
+    pub l1_fees: Option<&'a L1Fees>,
+}
+
+impl<'a> ScriptPlan<'a> {
+    /// Returns the sum of the gas limits of all transactions
+    fn total_gas(&self) -> U256 {
+        self.transactions
+            .iter()
+            .filter_map(|tx| tx.typed_tx().gas().copied())
+            .fold(U256::zero(), |total, gas| total.saturating_add(gas))
+    }
+
+    /// Returns the L1 fee of the transaction at `idx` that is charged in addition to its gas
+    fn additional_l1_fee(&self, idx: usize) -> U256 {
+        self.l1_fees.map(|fees| fees.additional_fee(idx)).unwrap_or_default()
+    }
+}
+
+impl<'a> fmt::Display for ScriptPlan<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+        let mut headers = vec!["#", "From", "To", "Function", "Value (ETH)", "Gas"];
+        if self.l1_fees.is_some() {
+            headers.push("L1 fee (ETH)");
+        }
+        headers.push("Cost (ETH)");
+        table.set_header(
+            headers
+                .into_iter()
+                .map(|header| Cell::new(header).add_attribute(Attribute::Bold).fg(Color::Blue))
+                .collect::<Vec<_>>(),
+        );
+
+        for (idx, tx) in self.transactions.iter().enumerate() {
+            let typed_tx = tx.typed_tx();
+            let gas = typed_tx.gas().copied().unwrap_or_default();
+            let from = typed_tx.from().map(|from| format!("{from:?}")).unwrap_or_default();
+            let to = match (tx.opcode.as_str(), &tx.contract_name, typed_tx.to()) {
+                ("CREATE" | "CREATE2", Some(name), _) => format!("new {name}"),
+                ("CREATE" | "CREATE2", None, _) => "new contract".to_string(),
+                (_, Some(name), _) => name.clone(),
+                (_, None, Some(NameOrAddress::Address(to))) => format!("{to:?}"),
+                (_, None, Some(NameOrAddress::Name(name))) => name.clone(),
+                (_, None, None) => String::new(),
+            };
+            let function = match (&tx.function, &tx.arguments) {
+                (Some(function), arguments) => {
+                    let name = function.split('(').next().unwrap_or(function);
+                    format!("{}({})", name, arguments.as_deref().unwrap_or_default().join(", "))
+                }
+                (None, _) if tx.opcode.starts_with("CREATE") => "constructor".to_string(),
+                (None, _) => String::new(),
+            };
+
+            let mut row = vec![
+                Cell::new(idx + 1),
+                Cell::new(from),
+                Cell::new(to),
+                Cell::new(function),
+                Cell::new(format_eth(typed_tx.value().copied().unwrap_or_default())),
+                Cell::new(gas),
+            ];
+            if let Some(l1_fees) = self.l1_fees {
+                row.push(Cell::new(format_eth(l1_fees.fees.get(idx).copied().unwrap_or_default())));
+            }
+            row.push(Cell::new(format_eth(
+                gas.saturating_mul(self.gas_price).saturating_add(self.additional_l1_fee(idx)),
+            )));
+            table.add_row(row);
+        }
+
+        writeln!(f, "{}", table)?;
+        let total_gas = self.total_gas();
+        let additional_l1_fees =
+            self.l1_fees.map(|fees| fees.additional_total()).unwrap_or_default();
+        write!(
+            f,
+            "Chain {}: {} transactions, {} gas, estimated cost {} ETH at {} gwei",
+            Chain::from(self.chain),
+            self.transactions.len(),
+            total_gas,
+            format_eth(total_gas.saturating_mul(self.gas_price).saturating_add(additional_l1_fees)),
+            format_units(self.gas_price, 9)
+                .map(|gwei| trim_decimals(&gwei))
+                .unwrap_or_else(|_| "[Could not calculate]".to_string())
+        )?;
+        if let Some(l1_fees) = self.l1_fees {
+            write!(f, ", including {} ETH of L1 data fees", format_eth(l1_fees.total()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats an amount of wei as ETH, without trailing zeros
+fn format_eth(wei: U256) -> String {
+    format_units(wei, 18)
+        .map(|eth| trim_decimals(&eth))
+        .unwrap_or_else(|_| "[Could not calculate]".to_string())
+}
+
+fn trim_decimals(amount: &str) -> String {
+    if amount.contains('.') {
+        amount.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        amount.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::forge::script::l1_fee::L1FeeModel;
+    use ethers::types::{transaction::eip2718::TypedTransaction, Address, TransactionRequest};
+
+    #[test]
+    fn can_print_plan() {
+        let deploy = TransactionWithMetadata {
+            opcode: "CREATE".to_string(),
+            contract_name: Some("Counter".to_string()),
+            tx: TypedTransaction::Legacy(
+                TransactionRequest::new().from(Address::random()).gas(100_000u64),
+            ),
+            ..Default::default()
+        };
+        let call = TransactionWithMetadata {
+            opcode: "CALL".to_string(),
+            contract_name: Some("Counter".to_string()),
+            function: Some("setNumber(uint256)".to_string()),
+            arguments: Some(vec!["42".to_string()]),
+            tx: TypedTransaction::Legacy(
+                TransactionRequest::new()
+                    .from(Address::random())
+                    .to(Address::random())
+                    .value(U256::exp10(17))
+                    .gas(50_000u64),
+            ),
+            ..Default::default()
+        };
+        let transactions = VecDeque::from([deploy, call]);
+
+        let plan = ScriptPlan {
+            chain: 1,
+            transactions: &transactions,
+            gas_price: U256::exp10(10),
+            l1_fees: None,
+        }
+        .to_string();
+        assert!(plan.contains("new Counter"));
+        assert!(plan.contains("constructor"));
+        assert!(plan.contains("setNumber(42)"));
+        assert!(plan.contains("0.1"));
+        assert!(plan.contains("2 transactions, 150000 gas, estimated cost 0.0015 ETH at 10 gwei"));
+
+        let l1_fees =
+            L1Fees { model: L1FeeModel::Optimism, fees: vec![U256::exp10(15), U256::exp10(15)] };
+        let plan = ScriptPlan {
+            chain: 10,
+            transactions: &transactions,
+            gas_price: U256::exp10(10),
+            l1_fees: Some(&l1_fees),
+        }
+        .to_string();
+        assert!(plan.contains("L1 fee (ETH)"));
+        assert!(plan.contains(
+            "2 transactions, 150000 gas, estimated cost 0.0035 ETH at 10 gwei, including 0.002 ETH of L1 data fees"
+        ));
+    }
+}
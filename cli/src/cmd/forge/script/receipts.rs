@@ -2,7 +2,8 @@ use crate::{
     cmd::forge::script::sequence::ScriptSequence, init_progress, update_progress,
     utils::print_receipt,
 };
-use ethers::prelude::{Http, PendingTransaction, Provider, RetryClient, TxHash};
+use ethers::prelude::{PendingTransaction, TxHash};
+use foundry_common::provider::RetryProvider;
 use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::sync::Arc;
@@ -10,7 +11,7 @@ use tracing::trace;
 
 /// Gets the receipts of previously pending transactions.
 pub async fn wait_for_pending(
-    provider: Arc<Provider<RetryClient<Http>>>,
+    provider: Arc<RetryProvider>,
     deployment_sequence: &mut ScriptSequence,
 ) -> eyre::Result<()> {
     if !deployment_sequence.pending.is_empty() {
@@ -26,7 +27,7 @@ pub async fn wait_for_pending(
 pub async fn wait_for_receipts(
     tx_hashes: Vec<TxHash>,
     deployment_sequence: &mut ScriptSequence,
-    provider: Arc<Provider<RetryClient<Http>>>,
+    provider: Arc<RetryProvider>,
 ) -> eyre::Result<()> {
     trace!("waiting for receipts of {} transactions", tx_hashes.len());
     let mut tasks = futures::stream::iter(
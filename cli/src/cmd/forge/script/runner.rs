@@ -130,6 +130,8 @@ impl ScriptRunner {
                 traces,
                 debug,
                 address: None,
+                state_changeset: None,
+                access_list: None,
             },
         ))
     }
@@ -150,12 +152,13 @@ impl ScriptRunner {
         if let Some(NameOrAddress::Address(to)) = to {
             self.call(from, to, calldata.unwrap_or_default(), value.unwrap_or(U256::zero()), true)
         } else if to.is_none() {
-            let DeployResult { address, gas, logs, traces, debug } = self.executor.deploy(
-                from,
-                calldata.expect("No data for create transaction").0,
-                value.unwrap_or(U256::zero()),
-                None,
-            )?;
+            let DeployResult { address, gas, logs, traces, debug, access_list } =
+                self.executor.deploy(
+                    from,
+                    calldata.expect("No data for create transaction").0,
+                    value.unwrap_or(U256::zero()),
+                    None,
+                )?;
 
             Ok(ScriptResult {
                 returned: bytes::Bytes::new(),
@@ -173,6 +176,8 @@ impl ScriptRunner {
                 labeled_addresses: Default::default(),
                 transactions: Default::default(),
                 address: Some(address),
+                state_changeset: None,
+                access_list,
             })
         } else {
             eyre::bail!("ENS not supported.");
@@ -197,6 +202,8 @@ impl ScriptRunner {
             labels,
             debug,
             transactions,
+            state_changeset,
+            access_list,
             ..
         } = if !commit {
             self.executor.call_raw(from, to, calldata.0, value)?
@@ -222,6 +229,8 @@ impl ScriptRunner {
             labeled_addresses: labels,
             transactions,
             address: None,
+            state_changeset,
+            access_list,
         })
     }
 }
@@ -0,0 +1,133 @@
+//! Batching of the transactions of a script for the broadcast
+//!
+//! The transactions of a single sender are ordered by their nonces, so they can be sent without
+//! waiting for each other. Transactions of different senders are only ordered by their batches:
+//! every batch is confirmed before the next one is sent. A transaction therefore starts a new
+//! batch if it depends on a transaction of another sender in the current batch.
+use super::sequence::TransactionWithMetadata;
+use ethers::types::{Address, NameOrAddress};
+use std::ops::Range;
+
+/// Splits the transactions into consecutive batches of at most `max_size` transactions that can
+/// be sent at once
+pub fn batches(transactions: &[&TransactionWithMetadata], max_size: usize) -> Vec<Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    for (idx, tx) in transactions.iter().enumerate() {
+        let depends_on_batch =
+            transactions[start..idx].iter().any(|earlier| depends_on(tx, earlier));
+        if idx - start == max_size || depends_on_batch {
+            batches.push(start..idx);
+            start = idx;
+        }
+    }
+    if start < transactions.len() {
+        batches.push(start..transactions.len());
+    }
+    batches
+}
+
+/// Returns `true` if `tx` must not be sent before `earlier` is confirmed.
+///
+/// That's the case if `tx` is sent by another sender and
+/// - calls or references a contract created by `earlier`,
+/// - accesses a storage slot `earlier` accessed,
+/// - or is sent by the recipient of `earlier`, which may fund it.
+///
+/// Without the access lists of the simulation, e.g. if the sequence was resumed, the transactions
+/// are assumed to depend on each other.
+fn depends_on(tx: &TransactionWithMetadata, earlier: &TransactionWithMetadata) -> bool {
+    let (tx_from, earlier_from) = match (tx.typed_tx().from(), earlier.typed_tx().from()) {
+        (Some(tx_from), Some(earlier_from)) => (tx_from, earlier_from),
+        _ => return true,
+    };
+    if tx_from == earlier_from {
+        return false
+    }
+    let (access_list, earlier_access_list) = match (&tx.access_list, &earlier.access_list) {
+        (Some(access_list), Some(earlier_access_list)) => (access_list, earlier_access_list),
+        _ => return true,
+    };
+
+    if let Some(created) = earlier.contract_address {
+        let is_called = to_address(tx) == Some(created) ||
+            access_list.0.iter().any(|item| item.address == created);
+        let is_referenced = tx
+            .typed_tx()
+            .data()
+            .map_or(false, |data| data.windows(20).any(|word| word == created.as_bytes()));
+        if is_called || is_referenced {
+            return true
+        }
+    }
+
+    let shares_slot = access_list.0.iter().any(|item| {
+        earlier_access_list.0.iter().any(|earlier_item| {
+            item.address == earlier_item.address &&
+                item.storage_keys.iter().any(|key| earlier_item.storage_keys.contains(key))
+        })
+    });
+    shares_slot || to_address(earlier) == Some(*tx_from)
+}
+
+fn to_address(tx: &TransactionWithMetadata) -> Option<Address> {
+    match tx.typed_tx().to() {
+        Some(NameOrAddress::Address(to)) => Some(*to),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{
+        transaction::eip2930::{AccessList, AccessListItem},
+        TransactionRequest, H256,
+    };
+
+    fn tx(from: u64, to: u64, slots: &[(u64, u64)]) -> TransactionWithMetadata {
+        let access_list = slots
+            .iter()
+            .map(|(address, slot)| AccessListItem {
+                address: Address::from_low_u64_be(*address),
+                storage_keys: vec![H256::from_low_u64_be(*slot)],
+            })
+            .collect();
+        TransactionWithMetadata {
+            tx: TransactionRequest::new()
+                .from(Address::from_low_u64_be(from))
+                .to(Address::from_low_u64_be(to))
+                .into(),
+            access_list: Some(AccessList(access_list)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn batches_independent_transactions() {
+        let mut deploy = tx(1, 0, &[]);
+        deploy.tx = TransactionRequest::new().from(Address::from_low_u64_be(1)).into();
+        deploy.contract_address = Some(Address::from_low_u64_be(100));
+        let transactions = vec![
+            deploy,
+            // the same sender calls the created contract
+            tx(1, 100, &[(100, 0)]),
+            // another sender touches another slot
+            tx(2, 200, &[(200, 0)]),
+            // another sender calls the created contract
+            tx(3, 100, &[(100, 1)]),
+            // another sender touches the slot of the previous transaction
+            tx(4, 300, &[(100, 1)]),
+        ];
+        let transactions = transactions.iter().collect::<Vec<_>>();
+
+        assert_eq!(batches(&transactions, 100), vec![0..3, 3..4, 4..5]);
+        assert_eq!(batches(&transactions, 2), vec![0..2, 2..4, 4..5]);
+
+        // without access lists the transactions of different senders are not batched
+        let mut resumed = transactions.iter().map(|tx| (*tx).clone()).collect::<Vec<_>>();
+        resumed.iter_mut().for_each(|tx| tx.access_list = None);
+        let resumed = resumed.iter().collect::<Vec<_>>();
+        assert_eq!(batches(&resumed, 100), vec![0..2, 2..3, 3..4, 4..5]);
+    }
+}
@@ -5,7 +5,11 @@ use ethers::{
     abi::{Abi, Address},
     prelude::{artifacts::Libraries, ArtifactId, NameOrAddress, TransactionReceipt, TxHash},
     solc::info::ContractInfo,
-    types::transaction::eip2718::TypedTransaction,
+    types::{
+        transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+        Bytes, Eip1559TransactionRequest, H256,
+    },
+    utils::keccak256,
 };
 use eyre::ContextCompat;
 use forge::trace::CallTraceDecoder;
@@ -91,6 +95,14 @@ impl ScriptSequence {
         Ok(())
     }
 
+    /// Saves the unsigned transactions next to the transactions sequence, as
+    /// `../[sig]-unsigned-latest.json`, and returns the path they are saved to
+    pub fn save_unsigned(&self, transactions: &[TypedTransaction]) -> eyre::Result<PathBuf> {
+        let path = self.path.to_string_lossy().replace("-latest.json", "-unsigned-latest.json");
+        serde_json::to_writer_pretty(BufWriter::new(fs::create_file(&path)?), transactions)?;
+        Ok(path.into())
+    }
+
     pub fn add_receipt(&mut self, receipt: TransactionReceipt) {
         self.receipts.push(receipt);
     }
@@ -249,21 +261,36 @@ pub struct TransactionWithMetadata {
     pub function: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub arguments: Option<Vec<String>>,
+    /// The hash of the initcode that was simulated, if this is a deployment, which is recorded
+    /// from the CREATE run by the executor rather than from `tx`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initcode_hash: Option<H256>,
     pub tx: TypedTransaction,
+    /// The access list collected in the simulation, which is attached to the transaction unless
+    /// it's a legacy transaction
+    #[serde(skip)]
+    pub access_list: Option<AccessList>,
 }
 
 impl TransactionWithMetadata {
     pub fn new(
         tx: TypedTransaction,
+        initcode: Option<&[u8]>,
         result: &ScriptResult,
         local_contracts: &BTreeMap<Address, (String, &Abi)>,
         decoder: &CallTraceDecoder,
     ) -> eyre::Result<Self> {
-        let mut metadata = Self { tx, ..Default::default() };
+        let mut metadata =
+            Self { tx, access_list: result.access_list.clone(), ..Default::default() };
 
         if let Some(NameOrAddress::Address(to)) = metadata.tx.to().cloned() {
             if to == DEFAULT_CREATE2_DEPLOYER {
-                metadata.set_create(true, Address::from_slice(&result.returned), local_contracts)
+                metadata.set_create(
+                    true,
+                    Address::from_slice(&result.returned),
+                    initcode,
+                    local_contracts,
+                )
             } else {
                 metadata.set_call(to, local_contracts, decoder)?;
             }
@@ -271,6 +298,7 @@ impl TransactionWithMetadata {
             metadata.set_create(
                 false,
                 result.address.expect("There should be a contract address."),
+                initcode,
                 local_contracts,
             );
         }
@@ -281,6 +309,7 @@ impl TransactionWithMetadata {
         &mut self,
         is_create2: bool,
         address: Address,
+        initcode: Option<&[u8]>,
         contracts: &BTreeMap<Address, (String, &Abi)>,
     ) {
         if is_create2 {
@@ -291,6 +320,7 @@ impl TransactionWithMetadata {
 
         self.contract_name = contracts.get(&address).map(|(name, _)| name.clone());
         self.contract_address = Some(address);
+        self.initcode_hash = initcode.map(|code| keccak256(code).into());
     }
 
     fn set_call(
@@ -342,11 +372,17 @@ impl TransactionWithMetadata {
         self.tx = tx;
     }
 
-    pub fn change_type(&mut self, is_legacy: bool) {
+    /// Converts the transaction to a legacy or EIP-1559 transaction, attaching the access list of
+    /// the simulation to the latter if `attach_access_list` is set
+    pub fn change_type(&mut self, is_legacy: bool, attach_access_list: bool) {
         self.tx = if is_legacy {
             TypedTransaction::Legacy(self.tx.clone().into())
         } else {
-            TypedTransaction::Eip1559(self.tx.clone().into())
+            let mut tx: Eip1559TransactionRequest = self.tx.clone().into();
+            if let Some(access_list) = self.access_list.as_ref().filter(|_| attach_access_list) {
+                tx.access_list = access_list.clone();
+            }
+            TypedTransaction::Eip1559(tx)
         };
     }
 
@@ -362,3 +398,169 @@ impl TransactionWithMetadata {
         self.opcode == "CREATE2"
     }
 }
+
+/// Returns the initcode of a deployment transaction.
+///
+/// Deployments through the CREATE2 deployer are prefixed with the salt.
+fn initcode(data: &Bytes, is_create2: bool) -> Option<&[u8]> {
+    if is_create2 {
+        data.0.get(32..)
+    } else {
+        Some(&data.0)
+    }
+}
+
+/// Verifies that the transactions about to be signed carry the exact initcode that was simulated,
+/// and that this initcode starts with the creation code of the currently compiled `contracts`.
+///
+/// This catches recompiles or artifact changes between the simulation and the broadcast, also
+/// when a broadcast is resumed from the saved sequence.
+pub fn verify_initcode<'a>(
+    txs: impl IntoIterator<Item = (&'a TransactionWithMetadata, &'a TypedTransaction)>,
+    contracts: &BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
+) -> eyre::Result<()> {
+    let diffs = txs
+        .into_iter()
+        .filter_map(|(metadata, tx)| {
+            let simulated = metadata.initcode_hash?;
+            let name = metadata.contract_name.as_deref().unwrap_or("<unknown>");
+            let address = metadata.contract_address.unwrap_or_default();
+            let code = tx.data().and_then(|data| initcode(data, metadata.is_create2()));
+            let broadcast: Option<H256> = code.map(|code| keccak256(code).into());
+            if broadcast != Some(simulated) {
+                return Some(format!(
+                    "  {} ({:?}):\n    simulated: {:?}\n    broadcast: {}",
+                    name,
+                    address,
+                    simulated,
+                    broadcast
+                        .map(|hash| format!("{:?}", hash))
+                        .unwrap_or_else(|| "none".to_string())
+                ))
+            }
+
+            // the initcode is the creation code of the contract followed by the constructor
+            // arguments
+            let mut artifacts = contracts
+                .iter()
+                .filter(|(id, _)| Some(&id.name) == metadata.contract_name.as_ref())
+                .map(|(_, (_, bytecode))| bytecode)
+                .peekable();
+            let code = code?;
+            if artifacts.peek().is_none() || artifacts.any(|bytecode| code.starts_with(bytecode)) {
+                return None
+            }
+            Some(format!(
+                "  {} ({:?}):\n    the initcode does not match the compiled artifact",
+                name, address
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    if !diffs.is_empty() {
+        eyre::bail!(
+            "The initcode changed between simulation and broadcast for:\n{}",
+            diffs.join("\n")
+        )
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::TransactionRequest;
+
+    fn deployment(data: Vec<u8>) -> TypedTransaction {
+        TypedTransaction::Legacy(TransactionRequest::new().data(Bytes::from(data)))
+    }
+
+    fn artifacts(name: &str, bytecode: Vec<u8>) -> BTreeMap<ArtifactId, (Abi, Vec<u8>)> {
+        let id = ArtifactId {
+            path: PathBuf::from(format!("out/{name}.sol/{name}.json")),
+            name: name.to_string(),
+            source: PathBuf::from(format!("src/{name}.sol")),
+            version: Version::new(0, 8, 15),
+        };
+        BTreeMap::from([(id, (Abi::default(), bytecode))])
+    }
+
+    #[test]
+    fn can_verify_initcode() {
+        let abi = Abi::default();
+        let contracts = BTreeMap::from([(Address::zero(), ("Counter".to_string(), &abi))]);
+        let mut metadata =
+            TransactionWithMetadata { tx: deployment(vec![0x60, 0x80]), ..Default::default() };
+        metadata.set_create(false, Address::zero(), Some(&[0x60, 0x80][..]), &contracts);
+        assert_eq!(metadata.initcode_hash, Some(keccak256([0x60, 0x80]).into()));
+
+        assert!(verify_initcode([(&metadata, &metadata.tx)], &BTreeMap::new()).is_ok());
+
+        // the transaction changed after the simulation
+        let broadcast = deployment(vec![0x60, 0x40]);
+        let err = verify_initcode([(&metadata, &broadcast)], &BTreeMap::new()).unwrap_err();
+        assert!(err.to_string().contains("Counter"));
+    }
+
+    #[test]
+    fn can_verify_initcode_against_artifacts() {
+        let abi = Abi::default();
+        let contracts = BTreeMap::from([(Address::zero(), ("Counter".to_string(), &abi))]);
+        // the creation code followed by a constructor argument
+        let mut metadata = TransactionWithMetadata {
+            tx: deployment(vec![0x60, 0x80, 0x01]),
+            ..Default::default()
+        };
+        metadata.set_create(false, Address::zero(), Some(&[0x60, 0x80, 0x01][..]), &contracts);
+
+        let compiled = artifacts("Counter", vec![0x60, 0x80]);
+        assert!(verify_initcode([(&metadata, &metadata.tx)], &compiled).is_ok());
+
+        // the contract was recompiled after the simulation, e.g. before resuming
+        let recompiled = artifacts("Counter", vec![0x60, 0x40]);
+        let err = verify_initcode([(&metadata, &metadata.tx)], &recompiled).unwrap_err();
+        assert!(err.to_string().contains("does not match the compiled artifact"));
+    }
+
+    #[test]
+    fn can_verify_create2_initcode() {
+        let salt = [0u8; 32];
+        let simulated = Bytes::from([&salt[..], &[0x60, 0x80][..]].concat());
+        let mut metadata =
+            TransactionWithMetadata { tx: deployment(simulated.to_vec()), ..Default::default() };
+        metadata.set_create(true, Address::zero(), Some(&[0x60, 0x80][..]), &BTreeMap::new());
+        assert_eq!(metadata.initcode_hash, Some(keccak256([0x60, 0x80]).into()));
+        assert!(verify_initcode([(&metadata, &metadata.tx)], &BTreeMap::new()).is_ok());
+
+        let broadcast = deployment([&salt[..], &[0x60, 0x40][..]].concat());
+        assert!(verify_initcode([(&metadata, &broadcast)], &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn attaches_access_list() {
+        let access_list = AccessList(vec![ethers::types::transaction::eip2930::AccessListItem {
+            address: Address::random(),
+            storage_keys: vec![H256::random()],
+        }]);
+        let mut metadata = TransactionWithMetadata {
+            tx: deployment(vec![0x60, 0x80]),
+            access_list: Some(access_list.clone()),
+            ..Default::default()
+        };
+
+        metadata.change_type(false, false);
+        match metadata.typed_tx() {
+            TypedTransaction::Eip1559(tx) => assert!(tx.access_list.0.is_empty()),
+            tx => panic!("unexpected transaction type {tx:?}"),
+        }
+
+        metadata.change_type(false, true);
+        match metadata.typed_tx() {
+            TypedTransaction::Eip1559(tx) => assert_eq!(tx.access_list, access_list),
+            tx => panic!("unexpected transaction type {tx:?}"),
+        }
+
+        metadata.change_type(true, true);
+        assert!(matches!(metadata.typed_tx(), TypedTransaction::Legacy(_)));
+    }
+}
@@ -102,7 +102,9 @@ impl SnapshotArgs {
 impl Cmd for SnapshotArgs {
     type Output = ();
 
-    fn run(self) -> eyre::Result<()> {
+    fn run(mut self) -> eyre::Result<()> {
+        // the snapshot needs the gas usage of every test
+        self.test.cache_results = false;
         let outcome = custom_run(self.test, self.include_fuzz_tests)?;
         outcome.ensure_ok()?;
         let tests = self.config.apply(outcome);
@@ -0,0 +1,235 @@
+//! Storage layout diff command
+use crate::{
+    cmd::{
+        forge::build::{self, CoreBuildArgs},
+        Cmd,
+    },
+    compile,
+    opts::{forge::CompilerArgs, ClapChain},
+};
+use clap::Parser;
+use ethers::{
+    etherscan::Client,
+    prelude::artifacts::output_selection::ContractOutputSelection,
+    solc::{
+        artifacts::StorageLayout, info::ContractInfo, ConfigurableArtifacts, Project,
+        ProjectCompileOutput, ProjectPathsConfig,
+    },
+    types::Address,
+};
+use eyre::WrapErr;
+use forge::storage_layout::diff_storage_layouts;
+use foundry_common::fs;
+use foundry_config::Config;
+use serde::Deserialize;
+use std::{path::Path, str::FromStr};
+use yansi::Paint;
+
+#[derive(Debug, Clone, Parser)]
+pub struct StorageDiffArgs {
+    #[clap(
+        help = "The old version of the contract.",
+        long_help = "The old version of the contract. Either the identifier of a contract of the project in the form `(<path>:)?<contractname>`, the path to an artifact, or the address of a contract verified on Etherscan.",
+        value_name = "OLD"
+    )]
+    pub old: String,
+
+    #[clap(
+        help = "The new version of the contract, specified like the old version.",
+        value_name = "NEW"
+    )]
+    pub new: String,
+
+    #[clap(flatten)]
+    pub layouts: StorageLayoutArgs,
+}
+
+/// The arguments to resolve the storage layouts of contracts with
+#[derive(Debug, Clone, Parser)]
+pub struct StorageLayoutArgs {
+    #[clap(
+        long,
+        env = "ETHERSCAN_API_KEY",
+        help = "The Etherscan API key, used to fetch the source code of verified contracts.",
+        value_name = "KEY"
+    )]
+    pub etherscan_api_key: Option<String>,
+
+    #[clap(flatten)]
+    pub chain: ClapChain,
+
+    /// All build arguments are supported
+    #[clap(flatten)]
+    pub build: build::CoreBuildArgs,
+}
+
+impl Cmd for StorageDiffArgs {
+    type Output = ();
+    fn run(self) -> eyre::Result<Self::Output> {
+        let old = self.layouts.storage_layout(&self.old)?;
+        let new = self.layouts.storage_layout(&self.new)?;
+        let changes = diff_storage_layouts(&old, &new)?;
+
+        if changes.is_empty() {
+            println!("{}", Paint::green("The storage layouts are identical"));
+            return Ok(())
+        }
+        for change in &changes {
+            if change.is_breaking() {
+                println!("{} {}", Paint::red("[BREAKING]"), change);
+            } else {
+                println!("{} {}", Paint::yellow("[CHANGED]"), change);
+            }
+        }
+
+        let breaking = changes.iter().filter(|change| change.is_breaking()).count();
+        if breaking > 0 {
+            eyre::bail!("Found {} incompatible storage layout changes", breaking)
+        }
+        println!("\n{}", Paint::green("The storage layouts are compatible"));
+        Ok(())
+    }
+}
+
+impl StorageLayoutArgs {
+    /// Returns the storage layout of the contract specified by `contract`, either the identifier
+    /// of a contract of the project, the path to an artifact or the address of a verified contract
+    pub fn storage_layout(&self, contract: &str) -> eyre::Result<StorageLayout> {
+        if let Ok(address) = Address::from_str(contract) {
+            return crate::utils::block_on(self.etherscan_storage_layout(address))
+        }
+        if contract.ends_with(".json") {
+            return artifact_storage_layout(contract)
+        }
+        let contract = ContractInfo::from_str(contract).map_err(|err| eyre::eyre!("{}", err))?;
+        self.project_storage_layout(contract)
+    }
+
+    /// Compiles the project, or only the file of the contract if its path is given, with the
+    /// storage layouts of the contracts.
+    ///
+    /// The path of the contract is canonicalized to find it in the output.
+    pub fn compile(&self, contract: &mut ContractInfo) -> eyre::Result<ProjectCompileOutput> {
+        let mut extra_output = self.build.compiler.extra_output.clone();
+        if !extra_output.contains(&ContractOutputSelection::StorageLayout) {
+            extra_output.push(ContractOutputSelection::StorageLayout);
+        }
+        let build = CoreBuildArgs {
+            compiler: CompilerArgs { extra_output, ..self.build.compiler.clone() },
+            ..self.build.clone()
+        };
+
+        let project = build.project()?;
+        if let Some(ref mut contract_path) = contract.path {
+            let target_path = dunce::canonicalize(&*contract_path)?;
+            *contract_path = target_path.to_string_lossy().into_owned();
+            compile::compile_files(&project, vec![target_path], true)
+        } else {
+            compile::suppress_compile(&project)
+        }
+    }
+
+    /// Compiles the project and returns the storage layout of the contract
+    fn project_storage_layout(&self, mut contract: ContractInfo) -> eyre::Result<StorageLayout> {
+        let outcome = self.compile(&mut contract)?;
+        let artifact = outcome.find_contract(&contract).ok_or_else(|| {
+            eyre::eyre!("Could not find artifact `{contract}` in the compiled artifacts")
+        })?;
+        artifact
+            .storage_layout
+            .clone()
+            .ok_or_else(|| eyre::eyre!("No storage layout for `{}`", contract))
+    }
+
+    /// Fetches the source code of a verified contract from Etherscan and compiles it
+    async fn etherscan_storage_layout(&self, address: Address) -> eyre::Result<StorageLayout> {
+        let api_key = match &self.etherscan_api_key {
+            Some(api_key) => api_key.clone(),
+            None => Config::load().etherscan_api_key.ok_or_else(|| {
+                eyre::eyre!("No Etherscan API Key is set. Consider using the ETHERSCAN_API_KEY env var, or setting --etherscan-api-key or etherscan-api-key in foundry.toml")
+            })?,
+        };
+        let client = Client::new(self.chain.inner, api_key)?;
+        let metadata = client
+            .contract_source_code(address)
+            .await
+            .wrap_err_with(|| format!("Failed to fetch the source code of {:?}", address))?;
+        let item = metadata
+            .items
+            .first()
+            .filter(|item| item.abi != "Contract source code not verified")
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "Contract source code at {:?} on {} not verified",
+                    address,
+                    self.chain.inner
+                )
+            })?;
+
+        // The sources are written to `<dir>/<contract name>/`
+        let dir = tempfile::tempdir()?;
+        metadata.source_tree()?.write_to(dir.path())?;
+        let root = dir.path().join(&item.contract_name);
+        let paths = ProjectPathsConfig::builder().root(&root).sources(&root).build()?;
+        let project = Project::builder()
+            .paths(paths)
+            .artifacts(ConfigurableArtifacts::new(
+                vec![ContractOutputSelection::StorageLayout],
+                vec![],
+            ))
+            .ephemeral()
+            .no_artifacts()
+            .build()?;
+
+        let output = project.compile()?;
+        if output.has_compiler_errors() {
+            eyre::bail!("Failed to compile the source code of {:?}:\n{}", address, output)
+        }
+        let name = &item.contract_name;
+        let artifact = output.find_first(name).ok_or_else(|| {
+            eyre::eyre!("Could not find artifact `{}` in the compiled artifacts", name)
+        })?;
+        artifact
+            .storage_layout
+            .clone()
+            .ok_or_else(|| eyre::eyre!("No storage layout for `{}`", name))
+    }
+}
+
+/// A thin wrapper around an artifact that only extracts the storage layout
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactStorageLayout {
+    storage_layout: Option<StorageLayout>,
+}
+
+/// Reads the storage layout of the artifact at the given path
+fn artifact_storage_layout(path: impl AsRef<Path>) -> eyre::Result<StorageLayout> {
+    let path = path.as_ref();
+    let artifact: ArtifactStorageLayout = serde_json::from_str(&fs::read_to_string(path)?)?;
+    artifact.storage_layout.ok_or_else(|| {
+        eyre::eyre!(
+            "No storage layout in {}, add `storageLayout` to `extra_output`",
+            path.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use forge::storage_layout::StorageLayoutChange;
+
+    #[test]
+    fn can_diff_artifacts() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("../testdata/fixtures");
+        let old = artifact_storage_layout(fixtures.join("StorageLayout/VaultV1.json")).unwrap();
+        let new =
+            artifact_storage_layout(fixtures.join("StorageLayout/VaultV2Broken.json")).unwrap();
+        let changes = diff_storage_layouts(&old, &new).unwrap();
+        assert!(matches!(changes[0], StorageLayoutChange::Collision { .. }));
+        assert!(matches!(changes[1], StorageLayoutChange::Moved { .. }));
+
+        assert!(artifact_storage_layout(fixtures.join("GetCode/WorkingContract.json")).is_err());
+    }
+}
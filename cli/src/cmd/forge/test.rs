@@ -10,29 +10,51 @@ use crate::{
     utils::FoundryPathExt,
 };
 use clap::{AppSettings, Parser};
-use ethers::solc::{utils::RuntimeOrHandle, FileFilter};
+use ethers::{
+    abi::Abi,
+    prelude::{Artifact, ProjectCompileOutput},
+    solc::{utils::RuntimeOrHandle, FileFilter, Graph, ProjectPathsConfig},
+    types::{Bytes, H256, U256},
+    utils::{format_units, keccak256},
+};
 use forge::{
+    abi::HEVM_ABI,
+    chrome_trace::ChromeTrace,
     decode::decode_console_logs,
-    executor::{inspector::CheatsConfig, opts::EvmOpts},
-    gas_report::GasReport,
-    result::{SuiteResult, TestKind, TestResult},
+    executor::{
+        genesis::write_state,
+        inspector::{CheatsConfig, CustomPrecompiles, GasSchedule},
+        opts::EvmOpts,
+    },
+    flamegraph::Flamegraph,
+    fuzz::{random_seed, seeded_rng},
+    gas_report::{GasReport, GasScheduleReport},
+    inline_config::InlineConfig,
+    reference::ReferenceEvm,
+    result::{CompilerSettings, SuiteResult, TestKind, TestResult},
+    state_diff::StorageLayouts,
+    storage_layout::StorageVariable,
     trace::{
-        identifier::{EtherscanIdentifier, LocalTraceIdentifier},
+        identifier::{EtherscanIdentifier, LocalTraceIdentifier, SignaturesIdentifier},
         CallTraceDecoderBuilder, TraceKind,
     },
-    MultiContractRunner, MultiContractRunnerBuilder, TestFilter,
+    MultiContractRunner, MultiContractRunnerBuilder, TestFilter, TestOptions,
 };
-use foundry_common::evm::EvmArgs;
+use foundry_common::{evm::EvmArgs, fs};
 use foundry_config::{figment::Figment, Config};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fmt,
+    fs::File,
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
-    sync::mpsc::channel,
+    sync::{atomic::Ordering, mpsc::channel, Arc},
     thread,
     time::Duration,
 };
+use tokio::sync::RwLock;
 use tracing::trace;
 use watchexec::config::{InitConfig, RuntimeConfig};
 use yansi::Paint;
@@ -99,6 +121,31 @@ pub struct Filter {
         value_name = "GLOB"
     )]
     pub path_pattern_inverse: Option<globset::Glob>,
+
+    /// Only run tests in source files that import a file matching the specified glob pattern,
+    /// directly or transitively.
+    #[clap(
+        long = "match-coverage",
+        visible_alias = "mcov",
+        conflicts_with = "pattern",
+        value_name = "GLOB"
+    )]
+    pub coverage_pattern: Option<globset::Glob>,
+
+    /// Only run tests in the given source files.
+    ///
+    /// Used by watch mode to run the test files affected by a change.
+    #[clap(long = "match-files", multiple_values = true, hide = true, value_name = "FILES")]
+    pub files: Option<Vec<PathBuf>>,
+
+    /// The test files that import a file matching `--match-coverage`, see
+    /// [Filter::resolve_coverage]
+    #[clap(skip)]
+    pub covering_files: Option<BTreeSet<PathBuf>>,
+
+    /// Only run the tests that failed in the previous run, see `--rerun-failed`
+    #[clap(skip)]
+    pub failures: Option<TestFailures>,
 }
 
 impl Filter {
@@ -125,6 +172,71 @@ impl Filter {
         }
         filter
     }
+
+    /// Resolves the test files that import a file matching the `--match-coverage` pattern,
+    /// directly or transitively, using the import graph of the project's sources.
+    ///
+    /// Patterns are matched against both absolute paths and paths relative to the project root.
+    pub fn resolve_coverage(&mut self, paths: &ProjectPathsConfig) -> eyre::Result<()> {
+        let matcher = match &self.coverage_pattern {
+            Some(glob) => glob.compile_matcher(),
+            None => return Ok(()),
+        };
+        let is_covered = |path: &Path| {
+            matcher.is_match(path) ||
+                path.strip_prefix(&paths.root)
+                    .map(|path| matcher.is_match(path))
+                    .unwrap_or(false)
+        };
+
+        let graph = Graph::resolve(paths)?;
+        let covering_files = graph
+            .files()
+            .iter()
+            .filter(|(path, _)| path.is_sol_test())
+            .filter(|(path, index)| {
+                is_covered(path) ||
+                    graph
+                        .all_imported_nodes(**index)
+                        .any(|import| is_covered(graph.node(import).unpack().0))
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.covering_files = Some(covering_files);
+        Ok(())
+    }
+
+    /// Returns `true` if the filter only selects some of the tests
+    pub fn is_filtered(&self) -> bool {
+        self.pattern.is_some() ||
+            self.test_pattern.is_some() ||
+            self.test_pattern_inverse.is_some() ||
+            self.contract_pattern.is_some() ||
+            self.contract_pattern_inverse.is_some() ||
+            self.path_pattern.is_some() ||
+            self.path_pattern_inverse.is_some() ||
+            self.coverage_pattern.is_some() ||
+            self.files.is_some() ||
+            self.failures.is_some()
+    }
+
+    /// Returns `true` if `path` is one of the resolved [Filter::covering_files], or if coverage
+    /// filtering is not enabled
+    fn is_covering(&self, path: &Path) -> bool {
+        match &self.covering_files {
+            Some(files) => files.iter().any(|file| file.ends_with(path)),
+            None => true,
+        }
+    }
+
+    /// Returns `true` if `path` is one of the `--match-files`, which may be relative to the project
+    /// root, or if no files are given
+    fn is_selected_file(&self, path: &Path) -> bool {
+        match &self.files {
+            Some(files) => files.iter().any(|file| path.ends_with(file)),
+            None => true,
+        }
+    }
 }
 
 impl FileFilter for Filter {
@@ -133,6 +245,9 @@ impl FileFilter for Filter {
     /// If no file regex is set this returns true if the file ends with `.t.sol`, see
     /// [FoundryPathExr::is_sol_test()]
     fn is_match(&self, file: &Path) -> bool {
+        if !self.is_covering(file) || !self.is_selected_file(file) {
+            return false
+        }
         if let Some(file) = file.as_os_str().to_str() {
             if let Some(ref glob) = self.path_pattern {
                 return glob.compile_matcher().is_match(file)
@@ -149,6 +264,9 @@ impl TestFilter for Filter {
     fn matches_test(&self, test_name: impl AsRef<str>) -> bool {
         let mut ok = true;
         let test_name = test_name.as_ref();
+        if let Some(failures) = &self.failures {
+            ok &= failures.contains_test(test_name);
+        }
         // Handle the deprecated option match
         if let Some(re) = &self.pattern {
             ok &= re.is_match(test_name);
@@ -165,6 +283,9 @@ impl TestFilter for Filter {
     fn matches_contract(&self, contract_name: impl AsRef<str>) -> bool {
         let mut ok = true;
         let contract_name = contract_name.as_ref();
+        if let Some(failures) = &self.failures {
+            ok &= failures.contains_contract(contract_name);
+        }
         if let Some(re) = &self.contract_pattern {
             ok &= re.is_match(contract_name);
         }
@@ -175,8 +296,8 @@ impl TestFilter for Filter {
     }
 
     fn matches_path(&self, path: impl AsRef<str>) -> bool {
-        let mut ok = true;
         let path = path.as_ref();
+        let mut ok = self.is_covering(Path::new(path)) && self.is_selected_file(Path::new(path));
         if let Some(ref glob) = self.path_pattern {
             ok &= glob.compile_matcher().is_match(path);
         }
@@ -211,6 +332,15 @@ impl fmt::Display for Filter {
         if let Some(ref p) = self.path_pattern_inverse {
             patterns.push(format!("\tno-match-path: `{}`", p.glob()));
         }
+        if let Some(ref p) = self.coverage_pattern {
+            patterns.push(format!("\tmatch-coverage: `{}`", p.glob()));
+        }
+        if let Some(ref files) = self.files {
+            patterns.push(format!("\tmatch-files: {} files", files.len()));
+        }
+        if let Some(ref failures) = self.failures {
+            patterns.push(format!("\trerun-failed: {} tests", failures.len()));
+        }
         write!(f, "{}", patterns.join("\n"))
     }
 }
@@ -246,10 +376,112 @@ pub struct TestArgs {
     #[clap(long, env = "FORGE_GAS_REPORT")]
     gas_report: bool,
 
+    /// Write a flamegraph of the gas used by the call paths of all tests.
+    ///
+    /// The flamegraph is written to `flamegraph.svg` in the cache directory, along with the
+    /// underlying stacks in the folded stack format in `flamegraph.folded`.
+    #[clap(long)]
+    flamegraph: bool,
+
+    /// Write a timeline of the calls of all tests in the Chrome trace event format.
+    ///
+    /// The timeline is written to `chrome-trace.json` in the cache directory and can be viewed in
+    /// `chrome://tracing` or Perfetto. Every test is shown as a thread and every call as a slice
+    /// whose duration is its gas usage.
+    #[clap(long)]
+    chrome_trace: bool,
+
+    /// Check standard tests against a reference EVM implementation.
+    ///
+    /// Takes the command of an EVM implementing the `t8n` interface, e.g. `evm t8n`. Every test is
+    /// executed by the reference EVM on top of the state after `setUp`, and fails if the status,
+    /// gas used or resulting state differ. Tests on forks or using cheatcodes are skipped.
+    #[clap(long, value_name = "COMMAND")]
+    reference_evm: Option<String>,
+
+    /// Write the state after every standard test to a directory.
+    ///
+    /// The state of every test is written to `<DIR>/<CONTRACT>/<TEST>.json` in the state format of
+    /// anvil, so it can be loaded with `anvil --load-state` or `vm.loadAllocs`.
+    #[clap(long, value_name = "DIR")]
+    dump_state: Option<PathBuf>,
+
+    /// Show the changes every standard test made to the state.
+    ///
+    /// Shows the balance, nonce, code and storage changes of every account, and includes them in
+    /// the `--json` output. Storage slots are named after the variables of the contract, if the
+    /// storage layout is part of the `extra_output`.
+    #[clap(long)]
+    show_state_diff: bool,
+
+    /// Report the gas usage of tests and functions under a hypothetical gas schedule.
+    ///
+    /// Takes a comma separated list of `<NAME>=<COST>` overrides, where `NAME` is an opcode (e.g.
+    /// `SLOAD=800`), `calldata_zero_byte` or `calldata_byte`. An opcode override replaces the
+    /// entire cost of the opcode, including dynamic costs. Fuzz tests are not part of the report.
+    #[clap(long, value_name = "OVERRIDES")]
+    gas_schedule: Option<GasSchedule>,
+
+    /// Run the tests once for every fee scenario of the config.
+    ///
+    /// Every scenario of `fee_scenarios` sets the gas price, base fee and block number the tests
+    /// run with, the results are reported per scenario.
+    #[clap(long, conflicts_with_all = &["debug", "list"])]
+    fee_scenarios: bool,
+
     /// Exit with code 0 even if a test fails.
     #[clap(long, env = "FORGE_ALLOW_FAILURE")]
     allow_failure: bool,
 
+    /// Stop running tests after the first failure.
+    ///
+    /// Tests that already started are completed, all remaining tests are skipped.
+    #[clap(long)]
+    fail_fast: bool,
+
+    /// Execute every call made by the body of a test as a transaction of its own.
+    ///
+    /// Every such call has its own gas accounting, including the intrinsic gas and refunds of a
+    /// transaction, on top of the state left by the calls before it. Tests can be isolated
+    /// individually with the `forge-config: isolate = true` natspec comment.
+    #[clap(long)]
+    isolate: bool,
+
+    /// Re-run only the tests that failed in the previous run.
+    #[clap(long)]
+    rerun_failed: bool,
+
+    /// Skip the test contracts whose tests all passed with the same code and configuration.
+    ///
+    /// Contracts with fuzz tests, unless the fuzz seed is pinned, and contracts that call
+    /// cheatcodes reading the environment, files or the network are always run. These cheatcodes
+    /// are detected by their selectors in the code of the contract and its libraries. The results
+    /// are never cached for reports, e.g. `--gas-report`, or with `--force`.
+    #[clap(long)]
+    pub(crate) cache_results: bool,
+
+    /// The seed of the fuzzer and the `random*` cheatcodes.
+    ///
+    /// A random seed is used for every run if not set. The seed is printed when a test fails, so
+    /// the run can be replayed with the same fuzz inputs and random values.
+    #[clap(long, parse(try_from_str = utils::parse_u256), value_name = "SEED")]
+    fuzz_seed: Option<U256>,
+
+    /// Run the tests from the cached artifacts without compiling.
+    ///
+    /// Fails with a list of the stale sources if any source or one of its imports changed since
+    /// the artifacts were compiled.
+    #[clap(long)]
+    no_build: bool,
+
+    /// Compile only the sources affected by the changes since the last build.
+    ///
+    /// Only the changed sources and the sources importing them are compiled, and of these only the
+    /// test files that match the filters. The artifacts of all other sources are read from the
+    /// cache.
+    #[clap(long, conflicts_with = "no-build")]
+    incremental: bool,
+
     /// Output test results in JSON format.
     #[clap(long, short, help_heading = "DISPLAY OPTIONS")]
     json: bool,
@@ -274,6 +506,13 @@ pub struct TestArgs {
     /// List tests instead of running them
     #[clap(long, short, help_heading = "DISPLAY OPTIONS")]
     list: bool,
+
+    /// The maximum number of lines of logs and traces to print for a single test.
+    ///
+    /// Output exceeding the limit is truncated. The full output of all tests is written to
+    /// `test-output.log` in the cache directory.
+    #[clap(long, help_heading = "DISPLAY OPTIONS", value_name = "LINES")]
+    output_limit: Option<usize>,
 }
 
 impl TestArgs {
@@ -291,13 +530,19 @@ impl TestArgs {
     pub fn config_and_evm_opts(&self) -> eyre::Result<(Config, EvmOpts)> {
         // merge all configs
         let figment: Figment = self.into();
-        let evm_opts = figment.extract()?;
+        let mut evm_opts: EvmOpts = figment.extract()?;
         let mut config = Config::from_provider(figment).sanitized();
 
         // merging etherscan api key into Config
         if let Some(etherscan_api_key) = &self.etherscan_api_key {
             config.etherscan_api_key = Some(etherscan_api_key.to_string());
         }
+        if let Some(fuzz_seed) = self.fuzz_seed {
+            config.fuzz_seed = Some(fuzz_seed);
+        }
+
+        // `--fork-url` can be an alias of the `rpc_endpoints`
+        evm_opts.resolve_fork_url(&config)?;
         Ok((config, evm_opts))
     }
 
@@ -359,11 +604,13 @@ pub struct TestOutcome {
     pub allow_failure: bool,
     /// Results for each suite of tests `contract -> SuiteResult`
     pub results: BTreeMap<String, SuiteResult>,
+    /// Whether the run was cancelled, in which case the results are partial
+    pub cancelled: bool,
 }
 
 impl TestOutcome {
     fn new(results: BTreeMap<String, SuiteResult>, allow_failure: bool) -> Self {
-        Self { results, allow_failure }
+        Self { results, allow_failure, cancelled: false }
     }
 
     /// Iterator over all succeeding tests and their names
@@ -412,9 +659,17 @@ impl TestOutcome {
                 std::process::exit(1);
             }
         }
+        if self.cancelled {
+            std::process::exit(130);
+        }
         Ok(())
     }
 
+    /// Returns `true` if the run was cancelled because a test failed with `--fail-fast`
+    fn failed_fast(&self, fail_fast: bool) -> bool {
+        fail_fast && self.failures().next().is_some()
+    }
+
     pub fn duration(&self) -> Duration {
         self.results
             .values()
@@ -434,6 +689,281 @@ impl TestOutcome {
     }
 }
 
+/// The tests that failed in a run, persisted in the cache directory so they can be re-run with
+/// `--rerun-failed`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestFailures {
+    /// The signatures of the failing tests, keyed by the identifiers of their test contracts
+    pub tests: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl TestFailures {
+    /// Collects the failing tests of the `outcome`
+    pub fn new(outcome: &TestOutcome) -> Self {
+        let mut failures = Self::default();
+        failures.update(outcome);
+        failures
+    }
+
+    /// Updates the failures with the tests that ran in the `outcome`, keeping the failures of the
+    /// tests that did not run
+    pub fn update(&mut self, outcome: &TestOutcome) {
+        for (id, SuiteResult { test_results, .. }) in &outcome.results {
+            let failed = self.tests.entry(id.clone()).or_default();
+            for (signature, result) in test_results {
+                if result.success {
+                    failed.remove(signature);
+                } else {
+                    failed.insert(signature.clone());
+                }
+            }
+        }
+        self.tests.retain(|_, failed| !failed.is_empty());
+    }
+
+    /// Returns the path of the failures file of the project
+    pub fn path(config: &Config) -> PathBuf {
+        config.cache_path.join("test-failures.json")
+    }
+
+    /// Reads the failures of the previous run, if any
+    pub fn read(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the failures to the given file
+    pub fn write(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the number of failing tests
+    pub fn len(&self) -> usize {
+        self.tests.values().map(BTreeSet::len).sum()
+    }
+
+    /// Returns `true` if no test failed
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+
+    /// Whether a test of a contract with the given name failed
+    pub fn contains_contract(&self, name: &str) -> bool {
+        self.tests.keys().any(|id| utils::get_contract_name(id) == name)
+    }
+
+    /// Whether a test with the given signature or function name failed in any contract.
+    ///
+    /// Which test failed in which contract is matched by [MultiContractRunner::selected_tests].
+    pub fn contains_test(&self, test: &str) -> bool {
+        self.tests.values().flatten().any(|sig| sig == test || sig.split('(').next() == Some(test))
+    }
+}
+
+/// The tests that passed in previous runs, persisted in the cache directory so unchanged test
+/// contracts are not run again
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestResultCache {
+    /// The cached results of every test contract, keyed by its identifier
+    pub suites: BTreeMap<String, CachedSuite>,
+}
+
+/// The cached results of a single test contract
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedSuite {
+    /// The hash of the code of the test contract, its libraries and the configuration it ran with
+    pub key: H256,
+    /// The signatures of the tests that passed
+    pub passed: BTreeSet<String>,
+}
+
+impl TestResultCache {
+    /// Returns the path of the test result cache of the project
+    pub fn path(config: &Config) -> PathBuf {
+        config.cache_path.join("test-results.json")
+    }
+
+    /// Reads the cached results, if any
+    pub fn read(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the cached results to the given file
+    pub fn write(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the cache keys of the test contracts of the `runner`.
+    ///
+    /// The keys cover the linked code of the contracts and the entire configuration, so any change
+    /// to either invalidates the cached results. Returns `None` if the results cannot be cached
+    /// because the tests run on a fork that is not pinned to a block, whose state may change.
+    ///
+    /// Test contracts whose results don't only depend on their code and the configuration get no
+    /// key and are always run, see [`is_cacheable`].
+    pub fn keys(
+        runner: &MultiContractRunner,
+        config: &Config,
+        verbosity: u8,
+    ) -> eyre::Result<Option<BTreeMap<String, H256>>> {
+        if runner.evm_opts.fork_url.is_some() && runner.evm_opts.fork_block_number.is_none() {
+            return Ok(None)
+        }
+
+        let mut settings = serde_json::to_vec(config)?;
+        settings.extend(serde_json::to_vec(&runner.evm_opts)?);
+        settings.push(verbosity);
+        // tests may fail only when their calls are isolated
+        settings.push(runner.isolate as u8);
+        let code_hashes = runner.code_hashes();
+        let keys = runner
+            .contracts
+            .iter()
+            .filter(|(_, (abi, code, libs))| is_cacheable(abi, code, libs, config))
+            .filter_map(|(id, _)| {
+                let identifier = id.identifier();
+                let hash = code_hashes.get(&identifier)?;
+                let key = keccak256([hash.as_bytes(), &settings].concat());
+                Some((identifier, H256::from(key)))
+            })
+            .collect();
+        Ok(Some(keys))
+    }
+
+    /// Returns the test contracts whose tests matching the `filter` all passed with the same key,
+    /// along with the signatures of these tests
+    pub fn cached_suites(
+        &self,
+        runner: &MultiContractRunner,
+        filter: &Filter,
+        keys: &BTreeMap<String, H256>,
+    ) -> BTreeMap<String, Vec<String>> {
+        runner
+            .contracts
+            .iter()
+            .filter(|(id, _)| {
+                filter.matches_path(id.source.to_string_lossy()) &&
+                    filter.matches_contract(&id.name)
+            })
+            .filter_map(|(id, (abi, _, _))| {
+                let identifier = id.identifier();
+                let suite = self.suites.get(&identifier)?;
+                if Some(&suite.key) != keys.get(&identifier) {
+                    return None
+                }
+                let tests = abi
+                    .functions()
+                    .filter(|func| func.name.starts_with("test"))
+                    .map(|func| func.signature())
+                    .filter(|signature| filter.matches_test(signature))
+                    .collect::<Vec<_>>();
+                (!tests.is_empty() && tests.iter().all(|test| suite.passed.contains(test)))
+                    .then_some((identifier, tests))
+            })
+            .collect()
+    }
+
+    /// Updates the cached results with the results of the `outcome`
+    pub fn update(&mut self, outcome: &TestOutcome, keys: &BTreeMap<String, H256>) {
+        for (id, SuiteResult { test_results, .. }) in &outcome.results {
+            let key = match keys.get(id) {
+                Some(key) => *key,
+                None => continue,
+            };
+            let suite = self.suites.entry(id.clone()).or_default();
+            if suite.key != key {
+                *suite = CachedSuite { key, passed: BTreeSet::new() };
+            }
+            for (signature, result) in test_results {
+                if result.success {
+                    suite.passed.insert(signature.clone());
+                } else {
+                    suite.passed.remove(signature);
+                }
+            }
+        }
+    }
+}
+
+/// The cheatcodes whose results depend on the environment, files, processes or the network
+/// rather than on the code of the test contract and the configuration
+const EXTERNAL_CHEATCODES: [&str; 26] = [
+    "envAddress",
+    "envBool",
+    "envBytes",
+    "envBytes32",
+    "envInt",
+    "envString",
+    "envUint",
+    "ffi",
+    "getCode",
+    "deployCode",
+    "openFile",
+    "readFile",
+    "readLine",
+    "writeFile",
+    "writeLine",
+    "writeJson",
+    "removeFile",
+    "closeFile",
+    "waitForFile",
+    "waitForHttp",
+    "waitForStorage",
+    "sleep",
+    "createFork",
+    "createSelectFork",
+    "rpcUrl",
+    "rpcUrls",
+];
+
+/// The cheatcodes that return random values, unless the fuzz seed is pinned
+const RANDOM_CHEATCODES: [&str; 3] = ["randomUint", "randomAddress", "randomBytes"];
+
+/// Returns `true` if the results of the tests of a contract only depend on its linked code and
+/// the configuration, so they can be cached.
+///
+/// This is not the case for fuzz tests and random values without a pinned `fuzz_seed`, and for
+/// contracts that call cheatcodes that read the environment, the file system or the network, or
+/// run commands. Calls to cheatcodes are detected by a `PUSH4` of their selector in the code.
+fn is_cacheable(abi: &Abi, code: &[u8], libs: &[Bytes], config: &Config) -> bool {
+    let random = config.fuzz_seed.is_none();
+    if random &&
+        abi.functions().any(|func| func.name.starts_with("test") && !func.inputs.is_empty())
+    {
+        return false
+    }
+
+    let selectors = HEVM_ABI
+        .functions()
+        .filter(|func| {
+            EXTERNAL_CHEATCODES.contains(&func.name.as_str()) ||
+                (random && RANDOM_CHEATCODES.contains(&func.name.as_str()))
+        })
+        .map(|func| func.short_signature())
+        .collect::<Vec<_>>();
+    !std::iter::once(code).chain(libs.iter().map(|lib| lib.as_ref())).any(|code| {
+        code.windows(5)
+            .any(|window| window[0] == 0x63 && selectors.iter().any(|sel| window[1..] == sel[..]))
+    })
+}
+
 fn short_test_result(name: &str, result: &TestResult) {
     let status = if result.success {
         Paint::green("[PASS]".to_string())
@@ -470,39 +1000,132 @@ pub fn custom_run(args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<Test
         max_global_rejects: config.fuzz_max_global_rejects,
         ..Default::default()
     };
-    let fuzzer = proptest::test_runner::TestRunner::new(cfg);
+    // The seed is not part of the config if it's random, so it doesn't invalidate the cached
+    // results of the suites without fuzz tests
+    let seed = config.fuzz_seed.unwrap_or_else(random_seed);
+    let fuzzer = proptest::test_runner::TestRunner::new_with_rng(cfg, seeded_rng(seed));
     let mut filter = args.filter(&config);
+    let failures_path = TestFailures::path(&config);
+    if args.rerun_failed {
+        filter.failures = Some(TestFailures::read(&failures_path)?);
+    }
 
     // Set up the project
     let project = config.project()?;
+    filter.resolve_coverage(&project.paths)?;
     let compiler = ProjectCompiler::default();
-    let output = if config.sparse_mode {
-        compiler.compile_sparse(&project, filter.clone())
+    // The Vyper sources are compiled by vyper, their artifacts and the precompiled artifacts are
+    // added to the ones of solc
+    let mut extra_artifacts = if args.no_build {
+        compile::vyper::read_artifacts(&project)?
+    } else {
+        compile::vyper::compile(&project, args.opts.silent)?
+    };
+    extra_artifacts.extend(compile::raw::ingest(&project, &config.raw_artifacts)?);
+    // With `--no-build` the tests are run from the cached artifacts, which must be up to date, and
+    // with compiler overrides from the artifacts of all compilation jobs
+    let mut inline_config = InlineConfig::default();
+    let (output, cached_artifacts) = if args.no_build {
+        let artifacts = compile::read_cached_artifacts(&project)?;
+        inline_config = InlineConfig::new(
+            &project.paths.root,
+            artifacts.iter().filter_map(|(_, a)| a.ast.as_ref()),
+        )?;
+        let artifacts = artifacts
+            .into_iter()
+            .map(|(id, artifact)| (id, artifact.into_contract_bytecode()))
+            .collect();
+        (None, Some(artifacts))
+    } else if args.incremental {
+        (Some(compile::compile_affected(&project, filter.clone())?), None)
+    } else if !config.compiler_overrides.is_empty() {
+        let (output, artifacts) = compiler.compile_with_overrides(&config, &project)?;
+        (Some(output), Some(artifacts))
+    } else if config.sparse_mode {
+        (Some(compiler.compile_sparse(&project, filter.clone())?), None)
     } else if args.opts.silent {
-        compile::suppress_compile(&project)
+        (Some(compile::suppress_compile(&project)?), None)
     } else {
-        compiler.compile(&project)
-    }?;
+        (Some(compiler.compile(&project)?), None)
+    };
+
+    // Read the settings of single tests from the natspec comments of the compiled sources
+    if let Some(output) = &output {
+        inline_config = InlineConfig::new(
+            &project.paths.root,
+            output.artifacts().filter_map(|(_, artifact)| artifact.ast.as_ref()),
+        )?;
+    }
+
+    // Collect the storage layouts to name the storage slots of the state diffs with
+    let storage_layouts = match &output {
+        Some(output) if args.show_state_diff => storage_layouts(output)?,
+        _ => StorageLayouts::default(),
+    };
 
     // Determine print verbosity and executor verbosity
     let verbosity = evm_opts.verbosity;
-    if args.gas_report && evm_opts.verbosity < 3 {
+    if (args.gas_report || args.flamegraph || args.chrome_trace) && evm_opts.verbosity < 3 {
         evm_opts.verbosity = 3;
     }
 
     let env = evm_opts.evm_env_blocking();
+    if evm_opts.fork_url.is_some() && evm_opts.fork_block_number.is_none() {
+        // pin the fork to the selected block, so it is reported and every test forks the same block
+        evm_opts.fork_block_number = Some(env.block.number.as_u64());
+    }
 
     // Prepare the test builder
     let evm_spec = utils::evm_spec(&config.evm_version);
 
-    let mut runner = MultiContractRunnerBuilder::default()
+    let builder = MultiContractRunnerBuilder::default()
         .fuzzer(fuzzer)
         .initial_balance(evm_opts.initial_balance)
         .evm_spec(evm_spec)
         .sender(evm_opts.sender)
         .with_fork(evm_opts.get_fork(&config, env.clone()))
-        .with_cheats_config(CheatsConfig::new(&config, &evm_opts))
-        .build(project.paths.root, output, env, evm_opts)?;
+        .with_cheats_config(CheatsConfig {
+            seed: Some(seed),
+            ..CheatsConfig::new(&config, &evm_opts)
+        })
+        .with_test_options(TestOptions { inline_config, ..TestOptions::from(&config) })
+        .with_compiler_settings(CompilerSettings::from(&config))
+        .with_source_graph(&Graph::resolve(&project.paths)?)
+        .with_libraries(config.parsed_libraries()?)
+        .with_precompiles(CustomPrecompiles::from_commands(&config.__root.0, &config.precompiles))
+        .set_gas_schedule(args.gas_schedule.clone())
+        .with_reference_evm(args.reference_evm.as_deref().map(ReferenceEvm::new))
+        .set_dump_state(args.dump_state.is_some())
+        .set_state_diff(args.show_state_diff)
+        .set_fail_fast(args.fail_fast)
+        .set_isolation(args.isolate)
+        // at verbosity level 3 only the traces of failing tests are displayed, so the tests run
+        // without tracing and only the failing tests are re-run with tracing
+        .set_trace_failures(
+            verbosity == 3 && !(args.gas_report || args.flamegraph || args.chrome_trace),
+        )
+        .with_cancellation(utils::cancel_on_ctrl_c());
+    let mut runner = match (output, cached_artifacts) {
+        (Some(output), None) if extra_artifacts.is_empty() => {
+            builder.build(project.paths.root, output, env, evm_opts)?
+        }
+        (output, artifacts) => {
+            let mut artifacts = match (output, artifacts) {
+                (_, Some(artifacts)) => artifacts,
+                (Some(output), None) => {
+                    compile::relative_artifacts(output, &project.paths.root).collect()
+                }
+                (None, None) => Vec::new(),
+            };
+            artifacts.extend(extra_artifacts);
+            builder.build_with_artifacts(project.paths.root, artifacts, env, evm_opts)?
+        }
+    };
+
+    // Only the failed tests of the contracts they failed in are re-run
+    if let Some(failures) = &filter.failures {
+        runner.selected_tests = Some(failures.tests.clone());
+    }
 
     if args.debug.is_some() {
         filter.test_pattern = args.debug;
@@ -552,8 +1175,44 @@ pub fn custom_run(args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<Test
             }
     } else if args.list {
         list(runner, filter, args.json)
+    } else if args.fee_scenarios {
+        run_fee_scenarios(
+            &config,
+            runner,
+            filter,
+            args.json,
+            args.allow_failure,
+            include_fuzz_tests,
+        )
     } else {
-        test(
+        // Skip the test contracts whose results are cached if enabled, unless the results are
+        // needed for a report or the cache is bypassed with `--force`
+        let results_path = TestResultCache::path(&config);
+        let mut results_cache = TestResultCache::read(&results_path)?;
+        let uses_results = args.gas_report ||
+            args.flamegraph ||
+            args.chrome_trace ||
+            args.dump_state.is_some() ||
+            args.show_state_diff ||
+            args.gas_schedule.is_some() ||
+            args.reference_evm.is_some();
+        let cache_keys = if !args.cache_results || args.opts.force || uses_results {
+            None
+        } else {
+            TestResultCache::keys(&runner, &config, verbosity)?
+        };
+        let cached = match &cache_keys {
+            Some(keys) => results_cache.cached_suites(&runner, &filter, keys),
+            None => BTreeMap::new(),
+        };
+        runner.skipped_suites = cached.keys().cloned().collect();
+        let skipped = cached.len();
+        if !args.json {
+            print_cached_suites(&cached);
+        }
+        let filtered = filter.is_filtered();
+
+        let outcome = test(
             config,
             runner,
             verbosity,
@@ -562,7 +1221,162 @@ pub fn custom_run(args: TestArgs, include_fuzz_tests: bool) -> eyre::Result<Test
             args.allow_failure,
             include_fuzz_tests,
             args.gas_report,
-        )
+            args.flamegraph,
+            args.chrome_trace,
+            &storage_layouts,
+            args.output_limit,
+        )?;
+
+        if let Some(dir) = &args.dump_state {
+            let count = dump_states(dir, &outcome)?;
+            if !args.json {
+                println!("\nWrote the state of {} tests to {}", count, dir.display());
+            }
+        }
+
+        // Persist the failures so they can be re-run with `--rerun-failed`, a filtered run only
+        // updates the failures of the tests that ran
+        let failures = if filtered {
+            let mut failures = TestFailures::read(&failures_path)?;
+            failures.update(&outcome);
+            failures
+        } else {
+            TestFailures::new(&outcome)
+        };
+        failures.write(&failures_path)?;
+
+        if let Some(keys) = &cache_keys {
+            results_cache.update(&outcome, keys);
+            results_cache.write(&results_path)?;
+        }
+        if outcome.failures().next().is_some() && !args.json {
+            println!(
+                "\nFuzz seed: {seed:#x}, run with `--fuzz-seed {seed:#x}` to replay the fuzz inputs and random values"
+            );
+        }
+        if skipped > 0 && !args.json {
+            println!(
+                "\n{} Skipped {} test suites whose tests passed with the same code and configuration, run with `--force` to run them anyway",
+                Paint::yellow("Note:").bold(),
+                skipped
+            );
+        }
+
+        Ok(outcome)
+    }
+}
+
+/// Prints the tests of the test contracts whose results are cached
+fn print_cached_suites(cached: &BTreeMap<String, Vec<String>>) {
+    for (contract_name, tests) in cached {
+        let term = if tests.len() > 1 { "tests" } else { "test" };
+        println!("\nCached {} {} for {}", tests.len(), term, contract_name);
+        for test in tests {
+            println!("{} {}", Paint::cyan("[CACHED]"), test);
+        }
+    }
+}
+
+/// Returns the storage layouts of the compiled contracts that have one
+fn storage_layouts(output: &ProjectCompileOutput) -> eyre::Result<StorageLayouts> {
+    let mut layouts = Vec::new();
+    for (name, artifact) in output.artifacts() {
+        let code = artifact.get_deployed_bytecode_bytes();
+        if let (Some(layout), Some(code)) = (&artifact.storage_layout, code) {
+            layouts.push((code.to_vec(), name, StorageVariable::from_layout(layout)?));
+        }
+    }
+    Ok(StorageLayouts::new(layouts))
+}
+
+/// Writes the state of every test that recorded one to `<dir>/<contract>/<test>.json`, returning
+/// the number of written states
+fn dump_states(dir: &Path, outcome: &TestOutcome) -> eyre::Result<usize> {
+    let mut count = 0;
+    for (id, suite) in &outcome.results {
+        let contract_dir = dir.join(utils::get_contract_name(id));
+        for (signature, result) in &suite.test_results {
+            if let Some(state) = &result.state {
+                fs::create_dir_all(&contract_dir)?;
+                let name = signature.split('(').next().unwrap_or(signature);
+                write_state(contract_dir.join(format!("{name}.json")), state)?;
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+/// Runs the tests once for every fee scenario of the config and reports the results per scenario.
+///
+/// The suites of the returned outcome are keyed by `[<scenario>] <contract>`.
+fn run_fee_scenarios(
+    config: &Config,
+    mut runner: MultiContractRunner,
+    filter: Filter,
+    json: bool,
+    allow_failure: bool,
+    include_fuzz_tests: bool,
+) -> eyre::Result<TestOutcome> {
+    if config.fee_scenarios.is_empty() {
+        eyre::bail!("No fee scenarios are configured, add them to `fee_scenarios` in foundry.toml")
+    }
+
+    let env = runner.env.clone();
+    let fork = runner.fork.clone();
+    let mut scenario_results = BTreeMap::new();
+    for (name, scenario) in &config.fee_scenarios {
+        runner.env = env.clone();
+        if let Some(gas_price) = scenario.gas_price {
+            runner.env.tx.gas_price = gas_price.into();
+        }
+        if let Some(base_fee) = scenario.block_base_fee_per_gas {
+            runner.env.block.basefee = base_fee.into();
+        }
+        if let Some(number) = scenario.block_number {
+            runner.env.block.number = number.into();
+        }
+        // every run consumes the fork
+        runner.fork = fork.clone();
+
+        let outcome =
+            TestOutcome::new(runner.test(&filter, None, include_fuzz_tests)?, allow_failure);
+        if !json {
+            println!(
+                "\nScenario {} (gas price {} gwei, base fee {} gwei, block {}): {} passed; {} failed",
+                Paint::cyan(name),
+                format_gwei(runner.env.tx.gas_price),
+                format_gwei(runner.env.block.basefee),
+                runner.env.block.number,
+                Paint::green(outcome.successes().count()),
+                Paint::red(outcome.failures().count()),
+            );
+            for (signature, result) in outcome.failures() {
+                short_test_result(signature, result);
+            }
+        }
+        scenario_results.insert(name.clone(), outcome.results);
+    }
+
+    if json {
+        println!("{}", serde_json::to_string(&scenario_results)?);
+    }
+    let results = scenario_results
+        .into_iter()
+        .flat_map(|(name, results)| {
+            results.into_iter().map(move |(id, suite)| (format!("[{name}] {id}"), suite))
+        })
+        .collect();
+    Ok(TestOutcome::new(results, allow_failure))
+}
+
+/// Formats an amount of wei as gwei, without trailing zeros
+fn format_gwei(wei: U256) -> String {
+    let gwei = format_units(wei, 9).unwrap_or_else(|_| wei.to_string());
+    if gwei.contains('.') {
+        gwei.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        gwei
     }
 }
 
@@ -595,6 +1409,10 @@ fn test(
     allow_failure: bool,
     include_fuzz_tests: bool,
     gas_reporting: bool,
+    flamegraph: bool,
+    chrome_trace: bool,
+    storage_layouts: &StorageLayouts,
+    output_limit: Option<usize>,
 ) -> eyre::Result<TestOutcome> {
     trace!(target: "forge::test", "running all tests");
     if runner.count_filtered_tests(&filter) == 0 {
@@ -617,11 +1435,21 @@ fn test(
         }
     }
 
+    let cancelled = runner.cancelled.clone();
+    let fail_fast = runner.fail_fast;
     if json {
         let results = runner.test(&filter, None, include_fuzz_tests)?;
         println!("{}", serde_json::to_string(&results)?);
-        Ok(TestOutcome::new(results, allow_failure))
+        let mut outcome = TestOutcome::new(results, allow_failure);
+        outcome.cancelled = cancelled.load(Ordering::Relaxed) && !outcome.failed_fast(fail_fast);
+        Ok(outcome)
     } else {
+        if let Some(block) =
+            runner.evm_opts.fork_url.as_ref().and(runner.evm_opts.fork_block_number)
+        {
+            println!("Forking at block {block}");
+        }
+
         // Set up identifiers
         let local_identifier = LocalTraceIdentifier::new(&runner.known_contracts);
         let remote_chain_id = runner.evm_opts.get_remote_chain_id();
@@ -634,6 +1462,18 @@ fn test(
             remote_chain_id.and_then(Config::foundry_etherscan_chain_cache_dir),
             cache_ttl,
         );
+        // Name the calls and events of unknown contracts with the selector databases, sharing the
+        // cache of the selectors between all tests
+        let signatures_identifier = if config.offline {
+            None
+        } else {
+            Some(Arc::new(RwLock::new(SignaturesIdentifier::new(Config::foundry_cache_dir())?)))
+        };
+
+        // Set up the report of gas differences under the overridden gas schedule
+        let mut gas_schedule_report = runner.gas_schedule.clone().map(|schedule| {
+            GasScheduleReport::new(schedule, runner.known_contracts.values().map(|(abi, _)| abi))
+        });
 
         // Set up test reporter channel
         let (tx, rx) = channel::<(String, SuiteResult)>();
@@ -644,6 +1484,12 @@ fn test(
 
         let mut results: BTreeMap<String, SuiteResult> = BTreeMap::new();
         let mut gas_report = GasReport::new(config.gas_reports);
+        let mut gas_flamegraph = flamegraph.then(Flamegraph::default);
+        let mut call_timeline = chrome_trace.then(ChromeTrace::default);
+        let mut output = TestOutputWriter::new(
+            output_limit,
+            output_limit.map(|_| config.cache_path.join("test-output.log")),
+        )?;
         for (contract_name, suite_result) in rx {
             let mut tests = suite_result.test_results.clone();
             println!();
@@ -657,15 +1503,28 @@ fn test(
             for (name, result) in &mut tests {
                 short_test_result(name, result);
 
+                if let Some(report) = &mut gas_schedule_report {
+                    let contract = utils::get_contract_name(&contract_name);
+                    report.add(format!("{contract}:{name}"), result);
+                }
+
+                // We only display the fuzz input distribution at level 2 and above
+                if verbosity >= 2 {
+                    if let Some(stats) = &result.fuzz_stats {
+                        let lines = stats.to_string();
+                        let lines =
+                            lines.lines().map(|line| format!("  {line}")).collect::<Vec<_>>();
+                        output.write(&contract_name, name, "Fuzz stats:", lines.into_iter())?;
+                    }
+                }
+
                 // We only display logs at level 2 and above
                 if verbosity >= 2 {
                     // We only decode logs from Hardhat and DS-style console events
                     let console_logs = decode_console_logs(&result.logs);
                     if !console_logs.is_empty() {
-                        println!("Logs:");
-                        for log in console_logs {
-                            println!("  {log}");
-                        }
+                        let lines = console_logs.into_iter().map(|log| format!("  {log}"));
+                        output.write(&contract_name, name, "Logs:", lines)?;
                         println!();
                     }
                 }
@@ -675,6 +1534,7 @@ fn test(
                     let mut decoder = CallTraceDecoderBuilder::new()
                         .with_labels(result.labeled_addresses.clone())
                         .with_events(local_identifier.events())
+                        .with_signature_identifier(signatures_identifier.clone())
                         .build();
 
                     // Decode the traces
@@ -698,9 +1558,9 @@ fn test(
                             _ => false,
                         };
 
-                        // We decode the trace if we either need to build a gas report or we need
-                        // to print it
-                        if should_include || gas_reporting {
+                        // We decode the trace if we either need to build a gas report,
+                        // flamegraph or timeline, or we need to print it
+                        if should_include || gas_reporting || flamegraph || chrome_trace {
                             rt.block_on(decoder.decode(trace));
                         }
 
@@ -710,13 +1570,32 @@ fn test(
                     }
 
                     if !decoded_traces.is_empty() {
-                        println!("Traces:");
-                        decoded_traces.into_iter().for_each(|trace| println!("{trace}"));
+                        let lines = decoded_traces.iter().flat_map(|trace| {
+                            trace.lines().map(str::to_string).chain(std::iter::once(String::new()))
+                        });
+                        output.write(&contract_name, name, "Traces:", lines)?;
                     }
 
                     if gas_reporting {
                         gas_report.analyze(&result.traces);
                     }
+
+                    if let Some(gas_flamegraph) = &mut gas_flamegraph {
+                        gas_flamegraph.add(&result.traces);
+                    }
+
+                    if let Some(call_timeline) = &mut call_timeline {
+                        let contract = utils::get_contract_name(&contract_name);
+                        call_timeline.add(&format!("{contract}::{name}"), &result.traces);
+                    }
+                }
+
+                if let Some(state_diff) = result.state_diff.as_ref().filter(|diff| !diff.is_empty())
+                {
+                    let diff = state_diff.render(&result.labeled_addresses, storage_layouts);
+                    let lines = diff.lines().map(|line| format!("  {line}"));
+                    output.write(&contract_name, name, "State diff:", lines)?;
+                    println!();
                 }
             }
             let block_outcome = TestOutcome::new(
@@ -731,10 +1610,145 @@ fn test(
             println!("{}", gas_report.finalize());
         }
 
+        if let Some(report) = gas_schedule_report {
+            println!("{}", report);
+        }
+
+        if let Some(gas_flamegraph) = gas_flamegraph {
+            if gas_flamegraph.is_empty() {
+                let note = Paint::yellow("Note:").bold();
+                println!("{} No gas was recorded for the flamegraph", note);
+            } else {
+                fs::create_dir_all(&config.cache_path)?;
+                let folded = config.cache_path.join("flamegraph.folded");
+                let svg = config.cache_path.join("flamegraph.svg");
+                gas_flamegraph.write_folded(&folded)?;
+                gas_flamegraph.write_svg(&svg, "Gas flamegraph")?;
+                println!("\nFlamegraph written to {} ({})", svg.display(), folded.display());
+            }
+        }
+
+        if let Some(call_timeline) = call_timeline {
+            if call_timeline.is_empty() {
+                let note = Paint::yellow("Note:").bold();
+                println!("{} No calls were recorded for the Chrome trace", note);
+            } else {
+                fs::create_dir_all(&config.cache_path)?;
+                let path = config.cache_path.join("chrome-trace.json");
+                call_timeline.write(&path)?;
+                println!("\nChrome trace written to {}", path.display());
+            }
+        }
+
+        if let Some(path) = output.finish()? {
+            println!(
+                "\n{} Some output was truncated, the full output was written to {}",
+                Paint::yellow("Note:").bold(),
+                path.display()
+            );
+        }
+
         // reattach the thread
         let _ = handle.join();
 
         trace!(target: "forge::test", "received {} results", results.len());
-        Ok(TestOutcome::new(results, allow_failure))
+        let mut outcome = TestOutcome::new(results, allow_failure);
+        if cancelled.load(Ordering::Relaxed) && outcome.failed_fast(fail_fast) {
+            println!(
+                "\n{} Stopped after the first failure, the remaining tests were skipped.",
+                Paint::yellow("Note:").bold(),
+            );
+        } else if cancelled.load(Ordering::Relaxed) {
+            outcome.cancelled = true;
+            println!(
+                "\n{} The test run was cancelled, only completed tests are reported.\n{}",
+                Paint::yellow("Note:").bold(),
+                outcome.summary()
+            );
+        }
+        Ok(outcome)
+    }
+}
+
+/// Prints the logs and traces of tests, truncating the output of a single test to a maximum number
+/// of lines.
+///
+/// If a limit is set, the full output is spooled to a log file so nothing is lost for debugging.
+struct TestOutputWriter {
+    /// The maximum number of lines to print per test
+    limit: Option<usize>,
+    /// The test that is being printed, as `<contract>:<test>`
+    test: String,
+    /// The number of lines printed for the current test
+    printed: usize,
+    /// The log file the full output is written to
+    spool: Option<(PathBuf, BufWriter<File>)>,
+    /// Whether any output was truncated
+    truncated: bool,
+}
+
+impl TestOutputWriter {
+    fn new(limit: Option<usize>, spool: Option<PathBuf>) -> eyre::Result<Self> {
+        let spool = if let Some(path) = spool {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let file = BufWriter::new(fs::create_file(&path)?);
+            Some((path, file))
+        } else {
+            None
+        };
+        Ok(Self { limit, spool, test: String::new(), printed: 0, truncated: false })
+    }
+
+    /// Prints the `header` followed by the `lines` of the given test, truncated to the limit
+    fn write(
+        &mut self,
+        contract_name: &str,
+        test_name: &str,
+        header: &str,
+        lines: impl Iterator<Item = String>,
+    ) -> eyre::Result<()> {
+        // The limit is shared by all sections of a test
+        let test = format!("{contract_name}:{test_name}");
+        if test != self.test {
+            self.test = test;
+            self.printed = 0;
+        }
+
+        println!("{header}");
+        if let Some((_, file)) = &mut self.spool {
+            writeln!(file, "{} {header}", self.test)?;
+        }
+
+        let mut truncated = 0usize;
+        for line in lines {
+            if let Some((_, file)) = &mut self.spool {
+                writeln!(file, "{line}")?;
+            }
+            if self.limit.map(|limit| self.printed < limit).unwrap_or(true) {
+                println!("{line}");
+                self.printed += 1;
+            } else {
+                truncated += 1;
+            }
+        }
+
+        if truncated > 0 {
+            self.truncated = true;
+            println!("{}", Paint::yellow(format!("…truncated {truncated} lines")));
+        }
+        Ok(())
+    }
+
+    /// Flushes the log file, returning its path if any output was truncated
+    fn finish(self) -> eyre::Result<Option<PathBuf>> {
+        match self.spool {
+            Some((path, mut file)) => {
+                file.flush()?;
+                Ok(self.truncated.then_some(path))
+            }
+            None => Ok(None),
+        }
     }
 }
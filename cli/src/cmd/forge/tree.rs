@@ -1,9 +1,14 @@
 //! tree command
 
 use crate::cmd::Cmd;
-use clap::Parser;
-use ethers::solc::Graph;
+use clap::{ArgEnum, Parser};
+use ethers::solc::{Graph, ProjectPathsConfig};
 use foundry_config::Config;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::Write,
+    path::{Path, PathBuf},
+};
 
 foundry_config::impl_figment_convert!(TreeArgs, opts);
 use crate::cmd::forge::build::ProjectPathsArgs;
@@ -21,6 +26,18 @@ pub struct TreeArgs {
         value_name = "CHARSET"
     )]
     charset: Charset,
+    /// Print the files that import the given file, directly or transitively, instead of the
+    /// files it imports.
+    #[clap(long, value_name = "FILE")]
+    invert: Option<PathBuf>,
+    /// Print the direct imports of every file, or the direct importers with --invert, as JSON.
+    #[clap(long)]
+    json: bool,
+    /// Print the import graph, or the graph of the importers with --invert, in the given format.
+    ///
+    /// Sources, tests, scripts and libraries are colored differently.
+    #[clap(long, arg_enum, conflicts_with = "json", value_name = "FORMAT")]
+    format: Option<GraphFormat>,
     #[clap(flatten, next_help_heading = "PROJECT OPTIONS")]
     opts: ProjectPathsArgs,
 }
@@ -30,10 +47,365 @@ impl Cmd for TreeArgs {
 
     fn run(self) -> eyre::Result<Self::Output> {
         let config: Config = From::from(&self);
-        let graph = Graph::resolve(&config.project_paths())?;
-        let opts = TreeOptions { charset: self.charset, no_dedupe: self.no_dedupe };
-        graph.print_with_options(opts);
+        let paths = config.project_paths();
+        let graph = Graph::resolve(&paths)?;
+        if self.invert.is_none() && !self.json && self.format.is_none() {
+            let opts = TreeOptions { charset: self.charset, no_dedupe: self.no_dedupe };
+            graph.print_with_options(opts);
+            return Ok(())
+        }
+
+        let imports = ImportGraph::new(&graph, &paths);
+        let invert = match self.invert {
+            Some(ref file) => Some(
+                imports
+                    .find(file, &paths.root)
+                    .ok_or_else(|| eyre::eyre!("{} is not part of the project", file.display()))?,
+            ),
+            None => None,
+        };
+        let files = match invert {
+            Some(file) => imports.importers_of(file),
+            None => (0..imports.paths.len()).collect(),
+        };
+
+        match (self.format, invert) {
+            (Some(GraphFormat::Dot), _) => print!("{}", imports.render_dot(&files)),
+            (Some(GraphFormat::Mermaid), _) => print!("{}", imports.render_mermaid(&files)),
+            (None, invert) if self.json => println!(
+                "{}",
+                serde_json::to_string_pretty(&imports.to_json(files, invert.is_some()))?
+            ),
+            (None, Some(file)) => {
+                print!("{}", imports.render_importers(file, &self.charset, self.no_dedupe))
+            }
+            (None, None) => unreachable!("the tree is printed by the graph"),
+        }
 
         Ok(())
     }
 }
+
+/// The formats the import graph can be printed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum GraphFormat {
+    /// A graphviz digraph
+    Dot,
+    /// A mermaid flowchart
+    Mermaid,
+}
+
+/// The kind of a file of a project, by the dir it's in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Source,
+    Test,
+    Script,
+    Lib,
+    Other,
+}
+
+impl FileKind {
+    fn new(path: &Path, paths: &ProjectPathsConfig) -> Self {
+        if path.starts_with(&paths.sources) {
+            FileKind::Source
+        } else if path.starts_with(&paths.tests) {
+            FileKind::Test
+        } else if path.starts_with(&paths.scripts) {
+            FileKind::Script
+        } else if paths.libraries.iter().any(|lib| path.starts_with(lib)) {
+            FileKind::Lib
+        } else {
+            FileKind::Other
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            FileKind::Source => "source",
+            FileKind::Test => "test",
+            FileKind::Script => "script",
+            FileKind::Lib => "lib",
+            FileKind::Other => "other",
+        }
+    }
+
+    fn color(&self) -> &'static str {
+        match self {
+            FileKind::Source => "#9ecae1",
+            FileKind::Test => "#a1d99b",
+            FileKind::Script => "#fdd0a2",
+            FileKind::Lib => "#d9d9d9",
+            FileKind::Other => "#ffffff",
+        }
+    }
+}
+
+/// The import graph of a project, with the paths of its files relative to the project root
+#[derive(Debug, Clone, Default)]
+struct ImportGraph {
+    paths: Vec<PathBuf>,
+    kinds: Vec<FileKind>,
+    /// The files every file imports directly
+    imports: Vec<Vec<usize>>,
+    /// The files that import every file directly
+    importers: Vec<Vec<usize>>,
+}
+
+impl ImportGraph {
+    fn new(graph: &Graph, paths: &ProjectPathsConfig) -> Self {
+        let num_files = graph.files().len();
+        let files = (0..num_files).map(|idx| graph.node(idx).unpack().0).collect::<Vec<_>>();
+        let kinds = files.iter().map(|file| FileKind::new(file, paths)).collect();
+        let files = files
+            .into_iter()
+            .map(|file| file.strip_prefix(&paths.root).unwrap_or(file).to_path_buf())
+            .collect();
+        let imports = (0..num_files).map(|idx| graph.imported_nodes(idx).to_vec()).collect();
+        Self::from_imports(files, kinds, imports)
+    }
+
+    fn from_imports(paths: Vec<PathBuf>, kinds: Vec<FileKind>, imports: Vec<Vec<usize>>) -> Self {
+        let mut importers = vec![Vec::new(); paths.len()];
+        for (idx, imported) in imports.iter().enumerate() {
+            for import in imported {
+                importers[*import].push(idx);
+            }
+        }
+        Self { paths, kinds, imports, importers }
+    }
+
+    /// Returns the index of the file, given by its absolute path or its path relative to the
+    /// project root or the current dir
+    fn find(&self, file: &Path, root: &Path) -> Option<usize> {
+        let mut candidates = vec![file.to_path_buf()];
+        if let Ok(file) = std::fs::canonicalize(file) {
+            candidates.push(file.strip_prefix(root).unwrap_or(&file).to_path_buf());
+        }
+        candidates.push(file.strip_prefix(root).unwrap_or(file).to_path_buf());
+        candidates.iter().find_map(|candidate| {
+            let candidate = candidate.strip_prefix(".").unwrap_or(candidate);
+            self.paths.iter().position(|path| path == candidate)
+        })
+    }
+
+    /// Returns the file and all files that import it, directly or transitively
+    fn importers_of(&self, file: usize) -> BTreeSet<usize> {
+        let mut importers = BTreeSet::from([file]);
+        let mut queue = VecDeque::from([file]);
+        while let Some(idx) = queue.pop_front() {
+            for importer in &self.importers[idx] {
+                if importers.insert(*importer) {
+                    queue.push_back(*importer);
+                }
+            }
+        }
+        importers
+    }
+
+    /// Returns the direct imports, or the direct importers if `inverted`, of the files by their
+    /// paths
+    fn to_json(
+        &self,
+        files: impl IntoIterator<Item = usize>,
+        inverted: bool,
+    ) -> BTreeMap<String, Vec<String>> {
+        let edges = if inverted { &self.importers } else { &self.imports };
+        files
+            .into_iter()
+            .map(|idx| {
+                let mut edges =
+                    edges[idx].iter().map(|edge| self.display(*edge)).collect::<Vec<_>>();
+                edges.sort();
+                (self.display(idx), edges)
+            })
+            .collect()
+    }
+
+    /// Renders the tree of the files that import the file, directly or transitively
+    ///
+    /// Files that were already printed are marked with `(*)` and not expanded again, unless
+    /// `no_dedupe` is set. Circular imports are always marked.
+    fn render_importers(&self, file: usize, charset: &Charset, no_dedupe: bool) -> String {
+        let mut out = String::new();
+        writeln!(out, "{}", self.display(file)).expect("writing to a string");
+        let mut seen = BTreeSet::from([file]);
+        self.write_importers(&mut out, file, "", charset, no_dedupe, &mut seen, &mut vec![file]);
+        out
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn write_importers(
+        &self,
+        out: &mut String,
+        file: usize,
+        prefix: &str,
+        charset: &Charset,
+        no_dedupe: bool,
+        seen: &mut BTreeSet<usize>,
+        stack: &mut Vec<usize>,
+    ) {
+        let (branch, last_branch, pipe) = match charset {
+            Charset::Utf8 => ("├── ", "└── ", "│   "),
+            Charset::Ascii => ("|-- ", "`-- ", "|   "),
+        };
+        let mut importers = self.importers[file].clone();
+        importers.sort_by_key(|idx| &self.paths[*idx]);
+        for (pos, importer) in importers.iter().enumerate() {
+            let is_last = pos == importers.len() - 1;
+            let repeated = stack.contains(importer) || (!seen.insert(*importer) && !no_dedupe);
+            writeln!(
+                out,
+                "{}{}{}{}",
+                prefix,
+                if is_last { last_branch } else { branch },
+                self.display(*importer),
+                if repeated { " (*)" } else { "" }
+            )
+            .expect("writing to a string");
+            if !repeated {
+                let prefix = format!("{}{}", prefix, if is_last { "    " } else { pipe });
+                stack.push(*importer);
+                self.write_importers(out, *importer, &prefix, charset, no_dedupe, seen, stack);
+                stack.pop();
+            }
+        }
+    }
+
+    /// Renders the files and the imports between them as a graphviz digraph
+    fn render_dot(&self, files: &BTreeSet<usize>) -> String {
+        let mut out = String::new();
+        out.push_str("digraph imports {\n    rankdir=LR;\n    node [shape=box, style=filled];\n");
+        for idx in self.sorted(files) {
+            let kind = self.kinds[idx];
+            writeln!(
+                out,
+                "    {:?} [fillcolor={:?}, tooltip={:?}];",
+                self.display(idx),
+                kind.color(),
+                kind.name()
+            )
+            .expect("writing to a string");
+        }
+        for (from, to) in self.edges(files) {
+            writeln!(out, "    {:?} -> {:?};", self.display(from), self.display(to))
+                .expect("writing to a string");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the files and the imports between them as a mermaid flowchart
+    fn render_mermaid(&self, files: &BTreeSet<usize>) -> String {
+        let mut out = String::new();
+        out.push_str("flowchart LR\n");
+        for idx in self.sorted(files) {
+            writeln!(out, "    n{}[\"{}\"]:::{}", idx, self.display(idx), self.kinds[idx].name())
+                .expect("writing to a string");
+        }
+        for (from, to) in self.edges(files) {
+            writeln!(out, "    n{} --> n{}", from, to).expect("writing to a string");
+        }
+        for kind in
+            [FileKind::Source, FileKind::Test, FileKind::Script, FileKind::Lib, FileKind::Other]
+        {
+            writeln!(out, "    classDef {} fill:{}", kind.name(), kind.color())
+                .expect("writing to a string");
+        }
+        out
+    }
+
+    /// Returns the files sorted by their paths
+    fn sorted(&self, files: &BTreeSet<usize>) -> Vec<usize> {
+        let mut files = files.iter().copied().collect::<Vec<_>>();
+        files.sort_by_key(|idx| &self.paths[*idx]);
+        files
+    }
+
+    /// Returns the imports between the files, as `(importer, imported)`, sorted by their paths
+    fn edges(&self, files: &BTreeSet<usize>) -> Vec<(usize, usize)> {
+        let mut edges = self
+            .sorted(files)
+            .into_iter()
+            .flat_map(|from| self.imports[from].iter().map(move |to| (from, *to)))
+            .filter(|(_, to)| files.contains(to))
+            .collect::<Vec<_>>();
+        edges.sort_by_key(|(from, to)| (&self.paths[*from], &self.paths[*to]));
+        edges
+    }
+
+    fn display(&self, idx: usize) -> String {
+        self.paths[idx].display().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> ImportGraph {
+        let paths = ["src/Token.sol", "src/Vault.sol", "test/Vault.t.sol", "test/Token.t.sol"];
+        ImportGraph::from_imports(
+            paths.iter().map(PathBuf::from).collect(),
+            vec![FileKind::Source, FileKind::Source, FileKind::Test, FileKind::Test],
+            vec![vec![], vec![0], vec![1, 0], vec![0]],
+        )
+    }
+
+    #[test]
+    fn can_find_importers() {
+        let graph = graph();
+        let token = graph.find(Path::new("./src/Token.sol"), Path::new("/root")).unwrap();
+        assert_eq!(token, 0);
+        assert_eq!(graph.importers_of(token), BTreeSet::from([0, 1, 2, 3]));
+        assert_eq!(graph.importers_of(1), BTreeSet::from([1, 2]));
+
+        let json = graph.to_json(graph.importers_of(1), true);
+        assert_eq!(
+            json,
+            BTreeMap::from([
+                ("src/Vault.sol".to_string(), vec!["test/Vault.t.sol".to_string()]),
+                ("test/Vault.t.sol".to_string(), vec![]),
+            ])
+        );
+        assert_eq!(
+            graph.to_json(0..4, false)["test/Vault.t.sol"],
+            ["src/Token.sol", "src/Vault.sol"]
+        );
+    }
+
+    #[test]
+    fn can_render_importers() {
+        let graph = graph();
+        assert_eq!(
+            graph.render_importers(0, &Charset::Ascii, false),
+            "src/Token.sol\n|-- src/Vault.sol\n|   `-- test/Vault.t.sol\n|-- test/Token.t.sol\n`-- test/Vault.t.sol (*)\n"
+        );
+        assert_eq!(
+            graph.render_importers(0, &Charset::Ascii, true),
+            "src/Token.sol\n|-- src/Vault.sol\n|   `-- test/Vault.t.sol\n|-- test/Token.t.sol\n`-- test/Vault.t.sol\n"
+        );
+    }
+
+    #[test]
+    fn can_render_graphs() {
+        let graph = graph();
+        let files = graph.importers_of(1);
+        assert_eq!(
+            graph.render_dot(&files),
+            r##"digraph imports {
+    rankdir=LR;
+    node [shape=box, style=filled];
+    "src/Vault.sol" [fillcolor="#9ecae1", tooltip="source"];
+    "test/Vault.t.sol" [fillcolor="#a1d99b", tooltip="test"];
+    "test/Vault.t.sol" -> "src/Vault.sol";
+}
+"##
+        );
+
+        let mermaid = graph.render_mermaid(&(0..4).collect());
+        assert!(mermaid.starts_with("flowchart LR\n    n0[\"src/Token.sol\"]:::source\n"));
+        assert!(mermaid.contains("    n2 --> n0\n    n2 --> n1\n"));
+        assert!(mermaid.contains("    classDef test fill:#a1d99b\n"));
+    }
+}
@@ -0,0 +1,80 @@
+//! Upgrade-safety check command
+use crate::cmd::{forge::storage_diff::StorageLayoutArgs, Cmd};
+use clap::Parser;
+use ethers::solc::info::ContractInfo;
+use forge::upgrade_check::{
+    check_storage_layout, ContractDefinitions, Severity, UpgradeCheckReport,
+};
+use std::str::FromStr;
+use yansi::Paint;
+
+#[derive(Debug, Clone, Parser)]
+pub struct UpgradeCheckArgs {
+    #[clap(
+        help = "The new implementation, in the form `(<path>:)?<contractname>`.",
+        value_name = "CONTRACT"
+    )]
+    pub contract: String,
+
+    #[clap(
+        long,
+        help = "The implementation that is upgraded, to check the storage layout against.",
+        long_help = "The implementation that is upgraded, to check the storage layout against. Either the identifier of a contract of the project in the form `(<path>:)?<contractname>`, the path to an artifact, or the address of a contract verified on Etherscan.",
+        value_name = "REFERENCE"
+    )]
+    pub reference: Option<String>,
+
+    #[clap(long, help = "Print the results as JSON.")]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub layouts: StorageLayoutArgs,
+}
+
+impl Cmd for UpgradeCheckArgs {
+    type Output = ();
+    fn run(self) -> eyre::Result<Self::Output> {
+        let mut contract =
+            ContractInfo::from_str(&self.contract).map_err(|err| eyre::eyre!("{}", err))?;
+        let output = self.layouts.compile(&mut contract)?;
+        let contracts = ContractDefinitions::new(
+            output.artifacts().filter_map(|(_, artifact)| artifact.ast.as_ref()),
+        )?;
+        let mut findings = contracts.check(&contract.name, contract.path.as_deref())?;
+
+        if let Some(reference) = &self.reference {
+            let artifact = output.find_contract(&contract).ok_or_else(|| {
+                eyre::eyre!("Could not find artifact `{contract}` in the compiled artifacts")
+            })?;
+            let new = artifact
+                .storage_layout
+                .as_ref()
+                .ok_or_else(|| eyre::eyre!("No storage layout for `{}`", contract))?;
+            let old = self.layouts.storage_layout(reference)?;
+            findings.splice(0..0, check_storage_layout(&old, new)?);
+        }
+
+        let report = UpgradeCheckReport::new(self.contract, self.reference, findings);
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            for finding in &report.findings {
+                match finding.severity {
+                    Severity::Error => println!("{} {}", Paint::red("[ERROR]"), finding),
+                    Severity::Warning => println!("{} {}", Paint::yellow("[WARNING]"), finding),
+                }
+            }
+        }
+
+        if !report.passed {
+            eyre::bail!("Found {} upgrade safety errors in `{}`", report.errors(), report.contract)
+        }
+        if !self.json {
+            println!(
+                "\n{}",
+                Paint::green(format!("No upgrade safety errors found in `{}`", report.contract))
+            );
+        }
+        Ok(())
+    }
+}
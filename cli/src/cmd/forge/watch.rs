@@ -5,8 +5,14 @@ use crate::{
     utils::{self, FoundryPathExt},
 };
 use clap::Parser;
+use ethers::solc::{Graph, ProjectPathsConfig};
 use foundry_config::Config;
-use std::{collections::HashSet, convert::Infallible, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tracing::trace;
 use watchexec::{
     action::{Action, Outcome, PreSpawn},
@@ -40,9 +46,14 @@ pub struct WatchArgs {
     #[clap(long = "no-restart", help = "Do not restart the command while it's still running.")]
     pub no_restart: bool,
 
+    /// Clear the terminal screen before each run.
+    #[clap(long = "clear")]
+    pub clear: bool,
+
     /// Explicitly re-run all tests when a change is made.
     ///
-    /// By default, only the tests of the last modified test file are executed.
+    /// By default, only the tests affected by the modified files are executed: modified test
+    /// files and all test files that import a modified file, directly or transitively.
     #[clap(long = "run-all")]
     pub run_all: bool,
 
@@ -143,7 +154,8 @@ pub async fn watch_test(args: TestArgs) -> eyre::Result<()> {
         args.watch.run_all;
 
     let state = WatchTestState {
-        project_root: config.__root.0,
+        project_root: config.__root.0.clone(),
+        paths: config.project_paths(),
         no_reconfigure,
         last_test_files: Default::default(),
     };
@@ -160,18 +172,62 @@ pub async fn watch_test(args: TestArgs) -> eyre::Result<()> {
 struct WatchTestState {
     /// the root directory of the project
     project_root: PathBuf,
+    /// the paths of the project, used to resolve the dependency graph of the sources
+    paths: ProjectPathsConfig,
     /// marks whether we can reconfigure the watcher command with the `--match-path` arg
     no_reconfigure: bool,
-    /// Tracks the last changed test files, if any so that if a non-test file was modified we run
-    /// this file instead *Note:* this is a vec, so we can also watch out for changes
-    /// introduced by `forge fmt`
-    last_test_files: HashSet<String>,
+    /// Tracks the last affected test files, if any so that if a file was modified that no test
+    /// depends on we run these files instead *Note:* this is a set, so we can also watch out for
+    /// changes introduced by `forge fmt`
+    last_test_files: HashSet<PathBuf>,
+}
+
+/// Returns all test files that are affected by the `changed` files, that is the changed test files
+/// themselves and all test files that import a changed file, directly or transitively.
+fn affected_test_files(
+    paths: &ProjectPathsConfig,
+    changed: &HashSet<PathBuf>,
+) -> eyre::Result<HashSet<PathBuf>> {
+    let graph = Graph::resolve(paths)?;
+    let files = graph.files().iter().map(|(path, index)| {
+        let imports =
+            graph.all_imported_nodes(*index).map(|import| graph.node(import).unpack().0.as_path());
+        (path.as_path(), imports.collect())
+    });
+    Ok(select_affected(files, changed))
+}
+
+/// Selects the test files among `files`, given with all their direct and transitive imports, that
+/// are changed or import a changed file
+fn select_affected<'a>(
+    files: impl IntoIterator<Item = (&'a Path, Vec<&'a Path>)>,
+    changed: &HashSet<PathBuf>,
+) -> HashSet<PathBuf> {
+    files
+        .into_iter()
+        .filter(|(path, _)| path.is_sol_test())
+        .filter(|(path, imports)| {
+            changed.contains(*path) || imports.iter().any(|import| changed.contains(*import))
+        })
+        .map(|(path, _)| path.to_path_buf())
+        .collect()
+}
+
+/// Returns the arguments that restrict `forge test` to the given test files, relative to the
+/// project root if possible
+fn match_files_args(project_root: &Path, files: &HashSet<PathBuf>) -> Vec<String> {
+    let mut files: Vec<_> = files
+        .iter()
+        .map(|file| file.strip_prefix(project_root).unwrap_or(file).display().to_string())
+        .collect();
+    files.sort();
+    std::iter::once("--match-files".to_string()).chain(files).collect()
 }
 
 /// The `on_action` hook for `forge test --watch`
 fn on_test(action: OnActionState<WatchTestState>) {
     let OnActionState { args, runtime, action, wx, cmd, other } = action;
-    let WatchTestState { project_root, no_reconfigure, last_test_files } = other;
+    let WatchTestState { project_root, paths, no_reconfigure, last_test_files } = other;
 
     if no_reconfigure {
         // nothing to reconfigure
@@ -180,27 +236,42 @@ fn on_test(action: OnActionState<WatchTestState>) {
 
     let mut cmd = cmd.clone();
 
-    let mut changed_sol_test_files: HashSet<_> = action
+    let changed_sol_files: HashSet<_> = action
         .events
         .iter()
         .flat_map(|e| e.paths())
-        .filter(|(path, _)| path.is_sol_test())
-        .filter_map(|(path, _)| path.to_str())
-        .map(str::to_string)
+        .filter(|(path, _)| path.is_sol())
+        .map(|(path, _)| path.to_path_buf())
         .collect();
 
+    let mut affected_test_files = if changed_sol_files.is_empty() {
+        HashSet::new()
+    } else {
+        match affected_test_files(&paths, &changed_sol_files) {
+            Ok(files) => files,
+            Err(err) => {
+                // if the graph can't be resolved, e.g. due to a syntax error, we only run the
+                // modified test files
+                trace!("failed to resolve dependency graph: {}", err);
+                changed_sol_files.iter().filter(|path| path.is_sol_test()).cloned().collect()
+            }
+        }
+    };
+
     // replace `--match-path` | `-mp` argument
     if let Some(pos) = cmd.iter().position(|arg| arg == "--match-path" || arg == "-mp") {
         // --match-path requires 1 argument
         cmd.drain(pos..=(pos + 1));
     }
 
-    if changed_sol_test_files.len() > 1 ||
-        (changed_sol_test_files.is_empty() && last_test_files.is_empty())
-    {
-        // this could happen if multiple files were changed at once, for example `forge fmt` was
-        // run, or if no test files were changed and no previous test files were modified in which
-        // case we simply run all
+    if affected_test_files.is_empty() {
+        // reuse the old test files if no test depends on the modified files
+        affected_test_files = last_test_files;
+    }
+
+    if affected_test_files.is_empty() {
+        // no test files were affected by the changes and no previous test files were affected in
+        // which case we simply run all
         let mut config = runtime.clone();
         config.command(watch_command(cmd.clone()));
         // re-register the action
@@ -209,34 +280,15 @@ fn on_test(action: OnActionState<WatchTestState>) {
             config,
             wx,
             cmd,
-            WatchTestState {
-                project_root,
-                no_reconfigure,
-                last_test_files: changed_sol_test_files,
-            },
+            WatchTestState { project_root, paths, no_reconfigure, last_test_files: HashSet::new() },
             on_test,
         );
         return
     }
 
-    if changed_sol_test_files.is_empty() {
-        // reuse the old test files if a non-test file was changed
-        changed_sol_test_files = last_test_files;
-    }
-
-    // append `--match-path` glob
-    let mut file = changed_sol_test_files.clone().into_iter().next().expect("test file present");
-
-    // remove the project root dir from the detected file
-    if let Some(root) = project_root.as_os_str().to_str() {
-        if let Some(f) = file.strip_prefix(root) {
-            file = f.trim_start_matches('/').to_string();
-        }
-    }
-
+    // pass the affected files as an explicit set, paths can't be reliably combined into a glob
     let mut new_cmd = cmd.clone();
-    new_cmd.push("--match-path".to_string());
-    new_cmd.push(file);
+    new_cmd.extend(match_files_args(&project_root, &affected_test_files));
     trace!("reconfigure test command {:?}", new_cmd);
 
     // reconfigure the executor with a new runtime
@@ -249,7 +301,12 @@ fn on_test(action: OnActionState<WatchTestState>) {
         config,
         wx,
         cmd,
-        WatchTestState { project_root, no_reconfigure, last_test_files: changed_sol_test_files },
+        WatchTestState {
+            project_root,
+            paths,
+            no_reconfigure,
+            last_test_files: affected_test_files,
+        },
         on_test,
     );
 }
@@ -373,8 +430,7 @@ fn on_action<F, T>(
             other: other.clone(),
         });
 
-        // mattsse: could be made into flag to never clear the shell
-        let clear = false;
+        let clear = args.clear;
         let when_running = match (clear, on_busy) {
             (_, "do-nothing") => Outcome::DoNothing,
             (true, "restart") => {
@@ -418,3 +474,58 @@ pub fn runtime(args: &WatchArgs) -> eyre::Result<RuntimeConfig> {
 
     Ok(config)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_affected_test_files() {
+        let token = Path::new("/project/src/Token.sol");
+        let vault = Path::new("/project/src/Vault.sol");
+        let math = Path::new("/project/src/Math.sol");
+        let token_test = Path::new("/project/test/Token.t.sol");
+        let vault_test = Path::new("/project/test/Vault.t.sol");
+        let math_test = Path::new("/project/test/Math.t.sol");
+        let files = || {
+            vec![
+                (token, vec![]),
+                (vault, vec![token]),
+                (math, vec![]),
+                (token_test, vec![token]),
+                // imports `Token.sol` transitively
+                (vault_test, vec![vault, token]),
+                (math_test, vec![math]),
+            ]
+        };
+        let changed = |paths: &[&Path]| paths.iter().map(|path| path.to_path_buf()).collect();
+        let selected = |paths: &[&Path]| paths.iter().map(|path| path.to_path_buf()).collect();
+
+        assert_eq!(
+            select_affected(files(), &changed(&[token])),
+            selected(&[token_test, vault_test])
+        );
+        assert_eq!(select_affected(files(), &changed(&[vault])), selected(&[vault_test]));
+        assert_eq!(select_affected(files(), &changed(&[math_test])), selected(&[math_test]));
+        assert_eq!(
+            select_affected(files(), &changed(&[Path::new("/project/README.md")])),
+            HashSet::new()
+        );
+    }
+
+    #[test]
+    fn can_match_affected_files_exactly() {
+        let root = Path::new("/project");
+        let files = [
+            PathBuf::from("/project/test/b,c.t.sol"),
+            PathBuf::from("/project/test/{a}.t.sol"),
+            PathBuf::from("/other/test/A.t.sol"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            match_files_args(root, &files),
+            vec!["--match-files", "/other/test/A.t.sol", "test/b,c.t.sol", "test/{a}.t.sol"]
+        );
+    }
+}
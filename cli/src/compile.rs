@@ -1,17 +1,29 @@
 //! Support for compiling [ethers::solc::Project]
 
-use crate::term;
+use crate::{term, utils::FoundryPathExt};
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
 use ethers::{
     prelude::Graph,
-    solc::{report::NoReporter, Artifact, FileFilter, Project, ProjectCompileOutput},
+    solc::{
+        artifacts::{CompactContractBytecode, Settings, Source},
+        cache::{CacheEntry, SolFilesCache},
+        report::NoReporter,
+        Artifact, ArtifactId, ConfigurableArtifacts, ConfigurableContractArtifact, FileFilter,
+        Project, ProjectCompileOutput,
+    },
 };
+use eyre::WrapErr;
+use foundry_config::Config;
+use globset::{Glob, GlobMatcher};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fmt::Display,
     path::{Path, PathBuf},
 };
 
+pub mod raw;
+pub mod vyper;
+
 /// Compiles the provided [`Project`], throws if there's any compiler error and logs whether
 /// compilation was successful or if there was a cache hit.
 pub fn compile(
@@ -120,6 +132,47 @@ impl ProjectCompiler {
         self.compile_with(project, |prj| Ok(prj.compile_sparse(filter)?))
     }
 
+    /// Compiles the project, except the sources matching a glob of the `compiler_overrides` of the
+    /// `config`, which are compiled by separate jobs with the settings of their override, see
+    /// [Config::compiler_override_configs()].
+    ///
+    /// Every job is given all sources, but only generates the artifacts of its own sources. The
+    /// override jobs are run first, so the names and sizes are printed for the project only.
+    ///
+    /// Returns the output of the project, and the artifacts of all jobs with their source paths
+    /// relative to the project root.
+    pub fn compile_with_overrides(
+        self,
+        config: &Config,
+        project: &Project,
+    ) -> eyre::Result<(ProjectCompileOutput, Vec<(ArtifactId, CompactContractBytecode)>)> {
+        let root = project.root().clone();
+        let overrides = config
+            .compiler_override_configs()
+            .into_iter()
+            .map(|(glob, config)| {
+                let matcher = Glob::new(&glob)
+                    .wrap_err_with(|| format!("Invalid glob of compiler override `{glob}`"))?
+                    .compile_matcher();
+                Ok((matcher, config.project()?))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        let globs = overrides.iter().map(|(matcher, _)| matcher.clone()).collect::<Vec<_>>();
+
+        let mut artifacts = Vec::new();
+        for (idx, (_, override_project)) in overrides.iter().enumerate() {
+            tracing::trace!(target: "forge::compile", glob = ?globs[idx].glob(), "compiling override");
+            let filter = OverrideFiles { root: root.clone(), globs: globs.clone(), job: Some(idx) };
+            let output = ProjectCompiler::default().compile_sparse(override_project, filter)?;
+            artifacts.extend(relative_artifacts(output, &root));
+        }
+
+        let filter = OverrideFiles { root: root.clone(), globs, job: None };
+        let output = self.compile_sparse(project, filter)?;
+        artifacts.extend(relative_artifacts(output.clone(), &root));
+        Ok((output, artifacts))
+    }
+
     /// Compiles the project with the given closure
     ///
     /// # Example
@@ -282,3 +335,216 @@ pub fn compile_target(
         compile(project, false, false)
     }
 }
+
+/// Compiles only the sources of the `project` that are affected by the changes since the last
+/// build, see [ProjectCompiler::compile_sparse()].
+///
+/// The affected sources are the changed sources and all sources that import them, directly or
+/// transitively, of which test files are only compiled if the `filter` matches them. All other
+/// sources are given a pruned output selection, so solc only generates the bytecode of the
+/// affected sources, and their artifacts are read from the cache, to be linked together with the
+/// new artifacts. Without a cache, the whole project is compiled.
+pub fn compile_affected<F: FileFilter + 'static>(
+    project: &Project,
+    filter: F,
+) -> eyre::Result<ProjectCompileOutput> {
+    let cache = match SolFilesCache::read_joined(&project.paths) {
+        Ok(cache) => cache,
+        Err(_) => return ProjectCompiler::default().compile(project),
+    };
+    let affected = stale_sources(project, &cache)?.into_keys().collect();
+    tracing::trace!(target: "forge::compile", ?affected, "compiling affected sources");
+    ProjectCompiler::default().compile_sparse(project, AffectedFiles { affected, filter })
+}
+
+/// Matches the affected sources, and of these the test files only if the inner filter matches them
+#[derive(Debug, Clone)]
+struct AffectedFiles<F> {
+    affected: BTreeSet<PathBuf>,
+    filter: F,
+}
+
+impl<F: FileFilter> FileFilter for AffectedFiles<F> {
+    fn is_match(&self, file: &Path) -> bool {
+        self.affected.contains(file) && (!file.is_sol_test() || self.filter.is_match(file))
+    }
+}
+
+/// Matches the sources of a compilation job of [ProjectCompiler::compile_with_overrides()]: the
+/// sources whose first matching glob is the one of the job, or the sources without a matching
+/// glob for the job of the project
+#[derive(Debug, Clone)]
+struct OverrideFiles {
+    root: PathBuf,
+    globs: Vec<GlobMatcher>,
+    job: Option<usize>,
+}
+
+impl FileFilter for OverrideFiles {
+    fn is_match(&self, file: &Path) -> bool {
+        let file = file.strip_prefix(&self.root).unwrap_or(file);
+        self.globs.iter().position(|glob| glob.is_match(file)) == self.job
+    }
+}
+
+/// Returns the artifacts of the `output` with their source paths relative to the `root`
+pub fn relative_artifacts(
+    output: ProjectCompileOutput,
+    root: &Path,
+) -> impl Iterator<Item = (ArtifactId, CompactContractBytecode)> {
+    output
+        .with_stripped_file_prefixes(root)
+        .into_artifacts()
+        .map(|(id, artifact)| (id, artifact.into_contract_bytecode()))
+}
+
+/// Reads the cached artifacts of the `project` without compiling, with their source paths relative
+/// to the project root.
+///
+/// The artifacts are validated against a fingerprint of their sources: the content hash of every
+/// source and of everything it imports must match the content hash recorded in the cache when the
+/// artifacts were written, and the sources must still import the same files with the current
+/// remappings. Otherwise this fails with a list of the stale sources and artifacts.
+///
+/// This also fails if the artifacts were built with other compiler settings, e.g. optimizer
+/// settings, or with another compiler version than the one the project is pinned to.
+pub fn read_cached_artifacts(
+    project: &Project,
+) -> eyre::Result<Vec<(ArtifactId, ConfigurableContractArtifact)>> {
+    let cache = SolFilesCache::read_joined(&project.paths)
+        .wrap_err("Could not read the compiler cache, run `forge build` first")?;
+
+    let changed = changed_settings(project, &cache);
+    if !changed.is_empty() {
+        eyre::bail!(
+            "The cached artifacts do not match the {} of the project, run `forge build` first.",
+            changed.join(" and ")
+        )
+    }
+
+    let stale = stale_sources(project, &cache)?;
+    if !stale.is_empty() {
+        let mut err =
+            "The cached artifacts are stale, run `forge build` first.\nStale sources:".to_string();
+        for (path, artifacts) in &stale {
+            let path = path.strip_prefix(project.root()).unwrap_or(path);
+            if artifacts.is_empty() {
+                err.push_str(&format!("\n  {} (not compiled)", path.display()));
+            } else {
+                err.push_str(&format!("\n  {} ({})", path.display(), artifacts.join(", ")));
+            }
+        }
+        eyre::bail!(err)
+    }
+
+    let artifacts = cache
+        .read_artifacts::<ConfigurableContractArtifact>()
+        .wrap_err("Could not read the cached artifacts, run `forge build` first")?;
+    let root = project.root();
+    Ok(artifacts
+        .into_artifacts::<ConfigurableArtifacts>()
+        .map(|(mut id, artifact)| {
+            if let Ok(source) = id.source.strip_prefix(root) {
+                id.source = source.to_path_buf();
+            }
+            if let Ok(path) = id.path.strip_prefix(root) {
+                id.path = path.to_path_buf();
+            }
+            (id, artifact)
+        })
+        .collect())
+}
+
+/// Returns which of the compiler settings and the compiler version of the `project` differ from the
+/// ones the cached artifacts were built with.
+///
+/// The output selection is not compared, since it only determines which outputs are written. The
+/// compiler version can only be compared if the project is pinned to a version.
+fn changed_settings(project: &Project, cache: &SolFilesCache) -> Vec<&'static str> {
+    let without_output_selection =
+        |settings: &Settings| Settings { output_selection: Default::default(), ..settings.clone() };
+    let settings = without_output_selection(&project.solc_config.settings);
+    let version = if project.auto_detect { None } else { project.solc.version().ok() };
+
+    let mut changed = Vec::new();
+    if cache
+        .files
+        .values()
+        .any(|entry| without_output_selection(&entry.solc_config.settings) != settings)
+    {
+        changed.push("compiler settings");
+    }
+    if let Some(version) = version {
+        if cache.files.values().flat_map(|entry| entry.artifacts.values()).any(|versions| {
+            !versions.keys().any(|cached| {
+                (cached.major, cached.minor, cached.patch) ==
+                    (version.major, version.minor, version.patch)
+            })
+        }) {
+            changed.push("compiler version");
+        }
+    }
+    changed
+}
+
+/// Returns the sources of the `project` whose cached artifacts are out of date, mapped to the
+/// names of their cached artifacts.
+///
+/// A source is stale if it is not cached, if its content changed, if it imports other files than
+/// when it was cached, e.g. because the remappings changed, or if it imports a stale source,
+/// directly or transitively.
+fn stale_sources(
+    project: &Project,
+    cache: &SolFilesCache,
+) -> eyre::Result<BTreeMap<PathBuf, Vec<String>>> {
+    let mut stale = BTreeMap::new();
+    for path in project.paths.input_files() {
+        if !cache.files.contains_key(&path) {
+            stale.insert(path, Vec::new());
+        }
+    }
+
+    // The imports of every source, resolved with the current remappings
+    let graph = Graph::resolve(&project.paths)?;
+    let imports = |path: &Path| {
+        let index = *graph.files().get(path)?;
+        Some(
+            graph
+                .imported_nodes(index)
+                .iter()
+                .map(|import| graph.node(*import).unpack().0.clone())
+                .collect::<BTreeSet<_>>(),
+        )
+    };
+
+    let artifact_names = |entry: &CacheEntry| entry.artifacts.keys().cloned().collect::<Vec<_>>();
+    for (path, entry) in &cache.files {
+        let unchanged = Source::read(path)
+            .map(|source| source.content_hash() == entry.content_hash)
+            .unwrap_or(false);
+        let same_imports = imports(path).map_or(true, |imports| {
+            imports == entry.imports.iter().map(|import| project.root().join(import)).collect()
+        });
+        if !unchanged || !same_imports {
+            stale.insert(path.clone(), artifact_names(entry));
+        }
+    }
+
+    // Propagate staleness to the importing sources until nothing changes
+    loop {
+        let importers = cache
+            .files
+            .iter()
+            .filter(|(path, _)| !stale.contains_key(*path))
+            .filter(|(_, entry)| {
+                entry.imports.iter().any(|import| stale.contains_key(&project.root().join(import)))
+            })
+            .map(|(path, entry)| (path.clone(), artifact_names(entry)))
+            .collect::<Vec<_>>();
+        if importers.is_empty() {
+            break
+        }
+        stale.extend(importers);
+    }
+    Ok(stale)
+}
@@ -0,0 +1,134 @@
+//! Support for precompiled artifacts of contracts that are not compiled by solc, e.g. Huff
+//! contracts
+//!
+//! Every contract in the `raw_artifacts` dirs of the config consists of a `<Name>.bin` file with
+//! its hex encoded creation code, an optional `<Name>.json` file with its ABI and an optional
+//! `<Name>.bin-runtime` file with its hex encoded runtime code, which identifies the contract in
+//! traces. The artifacts are written to the artifacts dir as `<out>/<Name>.bin/<Name>.json`, so
+//! the contracts can be deployed with `vm.getCode("<Name>.bin")`.
+use ethers::{
+    abi::Abi,
+    solc::{
+        artifacts::{
+            BytecodeObject, CompactBytecode, CompactContractBytecode, CompactDeployedBytecode,
+        },
+        ArtifactId, Project,
+    },
+};
+use eyre::WrapErr;
+use foundry_common::fs;
+use semver::Version;
+use std::path::{Path, PathBuf};
+
+/// The extension of files with hex encoded creation code
+pub const BIN_EXTENSION: &str = "bin";
+
+/// Reads the precompiled artifacts in the `dirs` and writes them to the artifacts dir of the
+/// `project`.
+///
+/// Returns the artifacts with their source paths, the paths of their `.bin` files, relative to the
+/// project root.
+pub fn ingest(
+    project: &Project,
+    dirs: &[PathBuf],
+) -> eyre::Result<Vec<(ArtifactId, CompactContractBytecode)>> {
+    let root = project.root();
+    let out = project.paths.artifacts.strip_prefix(root).unwrap_or(&project.paths.artifacts);
+
+    let mut artifacts = Vec::new();
+    for dir in dirs {
+        let entries = std::fs::read_dir(dir)
+            .wrap_err_with(|| format!("Could not read the raw artifacts dir {}", dir.display()))?;
+        let mut bins = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension() == Some(BIN_EXTENSION.as_ref()))
+            .collect::<Vec<_>>();
+        bins.sort();
+
+        for bin in bins {
+            let contract = read_contract(&bin)?;
+            let name = bin.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let file_name = bin.file_name().unwrap_or_default();
+            let path = out.join(file_name).join(format!("{name}.json"));
+            fs::create_dir_all(root.join(out).join(file_name))?;
+            fs::write(root.join(&path), serde_json::to_vec_pretty(&contract)?)?;
+
+            let source = bin.strip_prefix(root).unwrap_or(&bin).to_path_buf();
+            let id = ArtifactId { path, name, source, version: Version::new(0, 0, 0) };
+            artifacts.push((id, contract));
+        }
+    }
+    Ok(artifacts)
+}
+
+/// Reads the contract of the `<Name>.bin` file, along with its ABI and runtime code if any
+fn read_contract(bin: &Path) -> eyre::Result<CompactContractBytecode> {
+    let read_code = |path: &Path| -> eyre::Result<CompactBytecode> {
+        let code = fs::read_to_string(path)?;
+        let code = hex::decode(code.trim().trim_start_matches("0x"))
+            .wrap_err_with(|| format!("{} is not hex encoded", path.display()))?;
+        Ok(CompactBytecode {
+            object: BytecodeObject::Bytecode(code.into()),
+            source_map: None,
+            link_references: Default::default(),
+        })
+    };
+
+    let runtime = bin.with_extension(format!("{BIN_EXTENSION}-runtime"));
+    let abi = bin.with_extension("json");
+    let abi = if abi.exists() {
+        let abi: Abi = serde_json::from_slice(&fs::read(&abi)?)
+            .wrap_err_with(|| format!("{} is not an ABI", abi.display()))?;
+        Some(abi)
+    } else {
+        None
+    };
+
+    Ok(CompactContractBytecode {
+        abi,
+        bytecode: Some(read_code(bin)?),
+        deployed_bytecode: if runtime.exists() {
+            Some(CompactDeployedBytecode {
+                bytecode: Some(read_code(&runtime)?),
+                immutable_references: Default::default(),
+            })
+        } else {
+            None
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_read_raw_contract() {
+        let dir = tempfile::tempdir().unwrap();
+        let bin = dir.path().join("Counter.bin");
+        std::fs::write(&bin, "0x6001600055\n").unwrap();
+
+        let contract = read_contract(&bin).unwrap();
+        assert!(contract.abi.is_none());
+        assert!(contract.deployed_bytecode.is_none());
+        assert_eq!(
+            contract.bytecode.unwrap().object.as_bytes().unwrap().to_vec(),
+            vec![0x60, 0x01, 0x60, 0x00, 0x55]
+        );
+
+        std::fs::write(dir.path().join("Counter.bin-runtime"), "600054").unwrap();
+        std::fs::write(
+            dir.path().join("Counter.json"),
+            r#"[{"type":"function","name":"value","inputs":[],"outputs":[{"name":"","type":"uint256"}],"stateMutability":"view"}]"#,
+        )
+        .unwrap();
+        let contract = read_contract(&bin).unwrap();
+        assert!(contract.abi.unwrap().function("value").is_ok());
+        let deployed = contract.deployed_bytecode.unwrap().bytecode.unwrap();
+        assert_eq!(deployed.object.as_bytes().unwrap().to_vec(), vec![0x60, 0x00, 0x54]);
+
+        // not hex encoded
+        std::fs::write(&bin, "PUSH1 0x01").unwrap();
+        assert!(read_contract(&bin).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! Support for compiling the Vyper sources of a [Project] with the `vyper` compiler
+//!
+//! The Vyper sources, `.vy` files in the sources, tests and scripts dirs, are compiled with a
+//! single `vyper` invocation. Their artifacts are written to the artifacts dir like the ones of
+//! solc, as `<out>/<File>.vy/<File>.json`, so the contracts can be deployed with
+//! `vm.getCode("File.vy")` and are decoded in traces.
+use ethers::{
+    abi::Abi,
+    solc::{
+        artifacts::{
+            BytecodeObject, CompactBytecode, CompactContractBytecode, CompactDeployedBytecode,
+        },
+        ArtifactId, Project,
+    },
+};
+use eyre::WrapErr;
+use foundry_common::fs;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// The extension of Vyper sources
+pub const VYPER_EXTENSION: &str = "vy";
+
+/// Returns the path of the `vyper` binary, which can be set with the `VYPER` env var
+fn vyper_binary() -> String {
+    std::env::var("VYPER").unwrap_or_else(|_| "vyper".to_string())
+}
+
+/// Returns the Vyper sources of the `project`
+pub fn sources(project: &Project) -> BTreeSet<PathBuf> {
+    [&project.paths.sources, &project.paths.tests, &project.paths.scripts]
+        .into_iter()
+        .flat_map(|dir| walkdir::WalkDir::new(dir).into_iter().filter_map(Result::ok))
+        .filter(|entry| {
+            entry.file_type().is_file() &&
+                entry.path().extension() == Some(VYPER_EXTENSION.as_ref())
+        })
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// Compiles the Vyper sources of the `project` and writes their artifacts.
+///
+/// Returns the artifacts with their source paths relative to the project root, or nothing if the
+/// project has no Vyper sources.
+pub fn compile(
+    project: &Project,
+    silent: bool,
+) -> eyre::Result<Vec<(ArtifactId, CompactContractBytecode)>> {
+    let sources = sources(project);
+    if sources.is_empty() {
+        return Ok(Vec::new())
+    }
+    let root = project.root();
+    let vyper = vyper_binary();
+    let version = version(&vyper)?;
+
+    let output = Command::new(&vyper)
+        .current_dir(root)
+        .args(["-f", "combined_json"])
+        .args(sources.iter().map(|source| source.strip_prefix(root).unwrap_or(source)))
+        .output()
+        .wrap_err_with(|| format!("Failed to run `{vyper}`, is Vyper installed?"))?;
+    if !output.status.success() {
+        eyre::bail!("Vyper compilation failed:\n{}", String::from_utf8_lossy(&output.stderr))
+    }
+    let mut contracts: BTreeMap<String, serde_json::Value> =
+        serde_json::from_slice(&output.stdout).wrap_err("Failed to parse the vyper output")?;
+    contracts.remove("version");
+
+    let mut artifacts = Vec::with_capacity(contracts.len());
+    for (source, contract) in contracts {
+        let contract: VyperContract = serde_json::from_value(contract)
+            .wrap_err_with(|| format!("Failed to parse the vyper output of {source}"))?;
+        let artifact = VyperArtifact {
+            contract: contract.into_artifact()?,
+            compiler_version: version.clone(),
+        };
+        let id = artifact_id(project, Path::new(&source), version.clone());
+        let path = root.join(&id.path);
+        fs::create_dir_all(path.parent().expect("artifacts have a parent dir"))?;
+        fs::write(&path, serde_json::to_vec_pretty(&artifact)?)?;
+        artifacts.push((id, artifact.contract));
+    }
+
+    if !silent {
+        println!("Compiled {} Vyper sources with vyper {}", artifacts.len(), version);
+    }
+    Ok(artifacts)
+}
+
+/// Reads the artifacts of the Vyper sources of the `project` without compiling, with their source
+/// paths relative to the project root
+pub fn read_artifacts(
+    project: &Project,
+) -> eyre::Result<Vec<(ArtifactId, CompactContractBytecode)>> {
+    let root = project.root();
+    sources(project)
+        .into_iter()
+        .map(|source| {
+            let source = source.strip_prefix(root).unwrap_or(&source).to_path_buf();
+            let path = artifact_id(project, &source, Version::new(0, 0, 0)).path;
+            let artifact: VyperArtifact = serde_json::from_slice(&fs::read(root.join(&path))?)
+                .wrap_err_with(|| {
+                    format!(
+                        "Could not read the artifact of {}, run `forge build` first",
+                        source.display()
+                    )
+                })?;
+            let id = artifact_id(project, &source, artifact.compiler_version);
+            Ok((id, artifact.contract))
+        })
+        .collect()
+}
+
+/// Returns the id of the artifact of the `source`, relative to the project root
+fn artifact_id(project: &Project, source: &Path, version: Version) -> ArtifactId {
+    let root = project.root();
+    let name = source.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let artifacts = project.paths.artifacts.strip_prefix(root).unwrap_or(&project.paths.artifacts);
+    let path = artifacts.join(source.file_name().unwrap_or_default()).join(format!("{name}.json"));
+    ArtifactId { path, name, source: source.to_path_buf(), version }
+}
+
+/// Returns the version of the `vyper` binary
+fn version(vyper: &str) -> eyre::Result<Version> {
+    let output = Command::new(vyper)
+        .arg("--version")
+        .output()
+        .wrap_err_with(|| format!("Failed to run `{vyper}`, is Vyper installed?"))?;
+    let version = String::from_utf8_lossy(&output.stdout);
+    Version::parse(version.trim())
+        .wrap_err_with(|| format!("Failed to parse the vyper version `{}`", version.trim()))
+}
+
+/// The artifact of a Vyper contract, along with the version of vyper it was compiled with
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VyperArtifact {
+    #[serde(flatten)]
+    contract: CompactContractBytecode,
+    compiler_version: Version,
+}
+
+/// The output of `vyper -f combined_json` for a single contract
+#[derive(Debug, Deserialize)]
+struct VyperContract {
+    abi: Abi,
+    bytecode: String,
+    bytecode_runtime: String,
+    #[serde(default)]
+    source_map: Option<VyperSourceMap>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VyperSourceMap {
+    /// The source map of the runtime bytecode, in the compressed format of solc
+    pc_pos_map_compressed: Option<String>,
+}
+
+impl VyperContract {
+    fn into_artifact(self) -> eyre::Result<CompactContractBytecode> {
+        let bytecode = |code: &str, source_map| -> eyre::Result<_> {
+            Ok(CompactBytecode {
+                object: BytecodeObject::Bytecode(
+                    hex::decode(code.trim_start_matches("0x"))?.into(),
+                ),
+                source_map,
+                link_references: Default::default(),
+            })
+        };
+        let source_map = self.source_map.and_then(|source_map| source_map.pc_pos_map_compressed);
+        Ok(CompactContractBytecode {
+            abi: Some(self.abi),
+            bytecode: Some(bytecode(&self.bytecode, None)?),
+            deployed_bytecode: Some(CompactDeployedBytecode {
+                bytecode: Some(bytecode(&self.bytecode_runtime, source_map)?),
+                immutable_references: Default::default(),
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_convert_vyper_output() {
+        let contract: VyperContract = serde_json::from_value(serde_json::json!({
+            "abi": [{
+                "stateMutability": "view",
+                "type": "function",
+                "name": "value",
+                "inputs": [],
+                "outputs": [{ "name": "", "type": "uint256" }]
+            }],
+            "bytecode": "0x6001600055",
+            "bytecode_runtime": "0x600054",
+            "source_map": { "pc_pos_map_compressed": "-1:-1:0:-;;" },
+            "method_identifiers": { "value()": "0x3fa4f245" }
+        }))
+        .unwrap();
+
+        let artifact = contract.into_artifact().unwrap();
+        assert!(artifact.abi.unwrap().function("value").is_ok());
+        assert_eq!(
+            artifact.bytecode.unwrap().object.as_bytes().unwrap().to_vec(),
+            vec![0x60, 0x01, 0x60, 0x00, 0x55]
+        );
+        let deployed = artifact.deployed_bytecode.unwrap().bytecode.unwrap();
+        assert_eq!(deployed.object.as_bytes().unwrap().to_vec(), vec![0x60, 0x00, 0x54]);
+        assert_eq!(deployed.source_map.as_deref(), Some("-1:-1:0:-;;"));
+    }
+}
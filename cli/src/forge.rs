@@ -8,7 +8,7 @@ mod utils;
 
 use crate::{
     cmd::{
-        forge::{cache::CacheSubcommands, watch},
+        forge::{cache::CacheSubcommands, fourbyte::SelectorsSubcommands, watch},
         Cmd,
     },
     utils::CommandUtils,
@@ -52,6 +52,15 @@ fn main() -> eyre::Result<()> {
         Subcommands::Debug(cmd) => {
             utils::block_on(cmd.debug())?;
         }
+        Subcommands::Fixture(cmd) => {
+            cmd.run()?;
+        }
+        Subcommands::Mutate(cmd) => {
+            cmd.run()?;
+        }
+        Subcommands::Daemon(cmd) => {
+            cmd.run()?;
+        }
         Subcommands::VerifyContract(args) => {
             utils::block_on(args.run())?;
         }
@@ -65,10 +74,16 @@ fn main() -> eyre::Result<()> {
             CacheSubcommands::Ls(cmd) => {
                 cmd.run()?;
             }
+            CacheSubcommands::Warm(cmd) => {
+                utils::block_on(cmd.run())?;
+            }
         },
         Subcommands::Create(cmd) => {
             utils::block_on(cmd.run())?;
         }
+        Subcommands::Create2(cmd) => {
+            utils::block_on(cmd.run())?;
+        }
         Subcommands::Update { lib } => {
             let mut cmd = Command::new("git");
 
@@ -123,9 +138,20 @@ fn main() -> eyre::Result<()> {
         Subcommands::UploadSelectors(args) => {
             utils::block_on(args.run())?;
         }
+        Subcommands::Selectors(cmd) => match cmd.sub {
+            SelectorsSubcommands::Upload(args) => {
+                utils::block_on(args.run())?;
+            }
+        },
         Subcommands::Tree(cmd) => {
             cmd.run()?;
         }
+        Subcommands::StorageDiff(cmd) => {
+            cmd.run()?;
+        }
+        Subcommands::UpgradeCheck(cmd) => {
+            cmd.run()?;
+        }
     }
 
     Ok(())
@@ -1,6 +1,9 @@
 use super::{ClapChain, EthereumOpts, TransactionOpts};
 use crate::{
-    cmd::cast::{find_block::FindBlockArgs, rpc::RpcArgs, run::RunArgs, wallet::WalletSubcommands},
+    cmd::cast::{
+        find_block::FindBlockArgs, replay::ReplayBlockArgs, rpc::RpcArgs, run::RunArgs,
+        wallet::WalletSubcommands,
+    },
     utils::{parse_ether_value, parse_u256},
 };
 use clap::{Parser, Subcommand, ValueHint};
@@ -493,7 +496,7 @@ Defaults to decoding output data. To decode input data pass --input or use cast
     #[clap(name = "4byte")]
     #[clap(visible_aliases = &["4", "4b"])]
     #[clap(
-        about = "Get the function signatures for the given selector from https://sig.eth.samczsun.com."
+        about = "Get the function signatures for the given selector from https://openchain.xyz or https://4byte.directory."
     )]
     FourByte {
         #[clap(help = "The function selector.", value_name = "SELECTOR")]
@@ -501,7 +504,9 @@ Defaults to decoding output data. To decode input data pass --input or use cast
     },
     #[clap(name = "4byte-decode")]
     #[clap(visible_aliases = &["4d", "4bd"])]
-    #[clap(about = "Decode ABI-encoded calldata using https://sig.eth.samczsun.com.")]
+    #[clap(
+        about = "Decode ABI-encoded calldata using https://openchain.xyz or https://4byte.directory."
+    )]
     FourByteDecode {
         #[clap(help = "The ABI-encoded calldata.", value_name = "CALLDATA")]
         calldata: Option<String>,
@@ -509,7 +514,7 @@ Defaults to decoding output data. To decode input data pass --input or use cast
     #[clap(name = "4byte-event")]
     #[clap(visible_aliases = &["4e", "4be"])]
     #[clap(
-        about = "Get the event signature for a given topic 0 from https://sig.eth.samczsun.com."
+        about = "Get the event signature for a given topic 0 from https://openchain.xyz or https://4byte.directory."
     )]
     FourByteEvent {
         #[clap(help = "Topic 0", value_name = "TOPIC_0")]
@@ -517,7 +522,7 @@ Defaults to decoding output data. To decode input data pass --input or use cast
     },
     #[clap(name = "upload-signature")]
     #[clap(visible_aliases = &["ups"])]
-    #[clap(about = r#"Upload the given signatures to https://sig.eth.samczsun.com.
+    #[clap(about = r#"Upload the given signatures to https://openchain.xyz.
 
     Examples:
     - cast upload-signature "transfer(address,uint256)"
@@ -537,12 +542,12 @@ Defaults to decoding output data. To decode input data pass --input or use cast
         about = "Pretty print calldata.",
         long_about = r#"Pretty print calldata.
 
-Tries to decode the calldata using https://sig.eth.samczsun.com unless --offline is passed."#
+Tries to decode the calldata using https://openchain.xyz or https://4byte.directory unless --offline is passed."#
     )]
     PrettyCalldata {
         #[clap(help = "The calldata.", value_name = "CALLDATA")]
         calldata: String,
-        #[clap(long, short, help = "Skip the https://sig.eth.samczsun.com lookup.")]
+        #[clap(long, short, help = "Skip the selector database lookup.")]
         offline: bool,
     },
     #[clap(name = "age")]
@@ -806,6 +811,11 @@ If an address is specified, then the ABI is fetched from Etherscan."#,
         about = "Runs a published transaction in a local environment and prints the trace."
     )]
     Run(RunArgs),
+    #[clap(
+        name = "replay-block",
+        about = "Replays the transactions of a block with contract overrides and reports the differences to the on-chain results."
+    )]
+    ReplayBlock(ReplayBlockArgs),
     #[clap(name = "rpc")]
     #[clap(visible_alias = "rp")]
     #[clap(about = "Perform a raw JSON-RPC request")]
@@ -1,13 +1,13 @@
-use super::{Wallet, WalletType};
+use super::{aws_signers, Wallet, WalletType, Web3Signer};
+use crate::utils::get_provider;
 use clap::Parser;
 use ethers::{
     middleware::SignerMiddleware,
-    prelude::RetryClient,
-    providers::{Http, Provider},
     signers::{HDPath as LedgerHDPath, Ledger, Signer, Trezor, TrezorHDPath},
     types::{Address, Chain, U256},
 };
 use eyre::Result;
+use foundry_common::provider::{ProvidersManager, RetryProvider};
 use foundry_config::{
     figment::{
         self,
@@ -52,6 +52,8 @@ impl EthereumOpts {
                 WalletType::Ledger(signer) => signer.address(),
                 WalletType::Local(signer) => signer.address(),
                 WalletType::Trezor(signer) => signer.address(),
+                WalletType::Remote(signer) => signer.address(),
+                WalletType::Aws(signer) => signer.address(),
             }
         } else {
             self.wallet.from.unwrap_or_else(Address::zero)
@@ -60,11 +62,8 @@ impl EthereumOpts {
 
     #[allow(unused)]
     pub async fn signer(&self, chain_id: U256) -> eyre::Result<Option<WalletType>> {
-        self.signer_with(
-            chain_id,
-            Arc::new(Provider::<RetryClient<Http>>::new_client(self.rpc_url()?, 10, 1000)?),
-        )
-        .await
+        let providers = ProvidersManager::new(Config::from(self).rpc_endpoints);
+        self.signer_with(chain_id, get_provider(&providers, self.rpc_url()?, false)?).await
     }
 
     /// Returns a [`SignerMiddleware`] corresponding to the provided private key, mnemonic or hw
@@ -72,7 +71,7 @@ impl EthereumOpts {
     pub async fn signer_with(
         &self,
         chain_id: U256,
-        provider: Arc<Provider<RetryClient<Http>>>,
+        provider: Arc<RetryProvider>,
     ) -> eyre::Result<Option<WalletType>> {
         if self.wallet.ledger {
             let derivation = match &self.wallet.hd_path {
@@ -92,6 +91,14 @@ impl EthereumOpts {
             let trezor = Trezor::new(derivation, chain_id.as_u64(), None).await?;
 
             Ok(Some(WalletType::Trezor(SignerMiddleware::new(provider, trezor))))
+        } else if let Some(url) = &self.wallet.signer_url {
+            let remote = Web3Signer::connect_with(url, chain_id.as_u64(), self.wallet.from).await?;
+
+            Ok(Some(WalletType::Remote(SignerMiddleware::new(provider, remote))))
+        } else if let Some(key_id) = &self.wallet.aws_kms_key_id {
+            let aws = aws_signers(std::slice::from_ref(key_id), chain_id.as_u64()).await?.remove(0);
+
+            Ok(Some(WalletType::Aws(SignerMiddleware::new(provider, aws))))
         } else {
             let local = self
                 .wallet
@@ -9,16 +9,20 @@ use crate::cmd::forge::{
     cache::CacheArgs,
     config, coverage,
     create::CreateArgs,
+    create2::Create2Args,
+    daemon::DaemonArgs,
     debug::DebugArgs,
+    fixture::FixtureArgs,
     flatten,
     fmt::FmtArgs,
-    fourbyte::UploadSelectorsArgs,
+    fourbyte::{SelectorsArgs, UploadSelectorsArgs},
     init::InitArgs,
     inspect,
     install::InstallArgs,
+    mutate::MutateArgs,
     remappings::RemappingArgs,
     script::ScriptArgs,
-    snapshot, test, tree,
+    snapshot, storage_diff, test, tree, upgrade_check,
     verify::{VerifyArgs, VerifyCheckArgs},
 };
 use serde::Serialize;
@@ -69,6 +73,21 @@ pub enum Subcommands {
     #[clap(visible_alias = "d", about = "Debugs a single smart contract as a script.")]
     Debug(DebugArgs),
 
+    #[clap(
+        about = "Run the setUp function of a test contract and push the resulting state to a running Anvil node."
+    )]
+    Fixture(FixtureArgs),
+
+    #[clap(
+        about = "Apply source-level mutations to the project's contracts and report the mutants that are not detected by the tests."
+    )]
+    Mutate(MutateArgs),
+
+    #[clap(
+        about = "Run a long-lived daemon that serves compile, test and trace decoding requests over a unix socket, e.g. for editor integrations."
+    )]
+    Daemon(DaemonArgs),
+
     #[clap(
         visible_alias = "u",
         about = "Update one or multiple dependencies.",
@@ -115,6 +134,11 @@ pub enum Subcommands {
     #[clap(visible_alias = "c", about = "Deploy a smart contract.")]
     Create(CreateArgs),
 
+    #[clap(
+        about = "Compute the CREATE2 address of a contract, optionally mining a vanity salt, and deploy it through the CREATE2 deployer."
+    )]
+    Create2(Create2Args),
+
     #[clap(about = "Create a new Forge project.")]
     Init(InitArgs),
 
@@ -158,15 +182,30 @@ pub enum Subcommands {
 
     #[clap(
         visible_alias = "up",
-        about = "Uploads abi of given contract to https://sig.eth.samczsun.com function selector database."
+        about = "Uploads abi of given contract to https://openchain.xyz function selector database."
     )]
     UploadSelectors(UploadSelectorsArgs),
 
+    #[clap(visible_alias = "se", about = "Manage the selectors of the project's contracts.")]
+    Selectors(SelectorsArgs),
+
     #[clap(
         visible_alias = "tr",
         about = "Display a tree visualization of the project's dependency graph."
     )]
     Tree(tree::TreeArgs),
+
+    #[clap(
+        visible_alias = "sd",
+        about = "Compare the storage layouts of two versions of a contract, e.g. before upgrading a proxy."
+    )]
+    StorageDiff(storage_diff::StorageDiffArgs),
+
+    #[clap(
+        visible_alias = "uc",
+        about = "Check that a contract is safe to use as the implementation of a proxy, e.g. in CI before an upgrade."
+    )]
+    UpgradeCheck(upgrade_check::UpgradeCheckArgs),
 }
 
 // A set of solc compiler settings that can be set via command line arguments, which are intended
@@ -4,11 +4,13 @@ pub mod forge;
 mod chain;
 mod ethereum;
 mod multi_wallet;
+mod remote_signer;
 mod transaction;
 mod wallet;
 
 pub use chain::*;
 pub use ethereum::*;
 pub use multi_wallet::*;
+pub use remote_signer::*;
 pub use transaction::*;
 pub use wallet::*;
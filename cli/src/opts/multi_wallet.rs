@@ -6,16 +6,17 @@ use std::{
 use clap::Parser;
 use ethers::{
     middleware::SignerMiddleware,
-    prelude::{Http, Middleware, Provider, RetryClient, Signer},
-    signers::{HDPath as LedgerHDPath, Ledger, LocalWallet, Trezor, TrezorHDPath},
+    prelude::{Middleware, Signer},
+    signers::{AwsSigner, HDPath as LedgerHDPath, Ledger, LocalWallet, Trezor, TrezorHDPath},
     types::Address,
 };
 use eyre::Result;
 
+use foundry_common::provider::RetryProvider;
 use foundry_config::Config;
 use serde::Serialize;
 
-use super::{wallet::WalletTrait, WalletType};
+use super::{aws_signers, wallet::WalletTrait, WalletType, Web3Signer};
 
 macro_rules! get_wallets {
     ($id:ident, [ $($wallets:expr),+ ], $call:expr) => {
@@ -81,9 +82,11 @@ The wallet options can either be:
 1. Ledger
 2. Trezor
 3. Mnemonics (via file path)
-4. Keystores (via file path)
+4. Keystores (via file path or account name)
 5. Private Keys (cleartext in CLI)
 6. Private Keys (interactively via secure prompt)
+7. Remote signer (via Web3Signer URL)
+8. AWS KMS (via key ids)
 "#
 )]
 pub struct MultiWallet {
@@ -100,7 +103,7 @@ pub struct MultiWallet {
     #[clap(
         long = "private-keys",
         help_heading = "WALLET OPTIONS - RAW",
-        help = "Use the provided private key.",
+        help = "Use the provided private keys. Can be used multiple times, every transaction is signed by the key of its sender.",
         value_name = "RAW_PRIVATE_KEYS"
     )]
     pub private_keys: Option<Vec<String>>,
@@ -149,6 +152,14 @@ pub struct MultiWallet {
     )]
     pub keystore_passwords: Option<Vec<String>>,
 
+    #[clap(
+        long = "account",
+        help_heading = "WALLET OPTIONS - KEYSTORE",
+        help = "Use the keystores of the given accounts in the foundry keystore directory, see `cast wallet import`.",
+        value_name = "ACCOUNT_NAMES"
+    )]
+    pub accounts: Option<Vec<String>>,
+
     #[clap(
         short,
         long = "ledger",
@@ -173,6 +184,24 @@ pub struct MultiWallet {
     )]
     pub hd_paths: Option<Vec<String>>,
 
+    #[clap(
+        env = "ETH_SIGNER_URL",
+        long = "signer-url",
+        help_heading = "WALLET OPTIONS - REMOTE",
+        help = "Sign with the keys held by the Web3Signer instance at the given URL.",
+        value_name = "URL"
+    )]
+    pub signer_url: Option<String>,
+
+    #[clap(
+        env = "AWS_KMS_KEY_IDS",
+        long = "aws-kms-key-id",
+        help_heading = "WALLET OPTIONS - REMOTE",
+        help = "Sign with the AWS KMS keys of the given ids.",
+        value_name = "KEY_IDS"
+    )]
+    pub aws_kms_key_ids: Option<Vec<String>>,
+
     #[clap(
         env = "ETH_FROM",
         short = 'a',
@@ -191,7 +220,7 @@ impl MultiWallet {
     /// error, if it can't find all.
     pub async fn find_all(
         &self,
-        provider: Arc<Provider<RetryClient<Http>>>,
+        provider: Arc<RetryProvider>,
         mut addresses: HashSet<Address>,
     ) -> Result<HashMap<Address, WalletType>> {
         println!("\n###\nFinding wallets for all the necessary addresses...");
@@ -205,10 +234,13 @@ impl MultiWallet {
             [
                 self.trezors(chain).await?,
                 self.ledgers(chain).await?,
+                self.remotes(chain).await?,
+                self.aws_signers(chain).await?,
                 self.private_keys()?,
                 self.interactives()?,
                 self.mnemonics()?,
-                self.keystores()?
+                self.keystores()?,
+                self.accounts()?
             ],
             for wallet in wallets.into_iter() {
                 let address = wallet.address();
@@ -283,6 +315,17 @@ impl MultiWallet {
         Ok(None)
     }
 
+    pub fn accounts(&self) -> Result<Option<Vec<LocalWallet>>> {
+        if let Some(accounts) = &self.accounts {
+            let mut wallets = vec![];
+            for account in accounts {
+                wallets.push(self.get_from_account(account)?);
+            }
+            return Ok(Some(wallets))
+        }
+        Ok(None)
+    }
+
     pub fn mnemonics(&self) -> Result<Option<Vec<LocalWallet>>> {
         if let (Some(mnemonic_paths), Some(mnemonic_indexes)) =
             (self.mnemonic_paths.as_ref(), self.mnemonic_indexes.as_ref())
@@ -296,6 +339,20 @@ impl MultiWallet {
         Ok(None)
     }
 
+    pub async fn remotes(&self, chain_id: u64) -> Result<Option<Vec<Web3Signer>>> {
+        if let Some(url) = &self.signer_url {
+            return Ok(Some(Web3Signer::connect(url, chain_id).await?))
+        }
+        Ok(None)
+    }
+
+    pub async fn aws_signers(&self, chain_id: u64) -> Result<Option<Vec<AwsSigner<'static>>>> {
+        if let Some(key_ids) = &self.aws_kms_key_ids {
+            return Ok(Some(aws_signers(key_ids, chain_id).await?))
+        }
+        Ok(None)
+    }
+
     pub async fn ledgers(&self, chain_id: u64) -> Result<Option<Vec<Ledger>>> {
         if self.ledger {
             create_hw_wallets!(self, chain_id, get_from_ledger, wallets);
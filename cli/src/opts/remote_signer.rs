@@ -0,0 +1,232 @@
+//! Signing with keys held remotely, by a [Web3Signer](https://docs.web3signer.consensys.net)
+//! instance or by AWS KMS.
+//!
+//! The remote signers only sign: the nonces of the transactions of a script are allocated by the
+//! broadcast, per sender, before they are signed.
+use async_trait::async_trait;
+use ethers::{
+    prelude::Signer,
+    signers::AwsSigner,
+    types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Address, Signature,
+    },
+    utils::keccak256,
+};
+use eyre::WrapErr;
+use rusoto_core::Region;
+use rusoto_kms::KmsClient;
+use serde_json::json;
+
+/// The errors of a [Web3Signer]
+#[derive(Debug, thiserror::Error)]
+pub enum Web3SignerError {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid response from the remote signer: {0}")]
+    InvalidResponse(String),
+    #[error("Failed to encode the typed data: {0}")]
+    Eip712(String),
+}
+
+/// A signer that holds no keys itself, but asks a [Web3Signer](https://docs.web3signer.consensys.net)
+/// instance to sign with one of its keys.
+///
+/// Web3Signer signs the keccak256 hash of the data it is sent, so the signer sends the preimage of
+/// the hash it needs signed.
+#[derive(Debug, Clone)]
+pub struct Web3Signer {
+    client: reqwest::Client,
+    /// The base url of the Web3Signer instance
+    url: String,
+    /// The public key of the key to sign with, as listed by the instance
+    public_key: String,
+    address: Address,
+    chain_id: u64,
+}
+
+impl Web3Signer {
+    /// Returns a signer for every key held by the Web3Signer instance at `url`.
+    pub async fn connect(url: &str, chain_id: u64) -> Result<Vec<Self>, Web3SignerError> {
+        let client = reqwest::Client::new();
+        let url = url.trim_end_matches('/').to_string();
+        let public_keys: Vec<String> = client
+            .get(format!("{url}/api/v1/eth1/publicKeys"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        public_keys
+            .into_iter()
+            .map(|public_key| {
+                Ok(Self {
+                    client: client.clone(),
+                    url: url.clone(),
+                    address: public_key_to_address(&public_key)?,
+                    public_key,
+                    chain_id,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the signer for the key of the given address, or for the only key held by the
+    /// Web3Signer instance at `url` if no address is given.
+    pub async fn connect_with(
+        url: &str,
+        chain_id: u64,
+        address: Option<Address>,
+    ) -> eyre::Result<Self> {
+        let mut signers = Self::connect(url, chain_id).await?;
+        match address {
+            Some(address) => signers
+                .into_iter()
+                .find(|signer| signer.address == address)
+                .ok_or_else(|| eyre::eyre!("The remote signer holds no key for {:?}", address)),
+            None if signers.len() == 1 => Ok(signers.remove(0)),
+            None => eyre::bail!(
+                "The remote signer holds {} keys, use `--from` to select one of them",
+                signers.len()
+            ),
+        }
+    }
+
+    /// Has the instance sign the keccak256 hash of `data`.
+    async fn sign(&self, data: &[u8]) -> Result<Signature, Web3SignerError> {
+        let signature = self
+            .client
+            .post(format!("{}/api/v1/eth1/sign/{}", self.url, self.public_key))
+            .json(&json!({ "data": format!("0x{}", hex::encode(data)) }))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_signature(signature.trim())
+    }
+}
+
+#[async_trait]
+impl Signer for Web3Signer {
+    type Error = Web3SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        let message = message.as_ref();
+        let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+        data.extend_from_slice(message);
+        self.sign(&data).await
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        let mut tx = tx.clone();
+        if tx.chain_id().is_none() {
+            tx.set_chain_id(self.chain_id);
+        }
+        let chain_id = tx.chain_id().map(|id| id.as_u64()).unwrap_or(self.chain_id);
+
+        let mut signature = self.sign(&tx.rlp()).await?;
+        // EIP-155
+        signature.v = signature.v - 27 + 35 + chain_id * 2;
+        Ok(signature)
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        let domain_separator =
+            payload.domain_separator().map_err(|err| Web3SignerError::Eip712(err.to_string()))?;
+        let struct_hash =
+            payload.struct_hash().map_err(|err| Web3SignerError::Eip712(err.to_string()))?;
+        let data = [&[0x19, 0x01], &domain_separator[..], &struct_hash[..]].concat();
+        self.sign(&data).await
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+/// Returns a signer for every given AWS KMS key id.
+///
+/// The region and the credentials are read from the environment, e.g. `AWS_REGION`,
+/// `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`.
+pub async fn aws_signers(
+    key_ids: &[String],
+    chain_id: u64,
+) -> eyre::Result<Vec<AwsSigner<'static>>> {
+    // The signers borrow the client, which lives as long as the command
+    let kms: &'static KmsClient = Box::leak(Box::new(KmsClient::new(Region::default())));
+
+    let mut signers = Vec::with_capacity(key_ids.len());
+    for key_id in key_ids {
+        let signer = AwsSigner::new(kms, key_id, chain_id)
+            .await
+            .wrap_err_with(|| format!("Failed to load the AWS KMS key {key_id}"))?;
+        signers.push(signer);
+    }
+    Ok(signers)
+}
+
+/// Returns the address of an uncompressed secp256k1 public key, with or without its `0x04` prefix
+fn public_key_to_address(public_key: &str) -> Result<Address, Web3SignerError> {
+    let invalid = || Web3SignerError::InvalidResponse(format!("invalid public key {public_key}"));
+    let bytes = hex::decode(public_key.trim_start_matches("0x")).map_err(|_| invalid())?;
+    let key = match bytes.len() {
+        64 => &bytes[..],
+        65 if bytes[0] == 4 => &bytes[1..],
+        _ => return Err(invalid()),
+    };
+    Ok(Address::from_slice(&keccak256(key)[12..]))
+}
+
+/// Parses a 65 bytes `r || s || v` signature, with `v` either 0/1 or 27/28
+fn parse_signature(signature: &str) -> Result<Signature, Web3SignerError> {
+    let invalid = || Web3SignerError::InvalidResponse(format!("invalid signature {signature}"));
+    let bytes = hex::decode(signature.trim_start_matches("0x")).map_err(|_| invalid())?;
+    if bytes.len() != 65 {
+        return Err(invalid())
+    }
+    let mut signature = Signature::try_from(&bytes[..]).map_err(|_| invalid())?;
+    if signature.v < 27 {
+        signature.v += 27;
+    }
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_derive_address_from_public_key() {
+        // the public key of the private key `1`
+        let public_key = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+        let address: Address = "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf".parse().unwrap();
+        assert_eq!(public_key_to_address(public_key).unwrap(), address);
+        assert_eq!(public_key_to_address(&format!("0x04{public_key}")).unwrap(), address);
+        assert!(public_key_to_address("0x1234").is_err());
+    }
+
+    #[test]
+    fn can_parse_signature() {
+        let signature = format!("0x{}{}01", "11".repeat(32), "22".repeat(32));
+        let signature = parse_signature(&signature).unwrap();
+        assert_eq!(signature.v, 28);
+        assert!(parse_signature("0x1234").is_err());
+    }
+}
@@ -1,24 +1,28 @@
-use std::{str::FromStr, sync::Arc};
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+use super::Web3Signer;
 
 use clap::Parser;
 use ethers::{
     middleware::SignerMiddleware,
-    prelude::{RetryClient, Signer},
-    providers::{Http, Provider},
-    signers::{coins_bip39::English, Ledger, LocalWallet, MnemonicBuilder, Trezor},
+    prelude::Signer,
+    signers::{coins_bip39::English, AwsSigner, Ledger, LocalWallet, MnemonicBuilder, Trezor},
     types::Address,
 };
 use eyre::{eyre, Result};
-use foundry_common::fs;
+use foundry_common::{fs, provider::RetryProvider};
+use foundry_config::Config;
 use serde::Serialize;
 
-type SignerClient<T> = SignerMiddleware<Arc<Provider<RetryClient<Http>>>, T>;
+type SignerClient<T> = SignerMiddleware<Arc<RetryProvider>, T>;
 
 #[derive(Debug)]
 pub enum WalletType {
     Local(SignerClient<LocalWallet>),
     Ledger(SignerClient<Ledger>),
     Trezor(SignerClient<Trezor>),
+    Remote(SignerClient<Web3Signer>),
+    Aws(SignerClient<AwsSigner<'static>>),
 }
 
 impl From<SignerClient<Ledger>> for WalletType {
@@ -39,17 +43,31 @@ impl From<SignerClient<LocalWallet>> for WalletType {
     }
 }
 
+impl From<SignerClient<Web3Signer>> for WalletType {
+    fn from(remote: SignerClient<Web3Signer>) -> WalletType {
+        WalletType::Remote(remote)
+    }
+}
+
+impl From<SignerClient<AwsSigner<'static>>> for WalletType {
+    fn from(aws: SignerClient<AwsSigner<'static>>) -> WalletType {
+        WalletType::Aws(aws)
+    }
+}
+
 impl WalletType {
     pub fn chain_id(&self) -> u64 {
         match self {
             WalletType::Local(inner) => inner.signer().chain_id(),
             WalletType::Ledger(inner) => inner.signer().chain_id(),
             WalletType::Trezor(inner) => inner.signer().chain_id(),
+            WalletType::Remote(inner) => inner.signer().chain_id(),
+            WalletType::Aws(inner) => inner.signer().chain_id(),
         }
     }
 }
 
-#[derive(Parser, Debug, Clone, Serialize)]
+#[derive(Parser, Debug, Clone, Serialize, Default)]
 #[cfg_attr(not(doc), allow(missing_docs))]
 #[cfg_attr(
     doc,
@@ -58,9 +76,11 @@ The wallet options can either be:
 1. Ledger
 2. Trezor
 3. Mnemonic (via file path)
-4. Keystore (via file path)
+4. Keystore (via file path or account name)
 5. Private Key (cleartext in CLI)
 6. Private Key (interactively via secure prompt)
+7. Remote signer (via Web3Signer URL)
+8. AWS KMS (via key id)
 "#
 )]
 pub struct Wallet {
@@ -115,6 +135,15 @@ pub struct Wallet {
     )]
     pub keystore_password: Option<String>,
 
+    #[clap(
+        long = "account",
+        help_heading = "WALLET OPTIONS - KEYSTORE",
+        help = "Use the keystore of the given account in the foundry keystore directory, see `cast wallet import`.",
+        conflicts_with = "keystore-path",
+        value_name = "ACCOUNT_NAME"
+    )]
+    pub account: Option<String>,
+
     #[clap(
         short,
         long = "ledger",
@@ -139,6 +168,24 @@ pub struct Wallet {
     )]
     pub hd_path: Option<String>,
 
+    #[clap(
+        env = "ETH_SIGNER_URL",
+        long = "signer-url",
+        help_heading = "WALLET OPTIONS - REMOTE",
+        help = "Sign with a key held by the Web3Signer instance at the given URL. Use --from to select the key if it holds more than one.",
+        value_name = "URL"
+    )]
+    pub signer_url: Option<String>,
+
+    #[clap(
+        env = "AWS_KMS_KEY_ID",
+        long = "aws-kms-key-id",
+        help_heading = "WALLET OPTIONS - REMOTE",
+        help = "Sign with the AWS KMS key of the given id.",
+        value_name = "KEY_ID"
+    )]
+    pub aws_kms_key_id: Option<String>,
+
     #[clap(
         env = "ETH_FROM",
         short,
@@ -164,6 +211,9 @@ impl Wallet {
     }
 
     pub fn keystore(&self) -> Result<Option<LocalWallet>> {
+        if let Some(ref account) = self.account {
+            return Ok(Some(self.get_from_account(account)?))
+        }
         self.get_from_keystore(self.keystore_path.as_ref(), self.keystore_password.as_ref())
     }
 
@@ -197,6 +247,13 @@ pub trait WalletTrait {
         Ok(MnemonicBuilder::<English>::default().phrase(mnemonic.as_str()).index(index)?.build()?)
     }
 
+    fn get_from_account(&self, name: &str) -> Result<LocalWallet> {
+        let path = account_keystore_path(name)?;
+        println!("Insert password for account `{name}`:");
+        let password = rpassword::read_password()?;
+        Ok(LocalWallet::decrypt_keystore(path, password)?)
+    }
+
     fn get_from_keystore(
         &self,
         keystore_path: Option<&String>,
@@ -214,6 +271,22 @@ pub trait WalletTrait {
     }
 }
 
+/// Returns the path of the keystore of the account with the given name in the foundry keystore
+/// directory, see [Config::foundry_keystores_dir]
+pub fn account_keystore_path(name: &str) -> Result<PathBuf> {
+    let dir = Config::foundry_keystores_dir()
+        .ok_or_else(|| eyre!("Failed to find the foundry keystore directory"))?;
+    let path = dir.join(name);
+    if !path.is_file() {
+        eyre::bail!(
+            "No account named `{}` found in {}, see `cast wallet list`",
+            name,
+            dir.display()
+        )
+    }
+    Ok(path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,11 +299,14 @@ mod tests {
             private_key: Some("123".to_string()),
             keystore_path: None,
             keystore_password: None,
+            account: None,
             mnemonic_path: None,
             ledger: false,
             trezor: false,
             hd_path: None,
             mnemonic_index: 0,
+            signer_url: None,
+            aws_kms_key_id: None,
         };
         match wallet.private_key() {
             Ok(_) => {
@@ -1,12 +1,13 @@
 use console::Emoji;
 use ethers::{
     abi::token::{LenientTokenizer, Tokenizer},
-    prelude::{Http, Provider, RetryClient, TransactionReceipt},
+    prelude::TransactionReceipt,
     solc::EvmVersion,
     types::U256,
     utils::format_units,
 };
 use forge::executor::SpecId;
+use foundry_common::provider::{ProvidersManager, RetryProvider};
 use foundry_config::Config;
 use std::{
     future::Future,
@@ -14,7 +15,10 @@ use std::{
     path::Path,
     process::{Command, Output},
     str::FromStr,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 use tracing_error::ErrorLayer;
@@ -171,6 +175,31 @@ pub fn block_on<F: Future>(future: F) -> F::Output {
     rt.block_on(future)
 }
 
+/// Installs a Ctrl-C handler and returns the flag it sets.
+///
+/// The first Ctrl-C only sets the flag, so long running commands can stop cooperatively and report
+/// what they have done so far. A second Ctrl-C aborts the process immediately.
+pub fn cancel_on_ctrl_c() -> Arc<AtomicBool> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let flag = cancelled.clone();
+    std::thread::spawn(move || {
+        block_on(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return
+            }
+            flag.store(true, Ordering::Relaxed);
+            eprintln!(
+                "\n{} Finishing in-flight work, press Ctrl-C again to abort",
+                Paint::yellow("Cancelling...").bold()
+            );
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130)
+            }
+        })
+    });
+    cancelled
+}
+
 /// Conditionally print a message
 ///
 /// This macro accepts a predicate and the message to print if the predicate is tru
@@ -202,17 +231,25 @@ pub fn enable_paint() {
 
 /// Gives out a provider with a `100ms` interval poll if it's a localhost URL (most likely an anvil
 /// node) and with the default, `7s` if otherwise.
-pub fn get_http_provider(url: &str, aggressive: bool) -> Arc<Provider<RetryClient<Http>>> {
+///
+/// `url_or_alias` can also be an alias of the `rpc_endpoints` of the config the `providers` were
+/// built from. The provider uses the rate limit, retries and fallback urls of the endpoint, if it
+/// configures them.
+pub fn get_provider(
+    providers: &ProvidersManager,
+    url_or_alias: &str,
+    aggressive: bool,
+) -> eyre::Result<Arc<RetryProvider>> {
     let (max_retry, initial_backoff) = if aggressive { (1000, 1) } else { (10, 1000) };
 
-    let provider = Provider::<RetryClient<Http>>::new_client(url, max_retry, initial_backoff)
-        .expect("Bad fork provider.");
+    let url = providers.endpoint(url_or_alias)?.url;
+    let provider = providers.provider(url_or_alias, max_retry, initial_backoff)?;
 
-    Arc::new(if url.contains("127.0.0.1") || url.contains("localhost") {
+    Ok(Arc::new(if url.contains("127.0.0.1") || url.contains("localhost") {
         provider.interval(Duration::from_millis(100))
     } else {
         provider
-    })
+    }))
 }
 
 /// Prints parts of the receipt to stdout
@@ -124,6 +124,11 @@ impl ScriptTester {
         self.run(expected)
     }
 
+    pub fn unsigned(&mut self, expected: ScriptOutcome) -> &mut Self {
+        self.cmd.arg("--unsigned");
+        self.run(expected)
+    }
+
     pub fn resume(&mut self, expected: ScriptOutcome) -> &mut Self {
         self.cmd.arg("--resume");
         self.run(expected)
@@ -168,6 +173,7 @@ impl ScriptTester {
 pub enum ScriptOutcome {
     OkSimulation,
     OkBroadcast,
+    OkUnsigned,
     WarnSpecifyDeployer,
     MissingSender,
     MissingWallet,
@@ -179,6 +185,7 @@ impl ScriptOutcome {
         match self {
             ScriptOutcome::OkSimulation => "SIMULATION COMPLETE. To broadcast these",
             ScriptOutcome::OkBroadcast => "ONCHAIN EXECUTION COMPLETE & SUCCESSFUL",
+            ScriptOutcome::OkUnsigned => "UNSIGNED TRANSACTIONS written to",
             ScriptOutcome::WarnSpecifyDeployer => "You have more than one deployer who could predeploy libraries. Using `--sender` instead.",
             ScriptOutcome::MissingSender => "You seem to be using Foundry's default sender. Be sure to set your own --sender",
             ScriptOutcome::MissingWallet => "No associated wallet",
@@ -190,6 +197,7 @@ impl ScriptOutcome {
         match self {
             ScriptOutcome::OkSimulation |
             ScriptOutcome::OkBroadcast |
+            ScriptOutcome::OkUnsigned |
             ScriptOutcome::WarnSpecifyDeployer => false,
             ScriptOutcome::MissingSender |
             ScriptOutcome::MissingWallet |
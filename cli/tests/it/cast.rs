@@ -49,6 +49,25 @@ casttest!(new_wallet_keystore_with_password, |_: TestProject, mut cmd: TestComma
     assert!(out.contains("Public Address of the key"));
 });
 
+// tests that `cast wallet sign-typed-data` signs the `Mail` example of EIP-712
+casttest!(wallet_sign_typed_data, |_: TestProject, mut cmd: TestCommand| {
+    let typed_data = r#"{"types":{"EIP712Domain":[{"name":"name","type":"string"},{"name":"version","type":"string"},{"name":"chainId","type":"uint256"},{"name":"verifyingContract","type":"address"}],"Person":[{"name":"name","type":"string"},{"name":"wallet","type":"address"}],"Mail":[{"name":"from","type":"Person"},{"name":"to","type":"Person"},{"name":"contents","type":"string"}]},"primaryType":"Mail","domain":{"name":"Ether Mail","version":"1","chainId":1,"verifyingContract":"0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"},"message":{"from":{"name":"Cow","wallet":"0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"},"to":{"name":"Bob","wallet":"0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"},"contents":"Hello, Bob!"}}"#;
+
+    // keccak256("cow")
+    cmd.args([
+        "wallet",
+        "sign-typed-data",
+        typed_data,
+        "--private-key",
+        "c85ef7d79691fe79573b1a7064c19c1a9819ebdbd1faaab1a8ec92344438aaf4",
+    ]);
+    let output = cmd.stdout_lossy();
+    assert_eq!(
+        output.trim(),
+        "Signature: 0x4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b915621c"
+    );
+});
+
 // tests that the `cast upload-signatures` command works correctly
 casttest!(upload_signatures, |_: TestProject, mut cmd: TestCommand| {
     // test no prefix is accepted as function
@@ -8,7 +8,9 @@ use foundry_cli_test_utils::{
     forgetest, forgetest_init,
     util::{read_string, OutputExt, TestCommand, TestProject},
 };
-use foundry_config::{parse_with_profile, BasicConfig, Chain, Config, SolidityErrorCode};
+use foundry_config::{
+    parse_with_profile, BasicConfig, Chain, CompilerOverride, Config, SolidityErrorCode,
+};
 use std::{env, fs, path::PathBuf};
 
 // tests `--help` is printed to std out
@@ -334,6 +336,26 @@ forgetest!(can_clean_hardhat, PathStyle::HardHat, |prj: TestProject, mut cmd: Te
     prj.assert_cleaned();
 });
 
+// checks that the sources of compiler overrides are compiled by a separate job
+forgetest_init!(can_build_with_compiler_overrides, |prj: TestProject, mut cmd: TestCommand| {
+    let config = Config {
+        compiler_overrides: [(
+            "src/**".to_string(),
+            CompilerOverride { optimizer_runs: Some(1_000_000), ..Default::default() },
+        )]
+        .into(),
+        ..Default::default()
+    };
+    prj.write_config(config);
+    cmd.arg("build");
+    cmd.assert_non_empty_stdout();
+
+    // the artifacts of both jobs are written to the same directory
+    assert!(prj.root().join("out/Contract.sol/Contract.json").exists());
+    assert!(prj.root().join("out/Contract.t.sol/ContractTest.json").exists());
+    assert!(prj.root().join("cache/overrides/0/solidity-files-cache.json").exists());
+});
+
 // checks that `clean` also works with the "out" value set in Config
 forgetest_init!(can_clean_config, |prj: TestProject, mut cmd: TestCommand| {
     let config = Config { out: "custom-out".into(), ..Default::default() };
@@ -545,6 +567,31 @@ contract Foo {
     check_output(cmd.stdout_lossy());
 });
 
+// tests that `forge inspect --slot` computes storage slots from the storage layout
+forgetest!(can_inspect_storage_slot, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Foo",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Foo {
+    uint256[] public values;
+}
+    "#,
+        )
+        .unwrap();
+
+    cmd.args(["inspect", "Foo", "storage", "--slot", "values[0]"]);
+    assert_eq!(
+        cmd.stdout_lossy(),
+        "Slot: 0x290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563\nOffset: 0\nType: uint256\n"
+    );
+
+    cmd.forge_fuse().args(["inspect", "Foo", "abi", "--slot", "values[0]"]);
+    cmd.assert_err();
+});
+
 // test that `forge snapshot` commands work
 forgetest!(
     #[serial_test::serial]
@@ -861,3 +908,123 @@ contract MyTokenCopy is MyToken {
         assert!(output.contains("Compiler run successful",));
     }
 );
+
+// checks that `forge daemon` serves compile, test and trace decoding requests over its socket
+#[cfg(unix)]
+forgetest!(can_serve_daemon_requests, |prj: TestProject, mut cmd: TestCommand| {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixStream,
+        thread,
+        time::Duration,
+    };
+
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPass() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let socket = prj.root().join("forge.sock");
+    cmd.arg("daemon").arg("--socket").arg(&socket);
+    let mut daemon = cmd.cmd().spawn().unwrap();
+
+    let mut stream = (0..600)
+        .find_map(|_| {
+            let stream = UnixStream::connect(&socket).ok();
+            if stream.is_none() {
+                thread::sleep(Duration::from_millis(100));
+            }
+            stream
+        })
+        .expect("daemon did not start");
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request = |request: serde_json::Value| -> serde_json::Value {
+        writeln!(stream, "{request}").unwrap();
+        let mut response = String::new();
+        reader.read_line(&mut response).unwrap();
+        serde_json::from_str(&response).unwrap()
+    };
+
+    let response = request(serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "compile" }));
+    assert_eq!(response["result"]["success"], true);
+
+    let response = request(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "runTest",
+        "params": { "contract": "ATest", "test": "testPass" }
+    }));
+    assert_eq!(response["result"]["success"], true);
+
+    let response = request(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "decodeTraces",
+        "params": { "traces": response["result"]["traces"] }
+    }));
+    let traces = response["result"].as_array().unwrap();
+    assert!(traces.iter().any(|trace| trace.as_str().unwrap().contains("ATest::testPass()")));
+
+    let response = request(serde_json::json!({ "jsonrpc": "2.0", "id": 4, "method": "unknown" }));
+    assert_eq!(response["error"]["code"], -32601);
+
+    request(serde_json::json!({ "jsonrpc": "2.0", "id": 5, "method": "shutdown" }));
+    assert!(daemon.wait().unwrap().success());
+    assert!(!socket.exists());
+});
+
+// checks that `forge upgrade-check` reports unsafe implementations
+forgetest!(can_check_upgrade_safety, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Vault",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract VaultV1 {
+    uint256 public total;
+
+    function initialize(uint256 _total) external {
+        total = _total;
+    }
+}
+
+contract VaultV2 {
+    uint256 public fee = 1;
+    uint256 public total;
+
+    function initialize(uint256 _total) external {
+        total = _total;
+    }
+}
+"#,
+        )
+        .unwrap();
+
+    cmd.args(["upgrade-check", "VaultV2", "--reference", "VaultV1", "--json"]);
+    let output = cmd.unchecked_output();
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["passed"], false);
+    let kinds = report["findings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|finding| finding["kind"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert!(kinds.contains(&"storage-layout"));
+    assert!(kinds.contains(&"state-variable-assignment"));
+    assert!(kinds.contains(&"missing-initializer-guard"));
+});
@@ -16,7 +16,7 @@ use foundry_config::{
     Config, OptimizerDetails, SolcReq,
 };
 use path_slash::PathBufExt;
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{collections::BTreeMap, fs, path::PathBuf, str::FromStr};
 
 // tests all config values that are in use
 forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
@@ -35,6 +35,7 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         force: true,
         evm_version: EvmVersion::Byzantium,
         gas_reports: vec!["Contract".to_string()],
+        coverage_exclude: vec!["src/vendored/**".to_string()],
         solc: Some(SolcReq::Local(PathBuf::from("custom-solc"))),
         auto_detect_solc: false,
         offline: true,
@@ -59,13 +60,24 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         fuzz_runs: 1000,
         fuzz_max_local_rejects: 2000,
         fuzz_max_global_rejects: 100203,
+        fuzz_include_storage: false,
+        fuzz_include_push_bytes: false,
+        fuzz_dictionary_weight: 70,
+        fuzz_keep_logs: 5,
+        fuzz_seed: Some(U256::from(1000)),
+        gas_limit_per_test: Some(1000000),
         ffi: true,
+        allow_wait: true,
+        precompiles: vec![],
         sender: "00a329c0648769A73afAc7F9381D08FB43dBEA72".parse().unwrap(),
         tx_origin: "00a329c0648769A73afAc7F9F81E08FB43dBEA72".parse().unwrap(),
         initial_balance: U256::from(0xffffffffffffffffffffffffu128),
         block_number: 10,
         fork_block_number: Some(200),
+        fork_block_numbers: BTreeMap::from([("mainnet".to_string(), 15_000_000)]),
+        fork_block_confirmations: Some(12),
         chain_id: Some(9999.into()),
+        chain_profile: None,
         gas_limit: 99_000_000u64.into(),
         gas_price: Some(999),
         block_base_fee_per_gas: 10,
@@ -83,15 +95,20 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         ],
         ignored_error_codes: vec![],
         via_ir: true,
+        compiler_overrides: Default::default(),
+        fee_scenarios: Default::default(),
         rpc_storage_caching: StorageCachingConfig {
             chains: CachedChains::None,
             endpoints: CachedEndpoints::Remote,
         },
         no_storage_caching: true,
+        fork_prefetch: true,
         bytecode_hash: Default::default(),
         revert_strings: Some(RevertStrings::Strip),
         sparse_mode: true,
         allow_paths: vec![],
+        raw_artifacts: vec![],
+        fs_permissions: Default::default(),
         rpc_endpoints: Default::default(),
         build_info: false,
         build_info_path: None,
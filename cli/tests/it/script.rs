@@ -176,6 +176,49 @@ Gas used: 3957
     ));
 });
 
+// Tests that the script contract is deployed with the constructor arguments of a file
+forgetest!(
+    can_execute_script_command_with_constructor_args,
+    |prj: TestProject, mut cmd: TestCommand| {
+        let script = prj
+            .inner()
+            .add_source(
+                "Foo",
+                r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Demo {
+    event log_uint(uint);
+    uint256 a;
+    uint256 b;
+    constructor(uint256 _a, uint256 _b) {
+        a = _a;
+        b = _b;
+    }
+    function run() external {
+        emit log_uint(a);
+        emit log_uint(b);
+    }
+}
+   "#,
+            )
+            .unwrap();
+
+        let args = prj.root().join("args.json");
+        std::fs::write(&args, r#"["1", "2"]"#).unwrap();
+        cmd.arg("script").arg(&script).arg("--constructor-args-path").arg(&args);
+        let output = cmd.stdout_lossy();
+        assert!(output.contains("== Logs ==\n  1\n  2\n"));
+
+        // the arguments are validated against the constructor
+        std::fs::write(&args, r#"["1"]"#).unwrap();
+        cmd.forge_fuse().arg("script").arg(&script).arg("--constructor-args-path").arg(&args);
+        let output = cmd.stderr_lossy();
+        assert!(output
+            .contains("`constructor(uint256,uint256)` takes 2 arguments, but 1 were provided"));
+    }
+);
+
 // Tests that the run command can run functions with return values
 forgetest!(can_execute_script_command_with_returned, |prj: TestProject, mut cmd: TestCommand| {
     let script = prj
@@ -239,6 +282,16 @@ forgetest_async!(can_deploy_script_with_lib, |prj: TestProject, cmd: TestCommand
         .await;
 });
 
+forgetest_async!(can_save_unsigned_script, |prj: TestProject, cmd: TestCommand| async move {
+    let (_api, handle) = spawn(NodeConfig::test()).await;
+    let mut tester = ScriptTester::new_broadcast(cmd, &handle.http_endpoint(), prj.root());
+
+    // the senders are impersonated, so no wallets are needed
+    tester
+        .add_sig("BroadcastTestNoLinking", "deployDoesntPanic()")
+        .unsigned(ScriptOutcome::OkUnsigned);
+});
+
 forgetest_async!(can_resume_script, |prj: TestProject, cmd: TestCommand| async move {
     let (_api, handle) = spawn(NodeConfig::test()).await;
     let mut tester = ScriptTester::new_broadcast(cmd, &handle.http_endpoint(), prj.root());
@@ -299,6 +352,37 @@ forgetest_async!(can_deploy_no_arg_broadcast, |prj: TestProject, cmd: TestComman
         .await;
 });
 
+forgetest_async!(can_deploy_with_private_keys, |prj: TestProject, cmd: TestCommand| async move {
+    let (_api, handle) = spawn(NodeConfig::test()).await;
+    let mut tester = ScriptTester::new_broadcast(cmd, &handle.http_endpoint(), prj.root());
+
+    tester
+        .add_deployer(0)
+        .load_private_keys(vec![0, 1])
+        .await
+        .add_sig("BroadcastTestKeys", "deployWithKeys()")
+        .simulate(ScriptOutcome::OkSimulation)
+        .broadcast(ScriptOutcome::OkBroadcast)
+        .assert_nonce_increment(vec![(0, 2), (1, 2)])
+        .await;
+});
+
+forgetest_async!(
+    fail_broadcast_without_private_key,
+    |prj: TestProject, cmd: TestCommand| async move {
+        let (_api, handle) = spawn(NodeConfig::test()).await;
+        let mut tester = ScriptTester::new_broadcast(cmd, &handle.http_endpoint(), prj.root());
+
+        tester
+            .add_deployer(0)
+            .load_private_keys(vec![0])
+            .await
+            .add_sig("BroadcastTestKeys", "deployWithKeys()")
+            .simulate(ScriptOutcome::OkSimulation)
+            .broadcast(ScriptOutcome::MissingWallet);
+    }
+);
+
 forgetest_async!(can_deploy_with_create2, |prj: TestProject, cmd: TestCommand| async move {
     let (api, handle) = spawn(NodeConfig::test()).await;
     let mut tester = ScriptTester::new_broadcast(cmd, &handle.http_endpoint(), prj.root());
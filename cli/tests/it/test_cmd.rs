@@ -4,7 +4,7 @@ use foundry_cli_test_utils::{
     util::{OutputExt, TestCommand, TestProject},
 };
 use foundry_config::Config;
-use std::{path::PathBuf, str::FromStr};
+use std::{fs, path::PathBuf, str::FromStr};
 
 // tests that test filters are handled correctly
 forgetest!(can_set_filter_values, |prj: TestProject, mut cmd: TestCommand| {
@@ -199,6 +199,391 @@ contract FailTest is DSTest {
     cmd.stdout().contains("[PASS]") && !cmd.stdout().contains("[FAIL]")
 });
 
+// tests that using the --match-coverage option only runs files that import a matching file
+forgetest!(can_test_with_match_coverage, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "Counter.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    prj.inner()
+        .add_source(
+            "CounterTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+import "./Counter.sol";
+contract CounterTest is DSTest {
+    function testIncrement() external {
+        Counter counter = new Counter();
+        counter.increment();
+        assertEq(counter.number(), 1);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    prj.inner()
+        .add_source(
+            "FailTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract FailTest is DSTest {
+    function testNothing() external {
+        assertTrue(false);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--match-coverage", "src/Counter.sol"]);
+    cmd.stdout().contains("[PASS]") && !cmd.stdout().contains("[FAIL]")
+});
+
+// tests that --no-build runs the cached artifacts and rejects them once a source changed
+forgetest!(can_test_without_build, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let test = r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPass() external {
+        assertTrue(true);
+    }
+}
+   "#;
+    prj.inner().add_source("ATest.t.sol", test).unwrap();
+
+    // nothing is cached yet
+    cmd.args(["test", "--no-build"]);
+    let stderr = cmd.stderr_lossy();
+    assert!(stderr.contains("run `forge build` first"));
+
+    cmd.forge_fuse().arg("build");
+    cmd.assert_non_empty_stdout();
+
+    cmd.forge_fuse().args(["test", "--no-build"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("[PASS]"));
+    assert!(!stdout.contains("Compiling"));
+
+    // the artifacts were built without the optimizer
+    cmd.forge_fuse().args(["test", "--no-build", "--optimize"]);
+    let stderr = cmd.stderr_lossy();
+    assert!(stderr.contains("do not match the compiler settings"));
+
+    prj.inner().add_source("ATest.t.sol", test.replace("true", "false")).unwrap();
+    cmd.forge_fuse().args(["test", "--no-build"]);
+    let stderr = cmd.stderr_lossy();
+    assert!(stderr.contains("Stale sources"));
+    assert!(stderr.contains("ATest.t.sol (ATest)"));
+});
+
+// tests that `--incremental` only recompiles the sources affected by a change and still runs the
+// test suites of the unaffected sources from their cached artifacts
+forgetest!(can_test_incrementally, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let counter = r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number += 1;
+    }
+}
+   "#;
+    prj.inner().add_source("Counter.sol", counter).unwrap();
+    prj.inner()
+        .add_source(
+            "CounterTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+import "./Counter.sol";
+contract CounterTest is DSTest {
+    function testIncrement() external {
+        Counter counter = new Counter();
+        counter.increment();
+        assertEq(counter.number(), 1);
+    }
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "OtherTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract OtherTest is DSTest {
+    function testOther() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--incremental"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("[PASS] testIncrement()"));
+    assert!(stdout.contains("[PASS] testOther()"));
+
+    // only `Counter.sol` and the test importing it are recompiled
+    prj.inner().add_source("Counter.sol", counter.replace("+= 1;", "+= 1; // changed")).unwrap();
+    cmd.forge_fuse().args(["test", "--incremental"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("Compiling 2 files"));
+    assert!(stdout.contains("[PASS] testIncrement()"));
+    assert!(stdout.contains("[PASS] testOther()"));
+});
+
+// tests that the output limit applies to all logs and traces of a test
+forgetest!(can_limit_test_output, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testLogs() external {
+        emit log("first");
+        emit log("second");
+        emit log("third");
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "-vvvv", "--output-limit", "3"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("first"));
+    assert!(stdout.contains("third"));
+    // the logs used up the limit of the test
+    let traces = stdout.split("Traces:\n").nth(1).unwrap();
+    assert!(traces.lines().next().unwrap().contains("…truncated"));
+
+    let log = fs::read_to_string(prj.cache_path().join("test-output.log")).unwrap();
+    assert!(log.contains("ATest::testLogs()"));
+});
+
+// tests that test contracts whose tests passed are skipped until their code changes
+forgetest!(can_skip_cached_test_results, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let test = r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPass() external {
+        assertTrue(true);
+    }
+}
+   "#;
+    prj.inner().add_source("ATest.t.sol", test).unwrap();
+
+    cmd.args(["test", "--cache-results"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("[PASS]"));
+    assert!(!stdout.contains("Skipped"));
+
+    cmd.forge_fuse().args(["test", "--cache-results"]);
+    let stdout = cmd.stdout();
+    assert!(!stdout.contains("[PASS]"));
+    assert!(stdout.contains("[CACHED] testPass()"));
+    assert!(stdout.contains("Skipped 1 test suites"));
+
+    // the results are only cached when enabled
+    cmd.forge_fuse().arg("test");
+    assert!(cmd.stdout().contains("[PASS]"));
+
+    cmd.forge_fuse().args(["test", "--cache-results", "--force"]);
+    assert!(cmd.stdout().contains("[PASS]"));
+
+    prj.inner()
+        .add_source("ATest.t.sol", test.replace("assertTrue(true)", "assertEq(uint256(1), 1)"))
+        .unwrap();
+    cmd.forge_fuse().args(["test", "--cache-results"]);
+    assert!(cmd.stdout().contains("[PASS]"));
+});
+
+// tests that fuzz tests and tests that read the environment are not skipped, unless the seed of the
+// fuzz tests is pinned
+forgetest!(can_skip_only_deterministic_test_results, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "FuzzTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract FuzzTest is DSTest {
+    function testValue(uint256 value) external {
+        assertEq(value, value);
+    }
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "EnvTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+interface Vm {
+    function envUint(string calldata) external returns (uint256);
+}
+contract EnvTest is DSTest {
+    Vm constant vm = Vm(HEVM_ADDRESS);
+    function testEnv() external {
+        assertEq(vm.envUint("FORGE_TEST_CACHE_VALUE"), 1);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--cache-results"]);
+    cmd.set_env("FORGE_TEST_CACHE_VALUE", "1");
+    assert!(cmd.stdout().contains("[PASS]"));
+
+    cmd.forge_fuse().args(["test", "--cache-results"]);
+    cmd.set_env("FORGE_TEST_CACHE_VALUE", "1");
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("testValue(uint256)"));
+    assert!(stdout.contains("testEnv()"));
+    assert!(!stdout.contains("Skipped"));
+
+    // the fuzz tests are deterministic with a pinned seed
+    prj.write_config(Config { fuzz_seed: Some(1u64.into()), ..Default::default() });
+    cmd.forge_fuse().args(["test", "--cache-results"]);
+    cmd.set_env("FORGE_TEST_CACHE_VALUE", "1");
+    assert!(cmd.stdout().contains("testValue(uint256)"));
+    cmd.forge_fuse().args(["test", "--cache-results"]);
+    cmd.set_env("FORGE_TEST_CACHE_VALUE", "1");
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("[CACHED] testValue(uint256)"));
+    assert!(stdout.contains("[PASS] testEnv()"));
+    assert!(stdout.contains("Skipped 1 test suites"));
+});
+
+// tests that overloaded tests are cached by their signatures
+forgetest!(can_cache_overloaded_test_results, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let test = r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testOverloaded() external {
+        assertTrue(true);
+    }
+    function testOverloaded(uint256 value) external {
+        assertTrue(value == value);
+    }
+}
+   "#;
+    prj.inner().add_source("ATest.t.sol", test).unwrap();
+    prj.write_config(Config { fuzz_seed: Some(1u64.into()), ..Default::default() });
+
+    // only one of the overloads ran, so the suite is not skipped
+    cmd.args(["test", "--cache-results", "--match-test", "testOverloaded\\(\\)"]);
+    assert!(cmd.stdout().contains("[PASS]"));
+    cmd.forge_fuse().args(["test", "--cache-results"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("testOverloaded(uint256)"));
+    assert!(!stdout.contains("Skipped"));
+
+    cmd.forge_fuse().args(["test", "--cache-results"]);
+    assert!(cmd.stdout().contains("Skipped 1 test suites"));
+});
+
+// tests that `--rerun-failed` only re-runs the failed tests of the contracts they failed in
+forgetest!(can_rerun_failed_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let test = r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testOne() external {
+        assertTrue(false);
+    }
+    function testTwo() external {
+        assertTrue(true);
+    }
+}
+contract BTest is DSTest {
+    function testOne() external {
+        assertTrue(true);
+    }
+    function testTwo() external {
+        assertTrue(false);
+    }
+}
+   "#;
+    prj.inner().add_source("ATest.t.sol", test).unwrap();
+
+    cmd.args(["test", "--force"]);
+    let stdout = String::from_utf8_lossy(&cmd.unchecked_output().stdout).to_string();
+    assert!(stdout.contains("Running 2 tests for src/ATest.t.sol:ATest"));
+    assert!(stdout.contains("Running 2 tests for src/ATest.t.sol:BTest"));
+
+    cmd.forge_fuse().args(["test", "--force", "--rerun-failed"]);
+    let stdout = String::from_utf8_lossy(&cmd.unchecked_output().stdout).to_string();
+    assert!(stdout.contains("Running 1 test for src/ATest.t.sol:ATest"));
+    assert!(stdout.contains("Running 1 test for src/ATest.t.sol:BTest"));
+
+    // a filtered run only updates the failures of the tests that ran
+    prj.inner()
+        .add_source("ATest.t.sol", test.replacen("assertTrue(false)", "assertTrue(true)", 1))
+        .unwrap();
+    cmd.forge_fuse().args(["test", "--force", "--match-contract", "ATest"]);
+    assert!(cmd.stdout_lossy().contains("Running 2 tests for src/ATest.t.sol:ATest"));
+
+    cmd.forge_fuse().args(["test", "--force", "--rerun-failed"]);
+    let stdout = String::from_utf8_lossy(&cmd.unchecked_output().stdout).to_string();
+    assert!(!stdout.contains("src/ATest.t.sol:ATest"));
+    assert!(stdout.contains("Running 1 test for src/ATest.t.sol:BTest"));
+});
+
 // tests that `forge test` will pick up tests that are stored in the `test = <path>` config value
 forgetest!(can_run_test_in_custom_test_folder, |prj: TestProject, mut cmd: TestCommand| {
     prj.insert_ds_test();
@@ -232,6 +617,60 @@ contract MyTest is DSTest {
     );
 });
 
+// tests that the cheatcodes that wait for external conditions must be enabled
+forgetest!(can_allow_wait_cheatcodes, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "SleepTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+interface Vm {
+    function sleep(uint256) external;
+    function waitForFile(string calldata, uint256) external;
+    function waitForStorage(string calldata, address, bytes32, bytes32, uint256) external;
+}
+contract SleepTest is DSTest {
+    Vm constant vm = Vm(HEVM_ADDRESS);
+    function testSleep() external {
+        vm.sleep(1);
+    }
+}
+contract WaitForTest is DSTest {
+    Vm constant vm = Vm(HEVM_ADDRESS);
+    function testWaitForFile() external {
+        vm.waitForFile("missing.txt", 1);
+    }
+    function testWaitForStorage() external {
+        vm.waitForStorage("http://localhost:1", address(0), bytes32(0), bytes32(0), 1);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.arg("test");
+    let stdout = String::from_utf8_lossy(&cmd.unchecked_output().stdout).to_string();
+    for test in ["testSleep()", "testWaitForFile()", "testWaitForStorage()"] {
+        assert!(
+            stdout
+                .lines()
+                .any(|line| line.starts_with("[FAIL. Reason: Waiting disabled") &&
+                    line.contains(test))
+        );
+    }
+
+    cmd.forge_fuse().args(["test", "--allow-wait", "--match-contract", "SleepTest"]);
+    assert!(cmd.stdout().contains("[PASS] testSleep()"));
+
+    prj.write_config(Config { allow_wait: true, ..Default::default() });
+    cmd.forge_fuse().args(["test", "--match-contract", "SleepTest"]);
+    assert!(cmd.stdout().contains("[PASS] testSleep()"));
+});
+
 // checks that forge test repeatedly produces the same output
 forgetest_init!(can_test_repeatedly, |_prj: TestProject, mut cmd: TestCommand| {
     cmd.arg("test");
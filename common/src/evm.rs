@@ -8,7 +8,7 @@ use foundry_config::{
         value::{Dict, Map, Value},
         Metadata, Profile, Provider,
     },
-    Config,
+    ChainProfile, Config,
 };
 use serde::Serialize;
 
@@ -38,6 +38,8 @@ pub struct EvmArgs {
     /// Fetch state over a remote endpoint instead of starting from an empty state.
     ///
     /// If you want to fetch state from a specific block number, see --fork-block-number.
+    ///
+    /// This can also be an alias of the `rpc_endpoints` of the config.
     #[clap(long, short, visible_alias = "rpc-url", value_name = "URL")]
     #[serde(rename = "eth_rpc_url", skip_serializing_if = "Option::is_none")]
     pub fork_url: Option<String>,
@@ -49,6 +51,14 @@ pub struct EvmArgs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fork_block_number: Option<u64>,
 
+    /// Fetch state from the block that lies the given number of blocks behind the latest block,
+    /// unless the fork is pinned to a block number.
+    ///
+    /// Older blocks are less likely to be reorged, which would make tests flaky.
+    #[clap(long, value_name = "BLOCKS")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fork_block_confirmations: Option<u64>,
+
     /// Initial retry backoff on encountering errors.
     ///
     /// See --fork-url.
@@ -67,6 +77,17 @@ pub struct EvmArgs {
     #[serde(skip)]
     pub no_storage_caching: bool,
 
+    /// Prefetch the state previous runs accessed when creating a fork, and record the state this
+    /// run accesses.
+    ///
+    /// The state is fetched with parallel requests before the tests run, instead of one request
+    /// at a time while they run.
+    ///
+    /// See --fork-url.
+    #[clap(long)]
+    #[serde(skip)]
+    pub fork_prefetch: bool,
+
     /// The initial balance of deployed test contracts.
     #[clap(long, value_name = "BALANCE")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -82,6 +103,14 @@ pub struct EvmArgs {
     #[serde(skip)]
     pub ffi: bool,
 
+    /// Enable the `sleep` and `waitFor*` cheatcodes.
+    #[clap(
+        help = "Enables the `sleep` and `waitFor*` cheatcodes, which block on external conditions.",
+        long
+    )]
+    #[serde(skip)]
+    pub allow_wait: bool,
+
     /// Verbosity of the EVM.
     ///
     /// Pass multiple times to increase the verbosity (e.g. -v, -vv, -vvv).
@@ -121,10 +150,18 @@ impl Provider for EvmArgs {
             dict.insert("ffi".to_string(), self.ffi.into());
         }
 
+        if self.allow_wait {
+            dict.insert("allow_wait".to_string(), self.allow_wait.into());
+        }
+
         if self.no_storage_caching {
             dict.insert("no_storage_caching".to_string(), self.no_storage_caching.into());
         }
 
+        if self.fork_prefetch {
+            dict.insert("fork_prefetch".to_string(), self.fork_prefetch.into());
+        }
+
         if let Some(fork_url) = &self.fork_url {
             dict.insert("eth_rpc_url".to_string(), fork_url.clone().into());
         }
@@ -147,6 +184,14 @@ pub struct EnvArgs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub chain_id: Option<u64>,
 
+    /// Emulate the EVM semantics of an L2 or sidechain.
+    ///
+    /// Provides the default chain ID and coinbase, caps the EVM version and labels the predeploys
+    /// of the chain in traces.
+    #[clap(long, value_name = "CHAIN", possible_values = ["optimism", "arbitrum", "polygon"])]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_profile: Option<ChainProfile>,
+
     /// The gas price.
     #[clap(long, value_name = "GAS_PRICE")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -0,0 +1,280 @@
+//! Commonly used helpers to construct providers for the `rpc_endpoints` of the config
+
+use async_trait::async_trait;
+use ethers_providers::{Http, HttpClientError, JsonRpcClient, Provider, ProviderError};
+use foundry_config::{ResolvedRpcEndpoint, RpcEndpoints, UnresolvedEnvVarError};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::Debug,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{sync::Mutex, time::Instant};
+
+/// A [Provider] that rate limits, retries and fails over its requests, see [RpcClient]
+pub type RetryProvider = Provider<RpcClient>;
+
+/// The upper bound of the backoff between two attempts of a request
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Resolves urls and the aliases of the `rpc_endpoints` of the config to endpoints, and creates
+/// the providers for them.
+///
+/// This way an alias can be used wherever a url is accepted, and every provider of an alias uses
+/// its configured rate limit, retries and fallback urls.
+#[derive(Debug, Clone, Default)]
+pub struct ProvidersManager {
+    endpoints: RpcEndpoints,
+}
+
+// === impl ProvidersManager ===
+
+impl ProvidersManager {
+    /// Creates a new manager for the given endpoints
+    pub fn new(endpoints: RpcEndpoints) -> Self {
+        Self { endpoints }
+    }
+
+    /// Returns the endpoint of the alias
+    ///
+    /// If `url_or_alias` is not a known alias, it's treated as a url: the endpoint of the alias
+    /// with that url, or an endpoint with default settings if no alias has that url.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `url_or_alias` is a known alias but references an unresolved env var.
+    pub fn endpoint(
+        &self,
+        url_or_alias: &str,
+    ) -> Result<ResolvedRpcEndpoint, UnresolvedEnvVarError> {
+        if let Some(endpoint) = self.endpoints.get(url_or_alias) {
+            return endpoint.clone().resolve()
+        }
+        let endpoint = self
+            .endpoints
+            .values()
+            .filter_map(|endpoint| endpoint.clone().resolve().ok())
+            .find(|endpoint| endpoint.url == url_or_alias)
+            .unwrap_or_else(|| ResolvedRpcEndpoint::new(url_or_alias));
+        Ok(endpoint)
+    }
+
+    /// Creates a provider for the url or alias
+    ///
+    /// `retries` and `backoff` (in milliseconds) are used unless the endpoint configures its own.
+    pub fn provider(
+        &self,
+        url_or_alias: &str,
+        retries: u32,
+        backoff: u64,
+    ) -> Result<RetryProvider, RpcClientError> {
+        let endpoint = self.endpoint(url_or_alias)?;
+        Ok(Provider::new(RpcClient::new(&endpoint, retries, backoff)?))
+    }
+}
+
+/// Errors of the [RpcClient]
+#[derive(Debug, thiserror::Error)]
+pub enum RpcClientError {
+    /// The request failed
+    #[error(transparent)]
+    Http(#[from] HttpClientError),
+    /// The params of the request could not be serialized
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    /// The url of the alias references an env var that is not set
+    #[error(transparent)]
+    UnresolvedEnvVar(#[from] UnresolvedEnvVarError),
+    /// The url is not a valid http url
+    #[error("Invalid rpc url {0}")]
+    InvalidUrl(String),
+}
+
+impl From<RpcClientError> for ProviderError {
+    fn from(err: RpcClientError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(err))
+    }
+}
+
+/// A JSON-RPC client that sends its requests to the url of an endpoint and its fallback urls.
+///
+/// - Requests are rate limited to the `requests_per_second` of the endpoint.
+/// - A request the endpoint rejects because it's rate limited is retried with an exponential
+///   backoff, up to `retries` times.
+/// - Once a url keeps rejecting a request, or can't be reached at all, the request is sent to the
+///   next fallback url, which is then also used for all following requests.
+///
+/// Clones of the client share its rate limit and the url it currently sends requests to.
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    /// The transports of the url and the fallback urls, in order
+    transports: Vec<Http>,
+    /// The index of the transport requests are currently sent to
+    active: Arc<AtomicUsize>,
+    retries: u32,
+    backoff: Duration,
+    /// The minimum interval between two requests, if rate limited
+    interval: Option<Duration>,
+    /// The earliest time the next request may be sent
+    next_request: Arc<Mutex<Instant>>,
+}
+
+// === impl RpcClient ===
+
+impl RpcClient {
+    /// Creates a client for the endpoint
+    ///
+    /// `retries` and `backoff` (in milliseconds) are used unless the endpoint configures its own.
+    pub fn new(
+        endpoint: &ResolvedRpcEndpoint,
+        retries: u32,
+        backoff: u64,
+    ) -> Result<Self, RpcClientError> {
+        let transports = std::iter::once(&endpoint.url)
+            .chain(endpoint.fallback_urls.iter())
+            .map(|url| Http::from_str(url).map_err(|_| RpcClientError::InvalidUrl(url.clone())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            transports,
+            active: Arc::new(AtomicUsize::new(0)),
+            retries: endpoint.retries.unwrap_or(retries),
+            backoff: Duration::from_millis(endpoint.retry_backoff.unwrap_or(backoff)),
+            interval: endpoint
+                .requests_per_second
+                .filter(|rps| *rps > 0)
+                .map(|rps| Duration::from_secs(1) / rps),
+            next_request: Arc::new(Mutex::new(Instant::now())),
+        })
+    }
+
+    /// Waits until the rate limit allows sending the next request
+    async fn wait_for_rate_limit(&self) {
+        if let Some(interval) = self.interval {
+            // reserve the next slot, but don't hold the lock while waiting for it
+            let at = {
+                let mut next_request = self.next_request.lock().await;
+                let at = (*next_request).max(Instant::now());
+                *next_request = at + interval;
+                at
+            };
+            tokio::time::sleep_until(at).await;
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for RpcClient {
+    type Error = RpcClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        // serialize once so the params can be sent repeatedly
+        let params = serde_json::to_value(params)?;
+        let active = self.active.load(Ordering::Relaxed);
+
+        let mut last_err = None;
+        for idx in (active..self.transports.len()).chain(0..active) {
+            let transport = &self.transports[idx];
+            let mut backoff = self.backoff;
+            let mut attempt = 0;
+            loop {
+                self.wait_for_rate_limit().await;
+                // the response must not be held across the awaits below, since `R` is not `Send`
+                let err = match transport.request(method, &params).await {
+                    Ok(res) => {
+                        self.active.store(idx, Ordering::Relaxed);
+                        return Ok(res)
+                    }
+                    Err(err) => err,
+                };
+
+                if is_rate_limited(&err) && attempt < self.retries {
+                    tracing::trace!(?err, attempt, "request was rate limited, retrying");
+                    attempt += 1;
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                } else if is_rate_limited(&err) || is_unreachable(&err) {
+                    tracing::trace!(?err, idx, "request failed, trying the next url");
+                    last_err = Some(err);
+                    break
+                } else {
+                    // the endpoint responded, e.g. with a revert
+                    return Err(err.into())
+                }
+            }
+        }
+
+        Err(last_err.expect("endpoint has at least one url").into())
+    }
+}
+
+/// Returns `true` if the endpoint rejected the request because of its rate limit
+fn is_rate_limited(err: &HttpClientError) -> bool {
+    match err {
+        HttpClientError::ReqwestError(err) => {
+            err.status().map(|status| status.as_u16() == 429).unwrap_or_default()
+        }
+        // 429: too many requests, -32005: request limit exceeded (infura)
+        HttpClientError::JsonRpcError(err) => {
+            err.code == 429 || err.code == -32005 || err.message.contains("rate limit")
+        }
+        HttpClientError::SerdeJson { text, .. } => {
+            text.contains("rate limit") || text.contains("Too Many Requests")
+        }
+    }
+}
+
+/// Returns `true` if the request didn't reach the endpoint or the endpoint failed to respond
+fn is_unreachable(err: &HttpClientError) -> bool {
+    matches!(err, HttpClientError::ReqwestError(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundry_config::{RpcEndpoint, RpcEndpointConfig};
+
+    #[test]
+    fn can_resolve_endpoints() {
+        let manager = ProvidersManager::new(RpcEndpoints::new([(
+            "mainnet",
+            RpcEndpointConfig {
+                endpoint: RpcEndpoint::Url("https://eth-mainnet.alchemyapi.io/v2/123".to_string()),
+                fallback_endpoints: vec![RpcEndpoint::Url(
+                    "https://cloudflare-eth.com".to_string(),
+                )],
+                requests_per_second: Some(4),
+                retries: Some(2),
+                retry_backoff: None,
+            },
+        )]));
+
+        let endpoint = manager.endpoint("mainnet").unwrap();
+        assert_eq!(endpoint.url, "https://eth-mainnet.alchemyapi.io/v2/123");
+        assert_eq!(endpoint.fallback_urls, vec!["https://cloudflare-eth.com".to_string()]);
+
+        let client = RpcClient::new(&endpoint, 10, 1000).unwrap();
+        assert_eq!(client.transports.len(), 2);
+        assert_eq!(client.retries, 2);
+        assert_eq!(client.backoff, Duration::from_millis(1000));
+        assert_eq!(client.interval, Some(Duration::from_millis(250)));
+
+        // the url of an alias uses the settings of the alias
+        assert_eq!(manager.endpoint("https://eth-mainnet.alchemyapi.io/v2/123").unwrap(), endpoint);
+
+        // unknown aliases are used as urls
+        assert_eq!(
+            manager.endpoint("http://localhost:8545").unwrap(),
+            ResolvedRpcEndpoint::new("http://localhost:8545")
+        );
+        assert!(manager.provider("not a url", 10, 1000).is_err());
+    }
+}
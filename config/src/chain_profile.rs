@@ -0,0 +1,137 @@
+//! Presets for the EVM semantics of L2s and sidechains
+
+use crate::Chain;
+use ethers_core::types::{Address, Chain as NamedChain};
+use ethers_solc::EvmVersion;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// A chain whose EVM semantics are emulated by the executor, e.g. `chain_profile = "optimism"`
+///
+/// The profile provides the defaults of the chain id and the block coinbase, caps the
+/// `evm_version` at the latest version the chain supports and labels the predeploys of the chain
+/// in traces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainProfile {
+    Optimism,
+    Arbitrum,
+    Polygon,
+}
+
+impl ChainProfile {
+    /// All supported profiles
+    pub const ALL: [ChainProfile; 3] =
+        [ChainProfile::Optimism, ChainProfile::Arbitrum, ChainProfile::Polygon];
+
+    /// The chain of the profile
+    pub fn chain(&self) -> Chain {
+        match self {
+            ChainProfile::Optimism => NamedChain::Optimism.into(),
+            ChainProfile::Arbitrum => NamedChain::Arbitrum.into(),
+            ChainProfile::Polygon => NamedChain::Polygon.into(),
+        }
+    }
+
+    /// The latest EVM version the chain supports
+    pub fn evm_version(&self) -> EvmVersion {
+        match self {
+            ChainProfile::Optimism | ChainProfile::Arbitrum | ChainProfile::Polygon => {
+                EvmVersion::London
+            }
+        }
+    }
+
+    /// The value of `block.coinbase` on the chain
+    ///
+    /// Optimism credits the fees to the sequencer fee vault, Arbitrum returns a fixed sequencer
+    /// address and Polygon does not set the coinbase.
+    pub fn coinbase(&self) -> Address {
+        match self {
+            ChainProfile::Optimism => predeploy("0x4200000000000000000000000000000000000011"),
+            ChainProfile::Arbitrum => predeploy("0xa4b000000000000000000073657175656e636572"),
+            ChainProfile::Polygon => Address::zero(),
+        }
+    }
+
+    /// The well-known system contracts and precompiles of the chain, with their names
+    pub fn predeploys(&self) -> Vec<(Address, &'static str)> {
+        let predeploys: &[(&str, &str)] = match self {
+            ChainProfile::Optimism => &[
+                ("0x4200000000000000000000000000000000000000", "LegacyMessagePasser"),
+                ("0x4200000000000000000000000000000000000002", "DeployerWhitelist"),
+                ("0x4200000000000000000000000000000000000006", "WETH9"),
+                ("0x4200000000000000000000000000000000000007", "L2CrossDomainMessenger"),
+                ("0x420000000000000000000000000000000000000F", "GasPriceOracle"),
+                ("0x4200000000000000000000000000000000000010", "L2StandardBridge"),
+                ("0x4200000000000000000000000000000000000011", "SequencerFeeVault"),
+                ("0x4200000000000000000000000000000000000012", "OptimismMintableERC20Factory"),
+                ("0x4200000000000000000000000000000000000013", "L1BlockNumber"),
+                ("0x4200000000000000000000000000000000000015", "L1Block"),
+                ("0x4200000000000000000000000000000000000016", "L2ToL1MessagePasser"),
+                ("0x4200000000000000000000000000000000000042", "GovernanceToken"),
+            ],
+            ChainProfile::Arbitrum => &[
+                ("0x0000000000000000000000000000000000000064", "ArbSys"),
+                ("0x0000000000000000000000000000000000000065", "ArbInfo"),
+                ("0x0000000000000000000000000000000000000066", "ArbAddressTable"),
+                ("0x000000000000000000000000000000000000006b", "ArbOwnerPublic"),
+                ("0x000000000000000000000000000000000000006C", "ArbGasInfo"),
+                ("0x000000000000000000000000000000000000006D", "ArbAggregator"),
+                ("0x000000000000000000000000000000000000006E", "ArbRetryableTx"),
+                ("0x000000000000000000000000000000000000006F", "ArbStatistics"),
+                ("0x0000000000000000000000000000000000000070", "ArbOwner"),
+                ("0x00000000000000000000000000000000000000C8", "NodeInterface"),
+            ],
+            ChainProfile::Polygon => &[
+                ("0x0000000000000000000000000000000000001000", "BorValidatorSet"),
+                ("0x0000000000000000000000000000000000001001", "StateReceiver"),
+                ("0x0000000000000000000000000000000000001010", "MRC20"),
+            ],
+        };
+        predeploys.iter().map(|(address, name)| (predeploy(address), *name)).collect()
+    }
+}
+
+fn predeploy(address: &str) -> Address {
+    address.parse().expect("predeploy addresses are valid")
+}
+
+impl fmt::Display for ChainProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainProfile::Optimism => f.write_str("optimism"),
+            ChainProfile::Arbitrum => f.write_str("arbitrum"),
+            ChainProfile::Polygon => f.write_str("polygon"),
+        }
+    }
+}
+
+impl FromStr for ChainProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ChainProfile::ALL
+            .into_iter()
+            .find(|profile| profile.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("Unknown chain profile `{s}`, expected one of optimism, arbitrum, polygon")
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_chain_profiles() {
+        for profile in ChainProfile::ALL {
+            assert_eq!(profile.to_string().parse::<ChainProfile>().unwrap(), profile);
+            assert!(!profile.predeploys().is_empty());
+        }
+        assert_eq!("Optimism".parse::<ChainProfile>().unwrap(), ChainProfile::Optimism);
+        assert!("zksync".parse::<ChainProfile>().is_err());
+        assert_eq!(ChainProfile::Arbitrum.chain().id(), 42161);
+    }
+}
@@ -0,0 +1,140 @@
+//! Permissions of the file system cheatcodes
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// The permissions of the file system cheatcodes, by path
+///
+/// A path is accessed with the permission of the most specific path that contains it, e.g.
+///
+/// ```toml
+/// fs_permissions = [{ access = "read", path = "./" }, { access = "read-write", path = "./reports" }]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FsPermissions {
+    pub permissions: Vec<PathPermission>,
+}
+
+impl FsPermissions {
+    /// Creates the permissions from the permissions of single paths
+    pub fn new(permissions: impl IntoIterator<Item = PathPermission>) -> Self {
+        Self { permissions: permissions.into_iter().collect() }
+    }
+
+    /// Returns `true` if no permissions are configured
+    pub fn is_empty(&self) -> bool {
+        self.permissions.is_empty()
+    }
+
+    /// Returns the permission of the most specific path that contains `path`, if any
+    pub fn find_permission(&self, path: impl AsRef<Path>) -> Option<FsAccessPermission> {
+        let path = path.as_ref();
+        self.permissions
+            .iter()
+            .filter(|permission| path.starts_with(&permission.path))
+            .max_by_key(|permission| permission.path.components().count())
+            .map(|permission| permission.access)
+    }
+
+    /// Returns `true` if `path` may be accessed with the given kind of access
+    pub fn is_granted(&self, path: impl AsRef<Path>, kind: FsAccessKind) -> bool {
+        self.find_permission(path).map_or(false, |access| access.is_granted(kind))
+    }
+}
+
+/// The permission of a single path and everything in it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathPermission {
+    pub access: FsAccessPermission,
+    pub path: PathBuf,
+}
+
+impl PathPermission {
+    /// Returns a permission to read and write `path`
+    pub fn read_write(path: impl Into<PathBuf>) -> Self {
+        Self { access: FsAccessPermission::ReadWrite, path: path.into() }
+    }
+
+    /// Returns a permission to read `path`
+    pub fn read(path: impl Into<PathBuf>) -> Self {
+        Self { access: FsAccessPermission::Read, path: path.into() }
+    }
+
+    /// Returns a permission to write `path`
+    pub fn write(path: impl Into<PathBuf>) -> Self {
+        Self { access: FsAccessPermission::Write, path: path.into() }
+    }
+
+    /// Returns a permission that denies any access to `path`
+    pub fn none(path: impl Into<PathBuf>) -> Self {
+        Self { access: FsAccessPermission::None, path: path.into() }
+    }
+}
+
+/// The access granted to a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FsAccessPermission {
+    /// Files may be read, written and removed
+    ReadWrite,
+    /// Files may only be read
+    Read,
+    /// Files may only be written and removed
+    Write,
+    /// Files may not be accessed, e.g. to exclude a path from a permission of its parent
+    None,
+}
+
+impl FsAccessPermission {
+    /// Returns `true` if this permission grants the given kind of access
+    pub fn is_granted(&self, kind: FsAccessKind) -> bool {
+        match (self, kind) {
+            (FsAccessPermission::ReadWrite, _) => true,
+            (FsAccessPermission::Read, FsAccessKind::Read) => true,
+            (FsAccessPermission::Write, FsAccessKind::Write) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The kind of access of a file system cheatcode, removing files counts as writing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsAccessKind {
+    Read,
+    Write,
+}
+
+impl fmt::Display for FsAccessKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FsAccessKind::Read => f.write_str("read"),
+            FsAccessKind::Write => f.write_str("write"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_find_most_specific_permission() {
+        let permissions = FsPermissions::new([
+            PathPermission::read("/project"),
+            PathPermission::read_write("/project/reports"),
+            PathPermission::none("/project/reports/secret"),
+        ]);
+
+        assert!(permissions.is_granted("/project/fixtures/a.json", FsAccessKind::Read));
+        assert!(!permissions.is_granted("/project/fixtures/a.json", FsAccessKind::Write));
+        assert!(permissions.is_granted("/project/reports/gas.txt", FsAccessKind::Write));
+        assert!(!permissions.is_granted("/project/reports/secret/key", FsAccessKind::Read));
+        assert!(!permissions.is_granted("/etc/hosts", FsAccessKind::Read));
+        // paths are compared by components
+        assert!(!permissions.is_granted("/project-other/a.json", FsAccessKind::Read));
+    }
+}
@@ -26,7 +26,7 @@ use semver::Version;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     borrow::Cow,
-    collections::{hash_map::Entry, BTreeSet, HashMap},
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap},
     fs,
     path::{Path, PathBuf},
     str::FromStr,
@@ -42,7 +42,10 @@ pub mod utils;
 pub use crate::utils::*;
 
 mod rpc;
-pub use rpc::{ResolvedRpcEndpoints, RpcEndpoint, RpcEndpoints, UnresolvedEnvVarError};
+pub use rpc::{
+    ResolvedRpcEndpoint, ResolvedRpcEndpoints, RpcEndpoint, RpcEndpointConfig, RpcEndpoints,
+    UnresolvedEnvVarError,
+};
 
 pub mod cache;
 use cache::{Cache, ChainCache};
@@ -50,12 +53,21 @@ use cache::{Cache, ChainCache};
 mod chain;
 pub use chain::Chain;
 
+mod chain_profile;
+pub use chain_profile::ChainProfile;
+
 pub mod fmt;
 pub use fmt::FormatterConfig;
 
 mod error;
 pub use error::SolidityErrorCode;
 
+pub mod fs_permissions;
+pub use fs_permissions::{FsAccessKind, FsAccessPermission, FsPermissions, PathPermission};
+
+mod precompiles;
+pub use precompiles::PrecompileCommand;
+
 // helpers for fixing configuration warnings
 pub mod fix;
 
@@ -125,6 +137,13 @@ pub struct Config {
     pub broadcast: PathBuf,
     /// additional solc allow paths
     pub allow_paths: Vec<PathBuf>,
+    /// permissions of the file system cheatcodes by path, e.g. `[{ access = "read", path = "./"
+    /// }]`. Without permissions, the project root, libs and `allow_paths` may be read and written
+    pub fs_permissions: FsPermissions,
+    /// dirs of precompiled artifacts, e.g. of Huff contracts, that are deployable and decoded in
+    /// tests: `<Name>.bin` files of hex encoded creation code, with an optional `<Name>.json` ABI
+    /// and `<Name>.bin-runtime` runtime code
+    pub raw_artifacts: Vec<PathBuf>,
     /// whether to force a `project.clean()`
     pub force: bool,
     /// evm version to use
@@ -132,6 +151,8 @@ pub struct Config {
     pub evm_version: EvmVersion,
     /// list of contracts to report gas of
     pub gas_reports: Vec<String>,
+    /// Glob patterns of source files to exclude from coverage reports
+    pub coverage_exclude: Vec<String>,
     /// The Solc instance to use if any.
     ///
     /// This takes precedence over `auto_detect_solc`, if a version is set then this overrides
@@ -189,6 +210,11 @@ pub struct Config {
     pub fuzz_runs: u32,
     /// Whether to allow ffi cheatcodes in test
     pub ffi: bool,
+    /// Whether to allow the `sleep` and `waitFor*` cheatcodes in test
+    pub allow_wait: bool,
+    /// precompiles at custom addresses that are implemented by external commands, e.g.
+    /// `[{ address = "0x...0100", command = ["./p256.sh"], gas = 3450 }]`
+    pub precompiles: Vec<PrecompileCommand>,
     /// The address which will be executing all tests
     pub sender: Address,
     /// The tx.origin value during EVM execution
@@ -199,8 +225,17 @@ pub struct Config {
     pub block_number: u64,
     /// pins the block number for the state fork
     pub fork_block_number: Option<u64>,
+    /// pins the forks created with an alias of `rpc_endpoints` that don't specify a block number,
+    /// e.g. `vm.createFork("mainnet")`, to the block number of the alias
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fork_block_numbers: BTreeMap<String, u64>,
+    /// if set, forks that are not pinned to a block number start this many blocks behind the
+    /// latest block, which makes them unlikely to include blocks that are reorged later
+    pub fork_block_confirmations: Option<u64>,
     /// the chainid opcode value
     pub chain_id: Option<Chain>,
+    /// the L2 or sidechain whose EVM semantics are emulated, e.g. `optimism`, see [ChainProfile]
+    pub chain_profile: Option<ChainProfile>,
     /// Block gas limit
     pub gas_limit: GasLimit,
     /// `tx.gasprice` value during EVM execution"
@@ -254,6 +289,21 @@ pub struct Config {
     /// by proptest, to be encountered during usage of `vm.assume`
     /// cheatcode.
     pub fuzz_max_global_rejects: u32,
+    /// Whether to include the storage of all accounts in the fuzz dictionary
+    pub fuzz_include_storage: bool,
+    /// Whether to include the PUSH values of all bytecode in the fuzz dictionary
+    pub fuzz_include_push_bytes: bool,
+    /// The percentage of fuzz inputs that are drawn from the fuzz dictionary (values harvested
+    /// from storage, bytecode and logs) instead of being generated randomly
+    pub fuzz_dictionary_weight: u32,
+    /// The number of most recent successful fuzz cases whose logs are kept in addition to the
+    /// logs of the failing case, identical log sequences are only kept once
+    pub fuzz_keep_logs: usize,
+    /// The seed of the fuzzer and the `random*` cheatcodes, a random seed is used for every run if
+    /// not set
+    pub fuzz_seed: Option<U256>,
+    /// The maximum amount of gas a test may use, tests that use more gas fail
+    pub gas_limit_per_test: Option<u64>,
     /// Print the names of the compiled contracts
     pub names: bool,
     /// Print the sizes of the compiled contracts
@@ -261,11 +311,22 @@ pub struct Config {
     /// If set to true, changes compilation pipeline to go through the Yul intermediate
     /// representation.
     pub via_ir: bool,
+    /// Compiler settings for the sources matching a glob, e.g. `"src/Heavy.sol" = { via_ir = true
+    /// }`, which are compiled by separate jobs, see [`Config::compiler_override_configs()`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub compiler_overrides: BTreeMap<String, CompilerOverride>,
+    /// Fee conditions `forge test --fee-scenarios` runs the tests in, by name, e.g. `congested = {
+    /// gas_price = 200000000000, block_base_fee_per_gas = 150000000000 }`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fee_scenarios: BTreeMap<String, FeeScenario>,
     /// RPC storage caching settings determines what chains and endpoints to cache
     pub rpc_storage_caching: StorageCachingConfig,
     /// Disables storage caching entirely. This overrides any settings made in
     /// `rpc_storage_caching`
     pub no_storage_caching: bool,
+    /// Records the accounts and storage slots forks fetch from their endpoint, and fetches them in
+    /// parallel when the next run creates a fork of the same chain
+    pub fork_prefetch: bool,
     /// Multiple rpc endpoints and their aliases
     #[serde(default, skip_serializing_if = "RpcEndpoints::is_empty")]
     pub rpc_endpoints: RpcEndpoints,
@@ -410,9 +471,22 @@ impl Config {
         let figment = Figment::from(provider);
         let mut config = figment.extract::<Self>()?;
         config.profile = figment.profile().clone();
+        config.apply_chain_profile();
         Ok(config)
     }
 
+    /// Applies the defaults of the [ChainProfile], if any, to the settings that are not set
+    /// explicitly, and caps the `evm_version` at the latest version the chain supports
+    fn apply_chain_profile(&mut self) {
+        if let Some(profile) = self.chain_profile {
+            self.chain_id.get_or_insert_with(|| profile.chain());
+            if self.block_coinbase.is_zero() {
+                self.block_coinbase = profile.coinbase();
+            }
+            self.evm_version = self.evm_version.min(profile.evm_version());
+        }
+    }
+
     /// The config supports relative paths and tracks the root path separately see
     /// `Config::with_root`
     ///
@@ -466,6 +540,12 @@ impl Config {
 
         self.allow_paths = self.allow_paths.into_iter().map(|allow| p(&root, &allow)).collect();
 
+        self.raw_artifacts = self.raw_artifacts.into_iter().map(|dir| p(&root, &dir)).collect();
+
+        for permission in &mut self.fs_permissions.permissions {
+            permission.path = p(&root, &permission.path);
+        }
+
         if let Some(ref mut model_checker) = self.model_checker {
             model_checker.contracts = std::mem::take(&mut model_checker.contracts)
                 .into_iter()
@@ -630,6 +710,12 @@ impl Config {
             self.rpc_storage_caching.enable_for_endpoint(endpoint)
     }
 
+    /// Returns the directory of the access lists recorded for prefetching fork state, if
+    /// `fork_prefetch` is enabled
+    pub fn fork_prefetch_dir(&self) -> Option<PathBuf> {
+        self.fork_prefetch.then(|| self.cache_path.join("fork-prefetch"))
+    }
+
     /// Returns the `ProjectPathsConfig`  sub set of the config.
     ///
     /// **NOTE**: this uses the paths as they are and does __not__ modify them, see
@@ -732,8 +818,16 @@ impl Config {
 
     /// Parses all libraries in the form of
     /// `<file>:<lib>:<addr>`
+    ///
+    /// Libraries can also be given as `<lib>:<addr>`, these are stored under an empty file path and
+    /// link every library named `<lib>`.
     pub fn parsed_libraries(&self) -> Result<Libraries, SolcError> {
-        Libraries::parse(&self.libraries)
+        let libraries = self
+            .libraries
+            .iter()
+            .map(|lib| if lib.matches(':').count() == 1 { format!(":{lib}") } else { lib.clone() })
+            .collect::<Vec<_>>();
+        Libraries::parse(&libraries)
     }
 
     /// Returns the configured `solc` `Settings` that includes:
@@ -741,7 +835,9 @@ impl Config {
     ///   - the optimizer (including details, if configured)
     ///   - evm version
     pub fn solc_settings(&self) -> Result<Settings, SolcError> {
-        let libraries = self.parsed_libraries()?.with_applied_remappings(&self.project_paths());
+        let mut libraries = self.parsed_libraries()?.with_applied_remappings(&self.project_paths());
+        // solc can only link libraries of a known file, the others are linked by forge
+        libraries.libs.remove(Path::new(""));
         let optimizer = self.optimizer();
 
         // By default if no targets are specifically selected the model checker uses all targets.
@@ -776,6 +872,39 @@ impl Config {
         Ok(settings)
     }
 
+    /// Returns the configs of the `compiler_overrides`, by their globs, in the order their globs
+    /// take precedence: a source matching multiple globs is compiled with the first of them.
+    ///
+    /// Every override has its own cache, in `<cache_path>/overrides/<index>`, because the sources
+    /// it does not compile are still passed to solc, with a pruned output selection, and would
+    /// otherwise invalidate the cache of the project on every build.
+    pub fn compiler_override_configs(&self) -> Vec<(String, Config)> {
+        self.compiler_overrides
+            .iter()
+            .enumerate()
+            .map(|(idx, (glob, settings))| {
+                let mut config = self.clone();
+                config.compiler_overrides = Default::default();
+                // the artifacts of the project must not be removed by the override jobs
+                config.force = false;
+                config.cache_path = self.cache_path.join("overrides").join(idx.to_string());
+                if let Some(optimizer) = settings.optimizer {
+                    config.optimizer = optimizer;
+                }
+                if let Some(optimizer_runs) = settings.optimizer_runs {
+                    config.optimizer_runs = optimizer_runs;
+                }
+                if let Some(via_ir) = settings.via_ir {
+                    config.via_ir = via_ir;
+                }
+                if let Some(evm_version) = settings.evm_version {
+                    config.evm_version = evm_version;
+                }
+                (glob.clone(), config)
+            })
+            .collect()
+    }
+
     /// Returns the default figment
     ///
     /// The default figment reads from the following sources, in ascending
@@ -993,6 +1122,11 @@ impl Config {
         Self::foundry_dir().map(|p| p.join("cache"))
     }
 
+    /// Returns the path to foundry's keystores dir `~/.foundry/keystores`
+    pub fn foundry_keystores_dir() -> Option<PathBuf> {
+        Self::foundry_dir().map(|p| p.join("keystores"))
+    }
+
     /// Returns the path to foundry rpc cache dir `~/.foundry/cache/rpc`
     pub fn foundry_rpc_cache_dir() -> Option<PathBuf> {
         Some(Self::foundry_cache_dir()?.join("rpc"))
@@ -1176,9 +1310,12 @@ impl Config {
         }
         for block in chain_path.read_dir()?.flatten().filter(|x| x.file_type().unwrap().is_dir()) {
             let filepath = block.path().join("storage.json");
+            // entries that are not merged into the storage file yet are kept in its journal
+            let journal_size =
+                fs::metadata(block.path().join("storage.journal")).map(|m| m.len()).unwrap_or(0);
             blocks.push((
                 block.file_name().to_string_lossy().into_owned(),
-                fs::metadata(filepath)?.len(),
+                fs::metadata(filepath)?.len() + journal_size,
             ));
         }
         Ok(blocks)
@@ -1290,6 +1427,41 @@ impl From<Config> for Figment {
     }
 }
 
+/// Compiler settings that override the settings of the project for the sources matching a glob
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompilerOverride {
+    /// Whether to activate the optimizer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimizer: Option<bool>,
+    /// The optimizer runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimizer_runs: Option<usize>,
+    /// Whether to compile through the Yul intermediate representation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub via_ir: Option<bool>,
+    /// The evm version
+    #[serde(
+        default,
+        with = "serde_helpers::display_from_str_opt",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub evm_version: Option<EvmVersion>,
+}
+
+/// Fee conditions the tests run in, the settings that are not set are the ones of the project
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeScenario {
+    /// The `tx.gasprice` value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_price: Option<u64>,
+    /// The `block.basefee` value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_base_fee_per_gas: Option<u64>,
+    /// The `block.number` value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+}
+
 /// Wrapper type for `regex::Regex` that implements `PartialEq`
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(transparent)]
@@ -1425,9 +1597,12 @@ impl Default for Config {
             cache_path: "cache".into(),
             broadcast: "broadcast".into(),
             allow_paths: vec![],
+            raw_artifacts: vec![],
+            fs_permissions: Default::default(),
             force: false,
             evm_version: Default::default(),
             gas_reports: vec!["*".to_string()],
+            coverage_exclude: vec![],
             solc: None,
             auto_detect_solc: true,
             offline: false,
@@ -1448,13 +1623,24 @@ impl Default for Config {
             fuzz_runs: 256,
             fuzz_max_local_rejects: 1024,
             fuzz_max_global_rejects: 65536,
+            fuzz_include_storage: true,
+            fuzz_include_push_bytes: true,
+            fuzz_dictionary_weight: 40,
+            fuzz_keep_logs: 0,
+            fuzz_seed: None,
+            gas_limit_per_test: None,
             ffi: false,
+            allow_wait: false,
+            precompiles: Vec::new(),
             sender: Config::DEFAULT_SENDER,
             tx_origin: Config::DEFAULT_SENDER,
             initial_balance: U256::from(0xffffffffffffffffffffffffu128),
             block_number: 1,
             fork_block_number: None,
+            fork_block_numbers: Default::default(),
+            fork_block_confirmations: None,
             chain_id: None,
+            chain_profile: None,
             gas_limit: i64::MAX.into(),
             gas_price: None,
             block_base_fee_per_gas: 0,
@@ -1473,9 +1659,12 @@ impl Default for Config {
                 SolidityErrorCode::ContractExceeds24576Bytes,
             ],
             via_ir: false,
+            compiler_overrides: Default::default(),
+            fee_scenarios: Default::default(),
             rpc_storage_caching: Default::default(),
             rpc_endpoints: Default::default(),
             no_storage_caching: false,
+            fork_prefetch: false,
             bytecode_hash: BytecodeHash::Ipfs,
             revert_strings: None,
             sparse_mode: false,
@@ -2821,6 +3010,7 @@ mod tests {
                 "foundry.toml",
                 r#"
                 [profile.default]
+                allow_wait = false
                 auto_detect_solc = true
                 block_base_fee_per_gas = 0
                 block_coinbase = '0x0000000000000000000000000000000000000000'
@@ -2830,15 +3020,26 @@ mod tests {
                 bytecode_hash = 'ipfs'
                 cache = true
                 cache_path = 'cache'
+                chain_profile = 'optimism'
+                coverage_exclude = []
                 evm_version = 'london'
                 extra_output = []
                 extra_output_files = []
                 ffi = false
                 force = false
+                fork_block_confirmations = 12
+                fork_prefetch = true
+                fs_permissions = [{ access = 'read', path = './fixtures' }, { access = 'read-write', path = './reports' }]
+                fuzz_dictionary_weight = 40
+                fuzz_include_push_bytes = true
+                fuzz_include_storage = true
+                fuzz_keep_logs = 0
                 fuzz_max_global_rejects = 65536
                 fuzz_max_local_rejects = 1024
                 fuzz_runs = 256
+                fuzz_seed = '0x3e8'
                 gas_limit = 9223372036854775807
+                gas_limit_per_test = 1000000
                 gas_price = 0
                 gas_reports = ['*']
                 ignored_error_codes = [1878]
@@ -2852,6 +3053,8 @@ mod tests {
                 optimizer = true
                 optimizer_runs = 200
                 out = 'out'
+                precompiles = [{ address = '0x0000000000000000000000000000000000000100', command = ['./p256.sh'], gas = 3450 }]
+                raw_artifacts = []
                 remappings = ['nested/=lib/nested/']
                 sender = '0x00a329c0648769a73afac7f9381e08fb43dbea72'
                 sizes = false
@@ -2862,6 +3065,18 @@ mod tests {
                 verbosity = 0
                 via_ir = false
                 
+                [profile.default.compiler_overrides."src/Heavy.sol"]
+                evm_version = 'london'
+                optimizer_runs = 1000000
+                via_ir = true
+
+                [profile.default.fee_scenarios.congested]
+                block_base_fee_per_gas = 150000000000
+                gas_price = 200000000000
+
+                [profile.default.fork_block_numbers]
+                mainnet = 15000000
+
                 [profile.default.rpc_storage_caching]
                 chains = 'all'
                 endpoints = 'all'
@@ -2875,6 +3090,59 @@ mod tests {
             )?;
 
             let config = Config::load_with_root(jail.directory());
+            assert_eq!(
+                config.fork_block_numbers,
+                BTreeMap::from([("mainnet".to_string(), 15000000)])
+            );
+            assert_eq!(config.fork_block_confirmations, Some(12));
+            assert_eq!(config.chain_profile, Some(ChainProfile::Optimism));
+            assert_eq!(config.chain_id, Some(Chain::Named(ethers_core::types::Chain::Optimism)));
+            assert_eq!(config.block_coinbase, ChainProfile::Optimism.coinbase());
+            assert_eq!(
+                config.fs_permissions,
+                FsPermissions::new([
+                    PathPermission::read("./fixtures"),
+                    PathPermission::read_write("./reports"),
+                ])
+            );
+            assert_eq!(
+                config.precompiles,
+                vec![PrecompileCommand {
+                    address: Address::from_low_u64_be(0x100),
+                    command: vec!["./p256.sh".to_string()],
+                    gas: 3450,
+                }]
+            );
+            assert_eq!(
+                config.compiler_overrides,
+                BTreeMap::from([(
+                    "src/Heavy.sol".to_string(),
+                    CompilerOverride {
+                        optimizer: None,
+                        optimizer_runs: Some(1000000),
+                        via_ir: Some(true),
+                        evm_version: Some(EvmVersion::London),
+                    }
+                )])
+            );
+            assert_eq!(
+                config.fee_scenarios,
+                BTreeMap::from([(
+                    "congested".to_string(),
+                    FeeScenario {
+                        gas_price: Some(200000000000),
+                        block_base_fee_per_gas: Some(150000000000),
+                        block_number: None,
+                    }
+                )])
+            );
+            let overrides = config.compiler_override_configs();
+            assert_eq!(overrides.len(), 1);
+            assert!(overrides[0].1.via_ir);
+            assert!(overrides[0].1.optimizer);
+            assert_eq!(overrides[0].1.optimizer_runs, 1000000);
+            assert_eq!(overrides[0].1.cache_path, config.cache_path.join("overrides").join("0"));
+            assert_eq!(config.gas_limit_per_test, Some(1000000));
             assert_eq!(
                 config.remappings,
                 vec![Remapping::from_str("nested/=lib/nested/").unwrap().into()]
@@ -3220,6 +3488,39 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_parse_library_without_file() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "foundry.toml",
+                r#"
+                [profile.default]
+                libraries = [
+                    'Math:0x902f6cf364b8d9470d5793a9b2b2e86bddd21e0c',
+                    './src/SizeAuction.sol:ChainlinkTWAP:0xffedba5e171c4f15abaaabc86e8bd01f9b54dae5',
+                ]
+            "#,
+            )?;
+            let config = Config::load();
+
+            let libs = config.parsed_libraries().unwrap().libs;
+            assert_eq!(
+                libs[&PathBuf::new()],
+                BTreeMap::from([(
+                    "Math".to_string(),
+                    "0x902f6cf364b8d9470d5793a9b2b2e86bddd21e0c".to_string()
+                )])
+            );
+            assert!(libs.contains_key(&PathBuf::from("./src/SizeAuction.sol")));
+
+            // libraries without a file are not passed to solc
+            let settings = config.solc_settings().unwrap();
+            assert!(!settings.libraries.libs.contains_key(&PathBuf::new()));
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn config_roundtrip() {
         figment::Jail::expect_with(|jail| {
@@ -0,0 +1,24 @@
+//! Precompiles that are implemented by external commands
+
+use ethers_core::types::Address;
+use serde::{Deserialize, Serialize};
+
+/// A precompile at `address` whose output is computed by an external command, e.g.
+///
+/// ```toml
+/// precompiles = [{ address = "0x0000000000000000000000000000000000000100", command = ["./p256.sh"], gas = 3450 }]
+/// ```
+///
+/// The command is run in the project root with the hex encoded input of the call appended to its
+/// arguments, and must print the hex encoded output to stdout. The call reverts if the command
+/// exits with an error.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrecompileCommand {
+    /// The address of the precompile
+    pub address: Address,
+    /// The program and its arguments
+    pub command: Vec<String>,
+    /// The gas every call to the precompile costs
+    #[serde(default)]
+    pub gas: u64,
+}
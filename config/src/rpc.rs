@@ -13,15 +13,22 @@ pub static RE_PLACEHOLDER: Lazy<Regex> =
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct RpcEndpoints {
-    endpoints: BTreeMap<String, RpcEndpoint>,
+    endpoints: BTreeMap<String, RpcEndpointConfig>,
 }
 
 // === impl RpcEndpoints ===
 
 impl RpcEndpoints {
     /// Creates anew list of endpoints
-    pub fn new(endpoints: impl IntoIterator<Item = (impl Into<String>, RpcEndpoint)>) -> Self {
-        Self { endpoints: endpoints.into_iter().map(|(name, url)| (name.into(), url)).collect() }
+    pub fn new(
+        endpoints: impl IntoIterator<Item = (impl Into<String>, impl Into<RpcEndpointConfig>)>,
+    ) -> Self {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(name, endpoint)| (name.into(), endpoint.into()))
+                .collect(),
+        }
     }
 
     /// Returns `true` if this type holds no endpoints
@@ -29,7 +36,7 @@ impl RpcEndpoints {
         self.endpoints.is_empty()
     }
 
-    /// Returns all (alias -> url) pairs
+    /// Returns all (alias -> endpoint) pairs
     pub fn resolved(self) -> ResolvedRpcEndpoints {
         ResolvedRpcEndpoints {
             endpoints: self.endpoints.into_iter().map(|(name, e)| (name, e.resolve())).collect(),
@@ -38,7 +45,7 @@ impl RpcEndpoints {
 }
 
 impl Deref for RpcEndpoints {
-    type Target = BTreeMap<String, RpcEndpoint>;
+    type Target = BTreeMap<String, RpcEndpointConfig>;
 
     fn deref(&self) -> &Self::Target {
         &self.endpoints
@@ -150,12 +157,168 @@ impl<'de> Deserialize<'de> for RpcEndpoint {
     }
 }
 
+/// The config of a single endpoint alias
+///
+/// In its simplest form an alias is only a url, `mainnet = "${RPC_MAINNET}"`. The table form also
+/// configures how requests to the endpoint are sent:
+///
+/// ```toml
+/// [rpc_endpoints]
+/// mainnet = { url = "${RPC_MAINNET}", fallback_urls = ["https://cloudflare-eth.com"], requests_per_second = 10, retries = 5, retry_backoff = 500 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcEndpointConfig {
+    /// The url requests are sent to
+    pub endpoint: RpcEndpoint,
+    /// The urls requests are sent to, in order, once the requests to `endpoint` keep failing
+    pub fallback_endpoints: Vec<RpcEndpoint>,
+    /// The maximum number of requests sent to the endpoint per second
+    pub requests_per_second: Option<u32>,
+    /// How often a failed request is retried before failing over to the next url
+    pub retries: Option<u32>,
+    /// The initial backoff in milliseconds between two attempts of a request, doubled after every
+    /// attempt
+    pub retry_backoff: Option<u64>,
+}
+
+// === impl RpcEndpointConfig ===
+
+impl RpcEndpointConfig {
+    /// Returns `true` if this only configures the url of the endpoint
+    pub fn is_url_only(&self) -> bool {
+        self.fallback_endpoints.is_empty() &&
+            self.requests_per_second.is_none() &&
+            self.retries.is_none() &&
+            self.retry_backoff.is_none()
+    }
+
+    /// Resolves the urls of the endpoint
+    ///
+    /// # Error
+    ///
+    /// Returns an error if one of the urls references an env var that is not set
+    pub fn resolve(self) -> Result<ResolvedRpcEndpoint, UnresolvedEnvVarError> {
+        Ok(ResolvedRpcEndpoint {
+            url: self.endpoint.resolve()?,
+            fallback_urls: self
+                .fallback_endpoints
+                .into_iter()
+                .map(RpcEndpoint::resolve)
+                .collect::<Result<_, _>>()?,
+            requests_per_second: self.requests_per_second,
+            retries: self.retries,
+            retry_backoff: self.retry_backoff,
+        })
+    }
+}
+
+impl From<RpcEndpoint> for RpcEndpointConfig {
+    fn from(endpoint: RpcEndpoint) -> Self {
+        Self {
+            endpoint,
+            fallback_endpoints: vec![],
+            requests_per_second: None,
+            retries: None,
+            retry_backoff: None,
+        }
+    }
+}
+
+/// The table form of [RpcEndpointConfig]
+#[derive(Serialize, Deserialize)]
+struct RpcEndpointTable {
+    url: RpcEndpoint,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fallback_urls: Vec<RpcEndpoint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    requests_per_second: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retries: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_backoff: Option<u64>,
+}
+
+impl Serialize for RpcEndpointConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.is_url_only() {
+            return self.endpoint.serialize(serializer)
+        }
+        RpcEndpointTable {
+            url: self.endpoint.clone(),
+            fallback_urls: self.fallback_endpoints.clone(),
+            requests_per_second: self.requests_per_second,
+            retries: self.retries,
+            retry_backoff: self.retry_backoff,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RpcEndpointConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Value {
+            Url(RpcEndpoint),
+            Table(RpcEndpointTable),
+        }
+
+        let config = match Value::deserialize(deserializer)? {
+            Value::Url(endpoint) => endpoint.into(),
+            Value::Table(table) => RpcEndpointConfig {
+                endpoint: table.url,
+                fallback_endpoints: table.fallback_urls,
+                requests_per_second: table.requests_per_second,
+                retries: table.retries,
+                retry_backoff: table.retry_backoff,
+            },
+        };
+        Ok(config)
+    }
+}
+
+/// A [RpcEndpointConfig] with all env vars resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedRpcEndpoint {
+    /// The url requests are sent to
+    pub url: String,
+    /// The urls requests are sent to, in order, once the requests to `url` keep failing
+    pub fallback_urls: Vec<String>,
+    /// The maximum number of requests sent to the endpoint per second
+    pub requests_per_second: Option<u32>,
+    /// How often a failed request is retried before failing over to the next url
+    pub retries: Option<u32>,
+    /// The initial backoff in milliseconds between two attempts of a request
+    pub retry_backoff: Option<u64>,
+}
+
+// === impl ResolvedRpcEndpoint ===
+
+impl ResolvedRpcEndpoint {
+    /// Creates an endpoint for the url, without fallbacks or custom settings
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            fallback_urls: vec![],
+            requests_per_second: None,
+            retries: None,
+            retry_backoff: None,
+        }
+    }
+}
+
 /// Container type for _resolved_ RPC endpoints, see [RpcEndpoints::resolve_all()]
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct ResolvedRpcEndpoints {
-    /// contains all named endpoints and their URL or an error if we failed to resolve the env var
+    /// contains all named endpoints and their URLs or an error if we failed to resolve the env var
     /// alias
-    endpoints: BTreeMap<String, Result<String, UnresolvedEnvVarError>>,
+    endpoints: BTreeMap<String, Result<ResolvedRpcEndpoint, UnresolvedEnvVarError>>,
 }
 
 // === impl ResolvedRpcEndpoints ===
@@ -168,7 +331,7 @@ impl ResolvedRpcEndpoints {
 }
 
 impl Deref for ResolvedRpcEndpoints {
-    type Target = BTreeMap<String, Result<String, UnresolvedEnvVarError>>;
+    type Target = BTreeMap<String, Result<ResolvedRpcEndpoint, UnresolvedEnvVarError>>;
 
     fn deref(&self) -> &Self::Target {
         &self.endpoints
@@ -215,4 +378,40 @@ mod tests {
         assert_eq!(cap.name("outer").unwrap().as_str(), "${API_KEY}");
         assert_eq!(cap.name("inner").unwrap().as_str(), "API_KEY");
     }
+
+    #[test]
+    fn can_deserialize_endpoint_config() {
+        #[derive(Deserialize)]
+        struct Endpoints {
+            rpc_endpoints: RpcEndpoints,
+        }
+
+        let Endpoints { rpc_endpoints } = toml::from_str(
+            r#"
+            [rpc_endpoints]
+            optimism = "https://example.com/"
+            mainnet = { url = "${RPC_MAINNET}", fallback_urls = ["https://cloudflare-eth.com"], requests_per_second = 10, retries = 5, retry_backoff = 500 }
+        "#,
+        )
+        .unwrap();
+
+        assert!(rpc_endpoints["optimism"].is_url_only());
+        assert_eq!(
+            rpc_endpoints["mainnet"],
+            RpcEndpointConfig {
+                endpoint: RpcEndpoint::Env("${RPC_MAINNET}".to_string()),
+                fallback_endpoints: vec![RpcEndpoint::Url(
+                    "https://cloudflare-eth.com".to_string()
+                )],
+                requests_per_second: Some(10),
+                retries: Some(5),
+                retry_backoff: Some(500),
+            }
+        );
+
+        // serializes back to the same representation
+        let s = toml::Value::try_from(&rpc_endpoints).unwrap().to_string();
+        let parsed: RpcEndpoints = toml::from_str(&s).unwrap();
+        assert_eq!(parsed, rpc_endpoints);
+    }
 }
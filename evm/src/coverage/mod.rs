@@ -82,13 +82,79 @@ impl CoverageMap {
                 for item in source.items.iter_mut() {
                     // We found a matching coverage item, but there may be more
                     let anchor = item.anchor();
-                    if ic == anchor.instruction && contract_name == anchor.contract {
+                    if Some(ic) == anchor.instruction && contract_name == anchor.contract {
                         item.increment_hits(instruction_hits);
                     }
                 }
             }
         }
     }
+
+    /// Processes data from a [HitMap] by mapping every executed instruction back to the source
+    /// range it belongs to, instead of only looking at the anchors of the coverage items.
+    ///
+    /// This is a best-effort alternative to [add_hit_map] for bytecode produced with optimizer
+    /// settings that move instructions away from the anchors we found: an item is considered hit
+    /// as often as the most executed instruction whose source range lies within the item.
+    ///
+    /// Branches are still matched by their anchors, since both paths of a branch share the same
+    /// source range. Items without an anchor, including branches, are only matched by their range.
+    pub fn add_hit_map_by_range(
+        &mut self,
+        source_version: Version,
+        source_map: &SourceMap,
+        contract_name: &str,
+        hit_map: HitMap,
+    ) {
+        // The most hits of a single instruction within each item, keyed by source ID and index
+        let mut item_hits: HashMap<(u32, usize), u64> = HashMap::new();
+
+        for (ic, instruction_hits) in hit_map.hits.into_iter() {
+            if instruction_hits == 0 {
+                continue
+            }
+
+            let element = if let Some(element) = source_map.get(ic) { element } else { continue };
+            let source_id = if let Some(source_id) = element.index { source_id } else { continue };
+
+            if let Some(source) = self.sources.get_mut(&(source_version.clone(), source_id)) {
+                for (i, item) in source.items.iter_mut().enumerate() {
+                    let anchor = item.anchor();
+                    if contract_name != anchor.contract {
+                        continue
+                    }
+
+                    if let CoverageItem::Branch { .. } = item {
+                        if let Some(instruction) = anchor.instruction {
+                            if ic == instruction {
+                                item.increment_hits(instruction_hits);
+                            }
+                            continue
+                        }
+                    }
+
+                    let loc = item.source_location();
+                    if element.offset >= loc.start &&
+                        element.offset + element.length <=
+                            loc.start + loc.length.unwrap_or_default()
+                    {
+                        let hits = item_hits.entry((source_id, i)).or_default();
+                        *hits = (*hits).max(instruction_hits);
+                    }
+                }
+            }
+        }
+
+        for ((source_id, i), hits) in item_hits {
+            if let Some(item) = self
+                .sources
+                .get_mut(&(source_version.clone(), source_id))
+                .and_then(|source| source.items.get_mut(i))
+            {
+                item.increment_hits(hits);
+            }
+        }
+    }
 }
 
 impl IntoIterator for CoverageMap {
@@ -143,18 +209,30 @@ impl SourceFile {
                 }
                 summary
             }
-            CoverageItem::Branch { hits, .. } => {
+            CoverageItem::Branch { kind, hits, .. } => {
                 summary.branch_count += 1;
                 if *hits > 0 {
                     summary.branch_hits += 1;
                 }
+                if *kind == BranchKind::Require {
+                    summary.require_count += 1;
+                    if *hits > 0 {
+                        summary.require_hits += 1;
+                    }
+                }
                 summary
             }
-            CoverageItem::Function { hits, .. } => {
+            CoverageItem::Function { kind, hits, .. } => {
                 summary.function_count += 1;
                 if *hits > 0 {
                     summary.function_hits += 1;
                 }
+                if *kind == FunctionKind::Modifier {
+                    summary.modifier_count += 1;
+                    if *hits > 0 {
+                        summary.modifier_hits += 1;
+                    }
+                }
                 summary
             }
         })
@@ -164,15 +242,21 @@ impl SourceFile {
 /// An item anchor describes what instruction (and what contract) marks a [CoverageItem] as covered.
 #[derive(Clone, Debug)]
 pub struct ItemAnchor {
-    /// The instruction counter that constitutes this anchor
-    pub instruction: usize,
+    /// The instruction counter that constitutes this anchor.
+    ///
+    /// This is `None` if no instruction could be found for the item in best effort mode, in which
+    /// case the item is only hit by [CoverageMap::add_hit_map_by_range].
+    pub instruction: Option<usize>,
     /// The contract in which the instruction is in
     pub contract: String,
 }
 
 impl Display for ItemAnchor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}:{}", self.contract, self.instruction)
+        match self.instruction {
+            Some(instruction) => write!(f, "{}:{}", self.contract, instruction),
+            None => write!(f, "{}:?", self.contract),
+        }
     }
 }
 
@@ -204,6 +288,8 @@ pub enum CoverageItem {
         loc: SourceLocation,
         /// The instruction counter that covers this branch.
         anchor: ItemAnchor,
+        /// The kind of the branch.
+        kind: BranchKind,
         /// The ID that identifies the branch.
         ///
         /// There may be multiple items with the same branch ID - they belong to the same branch,
@@ -217,12 +303,14 @@ pub enum CoverageItem {
         hits: u64,
     },
 
-    /// A function in the code.
+    /// A function or modifier in the code.
     Function {
         /// The location of the function in the source code.
         loc: SourceLocation,
         /// The instruction counter that covers this function.
         anchor: ItemAnchor,
+        /// Whether this is a function or a modifier.
+        kind: FunctionKind,
         /// The name of the function.
         name: String,
         /// The number of times this item was hit.
@@ -230,6 +318,33 @@ pub enum CoverageItem {
     },
 }
 
+/// The kind of a [CoverageItem::Branch].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BranchKind {
+    /// A path of an `if` statement or a conditional expression.
+    Conditional,
+    /// The passing or the reverting path of an `assert` or `require` call.
+    Require,
+}
+
+impl Display for BranchKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BranchKind::Conditional => f.write_str("conditional"),
+            BranchKind::Require => f.write_str("require"),
+        }
+    }
+}
+
+/// The kind of a [CoverageItem::Function].
+///
+/// Modifiers are counted as functions, since they are counted as such in the LCOV format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FunctionKind {
+    Function,
+    Modifier,
+}
+
 impl CoverageItem {
     pub fn source_location(&self) -> &SourceLocation {
         match self {
@@ -277,12 +392,15 @@ impl Display for CoverageItem {
             CoverageItem::Statement { loc, anchor, hits } => {
                 write!(f, "Statement (location: {loc}, anchor: {anchor}, hits: {hits})")
             }
-            CoverageItem::Branch { loc, anchor, hits, branch_id, path_id } => {
-                write!(f, "Branch (branch: {branch_id}, path: {path_id}) (location: {loc}, anchor: {anchor}, hits: {hits})")
+            CoverageItem::Branch { loc, anchor, kind, hits, branch_id, path_id } => {
+                write!(f, "Branch (kind: {kind}, branch: {branch_id}, path: {path_id}) (location: {loc}, anchor: {anchor}, hits: {hits})")
             }
-            CoverageItem::Function { loc, anchor, hits, name } => {
+            CoverageItem::Function { loc, anchor, kind: FunctionKind::Function, hits, name } => {
                 write!(f, r#"Function "{name}" (location: {loc}, anchor: {anchor}, hits: {hits})"#)
             }
+            CoverageItem::Function { loc, anchor, kind: FunctionKind::Modifier, hits, name } => {
+                write!(f, r#"Modifier "{name}" (location: {loc}, anchor: {anchor}, hits: {hits})"#)
+            }
         }
     }
 }
@@ -328,6 +446,15 @@ pub struct CoverageSummary {
     pub function_count: usize,
     /// The number of functions hit.
     pub function_hits: usize,
+    /// The number of modifiers in the source file, which are included in the functions.
+    pub modifier_count: usize,
+    /// The number of modifiers hit.
+    pub modifier_hits: usize,
+    /// The number of `assert` and `require` paths in the source file, which are included in the
+    /// branches.
+    pub require_count: usize,
+    /// The number of `assert` and `require` paths hit.
+    pub require_hits: usize,
 }
 
 impl AddAssign<&Self> for CoverageSummary {
@@ -340,5 +467,57 @@ impl AddAssign<&Self> for CoverageSummary {
         self.branch_hits += other.branch_hits;
         self.function_count += other.function_count;
         self.function_hits += other.function_hits;
+        self.modifier_count += other.modifier_count;
+        self.modifier_hits += other.modifier_hits;
+        self.require_count += other.require_count;
+        self.require_hits += other.require_hits;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::prelude::sourcemap;
+
+    #[test]
+    fn can_add_hit_map_by_range() {
+        let version = Version::new(0, 8, 15);
+        let anchor = |instruction| ItemAnchor { instruction, contract: "A".to_string() };
+        let loc = |start, length| SourceLocation { start, length: Some(length), line: 1 };
+        let branch = |start, length, instruction, branch_id, path_id| CoverageItem::Branch {
+            loc: loc(start, length),
+            anchor: anchor(instruction),
+            kind: BranchKind::Conditional,
+            branch_id,
+            path_id,
+            hits: 0,
+        };
+
+        let mut map = CoverageMap::default();
+        map.sources.insert(
+            (version.clone(), 0),
+            SourceFile {
+                path: "A.sol".into(),
+                items: vec![
+                    CoverageItem::Statement { loc: loc(63, 5), anchor: anchor(None), hits: 0 },
+                    branch(40, 20, Some(2), 0, 0),
+                    branch(40, 20, Some(3), 0, 1),
+                    branch(90, 10, None, 1, 0),
+                ],
+            },
+        );
+
+        // Instruction 0 lies within the statement, 1 within no item, 2 and 3 are the anchors of the
+        // paths of the first branch, and 4 lies within the unanchored branch
+        let source_map = sourcemap::parse("63:2:0:-;0:100:0:-;45:1:0:-;50:1:0:-;92:1:0:-").unwrap();
+        let mut hit_map = HitMap::default();
+        for ic in [0, 0, 1, 2, 4] {
+            hit_map.hit(ic);
+        }
+        map.add_hit_map_by_range(version.clone(), &source_map, "A", hit_map);
+
+        let hits: Vec<_> =
+            map.sources[&(version, 0)].items.iter().map(CoverageItem::hits).collect();
+        assert_eq!(hits, vec![2, 1, 0, 1]);
     }
 }
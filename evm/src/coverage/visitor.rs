@@ -1,11 +1,11 @@
-use super::{CoverageItem, ItemAnchor, SourceLocation};
+use super::{BranchKind, CoverageItem, FunctionKind, ItemAnchor, SourceLocation};
 use ethers::{
     prelude::{sourcemap::SourceMap, Bytes},
     solc::artifacts::ast::{self, Ast, Node, NodeType},
 };
 use revm::{opcode, spec_opcode_gas, SpecId};
-use std::collections::{BTreeMap, HashMap};
-use tracing::warn;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tracing::{debug, warn};
 
 #[derive(Debug, Default, Clone)]
 pub struct Visitor {
@@ -15,6 +15,14 @@ pub struct Visitor {
     source_maps: HashMap<String, SourceMap>,
     /// Bytecodes for this specific source file, keyed by the contract name.
     bytecodes: HashMap<String, Bytes>,
+    /// Whether nodes that cannot be anchored to an instruction should be kept without an anchor
+    /// instead of failing the analysis.
+    ///
+    /// This is the case for optimized bytecode, where the optimizer may have moved or removed the
+    /// instructions of a node.
+    best_effort: bool,
+    /// The lines excluded from coverage by `coverage:ignore-*` comments.
+    ignored_lines: BTreeSet<usize>,
 
     /// The contract whose AST we are currently walking
     context: String,
@@ -33,7 +41,18 @@ impl Visitor {
         source_maps: HashMap<String, SourceMap>,
         bytecodes: HashMap<String, Bytes>,
     ) -> Self {
-        Self { source, source_maps, bytecodes, ..Default::default() }
+        let ignored_lines = ignored_lines(&source);
+        Self { source, source_maps, bytecodes, ignored_lines, ..Default::default() }
+    }
+
+    /// Keep nodes that cannot be anchored to an instruction without an anchor instead of failing.
+    ///
+    /// Their hits can only be collected with [CoverageMap::add_hit_map_by_range].
+    ///
+    /// [CoverageMap::add_hit_map_by_range]: super::CoverageMap::add_hit_map_by_range
+    pub fn best_effort(mut self, best_effort: bool) -> Self {
+        self.best_effort = best_effort;
+        self
     }
 
     pub fn visit_ast(mut self, ast: Ast) -> eyre::Result<Vec<CoverageItem>> {
@@ -64,10 +83,12 @@ impl Visitor {
             node.attribute("name").ok_or_else(|| eyre::eyre!("contract has no name"))?;
         self.context = contract_name;
 
-        // Find all functions and walk their AST
+        // Find all functions and modifiers and walk their AST
         for node in node.nodes {
-            if node.node_type == NodeType::FunctionDefinition {
-                self.visit_function_definition(node)?;
+            match node.node_type {
+                NodeType::FunctionDefinition => self.visit_function_definition(node)?,
+                NodeType::ModifierDefinition => self.visit_modifier_definition(node)?,
+                _ => (),
             }
         }
 
@@ -97,10 +118,34 @@ impl Visitor {
         match node.body.take() {
             // Skip virtual functions
             Some(body) if !is_virtual => {
+                let anchor = self.try_anchor_for(&body.src)?;
+                self.push_item(CoverageItem::Function {
+                    name: format!("{}.{}", self.context, name),
+                    loc: self.source_location_for(&node.src),
+                    anchor,
+                    kind: FunctionKind::Function,
+                    hits: 0,
+                });
+                self.visit_block(*body)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn visit_modifier_definition(&mut self, mut node: Node) -> eyre::Result<()> {
+        let name: String =
+            node.attribute("name").ok_or_else(|| eyre::eyre!("modifier has no name"))?;
+        let is_virtual: bool = node.attribute("virtual").unwrap_or_default();
+
+        match node.body.take() {
+            // Skip virtual modifiers
+            Some(body) if !is_virtual => {
+                let anchor = self.try_anchor_for(&body.src)?;
                 self.push_item(CoverageItem::Function {
                     name: format!("{}.{}", self.context, name),
                     loc: self.source_location_for(&node.src),
-                    anchor: self.anchor_for(&body.src)?,
+                    anchor,
+                    kind: FunctionKind::Modifier,
                     hits: 0,
                 });
                 self.visit_block(*body)
@@ -141,20 +186,12 @@ impl Visitor {
             NodeType::YulBreak |
             NodeType::YulContinue |
             NodeType::YulLeave => {
-                self.push_item(CoverageItem::Statement {
-                    loc: self.source_location_for(&node.src),
-                    anchor: self.anchor_for(&node.src)?,
-                    hits: 0,
-                });
+                self.push_statement(&node.src)?;
                 Ok(())
             }
             // Variable declaration
             NodeType::VariableDeclarationStatement => {
-                self.push_item(CoverageItem::Statement {
-                    loc: self.source_location_for(&node.src),
-                    anchor: self.anchor_for(&node.src)?,
-                    hits: 0,
-                });
+                self.push_statement(&node.src)?;
                 if let Some(expr) = node.attribute("initialValue") {
                     self.visit_expression(expr)?;
                 }
@@ -212,14 +249,24 @@ impl Visitor {
                 // branch ID as we do
                 self.branch_id += 1;
 
-                let (true_branch, false_branch) = self.find_branches(&node.src, branch_id)?;
+                let (true_branch, false_branch) =
+                    match self.try_find_branches(&node.src, branch_id, BranchKind::Conditional)? {
+                        Some((true_branch, false_branch)) => {
+                            (Some(true_branch), Some(false_branch))
+                        }
+                        None => (None, None),
+                    };
 
                 // Process the true branch
-                self.push_item(true_branch);
+                if let Some(true_branch) = true_branch {
+                    self.push_item(true_branch);
+                }
                 self.visit_block_or_statement(true_body)?;
 
                 // Process the false branch
-                self.push_item(false_branch);
+                if let Some(false_branch) = false_branch {
+                    self.push_item(false_branch);
+                }
                 let false_body: Option<Node> = node.attribute("falseBody");
                 if let Some(false_body) = false_body {
                     self.visit_block_or_statement(false_body)?;
@@ -242,11 +289,13 @@ impl Visitor {
                 // branch ID as we do
                 self.branch_id += 1;
 
+                let anchor = self.try_anchor_for(&node.src)?;
                 self.push_item(CoverageItem::Branch {
                     branch_id,
                     path_id: 0,
                     loc: self.source_location_for(&node.src),
-                    anchor: self.anchor_for(&node.src)?,
+                    anchor,
+                    kind: BranchKind::Conditional,
                     hits: 0,
                 });
                 self.visit_block(*body)?;
@@ -278,19 +327,11 @@ impl Visitor {
         //  yulfunctioncall
         match node.node_type {
             NodeType::Assignment | NodeType::UnaryOperation | NodeType::BinaryOperation => {
-                self.push_item(CoverageItem::Statement {
-                    loc: self.source_location_for(&node.src),
-                    anchor: self.anchor_for(&node.src)?,
-                    hits: 0,
-                });
+                self.push_statement(&node.src)?;
                 Ok(())
             }
             NodeType::FunctionCall => {
-                self.push_item(CoverageItem::Statement {
-                    loc: self.source_location_for(&node.src),
-                    anchor: self.anchor_for(&node.src)?,
-                    hits: 0,
-                });
+                self.push_statement(&node.src)?;
 
                 let name = node
                     .other
@@ -298,20 +339,28 @@ impl Visitor {
                     .and_then(|v| v.get("name"))
                     .and_then(|v| v.as_str());
                 if let Some("assert" | "require") = name {
-                    let (false_branch, true_branch) =
-                        self.find_branches(&node.src, self.branch_id)?;
-                    self.push_item(true_branch);
-                    self.push_item(false_branch);
+                    if let Some((false_branch, true_branch)) =
+                        self.try_find_branches(&node.src, self.branch_id, BranchKind::Require)?
+                    {
+                        self.push_item(true_branch);
+                        self.push_item(false_branch);
+                    }
                     self.branch_id += 1;
                 }
                 Ok(())
             }
             NodeType::Conditional => {
-                self.push_item(CoverageItem::Statement {
-                    loc: self.source_location_for(&node.src),
-                    anchor: self.anchor_for(&node.src)?,
-                    hits: 0,
-                });
+                self.push_statement(&node.src)?;
+
+                // The true expression directly follows the JUMPI, just like the true body of an
+                // if statement
+                if let Some((true_branch, false_branch)) =
+                    self.try_find_branches(&node.src, self.branch_id, BranchKind::Conditional)?
+                {
+                    self.push_item(true_branch);
+                    self.push_item(false_branch);
+                }
+                self.branch_id += 1;
                 Ok(())
             }
             // Does not count towards coverage
@@ -354,8 +403,13 @@ impl Visitor {
     }
 
     /// Pushes a coverage item to the internal collection, and might push a line item as well.
+    ///
+    /// Items on lines that are excluded from coverage are skipped.
     fn push_item(&mut self, item: CoverageItem) {
         let source_location = item.source_location();
+        if self.ignored_lines.contains(&source_location.line) {
+            return
+        }
 
         // Push a line item if we haven't already
         if matches!(item, CoverageItem::Statement { .. } | CoverageItem::Branch { .. }) &&
@@ -372,6 +426,17 @@ impl Visitor {
         self.items.push(item);
     }
 
+    /// Pushes a statement item for the given source range.
+    fn push_statement(&mut self, loc: &ast::SourceLocation) -> eyre::Result<()> {
+        let anchor = self.try_anchor_for(loc)?;
+        self.push_item(CoverageItem::Statement {
+            loc: self.source_location_for(loc),
+            anchor,
+            hits: 0,
+        });
+        Ok(())
+    }
+
     fn source_location_for(&self, loc: &ast::SourceLocation) -> SourceLocation {
         SourceLocation {
             start: loc.start,
@@ -413,7 +478,40 @@ impl Visitor {
                 )
             })?;
 
-        Ok(ItemAnchor { instruction, contract: self.context.clone() })
+        Ok(ItemAnchor { instruction: Some(instruction), contract: self.context.clone() })
+    }
+
+    /// Like [Self::anchor_for], but returns an anchor without an instruction instead of an error in
+    /// best effort mode.
+    fn try_anchor_for(&self, loc: &ast::SourceLocation) -> eyre::Result<ItemAnchor> {
+        match self.anchor_for(loc) {
+            Ok(anchor) => Ok(anchor),
+            Err(err) if self.best_effort => {
+                debug!("keeping coverage item without an anchor: {}", err);
+                Ok(ItemAnchor { instruction: None, contract: self.context.clone() })
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Like [Self::find_branches], but returns `None` instead of an error in best effort mode.
+    ///
+    /// Branches whose paths cannot be told apart are skipped, since mapping them by their shared
+    /// source range would count every path as hit.
+    fn try_find_branches(
+        &self,
+        loc: &ast::SourceLocation,
+        branch_id: usize,
+        kind: BranchKind,
+    ) -> eyre::Result<Option<(CoverageItem, CoverageItem)>> {
+        match self.find_branches(loc, branch_id, kind) {
+            Ok(branches) => Ok(Some(branches)),
+            Err(err) if self.best_effort => {
+                debug!("skipping coverage branches: {}", err);
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
     }
 
     /// Finds the true and false branches for a node.
@@ -421,7 +519,7 @@ impl Visitor {
     /// This finds the relevant anchors and creates coverage items for both of them. These anchors
     /// are found using the bytecode of the contract in the range of the branching node.
     ///
-    /// For `IfStatement` nodes, the template is generally:
+    /// For `IfStatement` and `Conditional` nodes, the template is generally:
     /// ```text
     /// <condition>
     /// PUSH <ic if false>
@@ -448,6 +546,7 @@ impl Visitor {
         &self,
         loc: &ast::SourceLocation,
         branch_id: usize,
+        kind: BranchKind,
     ) -> eyre::Result<(CoverageItem, CoverageItem)> {
         let source_map = self.source_maps.get(&self.context).ok_or_else(|| {
             eyre::eyre!(
@@ -502,7 +601,10 @@ impl Visitor {
                 let is_in_source_range = element.offset >= loc.start &&
                     element.offset + element.length <=
                         loc.start + loc.length.unwrap_or_default();
-                if source_ids_match && is_in_source_range && bytecode.0[pc + 1] == opcode::JUMPI {
+                if source_ids_match &&
+                    is_in_source_range &&
+                    bytecode.0.get(pc + 1) == Some(&opcode::JUMPI)
+                {
                     // We do not support program counters bigger than usize. This is also an
                     // assumption in REVM, so this is just a sanity check.
                     if push_size > 8 {
@@ -534,9 +636,10 @@ impl Visitor {
                     path_id: 0,
                     loc: self.source_location_for(loc),
                     anchor: ItemAnchor {
-                        instruction: first_branch_ic,
+                        instruction: Some(first_branch_ic),
                         contract: self.context.clone()
                     },
+                    kind,
                     hits: 0,
                 },
                 CoverageItem::Branch {
@@ -544,9 +647,10 @@ impl Visitor {
                     path_id: 1,
                     loc: self.source_location_for(loc),
                     anchor: ItemAnchor {
-                        instruction: *ic_map.get(&second_branch_pc).expect("we cannot translate the program counter of the second branch to an instruction counter"),
+                        instruction: Some(*ic_map.get(&second_branch_pc).expect("we cannot translate the program counter of the second branch to an instruction counter")),
                         contract: self.context.clone()
                     },
+                    kind,
                     hits: 0
                 }
             )),
@@ -554,3 +658,242 @@ impl Visitor {
         }
     }
 }
+
+/// Returns the (1-based) lines of the source that are excluded from coverage.
+///
+/// A line is excluded if it contains a `// coverage:ignore-line` comment, or if it is in between a
+/// `// coverage:ignore-start` and a `// coverage:ignore-end` comment (inclusive).
+fn ignored_lines(source: &str) -> BTreeSet<usize> {
+    let mut lines = BTreeSet::new();
+    let mut ignoring = false;
+    for (i, line) in source.lines().enumerate() {
+        if line.contains("coverage:ignore-start") {
+            ignoring = true;
+        }
+        if ignoring || line.contains("coverage:ignore-line") {
+            lines.insert(i + 1);
+        }
+        if line.contains("coverage:ignore-end") {
+            ignoring = false;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coverage::SourceFile;
+    use ethers::prelude::sourcemap;
+    use serde_json::json;
+
+    const SOURCE: &str = r#"contract A {
+    uint256 x;
+
+    function a() public {
+        x = 1;
+    }
+}
+"#;
+
+    /// The AST of [SOURCE]
+    fn ast() -> Ast {
+        serde_json::from_value(json!({
+            "absolutePath": "A.sol",
+            "id": 1,
+            "exportedSymbols": {},
+            "nodeType": "SourceUnit",
+            "src": "0:77:0",
+            "nodes": [{
+                "id": 2,
+                "nodeType": "ContractDefinition",
+                "src": "0:77:0",
+                "name": "A",
+                "contractKind": "contract",
+                "abstract": false,
+                "nodes": [{
+                    "id": 3,
+                    "nodeType": "FunctionDefinition",
+                    "src": "33:42:0",
+                    "name": "a",
+                    "kind": "function",
+                    "virtual": false,
+                    "body": {
+                        "id": 4,
+                        "nodeType": "Block",
+                        "src": "53:22:0",
+                        "statements": [{
+                            "id": 5,
+                            "nodeType": "ExpressionStatement",
+                            "src": "63:6:0",
+                            "expression": {
+                                "id": 6,
+                                "nodeType": "Assignment",
+                                "src": "63:5:0"
+                            }
+                        }]
+                    }
+                }]
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn visitor(source_map: &str) -> Visitor {
+        Visitor::new(
+            SOURCE.to_string(),
+            HashMap::from([("A".to_string(), sourcemap::parse(source_map).unwrap())]),
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn can_anchor_items() {
+        let items = visitor("53:22:0:-;63:5:0:-").visit_ast(ast()).unwrap();
+        let anchors: Vec<_> = items.iter().map(|item| item.anchor().instruction).collect();
+        assert_eq!(anchors, vec![Some(0), Some(1), Some(1)]);
+    }
+
+    #[test]
+    fn can_keep_items_without_anchor_in_best_effort_mode() {
+        // no instruction lies within the assignment
+        let source_map = "53:22:0:-;0:77:0:-";
+        assert!(visitor(source_map).visit_ast(ast()).is_err());
+
+        let items = visitor(source_map).best_effort(true).visit_ast(ast()).unwrap();
+        assert_eq!(items.len(), 3);
+        assert!(
+            matches!(&items[0], CoverageItem::Function { name, .. } if name == "A.a"),
+            "{}",
+            items[0]
+        );
+        assert_eq!(items[0].anchor().instruction, Some(0));
+        assert!(matches!(items[1], CoverageItem::Line { .. }), "{}", items[1]);
+        assert_eq!(items[1].anchor().instruction, None);
+        assert!(matches!(items[2], CoverageItem::Statement { .. }), "{}", items[2]);
+        assert_eq!(items[2].anchor().instruction, None);
+        assert_eq!(items[2].source_location().start, 63);
+    }
+
+    const MODIFIER_SOURCE: &str = r#"contract B {
+    modifier m() {
+        require(x);
+        _;
+    }
+}
+"#;
+
+    /// The AST of [MODIFIER_SOURCE]
+    fn modifier_ast() -> Ast {
+        serde_json::from_value(json!({
+            "absolutePath": "B.sol",
+            "id": 1,
+            "exportedSymbols": {},
+            "nodeType": "SourceUnit",
+            "src": "0:71:0",
+            "nodes": [{
+                "id": 2,
+                "nodeType": "ContractDefinition",
+                "src": "0:71:0",
+                "name": "B",
+                "contractKind": "contract",
+                "abstract": false,
+                "nodes": [{
+                    "id": 3,
+                    "nodeType": "ModifierDefinition",
+                    "src": "17:51:0",
+                    "name": "m",
+                    "virtual": false,
+                    "body": {
+                        "id": 4,
+                        "nodeType": "Block",
+                        "src": "30:38:0",
+                        "statements": [{
+                            "id": 5,
+                            "nodeType": "ExpressionStatement",
+                            "src": "40:11:0",
+                            "expression": {
+                                "id": 6,
+                                "nodeType": "FunctionCall",
+                                "src": "40:10:0",
+                                "expression": { "id": 7, "nodeType": "Identifier", "name": "require" }
+                            }
+                        }, {
+                            "id": 8,
+                            "nodeType": "PlaceholderStatement",
+                            "src": "60:2:0"
+                        }]
+                    }
+                }]
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn can_tell_modifiers_and_require_branches_apart() {
+        // PUSH1 0x04, JUMPI, INVALID, JUMPDEST, STOP: the revert path of the `require` is the
+        // instruction after the JUMPI, the passing path is the jump destination
+        let visitor = Visitor::new(
+            MODIFIER_SOURCE.to_string(),
+            HashMap::from([(
+                "B".to_string(),
+                sourcemap::parse("40:10:0:-;40:10:0:-;40:10:0:-;40:10:0:-;60:2:0:-").unwrap(),
+            )]),
+            HashMap::from([(
+                "B".to_string(),
+                Bytes::from(vec![0x60, 0x04, 0x57, 0xfe, 0x5b, 0x00]),
+            )]),
+        );
+        let items = visitor.visit_ast(modifier_ast()).unwrap();
+        assert_eq!(items.len(), 7);
+
+        assert!(
+            matches!(
+                &items[0],
+                CoverageItem::Function { name, kind: FunctionKind::Modifier, .. } if name == "B.m"
+            ),
+            "{}",
+            items[0]
+        );
+        assert!(matches!(items[2], CoverageItem::Statement { .. }), "{}", items[2]);
+        assert!(
+            matches!(
+                items[3],
+                CoverageItem::Branch { kind: BranchKind::Require, branch_id: 0, path_id: 1, .. }
+            ),
+            "{}",
+            items[3]
+        );
+        assert_eq!(items[3].anchor().instruction, Some(3));
+        assert!(
+            matches!(
+                items[4],
+                CoverageItem::Branch { kind: BranchKind::Require, branch_id: 0, path_id: 0, .. }
+            ),
+            "{}",
+            items[4]
+        );
+        assert_eq!(items[4].anchor().instruction, Some(2));
+        assert_eq!(items[6].anchor().instruction, Some(4));
+
+        let summary = SourceFile { path: "B.sol".into(), items }.summary();
+        assert_eq!((summary.function_count, summary.modifier_count), (1, 1));
+        assert_eq!((summary.branch_count, summary.require_count), (2, 2));
+    }
+
+    #[test]
+    fn can_find_ignored_lines() {
+        let source = r#"contract A {
+    function a() public {
+        x = 1; // coverage:ignore-line
+        y = 2;
+        // coverage:ignore-start
+        z = 3;
+        // coverage:ignore-end
+        w = 4;
+    }
+}"#;
+        assert_eq!(ignored_lines(source), BTreeSet::from([3, 5, 6, 7]));
+    }
+}
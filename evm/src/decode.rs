@@ -4,7 +4,7 @@ use crate::{
     executor::inspector::cheatcodes::util::ERROR_PREFIX,
 };
 use ethers::{
-    abi::{AbiDecode, Contract as Abi, RawLog},
+    abi::{AbiDecode, Contract as Abi, RawLog, Token},
     contract::EthLogDecode,
     prelude::U256,
     types::Log,
@@ -63,6 +63,28 @@ pub fn decode_console_log(log: &Log) -> Option<String> {
     Some(decoded)
 }
 
+/// Returns the reason of the Solidity builtin panic with the given code.
+///
+/// See <https://docs.soliditylang.org/en/latest/control-structures.html#panic-via-assert-and-error-via-require>
+pub fn panic_reason(code: U256) -> Option<&'static str> {
+    if code > U256::from(u8::MAX) {
+        return None
+    }
+    Some(match code.as_u32() {
+        0x00 => "Generic compiler panic",
+        0x01 => "Assertion violated",
+        0x11 => "Arithmetic over/underflow",
+        0x12 => "Division or modulo by 0",
+        0x21 => "Conversion into non-existent enum type",
+        0x22 => "Incorrectly encoded storage byte array",
+        0x31 => "`pop()` on empty array",
+        0x32 => "Index out of bounds",
+        0x41 => "Memory allocation overflow",
+        0x51 => "Calling a zero initialized variable of internal function type",
+        _ => return None,
+    })
+}
+
 /// Given an ABI encoded error string with the function signature `Error(string)`, it decodes
 /// it and returns the revert error message.
 pub fn decode_revert(
@@ -81,48 +103,10 @@ pub fn decode_revert(
     match err[..SELECTOR_LEN] {
         // keccak(Panic(uint256))
         [78, 72, 123, 113] => {
-            // ref: https://soliditydeveloper.com/solidity-0.8
-            match err[err.len() - 1] {
-                1 => {
-                    // assert
-                    Ok("Assertion violated".to_string())
-                }
-                17 => {
-                    // safemath over/underflow
-                    Ok("Arithmetic over/underflow".to_string())
-                }
-                18 => {
-                    // divide by 0
-                    Ok("Division or modulo by 0".to_string())
-                }
-                33 => {
-                    // conversion into non-existent enum type
-                    Ok("Conversion into non-existent enum type".to_string())
-                }
-                34 => {
-                    // incorrectly encoded storage byte array
-                    Ok("Incorrectly encoded storage byte array".to_string())
-                }
-                49 => {
-                    // pop() on empty array
-                    Ok("`pop()` on empty array".to_string())
-                }
-                50 => {
-                    // index out of bounds
-                    Ok("Index out of bounds".to_string())
-                }
-                65 => {
-                    // allocating too much memory or creating too large array
-                    Ok("Memory allocation overflow".to_string())
-                }
-                81 => {
-                    // calling a zero initialized variable of internal function type
-                    Ok("Calling a zero initialized variable of internal function type".to_string())
-                }
-                _ => {
-                    eyre::bail!("Unsupported solidity builtin panic")
-                }
-            }
+            let code = U256::from(&err[SELECTOR_LEN..err.len().min(SELECTOR_LEN + 32)]);
+            Ok(panic_reason(code)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("Panic(0x{:x})", code)))
         }
         // keccak(Error(string))
         [8, 195, 121, 160] => {
@@ -168,7 +152,13 @@ pub fn decode_revert(
                         if let Ok(decoded) = abi_error.decode(&err[SELECTOR_LEN..]) {
                             let inputs = decoded
                                 .iter()
-                                .map(foundry_utils::format_token)
+                                .map(|token| match token {
+                                    // errors may wrap the revert data of a nested call
+                                    Token::Bytes(bytes) => decode_revert(bytes, maybe_abi, None)
+                                        .map(|decoded| format!(r#""{}""#, decoded))
+                                        .unwrap_or_else(|_| foundry_utils::format_token(token)),
+                                    token => foundry_utils::format_token(token),
+                                })
                                 .collect::<Vec<_>>()
                                 .join(", ");
                             return Ok(format!("{}({})", abi_error.name, inputs))
@@ -198,3 +188,27 @@ pub fn decode_revert(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::AbiEncode;
+
+    fn panic(code: u64) -> Vec<u8> {
+        [[78, 72, 123, 113].as_slice(), &U256::from(code).encode()].concat()
+    }
+
+    #[test]
+    fn decodes_panics() {
+        assert_eq!(decode_revert(&panic(0x11), None, None).unwrap(), "Arithmetic over/underflow");
+        assert_eq!(decode_revert(&panic(0x32), None, None).unwrap(), "Index out of bounds");
+        assert_eq!(decode_revert(&panic(0x99), None, None).unwrap(), "Panic(0x99)");
+        assert_eq!(decode_revert(&panic(0x111), None, None).unwrap(), "Panic(0x111)");
+    }
+
+    #[test]
+    fn decodes_revert_strings() {
+        let err = [[8, 195, 121, 160].as_slice(), &"reason".to_string().encode()].concat();
+        assert_eq!(decode_revert(&err, None, None).unwrap(), "reason");
+    }
+}
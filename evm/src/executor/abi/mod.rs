@@ -16,8 +16,13 @@ ethers::contract::abigen!(
     HEVM,
     r#"[
             struct Log {bytes32[] topics; bytes data;}
+            struct StorageAccess {address account; address caller; bytes32 slot; bool isWrite; uint64 depth;}
+            struct Gas {uint64 gasLimit; uint64 gasTotalUsed; uint64 gasMemoryUsed; int64 gasRefunded;}
+            struct AccountStorageAccess {address account; bytes32 slot; bool isWrite; bytes32 previousValue; bytes32 newValue; bool reverted;}
+            struct AccountAccess {uint8 kind; address account; address accessor; bool initialized; uint256 oldBalance; uint256 newBalance; bytes deployedCode; uint256 value; bytes data; bool reverted; AccountStorageAccess[] storageAccesses; uint64 depth;}
             roll(uint256)
             warp(uint256)
+            rollBlocks(uint256,uint256)
             fee(uint256)
             coinbase(address)
             store(address,bytes32,bytes32)
@@ -39,7 +44,10 @@ ethers::contract::abigen!(
             envString(string,string)(string[])
             envBytes(string,string)(bytes[])
             addr(uint256)(address)
+            computeCreate2Address(bytes32,bytes32)(address)
+            computeCreate2Address(bytes32,bytes32,address)(address)
             sign(uint256,bytes32)(uint8,bytes32,bytes32)
+            signTypedData(uint256,string)(uint8,bytes32,bytes32)
             deriveKey(string,uint32)(uint256)
             deriveKey(string,string,uint32)(uint256)
             prank(address)
@@ -47,32 +55,60 @@ ethers::contract::abigen!(
             prank(address,address)
             startPrank(address,address)
             stopPrank()
+            startPrankScope(address)(uint256)
+            startPrankScope(address,address)(uint256)
+            stopPrankScope(uint256)
             deal(address,uint256)
             etch(address,bytes)
+            loadAllocs(string)
             expectRevert()
             expectRevert(bytes)
             expectRevert(bytes4)
+            expectPartialRevert(bytes4)
+            expectPartialRevert(bytes)
+            expectRevertSubstring(string)
+            expectNoRevert()
+            expectGasUsed(uint64)
+            lastCallGas()(Gas)
             record()
             accesses(address)(bytes32[],bytes32[])
+            getRecordedAccesses()(StorageAccess[])
+            startStateDiffRecording()
+            stopAndReturnStateDiff()(AccountAccess[])
             recordLogs()
             getRecordedLogs()(Log[])
             expectEmit(bool,bool,bool,bool)
             expectEmit(bool,bool,bool,bool,address)
+            expectNoEmit(bool,bool,bool,bool)
+            expectNoEmit(bool,bool,bool,bool,address)
             mockCall(address,bytes,bytes)
             mockCall(address,uint256,bytes,bytes)
+            mockContract(address,string,string)
+            strictMock(address)
             clearMockedCalls()
             expectCall(address,bytes)
             expectCall(address,uint256,bytes)
+            expectCall(address,bytes,uint64)
+            expectCall(address,uint256,bytes,uint64)
+            expectOrderedCalls()
             getCode(string)
+            deployCode(string)(address)
+            deployCode(string,bytes)(address)
             label(address,string)
             assume(bool)
+            randomUint()(uint256)
+            randomUint(uint256,uint256)(uint256)
+            randomAddress()(address)
+            randomBytes(uint256)(bytes)
             setNonce(address,uint64)
             getNonce(address)
             chainId(uint256)
             broadcast()
             broadcast(address)
+            broadcast(uint256)
             startBroadcast()
             startBroadcast(address)
+            startBroadcast(uint256)
             stopBroadcast()
             readFile(string)(string)
             writeFile(string,string)
@@ -81,12 +117,57 @@ ethers::contract::abigen!(
             writeLine(string,string)
             closeFile(string)
             removeFile(string)
+            parseJson(string,string)(bytes)
+            parseJson(string)(bytes)
+            parseJsonUint(string,string)(uint256)
+            parseJsonUintArray(string,string)(uint256[])
+            parseJsonInt(string,string)(int256)
+            parseJsonIntArray(string,string)(int256[])
+            parseJsonBool(string,string)(bool)
+            parseJsonBoolArray(string,string)(bool[])
+            parseJsonAddress(string,string)(address)
+            parseJsonAddressArray(string,string)(address[])
+            parseJsonBytes32(string,string)(bytes32)
+            parseJsonBytes32Array(string,string)(bytes32[])
+            parseJsonString(string,string)(string)
+            parseJsonStringArray(string,string)(string[])
+            parseJsonBytes(string,string)(bytes)
+            parseJsonBytesArray(string,string)(bytes[])
+            serializeBool(string,string,bool)(string)
+            serializeBool(string,string,bool[])(string)
+            serializeUint(string,string,uint256)(string)
+            serializeUint(string,string,uint256[])(string)
+            serializeInt(string,string,int256)(string)
+            serializeInt(string,string,int256[])(string)
+            serializeAddress(string,string,address)(string)
+            serializeAddress(string,string,address[])(string)
+            serializeBytes32(string,string,bytes32)(string)
+            serializeBytes32(string,string,bytes32[])(string)
+            serializeString(string,string,string)(string)
+            serializeString(string,string,string[])(string)
+            serializeBytes(string,string,bytes)(string)
+            serializeBytes(string,string,bytes[])(string)
+            writeJson(string,string)
+            writeJson(string,string,string)
             toString(bytes)
             toString(address)
             toString(uint256)
             toString(int256)
             toString(bytes32)
             toString(bool)
+            parseBytes(string)(bytes)
+            parseAddress(string)(address)
+            parseUint(string)(uint256)
+            parseInt(string)(int256)
+            parseBytes32(string)(bytes32)
+            parseBool(string)(bool)
+            toUppercase(string)(string)
+            toLowercase(string)(string)
+            trim(string)(string)
+            replace(string,string,string)(string)
+            split(string,string)(string[])
+            indexOf(string,string)(uint256)
+            concat(string[])(string)
             snapshot()(uint256)
             revertTo(uint256)(bool)
             createFork(string,uint256)(uint256)
@@ -97,8 +178,15 @@ ethers::contract::abigen!(
             activeFork()(uint256)
             rollFork(uint256)
             rollFork(uint256,uint256)
+            transact(bytes32)
             rpcUrl(string)(string)
             rpcUrls()(string[2][])
+            sleep(uint256)
+            waitForFile(string,uint256)
+            waitForHttp(string,uint256)(string)
+            waitForHttp(string,string,uint256)(string)
+            waitForStorage(string,address,bytes32,bytes32,uint256)
+            assertStorageLayoutCompatible(string,string)
     ]"#,
 );
 pub use hevm_mod::{HEVMCalls, HEVM_ABI};
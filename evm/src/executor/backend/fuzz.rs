@@ -2,7 +2,10 @@ use super::update_current_env_with_fork_env;
 use crate::{
     abi::CHEATCODE_ADDRESS,
     executor::{
-        backend::{snapshot::BackendSnapshot, Backend, BackendDatabase, BackendInner, DatabaseExt},
+        backend::{
+            replay_transaction, snapshot::BackendSnapshot, Backend, BackendDatabase, BackendInner,
+            DatabaseExt,
+        },
         fork::{CreateFork, ForkId},
     },
     Address,
@@ -12,8 +15,8 @@ use ethers::prelude::{H160, H256, U256};
 use hashbrown::HashMap as Map;
 use revm::{
     db::{CacheDB, DatabaseRef},
-    Account, AccountInfo, Database, Env, Inspector, Log, Return, SubRoutine, TransactOut,
-    TransactTo,
+    Account, AccountInfo, Database, DatabaseCommit, Env, Inspector, Log, Return, SubRoutine,
+    TransactOut, TransactTo,
 };
 use tracing::{trace, warn};
 
@@ -26,6 +29,10 @@ use tracing::{trace, warn};
 /// Main purpose for this type is for fuzzing. A test function fuzzer will repeatedly execute  the
 /// function via immutable raw (no state changes) calls.
 ///
+/// Regular (non-fuzz) tests are executed the same way, so the state of a test contract after
+/// `setUp()` is effectively a snapshot that every test of the contract starts from, without
+/// requiring a clone of the `Backend` per test.
+///
 /// **N.B.**: we're assuming cheatcodes that alter the state (like multi fork swapping) are niche.
 /// If they executed during fuzzing, it will require a clone of the initial input database. This way
 /// we can support these cheatcodes in fuzzing cheaply without adding overhead for fuzz tests that
@@ -239,6 +246,27 @@ impl<'a> DatabaseExt for FuzzBackendWrapper<'a> {
     fn ensure_fork_id(&self, id: U256) -> eyre::Result<&ForkId> {
         self.inner.ensure_fork_id(id).or_else(|_| self.backend.ensure_fork_id(id))
     }
+
+    fn transact(
+        &mut self,
+        tx_hash: H256,
+        env: &Env,
+        subroutine: &mut SubRoutine,
+    ) -> eyre::Result<(Map<Address, Account>, Vec<Log>)> {
+        replay_transaction(self.active_db().clone(), tx_hash, env, subroutine)
+    }
+
+    fn commit_changes(&mut self, changes: Map<Address, Account>) {
+        // the changes are committed to a copy of the database, the `backend` is never modified
+        match self.db_override {
+            Some(ref mut db) => db.commit(changes),
+            None => {
+                let mut db = self.backend.db.clone();
+                db.commit(changes);
+                self.set_active(db);
+            }
+        }
+    }
 }
 
 impl<'a> DatabaseRef for FuzzBackendWrapper<'a> {
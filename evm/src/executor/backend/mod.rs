@@ -1,6 +1,9 @@
-use crate::executor::{
-    fork::{CreateFork, ForkId, MultiFork, SharedBackend},
-    snapshot::Snapshots,
+use crate::{
+    executor::{
+        fork::{CreateFork, ForkId, MultiFork, SharedBackend},
+        snapshot::Snapshots,
+    },
+    utils::h256_to_u256_be,
 };
 use bytes::Bytes;
 use ethers::{
@@ -10,10 +13,10 @@ use ethers::{
 use hashbrown::HashMap as Map;
 use revm::{
     db::{CacheDB, DatabaseRef, EmptyDB},
-    Account, AccountInfo, Database, DatabaseCommit, Env, Inspector, Log, Return, SubRoutine,
-    TransactOut, TransactTo,
+    Account, AccountInfo, CreateScheme, Database, DatabaseCommit, Env, Inspector, Log, Return,
+    SubRoutine, TransactOut, TransactTo, TxEnv, KECCAK_EMPTY,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tracing::{trace, warn};
 mod fuzz;
 mod snapshot;
@@ -97,6 +100,25 @@ pub trait DatabaseExt: Database {
 
     /// Ensures that a corresponding `ForkId` exists for the given local `id`
     fn ensure_fork_id(&self, id: U256) -> eyre::Result<&ForkId>;
+
+    /// Fetches the transaction with the given hash from the active fork and executes it on top of
+    /// the current state, including the uncommitted changes of the `subroutine`.
+    ///
+    /// The transaction is _not_ committed, instead its state changes and logs are returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no fork is active or the transaction does not exist
+    fn transact(
+        &mut self,
+        tx_hash: H256,
+        env: &Env,
+        subroutine: &mut SubRoutine,
+    ) -> eyre::Result<(Map<Address, Account>, Vec<Log>)>;
+
+    /// Commits the `changes` to the active database in the middle of a transaction, so that a
+    /// transaction of its own that is executed on top of the database sees them
+    fn commit_changes(&mut self, changes: Map<Address, Account>);
 }
 
 /// Provides the underlying `revm::Database` implementation.
@@ -192,6 +214,38 @@ impl Backend {
         self.db.insert_account_info(address, account)
     }
 
+    /// Sets the value of a storage slot of an account
+    pub fn insert_account_storage(&mut self, address: Address, slot: U256, value: U256) {
+        self.db.insert_account_storage(address, slot, value)
+    }
+
+    /// Returns the local state of all accounts that were loaded or modified, including their code
+    /// and storage.
+    ///
+    /// In fork mode this does not include accounts of the fork that were never touched.
+    pub fn local_accounts(&self) -> BTreeMap<Address, (AccountInfo, BTreeMap<U256, U256>)> {
+        self.db
+            .accounts
+            .iter()
+            .map(|(address, account)| {
+                let mut info = account.info.clone();
+                if info.code.as_ref().map(|code| code.is_empty()).unwrap_or(true) &&
+                    info.code_hash != KECCAK_EMPTY
+                {
+                    info.code = Some(DatabaseRef::code_by_hash(&self.db, info.code_hash));
+                }
+                let storage = account.storage.iter().map(|(slot, value)| (*slot, *value)).collect();
+                (*address, (info, storage))
+            })
+            .filter(|(_, (info, storage))| {
+                !info.balance.is_zero() ||
+                    info.nonce != 0 ||
+                    info.code_hash != KECCAK_EMPTY ||
+                    !storage.is_empty()
+            })
+            .collect()
+    }
+
     /// Returns all forks created by this backend
     pub fn created_forks(&self) -> &HashMap<ForkId, SharedBackend> {
         &self.inner.created_forks
@@ -362,6 +416,19 @@ impl DatabaseExt for Backend {
     fn ensure_fork_id(&self, id: U256) -> eyre::Result<&ForkId> {
         self.inner.ensure_fork_id(id)
     }
+
+    fn transact(
+        &mut self,
+        tx_hash: H256,
+        env: &Env,
+        subroutine: &mut SubRoutine,
+    ) -> eyre::Result<(Map<Address, Account>, Vec<Log>)> {
+        replay_transaction(self.db.clone(), tx_hash, env, subroutine)
+    }
+
+    fn commit_changes(&mut self, changes: Map<Address, Account>) {
+        self.db.commit(changes)
+    }
 }
 
 impl DatabaseRef for Backend {
@@ -576,3 +643,59 @@ pub(crate) fn update_current_env_with_fork_env(current: &mut Env, fork: Env) {
     current.block = fork.block;
     current.cfg = fork.cfg;
 }
+
+/// Executes the transaction with the given hash, fetched from the fork of the `db`, on top of the
+/// `db` and the uncommitted changes of the `subroutine` without committing it
+pub(crate) fn replay_transaction(
+    mut db: CacheDB<BackendDatabase>,
+    tx_hash: H256,
+    env: &Env,
+    subroutine: &mut SubRoutine,
+) -> eyre::Result<(Map<Address, Account>, Vec<Log>)> {
+    let fork = match db.db {
+        BackendDatabase::Forked(ref fork, _) => fork,
+        BackendDatabase::InMemory(_) => eyre::bail!("No fork active"),
+    };
+    let tx = fork
+        .get_transaction(tx_hash)?
+        .ok_or_else(|| eyre::eyre!("Transaction `{:?}` not found", tx_hash))?;
+    trace!(target: "backend", "Replaying transaction {:?}", tx_hash);
+
+    let mut env = env.clone();
+    env.tx = TxEnv {
+        caller: tx.from,
+        gas_limit: tx.gas.as_u64(),
+        gas_price: tx.gas_price.unwrap_or_default(),
+        gas_priority_fee: tx.max_priority_fee_per_gas,
+        transact_to: match tx.to {
+            Some(to) => TransactTo::Call(to),
+            None => TransactTo::Create(CreateScheme::Create),
+        },
+        value: tx.value,
+        data: tx.input.0,
+        chain_id: tx.chain_id.map(|id| id.as_u64()),
+        // the transaction is replayed on top of a different state than the one it was included in,
+        // so its nonce is not checked
+        nonce: None,
+        access_list: tx
+            .access_list
+            .map(|list| {
+                list.0
+                    .into_iter()
+                    .map(|item| {
+                        (item.address, item.storage_keys.into_iter().map(h256_to_u256_be).collect())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+
+    db.commit(subroutine.state().clone());
+
+    let mut evm = revm::EVM::new();
+    evm.env = env;
+    evm.database(&db);
+    let (status, _, _, changes, logs) = evm.transact_ref();
+    trace!(target: "backend", ?status, "Replayed transaction {:?}", tx_hash);
+    Ok((changes, logs))
+}
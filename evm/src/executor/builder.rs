@@ -1,9 +1,13 @@
 use super::{
-    inspector::{Cheatcodes, InspectorStackConfig},
+    inspector::{
+        Cheatcodes, CustomInspectorFactory, CustomPrecompile, CustomPrecompiles, GasSchedule,
+        InspectorStackConfig,
+    },
     Executor,
 };
 use crate::executor::{backend::Backend, inspector::CheatsConfig};
-use ethers::types::U256;
+use ethers::types::{Address, U256};
+use std::sync::Arc;
 
 use revm::{Env, SpecId};
 
@@ -48,6 +52,49 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Enables or disables collecting the access lists of calls and deployments
+    #[must_use]
+    pub fn set_access_list(mut self, enable: bool) -> Self {
+        self.inspector_config.access_list = enable;
+        self
+    }
+
+    /// Sets the hypothetical gas schedule to measure gas differences against
+    #[must_use]
+    pub fn set_gas_schedule(mut self, schedule: Option<GasSchedule>) -> Self {
+        self.inspector_config.gas_schedule = schedule;
+        self
+    }
+
+    /// Sets the factories of the custom inspectors that run alongside the built-in inspectors
+    #[must_use]
+    pub fn with_custom_inspectors(
+        mut self,
+        factories: Vec<Arc<dyn CustomInspectorFactory>>,
+    ) -> Self {
+        self.inspector_config.custom = factories;
+        self
+    }
+
+    /// Registers a precompile at `address`, calls to the address are answered by the `precompile`
+    /// instead of the code at the address
+    #[must_use]
+    pub fn with_precompile(
+        mut self,
+        address: Address,
+        precompile: impl CustomPrecompile + 'static,
+    ) -> Self {
+        self.inspector_config.precompiles.insert(address, precompile);
+        self
+    }
+
+    /// Registers all the `precompiles`, see [ExecutorBuilder::with_precompile]
+    #[must_use]
+    pub fn with_precompiles(mut self, precompiles: CustomPrecompiles) -> Self {
+        self.inspector_config.precompiles.extend(precompiles);
+        self
+    }
+
     /// Sets the EVM spec to use
     #[must_use]
     pub fn with_spec(mut self, spec: SpecId) -> Self {
@@ -1,11 +1,11 @@
 //! Smart caching and deduplication of requests when using a forking provider
 use revm::{db::DatabaseRef, AccountInfo, KECCAK_EMPTY};
 
-use crate::executor::fork::{cache::FlushJsonBlockCacheDB, BlockchainDb};
+use crate::executor::fork::{cache::FlushJsonBlockCacheDB, BlockchainDb, MemDb};
 use ethers::{
     core::abi::ethereum_types::BigEndianHash,
     providers::Middleware,
-    types::{Address, BlockId, Bytes, H160, H256, U256},
+    types::{Address, BlockId, Bytes, Transaction, H160, H256, U256},
     utils::keccak256,
 };
 use futures::{
@@ -28,12 +28,15 @@ type AccountFuture<Err> =
     Pin<Box<dyn Future<Output = (Result<(U256, U256, Bytes), Err>, Address)> + Send>>;
 type StorageFuture<Err> = Pin<Box<dyn Future<Output = (Result<U256, Err>, Address, U256)> + Send>>;
 type BlockHashFuture<Err> = Pin<Box<dyn Future<Output = (Result<H256, Err>, u64)> + Send>>;
+type TransactionFuture<Err> =
+    Pin<Box<dyn Future<Output = (Result<Option<Transaction>, Err>, H256)> + Send>>;
 
 /// Request variants that are executed by the provider
 enum ProviderRequest<Err> {
     Account(AccountFuture<Err>),
     Storage(StorageFuture<Err>),
     BlockHash(BlockHashFuture<Err>),
+    Transaction(TransactionFuture<Err>),
 }
 
 /// The Request type the Backend listens for
@@ -45,6 +48,8 @@ enum BackendRequest {
     Storage(Address, U256, OneshotSender<U256>),
     /// Fetch a block hash
     BlockHash(u64, OneshotSender<H256>),
+    /// Fetch a transaction, if it exists
+    Transaction(H256, OneshotSender<eyre::Result<Option<Transaction>>>),
     /// Sets the pinned block to fetch data from
     SetPinnedBlock(BlockId),
 }
@@ -66,6 +71,8 @@ pub struct BackendHandler<M: Middleware> {
     storage_requests: HashMap<(Address, U256), Vec<OneshotSender<U256>>>,
     /// Listeners that wait for a `get_block` response
     block_requests: HashMap<u64, Vec<OneshotSender<H256>>>,
+    /// Listeners that wait for a `get_transaction` response
+    transaction_requests: HashMap<H256, Vec<OneshotSender<eyre::Result<Option<Transaction>>>>>,
     /// Incoming commands.
     incoming: Receiver<BackendRequest>,
     /// unprocessed queued requests
@@ -92,6 +99,7 @@ where
             account_requests: Default::default(),
             storage_requests: Default::default(),
             block_requests: Default::default(),
+            transaction_requests: Default::default(),
             queued_requests: Default::default(),
             incoming: rx,
             block_id,
@@ -134,6 +142,10 @@ where
                     self.request_account_storage(addr, idx, sender);
                 }
             }
+            BackendRequest::Transaction(hash, sender) => {
+                // transactions are not cached, they are only fetched to be replayed
+                self.request_transaction(hash, sender);
+            }
             BackendRequest::SetPinnedBlock(block_id) => {
                 self.block_id = Some(block_id);
             }
@@ -222,6 +234,29 @@ where
             }
         }
     }
+
+    /// process a request for a transaction
+    fn request_transaction(
+        &mut self,
+        hash: H256,
+        listener: OneshotSender<eyre::Result<Option<Transaction>>>,
+    ) {
+        match self.transaction_requests.entry(hash) {
+            Entry::Occupied(mut entry) => {
+                entry.get_mut().push(listener);
+            }
+            Entry::Vacant(entry) => {
+                trace!(target: "backendhandler", "preparing transaction request, hash={:?}", hash);
+                entry.insert(vec![listener]);
+                let provider = self.provider.clone();
+                let fut = Box::pin(async move {
+                    let tx = provider.get_transaction(hash).await.map_err(|err| eyre::eyre!(err));
+                    (tx, hash)
+                });
+                self.pending_requests.push(ProviderRequest::Transaction(fut));
+            }
+        }
+    }
 }
 
 impl<M> Future for BackendHandler<M>
@@ -263,16 +298,8 @@ where
                                 panic!("Failed to get account for {}\n{}", addr, report);
                             });
 
-                            // convert it to revm-style types
-                            let (code, code_hash) = if !code.0.is_empty() {
-                                (Some(code.0.clone()), keccak256(&code).into())
-                            } else {
-                                (Some(bytes::Bytes::default()), KECCAK_EMPTY)
-                            };
-
                             // update the cache
-                            let acc =
-                                AccountInfo { nonce: nonce.as_u64(), balance, code, code_hash };
+                            let acc = account_info(balance, nonce, code);
                             pin.db.accounts().write().insert(addr, acc.clone());
 
                             // notify all listeners
@@ -320,6 +347,21 @@ where
                             continue
                         }
                     }
+                    ProviderRequest::Transaction(fut) => {
+                        if let Poll::Ready((tx, hash)) = fut.poll_unpin(cx) {
+                            // unlike missing state, a missing transaction is not fatal, so the
+                            // error is forwarded to the listeners
+                            let tx = tx.map_err(|err| err.to_string());
+
+                            // notify all listeners
+                            if let Some(listeners) = pin.transaction_requests.remove(&hash) {
+                                listeners.into_iter().for_each(|l| {
+                                    let _ = l.send(tx.clone().map_err(|err| eyre::eyre!(err)));
+                                })
+                            }
+                            continue
+                        }
+                    }
                 }
                 // not ready, insert and poll again
                 pin.pending_requests.push(request);
@@ -334,6 +376,16 @@ where
     }
 }
 
+/// Converts the fetched account data to the revm-style [AccountInfo]
+pub(crate) fn account_info(balance: U256, nonce: U256, code: Bytes) -> AccountInfo {
+    let (code, code_hash) = if !code.0.is_empty() {
+        (Some(code.0.clone()), keccak256(&code).into())
+    } else {
+        (Some(bytes::Bytes::default()), KECCAK_EMPTY)
+    };
+    AccountInfo { nonce: nonce.as_u64(), balance, code, code_hash }
+}
+
 /// A cloneable backend type that shares access to the backend data with all its clones.
 ///
 /// This backend type is connected to the `BackendHandler` via a mpsc channel. The `BackendHandler`
@@ -472,10 +524,25 @@ impl SharedBackend {
         })
     }
 
+    /// Returns the transaction with the given hash, if it exists
+    pub fn get_transaction(&self, hash: H256) -> eyre::Result<Option<Transaction>> {
+        tokio::task::block_in_place(|| {
+            let (sender, rx) = oneshot_channel();
+            let req = BackendRequest::Transaction(hash, sender);
+            self.backend.clone().try_send(req).map_err(|e| eyre::eyre!("{:?}", e))?;
+            rx.recv()?
+        })
+    }
+
     /// Flushes the DB to disk if caching is enabled
     pub(crate) fn flush_cache(&self) {
         self.cache.0.flush();
     }
+
+    /// Returns the data fetched from the endpoint so far
+    pub(crate) fn fetched(&self) -> &MemDb {
+        self.cache.0.db()
+    }
 }
 
 impl DatabaseRef for SharedBackend {
@@ -528,7 +595,7 @@ mod tests {
 
     use crate::executor::fork::CreateFork;
     use ethers::types::Chain;
-    use foundry_config::Config;
+    use foundry_config::{Config, ResolvedRpcEndpoint};
     use std::{collections::BTreeSet, convert::TryFrom, path::PathBuf, sync::Arc};
 
     use super::*;
@@ -600,9 +667,10 @@ mod tests {
 
         let fork = CreateFork {
             enable_caching: true,
-            url: ENDPOINT.to_string(),
+            endpoint: ResolvedRpcEndpoint::new(ENDPOINT),
             env: env.clone(),
             evm_opts,
+            prefetch_dir: None,
         };
 
         let backend = Backend::spawn(Some(fork));
@@ -1,16 +1,22 @@
 //! Cache related abstraction
+//!
+//! The json cache of a block can be shared by multiple processes, e.g. `forge test` and
+//! `forge script` running at the same time. Access to it is synchronized with a lock file next to
+//! the cache, and instead of rewriting the whole cache each process appends the entries it fetched
+//! to a journal, which is merged back into the cache once it grows too large.
 use ethers::{
     types::{Address, H256, U256},
     utils::keccak256,
 };
-use parking_lot::RwLock;
+use fs2::FileExt;
+use parking_lot::{Mutex, RwLock};
 use revm::{Account, AccountInfo, DatabaseCommit, Filth, KECCAK_EMPTY};
 use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     collections::{BTreeMap, BTreeSet},
     fs,
-    io::BufWriter,
-    path::PathBuf,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 use tracing::{trace, trace_span, warn};
@@ -21,6 +27,9 @@ use crate::HashMap as Map;
 
 pub type StorageInfo = BTreeMap<U256, U256>;
 
+/// The size of the journal in bytes after which it's merged into the cache file
+const MAX_JOURNAL_SIZE: u64 = 8 * 1024 * 1024;
+
 /// A shareable Block database
 #[derive(Clone, Debug)]
 pub struct BlockchainDb {
@@ -182,6 +191,29 @@ impl MemDb {
         self.block_hashes.write().clear();
     }
 
+    /// Inserts the entries of `other` this db doesn't have yet
+    pub fn merge(&self, other: &MemDb) {
+        let mut accounts = self.accounts.write();
+        for (address, info) in other.accounts.read().iter() {
+            accounts.entry(*address).or_insert_with(|| info.clone());
+        }
+        drop(accounts);
+
+        let mut storage = self.storage.write();
+        for (address, slots) in other.storage.read().iter() {
+            let account_storage = storage.entry(*address).or_default();
+            for (slot, value) in slots {
+                account_storage.entry(*slot).or_insert(*value);
+            }
+        }
+        drop(storage);
+
+        let mut block_hashes = self.block_hashes.write();
+        for (number, hash) in other.block_hashes.read().iter() {
+            block_hashes.entry(*number).or_insert(*hash);
+        }
+    }
+
     // Inserts the account, replacing it if it exists already
     pub fn do_insert_account(&self, address: Address, account: AccountInfo) {
         self.accounts.write().insert(address, account);
@@ -246,6 +278,11 @@ impl DatabaseCommit for MemDb {
 }
 
 /// A [BlockCacheDB] that stores the cached content in a json file
+///
+/// Next to the json file `<name>.json` of the cache, the cache uses
+///   - `<name>.lock`, which is locked shared while reading and exclusively while writing the cache
+///   - `<name>.journal`, which holds the entries that were flushed since the json file was last
+///     written, one json object per line
 #[derive(Debug)]
 pub struct JsonBlockCacheDB {
     /// Where this cache file is stored.
@@ -254,16 +291,25 @@ pub struct JsonBlockCacheDB {
     cache_path: Option<PathBuf>,
     /// Object that's stored in a json file
     data: JsonBlockCacheData,
+    /// The entries of `data` that are already stored on disk
+    flushed: Mutex<FlushedEntries>,
 }
 
 impl JsonBlockCacheDB {
     /// Creates a new instance.
     fn new(meta: Arc<RwLock<BlockchainDbMeta>>, cache_path: Option<PathBuf>) -> Self {
-        Self { cache_path, data: JsonBlockCacheData { meta, data: Arc::new(Default::default()) } }
+        Self {
+            cache_path,
+            data: JsonBlockCacheData { meta, data: Arc::new(Default::default()) },
+            // nothing on disk belongs to this cache yet, so the first flush has to rewrite it
+            flushed: Mutex::new(FlushedEntries { rewrite: true, ..Default::default() }),
+        }
     }
 
     /// Loads the contents of the diskmap file and returns the read object
     ///
+    /// The entries of the journal are applied to the loaded data.
+    ///
     /// # Errors
     /// This will fail if
     ///   - the `path` does not exist
@@ -273,10 +319,10 @@ impl JsonBlockCacheDB {
         trace!(target: "cache", "reading json cache path={:?}", path);
         let span = trace_span!("cache", "path={:?}", &path);
         let _enter = span.enter();
-        let file = std::fs::File::open(&path).in_current_span()?;
-        let file = std::io::BufReader::new(file);
-        let data = serde_json::from_reader(file).in_current_span()?;
-        Ok(Self { cache_path: Some(path), data })
+        let _lock = lock(&path, false).in_current_span()?;
+        let data = read(&path).in_current_span()?;
+        let flushed = Mutex::new(FlushedEntries::new(&data.data));
+        Ok(Self { cache_path: Some(path), data, flushed })
     }
 
     /// Returns the [MemDb] it holds access to
@@ -295,22 +341,208 @@ impl JsonBlockCacheDB {
     }
 
     /// Flushes the DB to disk if caching is enabled
+    ///
+    /// The entries that were not flushed yet are appended to the journal. The json file is only
+    /// rewritten if it belongs to a different [BlockchainDbMeta], or if the journal grew too large,
+    /// in which case the entries other processes flushed in the meantime are merged in first.
     pub fn flush(&self) {
-        // writes the data to a json file
         if let Some(ref path) = self.cache_path {
             trace!(target: "cache", "saving json cache path={:?}", path);
             if let Some(parent) = path.parent() {
                 let _ = fs::create_dir_all(parent);
             }
-            let _ = fs::File::create(path)
-                .map_err(|e| warn!(target: "cache", "Failed to open json cache for writing: {}", e))
-                .and_then(|f| {
-                    serde_json::to_writer(BufWriter::new(f), &self.data)
-                        .map_err(|e| warn!(target: "cache" ,"Failed to write to json cache: {}", e))
-                });
+            let _lock = match lock(path, true) {
+                Ok(lock) => lock,
+                Err(err) => {
+                    warn!(target: "cache", "Failed to lock json cache: {}", err);
+                    return
+                }
+            };
+
+            let mut flushed = self.flushed.lock();
+            let journal = journal_path(path);
+            let journal_size = fs::metadata(&journal).map(|m| m.len()).unwrap_or_default();
+            let res = if flushed.rewrite || journal_size > MAX_JOURNAL_SIZE {
+                self.rewrite(path, &journal, &mut flushed)
+            } else {
+                self.append(&journal, &mut flushed)
+            };
+            if let Err(err) = res {
+                warn!(target: "cache", "Failed to write to json cache: {}", err);
+            }
             trace!(target: "cache", "saved json cache path={:?}", path);
         }
     }
+
+    /// Appends the entries that are not flushed yet to the journal
+    ///
+    /// The lock of the cache must be held exclusively.
+    fn append(&self, journal: &Path, flushed: &mut FlushedEntries) -> eyre::Result<()> {
+        let entry = flushed.unflushed(self.db());
+        if entry.is_empty() {
+            return Ok(())
+        }
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        fs::OpenOptions::new().create(true).append(true).open(journal)?.write_all(&line)?;
+        flushed.extend(&entry);
+        Ok(())
+    }
+
+    /// Merges the entries on disk into the data and replaces the json file and the journal with it
+    ///
+    /// The lock of the cache must be held exclusively.
+    fn rewrite(
+        &self,
+        path: &Path,
+        journal: &Path,
+        flushed: &mut FlushedEntries,
+    ) -> eyre::Result<()> {
+        if let Ok(on_disk) = read(path) {
+            if *on_disk.meta.read() == *self.meta().read() {
+                self.db().merge(&on_disk.data);
+            }
+        }
+
+        // the db is still being written to while it's flushed, so a snapshot of it is written
+        let data =
+            JsonBlockCacheData { meta: Arc::clone(self.meta()), data: Arc::new(self.db().clone()) };
+        // write to a temporary file first, so the cache is never left partially written
+        let tmp = path.with_extension("tmp");
+        serde_json::to_writer(BufWriter::new(fs::File::create(&tmp)?), &data)?;
+        fs::rename(&tmp, path)?;
+        if journal.exists() {
+            fs::remove_file(journal)?;
+        }
+        *flushed = FlushedEntries::new(&data.data);
+        Ok(())
+    }
+}
+
+/// Returns the path of the journal of the cache at `path`
+fn journal_path(path: &Path) -> PathBuf {
+    path.with_extension("journal")
+}
+
+/// Locks the lock file of the cache at `path`, exclusively if `exclusive` is set
+///
+/// The lock is released once the returned file is dropped.
+fn lock(path: &Path, exclusive: bool) -> std::io::Result<fs::File> {
+    let file = fs::OpenOptions::new().create(true).write(true).open(path.with_extension("lock"))?;
+    if exclusive {
+        file.lock_exclusive()?;
+    } else {
+        file.lock_shared()?;
+    }
+    Ok(file)
+}
+
+/// Reads the json file of the cache at `path` and applies its journal
+///
+/// Lines of the journal that can't be parsed, e.g. because the process writing them was killed,
+/// are skipped.
+fn read(path: &Path) -> eyre::Result<JsonBlockCacheData> {
+    let file = BufReader::new(fs::File::open(path)?);
+    let data: JsonBlockCacheData = serde_json::from_reader(file)?;
+    if let Ok(journal) = fs::File::open(journal_path(path)) {
+        for line in BufReader::new(journal).lines() {
+            match serde_json::from_str::<JournalEntry>(&line?) {
+                Ok(entry) => entry.apply(&data.data),
+                Err(err) => warn!(target: "cache", "Skipping malformed journal entry: {}", err),
+            }
+        }
+    }
+    Ok(data)
+}
+
+/// The entries of a single flush, as stored in the journal
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct JournalEntry {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    accounts: BTreeMap<Address, AccountInfo>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    storage: BTreeMap<Address, StorageInfo>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    block_hashes: BTreeMap<u64, H256>,
+}
+
+impl JournalEntry {
+    fn is_empty(&self) -> bool {
+        self.accounts.is_empty() && self.storage.is_empty() && self.block_hashes.is_empty()
+    }
+
+    /// Inserts the entries into the `db`
+    fn apply(self, db: &MemDb) {
+        db.accounts.write().extend(self.accounts);
+        let mut storage = db.storage.write();
+        for (address, slots) in self.storage {
+            storage.entry(address).or_default().extend(slots);
+        }
+        drop(storage);
+        db.block_hashes.write().extend(self.block_hashes);
+    }
+}
+
+/// Keeps track of the entries of a [JsonBlockCacheDB] that are already stored on disk
+#[derive(Debug, Default)]
+struct FlushedEntries {
+    /// Whether the json file needs to be rewritten on the next flush
+    rewrite: bool,
+    accounts: BTreeSet<Address>,
+    storage: BTreeSet<(Address, U256)>,
+    block_hashes: BTreeSet<u64>,
+}
+
+impl FlushedEntries {
+    /// Marks all entries of the `db` as flushed
+    fn new(db: &MemDb) -> Self {
+        Self {
+            rewrite: false,
+            accounts: db.accounts.read().keys().copied().collect(),
+            storage: db
+                .storage
+                .read()
+                .iter()
+                .flat_map(|(address, slots)| slots.keys().map(move |slot| (*address, *slot)))
+                .collect(),
+            block_hashes: db.block_hashes.read().keys().copied().collect(),
+        }
+    }
+
+    /// Marks the entries of the journal `entry` as flushed
+    fn extend(&mut self, entry: &JournalEntry) {
+        self.accounts.extend(entry.accounts.keys().copied());
+        self.storage.extend(
+            entry
+                .storage
+                .iter()
+                .flat_map(|(address, slots)| slots.keys().map(move |slot| (*address, *slot))),
+        );
+        self.block_hashes.extend(entry.block_hashes.keys().copied());
+    }
+
+    /// Returns the entries of the `db` that are not flushed yet
+    fn unflushed(&self, db: &MemDb) -> JournalEntry {
+        let mut entry = JournalEntry::default();
+        for (address, info) in db.accounts.read().iter() {
+            if !self.accounts.contains(address) {
+                entry.accounts.insert(*address, info.clone());
+            }
+        }
+        for (address, slots) in db.storage.read().iter() {
+            for (slot, value) in slots {
+                if !self.storage.contains(&(*address, *slot)) {
+                    entry.storage.entry(*address).or_default().insert(*slot, *value);
+                }
+            }
+        }
+        for (number, hash) in db.block_hashes.read().iter() {
+            if !self.block_hashes.contains(number) {
+                entry.block_hashes.insert(*number, *hash);
+            }
+        }
+        entry
+    }
 }
 
 /// The Data the [JsonBlockCacheDB] can read and flush
@@ -390,3 +622,38 @@ impl Drop for FlushJsonBlockCacheDB {
         trace!(target: "fork::cache", "flushed cache");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_share_cache_between_processes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("storage.json");
+        let meta = BlockchainDbMeta::new(Default::default(), "http://localhost:8545".to_string());
+
+        // two processes that start without a cache
+        let first = BlockchainDb::new(meta.clone(), Some(path.clone()));
+        let second = BlockchainDb::new(meta.clone(), Some(path.clone()));
+        let (a, b, c) = (Address::random(), Address::random(), Address::random());
+        first.accounts().write().insert(a, AccountInfo::default());
+        second.accounts().write().insert(b, AccountInfo::default());
+        second.storage().write().entry(b).or_default().insert(1u64.into(), 2u64.into());
+        first.cache().flush();
+        second.cache().flush();
+
+        // a process that starts with the cache only appends what it fetched to the journal
+        let third = BlockchainDb::new(meta, Some(path.clone()));
+        assert!(third.accounts().read().contains_key(&a));
+        assert!(third.accounts().read().contains_key(&b));
+        third.accounts().write().insert(c, AccountInfo::default());
+        third.cache().flush();
+        assert!(journal_path(&path).exists());
+
+        let cache = JsonBlockCacheDB::load(&path).unwrap();
+        let accounts = cache.db().accounts.read();
+        assert_eq!(accounts.keys().copied().collect::<BTreeSet<_>>(), BTreeSet::from([a, b, c]));
+        assert_eq!(cache.db().storage.read()[&b][&U256::from(1u64)], U256::from(2u64));
+    }
+}
@@ -1,8 +1,7 @@
 use ethers::{
     providers::Middleware,
-    types::{Address, U256},
+    types::{Address, BlockNumber, U256},
 };
-use eyre::WrapErr;
 use futures::TryFutureExt;
 use revm::{BlockEnv, CfgEnv, Env, TxEnv};
 
@@ -13,17 +12,12 @@ pub async fn environment<M: Middleware>(
     memory_limit: u64,
     gas_price: Option<u64>,
     override_chain_id: Option<u64>,
-    pin_block: Option<u64>,
+    block_number: BlockNumber,
     origin: Address,
 ) -> eyre::Result<Env>
 where
     M::Error: 'static,
 {
-    let block_number = if let Some(pin_block) = pin_block {
-        pin_block
-    } else {
-        provider.get_block_number().await.wrap_err("Failed to get latest block number")?.as_u64()
-    };
     let (fork_gas_price, rpc_chain_id, block) = tokio::try_join!(
         provider
             .get_gas_price()
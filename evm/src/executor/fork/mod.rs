@@ -3,7 +3,9 @@ mod backend;
 use super::opts::EvmOpts;
 pub use backend::{BackendHandler, SharedBackend};
 
+use foundry_config::ResolvedRpcEndpoint;
 use revm::Env;
+use std::path::PathBuf;
 
 mod init;
 pub use init::environment;
@@ -13,6 +15,8 @@ pub use cache::{BlockchainDb, BlockchainDbMeta, JsonBlockCacheDB, MemDb};
 
 pub mod database;
 
+pub mod prefetch;
+
 mod multi;
 pub use multi::{ForkId, MultiFork, MultiForkHandler};
 
@@ -21,10 +25,24 @@ pub use multi::{ForkId, MultiFork, MultiForkHandler};
 pub struct CreateFork {
     /// Whether to enable rpc storage caching for this fork
     pub enable_caching: bool,
-    /// The URL to a node for fetching remote state
-    pub url: String,
+    /// The endpoint of a node for fetching remote state
+    pub endpoint: ResolvedRpcEndpoint,
     /// The env to create this fork, main purpose is to provide some metadata for the fork
     pub env: Env,
     /// All env settings as configured by the user
     pub evm_opts: EvmOpts,
+    /// The directory of the recorded access lists, one per chain, if prefetching is enabled
+    ///
+    /// The accounts and slots recorded for the chain of the fork are fetched in parallel when the
+    /// fork is created, and the ones fetched while the fork is in use are recorded on shutdown.
+    pub prefetch_dir: Option<PathBuf>,
+}
+
+impl CreateFork {
+    /// Returns the path of the recorded access list of the fork's chain, see [Self::prefetch_dir]
+    pub fn prefetch_path(&self) -> Option<PathBuf> {
+        self.prefetch_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.json", self.env.cfg.chain_id.as_u64())))
+    }
 }
@@ -4,13 +4,14 @@
 //! concurrently active pairs at once.
 
 use crate::executor::fork::{
-    BackendHandler, BlockchainDb, BlockchainDbMeta, CreateFork, SharedBackend,
+    prefetch, BackendHandler, BlockchainDb, BlockchainDbMeta, CreateFork, SharedBackend,
 };
 use ethers::{
     abi::{AbiDecode, AbiEncode, AbiError},
-    providers::{Http, Provider, RetryClient},
+    providers::Provider,
     types::{BlockId, BlockNumber},
 };
+use foundry_common::provider::{RetryProvider, RpcClient};
 use foundry_config::Config;
 use futures::{
     channel::mpsc::{channel, Receiver, Sender},
@@ -29,7 +30,7 @@ use std::{
     },
     time::Duration,
 };
-use tracing::trace;
+use tracing::{trace, warn};
 
 /// The identifier for a specific fork, this could be the name of the network a custom descriptive
 /// name.
@@ -148,7 +149,7 @@ impl MultiFork {
     }
 }
 
-type Handler = BackendHandler<Arc<Provider<RetryClient<Http>>>>;
+type Handler = BackendHandler<Arc<RetryProvider>>;
 
 type CreateFuture = Pin<Box<dyn Future<Output = eyre::Result<(CreatedFork, Handler)>> + Send>>;
 type CreateSender = OneshotSender<eyre::Result<(ForkId, SharedBackend)>>;
@@ -225,7 +226,7 @@ impl MultiForkHandler {
     }
 
     fn create_fork(&mut self, fork: CreateFork, sender: CreateSender) {
-        let fork_id = create_fork_id(&fork.url, fork.evm_opts.fork_block_number);
+        let fork_id = create_fork_id(&fork.endpoint.url, fork.evm_opts.fork_block());
         if let Some(fork) = self.forks.get_mut(&fork_id) {
             fork.num_senders += 1;
             let _ = sender.send(Ok((fork_id, fork.backend.clone())));
@@ -260,6 +261,16 @@ impl MultiForkHandler {
             }
             Request::ShutDown(sender) => {
                 trace!(target: "fork::multi", "received shutdown signal");
+                // record the state the forks accessed, so the next run can prefetch it
+                for fork in self.forks.values() {
+                    if let Some(path) = fork.opts.prefetch_path() {
+                        if let Err(err) =
+                            prefetch::record_access_list(&path, fork.backend.fetched())
+                        {
+                            warn!(target: "fork::multi", ?path, %err, "failed to record access list");
+                        }
+                    }
+                }
                 // we're emptying all fork backends, this way we ensure all caches get flushed
                 self.forks.clear();
                 self.handlers.clear();
@@ -398,10 +409,9 @@ impl Drop for ShutDownMultiFork {
     }
 }
 
-/// Returns  the identifier for a Fork which consists of the url and the block number
-fn create_fork_id(url: &str, num: Option<u64>) -> ForkId {
-    let num = num.map(|num| BlockNumber::Number(num.into())).unwrap_or(BlockNumber::Latest);
-    ForkId(format!("{url}@{num}"))
+/// Returns  the identifier for a Fork which consists of the url and the block number or tag
+fn create_fork_id(url: &str, block: BlockNumber) -> ForkId {
+    ForkId(format!("{url}@{block}"))
 }
 
 /// Creates a new fork
@@ -412,28 +422,41 @@ async fn create_fork(
     retries: u32,
     backoff: u64,
 ) -> eyre::Result<(CreatedFork, Handler)> {
-    let provider = Arc::new(Provider::<RetryClient<Http>>::new_client(
-        fork.url.clone().as_str(),
-        retries,
-        backoff,
-    )?);
+    // the endpoint's own rate limit, retries and fallback urls take precedence
+    let provider = Arc::new(Provider::new(RpcClient::new(&fork.endpoint, retries, backoff)?));
 
     // initialise the fork environment
-    fork.env = fork.evm_opts.fork_evm_env(&fork.url).await?;
+    fork.env = fork.evm_opts.fork_evm_env(&fork.endpoint.url).await?;
 
-    let meta = BlockchainDbMeta::new(fork.env.clone(), fork.url.clone());
+    let meta = BlockchainDbMeta::new(fork.env.clone(), fork.endpoint.url.clone());
     let number = meta.block_env.number.as_u64();
+    // the pending block changes with every new transaction, so its state is neither cached nor
+    // pinned to its number
+    let pending = matches!(fork.evm_opts.fork_block(), BlockNumber::Pending);
 
     // determine the cache path if caching is enabled
-    let cache_path = if fork.enable_caching {
-        Config::foundry_block_cache_dir(meta.cfg_env.chain_id.as_u64(), number)
+    let cache_path = if fork.enable_caching && !pending {
+        Config::foundry_block_cache_file(meta.cfg_env.chain_id.as_u64(), number)
     } else {
         None
     };
 
     let db = BlockchainDb::new(meta, cache_path);
-    let (backend, handler) =
-        SharedBackend::new(provider, db, Some(BlockId::Number(BlockNumber::Number(number.into()))));
+    let block = if pending {
+        BlockId::Number(BlockNumber::Pending)
+    } else {
+        BlockId::Number(BlockNumber::Number(number.into()))
+    };
+
+    // fetch the state previous runs accessed on this chain ahead of time
+    if let Some(path) = fork.prefetch_path().filter(|path| path.exists()) {
+        match prefetch::read_access_list(&path) {
+            Ok(list) => prefetch::prefetch(provider.clone(), &db, &list, block).await,
+            Err(err) => warn!(target: "fork::multi", ?path, %err, "failed to read access list"),
+        }
+    }
+
+    let (backend, handler) = SharedBackend::new(provider, db, Some(block));
     let fork = CreatedFork::new(fork, backend);
     Ok((fork, handler))
 }
@@ -0,0 +1,171 @@
+//! Prefetching of fork state
+//!
+//! Executing against a fork fetches every missing account and storage slot with its own request,
+//! one after the other, so a test pays the latency of the endpoint for each of them. Since the
+//! same tests touch mostly the same state on every run, the accounts and slots fetched by a run
+//! are recorded as an access list, and fetched in parallel when the next run creates the fork.
+
+use crate::executor::fork::{backend::account_info, BlockchainDb, MemDb};
+use ethers::{
+    core::abi::ethereum_types::BigEndianHash,
+    providers::Middleware,
+    types::{
+        transaction::eip2930::{AccessList, AccessListItem},
+        BlockId, H256,
+    },
+};
+use futures::StreamExt;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+use tracing::{trace, warn};
+
+/// How many requests are in flight at once while prefetching
+const PREFETCH_CONCURRENCY: usize = 32;
+
+/// Reads the access list at `path`
+///
+/// The file holds a JSON encoded [EIP-2930](https://eips.ethereum.org/EIPS/eip-2930) access list,
+/// so besides the lists recorded by previous runs, a list returned by `eth_createAccessList` can be
+/// used as well.
+pub fn read_access_list(path: impl AsRef<Path>) -> eyre::Result<AccessList> {
+    let list = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&list)?)
+}
+
+/// Merges the accounts and slots fetched into the `db` into the access list at `path`
+pub fn record_access_list(path: impl AsRef<Path>, db: &MemDb) -> eyre::Result<()> {
+    let path = path.as_ref();
+    let mut accessed = BTreeMap::<_, BTreeSet<_>>::new();
+    if path.exists() {
+        for item in read_access_list(path)?.0 {
+            accessed.entry(item.address).or_default().extend(item.storage_keys);
+        }
+    }
+    for address in db.accounts.read().keys() {
+        accessed.entry(*address).or_default();
+    }
+    for (address, storage) in db.storage.read().iter() {
+        accessed.entry(*address).or_default().extend(storage.keys().map(H256::from_uint));
+    }
+
+    let list = AccessList(
+        accessed
+            .into_iter()
+            .map(|(address, slots)| AccessListItem {
+                address,
+                storage_keys: slots.into_iter().collect(),
+            })
+            .collect(),
+    );
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string(&list)?)?;
+    Ok(())
+}
+
+/// Fetches the accounts and slots of the access list that are not in the `db` yet, in parallel
+///
+/// Prefetching is best effort: entries that fail to be fetched are left to be fetched again once
+/// they're accessed.
+pub async fn prefetch<M>(provider: M, db: &BlockchainDb, list: &AccessList, block: BlockId)
+where
+    M: Middleware + Clone,
+{
+    let accounts = list
+        .0
+        .iter()
+        .map(|item| item.address)
+        .filter(|address| !db.accounts().read().contains_key(address))
+        .collect::<Vec<_>>();
+    let slots = list
+        .0
+        .iter()
+        .flat_map(|item| item.storage_keys.iter().map(|slot| (item.address, slot.into_uint())))
+        .filter(|(address, slot)| {
+            db.storage().read().get(address).map_or(true, |storage| !storage.contains_key(slot))
+        })
+        .collect::<Vec<_>>();
+    trace!(target: "fork::prefetch", accounts = accounts.len(), slots = slots.len(), "prefetching");
+
+    let mut fetch_accounts = futures::stream::iter(accounts.into_iter().map(|address| {
+        let provider = provider.clone();
+        async move {
+            let balance = provider.get_balance(address, Some(block));
+            let nonce = provider.get_transaction_count(address, Some(block));
+            let code = provider.get_code(address, Some(block));
+            (address, futures::try_join!(balance, nonce, code))
+        }
+    }))
+    .buffer_unordered(PREFETCH_CONCURRENCY);
+    while let Some((address, resp)) = fetch_accounts.next().await {
+        match resp {
+            Ok((balance, nonce, code)) => {
+                db.accounts().write().insert(address, account_info(balance, nonce, code));
+            }
+            Err(err) => warn!(target: "fork::prefetch", ?address, %err, "failed to prefetch"),
+        }
+    }
+
+    let mut fetch_slots = futures::stream::iter(slots.into_iter().map(|(address, slot)| {
+        let provider = provider.clone();
+        async move {
+            let value = provider.get_storage_at(address, H256::from_uint(&slot), Some(block)).await;
+            (address, slot, value)
+        }
+    }))
+    .buffer_unordered(PREFETCH_CONCURRENCY);
+    while let Some((address, slot, value)) = fetch_slots.next().await {
+        match value {
+            Ok(value) => {
+                db.storage().write().entry(address).or_default().insert(slot, value.into_uint());
+            }
+            Err(err) => {
+                warn!(target: "fork::prefetch", ?address, %slot, %err, "failed to prefetch")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Address, U256};
+    use revm::AccountInfo;
+
+    #[test]
+    fn can_record_access_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1.json");
+
+        let first = Address::random();
+        let second = Address::random();
+
+        let db = MemDb::default();
+        db.accounts.write().insert(first, AccountInfo::default());
+        db.storage.write().entry(first).or_default().insert(U256::from(1u64), U256::from(2u64));
+        record_access_list(&path, &db).unwrap();
+
+        // recording again merges with the recorded list
+        let db = MemDb::default();
+        db.storage.write().entry(second).or_default().insert(U256::from(3u64), U256::zero());
+        db.storage.write().entry(first).or_default().insert(U256::from(4u64), U256::zero());
+        record_access_list(&path, &db).unwrap();
+
+        let list = read_access_list(&path).unwrap();
+        let accessed = list
+            .0
+            .into_iter()
+            .map(|item| (item.address, item.storage_keys))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            accessed,
+            BTreeMap::from([
+                (first, vec![H256::from_low_u64_be(1), H256::from_low_u64_be(4)]),
+                (second, vec![H256::from_low_u64_be(3)]),
+            ])
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use ethers::types::{Address, Bytes, U256};
+use foundry_common::fs;
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// The accounts of a genesis-style state, keyed by their address
+pub type GenesisAlloc = BTreeMap<Address, GenesisAccount>;
+
+/// An account of a [GenesisAlloc].
+///
+/// Numeric values are accepted both as hex and decimal, so allocs of `genesis.json` files and of
+/// states dumped by anvil can be used.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisAccount {
+    #[serde(default, deserialize_with = "deserialize_quantity")]
+    pub balance: U256,
+    #[serde(default, deserialize_with = "deserialize_nonce")]
+    pub nonce: u64,
+    #[serde(default)]
+    pub code: Bytes,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// Parses the accounts of a JSON state, which is either
+///   - a plain alloc, mapping addresses to accounts
+///   - a genesis file, containing the alloc in `alloc`
+///   - a state dumped by anvil, containing the alloc in `accounts`
+pub fn parse_allocs(s: &str) -> eyre::Result<GenesisAlloc> {
+    let mut value: serde_json::Value = serde_json::from_str(s)?;
+    let alloc = match value.get_mut("alloc").or_else(|| value.get_mut("accounts")) {
+        Some(alloc) => alloc.take(),
+        None => value,
+    };
+    Ok(serde_json::from_value(alloc)?)
+}
+
+/// Writes the accounts to `path` in the state format of anvil, which can be loaded with
+/// `anvil --load-state` and [parse_allocs]
+pub fn write_state(path: impl AsRef<Path>, allocs: &GenesisAlloc) -> eyre::Result<()> {
+    let state = serde_json::json!({ "accounts": allocs });
+    fs::write(path, serde_json::to_string_pretty(&state)?)?;
+    Ok(())
+}
+
+/// A number that is either a JSON number or a hex or decimal string
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Quantity {
+    Number(u64),
+    String(String),
+}
+
+impl Quantity {
+    fn into_u256<E: Error>(self) -> Result<U256, E> {
+        match self {
+            Quantity::Number(num) => Ok(num.into()),
+            Quantity::String(s) => match s.strip_prefix("0x") {
+                Some(hex) => U256::from_str_radix(hex, 16).map_err(E::custom),
+                None => U256::from_dec_str(&s).map_err(E::custom),
+            },
+        }
+    }
+}
+
+fn deserialize_quantity<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    Quantity::deserialize(deserializer)?.into_u256()
+}
+
+fn deserialize_nonce<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let nonce = deserialize_quantity(deserializer)?;
+    if nonce > u64::MAX.into() {
+        return Err(D::Error::custom("nonce exceeds u64"))
+    }
+    Ok(nonce.as_u64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_allocs() {
+        let address: Address = "0x7df9a875a174b3bc565e6424a0050ebf203d8f4c".parse().unwrap();
+
+        let genesis = r#"{
+            "config": { "chainId": 1 },
+            "alloc": {
+                "7df9a875a174b3bc565e6424a0050ebf203d8f4c": {
+                    "balance": "1000",
+                    "nonce": "0x2",
+                    "code": "0x602a60005260206000f3",
+                    "storage": { "0x01": "0x02" }
+                }
+            }
+        }"#;
+        let allocs = parse_allocs(genesis).unwrap();
+        let account = &allocs[&address];
+        assert_eq!(account.balance, U256::from(1000));
+        assert_eq!(account.nonce, 2);
+        assert_eq!(account.code.len(), 10);
+        assert_eq!(account.storage[&U256::one()], U256::from(2));
+
+        let state = r#"{
+            "accounts": {
+                "0x7df9a875a174b3bc565e6424a0050ebf203d8f4c": { "balance": "0x3e8", "nonce": 2 }
+            }
+        }"#;
+        let allocs = parse_allocs(state).unwrap();
+        assert_eq!(allocs[&address].balance, U256::from(1000));
+        assert_eq!(allocs[&address].nonce, 2);
+        assert!(allocs[&address].code.is_empty());
+
+        let alloc = r#"{ "0x7df9a875a174b3bc565e6424a0050ebf203d8f4c": { "balance": 5 } }"#;
+        assert_eq!(parse_allocs(alloc).unwrap()[&address].balance, U256::from(5));
+    }
+
+    #[test]
+    fn can_write_state() {
+        let account = GenesisAccount {
+            balance: 1000.into(),
+            nonce: 2,
+            code: vec![0x60, 0x2a].into(),
+            storage: BTreeMap::from([(1.into(), 2.into())]),
+        };
+        let allocs = GenesisAlloc::from([(Address::random(), account)]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        write_state(&path, &allocs).unwrap();
+        assert_eq!(parse_allocs(&fs::read_to_string(&path).unwrap()).unwrap(), allocs);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::utils::u256_to_h256_be;
+use ethers::types::{
+    transaction::eip2930::{AccessList, AccessListItem},
+    Address, H256, U256,
+};
+use revm::{opcode, Database, EVMData, Inspector, Interpreter, Return};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// An inspector that collects the accounts and storage slots accessed by a transaction, to build
+/// its [EIP-2930](https://eips.ethereum.org/EIPS/eip-2930) access list.
+#[derive(Default, Debug)]
+pub struct AccessListTracer {
+    /// The accessed accounts and their accessed storage slots
+    pub accessed: BTreeMap<Address, BTreeSet<H256>>,
+}
+
+impl AccessListTracer {
+    /// Returns the access list of the accessed accounts and storage slots.
+    ///
+    /// The `warm` accounts (e.g. the sender and the recipient of the transaction) and the
+    /// precompiles are accessed at no extra cost, so they are only listed with their storage
+    /// slots.
+    pub fn access_list(&self, warm: &[Address]) -> AccessList {
+        let is_precompile = |address: &Address| *address <= Address::from_low_u64_be(9);
+        AccessList(
+            self.accessed
+                .iter()
+                .filter(|(address, slots)| {
+                    !slots.is_empty() || !(warm.contains(address) || is_precompile(address))
+                })
+                .map(|(address, slots)| AccessListItem {
+                    address: *address,
+                    storage_keys: slots.iter().copied().collect(),
+                })
+                .collect(),
+        )
+    }
+
+    fn access_account(&mut self, word: U256) {
+        self.accessed.entry(Address::from(u256_to_h256_be(word))).or_default();
+    }
+}
+
+impl<DB> Inspector<DB> for AccessListTracer
+where
+    DB: Database,
+{
+    fn step(&mut self, interpreter: &mut Interpreter, _: &mut EVMData<'_, DB>, _: bool) -> Return {
+        match interpreter.contract.code[interpreter.program_counter()] {
+            opcode::SLOAD | opcode::SSTORE => {
+                let slot = try_or_continue!(interpreter.stack().peek(0));
+                self.accessed
+                    .entry(interpreter.contract().address)
+                    .or_default()
+                    .insert(u256_to_h256_be(slot));
+            }
+            opcode::BALANCE |
+            opcode::EXTCODESIZE |
+            opcode::EXTCODECOPY |
+            opcode::EXTCODEHASH |
+            opcode::SELFDESTRUCT => {
+                self.access_account(try_or_continue!(interpreter.stack().peek(0)));
+            }
+            opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL => {
+                self.access_account(try_or_continue!(interpreter.stack().peek(1)));
+            }
+            _ => (),
+        }
+
+        Return::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_warm_accounts_without_slots() {
+        let sender = Address::random();
+        let recipient = Address::random();
+        let other = Address::random();
+
+        let mut tracer = AccessListTracer::default();
+        tracer.accessed.insert(sender, BTreeSet::new());
+        tracer.accessed.insert(recipient, BTreeSet::from([H256::from_low_u64_be(1)]));
+        tracer.accessed.insert(other, BTreeSet::new());
+        tracer.accessed.insert(Address::from_low_u64_be(1), BTreeSet::new());
+
+        // the accounts are listed in ascending order
+        let mut expected = vec![
+            AccessListItem { address: recipient, storage_keys: vec![H256::from_low_u64_be(1)] },
+            AccessListItem { address: other, storage_keys: vec![] },
+        ];
+        expected.sort_by_key(|item| item.address);
+        assert_eq!(tracer.access_list(&[sender, recipient]).0, expected);
+    }
+}
@@ -1,8 +1,15 @@
 use crate::executor::opts::EvmOpts;
 use bytes::Bytes;
+use ethers::types::U256;
 
-use foundry_config::{cache::StorageCachingConfig, Config, ResolvedRpcEndpoints};
-use std::path::{Path, PathBuf};
+use foundry_config::{
+    cache::StorageCachingConfig, Config, FsAccessKind, FsPermissions, ResolvedRpcEndpoint,
+    ResolvedRpcEndpoints,
+};
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+};
 
 use super::util;
 
@@ -14,19 +21,30 @@ use super::util;
 #[derive(Debug, Clone, Default)]
 pub struct CheatsConfig {
     pub ffi: bool,
+    /// Whether the `sleep` and `waitForHttp` cheatcodes are allowed
+    pub allow_wait: bool,
     /// RPC storage caching settings determines what chains and endpoints to cache
     pub rpc_storage_caching: StorageCachingConfig,
     /// All known endpoints and their aliases
     pub rpc_endpoints: ResolvedRpcEndpoints,
+    /// The block numbers forks of the endpoint aliases are pinned to
+    pub fork_block_numbers: BTreeMap<String, u64>,
+    /// The directory of the access lists recorded for prefetching fork state, if enabled
+    pub fork_prefetch_dir: Option<PathBuf>,
 
     /// Project root
     pub root: PathBuf,
 
-    /// Paths (directories) where file reading/writing is allowed
+    /// Paths (directories) where file reading/writing is allowed, if there are no `fs_permissions`
     pub allowed_paths: Vec<PathBuf>,
+    /// The permissions of the file system cheatcodes by path
+    pub fs_permissions: FsPermissions,
 
     /// How the evm was configured by the user
     pub evm_opts: EvmOpts,
+
+    /// The seed of the `random*` cheatcodes, a random seed is used if unset
+    pub seed: Option<U256>,
 }
 
 // === impl CheatsConfig ===
@@ -37,23 +55,47 @@ impl CheatsConfig {
         let mut allowed_paths = vec![config.__root.0.clone()];
         allowed_paths.extend(config.libs.clone());
         allowed_paths.extend(config.allow_paths.clone());
+        let mut fs_permissions = config.fs_permissions.clone();
+        for permission in &mut fs_permissions.permissions {
+            permission.path = normalize_path(&config.__root.0.join(&permission.path));
+        }
 
         Self {
             ffi: evm_opts.ffi,
+            allow_wait: evm_opts.allow_wait,
             rpc_storage_caching: config.rpc_storage_caching.clone(),
             rpc_endpoints: config.rpc_endpoints.clone().resolved(),
+            fork_block_numbers: config.fork_block_numbers.clone(),
+            fork_prefetch_dir: config.fork_prefetch_dir(),
             root: config.__root.0.clone(),
             allowed_paths,
+            fs_permissions,
             evm_opts: evm_opts.clone(),
+            seed: config.fuzz_seed,
         }
     }
 
-    pub fn is_path_allowed(&self, path: impl AsRef<Path>) -> bool {
-        return self.allowed_paths.iter().any(|allowed_path| path.as_ref().starts_with(allowed_path))
+    /// Returns `true` if the file system cheatcodes may access `path` with the given kind of
+    /// access.
+    ///
+    /// Without `fs_permissions`, everything in the `allowed_paths` may be read and written.
+    /// Otherwise the permission of the most specific path that contains `path` applies.
+    pub fn is_path_allowed(&self, path: impl AsRef<Path>, kind: FsAccessKind) -> bool {
+        // `..` must not escape an allowed path
+        let path = normalize_path(path.as_ref());
+        if self.fs_permissions.is_empty() {
+            self.allowed_paths.iter().any(|allowed_path| path.starts_with(allowed_path))
+        } else {
+            self.fs_permissions.is_granted(&path, kind)
+        }
     }
 
-    pub fn ensure_path_allowed(&self, path: impl AsRef<Path>) -> Result<(), String> {
-        if !self.is_path_allowed(path) {
+    pub fn ensure_path_allowed(
+        &self,
+        path: impl AsRef<Path>,
+        kind: FsAccessKind,
+    ) -> Result<(), String> {
+        if !self.is_path_allowed(path, kind) {
             return Err("Path is not allowed.".to_string())
         }
 
@@ -72,17 +114,79 @@ impl CheatsConfig {
     ///  - Returns an error if `url_or_alias` is not an alias but does not start with a `http` or
     ///    `scheme`
     pub fn get_rpc_url(&self, url_or_alias: impl Into<String>) -> Result<String, Bytes> {
+        self.get_rpc_endpoint(url_or_alias).map(|endpoint| endpoint.url)
+    }
+
+    /// Returns the endpoint to use, see [Self::get_rpc_url()]
+    ///
+    /// A URL that belongs to an alias uses the settings of that alias.
+    pub fn get_rpc_endpoint(
+        &self,
+        url_or_alias: impl Into<String>,
+    ) -> Result<ResolvedRpcEndpoint, Bytes> {
         let url_or_alias = url_or_alias.into();
         match self.rpc_endpoints.get(&url_or_alias) {
-            Some(Ok(url)) => Ok(url.clone()),
+            Some(Ok(endpoint)) => Ok(endpoint.clone()),
             Some(Err(err)) => Err(util::encode_error(err)),
             None => {
                 if !url_or_alias.starts_with("http") && !url_or_alias.starts_with("ws") {
                     Err(util::encode_error(format!("invalid rpc url {}", url_or_alias)))
                 } else {
-                    Ok(url_or_alias)
+                    Ok(self
+                        .rpc_endpoints
+                        .values()
+                        .flatten()
+                        .find(|endpoint| endpoint.url == url_or_alias)
+                        .cloned()
+                        .unwrap_or_else(|| ResolvedRpcEndpoint::new(url_or_alias)))
                 }
             }
         }
     }
 }
+
+/// Resolves the `.` and `..` components of the `path` without accessing the file system
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundry_config::PathPermission;
+
+    #[test]
+    fn test_allowed_paths() {
+        let root = "/my/project/root/";
+        let config = CheatsConfig::new(&Config::with_root(root), &Default::default());
+
+        assert!(config.is_path_allowed("/my/project/root/test/fixture.json", FsAccessKind::Read));
+        assert!(config.is_path_allowed("/my/project/root/../root/out.txt", FsAccessKind::Write));
+        assert!(!config.is_path_allowed("/my/project/root/../../etc/hosts", FsAccessKind::Read));
+
+        let config = CheatsConfig::new(
+            &Config {
+                fs_permissions: FsPermissions::new([
+                    PathPermission::read("./"),
+                    PathPermission::read_write("./reports"),
+                ]),
+                ..Config::with_root(root)
+            },
+            &Default::default(),
+        );
+        assert!(config.is_path_allowed("/my/project/root/test/fixture.json", FsAccessKind::Read));
+        assert!(!config.is_path_allowed("/my/project/root/test/fixture.json", FsAccessKind::Write));
+        assert!(config.is_path_allowed("/my/project/root/reports/gas.txt", FsAccessKind::Write));
+        assert!(!config.is_path_allowed("/my/project/root/reports/../out.txt", FsAccessKind::Write));
+    }
+}
@@ -1,14 +1,20 @@
 use std::collections::BTreeMap;
 
-use super::Cheatcodes;
-use crate::abi::HEVMCalls;
+use super::{util, Cheatcodes};
+use crate::{
+    abi::HEVMCalls,
+    executor::genesis::parse_allocs,
+    utils::{mine_blocks, u256_to_h256_be},
+};
 use bytes::Bytes;
 use ethers::{
     abi::{self, AbiEncode, RawLog, Token, Tokenizable, Tokenize},
-    types::{Address, H256, U256},
+    types::{Address, H256, I256, U256},
     utils::keccak256,
 };
-use revm::{Database, EVMData};
+use foundry_common::fs;
+use foundry_config::FsAccessKind;
+use revm::{Database, EVMData, Gas};
 
 #[derive(Clone, Debug, Default)]
 pub struct Broadcast {
@@ -36,6 +42,26 @@ pub struct Prank {
     pub depth: u64,
     /// Whether or not the prank stops by itself after the next call
     pub single_call: bool,
+    /// The id of the prank scope, if the prank was started with `startPrankScope`
+    pub scope: Option<u64>,
+}
+
+impl Prank {
+    pub fn new(
+        prank_caller: Address,
+        prank_origin: Address,
+        new_caller: Address,
+        new_origin: Option<Address>,
+        depth: u64,
+        single_call: bool,
+    ) -> Self {
+        Self { prank_caller, prank_origin, new_caller, new_origin, depth, single_call, scope: None }
+    }
+
+    /// Whether the prank was started by `prank_caller` at the given depth
+    fn started_in(&self, prank_caller: Address, depth: u64) -> bool {
+        self.prank_caller == prank_caller && self.depth == depth
+    }
 }
 
 fn broadcast(
@@ -47,7 +73,7 @@ fn broadcast(
 ) -> Result<Bytes, Bytes> {
     let broadcast = Broadcast { origin, original_caller, depth, single_call };
 
-    if state.prank.is_some() {
+    if !state.pranks.is_empty() {
         return Err("You have an active prank. Broadcasting and pranks are not compatible. Disable one or the other".to_string().encode().into());
     }
 
@@ -68,17 +94,78 @@ fn prank(
     depth: u64,
     single_call: bool,
 ) -> Result<Bytes, Bytes> {
-    let prank = Prank { prank_caller, prank_origin, new_caller, new_origin, depth, single_call };
+    let prank = Prank::new(prank_caller, prank_origin, new_caller, new_origin, depth, single_call);
+
+    // Pranks can be nested as long as they are started from different call frames, only prank
+    // scopes can be nested within the same call frame
+    if state
+        .pranks
+        .iter()
+        .any(|prank| prank.scope.is_none() && prank.started_in(prank_caller, depth))
+    {
+        return Err(util::encode_error("You have an active prank already."))
+    }
 
-    if state.prank.is_some() {
-        return Err("You have an active prank already.".to_string().encode().into())
+    if state.broadcast.is_some() {
+        return Err("You cannot `prank` for a broadcasted transaction. Pass the desired tx.origin into the broadcast cheatcode call".to_string().encode().into());
+    }
+
+    state.pranks.push(prank);
+    Ok(Bytes::new())
+}
+
+/// Stops the most recent prank started with `startPrank` by `prank_caller` at the given depth.
+///
+/// If there is no such prank, the most recent prank started with `startPrank` from another call
+/// frame is stopped instead. Prank scopes are only stopped by `stopPrankScope`.
+fn stop_prank(state: &mut Cheatcodes, prank_caller: Address, depth: u64) -> Result<Bytes, Bytes> {
+    let idx = state
+        .pranks
+        .iter()
+        .rposition(|prank| prank.scope.is_none() && prank.started_in(prank_caller, depth))
+        .or_else(|| state.pranks.iter().rposition(|prank| prank.scope.is_none()));
+    if let Some(idx) = idx {
+        state.pranks.remove(idx);
     }
+    Ok(Bytes::new())
+}
 
+/// Starts a prank scope, which can also be nested within other pranks of the same call frame.
+///
+/// Returns the id of the scope, which is passed to `stopPrankScope` to stop it.
+fn start_prank_scope(state: &mut Cheatcodes, mut prank: Prank) -> Result<Bytes, Bytes> {
     if state.broadcast.is_some() {
         return Err("You cannot `prank` for a broadcasted transaction. Pass the desired tx.origin into the broadcast cheatcode call".to_string().encode().into());
     }
 
-    state.prank = Some(prank);
+    state.prank_scopes += 1;
+    prank.scope = Some(state.prank_scopes);
+    state.pranks.push(prank);
+    Ok(U256::from(state.prank_scopes).encode().into())
+}
+
+/// Stops the prank scope with the given id, which must have been started by `prank_caller` at the
+/// given depth, after all pranks nested within it in the same call frame.
+fn stop_prank_scope(
+    state: &mut Cheatcodes,
+    prank_caller: Address,
+    depth: u64,
+    scope: U256,
+) -> Result<Bytes, Bytes> {
+    let idx = state
+        .pranks
+        .iter()
+        .position(|prank| prank.scope.map(U256::from) == Some(scope))
+        .ok_or_else(|| util::encode_error("Prank scope is not active"))?;
+
+    if !state.pranks[idx].started_in(prank_caller, depth) {
+        return Err(util::encode_error("Prank scope was started in another call frame"))
+    }
+    if state.pranks[idx + 1..].iter().any(|prank| prank.started_in(prank_caller, depth)) {
+        return Err(util::encode_error("Prank scope has nested pranks that are still active"))
+    }
+
+    state.pranks.remove(idx);
     Ok(Bytes::new())
 }
 
@@ -86,6 +173,62 @@ fn prank(
 pub struct RecordAccess {
     pub reads: BTreeMap<Address, Vec<U256>>,
     pub writes: BTreeMap<Address, Vec<U256>>,
+    /// Every storage access in execution order, with the context it was made in
+    pub entries: Vec<StorageAccess>,
+}
+
+/// A storage access and the call frame it was made in
+#[derive(Clone, Debug, Default)]
+pub struct StorageAccess {
+    /// The account whose storage was accessed
+    pub account: Address,
+    /// The caller of the call frame that accessed the storage
+    pub caller: Address,
+    /// The accessed storage slot
+    pub slot: U256,
+    /// Whether the access was an `SSTORE`
+    pub is_write: bool,
+    /// The depth of the call frame that accessed the storage
+    pub depth: u64,
+}
+
+/// The gas of the most recent call, see `lastCallGas`
+#[derive(Clone, Debug, Default)]
+pub struct LastCallGas {
+    /// The gas limit of the call
+    pub gas_limit: u64,
+    /// The total gas used by the call, before refunds
+    pub gas_total_used: u64,
+    /// The gas used by the call to expand its memory
+    pub gas_memory_used: u64,
+    /// The gas refunded by the call
+    pub gas_refunded: i64,
+}
+
+impl LastCallGas {
+    /// Records the gas of a finished call
+    pub fn new(gas: &Gas) -> Self {
+        Self {
+            gas_limit: gas.limit(),
+            gas_total_used: gas.spend(),
+            gas_memory_used: gas.memory(),
+            gas_refunded: gas.refunded(),
+        }
+    }
+}
+
+fn last_call_gas(state: &Cheatcodes) -> Result<Bytes, Bytes> {
+    let gas = state
+        .last_call_gas
+        .as_ref()
+        .ok_or_else(|| util::encode_error("No call was made before `lastCallGas`"))?;
+    Ok(ethers::abi::encode(&[Token::Tuple(vec![
+        Token::Uint(gas.gas_limit.into()),
+        Token::Uint(gas.gas_total_used.into()),
+        Token::Uint(gas.gas_memory_used.into()),
+        Token::Int(I256::from(gas.gas_refunded).into_raw()),
+    ])])
+    .into())
 }
 
 fn start_record(state: &mut Cheatcodes) {
@@ -104,6 +247,29 @@ fn accesses(state: &mut Cheatcodes, address: Address) -> Bytes {
     }
 }
 
+fn get_recorded_accesses(state: &mut Cheatcodes) -> Bytes {
+    let entries = state
+        .accesses
+        .as_mut()
+        .map(|accesses| std::mem::take(&mut accesses.entries))
+        .unwrap_or_default();
+    ethers::abi::encode(&[Token::Array(
+        entries
+            .into_iter()
+            .map(|entry| {
+                Token::Tuple(vec![
+                    Token::Address(entry.account),
+                    Token::Address(entry.caller),
+                    Token::FixedBytes(u256_to_h256_be(entry.slot).as_bytes().to_vec()),
+                    Token::Bool(entry.is_write),
+                    Token::Uint(entry.depth.into()),
+                ])
+            })
+            .collect(),
+    )])
+    .into()
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct RecordedLogs {
     pub entries: Vec<RawLog>,
@@ -134,6 +300,34 @@ fn get_recorded_logs(state: &mut Cheatcodes) -> Bytes {
     }
 }
 
+/// Loads the accounts of the alloc file at `path` into the journaled state
+fn load_allocs<DB: Database>(
+    state: &Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    path: &str,
+) -> Result<Bytes, Bytes> {
+    let path = state.config.root.join(path);
+    state.config.ensure_path_allowed(&path, FsAccessKind::Read).map_err(util::encode_error)?;
+    let allocs = parse_allocs(&fs::read_to_string(path).map_err(util::encode_error)?)
+        .map_err(|err| util::encode_error(format!("Failed to parse allocs: {}", err)))?;
+
+    for (address, account) in allocs {
+        let hash = H256::from_slice(&keccak256(&account.code));
+        data.subroutine.load_account(address, data.db);
+        data.subroutine.set_code(address, account.code.0, hash);
+
+        // we can safely unwrap because `load_account` insert the account to DB.
+        let info = &mut data.subroutine.state().get_mut(&address).unwrap().info;
+        info.balance = account.balance;
+        info.nonce = account.nonce;
+
+        for (slot, value) in account.storage {
+            data.subroutine.sstore(address, slot, value, data.db);
+        }
+    }
+    Ok(Bytes::new())
+}
+
 pub fn apply<DB: Database>(
     state: &mut Cheatcodes,
     data: &mut EVMData<'_, DB>,
@@ -149,6 +343,10 @@ pub fn apply<DB: Database>(
             data.env.block.number = inner.0;
             Ok(Bytes::new())
         }
+        HEVMCalls::RollBlocks(inner) => {
+            mine_blocks(&mut data.env.block, inner.0, inner.1);
+            Ok(Bytes::new())
+        }
         HEVMCalls::Fee(inner) => {
             data.env.block.basefee = inner.0;
             Ok(Bytes::new())
@@ -178,6 +376,7 @@ pub fn apply<DB: Database>(
             data.subroutine.set_code(inner.0, code.0, hash);
             Ok(Bytes::new())
         }
+        HEVMCalls::LoadAllocs(inner) => load_allocs(state, data, &inner.0),
         HEVMCalls::Deal(inner) => {
             let who = inner.0;
             let value = inner.1;
@@ -218,15 +417,32 @@ pub fn apply<DB: Database>(
             data.subroutine.depth(),
             false,
         ),
-        HEVMCalls::StopPrank(_) => {
-            state.prank = None;
-            Ok(Bytes::new())
+        HEVMCalls::StopPrank(_) => stop_prank(state, caller, data.subroutine.depth()),
+        HEVMCalls::StartPrankScope0(inner) => start_prank_scope(
+            state,
+            Prank::new(caller, data.env.tx.caller, inner.0, None, data.subroutine.depth(), false),
+        ),
+        HEVMCalls::StartPrankScope1(inner) => start_prank_scope(
+            state,
+            Prank::new(
+                caller,
+                data.env.tx.caller,
+                inner.0,
+                Some(inner.1),
+                data.subroutine.depth(),
+                false,
+            ),
+        ),
+        HEVMCalls::StopPrankScope(inner) => {
+            stop_prank_scope(state, caller, data.subroutine.depth(), inner.0)
         }
         HEVMCalls::Record(_) => {
             start_record(state);
             Ok(Bytes::new())
         }
         HEVMCalls::Accesses(inner) => Ok(accesses(state, inner.0)),
+        HEVMCalls::GetRecordedAccesses(_) => Ok(get_recorded_accesses(state)),
+        HEVMCalls::LastCallGas(_) => last_call_gas(state),
         HEVMCalls::RecordLogs(_) => {
             start_record_logs(state);
             Ok(Bytes::new())
@@ -270,6 +486,11 @@ pub fn apply<DB: Database>(
             correct_sender_nonce(&data.env.tx.caller, &mut data.subroutine, state);
             broadcast(state, inner.0, caller, data.subroutine.depth(), true)
         }
+        HEVMCalls::Broadcast2(inner) => {
+            correct_sender_nonce(&data.env.tx.caller, &mut data.subroutine, state);
+            util::private_key_to_address(inner.0)
+                .and_then(|origin| broadcast(state, origin, caller, data.subroutine.depth(), true))
+        }
         HEVMCalls::StartBroadcast0(_) => {
             correct_sender_nonce(&data.env.tx.caller, &mut data.subroutine, state);
             broadcast(state, data.env.tx.caller, caller, data.subroutine.depth(), false)
@@ -278,6 +499,11 @@ pub fn apply<DB: Database>(
             correct_sender_nonce(&data.env.tx.caller, &mut data.subroutine, state);
             broadcast(state, inner.0, caller, data.subroutine.depth(), false)
         }
+        HEVMCalls::StartBroadcast2(inner) => {
+            correct_sender_nonce(&data.env.tx.caller, &mut data.subroutine, state);
+            util::private_key_to_address(inner.0)
+                .and_then(|origin| broadcast(state, origin, caller, data.subroutine.depth(), false))
+        }
         HEVMCalls::StopBroadcast(_) => {
             state.broadcast = None;
             Ok(Bytes::new())
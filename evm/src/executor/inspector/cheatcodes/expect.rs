@@ -1,16 +1,22 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, collections::BTreeMap};
 
-use super::Cheatcodes;
+use super::{util, Cheatcodes};
 use crate::{
     abi::HEVMCalls,
-    executor::inspector::cheatcodes::util::{ERROR_PREFIX, REVERT_PREFIX},
+    decode::decode_revert,
+    executor::inspector::{
+        cheatcodes::util::{ERROR_PREFIX, REVERT_PREFIX},
+        utils::gas_used,
+    },
 };
 use bytes::Bytes;
 use ethers::{
-    abi::{AbiDecode, AbiEncode, RawLog},
-    types::{Address, H160, U256},
+    abi::{Abi, AbiDecode, AbiEncode, Function, ParamType, RawLog},
+    types::{Address, H160, H256, U256},
+    utils::keccak256,
 };
-use revm::{return_ok, Database, EVMData, Return};
+use foundry_utils::parse_tokens;
+use revm::{return_ok, Database, EVMData, Gas, Return, SpecId};
 
 /// For some cheatcodes we may internally change the status of the call, i.e. in `expectRevert`.
 /// Solidity will see a successful call and attempt to decode the return data. Therefore, we need
@@ -24,95 +30,189 @@ static DUMMY_CALL_OUTPUT: [u8; 320] = [0u8; 320];
 static DUMMY_CREATE_ADDRESS: Address =
     H160([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
 
+/// The code installed by `mockContract`, which reverts on calls to functions that are not mocked.
+///
+/// `PUSH1 0x00 DUP1 REVERT`
+static MOCK_CONTRACT_CODE: [u8; 4] = [0x60, 0x00, 0x80, 0xfd];
+
 #[derive(Clone, Debug, Default)]
 pub struct ExpectedRevert {
     /// The expected data returned by the revert
     pub reason: Bytes,
     /// The depth at which the revert is expected
     pub depth: u64,
+    /// How the revert data is matched against the expected data
+    pub kind: RevertMatch,
+}
+
+/// How the data of an expected revert is matched
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RevertMatch {
+    /// The revert data, or the reason of a revert string, equals the expected data
+    Exact,
+    /// The revert data starts with the expected data, e.g. the selector of a custom error followed
+    /// by its first arguments
+    Prefix,
+    /// The reason of a revert string contains the expected string
+    Substring,
+}
+
+impl Default for RevertMatch {
+    fn default() -> Self {
+        RevertMatch::Exact
+    }
+}
+
+/// The maximum amount of gas the next call is expected to use
+#[derive(Clone, Debug, Default)]
+pub struct ExpectedGasUsed {
+    /// The maximum amount of gas, net of refunds
+    pub max_gas: u64,
+    /// The depth of the call that's expected to stay below the maximum
+    pub depth: u64,
 }
 
-fn expect_revert(state: &mut Cheatcodes, reason: Bytes, depth: u64) -> Result<Bytes, Bytes> {
+impl ExpectedGasUsed {
+    /// Returns the revert data if the finished call used more gas than expected
+    pub fn check(&self, spec: SpecId, gas: &Gas) -> Option<Bytes> {
+        let used = gas_used(spec, gas.spend(), gas.refunded() as u64);
+        (used > self.max_gas).then(|| {
+            format!("Gas used {used} exceeds the expected maximum of {}", self.max_gas)
+                .encode()
+                .into()
+        })
+    }
+}
+
+fn expect_revert(
+    state: &mut Cheatcodes,
+    reason: Bytes,
+    depth: u64,
+    kind: RevertMatch,
+) -> Result<Bytes, Bytes> {
     if state.expected_revert.is_some() {
         Err("You must call another function prior to expecting a second revert."
             .to_string()
             .encode()
             .into())
     } else {
-        state.expected_revert = Some(ExpectedRevert { reason, depth });
+        state.expected_revert = Some(ExpectedRevert { reason, depth, kind });
         Ok(Bytes::new())
     }
 }
 
+/// Returns the reason of a revert string or a cheatcode error, if it can be decoded
+fn revert_string(retdata: &[u8]) -> Option<Bytes> {
+    let data = if retdata.starts_with(&REVERT_PREFIX) {
+        &retdata[REVERT_PREFIX.len()..]
+    } else if retdata.starts_with(ERROR_PREFIX.as_slice()) {
+        &retdata[ERROR_PREFIX.len()..]
+    } else {
+        return None
+    };
+    ethers::prelude::Bytes::decode(data).ok().map(|decoded| decoded.0)
+}
+
+/// Formats revert data for mismatch reports: revert strings and panics are decoded, custom errors
+/// are shown as their selector followed by their 32 byte argument words
+fn format_revert(data: &[u8]) -> String {
+    if let Some(reason) = revert_string(data) {
+        return format!(
+            "'{}'",
+            String::from_utf8(reason.to_vec())
+                .unwrap_or_else(|_| format!("0x{}", hex::encode(&reason)))
+        )
+    }
+    if data.starts_with(&[0x4e, 0x48, 0x7b, 0x71]) {
+        if let Ok(panic) = decode_revert(data, None, None) {
+            return panic
+        }
+    }
+    if data.len() <= 4 {
+        return format!("0x{}", hex::encode(data))
+    }
+    let args =
+        data[4..].chunks(32).map(|word| format!("0x{}", hex::encode(word))).collect::<Vec<_>>();
+    format!("0x{}({})", hex::encode(&data[..4]), args.join(", "))
+}
+
+/// Returns the revert data reported if a call that is expected not to revert reverted, with its
+/// decoded revert reason
+pub fn handle_expect_no_revert(status: Return, retdata: &[u8]) -> Option<Bytes> {
+    if matches!(status, return_ok!()) {
+        return None
+    }
+    let reason = decode_revert(retdata, None, Some(status))
+        .unwrap_or_else(|_| format!("0x{}", hex::encode(retdata)));
+    Some(format!("Call reverted unexpectedly: {reason}").encode().into())
+}
+
 pub fn handle_expect_revert(
     is_create: bool,
-    expected_revert: &Bytes,
+    expected_revert: &ExpectedRevert,
     status: Return,
     retdata: Bytes,
 ) -> Result<(Option<Address>, Bytes), Bytes> {
+    let expected = &expected_revert.reason;
     if matches!(status, return_ok!()) {
         return Err("Call did not revert as expected".to_string().encode().into())
     }
 
-    if !expected_revert.is_empty() && retdata.is_empty() {
+    if !expected.is_empty() && retdata.is_empty() {
         return Err("Call reverted as expected, but without data".to_string().encode().into())
     }
 
-    let string_data = match retdata {
-        _ if retdata.len() >= REVERT_PREFIX.len() &&
-            retdata[..REVERT_PREFIX.len()] == REVERT_PREFIX =>
-        {
-            Some(&retdata[4..])
-        }
-        _ if retdata.len() >= ERROR_PREFIX.len() &&
-            &retdata[..ERROR_PREFIX.len()] == ERROR_PREFIX.as_slice() =>
-        {
-            Some(&retdata[ERROR_PREFIX.len()..])
-        }
-        _ => None,
-    };
-
-    let (err, actual_revert): (_, Bytes) = match string_data {
-        Some(data) => {
+    let reason = revert_string(&retdata);
+    let (matches, err) = match expected_revert.kind {
+        RevertMatch::Exact => match &reason {
             // It's a revert string, so we do some conversion to perform the check
-            let decoded_data = ethers::prelude::Bytes::decode(data)
-                .expect("String error code, but data can't be decoded as bytes");
-
-            (
+            Some(reason) => (
+                reason == expected,
                 format!(
                     "Error != expected error: '{}' != '{}'",
-                    String::from_utf8(decoded_data.to_vec())
+                    String::from_utf8(reason.to_vec()).ok().unwrap_or_else(|| hex::encode(&reason)),
+                    String::from_utf8(expected.to_vec())
                         .ok()
-                        .unwrap_or_else(|| hex::encode(&decoded_data)),
-                    String::from_utf8(expected_revert.to_vec())
-                        .ok()
-                        .unwrap_or_else(|| hex::encode(&expected_revert))
-                )
-                .encode()
-                .into(),
-                decoded_data.0,
-            )
-        }
-        _ => (
+                        .unwrap_or_else(|| hex::encode(&expected))
+                ),
+            ),
+            None => (
+                retdata == expected,
+                format!(
+                    "Error != expected error: 0x{} != 0x{}",
+                    hex::encode(&retdata),
+                    hex::encode(&expected)
+                ),
+            ),
+        },
+        RevertMatch::Prefix => (
+            retdata.starts_with(expected),
             format!(
-                "Error != expected error: 0x{} != 0x{}",
-                hex::encode(&retdata),
-                hex::encode(&expected_revert)
-            )
-            .encode()
-            .into(),
-            retdata,
+                "Error != expected error: {} does not start with {}",
+                format_revert(&retdata),
+                format_revert(expected)
+            ),
+        ),
+        RevertMatch::Substring => (
+            reason.as_ref().map_or(false, |reason| {
+                expected.is_empty() || reason.windows(expected.len()).any(|part| part == expected)
+            }),
+            format!(
+                "Error != expected error: {} does not contain '{}'",
+                format_revert(&retdata),
+                String::from_utf8_lossy(expected)
+            ),
         ),
     };
 
-    if actual_revert == expected_revert {
+    if matches {
         Ok(if is_create {
             (Some(DUMMY_CREATE_ADDRESS), Bytes::new())
         } else {
             (None, DUMMY_CALL_OUTPUT.to_vec().into())
         })
     } else {
-        Err(err)
+        Err(err.encode().into())
     }
 }
 
@@ -134,47 +234,65 @@ pub struct ExpectedEmit {
     pub found: bool,
 }
 
-pub fn handle_expect_emit(state: &mut Cheatcodes, log: RawLog, address: &Address) {
-    // Fill or check the expected emits
-    if let Some(next_expect_to_fill) =
-        state.expected_emits.iter_mut().find(|expect| expect.log.is_none())
-    {
-        // We have unfilled expects, so we fill the first one
-        next_expect_to_fill.log = Some(log);
-    } else if let Some(next_expect) = state.expected_emits.iter_mut().find(|expect| !expect.found) {
-        // We do not have unfilled expects, so we try to match this log with the first unfound
-        // log that we expect
-        let expected =
-            next_expect.log.as_ref().expect("we should have a log to compare against here");
+impl ExpectedEmit {
+    /// Whether the log matches the expected log with the configured checks
+    fn matches(&self, log: &RawLog, address: &Address) -> bool {
+        let expected = self.log.as_ref().expect("we should have a log to compare against here");
 
         let expected_topic_0 = expected.topics.get(0);
         let log_topic_0 = log.topics.get(0);
 
         // same topic0 and equal number of topics should be verified further, others are a no
         // match
-        if expected_topic_0
+        if !expected_topic_0
             .zip(log_topic_0)
             .map_or(false, |(a, b)| a == b && expected.topics.len() == log.topics.len())
         {
-            // Match topics
-            next_expect.found = log
-                .topics
-                .iter()
-                .skip(1)
-                .enumerate()
-                .filter(|(i, _)| next_expect.checks[*i])
-                .all(|(i, topic)| topic == &expected.topics[i + 1]);
-
-            // Maybe match source address
-            if let Some(addr) = next_expect.address {
-                next_expect.found &= addr == *address;
-            }
+            return false
+        }
 
-            // Maybe match data
-            if next_expect.checks[3] {
-                next_expect.found &= expected.data == log.data;
-            }
+        // Match topics
+        let mut found = log
+            .topics
+            .iter()
+            .skip(1)
+            .enumerate()
+            .filter(|(i, _)| self.checks[*i])
+            .all(|(i, topic)| topic == &expected.topics[i + 1]);
+
+        // Maybe match source address
+        if let Some(addr) = self.address {
+            found &= addr == *address;
         }
+
+        // Maybe match data
+        if self.checks[3] {
+            found &= expected.data == log.data;
+        }
+        found
+    }
+}
+
+pub fn handle_expect_emit(state: &mut Cheatcodes, log: RawLog, address: &Address) {
+    // Fill or check the expected emits, and the emits expected not to occur
+    if let Some(next_expect_to_fill) = state
+        .expected_emits
+        .iter_mut()
+        .chain(state.unexpected_emits.iter_mut())
+        .find(|expect| expect.log.is_none())
+    {
+        // We have unfilled expects, so we fill the first one
+        next_expect_to_fill.log = Some(log);
+        return
+    }
+
+    // We do not have unfilled expects, so we try to match this log with the first unfound log
+    // that we expect
+    if let Some(next_expect) = state.expected_emits.iter_mut().find(|expect| !expect.found) {
+        next_expect.found = next_expect.matches(&log, address);
+    }
+    for unexpected in state.unexpected_emits.iter_mut() {
+        unexpected.found |= unexpected.matches(&log, address);
     }
 }
 
@@ -184,6 +302,131 @@ pub struct ExpectedCallData {
     pub calldata: Bytes,
     /// The expected value sent in the call
     pub value: Option<U256>,
+    /// The exact number of times the call is expected, or `None` if it is expected at least once
+    pub count: Option<u64>,
+    /// The number of matching calls that were made
+    pub found: u64,
+    /// The position of the call in the expected call order, if the calls are expected in order
+    pub order: Option<u64>,
+}
+
+impl ExpectedCallData {
+    /// Whether the call matches the expected calldata and value
+    fn matches(&self, calldata: &[u8], value: U256) -> bool {
+        self.calldata.len() <= calldata.len() &&
+            self.calldata == calldata[..self.calldata.len()] &&
+            self.value.map(|expected| expected == value).unwrap_or(true)
+    }
+
+    /// Whether the call was made as many times as expected
+    pub fn is_fulfilled(&self) -> bool {
+        self.count.map(|count| self.found == count).unwrap_or(self.found > 0)
+    }
+
+    /// Returns the error message of an unfulfilled expected call to `address`
+    pub fn error(&self, address: Address, observed: &[(Bytes, U256)]) -> String {
+        let data = ethers::types::Bytes::from(self.calldata.clone());
+        let value = self.value.map(|v| format!(" and value {}", v)).unwrap_or_default();
+        let mut msg = match self.count {
+            Some(count) => format!(
+                "Expected {} call(s) to {:?} with data {}{}, but got {}",
+                count, address, data, value, self.found
+            ),
+            None => format!(
+                "Expected a call to {:?} with data {}{}, but got none",
+                address, data, value
+            ),
+        };
+        if !observed.is_empty() {
+            msg.push_str(&format!("\nObserved calls to {:?}:", address));
+            for (calldata, value) in observed {
+                msg.push_str(&format!(
+                    "\n  {} with value {}",
+                    ethers::types::Bytes::from(calldata.clone()),
+                    value
+                ));
+            }
+        }
+        msg
+    }
+}
+
+/// Tracks the order of the calls expected after `expectOrderedCalls`
+#[derive(Clone, Debug, Default)]
+pub struct ExpectedCallOrder {
+    /// The number of ordered calls that were expected
+    pub expected: u64,
+    /// The number of ordered calls that were made
+    pub found: u64,
+    /// The first call that was made out of order
+    pub violation: Option<String>,
+}
+
+/// Records a call to an address with expected calls.
+///
+/// Calls expected an exact number of times count every matching call, other calls are fulfilled
+/// by the first matching call.
+pub fn handle_expected_call(
+    expecteds: &mut [ExpectedCallData],
+    order: Option<&mut ExpectedCallOrder>,
+    address: Address,
+    calldata: &[u8],
+    value: U256,
+) {
+    let mut first_matches = vec![];
+    for (idx, expected) in expecteds.iter_mut().enumerate() {
+        if expected.count.is_some() && expected.matches(calldata, value) {
+            expected.found += 1;
+            if expected.found == 1 {
+                first_matches.push(idx);
+            }
+        }
+    }
+    if let Some(idx) = expecteds
+        .iter()
+        .position(|e| e.count.is_none() && e.found == 0 && e.matches(calldata, value))
+    {
+        expecteds[idx].found += 1;
+        first_matches.push(idx);
+    }
+
+    if let Some(order) = order {
+        for idx in first_matches {
+            if let Some(position) = expecteds[idx].order {
+                if position != order.found && order.violation.is_none() {
+                    order.violation = Some(format!(
+                        "Expected the call to {:?} with data {} to be ordered call #{}, but it was ordered call #{}",
+                        address,
+                        ethers::types::Bytes::from(expecteds[idx].calldata.clone()),
+                        position + 1,
+                        order.found + 1
+                    ));
+                }
+                order.found += 1;
+            }
+        }
+    }
+}
+
+fn expect_call(
+    state: &mut Cheatcodes,
+    address: Address,
+    calldata: Bytes,
+    value: Option<U256>,
+    count: Option<u64>,
+) -> Result<Bytes, Bytes> {
+    let order = state.expected_call_order.as_mut().map(|order| {
+        order.expected += 1;
+        order.expected - 1
+    });
+    state.expected_calls.entry(address).or_default().push(ExpectedCallData {
+        calldata,
+        value,
+        count,
+        order,
+        ..Default::default()
+    });
+    Ok(Bytes::new())
 }
 
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
@@ -211,18 +454,145 @@ impl PartialOrd for MockCallDataContext {
     }
 }
 
+/// Finds the function of the ABI by its signature, or by its name if it's not overloaded
+fn find_function<'a>(abi: &'a Abi, name: &str) -> Result<&'a Function, String> {
+    if name.contains('(') {
+        return abi
+            .functions()
+            .find(|func| {
+                let inputs: Vec<_> =
+                    func.inputs.iter().map(|param| param.kind.to_string()).collect();
+                format!("{}({})", func.name, inputs.join(",")) == name
+            })
+            .ok_or_else(|| format!("Function {} not found in the ABI", name))
+    }
+    match abi
+        .functions_by_name(name)
+        .map_err(|_| format!("Function {} not found in the ABI", name))?
+    {
+        [func] => Ok(func),
+        _ => Err(format!("Function {} is overloaded, use its signature instead", name)),
+    }
+}
+
+/// Formats a JSON value like a value given on the command line, e.g. `[0x01,0x02]`
+fn json_value_to_arg(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        serde_json::Value::Array(values) => {
+            let values: Vec<_> = values.iter().map(json_value_to_arg).collect();
+            format!("[{}]", values.join(","))
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Mocks the functions of the JSON ABI at `address` to return the values of the JSON object of
+/// function names or signatures to return values, and installs code at `address` that reverts on
+/// calls to the other functions.
+fn mock_contract<DB: Database>(
+    state: &mut Cheatcodes,
+    data: &mut EVMData<'_, DB>,
+    address: Address,
+    abi: &str,
+    returns: &str,
+) -> Result<Bytes, Bytes> {
+    let abi: Abi = serde_json::from_str(abi)
+        .map_err(|err| util::encode_error(format!("Failed to parse the ABI: {}", err)))?;
+    let returns: BTreeMap<String, serde_json::Value> = serde_json::from_str(returns)
+        .map_err(|err| util::encode_error(format!("Failed to parse the return values: {}", err)))?;
+
+    let mut mocks = BTreeMap::new();
+    for (name, values) in returns {
+        let func = find_function(&abi, &name).map_err(util::encode_error)?;
+        // the value of a function with a single output doesn't need to be wrapped in an array
+        let single_array = func.outputs.len() == 1 &&
+            matches!(func.outputs[0].kind, ParamType::Array(_) | ParamType::FixedArray(..));
+        let values = match values {
+            serde_json::Value::Array(values)
+                if !single_array || (values.len() == 1 && values[0].is_array()) =>
+            {
+                values
+            }
+            value => vec![value],
+        };
+        if values.len() != func.outputs.len() {
+            return Err(util::encode_error(format!(
+                "Function {} returns {} values, but {} were given",
+                name,
+                func.outputs.len(),
+                values.len()
+            )))
+        }
+
+        let values: Vec<_> = values.iter().map(json_value_to_arg).collect();
+        let tokens = parse_tokens(
+            func.outputs.iter().map(|param| &param.kind).zip(values.iter().map(String::as_str)),
+            true,
+        )
+        .map_err(|err| util::encode_error(format!("{}: {:#}", name, err)))?;
+
+        mocks.insert(
+            MockCallDataContext { calldata: func.short_signature().to_vec().into(), value: None },
+            ethers::abi::encode(&tokens).into(),
+        );
+    }
+    state.mocked_calls.entry(address).or_default().extend(mocks);
+
+    let code = Bytes::from_static(&MOCK_CONTRACT_CODE);
+    let hash = H256::from_slice(&keccak256(&code));
+    data.subroutine.load_account(address, data.db);
+    data.subroutine.set_code(address, code, hash);
+    Ok(Bytes::new())
+}
+
 pub fn apply<DB: Database>(
     state: &mut Cheatcodes,
     data: &mut EVMData<'_, DB>,
     call: &HEVMCalls,
 ) -> Option<Result<Bytes, Bytes>> {
     Some(match call {
-        HEVMCalls::ExpectRevert0(_) => expect_revert(state, Bytes::new(), data.subroutine.depth()),
-        HEVMCalls::ExpectRevert1(inner) => {
-            expect_revert(state, inner.0.to_vec().into(), data.subroutine.depth())
+        HEVMCalls::ExpectRevert0(_) => {
+            expect_revert(state, Bytes::new(), data.subroutine.depth(), RevertMatch::Exact)
+        }
+        HEVMCalls::ExpectRevert1(inner) => expect_revert(
+            state,
+            inner.0.to_vec().into(),
+            data.subroutine.depth(),
+            RevertMatch::Exact,
+        ),
+        HEVMCalls::ExpectRevert2(inner) => expect_revert(
+            state,
+            inner.0.to_vec().into(),
+            data.subroutine.depth(),
+            RevertMatch::Exact,
+        ),
+        HEVMCalls::ExpectPartialRevert0(inner) => expect_revert(
+            state,
+            inner.0.to_vec().into(),
+            data.subroutine.depth(),
+            RevertMatch::Prefix,
+        ),
+        HEVMCalls::ExpectPartialRevert1(inner) => expect_revert(
+            state,
+            inner.0.to_vec().into(),
+            data.subroutine.depth(),
+            RevertMatch::Prefix,
+        ),
+        HEVMCalls::ExpectRevertSubstring(inner) => expect_revert(
+            state,
+            inner.0.clone().into_bytes().into(),
+            data.subroutine.depth(),
+            RevertMatch::Substring,
+        ),
+        HEVMCalls::ExpectNoRevert(_) => {
+            state.expected_no_revert = Some(data.subroutine.depth());
+            Ok(Bytes::new())
         }
-        HEVMCalls::ExpectRevert2(inner) => {
-            expect_revert(state, inner.0.to_vec().into(), data.subroutine.depth())
+        HEVMCalls::ExpectGasUsed(inner) => {
+            state.expected_gas_used =
+                Some(ExpectedGasUsed { max_gas: inner.0, depth: data.subroutine.depth() });
+            Ok(Bytes::new())
         }
         HEVMCalls::ExpectEmit0(inner) => {
             state.expected_emits.push(ExpectedEmit {
@@ -241,20 +611,39 @@ pub fn apply<DB: Database>(
             });
             Ok(Bytes::new())
         }
-        HEVMCalls::ExpectCall0(inner) => {
-            state
-                .expected_calls
-                .entry(inner.0)
-                .or_default()
-                .push(ExpectedCallData { calldata: inner.1.to_vec().into(), value: None });
+        HEVMCalls::ExpectNoEmit0(inner) => {
+            state.unexpected_emits.push(ExpectedEmit {
+                depth: data.subroutine.depth() - 1,
+                checks: [inner.0, inner.1, inner.2, inner.3],
+                ..Default::default()
+            });
+            Ok(Bytes::new())
+        }
+        HEVMCalls::ExpectNoEmit1(inner) => {
+            state.unexpected_emits.push(ExpectedEmit {
+                depth: data.subroutine.depth() - 1,
+                checks: [inner.0, inner.1, inner.2, inner.3],
+                address: Some(inner.4),
+                ..Default::default()
+            });
             Ok(Bytes::new())
         }
+        HEVMCalls::ExpectCall0(inner) => {
+            expect_call(state, inner.0, inner.1.to_vec().into(), None, None)
+        }
         HEVMCalls::ExpectCall1(inner) => {
-            state
-                .expected_calls
-                .entry(inner.0)
-                .or_default()
-                .push(ExpectedCallData { calldata: inner.2.to_vec().into(), value: Some(inner.1) });
+            expect_call(state, inner.0, inner.2.to_vec().into(), Some(inner.1), None)
+        }
+        HEVMCalls::ExpectCall2(inner) => {
+            expect_call(state, inner.0, inner.1.to_vec().into(), None, Some(inner.2))
+        }
+        HEVMCalls::ExpectCall3(inner) => {
+            expect_call(state, inner.0, inner.2.to_vec().into(), Some(inner.1), Some(inner.3))
+        }
+        HEVMCalls::ExpectOrderedCalls(_) => {
+            if state.expected_call_order.is_none() {
+                state.expected_call_order = Some(Default::default());
+            }
             Ok(Bytes::new())
         }
         HEVMCalls::MockCall0(inner) => {
@@ -271,6 +660,11 @@ pub fn apply<DB: Database>(
             );
             Ok(Bytes::new())
         }
+        HEVMCalls::MockContract(inner) => mock_contract(state, data, inner.0, &inner.1, &inner.2),
+        HEVMCalls::StrictMock(inner) => {
+            state.strict_mocks.insert(inner.0);
+            Ok(Bytes::new())
+        }
         HEVMCalls::ClearMockedCalls(_) => {
             state.mocked_calls = Default::default();
             Ok(Bytes::new())
@@ -1,22 +1,29 @@
 use crate::{
     abi::HEVMCalls,
     executor::inspector::{cheatcodes::util, Cheatcodes},
+    storage_layout::diff_storage_layouts,
 };
 use bytes::Bytes;
 use ethers::{
     abi::{self, AbiEncode, ParamType, Token},
-    prelude::{artifacts::CompactContractBytecode, ProjectPathsConfig},
-    types::{Address, I256, U256},
-    utils::hex::FromHex,
+    prelude::{
+        artifacts::{CompactContractBytecode, StorageLayout},
+        Lazy, ProjectPathsConfig,
+    },
+    providers::{Middleware, Provider},
+    solc::utils::RuntimeOrHandle,
+    types::{Address, H160, H256, U256},
 };
-use foundry_common::fs;
+use foundry_common::{fs, provider::RpcClient};
+use foundry_config::FsAccessKind;
+use futures::Future;
 use serde::Deserialize;
 use std::{
     env,
     io::{BufRead, BufReader, Write},
     path::{Path, PathBuf},
     process::Command,
-    str::FromStr,
+    time::{Duration, Instant},
 };
 
 fn ffi(state: &Cheatcodes, args: &[String]) -> Result<Bytes, Bytes> {
@@ -61,25 +68,105 @@ struct HardhatArtifact {
     bytecode: ethers::types::Bytes,
 }
 
-fn get_code(path: &str) -> Result<Bytes, Bytes> {
-    let path = if path.ends_with(".json") {
+/// Returns the path of the artifact, given either as a path to the artifact file or in the form
+/// `<file>(:<contract>)?`
+fn artifact_path(path: &str) -> PathBuf {
+    if path.ends_with(".json") {
         Path::new(&path).to_path_buf()
     } else {
         let parts: Vec<&str> = path.split(':').collect();
         let file = parts[0];
-        let contract_name =
-            if parts.len() == 1 { parts[0].replace(".sol", "") } else { parts[1].to_string() };
+        // without a contract name, the contract is named like the file, e.g. `Foo.sol` or `Foo.vy`
+        let contract_name = if parts.len() == 1 {
+            Path::new(file).file_stem().unwrap_or_default().to_string_lossy().to_string()
+        } else {
+            parts[1].to_string()
+        };
         let out_dir = ProjectPathsConfig::find_artifacts_dir(Path::new("./"));
         out_dir.join(format!("{file}/{contract_name}.json"))
-    };
+    }
+}
 
-    let data = fs::read_to_string(path).map_err(util::encode_error)?;
+/// Returns the creation code of the artifact
+fn read_code(path: &str) -> Result<Bytes, Bytes> {
+    let data = fs::read_to_string(artifact_path(path)).map_err(util::encode_error)?;
     let bytecode = serde_json::from_str::<ArtifactBytecode>(&data).map_err(util::encode_error)?;
 
-    if let Some(bin) = bytecode.into_inner() {
-        Ok(abi::encode(&[Token::Bytes(bin.to_vec())]).into())
+    bytecode.into_inner().map(|bin| bin.0).ok_or_else(|| {
+        "No bytecode for contract. Is it abstract or unlinked?".to_string().encode().into()
+    })
+}
+
+fn get_code(path: &str) -> Result<Bytes, Bytes> {
+    let bin = read_code(path)?;
+    Ok(abi::encode(&[Token::Bytes(bin.to_vec())]).into())
+}
+
+/// The address of the deployer of `deployCode`
+pub const CODE_DEPLOYER_ADDRESS: Address =
+    H160([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xc0, 0xde]);
+
+/// The runtime code of the deployer of `deployCode`, which creates a contract with its calldata as
+/// init code and returns its address, or bubbles up the revert of the constructor:
+///
+/// ```text
+/// calldatacopy(0, 0, calldatasize())
+/// let addr := create(0, 0, calldatasize())
+/// if iszero(addr) { returndatacopy(0, 0, returndatasize()) revert(0, returndatasize()) }
+/// mstore(0, addr)
+/// return(0, 32)
+/// ```
+pub static CODE_DEPLOYER: Lazy<Bytes> = Lazy::new(|| {
+    hex::decode("3660006000373660006000f080601a573d600060003e3d6000fd5b60005260206000f3")
+        .expect("valid hex")
+        .into()
+});
+
+/// Returns the init code of a `deployCode` call: the creation code of the artifact, followed by
+/// the constructor arguments if any
+pub fn deploy_code_init_code(call: &HEVMCalls) -> Option<Result<Bytes, Bytes>> {
+    match call {
+        HEVMCalls::DeployCode0(inner) => Some(read_code(&inner.0)),
+        HEVMCalls::DeployCode1(inner) => Some(
+            read_code(&inner.0)
+                .map(|code| code.iter().chain(inner.1.iter()).copied().collect::<Vec<_>>().into()),
+        ),
+        _ => None,
+    }
+}
+
+/// A thin wrapper around a Forge-style artifact that only extracts the storage layout.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactStorageLayout {
+    storage_layout: Option<StorageLayout>,
+}
+
+fn storage_layout(path: &str) -> Result<StorageLayout, Bytes> {
+    let data = fs::read_to_string(artifact_path(path)).map_err(util::encode_error)?;
+    let artifact =
+        serde_json::from_str::<ArtifactStorageLayout>(&data).map_err(util::encode_error)?;
+    artifact.storage_layout.ok_or_else(|| {
+        format!(
+            "No storage layout in the artifact of {path}, add `storageLayout` to `extra_output`"
+        )
+        .encode()
+        .into()
+    })
+}
+
+fn assert_storage_layout_compatible(old: &str, new: &str) -> Result<Bytes, Bytes> {
+    let changes = diff_storage_layouts(&storage_layout(old)?, &storage_layout(new)?)
+        .map_err(util::encode_error)?;
+    let breaking = changes
+        .iter()
+        .filter(|change| change.is_breaking())
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    if breaking.is_empty() {
+        Ok(Bytes::new())
     } else {
-        Err("No bytecode for contract. Is it abstract or unlinked?".to_string().encode().into())
+        Err(format!("Incompatible storage layout: {}", breaking.join("; ")).encode().into())
     }
 }
 
@@ -114,40 +201,8 @@ fn get_env(key: &str, r#type: ParamType, delim: Option<&str>) -> Result<Bytes, B
         vec![val.as_str()]
     };
 
-    let parse_bool = |v: &str| v.to_lowercase().parse::<bool>();
-    let parse_uint = |v: &str| {
-        if v.starts_with("0x") {
-            let v = Vec::from_hex(v.strip_prefix("0x").unwrap()).map_err(|e| e.to_string())?;
-            Ok(U256::from_little_endian(&v))
-        } else {
-            U256::from_dec_str(v).map_err(|e| e.to_string())
-        }
-    };
-    let parse_int = |v: &str| {
-        // hex string may start with "0x", "+0x", or "-0x"
-        if v.starts_with("0x") || v.starts_with("+0x") || v.starts_with("-0x") {
-            I256::from_hex_str(&v.replacen("0x", "", 1)).map(|v| v.into_raw())
-        } else {
-            I256::from_dec_str(v).map(|v| v.into_raw())
-        }
-    };
-    let parse_address = |v: &str| Address::from_str(v);
-    let parse_string = |v: &str| -> Result<String, ()> { Ok(v.to_string()) };
-    let parse_bytes = |v: &str| Vec::from_hex(v.strip_prefix("0x").unwrap_or(v));
-
     val.iter()
-        .map(|v| match r#type {
-            ParamType::Bool => parse_bool(v).map(Token::Bool).map_err(|e| e.to_string()),
-            ParamType::Uint(256) => parse_uint(v).map(Token::Uint),
-            ParamType::Int(256) => parse_int(v).map(Token::Int).map_err(|e| e.to_string()),
-            ParamType::Address => parse_address(v).map(Token::Address).map_err(|e| e.to_string()),
-            ParamType::FixedBytes(32) => {
-                parse_bytes(v).map(Token::FixedBytes).map_err(|e| e.to_string())
-            }
-            ParamType::String => parse_string(v).map(Token::String).map_err(|_| "".to_string()),
-            ParamType::Bytes => parse_bytes(v).map(Token::Bytes).map_err(|e| e.to_string()),
-            _ => Err(format!("{} is not a supported type", r#type)),
-        })
+        .map(|v| util::parse(v, &r#type))
         .collect::<Result<Vec<Token>, String>>()
         .map(|mut tokens| {
             if delim.is_none() {
@@ -165,7 +220,7 @@ fn full_path(state: &Cheatcodes, path: impl AsRef<Path>) -> PathBuf {
 
 fn read_file(state: &Cheatcodes, path: impl AsRef<Path>) -> Result<Bytes, Bytes> {
     let path = full_path(state, &path);
-    state.config.ensure_path_allowed(&path).map_err(util::encode_error)?;
+    state.config.ensure_path_allowed(&path, FsAccessKind::Read).map_err(util::encode_error)?;
 
     let data = fs::read_to_string(path).map_err(util::encode_error)?;
 
@@ -174,7 +229,7 @@ fn read_file(state: &Cheatcodes, path: impl AsRef<Path>) -> Result<Bytes, Bytes>
 
 fn read_line(state: &mut Cheatcodes, path: impl AsRef<Path>) -> Result<Bytes, Bytes> {
     let path = full_path(state, &path);
-    state.config.ensure_path_allowed(&path).map_err(util::encode_error)?;
+    state.config.ensure_path_allowed(&path, FsAccessKind::Read).map_err(util::encode_error)?;
 
     // Get reader for previously opened file to continue reading OR initialize new reader
     let reader = state
@@ -199,7 +254,7 @@ fn read_line(state: &mut Cheatcodes, path: impl AsRef<Path>) -> Result<Bytes, By
 
 fn write_file(state: &Cheatcodes, path: impl AsRef<Path>, data: &str) -> Result<Bytes, Bytes> {
     let path = full_path(state, &path);
-    state.config.ensure_path_allowed(&path).map_err(util::encode_error)?;
+    state.config.ensure_path_allowed(&path, FsAccessKind::Write).map_err(util::encode_error)?;
 
     fs::write(path, data).map_err(util::encode_error)?;
 
@@ -208,7 +263,7 @@ fn write_file(state: &Cheatcodes, path: impl AsRef<Path>, data: &str) -> Result<
 
 fn write_line(state: &Cheatcodes, path: impl AsRef<Path>, line: &str) -> Result<Bytes, Bytes> {
     let path = full_path(state, &path);
-    state.config.ensure_path_allowed(&path).map_err(util::encode_error)?;
+    state.config.ensure_path_allowed(&path, FsAccessKind::Write).map_err(util::encode_error)?;
 
     let mut file = std::fs::OpenOptions::new()
         .append(true)
@@ -223,7 +278,7 @@ fn write_line(state: &Cheatcodes, path: impl AsRef<Path>, line: &str) -> Result<
 
 fn close_file(state: &mut Cheatcodes, path: impl AsRef<Path>) -> Result<Bytes, Bytes> {
     let path = full_path(state, &path);
-    state.config.ensure_path_allowed(&path).map_err(util::encode_error)?;
+    state.config.ensure_path_allowed(&path, FsAccessKind::Read).map_err(util::encode_error)?;
 
     state.context.opened_read_files.remove(&path);
 
@@ -232,14 +287,145 @@ fn close_file(state: &mut Cheatcodes, path: impl AsRef<Path>) -> Result<Bytes, B
 
 fn remove_file(state: &mut Cheatcodes, path: impl AsRef<Path>) -> Result<Bytes, Bytes> {
     let path = full_path(state, &path);
-    state.config.ensure_path_allowed(&path).map_err(util::encode_error)?;
+    state.config.ensure_path_allowed(&path, FsAccessKind::Write).map_err(util::encode_error)?;
 
-    close_file(state, &path)?;
+    state.context.opened_read_files.remove(&path);
     fs::remove_file(&path).map_err(util::encode_error)?;
 
     Ok(Bytes::new())
 }
 
+/// The interval in which external conditions are polled
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Converts a number of milliseconds passed to a cheatcode into a [Duration]
+fn millis(ms: U256) -> Duration {
+    Duration::from_millis(ms.min(U256::from(u64::MAX)).as_u64())
+}
+
+/// Fails unless the cheatcodes that block on external conditions are enabled
+fn ensure_wait_allowed(state: &Cheatcodes) -> Result<(), Bytes> {
+    if state.config.allow_wait {
+        Ok(())
+    } else {
+        Err(util::encode_error("Waiting disabled: run again with `--allow-wait` if you want to allow tests to sleep and wait for external conditions."))
+    }
+}
+
+fn sleep(state: &Cheatcodes, ms: U256) -> Result<Bytes, Bytes> {
+    ensure_wait_allowed(state)?;
+    std::thread::sleep(millis(ms));
+    Ok(Bytes::new())
+}
+
+/// Polls `condition` until it returns a value, failing once `timeout` milliseconds have elapsed.
+///
+/// The polling runs on a separate thread with its own runtime, since cheatcodes can be executed
+/// from within an async context (e.g. scripts).
+fn wait_for<T, F, Fut>(timeout: U256, condition_name: String, mut condition: F) -> Result<T, Bytes>
+where
+    T: Send + 'static,
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Option<T>>,
+{
+    let timeout = millis(timeout);
+    std::thread::spawn(move || {
+        RuntimeOrHandle::new().block_on(async move {
+            let start = Instant::now();
+            loop {
+                if let Some(value) = condition().await {
+                    return Ok(value)
+                }
+                if start.elapsed() >= timeout {
+                    return Err(util::encode_error(format!(
+                        "Timed out after {}ms waiting for {}",
+                        timeout.as_millis(),
+                        condition_name
+                    )))
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        })
+    })
+    .join()
+    .map_err(|_| util::encode_error(format!("Failed to wait for {}", condition_name)))?
+}
+
+fn wait_for_file(
+    state: &Cheatcodes,
+    path: impl AsRef<Path>,
+    timeout: U256,
+) -> Result<Bytes, Bytes> {
+    ensure_wait_allowed(state)?;
+    let name = format!("file {}", path.as_ref().display());
+    let path = full_path(state, &path);
+    state.config.ensure_path_allowed(&path, FsAccessKind::Read).map_err(util::encode_error)?;
+
+    wait_for(timeout, name, move || {
+        let exists = path.exists();
+        async move { exists.then_some(()) }
+    })?;
+
+    Ok(Bytes::new())
+}
+
+fn wait_for_http(
+    state: &Cheatcodes,
+    url: &str,
+    expected: Option<&str>,
+    timeout: U256,
+) -> Result<Bytes, Bytes> {
+    ensure_wait_allowed(state)?;
+    let url = url.to_string();
+    let expected = expected.map(|expected| expected.trim().to_string());
+    let name = match &expected {
+        Some(expected) => format!("{} to respond with `{}`", url, expected),
+        None => format!("{} to respond", url),
+    };
+
+    let body = wait_for(timeout, name, move || {
+        let url = url.clone();
+        let expected = expected.clone();
+        async move {
+            let response = reqwest::get(&url).await.ok()?;
+            if !response.status().is_success() {
+                return None
+            }
+            let body = response.text().await.ok()?;
+            match expected {
+                Some(expected) if body.trim() != expected => None,
+                _ => Some(body),
+            }
+        }
+    })?;
+
+    Ok(abi::encode(&[Token::String(body)]).into())
+}
+
+fn wait_for_storage(
+    state: &Cheatcodes,
+    url_or_alias: &str,
+    address: Address,
+    slot: H256,
+    expected: H256,
+    timeout: U256,
+) -> Result<Bytes, Bytes> {
+    ensure_wait_allowed(state)?;
+    let endpoint = state.config.get_rpc_endpoint(url_or_alias)?;
+    let provider = Provider::new(RpcClient::new(&endpoint, 10, 1000).map_err(util::encode_error)?);
+
+    let name = format!("slot {:?} of {:?} to be {:?}", slot, address, expected);
+    wait_for(timeout, name, move || {
+        let provider = provider.clone();
+        async move {
+            let value = provider.get_storage_at(address, slot, None).await.ok()?;
+            (value == expected).then_some(())
+        }
+    })?;
+
+    Ok(Bytes::new())
+}
+
 pub fn apply(
     state: &mut Cheatcodes,
     ffi_enabled: bool,
@@ -254,6 +440,9 @@ pub fn apply(
             }
         }
         HEVMCalls::GetCode(inner) => get_code(&inner.0),
+        HEVMCalls::AssertStorageLayoutCompatible(inner) => {
+            assert_storage_layout_compatible(&inner.0, &inner.1)
+        }
         HEVMCalls::SetEnv(inner) => set_env(&inner.0, &inner.1),
         HEVMCalls::EnvBool0(inner) => get_env(&inner.0, ParamType::Bool, None),
         HEVMCalls::EnvUint0(inner) => get_env(&inner.0, ParamType::Uint(256), None),
@@ -277,6 +466,18 @@ pub fn apply(
         HEVMCalls::WriteLine(inner) => write_line(state, &inner.0, &inner.1),
         HEVMCalls::CloseFile(inner) => close_file(state, &inner.0),
         HEVMCalls::RemoveFile(inner) => remove_file(state, &inner.0),
+        HEVMCalls::Sleep(inner) => sleep(state, inner.0),
+        HEVMCalls::WaitForFile(inner) => wait_for_file(state, &inner.0, inner.1),
+        HEVMCalls::WaitForHttp0(inner) => wait_for_http(state, &inner.0, None, inner.1),
+        HEVMCalls::WaitForHttp1(inner) => wait_for_http(state, &inner.0, Some(&inner.1), inner.2),
+        HEVMCalls::WaitForStorage(inner) => wait_for_storage(
+            state,
+            &inner.0,
+            inner.1,
+            H256::from(inner.2),
+            H256::from(inner.3),
+            inner.4,
+        ),
         _ => return None,
     })
 }
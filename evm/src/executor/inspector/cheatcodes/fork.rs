@@ -1,10 +1,13 @@
 use super::{util, Cheatcodes};
 use crate::{
     abi::HEVMCalls,
-    executor::{backend::DatabaseExt, fork::CreateFork},
+    executor::{backend::DatabaseExt, fork::CreateFork, inspector::utils::apply_state_changes},
 };
 use bytes::Bytes;
-use ethers::{abi::AbiEncode, prelude::U256};
+use ethers::{
+    abi::AbiEncode,
+    prelude::{H256, U256},
+};
 use revm::EVMData;
 
 /// Handles fork related cheatcodes
@@ -48,6 +51,7 @@ pub fn apply<DB: DatabaseExt>(
                 .map(|_| Default::default())
                 .map_err(util::encode_error)
         }
+        HEVMCalls::Transact(inner) => transact(data, inner.0.into()),
         HEVMCalls::RpcUrl(rpc) => state.config.get_rpc_url(&rpc.0).map(|url| url.encode().into()),
         HEVMCalls::RpcUrls(_) => {
             let mut urls = Vec::with_capacity(state.config.rpc_endpoints.len());
@@ -72,6 +76,18 @@ fn select_fork<DB: DatabaseExt>(data: &mut EVMData<DB>, fork_id: U256) -> Result
     data.db.select_fork(fork_id, data.env).map(|_| Default::default()).map_err(util::encode_error)
 }
 
+/// Replays the given transaction of the active fork on top of the current state and applies its
+/// state changes and logs
+fn transact<DB: DatabaseExt>(data: &mut EVMData<'_, DB>, tx_hash: H256) -> Result<Bytes, Bytes> {
+    let (changes, logs) =
+        data.db.transact(tx_hash, data.env, data.subroutine).map_err(util::encode_error)?;
+
+    apply_state_changes(data.subroutine, data.db, changes);
+    data.subroutine.logs.extend(logs);
+
+    Ok(Bytes::new())
+}
+
 /// Creates and then also selects the new fork
 fn create_select_fork<DB: DatabaseExt>(
     state: &mut Cheatcodes,
@@ -101,14 +117,17 @@ fn create_fork_request<DB: DatabaseExt>(
     block: Option<u64>,
     data: &EVMData<DB>,
 ) -> Result<CreateFork, Bytes> {
-    let url = state.config.get_rpc_url(url_or_alias)?;
+    // forks of an alias without a block number use the block the alias is pinned to, if any
+    let block = block.or_else(|| state.config.fork_block_numbers.get(&url_or_alias).copied());
+    let endpoint = state.config.get_rpc_endpoint(url_or_alias)?;
     let mut evm_opts = state.config.evm_opts.clone();
     evm_opts.fork_block_number = block;
     let fork = CreateFork {
-        enable_caching: state.config.rpc_storage_caching.enable_for_endpoint(&url),
-        url,
+        enable_caching: state.config.rpc_storage_caching.enable_for_endpoint(&endpoint.url),
+        endpoint,
         env: data.env.clone(),
         evm_opts,
+        prefetch_dir: state.config.fork_prefetch_dir.clone(),
     };
     Ok(fork)
 }
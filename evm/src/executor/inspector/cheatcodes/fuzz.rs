@@ -1,14 +1,71 @@
-use crate::{abi::HEVMCalls, fuzz::ASSUME_MAGIC_RETURN_CODE};
+use super::{util, Cheatcodes};
+use crate::{
+    abi::HEVMCalls,
+    fuzz::{assume_rejection, random_seed, seeded_rng},
+};
 use bytes::Bytes;
+use ethers::{
+    abi::{self, AbiEncode, Token},
+    types::{Address, U256},
+};
+use proptest::{prelude::RngCore, test_runner::TestRng};
 use revm::{Database, EVMData};
 
+/// Returns the rng of the `random*` cheatcodes, seeded with the configured seed, or a random one.
+///
+/// Every test starts with the rng state after `setUp`, so a test draws the same values for the
+/// same seed.
+fn rng(state: &mut Cheatcodes) -> &mut TestRng {
+    let seed = state.config.seed;
+    state.rng.get_or_insert_with(|| seeded_rng(seed.unwrap_or_else(random_seed)))
+}
+
+fn random_uint(rng: &mut TestRng) -> U256 {
+    let mut bytes = [0u8; 32];
+    rng.fill_bytes(&mut bytes);
+    U256::from_big_endian(&bytes)
+}
+
+/// Returns a random uint in the inclusive range `[min, max]`
+fn random_uint_in_range(rng: &mut TestRng, min: U256, max: U256) -> Result<Bytes, Bytes> {
+    if min > max {
+        return Err(util::encode_error(format!("min ({min}) is greater than max ({max})")))
+    }
+    let value = random_uint(rng);
+    let value = match (max - min).checked_add(U256::one()) {
+        Some(range) => min + value % range,
+        // the range is the entire uint256 range
+        None => value,
+    };
+    Ok(value.encode().into())
+}
+
 pub fn apply<DB: Database>(
+    state: &mut Cheatcodes,
     _: &mut EVMData<'_, DB>,
     call: &HEVMCalls,
 ) -> Option<Result<Bytes, Bytes>> {
-    if let HEVMCalls::Assume(inner) = call {
-        Some(if inner.0 { Ok(Bytes::new()) } else { Err(ASSUME_MAGIC_RETURN_CODE.into()) })
-    } else {
-        None
-    }
+    Some(match call {
+        HEVMCalls::Assume(inner) => {
+            state.assume_calls += 1;
+            if inner.0 {
+                Ok(Bytes::new())
+            } else {
+                Err(assume_rejection(state.assume_calls))
+            }
+        }
+        HEVMCalls::RandomUint0(_) => Ok(random_uint(rng(state)).encode().into()),
+        HEVMCalls::RandomUint1(inner) => random_uint_in_range(rng(state), inner.0, inner.1),
+        HEVMCalls::RandomAddress(_) => {
+            let mut bytes = [0u8; 20];
+            rng(state).fill_bytes(&mut bytes);
+            Ok(Address::from(bytes).encode().into())
+        }
+        HEVMCalls::RandomBytes(inner) => {
+            let mut bytes = vec![0u8; inner.0.min(U256::from(u32::MAX)).as_usize()];
+            rng(state).fill_bytes(&mut bytes);
+            Ok(abi::encode(&[Token::Bytes(bytes)]).into())
+        }
+        _ => return None,
+    })
 }
@@ -0,0 +1,386 @@
+use super::{util, Cheatcodes};
+use crate::abi::HEVMCalls;
+use bytes::Bytes;
+use ethers::{
+    abi::{self, ParamType, Token},
+    types::{Address, I256, U256},
+    utils::to_checksum,
+};
+use foundry_common::fs;
+use foundry_config::FsAccessKind;
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+/// A segment of a key, which selects a value in a JSON document, e.g. `.deployments[0].address`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Parses a key of the form `$.a.b[0]`, where the leading `$` is optional and the empty key, `$`
+/// and `.` select the whole document
+fn parse_key(key: &str) -> Result<Vec<Segment>, String> {
+    let key = key.strip_prefix('$').unwrap_or(key);
+    let mut segments = Vec::new();
+    let mut chars = key.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut name = String::new();
+                while let Some(c) = chars.peek().filter(|c| **c != '.' && **c != '[') {
+                    name.push(*c);
+                    chars.next();
+                }
+                if !name.is_empty() {
+                    segments.push(Segment::Key(name));
+                }
+            }
+            '[' => {
+                let mut index = String::new();
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break
+                    }
+                    index.push(c);
+                }
+                let index = index.trim_matches(|c| c == '"' || c == '\'');
+                match index.parse() {
+                    Ok(index) => segments.push(Segment::Index(index)),
+                    Err(_) => segments.push(Segment::Key(index.to_string())),
+                }
+            }
+            _ => return Err(format!("Invalid key `{key}`, keys look like `.a.b[0]`")),
+        }
+    }
+    Ok(segments)
+}
+
+/// Returns the value at `key` in the `json` document
+fn select<'a>(json: &'a Value, key: &str) -> Result<&'a Value, String> {
+    parse_key(key)?.iter().try_fold(json, |value, segment| {
+        match segment {
+            Segment::Key(name) => value.get(name),
+            Segment::Index(index) => value.get(index),
+        }
+        .ok_or_else(|| format!("Key `{key}` not found in JSON"))
+    })
+}
+
+/// Returns the value at `key` in the `json` document for replacing it
+fn select_mut<'a>(json: &'a mut Value, key: &str) -> Result<&'a mut Value, String> {
+    parse_key(key)?.iter().try_fold(json, |value, segment| {
+        match segment {
+            Segment::Key(name) => value.get_mut(name),
+            Segment::Index(index) => value.get_mut(index),
+        }
+        .ok_or_else(|| format!("Key `{key}` not found in JSON"))
+    })
+}
+
+/// Converts a JSON value to a token of the given type.
+///
+/// Numbers may be given as JSON numbers, or as decimal or `0x` prefixed hex strings, which is
+/// required for numbers that don't fit into 64 bits.
+fn value_to_token(value: &Value, kind: &ParamType) -> Result<Token, String> {
+    let as_str = |value: &Value| match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    };
+    let invalid = || format!("Cannot parse {value} as {kind}");
+    match kind {
+        ParamType::Array(inner) => match value {
+            Value::Array(values) => values
+                .iter()
+                .map(|value| value_to_token(value, inner))
+                .collect::<Result<_, _>>()
+                .map(Token::Array),
+            _ => Err(invalid()),
+        },
+        ParamType::Bool => match value {
+            Value::Bool(b) => Ok(Token::Bool(*b)),
+            Value::String(s) => s.parse().map(Token::Bool).map_err(|_| invalid()),
+            _ => Err(invalid()),
+        },
+        ParamType::Uint(_) => {
+            let s = as_str(value);
+            match s.strip_prefix("0x") {
+                Some(hex) => U256::from_str_radix(hex, 16).ok(),
+                None => U256::from_dec_str(&s).ok(),
+            }
+            .map(Token::Uint)
+            .ok_or_else(invalid)
+        }
+        ParamType::Int(_) => {
+            let s = as_str(value);
+            if s.starts_with("0x") || s.starts_with("-0x") {
+                I256::from_hex_str(&s.replacen("0x", "", 1))
+            } else {
+                I256::from_dec_str(&s)
+            }
+            .map(|i| Token::Int(i.into_raw()))
+            .map_err(|_| invalid())
+        }
+        ParamType::Address => {
+            Address::from_str(&as_str(value)).map(Token::Address).map_err(|_| invalid())
+        }
+        ParamType::FixedBytes(size) => {
+            let mut bytes = hex::decode(as_str(value).trim_start_matches("0x"))
+                .ok()
+                .filter(|bytes| bytes.len() <= *size)
+                .ok_or_else(invalid)?;
+            bytes.resize(*size, 0);
+            Ok(Token::FixedBytes(bytes))
+        }
+        ParamType::Bytes => hex::decode(as_str(value).trim_start_matches("0x"))
+            .map(Token::Bytes)
+            .map_err(|_| invalid()),
+        ParamType::String => Ok(Token::String(as_str(value))),
+        _ => Err(format!("{kind} is not a supported type")),
+    }
+}
+
+/// Converts a JSON value to a token, inferring its type: `0x` prefixed hex strings are addresses
+/// if they're 20 bytes long, `bytes32` if they're 32 bytes long and `bytes` otherwise, integers
+/// are `uint256`, or `int256` if they're negative, arrays are arrays and objects are tuples of
+/// their values in the alphabetical order of their keys.
+fn infer_token(value: &Value) -> Result<Token, String> {
+    match value {
+        Value::Null => Err("Cannot encode null".to_string()),
+        Value::Bool(b) => Ok(Token::Bool(*b)),
+        Value::Number(n) => {
+            let s = n.to_string();
+            U256::from_dec_str(&s)
+                .map(Token::Uint)
+                .or_else(|_| I256::from_dec_str(&s).map(|i| Token::Int(i.into_raw())))
+                .map_err(|_| format!("Cannot encode {s}, only integers are supported"))
+        }
+        Value::String(s) => {
+            let bytes = s.strip_prefix("0x").and_then(|hex| hex::decode(hex).ok());
+            Ok(match bytes {
+                Some(bytes) if bytes.len() == 20 => Token::Address(Address::from_slice(&bytes)),
+                Some(bytes) if bytes.len() == 32 => Token::FixedBytes(bytes),
+                Some(bytes) => Token::Bytes(bytes),
+                None => Token::String(s.clone()),
+            })
+        }
+        Value::Array(values) => {
+            values.iter().map(infer_token).collect::<Result<_, _>>().map(Token::Array)
+        }
+        Value::Object(map) => {
+            map.values().map(infer_token).collect::<Result<_, _>>().map(Token::Tuple)
+        }
+    }
+}
+
+fn parse_json(json: &str, key: &str, kind: Option<ParamType>) -> Result<Bytes, Bytes> {
+    let json: Value = serde_json::from_str(json).map_err(util::encode_error)?;
+    let value = select(&json, key).map_err(util::encode_error)?;
+    match kind {
+        Some(kind) => {
+            let token = value_to_token(value, &kind).map_err(util::encode_error)?;
+            Ok(abi::encode(&[token]).into())
+        }
+        None => {
+            let token = infer_token(value).map_err(util::encode_error)?;
+            Ok(abi::encode(&[Token::Bytes(abi::encode(&[token]))]).into())
+        }
+    }
+}
+
+/// Adds the value at `value_key` to the object that is serialized as `object_key`, and returns the
+/// serialized object
+fn serialize(
+    state: &mut Cheatcodes,
+    object_key: &str,
+    value_key: &str,
+    value: Value,
+) -> Result<Bytes, Bytes> {
+    let object = state.serialized_jsons.entry(object_key.to_string()).or_default();
+    object.insert(value_key.to_string(), value);
+    let json = Value::Object(object.clone()).to_string();
+    Ok(abi::encode(&[Token::String(json)]).into())
+}
+
+fn uint_value(value: U256) -> Value {
+    if value <= U256::from(u64::MAX) {
+        Value::from(value.as_u64())
+    } else {
+        Value::String(value.to_string())
+    }
+}
+
+fn int_value(value: I256) -> Value {
+    if value >= I256::from(i64::MIN) && value <= I256::from(i64::MAX) {
+        Value::from(value.as_i64())
+    } else {
+        Value::String(value.to_string())
+    }
+}
+
+fn address_value(value: Address) -> Value {
+    Value::String(to_checksum(&value, None))
+}
+
+fn bytes_value(value: impl AsRef<[u8]>) -> Value {
+    Value::String(format!("0x{}", hex::encode(value)))
+}
+
+/// Strings that are JSON objects, e.g. serialized objects, are nested as objects
+fn string_value(value: &str) -> Value {
+    match serde_json::from_str::<Value>(value) {
+        Ok(object @ Value::Object(_)) => object,
+        _ => Value::String(value.to_string()),
+    }
+}
+
+fn array_value<T>(values: &[T], f: impl Fn(&T) -> Value) -> Value {
+    Value::Array(values.iter().map(f).collect())
+}
+
+/// Writes the `json`, or replaces the value at `value_key` in the JSON file with it
+fn write_json(
+    state: &Cheatcodes,
+    json: &str,
+    path: &str,
+    value_key: Option<&str>,
+) -> Result<Bytes, Bytes> {
+    let path = state.config.root.join(path);
+    state.config.ensure_path_allowed(&path, FsAccessKind::Write).map_err(util::encode_error)?;
+
+    let json: Value = serde_json::from_str(json).unwrap_or_else(|_| Value::String(json.into()));
+    let json = match value_key {
+        Some(value_key) => {
+            state
+                .config
+                .ensure_path_allowed(&path, FsAccessKind::Read)
+                .map_err(util::encode_error)?;
+            let mut file: Value =
+                serde_json::from_str(&fs::read_to_string(&path).map_err(util::encode_error)?)
+                    .map_err(util::encode_error)?;
+            *select_mut(&mut file, value_key).map_err(util::encode_error)? = json;
+            file
+        }
+        None => json,
+    };
+    fs::write(path, serde_json::to_string_pretty(&json).map_err(util::encode_error)?)
+        .map_err(util::encode_error)?;
+    Ok(Bytes::new())
+}
+
+pub fn apply(state: &mut Cheatcodes, call: &HEVMCalls) -> Option<Result<Bytes, Bytes>> {
+    let uint = || ParamType::Uint(256);
+    let int = || ParamType::Int(256);
+    let bytes32 = || ParamType::FixedBytes(32);
+    let array = |kind: ParamType| Some(ParamType::Array(Box::new(kind)));
+    Some(match call {
+        HEVMCalls::ParseJson0(inner) => parse_json(&inner.0, &inner.1, None),
+        HEVMCalls::ParseJson1(inner) => parse_json(&inner.0, "$", None),
+        HEVMCalls::ParseJsonUint(inner) => parse_json(&inner.0, &inner.1, Some(uint())),
+        HEVMCalls::ParseJsonUintArray(inner) => parse_json(&inner.0, &inner.1, array(uint())),
+        HEVMCalls::ParseJsonInt(inner) => parse_json(&inner.0, &inner.1, Some(int())),
+        HEVMCalls::ParseJsonIntArray(inner) => parse_json(&inner.0, &inner.1, array(int())),
+        HEVMCalls::ParseJsonBool(inner) => parse_json(&inner.0, &inner.1, Some(ParamType::Bool)),
+        HEVMCalls::ParseJsonBoolArray(inner) => {
+            parse_json(&inner.0, &inner.1, array(ParamType::Bool))
+        }
+        HEVMCalls::ParseJsonAddress(inner) => {
+            parse_json(&inner.0, &inner.1, Some(ParamType::Address))
+        }
+        HEVMCalls::ParseJsonAddressArray(inner) => {
+            parse_json(&inner.0, &inner.1, array(ParamType::Address))
+        }
+        HEVMCalls::ParseJsonBytes32(inner) => parse_json(&inner.0, &inner.1, Some(bytes32())),
+        HEVMCalls::ParseJsonBytes32Array(inner) => parse_json(&inner.0, &inner.1, array(bytes32())),
+        HEVMCalls::ParseJsonString(inner) => {
+            parse_json(&inner.0, &inner.1, Some(ParamType::String))
+        }
+        HEVMCalls::ParseJsonStringArray(inner) => {
+            parse_json(&inner.0, &inner.1, array(ParamType::String))
+        }
+        HEVMCalls::ParseJsonBytes(inner) => parse_json(&inner.0, &inner.1, Some(ParamType::Bytes)),
+        HEVMCalls::ParseJsonBytesArray(inner) => {
+            parse_json(&inner.0, &inner.1, array(ParamType::Bytes))
+        }
+        HEVMCalls::SerializeBool0(inner) => {
+            serialize(state, &inner.0, &inner.1, Value::Bool(inner.2))
+        }
+        HEVMCalls::SerializeBool1(inner) => {
+            serialize(state, &inner.0, &inner.1, array_value(&inner.2, |b| Value::Bool(*b)))
+        }
+        HEVMCalls::SerializeUint0(inner) => {
+            serialize(state, &inner.0, &inner.1, uint_value(inner.2))
+        }
+        HEVMCalls::SerializeUint1(inner) => {
+            serialize(state, &inner.0, &inner.1, array_value(&inner.2, |v| uint_value(*v)))
+        }
+        HEVMCalls::SerializeInt0(inner) => serialize(state, &inner.0, &inner.1, int_value(inner.2)),
+        HEVMCalls::SerializeInt1(inner) => {
+            serialize(state, &inner.0, &inner.1, array_value(&inner.2, |v| int_value(*v)))
+        }
+        HEVMCalls::SerializeAddress0(inner) => {
+            serialize(state, &inner.0, &inner.1, address_value(inner.2))
+        }
+        HEVMCalls::SerializeAddress1(inner) => {
+            serialize(state, &inner.0, &inner.1, array_value(&inner.2, |v| address_value(*v)))
+        }
+        HEVMCalls::SerializeBytes320(inner) => {
+            serialize(state, &inner.0, &inner.1, bytes_value(inner.2))
+        }
+        HEVMCalls::SerializeBytes321(inner) => {
+            serialize(state, &inner.0, &inner.1, array_value(&inner.2, |v| bytes_value(v)))
+        }
+        HEVMCalls::SerializeString0(inner) => {
+            serialize(state, &inner.0, &inner.1, string_value(&inner.2))
+        }
+        HEVMCalls::SerializeString1(inner) => {
+            serialize(state, &inner.0, &inner.1, array_value(&inner.2, |v| string_value(v)))
+        }
+        HEVMCalls::SerializeBytes0(inner) => {
+            serialize(state, &inner.0, &inner.1, bytes_value(&inner.2))
+        }
+        HEVMCalls::SerializeBytes1(inner) => {
+            serialize(state, &inner.0, &inner.1, array_value(&inner.2, |v| bytes_value(v)))
+        }
+        HEVMCalls::WriteJson0(inner) => write_json(state, &inner.0, &inner.1, None),
+        HEVMCalls::WriteJson1(inner) => write_json(state, &inner.0, &inner.1, Some(&inner.2)),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_select_keys() {
+        let json: Value =
+            serde_json::from_str(r#"{"a": {"b": [1, {"c": "0x01"}]}, "with.dot": true}"#).unwrap();
+        assert_eq!(select(&json, "$").unwrap(), &json);
+        assert_eq!(select(&json, ".a.b[0]").unwrap(), &Value::from(1));
+        assert_eq!(select(&json, "$.a.b[1].c").unwrap(), &Value::from("0x01"));
+        assert_eq!(select(&json, "['with.dot']").unwrap(), &Value::Bool(true));
+        assert!(select(&json, ".a.d").is_err());
+        assert!(select(&json, "a").is_err());
+    }
+
+    #[test]
+    fn can_infer_tokens() {
+        let json: Value = serde_json::from_str(
+            r#"{"b": -1, "a": ["0x00a329c0648769A73afAc7F9381E08FB43dBEA72", "text", "0x0102"]}"#,
+        )
+        .unwrap();
+        // objects are tuples of their values in the alphabetical order of their keys
+        assert_eq!(
+            infer_token(&json).unwrap(),
+            Token::Tuple(vec![
+                Token::Array(vec![
+                    Token::Address("0x00a329c0648769A73afAc7F9381E08FB43dBEA72".parse().unwrap()),
+                    Token::String("text".to_string()),
+                    Token::Bytes(vec![1, 2]),
+                ]),
+                Token::Int(I256::from(-1).into_raw()),
+            ])
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use self::{
     env::Broadcast,
-    expect::{handle_expect_emit, handle_expect_revert},
+    expect::{
+        handle_expect_emit, handle_expect_no_revert, handle_expect_revert, handle_expected_call,
+    },
+    state_diff::RecordedAccountAccesses,
     util::process_create,
 };
 use crate::{
@@ -9,6 +12,7 @@ use crate::{
         backend::DatabaseExt, inspector::cheatcodes::env::RecordedLogs, CHEATCODE_ADDRESS,
         HARDHAT_CONSOLE_ADDRESS,
     },
+    utils::u256_to_h256_be,
 };
 use bytes::Bytes;
 use ethers::{
@@ -17,12 +21,15 @@ use ethers::{
         transaction::eip2718::TypedTransaction, Address, NameOrAddress, TransactionRequest, H256,
         U256,
     },
+    utils::keccak256,
 };
+use proptest::test_runner::TestRng;
 use revm::{
-    opcode, BlockEnv, CallInputs, CreateInputs, EVMData, Gas, Inspector, Interpreter, Return,
+    opcode, return_ok, BlockEnv, CallContext, CallInputs, CallScheme, CreateInputs, Database,
+    EVMData, Gas, Inspector, Interpreter, Return, Transfer,
 };
 use std::{
-    collections::{BTreeMap, HashMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     fs::File,
     io::BufReader,
     path::PathBuf,
@@ -31,10 +38,13 @@ use std::{
 
 /// Cheatcodes related to the execution environment.
 mod env;
-pub use env::{Prank, RecordAccess};
+pub use env::{LastCallGas, Prank, RecordAccess, StorageAccess};
 /// Assertion helpers (such as `expectEmit`)
 mod expect;
-pub use expect::{ExpectedCallData, ExpectedEmit, ExpectedRevert, MockCallDataContext};
+pub use expect::{
+    ExpectedCallData, ExpectedCallOrder, ExpectedEmit, ExpectedGasUsed, ExpectedRevert,
+    MockCallDataContext, RevertMatch,
+};
 
 /// Cheatcodes that interact with the external environment (FFI etc.)
 mod ext;
@@ -42,8 +52,13 @@ mod ext;
 mod fork;
 /// Cheatcodes that configure the fuzzer
 mod fuzz;
+/// JSON parsing and serialization cheatcodes
+mod json;
 /// Snapshot related cheatcodes
 mod snapshot;
+/// Account access recording cheatcodes
+mod state_diff;
+pub use state_diff::{AccountAccess, AccountAccessKind, AccountStorageAccess};
 /// Utility cheatcodes (`sign` etc.)
 pub mod util;
 pub use util::{DEFAULT_CREATE2_DEPLOYER, MISSING_CREATE2_DEPLOYER};
@@ -72,27 +87,62 @@ pub struct Cheatcodes {
     /// Address labels
     pub labels: BTreeMap<Address, String>,
 
-    /// Prank information
-    pub prank: Option<Prank>,
+    /// The stack of active pranks
+    ///
+    /// Pranks started from different call frames (e.g. by a helper contract while the test
+    /// contract has an active prank) and prank scopes are nested, the most recent matching prank
+    /// is applied.
+    pub pranks: Vec<Prank>,
+
+    /// The number of prank scopes started so far, the id of the most recent scope
+    pub prank_scopes: u64,
 
     /// Expected revert information
     pub expected_revert: Option<ExpectedRevert>,
 
+    /// The depth of the next call that is expected not to revert
+    pub expected_no_revert: Option<u64>,
+
+    /// The revert data of a call that reverted although it was expected not to, which fails the
+    /// test even if the revert was caught
+    pub unexpected_revert: Option<Bytes>,
+
+    /// The maximum amount of gas the next call is expected to use
+    pub expected_gas_used: Option<ExpectedGasUsed>,
+
+    /// The gas of the most recent call, see `lastCallGas`
+    pub last_call_gas: Option<LastCallGas>,
+
     /// Recorded storage reads and writes
     pub accesses: Option<RecordAccess>,
 
     /// Recorded logs
     pub recorded_logs: Option<RecordedLogs>,
 
+    /// Recorded account accesses, see `startStateDiffRecording`
+    pub recorded_account_diffs: Option<RecordedAccountAccesses>,
+
     /// Mocked calls
     pub mocked_calls: BTreeMap<Address, BTreeMap<MockCallDataContext, Bytes>>,
 
+    /// Addresses where calls that are not mocked revert
+    pub strict_mocks: BTreeSet<Address>,
+
     /// Expected calls
     pub expected_calls: BTreeMap<Address, Vec<ExpectedCallData>>,
 
+    /// The order of the expected calls, if they are expected in order
+    pub expected_call_order: Option<ExpectedCallOrder>,
+
+    /// Calls made to addresses with expected calls, used to report unfulfilled expectations
+    pub observed_calls: BTreeMap<Address, Vec<(Bytes, U256)>>,
+
     /// Expected emits
     pub expected_emits: Vec<ExpectedEmit>,
 
+    /// Emits that are expected not to occur
+    pub unexpected_emits: Vec<ExpectedEmit>,
+
     /// Current broadcasting information
     pub broadcast: Option<Broadcast>,
 
@@ -102,6 +152,15 @@ pub struct Cheatcodes {
     /// Scripting based transactions
     pub broadcastable_transactions: VecDeque<TypedTransaction>,
 
+    /// The rng of the `random*` cheatcodes, created on first use
+    pub rng: Option<TestRng>,
+
+    /// The number of `vm.assume` calls so far, which identifies the call that rejects an input
+    pub assume_calls: u64,
+
+    /// The JSON objects built with the `serialize*` cheatcodes, by their object keys
+    pub serialized_jsons: BTreeMap<String, serde_json::Map<String, serde_json::Value>>,
+
     /// Additional, user configurable context this Inspector has access to when inspecting a call
     pub config: Arc<CheatsConfig>,
 
@@ -124,8 +183,21 @@ impl Clone for Context {
 
 impl Cheatcodes {
     pub fn new(block: BlockEnv, gas_price: U256, config: CheatsConfig) -> Self {
+        // the predeploys of the emulated chain are labeled in traces
+        let labels = config
+            .evm_opts
+            .chain_profile
+            .map(|profile| {
+                profile
+                    .predeploys()
+                    .into_iter()
+                    .map(|(address, name)| (address, name.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
         Self {
             corrected_nonce: false,
+            labels,
             block: Some(block),
             gas_price: Some(gas_price),
             config: Arc::new(config),
@@ -133,6 +205,22 @@ impl Cheatcodes {
         }
     }
 
+    /// Returns the most recent prank that applies to a call made by `caller` at the given depth
+    fn active_prank(&self, depth: u64, caller: Address) -> Option<&Prank> {
+        self.pranks.iter().rev().find(|prank| depth >= prank.depth && caller == prank.prank_caller)
+    }
+
+    /// Restores `tx.origin` for all pranks started at the given depth and removes single call
+    /// pranks
+    fn clean_up_pranks(&mut self, depth: u64, tx_caller: &mut Address) {
+        for prank in self.pranks.iter().rev() {
+            if depth == prank.depth {
+                *tx_caller = prank.prank_origin;
+            }
+        }
+        self.pranks.retain(|prank| !prank.single_call);
+    }
+
     fn apply_cheatcode<DB: DatabaseExt>(
         &mut self,
         data: &mut EVMData<'_, DB>,
@@ -146,14 +234,42 @@ impl Cheatcodes {
         env::apply(self, data, caller, &decoded)
             .or_else(|| util::apply(self, data, &decoded))
             .or_else(|| expect::apply(self, data, &decoded))
-            .or_else(|| fuzz::apply(data, &decoded))
+            .or_else(|| fuzz::apply(self, data, &decoded))
             .or_else(|| ext::apply(self, self.config.ffi, &decoded))
+            .or_else(|| json::apply(self, &decoded))
             .or_else(|| snapshot::apply(self, data, &decoded))
             .or_else(|| fork::apply(self, data, &decoded))
+            .or_else(|| state_diff::apply(self, &decoded))
             .ok_or_else(|| "Cheatcode was unhandled. This is a bug.".to_string().encode())?
     }
 }
 
+/// Turns the call to the cheatcode address into a delegatecall of the caller to the deployer of
+/// `deployCode`, with the `init_code` as calldata
+fn delegate_to_code_deployer<DB: Database>(
+    data: &mut EVMData<'_, DB>,
+    call: &mut CallInputs,
+    init_code: Bytes,
+) {
+    let deployer = ext::CODE_DEPLOYER_ADDRESS;
+    let code = ext::CODE_DEPLOYER.clone();
+    let hash = H256::from_slice(&keccak256(&code));
+    data.subroutine.load_account(deployer, data.db);
+    data.subroutine.set_code(deployer, code, hash);
+
+    let caller = call.context.caller;
+    call.contract = deployer;
+    call.input = init_code;
+    call.transfer = Transfer { source: caller, target: caller, value: U256::zero() };
+    call.context = CallContext {
+        address: caller,
+        caller,
+        code_address: deployer,
+        apparent_value: U256::zero(),
+        scheme: CallScheme::DelegateCall,
+    };
+}
+
 impl<DB> Inspector<DB> for Cheatcodes
 where
     DB: DatabaseExt,
@@ -177,8 +293,8 @@ where
     }
 
     fn log(&mut self, _: &mut EVMData<'_, DB>, address: &Address, topics: &[H256], data: &Bytes) {
-        // Match logs if `expectEmit` has been called
-        if !self.expected_emits.is_empty() {
+        // Match logs if `expectEmit` or `expectNoEmit` has been called
+        if !self.expected_emits.is_empty() || !self.unexpected_emits.is_empty() {
             handle_expect_emit(
                 self,
                 RawLog { topics: topics.to_vec(), data: data.to_vec() },
@@ -201,6 +317,21 @@ where
         is_static: bool,
     ) -> (Return, Gas, Bytes) {
         if call.contract == CHEATCODE_ADDRESS {
+            // `deployCode` is turned into a delegatecall of the caller to a deployer, so the
+            // contract is created by the caller through the standard create path
+            if let Some(init_code) = HEVMCalls::decode(&call.input)
+                .ok()
+                .and_then(|decoded| ext::deploy_code_init_code(&decoded))
+            {
+                return match init_code {
+                    Ok(init_code) => {
+                        delegate_to_code_deployer(data, call, init_code);
+                        (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
+                    }
+                    Err(err) => (Return::Revert, Gas::new(call.gas_limit), err),
+                }
+            }
+
             match self.apply_cheatcode(data, call.context.caller, call) {
                 Ok(retdata) => (Return::Return, Gas::new(call.gas_limit), retdata),
                 Err(err) => (Return::Revert, Gas::new(call.gas_limit), err),
@@ -208,13 +339,17 @@ where
         } else if call.contract != HARDHAT_CONSOLE_ADDRESS {
             // Handle expected calls
             if let Some(expecteds) = self.expected_calls.get_mut(&call.contract) {
-                if let Some(found_match) = expecteds.iter().position(|expected| {
-                    expected.calldata.len() <= call.input.len() &&
-                        expected.calldata == call.input[..expected.calldata.len()] &&
-                        expected.value.map(|value| value == call.transfer.value).unwrap_or(true)
-                }) {
-                    expecteds.remove(found_match);
-                }
+                self.observed_calls
+                    .entry(call.contract)
+                    .or_default()
+                    .push((call.input.clone(), call.transfer.value));
+                handle_expected_call(
+                    expecteds,
+                    self.expected_call_order.as_mut(),
+                    call.contract,
+                    &call.input,
+                    call.transfer.value,
+                );
             }
 
             // Handle mocked calls
@@ -234,24 +369,51 @@ where
                 }
             }
 
+            // Calls to strict mocks that are not mocked revert
+            if self.strict_mocks.contains(&call.contract) {
+                let selector = call.input.get(..4).unwrap_or(&call.input);
+                return (
+                    Return::Revert,
+                    Gas::new(call.gas_limit),
+                    format!(
+                        "Unexpected call to strict mock {:?} with selector 0x{} and data {}",
+                        call.contract,
+                        hex::encode(selector),
+                        ethers::types::Bytes::from(call.input.clone())
+                    )
+                    .encode()
+                    .into(),
+                )
+            }
+
             // Apply our prank
-            if let Some(prank) = &self.prank {
-                if data.subroutine.depth() >= prank.depth &&
-                    call.context.caller == prank.prank_caller
-                {
-                    // At the target depth we set `msg.sender`
-                    if data.subroutine.depth() == prank.depth {
-                        call.context.caller = prank.new_caller;
-                        call.transfer.source = prank.new_caller;
-                    }
+            if let Some(prank) = self.active_prank(data.subroutine.depth(), call.context.caller) {
+                // At the target depth we set `msg.sender`
+                if data.subroutine.depth() == prank.depth {
+                    call.context.caller = prank.new_caller;
+                    call.transfer.source = prank.new_caller;
+                }
 
-                    // At the target depth, or deeper, we set `tx.origin`
-                    if let Some(new_origin) = prank.new_origin {
-                        data.env.tx.caller = new_origin;
-                    }
+                // At the target depth, or deeper, we set `tx.origin`
+                if let Some(new_origin) = prank.new_origin {
+                    data.env.tx.caller = new_origin;
                 }
             }
 
+            // Record the call if `startStateDiffRecording` has been called, mocked calls are not
+            // executed and not recorded
+            if let Some(recorded) = &mut self.recorded_account_diffs {
+                let mut access = AccountAccess::new(
+                    data,
+                    call.context.scheme.into(),
+                    call.contract,
+                    call.context.caller,
+                );
+                access.value = call.transfer.value;
+                access.data = call.input.clone();
+                recorded.start_frame(access);
+            }
+
             // Apply our broadcast
             if let Some(broadcast) = &self.broadcast {
                 // We only apply a broadcast *to a specific depth*.
@@ -306,12 +468,25 @@ where
         }
     }
 
-    fn step(&mut self, interpreter: &mut Interpreter, _: &mut EVMData<'_, DB>, _: bool) -> Return {
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _: bool,
+    ) -> Return {
         // Record writes and reads if `record` has been called
         if let Some(storage_accesses) = &mut self.accesses {
+            let access = |slot, is_write| StorageAccess {
+                account: interpreter.contract().address,
+                caller: interpreter.contract().caller,
+                slot,
+                is_write,
+                depth: data.subroutine.depth(),
+            };
             match interpreter.contract.code[interpreter.program_counter()] {
                 opcode::SLOAD => {
                     let key = try_or_continue!(interpreter.stack().peek(0));
+                    storage_accesses.entries.push(access(key, false));
                     storage_accesses
                         .reads
                         .entry(interpreter.contract().address)
@@ -320,6 +495,7 @@ where
                 }
                 opcode::SSTORE => {
                     let key = try_or_continue!(interpreter.stack().peek(0));
+                    storage_accesses.entries.push(access(key, true));
 
                     // An SSTORE does an SLOAD internally
                     storage_accesses
@@ -337,6 +513,49 @@ where
             }
         }
 
+        // Record account and storage accesses if `startStateDiffRecording` has been called
+        if let Some(recorded) = &mut self.recorded_account_diffs {
+            let address = interpreter.contract().address;
+            let op = interpreter.contract.code[interpreter.program_counter()];
+            let kind = match op {
+                opcode::SLOAD | opcode::SSTORE => None,
+                opcode::BALANCE => Some(AccountAccessKind::Balance),
+                opcode::EXTCODESIZE => Some(AccountAccessKind::Extcodesize),
+                opcode::EXTCODEHASH => Some(AccountAccessKind::Extcodehash),
+                opcode::EXTCODECOPY => Some(AccountAccessKind::Extcodecopy),
+                opcode::SELFDESTRUCT => Some(AccountAccessKind::SelfDestruct),
+                _ => return Return::Continue,
+            };
+            match kind {
+                None => {
+                    let slot = try_or_continue!(interpreter.stack().peek(0));
+                    let new_value = if op == opcode::SSTORE {
+                        Some(try_or_continue!(interpreter.stack().peek(1)))
+                    } else {
+                        None
+                    };
+                    let access = AccountStorageAccess::new(data, address, slot, new_value);
+                    recorded.record_storage(
+                        access,
+                        interpreter.contract().caller,
+                        data.subroutine.depth(),
+                    );
+                }
+                Some(AccountAccessKind::SelfDestruct) => {
+                    let mut access =
+                        AccountAccess::new(data, AccountAccessKind::SelfDestruct, address, address);
+                    access.value = access.old_balance;
+                    access.new_balance = U256::zero();
+                    recorded.record(access);
+                }
+                Some(kind) => {
+                    let account = try_or_continue!(interpreter.stack().peek(0));
+                    let account = Address::from(u256_to_h256_be(account));
+                    recorded.record(AccountAccess::new(data, kind, account, address));
+                }
+            }
+        }
+
         Return::Continue
     }
 
@@ -353,16 +572,24 @@ where
             return (status, remaining_gas, retdata)
         }
 
-        // Clean up pranks
-        if let Some(prank) = &self.prank {
-            if data.subroutine.depth() == prank.depth {
-                data.env.tx.caller = prank.prank_origin;
-            }
-            if prank.single_call {
-                std::mem::take(&mut self.prank);
-            }
+        // The calls of a call end before it, so the gas of the outermost call that ended last is
+        // recorded. The top-level call, e.g. `setUp`, is not a call made by the test
+        if data.subroutine.depth() > 0 {
+            self.last_call_gas = Some(LastCallGas::new(&remaining_gas));
+        }
+
+        // Record the end of the call if `startStateDiffRecording` has been called
+        if let Some(recorded) = &mut self.recorded_account_diffs {
+            let new_balance = state_diff::account_info(data, call.contract).balance;
+            let reverted = !matches!(status, return_ok!());
+            recorded.end_frame(data.subroutine.depth(), reverted, |access| {
+                access.new_balance = new_balance;
+            });
         }
 
+        // Clean up pranks
+        self.clean_up_pranks(data.subroutine.depth(), &mut data.env.tx.caller);
+
         // Clean up broadcast
         if let Some(broadcast) = &self.broadcast {
             if broadcast.single_call {
@@ -370,11 +597,33 @@ where
             }
         }
 
+        // Handle expected gas usage
+        if let Some(expected_gas_used) = &self.expected_gas_used {
+            if data.subroutine.depth() <= expected_gas_used.depth {
+                let expected_gas_used = self.expected_gas_used.take().unwrap();
+                if let Some(retdata) = expected_gas_used.check(data.env.cfg.spec_id, &remaining_gas)
+                {
+                    return (Return::Revert, remaining_gas, retdata)
+                }
+            }
+        }
+
+        // Handle calls expected not to revert
+        if let Some(depth) = self.expected_no_revert {
+            if data.subroutine.depth() <= depth {
+                self.expected_no_revert = None;
+                if let Some(retdata) = handle_expect_no_revert(status, &retdata) {
+                    self.unexpected_revert = Some(retdata.clone());
+                    return (Return::Revert, remaining_gas, retdata)
+                }
+            }
+        }
+
         // Handle expected reverts
         if let Some(expected_revert) = &self.expected_revert {
             if data.subroutine.depth() <= expected_revert.depth {
                 let expected_revert = std::mem::take(&mut self.expected_revert).unwrap();
-                return match handle_expect_revert(false, &expected_revert.reason, status, retdata) {
+                return match handle_expect_revert(false, &expected_revert, status, retdata) {
                     Err(retdata) => (Return::Revert, remaining_gas, retdata),
                     Ok((_, retdata)) => (Return::Return, remaining_gas, retdata),
                 }
@@ -398,25 +647,47 @@ where
             self.expected_emits.retain(|expected| !expected.found)
         }
 
+        // Handle emits expected not to occur at current depth, which are done with this call
+        if self
+            .unexpected_emits
+            .iter()
+            .any(|unexpected| unexpected.depth == data.subroutine.depth() && unexpected.found)
+        {
+            self.unexpected_emits.clear();
+            return (
+                Return::Revert,
+                remaining_gas,
+                "Emitted a log that was expected not to be emitted".to_string().encode().into(),
+            )
+        }
+        self.unexpected_emits.retain(|unexpected| unexpected.depth != data.subroutine.depth());
+
         // If the depth is 0, then this is the root call terminating
         if data.subroutine.depth() == 0 {
-            // Handle expected calls that were not fulfilled
-            if let Some((address, expecteds)) =
-                self.expected_calls.iter().find(|(_, expecteds)| !expecteds.is_empty())
+            // Handle expected calls that were not fulfilled or made out of order
+            let expected_calls = std::mem::take(&mut self.expected_calls);
+            let observed_calls = std::mem::take(&mut self.observed_calls);
+            let expected_call_order = self.expected_call_order.take();
+            if let Some((address, expected)) =
+                expected_calls.iter().find_map(|(address, expecteds)| {
+                    expecteds.iter().find(|expected| !expected.is_fulfilled()).map(|e| (address, e))
+                })
             {
+                let observed = observed_calls.get(address).map(Vec::as_slice).unwrap_or_default();
                 return (
                     Return::Revert,
                     remaining_gas,
-                    format!(
-                        "Expected a call to {:?} with data {}{}, but got none",
-                        address,
-                        ethers::types::Bytes::from(expecteds[0].calldata.clone()),
-                        expecteds[0].value.map(|v| format!(" and value {}", v)).unwrap_or_default()
-                    )
-                    .encode()
-                    .into(),
+                    expected.error(*address, observed).encode().into(),
                 )
             }
+            if let Some(violation) = expected_call_order.and_then(|order| order.violation) {
+                return (Return::Revert, remaining_gas, violation.encode().into())
+            }
+
+            // Fail the test if a call expected not to revert reverted, even if it was caught
+            if let Some(retdata) = self.unexpected_revert.take() {
+                return (Return::Revert, remaining_gas, retdata)
+            }
 
             // Check if we have any leftover expected emits
             if !self.expected_emits.is_empty() {
@@ -440,20 +711,30 @@ where
         call: &mut CreateInputs,
     ) -> (Return, Option<Address>, Gas, Bytes) {
         // Apply our prank
-        if let Some(prank) = &self.prank {
-            if data.subroutine.depth() >= prank.depth && call.caller == prank.prank_caller {
-                // At the target depth we set `msg.sender`
-                if data.subroutine.depth() == prank.depth {
-                    call.caller = prank.new_caller;
-                }
+        if let Some(prank) = self.active_prank(data.subroutine.depth(), call.caller) {
+            // At the target depth we set `msg.sender`
+            if data.subroutine.depth() == prank.depth {
+                call.caller = prank.new_caller;
+            }
 
-                // At the target depth, or deeper, we set `tx.origin`
-                if let Some(new_origin) = prank.new_origin {
-                    data.env.tx.caller = new_origin;
-                }
+            // At the target depth, or deeper, we set `tx.origin`
+            if let Some(new_origin) = prank.new_origin {
+                data.env.tx.caller = new_origin;
             }
         }
 
+        // Record the create if `startStateDiffRecording` has been called, the address of the
+        // created account is set when the create ends
+        if let Some(recorded) = &mut self.recorded_account_diffs {
+            let mut access =
+                AccountAccess::new(data, AccountAccessKind::Create, Address::zero(), call.caller);
+            access.initialized = false;
+            access.old_balance = U256::zero();
+            access.value = call.value;
+            access.data = call.init_code.clone();
+            recorded.start_frame(access);
+        }
+
         // Apply our broadcast
         if let Some(broadcast) = &self.broadcast {
             if data.subroutine.depth() == broadcast.depth &&
@@ -489,16 +770,22 @@ where
         remaining_gas: Gas,
         retdata: Bytes,
     ) -> (Return, Option<Address>, Gas, Bytes) {
-        // Clean up pranks
-        if let Some(prank) = &self.prank {
-            if data.subroutine.depth() == prank.depth {
-                data.env.tx.caller = prank.prank_origin;
-            }
-            if prank.single_call {
-                std::mem::take(&mut self.prank);
-            }
+        // Record the end of the create if `startStateDiffRecording` has been called
+        if let Some(recorded) = &mut self.recorded_account_diffs {
+            let info = address.map(|address| state_diff::account_info(data, address));
+            let reverted = !matches!(status, return_ok!());
+            recorded.end_frame(data.subroutine.depth(), reverted, |access| {
+                if let (Some(address), Some(info)) = (address, info) {
+                    access.account = address;
+                    access.new_balance = info.balance;
+                    access.deployed_code = info.code.unwrap_or_default();
+                }
+            });
         }
 
+        // Clean up pranks
+        self.clean_up_pranks(data.subroutine.depth(), &mut data.env.tx.caller);
+
         // Clean up broadcasts
         if let Some(broadcast) = &self.broadcast {
             if broadcast.single_call {
@@ -506,11 +793,33 @@ where
             }
         }
 
+        // Handle expected gas usage
+        if let Some(expected_gas_used) = &self.expected_gas_used {
+            if data.subroutine.depth() <= expected_gas_used.depth {
+                let expected_gas_used = self.expected_gas_used.take().unwrap();
+                if let Some(retdata) = expected_gas_used.check(data.env.cfg.spec_id, &remaining_gas)
+                {
+                    return (Return::Revert, None, remaining_gas, retdata)
+                }
+            }
+        }
+
+        // Handle creates expected not to revert
+        if let Some(depth) = self.expected_no_revert {
+            if data.subroutine.depth() <= depth {
+                self.expected_no_revert = None;
+                if let Some(retdata) = handle_expect_no_revert(status, &retdata) {
+                    self.unexpected_revert = Some(retdata.clone());
+                    return (Return::Revert, None, remaining_gas, retdata)
+                }
+            }
+        }
+
         // Handle expected reverts
         if let Some(expected_revert) = &self.expected_revert {
             if data.subroutine.depth() <= expected_revert.depth {
                 let expected_revert = std::mem::take(&mut self.expected_revert).unwrap();
-                return match handle_expect_revert(true, &expected_revert.reason, status, retdata) {
+                return match handle_expect_revert(true, &expected_revert, status, retdata) {
                     Err(retdata) => (Return::Revert, None, remaining_gas, retdata),
                     Ok((address, retdata)) => (Return::Return, address, remaining_gas, retdata),
                 }
@@ -0,0 +1,353 @@
+use super::Cheatcodes;
+use crate::{abi::HEVMCalls, utils::u256_to_h256_be};
+use bytes::Bytes;
+use ethers::{
+    abi::{self, Token},
+    types::{Address, U256},
+};
+use revm::{AccountInfo, CallScheme, Database, EVMData, KECCAK_EMPTY};
+
+/// The kind of an account access, in the order of the `AccountAccessKind` enum of the cheatcodes
+/// interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountAccessKind {
+    Call,
+    DelegateCall,
+    CallCode,
+    StaticCall,
+    Create,
+    SelfDestruct,
+    /// The storage accesses of a call frame that started before the recording
+    Resume,
+    Balance,
+    Extcodesize,
+    Extcodehash,
+    Extcodecopy,
+}
+
+impl From<CallScheme> for AccountAccessKind {
+    fn from(scheme: CallScheme) -> Self {
+        match scheme {
+            CallScheme::Call => AccountAccessKind::Call,
+            CallScheme::CallCode => AccountAccessKind::CallCode,
+            CallScheme::DelegateCall => AccountAccessKind::DelegateCall,
+            CallScheme::StaticCall => AccountAccessKind::StaticCall,
+        }
+    }
+}
+
+/// An access of an account, along with the storage accesses of the account's code if it's a call
+#[derive(Clone, Debug)]
+pub struct AccountAccess {
+    pub kind: AccountAccessKind,
+    /// The accessed account
+    pub account: Address,
+    /// The account that accessed it, e.g. the caller of a call
+    pub accessor: Address,
+    /// Whether the account existed, i.e. had a nonce, balance or code, before the access
+    pub initialized: bool,
+    /// The balance of the account before the access
+    pub old_balance: U256,
+    /// The balance of the account after the access
+    pub new_balance: U256,
+    /// The code of a created account
+    pub deployed_code: Bytes,
+    /// The value sent along with a call or create
+    pub value: U256,
+    /// The calldata of a call or the init code of a create
+    pub data: Bytes,
+    /// Whether the access was reverted, either by the access itself or by a call it was made in
+    pub reverted: bool,
+    /// The storage accesses made by the account's code
+    pub storage_accesses: Vec<AccountStorageAccess>,
+    /// The depth of the call frame the access was made in
+    pub depth: u64,
+}
+
+impl AccountAccess {
+    /// Returns an access of the `account` by the `accessor` in the current call frame
+    pub fn new<DB: Database>(
+        data: &mut EVMData<'_, DB>,
+        kind: AccountAccessKind,
+        account: Address,
+        accessor: Address,
+    ) -> Self {
+        let info = account_info(data, account);
+        Self {
+            kind,
+            account,
+            accessor,
+            initialized: info.nonce != 0 ||
+                !info.balance.is_zero() ||
+                info.code_hash != KECCAK_EMPTY,
+            old_balance: info.balance,
+            new_balance: info.balance,
+            deployed_code: Bytes::new(),
+            value: U256::zero(),
+            data: Bytes::new(),
+            reverted: false,
+            storage_accesses: Vec::new(),
+            depth: data.subroutine.depth(),
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Tuple(vec![
+            Token::Uint((self.kind as u8).into()),
+            Token::Address(self.account),
+            Token::Address(self.accessor),
+            Token::Bool(self.initialized),
+            Token::Uint(self.old_balance),
+            Token::Uint(self.new_balance),
+            Token::Bytes(self.deployed_code.to_vec()),
+            Token::Uint(self.value),
+            Token::Bytes(self.data.to_vec()),
+            Token::Bool(self.reverted),
+            Token::Array(
+                self.storage_accesses.into_iter().map(AccountStorageAccess::into_token).collect(),
+            ),
+            Token::Uint(self.depth.into()),
+        ])
+    }
+}
+
+/// A storage read or write
+#[derive(Clone, Debug)]
+pub struct AccountStorageAccess {
+    /// The account whose storage was accessed
+    pub account: Address,
+    pub slot: U256,
+    /// Whether the access was an `SSTORE`
+    pub is_write: bool,
+    /// The value of the slot before the access
+    pub previous_value: U256,
+    /// The value of the slot after the access
+    pub new_value: U256,
+    /// Whether the access was reverted by a call it was made in
+    pub reverted: bool,
+}
+
+impl AccountStorageAccess {
+    /// Returns an access of the `slot` of the `account`, which writes `new_value` if any
+    pub fn new<DB: Database>(
+        data: &mut EVMData<'_, DB>,
+        account: Address,
+        slot: U256,
+        new_value: Option<U256>,
+    ) -> Self {
+        let previous_value = data
+            .subroutine
+            .state()
+            .get(&account)
+            .and_then(|acc| acc.storage.get(&slot).copied())
+            .unwrap_or_else(|| data.db.storage(account, slot));
+        Self {
+            account,
+            slot,
+            is_write: new_value.is_some(),
+            previous_value,
+            new_value: new_value.unwrap_or(previous_value),
+            reverted: false,
+        }
+    }
+
+    fn into_token(self) -> Token {
+        Token::Tuple(vec![
+            Token::Address(self.account),
+            Token::FixedBytes(u256_to_h256_be(self.slot).as_bytes().to_vec()),
+            Token::Bool(self.is_write),
+            Token::FixedBytes(u256_to_h256_be(self.previous_value).as_bytes().to_vec()),
+            Token::FixedBytes(u256_to_h256_be(self.new_value).as_bytes().to_vec()),
+            Token::Bool(self.reverted),
+        ])
+    }
+}
+
+/// Returns the info of the `address` without loading the account, which would make later
+/// accesses of the account warm
+pub fn account_info<DB: Database>(data: &mut EVMData<'_, DB>, address: Address) -> AccountInfo {
+    data.subroutine
+        .state()
+        .get(&address)
+        .map(|acc| acc.info.clone())
+        .unwrap_or_else(|| data.db.basic(address))
+}
+
+/// The account accesses recorded since `startStateDiffRecording`, in execution order
+#[derive(Clone, Debug)]
+pub struct RecordedAccountAccesses {
+    /// The accesses of the calls and creates that have not ended yet, along with their depths.
+    ///
+    /// The first access of every frame is the call or create itself. The first frame holds the
+    /// accesses of the call frames that started before the recording and has no depth.
+    frames: Vec<(Option<u64>, Vec<AccountAccess>)>,
+}
+
+impl Default for RecordedAccountAccesses {
+    fn default() -> Self {
+        Self { frames: vec![(None, Vec::new())] }
+    }
+}
+
+impl RecordedAccountAccesses {
+    /// Records the start of a call or create
+    pub fn start_frame(&mut self, access: AccountAccess) {
+        self.frames.push((Some(access.depth), vec![access]));
+    }
+
+    /// Records the end of the call or create at the given depth, if its start was recorded.
+    ///
+    /// The accesses of a reverted frame are all reverted.
+    pub fn end_frame(
+        &mut self,
+        depth: u64,
+        reverted: bool,
+        update: impl FnOnce(&mut AccountAccess),
+    ) {
+        if self.frames.len() < 2 || self.frames.last().map(|(d, _)| *d) != Some(Some(depth)) {
+            return
+        }
+        let (_, mut accesses) = self.frames.pop().unwrap();
+        update(&mut accesses[0]);
+        if reverted {
+            for access in accesses.iter_mut() {
+                access.reverted = true;
+                access.storage_accesses.iter_mut().for_each(|access| access.reverted = true);
+            }
+        }
+        self.frames.last_mut().unwrap().1.extend(accesses);
+    }
+
+    /// Records an access that is not a call or create, e.g. a `BALANCE`
+    pub fn record(&mut self, access: AccountAccess) {
+        self.frames.last_mut().unwrap().1.push(access);
+    }
+
+    /// Records a storage access made by the code of the `accessor`
+    pub fn record_storage(&mut self, access: AccountStorageAccess, accessor: Address, depth: u64) {
+        let (frame_depth, accesses) = self.frames.last_mut().unwrap();
+        if frame_depth.is_some() {
+            accesses[0].storage_accesses.push(access);
+            return
+        }
+
+        // the storage accesses of a frame that started before the recording are grouped by the
+        // accessed account, as long as no other account is accessed in between
+        match accesses.last_mut() {
+            Some(resume)
+                if resume.kind == AccountAccessKind::Resume && resume.account == access.account =>
+            {
+                resume.storage_accesses.push(access)
+            }
+            _ => accesses.push(AccountAccess {
+                kind: AccountAccessKind::Resume,
+                account: access.account,
+                accessor,
+                initialized: true,
+                old_balance: U256::zero(),
+                new_balance: U256::zero(),
+                deployed_code: Bytes::new(),
+                value: U256::zero(),
+                data: Bytes::new(),
+                reverted: false,
+                storage_accesses: vec![access],
+                depth,
+            }),
+        }
+    }
+
+    /// Returns all recorded accesses in execution order, including the ones of the calls that
+    /// have not ended yet
+    pub fn finish(mut self) -> Vec<AccountAccess> {
+        while self.frames.len() > 1 {
+            let (_, accesses) = self.frames.pop().unwrap();
+            self.frames.last_mut().unwrap().1.extend(accesses);
+        }
+        self.frames.pop().map(|(_, accesses)| accesses).unwrap_or_default()
+    }
+}
+
+/// Handles the account access recording cheatcodes
+pub fn apply(state: &mut Cheatcodes, call: &HEVMCalls) -> Option<Result<Bytes, Bytes>> {
+    Some(match call {
+        HEVMCalls::StartStateDiffRecording(_) => {
+            state.recorded_account_diffs = Some(Default::default());
+            Ok(Bytes::new())
+        }
+        HEVMCalls::StopAndReturnStateDiff(_) => {
+            let accesses =
+                state.recorded_account_diffs.take().map(|diffs| diffs.finish()).unwrap_or_default();
+            Ok(abi::encode(&[Token::Array(
+                accesses.into_iter().map(AccountAccess::into_token).collect(),
+            )])
+            .into())
+        }
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access(kind: AccountAccessKind, account: u64, depth: u64) -> AccountAccess {
+        AccountAccess {
+            kind,
+            account: Address::from_low_u64_be(account),
+            accessor: Address::zero(),
+            initialized: true,
+            old_balance: U256::zero(),
+            new_balance: U256::zero(),
+            deployed_code: Bytes::new(),
+            value: U256::zero(),
+            data: Bytes::new(),
+            reverted: false,
+            storage_accesses: Vec::new(),
+            depth,
+        }
+    }
+
+    fn storage_access(account: u64) -> AccountStorageAccess {
+        AccountStorageAccess {
+            account: Address::from_low_u64_be(account),
+            slot: U256::zero(),
+            is_write: true,
+            previous_value: U256::zero(),
+            new_value: U256::one(),
+            reverted: false,
+        }
+    }
+
+    #[test]
+    fn records_accesses_in_execution_order() {
+        let mut recorded = RecordedAccountAccesses::default();
+        recorded.record_storage(storage_access(1), Address::zero(), 1);
+        recorded.start_frame(access(AccountAccessKind::Call, 2, 1));
+        recorded.start_frame(access(AccountAccessKind::Create, 3, 2));
+        recorded.record_storage(storage_access(3), Address::zero(), 3);
+        recorded.end_frame(2, false, |access| access.deployed_code = vec![0x00].into());
+        recorded.record(access(AccountAccessKind::Balance, 4, 2));
+        recorded.end_frame(1, true, |_| ());
+        // the end of a frame that started before the recording is ignored
+        recorded.end_frame(0, true, |_| ());
+        recorded.record_storage(storage_access(1), Address::zero(), 1);
+
+        let accesses = recorded.finish();
+        let kinds = accesses.iter().map(|access| access.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                AccountAccessKind::Resume,
+                AccountAccessKind::Call,
+                AccountAccessKind::Create,
+                AccountAccessKind::Balance,
+                AccountAccessKind::Resume,
+            ]
+        );
+        assert!(!accesses[0].reverted);
+        assert!(accesses[1..4].iter().all(|access| access.reverted));
+        assert!(accesses[2].storage_accesses[0].reverted);
+        assert_eq!(accesses[2].deployed_code.as_ref(), &[0x00]);
+        assert!(!accesses[4].reverted);
+    }
+}
@@ -2,19 +2,23 @@ use super::Cheatcodes;
 use crate::abi::HEVMCalls;
 use bytes::{BufMut, Bytes, BytesMut};
 use ethers::{
-    abi::{AbiEncode, Address, Token},
+    abi::{self, AbiEncode, Address, ParamType, Token},
     core::k256::elliptic_curve::Curve,
     prelude::{
         k256::{ecdsa::SigningKey, elliptic_curve::bigint::Encoding, Secp256k1},
         Lazy, LocalWallet, Signer, H160,
     },
     signers::{coins_bip39::English, MnemonicBuilder},
-    types::{NameOrAddress, H256, U256},
+    types::{
+        transaction::eip712::{Eip712, TypedData},
+        NameOrAddress, H256, I256, U256,
+    },
     utils,
     utils::keccak256,
 };
 use foundry_common::fmt::*;
 use revm::{CreateInputs, Database, EVMData};
+use std::str::FromStr;
 
 const DEFAULT_DERIVATION_PATH_PREFIX: &str = "m/44'/60'/0'/0/";
 
@@ -29,6 +33,11 @@ pub static REVERT_PREFIX: [u8; 4] = [8, 195, 121, 160];
 pub static ERROR_PREFIX: Lazy<[u8; 32]> = Lazy::new(|| keccak256("CheatCodeError"));
 
 fn addr(private_key: U256) -> Result<Bytes, Bytes> {
+    Ok(private_key_to_address(private_key)?.encode().into())
+}
+
+/// Returns the address of the private key
+pub fn private_key_to_address(private_key: U256) -> Result<Address, Bytes> {
     if private_key.is_zero() {
         return Err("Private key cannot be 0.".to_string().encode().into())
     }
@@ -41,8 +50,7 @@ fn addr(private_key: U256) -> Result<Bytes, Bytes> {
     private_key.to_big_endian(&mut bytes);
 
     let key = SigningKey::from_bytes(&bytes).map_err(|err| err.to_string().encode())?;
-    let addr = utils::secret_key_to_address(&key);
-    Ok(addr.encode().into())
+    Ok(utils::secret_key_to_address(&key))
 }
 
 fn sign(private_key: U256, digest: H256, chain_id: U256) -> Result<Bytes, Bytes> {
@@ -74,6 +82,14 @@ fn sign(private_key: U256, digest: H256, chain_id: U256) -> Result<Bytes, Bytes>
     Ok((sig.v, r_bytes, s_bytes).encode().into())
 }
 
+/// Signs the EIP-712 hash of the typed data, given in the JSON format of `eth_signTypedData_v4`
+fn sign_typed_data(private_key: U256, typed_data: &str, chain_id: U256) -> Result<Bytes, Bytes> {
+    let typed_data: TypedData = serde_json::from_str(typed_data)
+        .map_err(|err| format!("Failed to parse typed data: {}", err).encode())?;
+    let digest = typed_data.encode_eip712().map_err(|err| err.to_string().encode())?;
+    sign(private_key, digest.into(), chain_id)
+}
+
 fn derive_key(mnemonic: &str, path: &str, index: u32) -> Result<Bytes, Bytes> {
     let derivation_path = format!("{}{}", path, index);
 
@@ -89,6 +105,78 @@ fn derive_key(mnemonic: &str, path: &str, index: u32) -> Result<Bytes, Bytes> {
     Ok(private_key.encode().into())
 }
 
+fn compute_create2_address(salt: [u8; 32], init_code_hash: [u8; 32], deployer: Address) -> Bytes {
+    utils::get_create2_address_from_hash(deployer, salt.to_vec(), init_code_hash.to_vec())
+        .encode()
+        .into()
+}
+
+/// Parses the string as a value of the given type, the inverse of `toString`.
+///
+/// Integers can be given in decimal or as `0x` prefixed hex, bools are case insensitive and bytes
+/// are hex encoded with an optional `0x` prefix.
+pub fn parse(s: &str, ty: &ParamType) -> Result<Token, String> {
+    let invalid = |err: String| format!("Failed to parse `{s}` as type `{ty}`: {err}");
+    match ty {
+        ParamType::Bool => s
+            .to_lowercase()
+            .parse()
+            .map(Token::Bool)
+            .map_err(|e: std::str::ParseBoolError| invalid(e.to_string())),
+        ParamType::Uint(256) => match s.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).map_err(|e| invalid(format!("{e:?}"))),
+            None => U256::from_dec_str(s).map_err(|e| invalid(e.to_string())),
+        }
+        .map(Token::Uint),
+        // hex string may start with "0x", "+0x", or "-0x"
+        ParamType::Int(256) => {
+            if s.starts_with("0x") || s.starts_with("+0x") || s.starts_with("-0x") {
+                I256::from_hex_str(&s.replacen("0x", "", 1))
+            } else {
+                I256::from_dec_str(s)
+            }
+            .map(|v| Token::Int(v.into_raw()))
+            .map_err(|e| invalid(e.to_string()))
+        }
+        ParamType::Address => {
+            Address::from_str(s).map(Token::Address).map_err(|e| invalid(e.to_string()))
+        }
+        ParamType::FixedBytes(32) => {
+            let bytes = hex::decode(s.strip_prefix("0x").unwrap_or(s))
+                .map_err(|e| invalid(e.to_string()))?;
+            if bytes.len() > 32 {
+                return Err(invalid(format!("{} bytes are longer than 32 bytes", bytes.len())))
+            }
+            Ok(Token::FixedBytes(bytes))
+        }
+        ParamType::String => Ok(Token::String(s.to_string())),
+        ParamType::Bytes => hex::decode(s.strip_prefix("0x").unwrap_or(s))
+            .map(Token::Bytes)
+            .map_err(|e| invalid(e.to_string())),
+        _ => Err(format!("{} is not a supported type", ty)),
+    }
+}
+
+fn parse_cheatcode(s: &str, ty: ParamType) -> Result<Bytes, Bytes> {
+    let token = parse(s, &ty).map_err(encode_error)?;
+    Ok(abi::encode(&[token]).into())
+}
+
+fn encode_string(s: String) -> Result<Bytes, Bytes> {
+    Ok(abi::encode(&[Token::String(s)]).into())
+}
+
+/// Returns the index of the first occurrence of `needle`, or `type(uint256).max` if there is none
+fn index_of(haystack: &str, needle: &str) -> Result<Bytes, Bytes> {
+    let index = haystack.find(needle).map(U256::from).unwrap_or_else(U256::max_value);
+    Ok(index.encode().into())
+}
+
+fn split(s: &str, delimiter: &str) -> Result<Bytes, Bytes> {
+    let parts = s.split(delimiter).map(|part| Token::String(part.to_string())).collect();
+    Ok(abi::encode(&[Token::Array(parts)]).into())
+}
+
 pub fn apply<DB: Database>(
     state: &mut Cheatcodes,
     data: &mut EVMData<'_, DB>,
@@ -97,6 +185,15 @@ pub fn apply<DB: Database>(
     Some(match call {
         HEVMCalls::Addr(inner) => addr(inner.0),
         HEVMCalls::Sign(inner) => sign(inner.0, inner.1.into(), data.env.cfg.chain_id),
+        HEVMCalls::SignTypedData(inner) => {
+            sign_typed_data(inner.0, &inner.1, data.env.cfg.chain_id)
+        }
+        HEVMCalls::ComputeCreate2Address0(inner) => {
+            Ok(compute_create2_address(inner.0, inner.1, DEFAULT_CREATE2_DEPLOYER))
+        }
+        HEVMCalls::ComputeCreate2Address1(inner) => {
+            Ok(compute_create2_address(inner.0, inner.1, inner.2))
+        }
         HEVMCalls::DeriveKey0(inner) => {
             derive_key(&inner.0, DEFAULT_DERIVATION_PATH_PREFIX, inner.1)
         }
@@ -123,6 +220,19 @@ pub fn apply<DB: Database>(
         HEVMCalls::ToString5(inner) => {
             Ok(ethers::abi::encode(&[Token::String(inner.0.pretty())]).into())
         }
+        HEVMCalls::ParseBytes(inner) => parse_cheatcode(&inner.0, ParamType::Bytes),
+        HEVMCalls::ParseAddress(inner) => parse_cheatcode(&inner.0, ParamType::Address),
+        HEVMCalls::ParseUint(inner) => parse_cheatcode(&inner.0, ParamType::Uint(256)),
+        HEVMCalls::ParseInt(inner) => parse_cheatcode(&inner.0, ParamType::Int(256)),
+        HEVMCalls::ParseBytes32(inner) => parse_cheatcode(&inner.0, ParamType::FixedBytes(32)),
+        HEVMCalls::ParseBool(inner) => parse_cheatcode(&inner.0, ParamType::Bool),
+        HEVMCalls::ToUppercase(inner) => encode_string(inner.0.to_uppercase()),
+        HEVMCalls::ToLowercase(inner) => encode_string(inner.0.to_lowercase()),
+        HEVMCalls::Trim(inner) => encode_string(inner.0.trim().to_string()),
+        HEVMCalls::Replace(inner) => encode_string(inner.0.replace(&inner.1, &inner.2)),
+        HEVMCalls::Split(inner) => split(&inner.0, &inner.1),
+        HEVMCalls::IndexOf(inner) => index_of(&inner.0, &inner.1),
+        HEVMCalls::Concat(inner) => encode_string(inner.0.concat()),
         _ => return None,
     })
 }
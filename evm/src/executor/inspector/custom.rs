@@ -0,0 +1,244 @@
+use bytes::Bytes;
+use ethers::types::{Address, H256};
+use revm::{CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter, Return};
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
+
+/// An inspector defined outside of foundry, e.g. by a crate that uses forge as a library.
+///
+/// Unlike [Inspector], this trait is object safe and does not depend on the database, so custom
+/// inspectors can run alongside the built-in inspectors on every backend.
+///
+/// All hooks do nothing by default.
+#[allow(unused_variables)]
+pub trait CustomInspector {
+    /// Called before the interpreter is initialized.
+    fn initialize_interp(&mut self, interpreter: &mut Interpreter, is_static: bool) -> Return {
+        Return::Continue
+    }
+
+    /// Called on each step of the interpreter.
+    fn step(&mut self, interpreter: &mut Interpreter, is_static: bool) -> Return {
+        Return::Continue
+    }
+
+    /// Called after each step of the interpreter.
+    fn step_end(
+        &mut self,
+        interpreter: &mut Interpreter,
+        is_static: bool,
+        status: Return,
+    ) -> Return {
+        Return::Continue
+    }
+
+    /// Called when a log is emitted.
+    fn log(&mut self, address: &Address, topics: &[H256], data: &Bytes) {}
+
+    /// Called whenever a call to a contract is about to start.
+    fn call(&mut self, call: &mut CallInputs, is_static: bool) -> (Return, Gas, Bytes) {
+        (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    /// Called when a call to a contract has concluded.
+    fn call_end(
+        &mut self,
+        call: &CallInputs,
+        remaining_gas: Gas,
+        status: Return,
+        retdata: Bytes,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        (status, remaining_gas, retdata)
+    }
+
+    /// Called when a contract is about to be created.
+    fn create(&mut self, call: &mut CreateInputs) -> (Return, Option<Address>, Gas, Bytes) {
+        (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    /// Called when a contract has been created.
+    fn create_end(
+        &mut self,
+        call: &CreateInputs,
+        status: Return,
+        address: Option<Address>,
+        remaining_gas: Gas,
+        retdata: Bytes,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        (status, address, remaining_gas, retdata)
+    }
+
+    /// Called when a contract has been self-destructed.
+    fn selfdestruct(&mut self) {}
+
+    /// Returns the data collected by the inspector, if any.
+    ///
+    /// The data is attached to the result of the execution under the name of the inspector, see
+    /// [CustomInspectorFactory::name].
+    fn finish(self: Box<Self>) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+/// Creates a fresh [CustomInspector] for every execution.
+pub trait CustomInspectorFactory: Debug + Send + Sync {
+    /// The name the data collected by the inspectors is attached under.
+    fn name(&self) -> &str;
+
+    /// Creates a new inspector.
+    fn create(&self) -> Box<dyn CustomInspector>;
+}
+
+/// The [CustomInspector]s of a single execution, in the order they were registered.
+pub struct CustomInspectors {
+    inspectors: Vec<(String, Box<dyn CustomInspector>)>,
+}
+
+impl CustomInspectors {
+    pub fn new(factories: &[Arc<dyn CustomInspectorFactory>]) -> Self {
+        Self {
+            inspectors: factories
+                .iter()
+                .map(|factory| (factory.name().to_string(), factory.create()))
+                .collect(),
+        }
+    }
+
+    /// Returns the data collected by the inspectors, keyed by their names.
+    pub fn finish(self) -> BTreeMap<String, serde_json::Value> {
+        self.inspectors
+            .into_iter()
+            .filter_map(|(name, inspector)| Some((name, inspector.finish()?)))
+            .collect()
+    }
+}
+
+impl<DB> Inspector<DB> for CustomInspectors
+where
+    DB: Database,
+{
+    fn initialize_interp(
+        &mut self,
+        interpreter: &mut Interpreter,
+        _: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        for (_, inspector) in &mut self.inspectors {
+            let status = inspector.initialize_interp(interpreter, is_static);
+            if status != Return::Continue {
+                return status
+            }
+        }
+        Return::Continue
+    }
+
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        _: &mut EVMData<'_, DB>,
+        is_static: bool,
+    ) -> Return {
+        for (_, inspector) in &mut self.inspectors {
+            let status = inspector.step(interpreter, is_static);
+            if status != Return::Continue {
+                return status
+            }
+        }
+        Return::Continue
+    }
+
+    fn log(&mut self, _: &mut EVMData<'_, DB>, address: &Address, topics: &[H256], data: &Bytes) {
+        for (_, inspector) in &mut self.inspectors {
+            inspector.log(address, topics, data);
+        }
+    }
+
+    fn step_end(
+        &mut self,
+        interpreter: &mut Interpreter,
+        _: &mut EVMData<'_, DB>,
+        is_static: bool,
+        status: Return,
+    ) -> Return {
+        for (_, inspector) in &mut self.inspectors {
+            let status = inspector.step_end(interpreter, is_static, status);
+            if status != Return::Continue {
+                return status
+            }
+        }
+        Return::Continue
+    }
+
+    fn call(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &mut CallInputs,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        for (_, inspector) in &mut self.inspectors {
+            let (status, gas, retdata) = inspector.call(call, is_static);
+            if status != Return::Continue {
+                return (status, gas, retdata)
+            }
+        }
+        (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &CallInputs,
+        remaining_gas: Gas,
+        status: Return,
+        retdata: Bytes,
+        is_static: bool,
+    ) -> (Return, Gas, Bytes) {
+        for (_, inspector) in &mut self.inspectors {
+            let (new_status, new_gas, new_retdata) =
+                inspector.call_end(call, remaining_gas, status, retdata.clone(), is_static);
+            if new_status != status {
+                return (new_status, new_gas, new_retdata)
+            }
+        }
+        (status, remaining_gas, retdata)
+    }
+
+    fn create(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &mut CreateInputs,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        for (_, inspector) in &mut self.inspectors {
+            let (status, address, gas, retdata) = inspector.create(call);
+            if status != Return::Continue {
+                return (status, address, gas, retdata)
+            }
+        }
+        (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn create_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &CreateInputs,
+        status: Return,
+        address: Option<Address>,
+        remaining_gas: Gas,
+        retdata: Bytes,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        for (_, inspector) in &mut self.inspectors {
+            let (new_status, new_address, new_gas, new_retdata) =
+                inspector.create_end(call, status, address, remaining_gas, retdata.clone());
+            if new_status != status {
+                return (new_status, new_address, new_gas, new_retdata)
+            }
+        }
+        (status, address, remaining_gas, retdata)
+    }
+
+    fn selfdestruct(&mut self) {
+        for (_, inspector) in &mut self.inspectors {
+            inspector.selfdestruct();
+        }
+    }
+}
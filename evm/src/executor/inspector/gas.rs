@@ -0,0 +1,278 @@
+use bytes::Bytes;
+use ethers::types::Address;
+use revm::{
+    spec_opcode_gas, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter,
+    OpCode, Return, SpecId,
+};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// A hypothetical gas schedule that overrides the cost of individual opcodes and calldata bytes
+///
+/// An opcode override replaces the entire cost charged for executing the opcode, including
+/// dynamic costs such as cold account and storage access.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GasSchedule {
+    /// The cost of opcodes
+    pub opcodes: BTreeMap<u8, u64>,
+    /// The cost of a zero byte in the calldata of the transaction
+    pub calldata_zero_byte: Option<u64>,
+    /// The cost of a non-zero byte in the calldata of the transaction
+    pub calldata_byte: Option<u64>,
+}
+
+impl GasSchedule {
+    /// Returns `true` if nothing is overridden
+    pub fn is_empty(&self) -> bool {
+        self.opcodes.is_empty() && self.calldata_zero_byte.is_none() && self.calldata_byte.is_none()
+    }
+
+    /// Adds an override in the form of `<NAME>=<COST>`, where `NAME` is either an opcode (e.g.
+    /// `SLOAD`), `calldata_zero_byte` or `calldata_byte`
+    pub fn add_override(&mut self, s: &str) -> Result<(), String> {
+        let (name, cost) =
+            s.split_once('=').ok_or_else(|| format!("Expected <NAME>=<COST>, got `{}`", s))?;
+        let cost = cost.trim().parse::<u64>().map_err(|err| format!("Invalid cost: {}", err))?;
+        match name.trim().to_lowercase().as_str() {
+            "calldata_zero_byte" => self.calldata_zero_byte = Some(cost),
+            "calldata_byte" => self.calldata_byte = Some(cost),
+            name => {
+                let op = (0..=u8::MAX)
+                    .find(|op| {
+                        OpCode::try_from_u8(*op)
+                            .map(|opcode| opcode.as_str().eq_ignore_ascii_case(name))
+                            .unwrap_or_default()
+                    })
+                    .ok_or_else(|| format!("Unknown opcode `{}`", name))?;
+                self.opcodes.insert(op, cost);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the difference in the cost of the given transaction calldata compared to the
+    /// standard schedule
+    pub fn calldata_delta(&self, calldata: &[u8], spec: SpecId) -> i64 {
+        let standard_byte = if SpecId::enabled(spec, SpecId::ISTANBUL) { 16 } else { 68 };
+        let zero_delta = self.calldata_zero_byte.map(|cost| cost as i64 - 4).unwrap_or_default();
+        let byte_delta =
+            self.calldata_byte.map(|cost| cost as i64 - standard_byte).unwrap_or_default();
+        calldata.iter().map(|byte| if *byte == 0 { zero_delta } else { byte_delta }).sum()
+    }
+}
+
+impl FromStr for GasSchedule {
+    type Err = String;
+
+    /// Parses a comma separated list of overrides, see [GasSchedule::add_override]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut schedule = GasSchedule::default();
+        for item in s.split(',').filter(|item| !item.trim().is_empty()) {
+            schedule.add_override(item)?;
+        }
+        Ok(schedule)
+    }
+}
+
+impl fmt::Display for GasSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut overrides = self
+            .opcodes
+            .iter()
+            .map(|(op, cost)| {
+                let name = OpCode::try_from_u8(*op).map(|opcode| opcode.as_str()).unwrap_or("?");
+                format!("{}={}", name, cost)
+            })
+            .collect::<Vec<_>>();
+        if let Some(cost) = self.calldata_zero_byte {
+            overrides.push(format!("calldata_zero_byte={}", cost));
+        }
+        if let Some(cost) = self.calldata_byte {
+            overrides.push(format!("calldata_byte={}", cost));
+        }
+        write!(f, "{}", overrides.join(","))
+    }
+}
+
+/// The gas differences of an execution under a [GasSchedule] compared to the standard schedule
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GasDeltas {
+    /// The difference in gas used by the entire execution
+    pub total: i64,
+    /// The accumulated differences of all calls to a function, keyed by selector.
+    ///
+    /// The difference of a call includes the differences of its subcalls.
+    pub functions: BTreeMap<[u8; 4], i64>,
+}
+
+impl GasDeltas {
+    /// Merges the deltas of another execution into this one
+    pub fn merge(&mut self, other: &GasDeltas) {
+        self.total += other.total;
+        for (selector, delta) in &other.functions {
+            *self.functions.entry(*selector).or_default() += delta;
+        }
+    }
+}
+
+/// A frame of the [GasOverrides] inspector
+#[derive(Debug, Default)]
+struct Frame {
+    /// The selector of the called function, if any
+    selector: Option<[u8; 4]>,
+    /// The accumulated delta of the frame
+    delta: i64,
+    /// The opcode currently being executed, its static cost and the gas remaining before its
+    /// execution, if the opcode is overridden
+    pending: Option<(u8, u64, u64)>,
+}
+
+/// An inspector that measures how much gas an execution would use under a [GasSchedule]
+#[derive(Debug, Default)]
+pub struct GasOverrides {
+    /// The gas schedule to apply
+    pub schedule: GasSchedule,
+    /// The collected differences
+    pub deltas: GasDeltas,
+    /// The call frames, with the topmost one being the current frame
+    frames: Vec<Frame>,
+}
+
+impl GasOverrides {
+    pub fn new(schedule: GasSchedule) -> Self {
+        Self { schedule, ..Default::default() }
+    }
+
+    fn enter(&mut self, selector: Option<[u8; 4]>) {
+        self.frames.push(Frame { selector, ..Default::default() });
+    }
+
+    fn exit(&mut self) {
+        if let Some(frame) = self.frames.pop() {
+            if let Some(selector) = frame.selector {
+                *self.deltas.functions.entry(selector).or_default() += frame.delta;
+            }
+            match self.frames.last_mut() {
+                Some(parent) => parent.delta += frame.delta,
+                None => self.deltas.total += frame.delta,
+            }
+        }
+    }
+}
+
+impl<DB> Inspector<DB> for GasOverrides
+where
+    DB: Database,
+{
+    fn step(&mut self, interp: &mut Interpreter, data: &mut EVMData<'_, DB>, _: bool) -> Return {
+        let op = interp.contract.code[interp.program_counter()];
+        if self.schedule.opcodes.contains_key(&op) {
+            if let Some(frame) = self.frames.last_mut() {
+                let info = &spec_opcode_gas(data.env.cfg.spec_id)[op as usize];
+                // the static cost is charged per gas block, so it is not reflected in the gas
+                // remaining after the step. The gas charged by opcodes ending a gas block
+                // includes the cost of the next block, so only their static cost is considered.
+                let remaining = (!info.is_gas_block_end).then(|| interp.gas.remaining());
+                frame.pending = Some((op, info.gas, remaining.unwrap_or_default()));
+            }
+        }
+        Return::Continue
+    }
+
+    fn step_end(
+        &mut self,
+        interp: &mut Interpreter,
+        _: &mut EVMData<'_, DB>,
+        _: bool,
+        _: Return,
+    ) -> Return {
+        if let Some(frame) = self.frames.last_mut() {
+            if let Some((op, static_cost, remaining)) = frame.pending.take() {
+                let dynamic_cost = remaining.saturating_sub(interp.gas.remaining());
+                let standard = static_cost + dynamic_cost;
+                frame.delta += self.schedule.opcodes[&op] as i64 - standard as i64;
+            }
+        }
+        Return::Continue
+    }
+
+    fn call(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &mut CallInputs,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        if self.frames.is_empty() {
+            self.deltas.total += self.schedule.calldata_delta(&call.input, data.env.cfg.spec_id);
+        }
+        let selector = call.input.get(..4).map(|selector| selector.try_into().unwrap());
+        self.enter(selector);
+
+        (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CallInputs,
+        gas: Gas,
+        status: Return,
+        retdata: Bytes,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.exit();
+
+        (status, gas, retdata)
+    }
+
+    fn create(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &mut CreateInputs,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        if self.frames.is_empty() {
+            self.deltas.total +=
+                self.schedule.calldata_delta(&call.init_code, data.env.cfg.spec_id);
+        }
+        self.enter(None);
+
+        (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn create_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CreateInputs,
+        status: Return,
+        address: Option<Address>,
+        gas: Gas,
+        retdata: Bytes,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        self.exit();
+
+        (status, address, gas, retdata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_gas_schedule() {
+        let schedule: GasSchedule = "SLOAD=100, sstore=5000,calldata_byte=8".parse().unwrap();
+        assert_eq!(schedule.opcodes.get(&revm::opcode::SLOAD), Some(&100));
+        assert_eq!(schedule.opcodes.get(&revm::opcode::SSTORE), Some(&5000));
+        assert_eq!(schedule.calldata_byte, Some(8));
+        assert_eq!(schedule.calldata_zero_byte, None);
+        assert_eq!(schedule.to_string(), "SLOAD=100,SSTORE=5000,calldata_byte=8");
+
+        assert!("FOO=1".parse::<GasSchedule>().is_err());
+        assert!("SLOAD".parse::<GasSchedule>().is_err());
+    }
+
+    #[test]
+    fn can_compute_calldata_delta() {
+        let schedule: GasSchedule = "calldata_byte=8,calldata_zero_byte=2".parse().unwrap();
+        assert_eq!(schedule.calldata_delta(&[0, 1, 2], SpecId::LONDON), -2 - 8 - 8);
+    }
+}
@@ -13,6 +13,20 @@ pub use debugger::Debugger;
 mod coverage;
 pub use coverage::CoverageCollector;
 
+mod access_list;
+pub use access_list::AccessListTracer;
+
+mod gas;
+pub use gas::{GasDeltas, GasOverrides, GasSchedule};
+
+mod custom;
+pub use custom::{CustomInspector, CustomInspectorFactory, CustomInspectors};
+
+mod precompiles;
+pub use precompiles::{
+    CommandPrecompile, CustomPrecompile, CustomPrecompileResult, CustomPrecompiles,
+};
+
 mod stack;
 pub use stack::{InspectorData, InspectorStack};
 
@@ -20,6 +34,7 @@ pub mod cheatcodes;
 pub use cheatcodes::{Cheatcodes, CheatsConfig, DEFAULT_CREATE2_DEPLOYER};
 
 use ethers::types::U256;
+use std::sync::Arc;
 
 use revm::BlockEnv;
 
@@ -44,12 +59,25 @@ pub struct InspectorStackConfig {
     pub debugger: bool,
     /// Whether or not coverage info should be collected
     pub coverage: bool,
+    /// Whether or not the accessed accounts and storage slots should be collected
+    pub access_list: bool,
+    /// The hypothetical gas schedule to measure gas differences against, if any
+    pub gas_schedule: Option<GasSchedule>,
+    /// The factories of the custom inspectors that run alongside the built-in inspectors
+    pub custom: Vec<Arc<dyn CustomInspectorFactory>>,
+    /// The precompiles at custom addresses
+    pub precompiles: CustomPrecompiles,
+    /// Whether the calls of the top-level call are executed as transactions of their own
+    pub isolate: bool,
 }
 
 impl InspectorStackConfig {
     pub fn stack(&self) -> InspectorStack {
-        let mut stack =
-            InspectorStack { logs: Some(LogCollector::default()), ..Default::default() };
+        let mut stack = InspectorStack {
+            logs: Some(LogCollector::default()),
+            isolate: self.isolate,
+            ..Default::default()
+        };
 
         stack.cheatcodes = self.create_cheatcodes();
         if let Some(ref mut cheatcodes) = stack.cheatcodes {
@@ -66,6 +94,18 @@ impl InspectorStackConfig {
         if self.coverage {
             stack.coverage = Some(CoverageCollector::default());
         }
+        if self.access_list {
+            stack.access_list = Some(AccessListTracer::default());
+        }
+        if let Some(ref schedule) = self.gas_schedule {
+            stack.gas = Some(GasOverrides::new(schedule.clone()));
+        }
+        if !self.custom.is_empty() {
+            stack.custom = Some(CustomInspectors::new(&self.custom));
+        }
+        if !self.precompiles.is_empty() {
+            stack.precompiles = Some(self.precompiles.clone());
+        }
         stack
     }
 
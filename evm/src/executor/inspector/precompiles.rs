@@ -0,0 +1,176 @@
+use crate::executor::inspector::cheatcodes::util;
+use bytes::Bytes;
+use ethers::types::Address;
+use foundry_config::PrecompileCommand;
+use revm::{CallInputs, Database, EVMData, Gas, Inspector, Return};
+use std::{collections::BTreeMap, fmt, path::PathBuf, process::Command, sync::Arc};
+
+/// The result of a call to a [CustomPrecompile]: the gas used and the output, or the revert data
+pub type CustomPrecompileResult = Result<(u64, Bytes), Bytes>;
+
+/// A precompile that is implemented in Rust, e.g. by a crate that uses forge as a library.
+///
+/// Solidity checks that the target of a high-level call has code, so, like the standard
+/// precompiles, custom precompiles should be called with a low-level `call` or `staticcall`.
+pub trait CustomPrecompile: Send + Sync {
+    /// Executes the precompile with the input of the call and the gas available to it.
+    fn call(&self, input: &[u8], gas_limit: u64) -> CustomPrecompileResult;
+}
+
+impl<F> CustomPrecompile for F
+where
+    F: Fn(&[u8], u64) -> CustomPrecompileResult + Send + Sync,
+{
+    fn call(&self, input: &[u8], gas_limit: u64) -> CustomPrecompileResult {
+        self(input, gas_limit)
+    }
+}
+
+/// A [CustomPrecompile] that runs an external command, see [PrecompileCommand]
+#[derive(Debug, Clone)]
+pub struct CommandPrecompile {
+    /// The dir the command is run in
+    pub root: PathBuf,
+    /// The program and its arguments
+    pub command: Vec<String>,
+    /// The gas every call costs
+    pub gas: u64,
+}
+
+impl CommandPrecompile {
+    pub fn new(root: impl Into<PathBuf>, config: &PrecompileCommand) -> Self {
+        Self { root: root.into(), command: config.command.clone(), gas: config.gas }
+    }
+}
+
+impl CustomPrecompile for CommandPrecompile {
+    fn call(&self, input: &[u8], _: u64) -> CustomPrecompileResult {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| util::encode_error("The precompile command is empty"))?;
+        let output = Command::new(program)
+            .current_dir(&self.root)
+            .args(args)
+            .arg(format!("0x{}", hex::encode(input)))
+            .output()
+            .map_err(|err| util::encode_error(format!("Failed to run `{program}`: {err}")))?;
+        if !output.status.success() {
+            return Err(util::encode_error(format!(
+                "Precompile command `{program}` failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stdout = stdout.trim();
+        let output = hex::decode(stdout.strip_prefix("0x").unwrap_or(stdout)).map_err(|err| {
+            util::encode_error(format!("Precompile command `{program}` printed invalid hex: {err}"))
+        })?;
+        Ok((self.gas, output.into()))
+    }
+}
+
+/// The custom precompiles of an executor, keyed by their addresses.
+///
+/// Calls to these addresses are answered by the precompiles instead of the code at the address,
+/// value transfers of the calls are not executed.
+#[derive(Clone, Default)]
+pub struct CustomPrecompiles {
+    precompiles: BTreeMap<Address, Arc<dyn CustomPrecompile>>,
+}
+
+impl CustomPrecompiles {
+    /// Creates the [CommandPrecompile]s of the `configs`, run in `root`
+    pub fn from_commands(root: impl Into<PathBuf>, configs: &[PrecompileCommand]) -> Self {
+        let root = root.into();
+        let mut precompiles = Self::default();
+        for config in configs {
+            precompiles.insert(config.address, CommandPrecompile::new(&root, config));
+        }
+        precompiles
+    }
+
+    /// Registers the precompile at `address`, replacing any precompile already registered there
+    pub fn insert(&mut self, address: Address, precompile: impl CustomPrecompile + 'static) {
+        self.precompiles.insert(address, Arc::new(precompile));
+    }
+
+    /// Registers all precompiles of `other`, replacing the ones at the same addresses
+    pub fn extend(&mut self, other: CustomPrecompiles) {
+        self.precompiles.extend(other.precompiles);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.precompiles.is_empty()
+    }
+
+    /// Returns the addresses of the precompiles
+    pub fn addresses(&self) -> impl Iterator<Item = &Address> {
+        self.precompiles.keys()
+    }
+}
+
+impl fmt::Debug for CustomPrecompiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomPrecompiles").field(&self.precompiles.keys()).finish()
+    }
+}
+
+impl<DB> Inspector<DB> for CustomPrecompiles
+where
+    DB: Database,
+{
+    fn call(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &mut CallInputs,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        let precompile = match self.precompiles.get(&call.contract) {
+            Some(precompile) => precompile,
+            None => return (Return::Continue, Gas::new(call.gas_limit), Bytes::new()),
+        };
+
+        let mut gas = Gas::new(call.gas_limit);
+        match precompile.call(&call.input, call.gas_limit) {
+            Ok((used, output)) => {
+                if gas.record_cost(used) {
+                    (Return::Return, gas, output)
+                } else {
+                    (Return::OutOfGas, Gas::new(call.gas_limit), Bytes::new())
+                }
+            }
+            Err(output) => (Return::Revert, gas, output),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_register_precompiles() {
+        let mut precompiles = CustomPrecompiles::default();
+        precompiles.insert(Address::from_low_u64_be(0x100), |input: &[u8], _: u64| {
+            Ok((100, Bytes::copy_from_slice(input)))
+        });
+        precompiles.extend(CustomPrecompiles::from_commands(
+            ".",
+            &[PrecompileCommand {
+                address: Address::from_low_u64_be(0x101),
+                command: vec!["echo".to_string()],
+                gas: 10,
+            }],
+        ));
+
+        assert_eq!(
+            precompiles.addresses().copied().collect::<Vec<_>>(),
+            vec![Address::from_low_u64_be(0x100), Address::from_low_u64_be(0x101)]
+        );
+        let echo = &precompiles.precompiles[&Address::from_low_u64_be(0x101)];
+        assert_eq!(echo.call(&[0xab, 0xcd], 100).unwrap(), (10, Bytes::from_static(&[0xab, 0xcd])));
+        let identity = &precompiles.precompiles[&Address::from_low_u64_be(0x100)];
+        assert_eq!(identity.call(&[1], 100).unwrap(), (100, Bytes::from_static(&[1])));
+    }
+}
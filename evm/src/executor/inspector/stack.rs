@@ -1,4 +1,7 @@
-use super::{Cheatcodes, Debugger, LogCollector, Tracer};
+use super::{
+    utils::apply_state_changes, AccessListTracer, Cheatcodes, CustomInspectors, CustomPrecompiles,
+    Debugger, GasDeltas, GasOverrides, LogCollector, Tracer,
+};
 use crate::{
     coverage::HitMaps,
     debug::DebugArena,
@@ -7,7 +10,10 @@ use crate::{
 };
 use bytes::Bytes;
 use ethers::types::{Address, Log, H256};
-use revm::{CallInputs, CreateInputs, EVMData, Gas, Inspector, Interpreter, Return};
+use revm::{
+    CallInputs, CreateInputs, EVMData, Gas, Inspector, Interpreter, Return, TransactOut,
+    TransactTo, TxEnv,
+};
 use std::collections::BTreeMap;
 
 /// Helper macro to call the same method on multiple inspectors without resorting to dynamic
@@ -28,7 +34,11 @@ pub struct InspectorData {
     pub traces: Option<CallTraceArena>,
     pub debug: Option<DebugArena>,
     pub coverage: Option<HitMaps>,
+    pub gas_deltas: Option<GasDeltas>,
     pub cheatcodes: Option<Cheatcodes>,
+    pub access_list: Option<AccessListTracer>,
+    /// The data collected by the custom inspectors, keyed by their names
+    pub extensions: BTreeMap<String, serde_json::Value>,
 }
 
 /// An inspector that calls multiple inspectors in sequence.
@@ -42,6 +52,18 @@ pub struct InspectorStack {
     pub cheatcodes: Option<Cheatcodes>,
     pub debugger: Option<Debugger>,
     pub coverage: Option<CoverageCollector>,
+    pub gas: Option<GasOverrides>,
+    pub access_list: Option<AccessListTracer>,
+    pub custom: Option<CustomInspectors>,
+    pub precompiles: Option<CustomPrecompiles>,
+    /// Whether the calls of the top-level call, e.g. the calls made by the body of a test, are
+    /// executed as transactions of their own.
+    ///
+    /// Every such call starts from a fresh transaction context, with its own gas accounting,
+    /// intrinsic gas and refunds, on top of the state committed by the calls before it.
+    pub isolate: bool,
+    /// Set while a call is executed as a transaction of its own
+    in_isolation: bool,
 }
 
 impl InspectorStack {
@@ -56,8 +78,53 @@ impl InspectorStack {
             traces: self.tracer.map(|tracer| tracer.traces),
             debug: self.debugger.map(|debugger| debugger.arena),
             coverage: self.coverage.map(|coverage| coverage.maps),
+            gas_deltas: self.gas.map(|gas| gas.deltas),
             cheatcodes: self.cheatcodes,
+            access_list: self.access_list,
+            extensions: self.custom.map(|custom| custom.finish()).unwrap_or_default(),
+        }
+    }
+
+    /// Executes the `call` as a transaction of its own on top of the state of the current
+    /// transaction and applies its changes to the current transaction
+    fn transact_isolated<DB: DatabaseExt>(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &CallInputs,
+    ) -> (Return, Gas, Bytes) {
+        let mut env = data.env.clone();
+        env.tx = TxEnv {
+            caller: call.context.caller,
+            gas_limit: call.gas_limit,
+            transact_to: TransactTo::Call(call.contract),
+            value: call.transfer.value,
+            data: call.input.clone(),
+            // the caller is usually a contract, so its nonce is not checked
+            nonce: None,
+            ..data.env.tx.clone()
+        };
+        let nonce = data.subroutine.state().get(&call.context.caller).map(|acc| acc.info.nonce);
+
+        data.db.commit_changes(data.subroutine.state().clone());
+        self.in_isolation = true;
+        let (status, out, gas_used, mut changes, logs) =
+            revm::evm_inner::<DB, true>(&mut env, &mut *data.db, self).transact();
+        self.in_isolation = false;
+
+        // the transaction must not change the addresses of the contracts the caller creates
+        if let (Some(caller), Some(nonce)) = (changes.get_mut(&call.context.caller), nonce) {
+            caller.info.nonce = nonce;
         }
+        apply_state_changes(data.subroutine, data.db, changes);
+        data.subroutine.logs.extend(logs);
+
+        let mut gas = Gas::new(call.gas_limit);
+        gas.record_cost(gas_used);
+        let out = match out {
+            TransactOut::Call(out) => out,
+            _ => Bytes::new(),
+        };
+        (status, gas, out)
     }
 }
 
@@ -78,6 +145,7 @@ where
                 &mut self.coverage,
                 &mut self.tracer,
                 &mut self.logs,
+                &mut self.custom,
                 &mut self.cheatcodes
             ],
             {
@@ -104,8 +172,11 @@ where
             [
                 &mut self.debugger,
                 &mut self.tracer,
+                &mut self.gas,
                 &mut self.coverage,
+                &mut self.access_list,
                 &mut self.logs,
+                &mut self.custom,
                 &mut self.cheatcodes
             ],
             {
@@ -128,9 +199,13 @@ where
         topics: &[H256],
         data: &Bytes,
     ) {
-        call_inspectors!(inspector, [&mut self.tracer, &mut self.logs, &mut self.cheatcodes], {
-            inspector.log(evm_data, address, topics, data);
-        });
+        call_inspectors!(
+            inspector,
+            [&mut self.tracer, &mut self.logs, &mut self.custom, &mut self.cheatcodes],
+            {
+                inspector.log(evm_data, address, topics, data);
+            }
+        );
     }
 
     fn step_end(
@@ -142,7 +217,14 @@ where
     ) -> Return {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.debugger,
+                &mut self.tracer,
+                &mut self.gas,
+                &mut self.logs,
+                &mut self.custom,
+                &mut self.cheatcodes
+            ],
             {
                 let status = inspector.step_end(interpreter, data, is_static, status);
 
@@ -162,14 +244,24 @@ where
         call: &mut CallInputs,
         is_static: bool,
     ) -> (Return, Gas, Bytes) {
+        if self.in_isolation && data.subroutine.depth() == 0 {
+            // the top-level call of an isolated transaction was already inspected as the call it
+            // stands for, its subcalls are nested as deep as the ones of that call
+            data.subroutine.create_checkpoint();
+            return (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
+        }
+
         call_inspectors!(
             inspector,
             [
                 &mut self.debugger,
                 &mut self.tracer,
+                &mut self.gas,
                 &mut self.coverage,
                 &mut self.logs,
-                &mut self.cheatcodes
+                &mut self.custom,
+                &mut self.cheatcodes,
+                &mut self.precompiles
             ],
             {
                 let (status, gas, retdata) = inspector.call(data, call, is_static);
@@ -181,6 +273,10 @@ where
             }
         );
 
+        if self.isolate && !self.in_isolation && data.subroutine.depth() == 1 && !is_static {
+            return self.transact_isolated(data, call)
+        }
+
         (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
     }
 
@@ -193,13 +289,20 @@ where
         retdata: Bytes,
         is_static: bool,
     ) -> (Return, Gas, Bytes) {
+        if self.in_isolation && data.subroutine.depth() == 1 {
+            // the end of the call is inspected when the isolated transaction returns
+            return (status, remaining_gas, retdata)
+        }
+
         call_inspectors!(
             inspector,
             [
                 &mut self.debugger,
                 &mut self.tracer,
+                &mut self.gas,
                 &mut self.coverage,
                 &mut self.logs,
+                &mut self.custom,
                 &mut self.cheatcodes
             ],
             {
@@ -233,8 +336,10 @@ where
             [
                 &mut self.debugger,
                 &mut self.tracer,
+                &mut self.gas,
                 &mut self.coverage,
                 &mut self.logs,
+                &mut self.custom,
                 &mut self.cheatcodes
             ],
             {
@@ -264,8 +369,10 @@ where
             [
                 &mut self.debugger,
                 &mut self.tracer,
+                &mut self.gas,
                 &mut self.coverage,
                 &mut self.logs,
+                &mut self.custom,
                 &mut self.cheatcodes
             ],
             {
@@ -290,7 +397,13 @@ where
     fn selfdestruct(&mut self) {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.debugger,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.custom,
+                &mut self.cheatcodes
+            ],
             {
                 Inspector::<DB>::selfdestruct(inspector);
             }
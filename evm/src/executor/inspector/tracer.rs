@@ -1,6 +1,7 @@
 use crate::{
     executor::inspector::utils::{gas_used, get_create_address},
     trace::{
+        proxy::{ProxyInfo, ProxyKind},
         CallTrace, CallTraceArena, LogCallOrder, RawOrDecodedCall, RawOrDecodedLog,
         RawOrDecodedReturnData,
     },
@@ -65,6 +66,62 @@ impl Tracer {
             trace.address = address;
         }
     }
+
+    /// Records the proxy pattern of the contract of the ongoing call if the call was delegated to
+    /// an implementation
+    fn detect_proxy<DB: Database>(&mut self, data: &mut EVMData<'_, DB>) {
+        let node = &self.traces.arena[*self.trace_stack.last().expect("no ongoing trace")];
+        if !matches!(node.trace.kind, CallKind::Call | CallKind::StaticCall) {
+            return
+        }
+        let implementation = match node
+            .children
+            .iter()
+            .map(|child| &self.traces.arena[*child].trace)
+            .find(|trace| trace.kind == CallKind::DelegateCall)
+        {
+            Some(trace) => trace.address,
+            None => return,
+        };
+
+        let proxy = node.trace.address;
+        let proxy_code = code(data, proxy);
+        let kind = match ProxyKind::detect(&proxy_code, |slot| storage(data, proxy, slot)) {
+            Some(kind) => kind,
+            None => return,
+        };
+        let implementation_code = code(data, implementation);
+        let node = &mut self.traces.arena[*self.trace_stack.last().expect("no ongoing trace")];
+        node.trace.proxy = Some(ProxyInfo {
+            kind,
+            implementation,
+            implementation_code,
+            implementation_label: None,
+        });
+    }
+}
+
+/// Returns the runtime code of the account, without loading it
+fn code<DB: Database>(data: &mut EVMData<'_, DB>, address: Address) -> Vec<u8> {
+    let info = data
+        .subroutine
+        .state()
+        .get(&address)
+        .map(|acc| acc.info.clone())
+        .unwrap_or_else(|| data.db.basic(address));
+    match info.code {
+        Some(code) => code.to_vec(),
+        None => data.db.code_by_hash(info.code_hash).to_vec(),
+    }
+}
+
+/// Returns the value of a storage slot of the account, without loading it
+fn storage<DB: Database>(data: &mut EVMData<'_, DB>, address: Address, slot: U256) -> U256 {
+    data.subroutine
+        .state()
+        .get(&address)
+        .and_then(|acc| acc.storage.get(&slot).copied())
+        .unwrap_or_else(|| data.db.storage(address, slot))
 }
 
 impl<DB> Inspector<DB> for Tracer
@@ -105,6 +162,7 @@ where
         retdata: Bytes,
         _: bool,
     ) -> (Return, Gas, Bytes) {
+        self.detect_proxy(data);
         self.fill_trace(
             status,
             gas_used(data.env.cfg.spec_id, gas.spend(), gas.refunded() as u64),
@@ -2,7 +2,8 @@ use ethers::{
     types::Address,
     utils::{get_contract_address, get_create2_address},
 };
-use revm::{CreateInputs, CreateScheme, SpecId};
+use hashbrown::HashMap as Map;
+use revm::{Account, CreateInputs, CreateScheme, Database, SpecId, SubRoutine};
 
 /// Returns [Return::Continue] on an error, discarding the error.
 ///
@@ -34,3 +35,24 @@ pub fn gas_used(spec: SpecId, spent: u64, refunded: u64) -> u64 {
     let refund_quotient = if SpecId::enabled(spec, SpecId::LONDON) { 5 } else { 2 };
     spent - (refunded).min(spent / refund_quotient)
 }
+
+/// Applies the state `changes` of a transaction that was executed on top of the current state,
+/// e.g. a replayed transaction, to the `subroutine`
+pub fn apply_state_changes<DB: Database>(
+    subroutine: &mut SubRoutine,
+    db: &mut DB,
+    changes: Map<Address, Account>,
+) {
+    for (address, account) in changes {
+        subroutine.load_account(address, db);
+        if let Some(code) = account.info.code {
+            subroutine.set_code(address, code, account.info.code_hash);
+        }
+        let info = &mut subroutine.state().get_mut(&address).unwrap().info;
+        info.balance = account.info.balance;
+        info.nonce = account.info.nonce;
+        for (slot, value) in account.storage {
+            subroutine.sstore(address, slot, value, db);
+        }
+    }
+}
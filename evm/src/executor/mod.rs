@@ -1,4 +1,4 @@
-use self::inspector::{InspectorData, InspectorStackConfig};
+use self::inspector::{GasDeltas, InspectorData, InspectorStackConfig};
 use crate::{debug::DebugArena, decode, trace::CallTraceArena, CALLER};
 pub use abi::{
     format_hardhat_call, patch_hardhat_console_selector, HardhatConsoleCalls, CHEATCODE_ADDRESS,
@@ -9,17 +9,25 @@ use bytes::Bytes;
 use ethers::{
     abi::{Abi, Contract, Detokenize, Function, Tokenize},
     prelude::{decode_function_data, encode_function_data, Address, U256},
-    types::{transaction::eip2718::TypedTransaction, Log},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip2930::AccessList},
+        Log, H256,
+    },
+    utils::keccak256,
 };
 use foundry_utils::IntoFunction;
+use genesis::{GenesisAccount, GenesisAlloc};
 use hashbrown::HashMap;
 use revm::{
-    db::DatabaseCommit, return_ok, Account, BlockEnv, CreateScheme, Return, TransactOut,
-    TransactTo, TxEnv, EVM,
+    db::DatabaseCommit, return_ok, Account, AccountInfo, BlockEnv, CreateScheme, Return,
+    TransactOut, TransactTo, TxEnv, EVM, KECCAK_EMPTY,
 };
 /// Reexport commonly used revm types
 pub use revm::{db::DatabaseRef, Env, SpecId};
-use std::collections::{BTreeMap, VecDeque};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
 use tracing::trace;
 
 /// ABIs used internally in the executor
@@ -31,6 +39,8 @@ pub use backend::Backend;
 pub mod builder;
 /// Forking provider
 pub mod fork;
+/// Genesis-style account allocations
+pub mod genesis;
 /// Executor inspectors
 pub mod inspector;
 /// Executor configuration
@@ -46,6 +56,13 @@ pub use builder::ExecutorBuilder;
 /// A mapping of addresses to their changed state.
 pub type StateChangeset = HashMap<Address, Account>;
 
+/// A copy-on-write snapshot of the state of an [Executor], see [Executor::snapshot_state]
+#[derive(Debug, Clone)]
+pub struct StateSnapshot {
+    backend: Arc<Backend>,
+    env: Env,
+}
+
 /// A type that can execute calls
 ///
 /// The executor can be configured with various `revm::Inspector`s, like `Cheatcodes`.
@@ -61,13 +78,19 @@ pub struct Executor {
     // only interested in the database. REVM's `EVM` is a thin
     // wrapper around spawning a new EVM on every call anyway,
     // so the performance difference should be negligible.
-    backend: Backend,
+    //
+    // The backend is shared with the snapshots of the state and the clones of the executor, and
+    // only copied once it's modified.
+    backend: Arc<Backend>,
     env: Env,
     inspector_config: InspectorStackConfig,
     /// The gas limit for calls and deployments. This is different from the gas limit imposed by
     /// the passed in environment, as those limits are used by the EVM for certain opcodes like
     /// `gaslimit`.
     gas_limit: U256,
+    /// Whether calls and deployments pay the gas price and base fee of the environment, instead
+    /// of executing without fees regardless of network conditions
+    charge_fees: bool,
 }
 
 // === impl Executor ===
@@ -86,18 +109,47 @@ impl Executor {
             revm::AccountInfo { code: Some(Bytes::from_static(&[1])), ..Default::default() },
         );
 
-        Executor { backend, env, inspector_config, gas_limit }
+        Executor {
+            backend: Arc::new(backend),
+            env,
+            inspector_config,
+            gas_limit,
+            charge_fees: false,
+        }
     }
 
     /// Returns a mutable reference to the Backend
+    ///
+    /// If the backend is shared with a snapshot or a clone of the executor, it's copied first.
     pub fn backend_mut(&mut self) -> &mut Backend {
-        &mut self.backend
+        Arc::make_mut(&mut self.backend)
     }
 
     pub fn backend(&self) -> &Backend {
         &self.backend
     }
 
+    /// Takes a snapshot of the current state, e.g. the state after `setUp()`.
+    ///
+    /// The snapshot shares the state with the executor instead of copying it, the state is only
+    /// copied once the executor modifies it.
+    pub fn snapshot_state(&self) -> StateSnapshot {
+        StateSnapshot { backend: self.backend.clone(), env: self.env.clone() }
+    }
+
+    /// Restores the state of the snapshot, discarding all changes made since it was taken.
+    ///
+    /// The state is shared with the snapshot again, so restoring it does not copy it either.
+    pub fn restore_state(&mut self, snapshot: &StateSnapshot) {
+        self.backend = snapshot.backend.clone();
+        self.env = snapshot.env.clone();
+    }
+
+    /// Returns the environment calls and deployments are executed in
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
     /// Creates the default CREATE2 Contract Deployer for local tests and scripts.
     pub fn deploy_create2_deployer(&mut self) -> eyre::Result<()> {
         let create2_deployer_account = self.backend_mut().basic(DEFAULT_CREATE2_DEPLOYER);
@@ -145,6 +197,46 @@ impl Executor {
         self
     }
 
+    /// Inserts the given accounts, including their code and storage, into the backend.
+    ///
+    /// Existing accounts are overwritten, storage slots that are not part of an allocation are
+    /// kept.
+    pub fn load_allocs(&mut self, allocs: &GenesisAlloc) -> &mut Self {
+        for (address, account) in allocs {
+            let (code, code_hash) = if account.code.is_empty() {
+                (None, KECCAK_EMPTY)
+            } else {
+                (Some(account.code.0.clone()), H256::from_slice(&keccak256(&account.code)))
+            };
+            let info =
+                AccountInfo { balance: account.balance, nonce: account.nonce, code, code_hash };
+            self.backend_mut().insert_account_info(*address, info);
+            for (slot, value) in &account.storage {
+                self.backend_mut().insert_account_storage(*address, *slot, *value);
+            }
+        }
+        self
+    }
+
+    /// Returns the accounts of the backend with their code and storage, see
+    /// [Backend::local_accounts]. The cheatcode address is excluded.
+    pub fn dump_allocs(&self) -> GenesisAlloc {
+        self.backend()
+            .local_accounts()
+            .into_iter()
+            .filter(|(address, _)| *address != CHEATCODE_ADDRESS)
+            .map(|(address, (info, storage))| {
+                let account = GenesisAccount {
+                    balance: info.balance,
+                    nonce: info.nonce,
+                    code: info.code.map(Into::into).unwrap_or_default(),
+                    storage,
+                };
+                (address, account)
+            })
+            .collect()
+    }
+
     pub fn set_tracing(&mut self, tracing: bool) -> &mut Self {
         self.inspector_config.tracing = tracing;
         self
@@ -155,11 +247,47 @@ impl Executor {
         self
     }
 
+    /// Sets whether the calls of the top-level call are executed as transactions of their own, see
+    /// [InspectorStack::isolate]
+    pub fn set_isolation(&mut self, isolate: bool) -> &mut Self {
+        self.inspector_config.isolate = isolate;
+        self
+    }
+
     pub fn set_gas_limit(&mut self, gas_limit: U256) -> &mut Self {
         self.gas_limit = gas_limit;
         self
     }
 
+    /// Sets the gas price calls and deployments are executed with
+    pub fn set_gas_price(&mut self, gas_price: U256) -> &mut Self {
+        self.env.tx.gas_price = gas_price;
+        self.inspector_config.gas_price = gas_price;
+        self
+    }
+
+    /// Sets whether calls and deployments pay the gas price and base fee of the environment, like
+    /// the transactions of a block do.
+    ///
+    /// The gas price must be at least the base fee then, otherwise the EVM rejects the call.
+    pub fn set_charge_fees(&mut self, charge_fees: bool) -> &mut Self {
+        self.charge_fees = charge_fees;
+        self
+    }
+
+    /// Sets the block environment calls and deployments are executed in
+    pub fn set_block_env(&mut self, block: BlockEnv) -> &mut Self {
+        self.env.block = block;
+        self
+    }
+
+    /// Advances the block environment by `blocks` blocks mined `interval` seconds apart, see
+    /// [crate::utils::mine_blocks]
+    pub fn mine_blocks(&mut self, blocks: U256, interval: U256) -> &mut Self {
+        crate::utils::mine_blocks(&mut self.env.block, blocks, interval);
+        self
+    }
+
     /// Calls the `setUp()` function on a contract.
     ///
     /// This will commit any state changes to the underlying database
@@ -196,9 +324,12 @@ impl Executor {
             labels,
             traces,
             coverage,
+            gas_deltas,
             debug,
             transactions,
             state_changeset,
+            extensions,
+            ..
         } = self.call_raw_committing(from, to, calldata, value)?;
         match status {
             return_ok!() => {
@@ -212,9 +343,11 @@ impl Executor {
                     labels,
                     traces,
                     coverage,
+                    gas_deltas,
                     debug,
                     transactions,
                     state_changeset,
+                    extensions,
                 })
             }
             _ => {
@@ -261,8 +394,17 @@ impl Executor {
             _ => Bytes::default(),
         };
 
-        let InspectorData { logs, labels, traces, coverage, debug, mut cheatcodes } =
-            inspector.collect_inspector_states();
+        let InspectorData {
+            logs,
+            labels,
+            traces,
+            coverage,
+            gas_deltas,
+            debug,
+            mut cheatcodes,
+            access_list,
+            extensions,
+        } = inspector.collect_inspector_states();
 
         // Persist the changed block environment
         self.inspector_config.block = evm.env.block.clone();
@@ -295,16 +437,23 @@ impl Executor {
             logs,
             labels,
             coverage,
+            gas_deltas,
             traces,
             debug,
             transactions,
             state_changeset: None,
+            access_list: access_list.map(|tracer| tracer.access_list(&[from, to])),
+            extensions,
         })
     }
 
     /// Executes the test function call
+    ///
+    /// The call is executed against a copy-on-write view of the current state (see
+    /// [FuzzBackendWrapper]), so the state of the executor (e.g. after `setUp()`) can be reused
+    /// for all tests of a contract without cloning the entire database for every test.
     pub fn execute_test<D: Detokenize, T: Tokenize, F: IntoFunction>(
-        &mut self,
+        &self,
         from: Address,
         test_contract: Address,
         func: F,
@@ -319,8 +468,8 @@ impl Executor {
         let mut inspector = self.inspector_config.stack();
         let stipend = calc_stipend(&calldata, self.env.cfg.spec_id);
         let env = self.build_env(from, TransactTo::Call(test_contract), calldata, value);
-        let (status, out, gas, state_changeset, logs) =
-            self.backend_mut().inspect_ref(env, &mut inspector);
+        let mut db = FuzzBackendWrapper::new(self.backend());
+        let (status, out, gas, state_changeset, logs) = db.inspect_ref(env, &mut inspector);
 
         let executed_call = ExecutedCall { status, out, gas, state_changeset, logs, stipend };
         let call_result = convert_executed_call(inspector, executed_call)?;
@@ -387,7 +536,7 @@ impl Executor {
         evm.database(self.backend_mut());
 
         let (status, out, gas, _) = evm.inspect_commit(&mut inspector);
-        let InspectorData { logs, labels, traces, debug, cheatcodes, .. } =
+        let InspectorData { logs, labels, traces, debug, cheatcodes, access_list, .. } =
             inspector.collect_inspector_states();
 
         let result = match out {
@@ -440,7 +589,8 @@ impl Executor {
 
         trace!(address=?address, "deployed contract");
 
-        Ok(DeployResult { address, gas, logs, traces, debug })
+        let access_list = access_list.map(|tracer| tracer.access_list(&[from, address]));
+        Ok(DeployResult { address, gas, logs, traces, debug, access_list })
     }
 
     /// Check if a call to a test contract was successful.
@@ -482,6 +632,21 @@ impl Executor {
 
     /// Creates the environment to use when executing the transaction
     fn build_env(&self, caller: Address, transact_to: TransactTo, data: Bytes, value: U256) -> Env {
+        if self.charge_fees {
+            return Env {
+                cfg: self.env.cfg.clone(),
+                block: BlockEnv { gas_limit: self.gas_limit, ..self.env.block.clone() },
+                tx: TxEnv {
+                    caller,
+                    transact_to,
+                    data,
+                    value,
+                    gas_priority_fee: None,
+                    gas_limit: self.gas_limit.as_u64(),
+                    ..self.env.tx.clone()
+                },
+            }
+        }
         Env {
             cfg: self.env.cfg.clone(),
             // We always set the gas price to 0 so we can execute the transaction regardless of
@@ -544,6 +709,8 @@ pub struct DeployResult {
     pub traces: Option<CallTraceArena>,
     /// The debug nodes of the call
     pub debug: Option<DebugArena>,
+    /// The access list of the deployment, if collected
+    pub access_list: Option<AccessList>,
 }
 
 /// The result of a call.
@@ -565,6 +732,8 @@ pub struct CallResult<D: Detokenize> {
     pub traces: Option<CallTraceArena>,
     /// The coverage info collected during the call
     pub coverage: Option<HitMaps>,
+    /// The gas differences under the configured gas schedule, if any
+    pub gas_deltas: Option<GasDeltas>,
     /// The debug nodes of the call
     pub debug: Option<DebugArena>,
     /// Scripted transactions generated from this call
@@ -574,6 +743,8 @@ pub struct CallResult<D: Detokenize> {
     /// This is only present if the changed state was not committed to the database (i.e. if you
     /// used `call` and `call_raw` not `call_committing` or `call_raw_committing`).
     pub state_changeset: Option<StateChangeset>,
+    /// The data collected by the custom inspectors, keyed by their names
+    pub extensions: BTreeMap<String, serde_json::Value>,
 }
 
 /// The result of a raw call.
@@ -597,6 +768,8 @@ pub struct RawCallResult {
     pub traces: Option<CallTraceArena>,
     /// The coverage info collected during the call
     pub coverage: Option<HitMaps>,
+    /// The gas differences under the configured gas schedule, if any
+    pub gas_deltas: Option<GasDeltas>,
     /// The debug nodes of the call
     pub debug: Option<DebugArena>,
     /// Scripted transactions generated from this call
@@ -606,6 +779,10 @@ pub struct RawCallResult {
     /// This is only present if the changed state was not committed to the database (i.e. if you
     /// used `call` and `call_raw` not `call_committing` or `call_raw_committing`).
     pub state_changeset: Option<StateChangeset>,
+    /// The access list of the call, if collected
+    pub access_list: Option<AccessList>,
+    /// The data collected by the custom inspectors, keyed by their names
+    pub extensions: BTreeMap<String, serde_json::Value>,
 }
 
 impl Default for RawCallResult {
@@ -620,9 +797,12 @@ impl Default for RawCallResult {
             labels: BTreeMap::new(),
             traces: None,
             coverage: None,
+            gas_deltas: None,
             debug: None,
             transactions: None,
             state_changeset: None,
+            access_list: None,
+            extensions: BTreeMap::new(),
         }
     }
 }
@@ -656,8 +836,17 @@ fn convert_executed_call(
         _ => Bytes::default(),
     };
 
-    let InspectorData { logs, labels, traces, debug, cheatcodes, coverage, .. } =
-        inspector.collect_inspector_states();
+    let InspectorData {
+        logs,
+        labels,
+        traces,
+        debug,
+        cheatcodes,
+        coverage,
+        gas_deltas,
+        extensions,
+        ..
+    } = inspector.collect_inspector_states();
 
     let transactions = if let Some(cheats) = cheatcodes {
         if !cheats.broadcastable_transactions.is_empty() {
@@ -679,9 +868,12 @@ fn convert_executed_call(
         labels,
         traces,
         coverage,
+        gas_deltas,
         debug,
         transactions,
         state_changeset: Some(state_changeset),
+        access_list: None,
+        extensions,
     })
 }
 
@@ -700,9 +892,12 @@ fn convert_call_result<D: Detokenize>(
         labels,
         traces,
         coverage,
+        gas_deltas,
         debug,
         transactions,
         state_changeset,
+        extensions,
+        ..
     } = call_result;
 
     match status {
@@ -717,14 +912,26 @@ fn convert_call_result<D: Detokenize>(
                 labels,
                 traces,
                 coverage,
+                gas_deltas,
                 debug,
                 transactions,
                 state_changeset,
+                extensions,
             })
         }
         _ => {
-            let reason = decode::decode_revert(result.as_ref(), abi, Some(status))
+            let mut reason = decode::decode_revert(result.as_ref(), abi, Some(status))
                 .unwrap_or_else(|_| format!("{:?}", status));
+            if let Some(traces) = &traces {
+                // append the underlying reasons if the revert was re-thrown by a caller
+                let chain = traces.revert_chain(abi);
+                if chain.len() > 1 {
+                    reason = std::iter::once(reason)
+                        .chain(chain.into_iter().skip(1))
+                        .collect::<Vec<_>>()
+                        .join("; caused by: ");
+                }
+            }
             Err(EvmError::Execution {
                 reverted,
                 reason,
@@ -740,3 +947,57 @@ fn convert_call_result<D: Detokenize>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn executor() -> Executor {
+        ExecutorBuilder::default()
+            .with_spec(SpecId::LONDON)
+            .with_gas_limit(30_000_000u64.into())
+            .build(Backend::spawn(None))
+    }
+
+    #[test]
+    fn can_restore_state_snapshot() {
+        let mut executor = executor();
+        let alice = Address::repeat_byte(1);
+        executor.set_balance(alice, 100.into());
+        let snapshot = executor.snapshot_state();
+
+        executor.set_balance(alice, 200.into()).set_nonce(alice, 5);
+        assert_eq!(executor.get_balance(alice), 200.into());
+
+        executor.restore_state(&snapshot);
+        assert_eq!(executor.get_balance(alice), 100.into());
+        assert_eq!(executor.backend().basic(alice).nonce, 0);
+
+        // the same snapshot can be restored again, e.g. for the next test
+        executor.set_balance(alice, 300.into());
+        executor.restore_state(&snapshot);
+        assert_eq!(executor.get_balance(alice), 100.into());
+    }
+
+    #[test]
+    fn snapshots_share_state_until_modified() {
+        let mut executor = executor();
+        let alice = Address::repeat_byte(1);
+        let snapshot = executor.snapshot_state();
+        let clone = executor.clone();
+        assert!(Arc::ptr_eq(&snapshot.backend, &executor.backend));
+        assert!(Arc::ptr_eq(&clone.backend, &executor.backend));
+
+        // executing a test does not modify the state
+        executor.execute_test::<(), _, _>(CALLER, alice, "test()", (), 0.into(), None).unwrap();
+        assert!(Arc::ptr_eq(&snapshot.backend, &executor.backend));
+
+        executor.set_balance(alice, 100.into());
+        assert!(!Arc::ptr_eq(&snapshot.backend, &executor.backend));
+        assert_eq!(snapshot.backend.basic(alice).balance, U256::zero());
+        assert_eq!(clone.get_balance(alice), U256::zero());
+
+        executor.restore_state(&snapshot);
+        assert!(Arc::ptr_eq(&snapshot.backend, &executor.backend));
+    }
+}
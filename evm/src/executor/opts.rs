@@ -1,14 +1,15 @@
 use ethers::{
     providers::{Middleware, Provider},
     solc::utils::RuntimeOrHandle,
-    types::{Address, Chain, U256},
+    types::{Address, BlockNumber, Chain, U256},
 };
+use eyre::WrapErr;
 use revm::{BlockEnv, CfgEnv, SpecId, TxEnv};
 use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::executor::fork::CreateFork;
-use foundry_common;
-use foundry_config::Config;
+use foundry_common::{self, provider::ProvidersManager};
+use foundry_config::{ChainProfile, Config, ResolvedRpcEndpoint};
 
 use super::fork::environment;
 
@@ -24,6 +25,14 @@ pub struct EvmOpts {
     /// pins the block number for the state fork
     pub fork_block_number: Option<u64>,
 
+    /// if the fork is not pinned, the number of blocks behind the latest block to fork from
+    pub fork_block_confirmations: Option<u64>,
+
+    /// if the fork is not pinned, fork from the pending block of the endpoint instead of the
+    /// latest one
+    #[serde(skip)]
+    pub fork_block_pending: bool,
+
     /// initial retry backoff
     pub fork_retry_backoff: Option<u64>,
 
@@ -39,11 +48,17 @@ pub struct EvmOpts {
     /// enables the FFI cheatcode
     pub ffi: bool,
 
+    /// enables the `sleep` and `waitForHttp` cheatcodes
+    pub allow_wait: bool,
+
     /// Verbosity mode of EVM output as number of occurrences
     pub verbosity: u8,
 
     /// The memory limit of the EVM in bytes.
     pub memory_limit: u64,
+
+    /// The L2 or sidechain whose EVM semantics are emulated, if any
+    pub chain_profile: Option<ChainProfile>,
 }
 
 impl EvmOpts {
@@ -75,30 +90,71 @@ impl EvmOpts {
     /// Returns the `revm::Env` configured with settings retrieved from the endpoints
     pub async fn fork_evm_env(&self, fork_url: impl AsRef<str>) -> eyre::Result<revm::Env> {
         let provider = Provider::try_from(fork_url.as_ref())?;
+        let block = match (self.fork_block(), self.fork_block_confirmations) {
+            (BlockNumber::Latest, Some(confirmations)) => {
+                let latest = provider
+                    .get_block_number()
+                    .await
+                    .wrap_err("Failed to get latest block number")?
+                    .as_u64();
+                BlockNumber::Number(latest.saturating_sub(confirmations).into())
+            }
+            (block, _) => block,
+        };
         environment(
             &provider,
             self.memory_limit,
             self.env.gas_price,
             self.env.chain_id,
-            self.fork_block_number,
+            block,
             self.sender,
         )
         .await
     }
 
+    /// Returns the block the fork is pinned to, or `pending` or `latest` if it's not pinned
+    ///
+    /// This does not account for the `fork_block_confirmations`.
+    pub fn fork_block(&self) -> BlockNumber {
+        match self.fork_block_number {
+            Some(number) => BlockNumber::Number(number.into()),
+            None if self.fork_block_pending => BlockNumber::Pending,
+            None => BlockNumber::Latest,
+        }
+    }
+
+    /// Pins the fork to the given block, or unpins it to follow the `latest` or `pending` block
+    pub fn set_fork_block(&mut self, block: BlockNumber) {
+        self.fork_block_number = match block {
+            BlockNumber::Number(number) => Some(number.as_u64()),
+            BlockNumber::Earliest => Some(0),
+            BlockNumber::Latest | BlockNumber::Pending => None,
+        };
+        self.fork_block_pending = matches!(block, BlockNumber::Pending);
+        self.fork_block_confirmations = None;
+    }
+
     /// Returns the `revm::Env` configured with only local settings
     pub fn local_evm_env(&self) -> revm::Env {
         revm::Env {
             block: BlockEnv {
                 number: self.env.block_number.into(),
-                coinbase: self.env.block_coinbase,
+                coinbase: match self.chain_profile {
+                    Some(profile) if self.env.block_coinbase.is_zero() => profile.coinbase(),
+                    _ => self.env.block_coinbase,
+                },
                 timestamp: self.env.block_timestamp.into(),
                 difficulty: self.env.block_difficulty.into(),
                 basefee: self.env.block_base_fee_per_gas.into(),
                 gas_limit: self.gas_limit(),
             },
             cfg: CfgEnv {
-                chain_id: self.env.chain_id.unwrap_or(foundry_common::DEV_CHAIN_ID).into(),
+                chain_id: self
+                    .env
+                    .chain_id
+                    .or_else(|| Some(self.chain_profile?.chain().id()))
+                    .unwrap_or(foundry_common::DEV_CHAIN_ID)
+                    .into(),
                 spec_id: SpecId::LONDON,
                 perf_all_precompiles_have_balance: false,
                 memory_limit: self.memory_limit,
@@ -125,10 +181,38 @@ impl EvmOpts {
     ///
     /// for `mainnet` and `--fork-block-number 14435000` on mac the corresponding storage cache will
     /// be at `~/.foundry/cache/mainnet/14435000/storage.json`
+    ///
+    /// If the `fork_url` belongs to an alias of the `rpc_endpoints`, the fork uses the settings of
+    /// that alias.
     pub fn get_fork(&self, config: &Config, env: revm::Env) -> Option<CreateFork> {
         let url = self.fork_url.clone()?;
-        let enable_caching = config.enable_caching(&url, env.cfg.chain_id.as_u64());
-        Some(CreateFork { url, enable_caching, env, evm_opts: self.clone() })
+        let endpoint = ProvidersManager::new(config.rpc_endpoints.clone())
+            .endpoint(&url)
+            .unwrap_or_else(|_| ResolvedRpcEndpoint::new(url));
+        let enable_caching = config.enable_caching(&endpoint.url, env.cfg.chain_id.as_u64());
+        Some(CreateFork {
+            endpoint,
+            enable_caching,
+            env,
+            evm_opts: self.clone(),
+            prefetch_dir: config.fork_prefetch_dir(),
+        })
+    }
+
+    /// Replaces the `fork_url` with the url of its alias, if it is an alias of the `rpc_endpoints`
+    /// of the config
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the alias references an unresolved env var.
+    pub fn resolve_fork_url(&mut self, config: &Config) -> eyre::Result<()> {
+        if let Some(ref url_or_alias) = self.fork_url {
+            let endpoint = ProvidersManager::new(config.rpc_endpoints.clone())
+                .endpoint(url_or_alias)
+                .wrap_err_with(|| format!("Failed to resolve the rpc alias `{url_or_alias}`"))?;
+            self.fork_url = Some(endpoint.url);
+        }
+        Ok(())
     }
 
     /// Returns the gas limit to use
@@ -138,6 +222,7 @@ impl EvmOpts {
 
     /// Returns the configured chain id, which will be
     ///   - the value of `chain_id` if set
+    ///   - the chain of the `chain_profile` if set
     ///   - mainnet if `fork_url` contains "mainnet"
     ///   - the chain if `fork_url` is set and the endpoints returned its chain id successfully
     ///   - mainnet otherwise
@@ -145,6 +230,9 @@ impl EvmOpts {
         if let Some(id) = self.env.chain_id {
             return id
         }
+        if let Some(profile) = self.chain_profile {
+            return profile.chain().id()
+        }
         self.get_remote_chain_id().map_or(Chain::Mainnet as u64, |id| id as u64)
     }
 
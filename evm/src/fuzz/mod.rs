@@ -2,26 +2,75 @@ use crate::{
     decode,
     executor::{Executor, RawCallResult},
     trace::CallTraceArena,
+    CALLER,
 };
 use ethers::{
     abi::{Abi, Function, Token},
-    types::{Address, Bytes, Log},
+    types::{Address, Bytes, Log, U256},
 };
 pub use proptest::test_runner::{Config as FuzzConfig, Reason};
-use proptest::test_runner::{TestCaseError, TestError, TestRunner};
+use proptest::{
+    prelude::RngCore,
+    strategy::Strategy,
+    test_runner::{RngAlgorithm, TestCaseError, TestError, TestRng, TestRunner},
+};
 
+use revm::DatabaseCommit;
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, collections::BTreeMap, fmt};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 use strategies::{
     build_initial_state, collect_state_from_call, fuzz_calldata, fuzz_calldata_from_state,
     EvmFuzzState,
 };
+pub use strategies::{is_fixture_output, FuzzFixtures, FIXTURE_PREFIX};
 
 mod strategies;
 
+mod stats;
+pub use stats::{FuzzStats, ParamStats};
+
 /// Magic return code for the `assume` cheatcode
 pub const ASSUME_MAGIC_RETURN_CODE: &[u8] = b"FOUNDRY::ASSUME";
 
+/// Returns the revert data of the `n`th `vm.assume` call of a test that rejected the input, the
+/// magic return code followed by the big endian `n`
+pub fn assume_rejection(n: u64) -> bytes::Bytes {
+    [ASSUME_MAGIC_RETURN_CODE, &n.to_be_bytes()].concat().into()
+}
+
+/// Returns the number of the `vm.assume` call that rejected the input, if the result is a
+/// rejection, see [assume_rejection].
+///
+/// The number is 0 if it's unknown.
+pub fn rejected_assume(result: &[u8]) -> Option<u64> {
+    let n = result.strip_prefix(ASSUME_MAGIC_RETURN_CODE)?;
+    Some(n.try_into().map(u64::from_be_bytes).unwrap_or_default())
+}
+
+/// Returns the rng for the `seed`, which generates the inputs of the fuzzer and the values of the
+/// `random*` cheatcodes
+pub fn seeded_rng(seed: U256) -> TestRng {
+    let mut bytes = [0u8; 32];
+    seed.to_big_endian(&mut bytes);
+    TestRng::from_seed(RngAlgorithm::ChaCha, &bytes)
+}
+
+/// Returns a random seed, used if no seed is configured
+pub fn random_seed() -> U256 {
+    let mut bytes = [0u8; 32];
+    TestRunner::default().rng().fill_bytes(&mut bytes);
+    U256::from_big_endian(&bytes)
+}
+
 /// Wrapper around an [`Executor`] which provides fuzzing support using [`proptest`](https://docs.rs/proptest/1.0.0/proptest/).
 ///
 /// After instantiation, calling `fuzz` will proceed to hammer the deployed smart contract with
@@ -34,12 +83,79 @@ pub struct FuzzedExecutor<'a> {
     runner: TestRunner,
     /// The account that calls tests
     sender: Address,
+    /// How the dictionary of values harvested from the EVM is built and used
+    dictionary: FuzzDictionaryConfig,
+    /// Values provided by the test contract for parameters with matching names
+    fixtures: FuzzFixtures,
+    /// The number of most recent successful cases whose logs are kept
+    keep_logs: usize,
+    /// Set when the run was cancelled, in which case the remaining cases are skipped
+    cancelled: Arc<AtomicBool>,
+    /// The functions of the test contract that are called before every case, in order
+    before_case: Vec<Function>,
+    /// The function of the test contract that is called after every case
+    after_case: Option<Function>,
 }
 
 impl<'a> FuzzedExecutor<'a> {
     /// Instantiates a fuzzed executor given a testrunner
     pub fn new(executor: &'a Executor, runner: TestRunner, sender: Address) -> Self {
-        Self { executor, runner, sender }
+        Self {
+            executor,
+            runner,
+            sender,
+            dictionary: Default::default(),
+            fixtures: Default::default(),
+            keep_logs: 0,
+            cancelled: Default::default(),
+            before_case: Default::default(),
+            after_case: None,
+        }
+    }
+
+    /// Sets the configuration of the fuzz dictionary
+    #[must_use]
+    pub fn with_dictionary(mut self, dictionary: FuzzDictionaryConfig) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
+    /// Sets the fixtures that are injected into the fuzz inputs
+    #[must_use]
+    pub fn with_fixtures(mut self, fixtures: FuzzFixtures) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    /// Sets the number of most recent successful cases whose logs are kept.
+    ///
+    /// The logs of the failing case are always kept.
+    #[must_use]
+    pub fn with_keep_logs(mut self, keep_logs: usize) -> Self {
+        self.keep_logs = keep_logs;
+        self
+    }
+
+    /// Sets the flag that cancels the run.
+    ///
+    /// Once set, the remaining cases pass without being executed, so the result of a cancelled
+    /// run is only meaningful if it failed.
+    #[must_use]
+    pub fn with_cancellation(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancelled = cancelled;
+        self
+    }
+
+    /// Sets the functions of the test contract that are called before every case, in order, and
+    /// the function that is called after every case, on top of the state after the case.
+    ///
+    /// A case fails if one of them reverts, or if a DSTest assertion in the function called after
+    /// the case fails.
+    #[must_use]
+    pub fn with_hooks(mut self, before_case: Vec<Function>, after_case: Option<Function>) -> Self {
+        self.before_case = before_case;
+        self.after_case = after_case;
+        self
     }
 
     /// Fuzzes the provided function, assuming it is available at the contract at `address`
@@ -57,75 +173,115 @@ impl<'a> FuzzedExecutor<'a> {
         // Stores the consumed gas and calldata of every successful fuzz call
         let cases: RefCell<Vec<FuzzCase>> = RefCell::new(Default::default());
 
+        // Stores the logs of the most recent successful calls
+        let case_logs: RefCell<CaseLogs> = RefCell::new(CaseLogs::new(self.keep_logs));
+
+        // Counts the inputs rejected by every `vm.assume` call, by the number of the call
+        let rejects: RefCell<BTreeMap<u64, usize>> = RefCell::new(Default::default());
+
         // Stores the result and calldata of the last failed call, if any.
         let counterexample: RefCell<(Bytes, RawCallResult)> = RefCell::new(Default::default());
 
         // Stores fuzz state for use with [fuzz_calldata_from_state]
-        let state: EvmFuzzState = build_initial_state(&self.executor.backend().db);
-
-        // TODO: We should have a `FuzzerOpts` struct where we can configure the fuzzer. When we
-        // have that, we should add a way to configure strategy weights
-        let strat = proptest::strategy::Union::new_weighted(vec![
-            (60, fuzz_calldata(func.clone())),
-            (40, fuzz_calldata_from_state(func.clone(), state.clone())),
-        ]);
+        let state: EvmFuzzState =
+            build_initial_state(&self.executor.backend().db, &self.dictionary);
+
+        let dictionary_weight = self.dictionary.dictionary_weight.min(100);
+        let strat = match dictionary_weight {
+            0 => fuzz_calldata(func.clone(), &self.fixtures),
+            100 => fuzz_calldata_from_state(func.clone(), state.clone(), &self.fixtures),
+            weight => proptest::strategy::Union::new_weighted(vec![
+                (100 - weight, fuzz_calldata(func.clone(), &self.fixtures)),
+                (weight, fuzz_calldata_from_state(func.clone(), state.clone(), &self.fixtures)),
+            ])
+            .boxed(),
+        };
         tracing::debug!(func = ?func.name, should_fail, "fuzzing");
         let run_result = self.runner.clone().run(&strat, |calldata| {
-            let call = self
-                .executor
+            if self.cancelled.load(Ordering::Relaxed) {
+                return Ok(())
+            }
+
+            // Run the hooks that precede every case on top of the state shared by all cases
+            let mut executor = Cow::Borrowed(self.executor);
+            let mut logs = Vec::new();
+            for hook in &self.before_case {
+                let call = executor
+                    .to_mut()
+                    .call_raw_committing(CALLER, address, hook_calldata(hook), 0.into())
+                    .expect("could not make raw evm call");
+                if call.reverted {
+                    let reason = format!("Setup failed: {}", revert_reason(&call, errors));
+                    *counterexample.borrow_mut() = (calldata, call);
+                    return Err(TestCaseError::fail(reason))
+                }
+                logs.extend(call.logs);
+            }
+
+            let call = executor
                 .call_raw(self.sender, address, calldata.0.clone(), 0.into())
                 .expect("could not make raw evm call");
             let state_changeset =
                 call.state_changeset.as_ref().expect("we should have a state changeset");
 
             // Build fuzzer state
-            collect_state_from_call(&call.logs, state_changeset, state.clone());
+            collect_state_from_call(&call.logs, state_changeset, state.clone(), &self.dictionary);
 
             // When assume cheat code is triggered return a special string "FOUNDRY::ASSUME"
-            if call.result.as_ref() == ASSUME_MAGIC_RETURN_CODE {
+            if let Some(n) = rejected_assume(call.result.as_ref()) {
+                *rejects.borrow_mut().entry(n).or_default() += 1;
                 return Err(TestCaseError::reject("ASSUME: Too many rejects"))
             }
 
-            let success = self.executor.is_success(
-                address,
-                call.reverted,
-                state_changeset.clone(),
-                should_fail,
-            );
-
-            if success {
-                cases.borrow_mut().push(FuzzCase {
-                    calldata,
-                    gas: call.gas,
-                    stipend: call.stipend,
-                });
-                Ok(())
-            } else {
-                let status = call.status;
+            let success =
+                executor.is_success(address, call.reverted, state_changeset.clone(), should_fail);
+            if !success {
                 // We cannot use the calldata returned by the test runner in `TestError::Fail`,
                 // since that input represents the last run case, which may not correspond with our
                 // failure - when a fuzz case fails, proptest will try to run at least one more
                 // case to find a minimal failure case.
+                let reason = revert_reason(&call, errors);
                 *counterexample.borrow_mut() = (calldata, call);
-                Err(TestCaseError::fail(
-                    match decode::decode_revert(
-                        counterexample.borrow().1.result.as_ref(),
-                        errors,
-                        Some(status),
-                    ) {
-                        Ok(e) => e,
-                        Err(_) => "".to_string(),
-                    },
-                ))
+                return Err(TestCaseError::fail(reason))
+            }
+
+            // Run the hook that follows every case on top of the state after the case
+            let mut after_logs = Vec::new();
+            if let Some(hook) = &self.after_case {
+                let executor = executor.to_mut();
+                executor.backend_mut().commit(state_changeset.clone());
+                let after = executor
+                    .call_raw_committing(CALLER, address, hook_calldata(hook), 0.into())
+                    .expect("could not make raw evm call");
+                if !executor.is_success(address, after.reverted, Default::default(), false) {
+                    let reason = if after.reverted {
+                        revert_reason(&after, errors)
+                    } else {
+                        "assertion failed".to_string()
+                    };
+                    let reason = format!("{}() failed: {}", hook.name, reason);
+                    *counterexample.borrow_mut() = (calldata, after);
+                    return Err(TestCaseError::fail(reason))
+                }
+                after_logs = after.logs;
             }
+
+            cases.borrow_mut().push(FuzzCase { calldata, gas: call.gas, stipend: call.stipend });
+            logs.extend(call.logs);
+            logs.extend(after_logs);
+            case_logs.borrow_mut().push(logs);
+            Ok(())
         });
 
         let (calldata, call) = counterexample.into_inner();
+        let cases = cases.into_inner();
         let mut result = FuzzTestResult {
-            cases: FuzzedCases::new(cases.into_inner()),
+            stats: FuzzStats::new(func, &cases, rejects.into_inner()),
+            cases: FuzzedCases::new(cases),
             success: run_result.is_ok(),
             reason: None,
             counterexample: None,
+            case_logs: case_logs.into_inner().into_logs(),
             logs: call.logs,
             traces: call.traces,
             labeled_addresses: call.labels,
@@ -133,7 +289,11 @@ impl<'a> FuzzedExecutor<'a> {
 
         match run_result {
             Err(TestError::Abort(reason)) => {
-                result.reason = Some(reason.to_string());
+                let mut reason = reason.to_string();
+                if let Some(diagnostic) = result.stats.assume_diagnostic() {
+                    reason = format!("{reason}. {diagnostic}");
+                }
+                result.reason = Some(reason);
             }
             Err(TestError::Fail(reason, _)) => {
                 let reason = reason.to_string();
@@ -151,6 +311,69 @@ impl<'a> FuzzedExecutor<'a> {
     }
 }
 
+/// Returns the calldata that calls the given function of the test contract, which takes no
+/// arguments
+fn hook_calldata(hook: &Function) -> Bytes {
+    hook.short_signature().to_vec().into()
+}
+
+/// Returns the decoded revert reason of the call, or an empty string if it can't be decoded
+fn revert_reason(call: &RawCallResult, errors: Option<&Abi>) -> String {
+    decode::decode_revert(call.result.as_ref(), errors, Some(call.status)).unwrap_or_default()
+}
+
+/// A bounded collection of the logs of the most recent fuzz cases, without duplicates
+#[derive(Debug, Default)]
+struct CaseLogs {
+    /// The maximum number of log sequences to keep
+    limit: usize,
+    logs: VecDeque<Vec<Log>>,
+}
+
+impl CaseLogs {
+    fn new(limit: usize) -> Self {
+        Self { limit, logs: VecDeque::with_capacity(limit) }
+    }
+
+    /// Adds the logs of a case, evicting the oldest logs if the limit is reached.
+    ///
+    /// If the same logs are already kept, they are moved to the back instead.
+    fn push(&mut self, logs: Vec<Log>) {
+        if self.limit == 0 || logs.is_empty() {
+            return
+        }
+        if let Some(idx) = self.logs.iter().position(|existing| *existing == logs) {
+            self.logs.remove(idx);
+        } else if self.logs.len() == self.limit {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(logs);
+    }
+
+    fn into_logs(self) -> Vec<Vec<Log>> {
+        self.logs.into()
+    }
+}
+
+/// Configures the dictionary of values harvested from the EVM that fuzz inputs are drawn from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FuzzDictionaryConfig {
+    /// Whether to include the storage slots and values of all accounts
+    pub include_storage: bool,
+    /// Whether to include the PUSH values of the bytecode of all accounts
+    pub include_push_bytes: bool,
+    /// The percentage of fuzz inputs that are generated from the dictionary, between 0 and 100.
+    ///
+    /// The remaining inputs are generated randomly.
+    pub dictionary_weight: u32,
+}
+
+impl Default for FuzzDictionaryConfig {
+    fn default() -> Self {
+        Self { include_storage: true, include_push_bytes: true, dictionary_weight: 40 }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CounterExample {
     pub calldata: Bytes,
@@ -188,11 +411,19 @@ pub struct FuzzTestResult {
     /// be printed to the user.
     pub logs: Vec<Log>,
 
+    /// The logs of the most recent successful cases, oldest first.
+    ///
+    /// Identical log sequences are only kept once.
+    pub case_logs: Vec<Vec<Log>>,
+
     /// Traces
     pub traces: Option<CallTraceArena>,
 
     /// Labeled addresses
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// The distribution of the inputs of the successful cases
+    pub stats: FuzzStats,
 }
 
 /// Container type for all successful test cases
@@ -272,3 +503,27 @@ pub struct FuzzCase {
     /// The initial gas stipend for the transaction
     pub stipend: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_logs_are_bounded_and_deduplicated() {
+        let log = |n: u64| Log { log_index: Some(n.into()), ..Default::default() };
+
+        let mut case_logs = CaseLogs::new(2);
+        case_logs.push(vec![log(1)]);
+        case_logs.push(vec![]);
+        case_logs.push(vec![log(2)]);
+        case_logs.push(vec![log(1)]);
+        assert_eq!(case_logs.logs, vec![vec![log(2)], vec![log(1)]]);
+
+        case_logs.push(vec![log(3)]);
+        assert_eq!(case_logs.into_logs(), vec![vec![log(1)], vec![log(3)]]);
+
+        let mut disabled = CaseLogs::new(0);
+        disabled.push(vec![log(1)]);
+        assert!(disabled.into_logs().is_empty());
+    }
+}
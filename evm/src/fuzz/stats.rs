@@ -0,0 +1,274 @@
+use super::FuzzCase;
+use ethers::{
+    abi::{Function, Token},
+    types::{I256, U256},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+/// The number of buckets of the histograms of the parameters
+pub const HISTOGRAM_BUCKETS: usize = 10;
+
+/// The width of the bar of the fullest bucket when printing a histogram
+const BAR_WIDTH: usize = 30;
+
+/// Statistics of the inputs of the successful cases of a fuzz test, to tell whether the fuzzer
+/// explores the input space
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FuzzStats {
+    /// The number of successful cases
+    pub runs: usize,
+    /// The number of inputs that were rejected by `vm.assume`
+    pub rejects: usize,
+    /// The number of inputs rejected by every `vm.assume` call, by the number of the call in the
+    /// test, starting at 1
+    pub rejects_by_assume: BTreeMap<u64, usize>,
+    /// The statistics of the parameters that can be measured, in the order of the parameters
+    pub params: Vec<ParamStats>,
+}
+
+/// The distribution of a single parameter.
+///
+/// Integers are measured by their value, booleans as 0 or 1 and dynamic bytes, strings and arrays
+/// by their length.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParamStats {
+    /// The name of the parameter, or its index if it's unnamed
+    pub name: String,
+    /// The Solidity type of the parameter
+    pub ty: String,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// The number of inputs in each of the equally wide buckets between `min` and `max`
+    pub histogram: Vec<usize>,
+}
+
+impl FuzzStats {
+    /// Collects the statistics of the inputs of the `cases` of `func`, and the numbers of inputs
+    /// rejected by every `vm.assume` call
+    pub fn new(
+        func: &Function,
+        cases: &[FuzzCase],
+        rejects_by_assume: BTreeMap<u64, usize>,
+    ) -> Self {
+        let inputs: Vec<Vec<Token>> = cases
+            .iter()
+            .filter_map(|case| func.decode_input(case.calldata.get(4..)?).ok())
+            .collect();
+
+        let params = func
+            .inputs
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, param)| {
+                let values = inputs
+                    .iter()
+                    .map(|tokens| tokens.get(idx).and_then(measure))
+                    .collect::<Option<Vec<_>>>()?;
+                let name = if param.name.is_empty() { idx.to_string() } else { param.name.clone() };
+                ParamStats::new(name, param.kind.to_string(), &values)
+            })
+            .collect();
+
+        let rejects = rejects_by_assume.values().sum();
+        Self { runs: cases.len(), rejects, rejects_by_assume, params }
+    }
+
+    /// The share of the generated inputs that were rejected by `vm.assume`, between 0 and 1
+    pub fn rejection_rate(&self) -> f64 {
+        let total = self.runs + self.rejects;
+        if total == 0 {
+            0.
+        } else {
+            self.rejects as f64 / total as f64
+        }
+    }
+
+    /// Returns a diagnostic of the rejected inputs, naming the `vm.assume` call that rejected most
+    /// of them and suggesting to generate the inputs in range with `bound` instead.
+    ///
+    /// Returns `None` if no inputs were rejected.
+    pub fn assume_diagnostic(&self) -> Option<String> {
+        let (n, rejects) = self.rejects_by_assume.iter().max_by_key(|(_, rejects)| **rejects)?;
+        let mut diagnostic = format!("vm.assume rejected {} inputs", self.rejects);
+        if *n > 0 {
+            diagnostic.push_str(&format!(
+                ", {} of them ({:.2}%) by vm.assume call #{} of the test",
+                rejects,
+                *rejects as f64 / self.rejects as f64 * 100.,
+                n
+            ));
+        }
+        diagnostic.push_str(
+            ". Instead of rejecting inputs, generate them in range with `bound(x, min, max)`",
+        );
+
+        // the accepted inputs of the integer parameters hint at the range the test needs
+        let ranges = self
+            .params
+            .iter()
+            .filter(|param| param.ty.starts_with("uint") || param.ty.starts_with("int"))
+            .map(|param| {
+                format!(
+                    "{0} = bound({0}, {1}, {2});",
+                    param.name,
+                    format_measure(param.min),
+                    format_measure(param.max)
+                )
+            })
+            .collect::<Vec<_>>();
+        if !ranges.is_empty() {
+            diagnostic
+                .push_str(&format!(", e.g. based on the accepted inputs: `{}`", ranges.join(" ")));
+        }
+        Some(diagnostic)
+    }
+}
+
+impl ParamStats {
+    /// Returns the statistics of the `values`, or `None` if there are none
+    fn new(name: String, ty: String, values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None
+        }
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let mean = values.iter().map(|value| value / values.len() as f64).sum();
+
+        let mut histogram = vec![0; HISTOGRAM_BUCKETS];
+        let width = (max - min) / HISTOGRAM_BUCKETS as f64;
+        for value in values {
+            let bucket = if width > 0. { ((value - min) / width) as usize } else { 0 };
+            histogram[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+        }
+
+        Some(Self { name, ty, min, max, mean, histogram })
+    }
+}
+
+/// Returns the numeric measure of a fuzz input, if it has one
+fn measure(token: &Token) -> Option<f64> {
+    match token {
+        Token::Uint(value) => Some(u256_to_f64(*value)),
+        Token::Int(value) => {
+            let value = I256::from_raw(*value);
+            let abs = u256_to_f64(value.unsigned_abs());
+            Some(if value.is_negative() { -abs } else { abs })
+        }
+        Token::Bool(value) => Some(if *value { 1. } else { 0. }),
+        Token::Bytes(bytes) => Some(bytes.len() as f64),
+        Token::String(s) => Some(s.len() as f64),
+        Token::Array(tokens) => Some(tokens.len() as f64),
+        _ => None,
+    }
+}
+
+fn u256_to_f64(value: U256) -> f64 {
+    value.0.iter().rev().fold(0., |acc, limb| acc * 2f64.powi(64) + *limb as f64)
+}
+
+/// Formats a measure as an integer if it's small enough, in scientific notation otherwise
+fn format_measure(value: f64) -> String {
+    if value.abs() < 1e15 {
+        if value.fract() == 0. {
+            format!("{value}")
+        } else {
+            format!("{value:.2}")
+        }
+    } else {
+        format!("{value:.3e}")
+    }
+}
+
+impl fmt::Display for FuzzStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} runs, {} inputs rejected by vm.assume ({:.2}%)",
+            self.runs,
+            self.rejects,
+            self.rejection_rate() * 100.
+        )?;
+        for param in &self.params {
+            write!(f, "\n{param}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ParamStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): min {}, max {}, mean {}",
+            self.name,
+            self.ty,
+            format_measure(self.min),
+            format_measure(self.max),
+            format_measure(self.mean)
+        )?;
+        if self.min == self.max {
+            return Ok(())
+        }
+
+        let fullest = self.histogram.iter().copied().max().unwrap_or_default().max(1);
+        let width = (self.max - self.min) / self.histogram.len() as f64;
+        for (idx, count) in self.histogram.iter().enumerate() {
+            let start = self.min + width * idx as f64;
+            let bar = "█".repeat((count * BAR_WIDTH + fullest - 1) / fullest);
+            write!(f, "\n  {:>10} | {bar} {count}", format_measure(start))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{AbiEncode, Param, ParamType};
+
+    #[test]
+    fn can_collect_input_stats() {
+        #[allow(deprecated)]
+        let func = Function {
+            name: "testFuzz".to_string(),
+            inputs: vec![
+                Param {
+                    name: "amount".to_string(),
+                    kind: ParamType::Uint(256),
+                    internal_type: None,
+                },
+                Param { name: "".to_string(), kind: ParamType::Address, internal_type: None },
+            ],
+            outputs: vec![],
+            constant: None,
+            state_mutability: ethers::abi::StateMutability::NonPayable,
+        };
+        let cases = (0..100u64)
+            .map(|amount| {
+                let mut calldata = func.short_signature().to_vec();
+                calldata.extend((U256::from(amount), ethers::types::Address::zero()).encode());
+                FuzzCase { calldata: calldata.into(), gas: 0, stipend: 0 }
+            })
+            .collect::<Vec<_>>();
+
+        let stats = FuzzStats::new(&func, &cases, BTreeMap::from([(1, 5), (2, 20)]));
+        assert_eq!(stats.runs, 100);
+        assert_eq!(stats.rejection_rate(), 0.2);
+        // addresses are not measured
+        assert_eq!(stats.params.len(), 1);
+        let amount = &stats.params[0];
+        assert_eq!((amount.min, amount.max), (0., 99.));
+        assert!((amount.mean - 49.5).abs() < 1e-9);
+        assert_eq!(amount.histogram, vec![10; HISTOGRAM_BUCKETS]);
+        assert!(stats
+            .to_string()
+            .starts_with("100 runs, 25 inputs rejected by vm.assume (20.00%)"));
+
+        let diagnostic = stats.assume_diagnostic().unwrap();
+        assert!(diagnostic.contains("20 of them (80.00%) by vm.assume call #2"));
+        assert!(diagnostic.contains("`amount = bound(amount, 0, 99);`"));
+        assert!(FuzzStats::new(&func, &cases, BTreeMap::new()).assume_diagnostic().is_none());
+    }
+}
@@ -1,13 +1,17 @@
-use super::fuzz_param;
+use super::{fuzz_param, FuzzFixtures};
 use ethers::{abi::Function, types::Bytes};
 use proptest::prelude::{BoxedStrategy, Strategy};
 
 /// Given a function, it returns a strategy which generates valid calldata
-/// for that function's input types.
-pub fn fuzz_calldata(func: Function) -> BoxedStrategy<Bytes> {
+/// for that function's input types, mixing in the given fixtures.
+pub fn fuzz_calldata(func: Function, fixtures: &FuzzFixtures) -> BoxedStrategy<Bytes> {
     // We need to compose all the strategies generated for each parameter in all
     // possible combinations
-    let strats = func.inputs.iter().map(|input| fuzz_param(&input.kind)).collect::<Vec<_>>();
+    let strats = func
+        .inputs
+        .iter()
+        .map(|input| fixtures.with_fixtures(input, fuzz_param(&input.kind).boxed()))
+        .collect::<Vec<_>>();
 
     strats
         .prop_map(move |tokens| {
@@ -0,0 +1,109 @@
+use ethers::abi::{Param, ParamType, Token};
+use proptest::prelude::{BoxedStrategy, Strategy};
+use std::collections::BTreeMap;
+
+/// The prefix of functions returning fuzz fixtures, e.g. `fixtureAmount()`
+pub const FIXTURE_PREFIX: &str = "fixture";
+
+/// Values declared by a test contract that are injected into the fuzz inputs of parameters with a
+/// matching name.
+///
+/// A function `fixtureAmount() returns (uint256[] memory)` provides the values for every parameter
+/// named `amount` (or `_amount`).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FuzzFixtures {
+    fixtures: BTreeMap<String, Vec<Token>>,
+}
+
+impl FuzzFixtures {
+    /// Adds the values returned by the fixture function with the given name.
+    ///
+    /// Returns `false` if the name is not a fixture function name.
+    pub fn insert(&mut self, fn_name: &str, values: Vec<Token>) -> bool {
+        match param_name(fn_name) {
+            Some(name) => {
+                self.fixtures.entry(name).or_default().extend(values);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` if there are no fixtures
+    pub fn is_empty(&self) -> bool {
+        self.fixtures.is_empty()
+    }
+
+    /// Returns the fixture values for the given parameter that match its type, if any
+    pub fn param_fixtures(&self, param: &Param) -> Option<Vec<Token>> {
+        let values = self
+            .fixtures
+            .get(param.name.trim_start_matches('_'))?
+            .iter()
+            .filter(|value| value.type_check(&param.kind))
+            .cloned()
+            .collect::<Vec<_>>();
+        (!values.is_empty()).then_some(values)
+    }
+
+    /// Returns a strategy that picks from the fixtures of the parameter half of the time, and from
+    /// `strategy` otherwise
+    pub fn with_fixtures(
+        &self,
+        param: &Param,
+        strategy: BoxedStrategy<Token>,
+    ) -> BoxedStrategy<Token> {
+        match self.param_fixtures(param) {
+            Some(values) => proptest::strategy::Union::new(vec![
+                proptest::sample::select(values).boxed(),
+                strategy,
+            ])
+            .boxed(),
+            None => strategy,
+        }
+    }
+}
+
+/// Returns the name of the parameter the fixture function with the given name provides values
+/// for, e.g. `amount` for `fixtureAmount`
+fn param_name(fn_name: &str) -> Option<String> {
+    let name = fn_name.strip_prefix(FIXTURE_PREFIX)?;
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    Some(first.to_lowercase().chain(chars).collect())
+}
+
+/// Returns `true` if the output of a fixture function can be used as fuzz fixtures
+pub fn is_fixture_output(outputs: &[Param]) -> bool {
+    matches!(outputs, [Param { kind: ParamType::Array(_) | ParamType::FixedArray(_, _), .. }])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::U256;
+
+    #[test]
+    fn can_match_fixtures() {
+        let mut fixtures = FuzzFixtures::default();
+        assert!(fixtures.insert(
+            "fixtureAmount",
+            vec![Token::Uint(U256::from(1)), Token::Bool(true), Token::Uint(U256::MAX)]
+        ));
+        assert!(!fixtures.insert("amount", vec![]));
+        assert!(!fixtures.insert("fixture", vec![]));
+
+        let param = |name: &str, kind| Param { name: name.to_string(), kind, internal_type: None };
+        let expected = vec![Token::Uint(U256::from(1)), Token::Uint(U256::MAX)];
+        assert_eq!(
+            fixtures.param_fixtures(&param("amount", ParamType::Uint(256))),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            fixtures.param_fixtures(&param("_amount", ParamType::Uint(256))),
+            Some(expected)
+        );
+        assert_eq!(fixtures.param_fixtures(&param("amount", ParamType::Address)), None);
+        assert_eq!(fixtures.param_fixtures(&param("other", ParamType::Uint(256))), None);
+    }
+}
@@ -4,6 +4,9 @@ pub use uint::UintStrategy;
 mod param;
 pub use param::{fuzz_param, fuzz_param_from_state};
 
+mod fixtures;
+pub use fixtures::{is_fixture_output, FuzzFixtures, FIXTURE_PREFIX};
+
 mod calldata;
 pub use calldata::fuzz_calldata;
 
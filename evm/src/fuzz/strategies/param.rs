@@ -146,7 +146,9 @@ pub fn fuzz_param_from_state(param: &ParamType, state: EvmFuzzState) -> BoxedStr
 
 #[cfg(test)]
 mod tests {
-    use crate::fuzz::strategies::{build_initial_state, fuzz_calldata, fuzz_calldata_from_state};
+    use crate::fuzz::strategies::{
+        build_initial_state, fuzz_calldata, fuzz_calldata_from_state, FuzzFixtures,
+    };
     use ethers::abi::HumanReadableParser;
     use revm::db::{CacheDB, EmptyDB};
 
@@ -156,11 +158,12 @@ mod tests {
         let func = HumanReadableParser::parse_function(f).unwrap();
 
         let db = CacheDB::new(EmptyDB());
-        let state = build_initial_state(&db);
+        let state = build_initial_state(&db, &Default::default());
+        let fixtures = FuzzFixtures::default();
 
         let strat = proptest::strategy::Union::new_weighted(vec![
-            (60, fuzz_calldata(func.clone())),
-            (40, fuzz_calldata_from_state(func, state)),
+            (60, fuzz_calldata(func.clone(), &fixtures)),
+            (40, fuzz_calldata_from_state(func, state, &fixtures)),
         ]);
 
         let cfg = proptest::test_runner::Config { failure_persistence: None, ..Default::default() };
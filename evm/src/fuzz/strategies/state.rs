@@ -1,5 +1,5 @@
-use super::fuzz_param_from_state;
-use crate::{executor::StateChangeset, utils};
+use super::{fuzz_param_from_state, FuzzFixtures};
+use crate::{executor::StateChangeset, fuzz::FuzzDictionaryConfig, utils};
 use bytes::Bytes;
 use ethers::{
     abi::Function,
@@ -18,15 +18,18 @@ use std::{cell::RefCell, collections::HashSet, io::Write, rc::Rc};
 pub type EvmFuzzState = Rc<RefCell<HashSet<[u8; 32]>>>;
 
 /// Given a function and some state, it returns a strategy which generated valid calldata for the
-/// given function's input types, based on state taken from the EVM.
+/// given function's input types, based on state taken from the EVM and the given fixtures.
 pub fn fuzz_calldata_from_state(
     func: Function,
     state: EvmFuzzState,
+    fixtures: &FuzzFixtures,
 ) -> BoxedStrategy<ethers::types::Bytes> {
     let strats = func
         .inputs
         .iter()
-        .map(|input| fuzz_param_from_state(&input.kind, state.clone()))
+        .map(|input| {
+            fixtures.with_fixtures(input, fuzz_param_from_state(&input.kind, state.clone()))
+        })
         .collect::<Vec<_>>();
 
     strats
@@ -47,7 +50,10 @@ This is a bug, please open an issue: https://github.com/foundry-rs/foundry/issue
 }
 
 /// Builds the initial [EvmFuzzState] from a database.
-pub fn build_initial_state<DB: DatabaseRef>(db: &CacheDB<DB>) -> EvmFuzzState {
+pub fn build_initial_state<DB: DatabaseRef>(
+    db: &CacheDB<DB>,
+    config: &FuzzDictionaryConfig,
+) -> EvmFuzzState {
     let mut state: HashSet<[u8; 32]> = HashSet::new();
     for (address, account) in db.accounts.iter() {
         let info = db.basic(*address);
@@ -58,9 +64,17 @@ pub fn build_initial_state<DB: DatabaseRef>(db: &CacheDB<DB>) -> EvmFuzzState {
         state.insert(utils::u256_to_h256_le(U256::from(info.nonce)).into());
 
         // Insert storage
-        for (slot, value) in &account.storage {
-            state.insert(utils::u256_to_h256_le(*slot).into());
-            state.insert(utils::u256_to_h256_le(*value).into());
+        if config.include_storage {
+            for (slot, value) in &account.storage {
+                state.insert(utils::u256_to_h256_le(*slot).into());
+                state.insert(utils::u256_to_h256_le(*value).into());
+            }
+        }
+
+        // Insert push bytes
+        if config.include_push_bytes {
+            let code = info.code.clone().unwrap_or_else(|| db.code_by_hash(info.code_hash));
+            state.extend(collect_push_bytes(code));
         }
     }
 
@@ -79,6 +93,7 @@ pub fn collect_state_from_call(
     logs: &[Log],
     state_changeset: &StateChangeset,
     state: EvmFuzzState,
+    config: &FuzzDictionaryConfig,
 ) {
     let state = &mut *state.borrow_mut();
 
@@ -89,15 +104,19 @@ pub fn collect_state_from_call(
         state.insert(utils::u256_to_h256_le(U256::from(account.info.nonce)).into());
 
         // Insert storage
-        for (slot, value) in &account.storage {
-            state.insert(utils::u256_to_h256_le(*slot).into());
-            state.insert(utils::u256_to_h256_le(*value).into());
+        if config.include_storage {
+            for (slot, value) in &account.storage {
+                state.insert(utils::u256_to_h256_le(*slot).into());
+                state.insert(utils::u256_to_h256_le(*value).into());
+            }
         }
 
         // Insert push bytes
-        if let Some(code) = &account.info.code {
-            for push_byte in collect_push_bytes(code.clone()) {
-                state.insert(push_byte);
+        if config.include_push_bytes {
+            if let Some(code) = &account.info.code {
+                for push_byte in collect_push_bytes(code.clone()) {
+                    state.insert(push_byte);
+                }
             }
         }
 
@@ -23,6 +23,9 @@ pub mod fuzz;
 /// utils for working with revm
 pub mod utils;
 
+/// Storage layout comparison
+pub mod storage_layout;
+
 // Re-exports
 pub use ethers::types::Address;
 pub use hashbrown::{self, HashMap};
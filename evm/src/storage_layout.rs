@@ -0,0 +1,506 @@
+use ethers::{
+    abi::{
+        param_type::Reader,
+        token::{LenientTokenizer, Tokenizer},
+        ParamType, Token,
+    },
+    solc::artifacts::{StorageLayout, StorageType},
+    types::U256,
+    utils::keccak256,
+};
+use std::{collections::BTreeMap, fmt};
+
+/// A state variable of a [StorageLayout], resolved to the bytes of storage it occupies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageVariable {
+    /// The name of the variable
+    pub label: String,
+    /// The type of the variable, e.g. `uint256` or `mapping(address => uint256)`
+    pub type_label: String,
+    /// The slot the variable starts at
+    pub slot: U256,
+    /// The offset of the variable within its first slot, in bytes
+    pub offset: u64,
+    /// The number of bytes the variable occupies
+    pub bytes: U256,
+    /// The contract that declares the variable
+    pub contract: String,
+}
+
+impl StorageVariable {
+    /// Returns the variables of the given layout
+    pub fn from_layout(layout: &StorageLayout) -> eyre::Result<Vec<Self>> {
+        layout
+            .storage
+            .iter()
+            .map(|storage| {
+                let storage_type = layout.types.get(&storage.storage_type);
+                Ok(Self {
+                    label: storage.label.clone(),
+                    type_label: storage_type
+                        .map(|ty| ty.label.clone())
+                        .unwrap_or_else(|| storage.storage_type.clone()),
+                    slot: U256::from_dec_str(&storage.slot)?,
+                    offset: storage.offset as u64,
+                    bytes: storage_type
+                        .map(|ty| U256::from_dec_str(&ty.number_of_bytes))
+                        .transpose()?
+                        .unwrap_or_else(|| 32.into()),
+                    contract: storage.contract.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// The first byte of storage the variable occupies, counting from the start of slot 0
+    fn start(&self) -> U256 {
+        self.slot * 32 + self.offset
+    }
+
+    /// The byte after the last byte of storage the variable occupies
+    fn end(&self) -> U256 {
+        self.start() + self.bytes
+    }
+
+    /// Returns `true` if both variables occupy at least one common byte of storage
+    fn overlaps(&self, other: &Self) -> bool {
+        self.start() < other.end() && other.start() < self.end()
+    }
+}
+
+/// A change between two versions of a storage layout, see [diff_storage_layouts]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageLayoutChange {
+    /// A variable was added to previously unused storage
+    Added { new: StorageVariable },
+    /// A variable was removed, leaving its values behind in storage
+    Removed { old: StorageVariable },
+    /// A variable now starts at a different position, so its previous values are lost
+    Moved { old: StorageVariable, new: StorageVariable },
+    /// A variable changed its type, so its previous values are reinterpreted
+    Retyped { old: StorageVariable, new: StorageVariable },
+    /// A variable of a new name took the position of a removed variable, inheriting its values
+    Renamed { old: StorageVariable, new: StorageVariable },
+    /// A variable occupies storage that was used by a different variable
+    Collision { old: StorageVariable, new: StorageVariable },
+}
+
+impl StorageLayoutChange {
+    /// Returns `true` if the change breaks contracts whose storage was written with the old
+    /// layout, e.g. the implementation behind a proxy
+    pub fn is_breaking(&self) -> bool {
+        !matches!(self, StorageLayoutChange::Added { .. } | StorageLayoutChange::Removed { .. })
+    }
+}
+
+impl fmt::Display for StorageLayoutChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageLayoutChange::Added { new } => {
+                write!(f, "added `{}` ({}) at slot {}", new.label, new.type_label, new.slot)
+            }
+            StorageLayoutChange::Removed { old } => {
+                write!(f, "removed `{}` ({}) from slot {}", old.label, old.type_label, old.slot)
+            }
+            StorageLayoutChange::Moved { old, new } => write!(
+                f,
+                "moved `{}` from slot {} (offset {}) to slot {} (offset {})",
+                new.label, old.slot, old.offset, new.slot, new.offset
+            ),
+            StorageLayoutChange::Retyped { old, new } => write!(
+                f,
+                "changed the type of `{}` at slot {} from {} to {}",
+                new.label, new.slot, old.type_label, new.type_label
+            ),
+            StorageLayoutChange::Renamed { old, new } if old.type_label == new.type_label => {
+                write!(
+                    f,
+                    "renamed `{}` to `{}` ({}) at slot {}",
+                    old.label, new.label, new.type_label, new.slot
+                )
+            }
+            StorageLayoutChange::Renamed { old, new } => write!(
+                f,
+                "replaced `{}` ({}) with `{}` ({}) at slot {}",
+                old.label, old.type_label, new.label, new.type_label, new.slot
+            ),
+            StorageLayoutChange::Collision { old, new } => write!(
+                f,
+                "`{}` ({}) at slot {} collides with `{}` ({}) of the old layout",
+                new.label, new.type_label, new.slot, old.label, old.type_label
+            ),
+        }
+    }
+}
+
+/// Compares the storage layout of a new version of a contract with the layout of the old
+/// version, returning the changes ordered by the position of the affected variables
+///
+/// A new variable is compared with the old variable of the same name, or else with the removed
+/// variable at the same slot and offset, which is reported as renamed. Both are then compared by
+/// their position and type.
+pub fn diff_storage_layouts(
+    old: &StorageLayout,
+    new: &StorageLayout,
+) -> eyre::Result<Vec<StorageLayoutChange>> {
+    let old = StorageVariable::from_layout(old)?;
+    let new = StorageVariable::from_layout(new)?;
+    let old_by_label = old.iter().map(|var| (&var.label, var)).collect::<BTreeMap<_, _>>();
+    let new_by_label = new.iter().map(|var| (&var.label, var)).collect::<BTreeMap<_, _>>();
+
+    let mut changes = Vec::new();
+    for new_var in &new {
+        // The old variable whose storage the new variable takes over: the variable of the same
+        // name, or else a removed variable at the same position
+        let matched = old_by_label.get(&new_var.label).copied().or_else(|| {
+            old.iter().find(|old_var| {
+                old_var.start() == new_var.start() && !new_by_label.contains_key(&old_var.label)
+            })
+        });
+        match matched {
+            Some(old_var) if old_var.start() != new_var.start() => {
+                changes.push(StorageLayoutChange::Moved {
+                    old: old_var.clone(),
+                    new: new_var.clone(),
+                });
+            }
+            Some(old_var) if old_var.label != new_var.label => {
+                changes.push(StorageLayoutChange::Renamed {
+                    old: old_var.clone(),
+                    new: new_var.clone(),
+                });
+            }
+            Some(old_var) if old_var.type_label != new_var.type_label => {
+                changes.push(StorageLayoutChange::Retyped {
+                    old: old_var.clone(),
+                    new: new_var.clone(),
+                });
+            }
+            _ => {}
+        }
+
+        let collisions = old
+            .iter()
+            .filter(|old_var| Some(*old_var) != matched && old_var.overlaps(new_var))
+            .map(|old_var| StorageLayoutChange::Collision {
+                old: old_var.clone(),
+                new: new_var.clone(),
+            })
+            .collect::<Vec<_>>();
+        if matched.is_none() && collisions.is_empty() {
+            changes.push(StorageLayoutChange::Added { new: new_var.clone() });
+        }
+        changes.extend(collisions);
+    }
+
+    // Variables that were renamed or overwritten by a new variable are already reported
+    for old_var in old.iter().filter(|var| !new_by_label.contains_key(&var.label)) {
+        if !new.iter().any(|new_var| new_var.overlaps(old_var)) {
+            changes.push(StorageLayoutChange::Removed { old: old_var.clone() });
+        }
+    }
+
+    changes.sort_by_key(|change| match change {
+        StorageLayoutChange::Removed { old } => old.start(),
+        StorageLayoutChange::Added { new } |
+        StorageLayoutChange::Moved { new, .. } |
+        StorageLayoutChange::Retyped { new, .. } |
+        StorageLayoutChange::Renamed { new, .. } |
+        StorageLayoutChange::Collision { new, .. } => new.start(),
+    });
+    Ok(changes)
+}
+
+/// The storage location of a value, see [compute_storage_slot]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageSlot {
+    /// The slot the value starts at
+    pub slot: U256,
+    /// The offset of the value within its slot, in bytes
+    pub offset: u64,
+    /// The type of the value, e.g. `uint256`
+    pub type_label: String,
+}
+
+/// Computes the storage slot of an expression like `balances[0xabc]`, `owners[1].amount` or
+/// `allowances[0xabc][0xdef]` from the storage layout of a contract.
+///
+/// Mapping keys are given as on the command line: value types are ABI encoded, `string` keys are
+/// hashed as their UTF-8 bytes and `bytes` keys as their hex decoded bytes.
+pub fn compute_storage_slot(layout: &StorageLayout, expression: &str) -> eyre::Result<StorageSlot> {
+    let expression = expression.trim();
+    let end = expression.find(|c| c == '[' || c == '.').unwrap_or(expression.len());
+    let (label, mut rest) = expression.split_at(end);
+    let var = layout
+        .storage
+        .iter()
+        .find(|storage| storage.label == label)
+        .ok_or_else(|| eyre::eyre!("No state variable named `{}`", label))?;
+
+    let mut slot = U256::from_dec_str(&var.slot)?;
+    let mut offset = var.offset as u64;
+    let mut type_id = var.storage_type.clone();
+
+    while !rest.is_empty() {
+        let ty = storage_type(layout, &type_id)?;
+        if let Some(member) = rest.strip_prefix('.') {
+            let end = member.find(|c| c == '[' || c == '.').unwrap_or(member.len());
+            let (name, remaining) = member.split_at(end);
+            rest = remaining;
+
+            let members = ty
+                .other
+                .get("members")
+                .and_then(|members| members.as_array())
+                .ok_or_else(|| eyre::eyre!("`{}` is not a struct", ty.label))?;
+            let member = members
+                .iter()
+                .find(|member| member["label"] == name)
+                .ok_or_else(|| eyre::eyre!("`{}` has no member named `{}`", ty.label, name))?;
+            slot += U256::from_dec_str(member["slot"].as_str().unwrap_or("0"))?;
+            offset = member["offset"].as_u64().unwrap_or_default();
+            type_id = member["type"].as_str().unwrap_or_default().to_string();
+        } else if let Some(key) = rest.strip_prefix('[') {
+            let end = key.find(']').ok_or_else(|| eyre::eyre!("Missing `]` in `{}`", rest))?;
+            rest = &key[end + 1..];
+            let key = key[..end].trim();
+
+            match ty.encoding.as_str() {
+                "mapping" => {
+                    let key_type = storage_type(layout, ty.key.as_deref().unwrap_or_default())?;
+                    let mut preimage = encode_mapping_key(&key_type.label, key)?;
+                    preimage.extend_from_slice(&ethers::abi::encode(&[Token::Uint(slot)]));
+                    slot = U256::from(keccak256(preimage));
+                    offset = 0;
+                    type_id = ty.value.clone().unwrap_or_default();
+                }
+                "dynamic_array" | "inplace" if ty.other.contains_key("base") => {
+                    let base_id = ty.other["base"].as_str().unwrap_or_default().to_string();
+                    let base_bytes =
+                        U256::from_dec_str(&storage_type(layout, &base_id)?.number_of_bytes)?;
+                    let index = U256::from_dec_str(key)
+                        .map_err(|_| eyre::eyre!("Invalid array index `{}`", key))?;
+
+                    // the elements of dynamic arrays start at the hash of the array slot
+                    let start = if ty.encoding == "dynamic_array" {
+                        U256::from(keccak256(ethers::abi::encode(&[Token::Uint(slot)])))
+                    } else {
+                        slot
+                    };
+                    // elements smaller than a slot are packed, larger ones span multiple slots
+                    if base_bytes <= 16.into() {
+                        let per_slot = U256::from(32) / base_bytes;
+                        slot = start + index / per_slot;
+                        offset = ((index % per_slot) * base_bytes).as_u64();
+                    } else {
+                        slot = start + index * ((base_bytes + 31) / 32);
+                        offset = 0;
+                    }
+                    type_id = base_id;
+                }
+                _ => eyre::bail!("`{}` is not a mapping or an array", ty.label),
+            }
+        } else {
+            eyre::bail!("Unexpected `{}` in the expression", rest)
+        }
+    }
+
+    Ok(StorageSlot { slot, offset, type_label: storage_type(layout, &type_id)?.label.clone() })
+}
+
+fn storage_type<'a>(layout: &'a StorageLayout, id: &str) -> eyre::Result<&'a StorageType> {
+    layout.types.get(id).ok_or_else(|| eyre::eyre!("Unknown type `{}` in the storage layout", id))
+}
+
+/// Returns the bytes a mapping key is hashed with
+fn encode_mapping_key(key_type: &str, key: &str) -> eyre::Result<Vec<u8>> {
+    let key_type = if key_type.starts_with("contract ") {
+        "address"
+    } else if key_type.starts_with("enum ") {
+        "uint8"
+    } else {
+        key_type
+    };
+    Ok(match Reader::read(key_type)? {
+        ParamType::String => key.trim_matches('"').as_bytes().to_vec(),
+        ParamType::Bytes => hex::decode(key.trim_start_matches("0x"))?,
+        param => {
+            let token = LenientTokenizer::tokenize(&param, key)
+                .map_err(|err| eyre::eyre!("Invalid {} key `{}`: {}", key_type, key, err))?;
+            ethers::abi::encode(&[token])
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a layout from `(label, type, slot, offset)` entries
+    fn layout(vars: &[(&str, &str, u64, u64)]) -> StorageLayout {
+        let storage = vars
+            .iter()
+            .map(|(label, ty, slot, offset)| {
+                serde_json::json!({
+                    "astId": 1,
+                    "contract": "src/Counter.sol:Counter",
+                    "label": label,
+                    "offset": offset,
+                    "slot": slot.to_string(),
+                    "type": format!("t_{ty}"),
+                })
+            })
+            .collect::<Vec<_>>();
+        let types = serde_json::json!({
+            "t_address": { "encoding": "inplace", "label": "address", "numberOfBytes": "20" },
+            "t_bool": { "encoding": "inplace", "label": "bool", "numberOfBytes": "1" },
+            "t_uint256": { "encoding": "inplace", "label": "uint256", "numberOfBytes": "32" },
+            "t_uint128": { "encoding": "inplace", "label": "uint128", "numberOfBytes": "16" },
+        });
+        serde_json::from_value(serde_json::json!({ "storage": storage, "types": types })).unwrap()
+    }
+
+    #[test]
+    fn can_diff_storage_layouts() {
+        let old = layout(&[
+            ("owner", "address", 0, 0),
+            ("paused", "bool", 0, 20),
+            ("count", "uint256", 1, 0),
+            ("total", "uint256", 2, 0),
+            ("legacy", "uint256", 3, 0),
+        ]);
+
+        // appending and removing a trailing variable is safe
+        let changes = diff_storage_layouts(
+            &old,
+            &layout(&[
+                ("owner", "address", 0, 0),
+                ("paused", "bool", 0, 20),
+                ("count", "uint256", 1, 0),
+                ("total", "uint256", 2, 0),
+                ("fee", "uint256", 4, 0),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(changes.iter().all(|change| !change.is_breaking()));
+        assert_eq!(changes[0].to_string(), "removed `legacy` (uint256) from slot 3");
+        assert_eq!(changes[1].to_string(), "added `fee` (uint256) at slot 4");
+
+        // inserting a variable shifts and overwrites the following ones
+        let changes = diff_storage_layouts(
+            &old,
+            &layout(&[
+                ("owner", "address", 0, 0),
+                ("paused", "bool", 0, 20),
+                ("fee", "uint128", 1, 0),
+                ("count", "uint256", 2, 0),
+                ("total", "uint128", 3, 0),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "`fee` (uint128) at slot 1 collides with `count` (uint256) of the old layout",
+                "moved `count` from slot 1 (offset 0) to slot 2 (offset 0)",
+                "`count` (uint256) at slot 2 collides with `total` (uint256) of the old layout",
+                "moved `total` from slot 2 (offset 0) to slot 3 (offset 0)",
+                "`total` (uint128) at slot 3 collides with `legacy` (uint256) of the old layout",
+            ]
+        );
+        assert!(changes.iter().all(StorageLayoutChange::is_breaking));
+
+        let changes = diff_storage_layouts(&old, &layout(&[("owner", "uint256", 0, 0)])).unwrap();
+        assert_eq!(
+            changes[0].to_string(),
+            "changed the type of `owner` at slot 0 from address to uint256"
+        );
+        assert!(matches!(changes[1], StorageLayoutChange::Collision { .. }));
+
+        // a variable of a new name at the position of a removed variable inherits its values
+        let changes = diff_storage_layouts(
+            &old,
+            &layout(&[
+                ("admin", "address", 0, 0),
+                ("paused", "bool", 0, 20),
+                ("count", "uint256", 1, 0),
+                ("supply", "uint128", 2, 0),
+                ("legacy", "uint256", 3, 0),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "renamed `owner` to `admin` (address) at slot 0",
+                "replaced `total` (uint256) with `supply` (uint128) at slot 2",
+            ]
+        );
+        assert!(changes.iter().all(StorageLayoutChange::is_breaking));
+
+        // a variable that moves to the position of a removed variable overwrites it
+        let changes = diff_storage_layouts(
+            &old,
+            &layout(&[
+                ("owner", "address", 0, 0),
+                ("paused", "bool", 0, 20),
+                ("total", "uint256", 1, 0),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "moved `total` from slot 2 (offset 0) to slot 1 (offset 0)",
+                "`total` (uint256) at slot 1 collides with `count` (uint256) of the old layout",
+                "removed `legacy` (uint256) from slot 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn can_compute_storage_slots() {
+        let layout: StorageLayout = serde_json::from_value(serde_json::json!({
+            "storage": [
+                { "astId": 1, "contract": "src/Token.sol:Token", "label": "balances", "offset": 0, "slot": "0", "type": "t_mapping(t_address,t_uint256)" },
+                { "astId": 2, "contract": "src/Token.sol:Token", "label": "amounts", "offset": 0, "slot": "1", "type": "t_array(t_uint128)dyn_storage" },
+                { "astId": 3, "contract": "src/Token.sol:Token", "label": "positions", "offset": 0, "slot": "2", "type": "t_array(t_struct(Position)1_storage)3_storage" },
+            ],
+            "types": {
+                "t_address": { "encoding": "inplace", "label": "address", "numberOfBytes": "20" },
+                "t_uint256": { "encoding": "inplace", "label": "uint256", "numberOfBytes": "32" },
+                "t_uint128": { "encoding": "inplace", "label": "uint128", "numberOfBytes": "16" },
+                "t_mapping(t_address,t_uint256)": { "encoding": "mapping", "key": "t_address", "label": "mapping(address => uint256)", "numberOfBytes": "32", "value": "t_uint256" },
+                "t_array(t_uint128)dyn_storage": { "encoding": "dynamic_array", "base": "t_uint128", "label": "uint128[]", "numberOfBytes": "32" },
+                "t_array(t_struct(Position)1_storage)3_storage": { "encoding": "inplace", "base": "t_struct(Position)1_storage", "label": "struct Token.Position[3]", "numberOfBytes": "192" },
+                "t_struct(Position)1_storage": { "encoding": "inplace", "label": "struct Token.Position", "numberOfBytes": "64", "members": [
+                    { "astId": 4, "contract": "src/Token.sol:Token", "label": "owner", "offset": 0, "slot": "0", "type": "t_address" },
+                    { "astId": 5, "contract": "src/Token.sol:Token", "label": "amount", "offset": 0, "slot": "1", "type": "t_uint256" },
+                ] },
+            },
+        }))
+        .unwrap();
+
+        let owner = "0x00000000000000000000000000000000000000aa";
+        let slot = compute_storage_slot(&layout, &format!("balances[{owner}]")).unwrap();
+        let preimage = ethers::abi::encode(&[
+            Token::Address(owner.parse().unwrap()),
+            Token::Uint(U256::zero()),
+        ]);
+        assert_eq!(slot.slot, U256::from(keccak256(preimage)));
+        assert_eq!(slot.type_label, "uint256");
+
+        // two `uint128` elements are packed in a slot
+        let start = U256::from(keccak256(ethers::abi::encode(&[Token::Uint(1.into())])));
+        let slot = compute_storage_slot(&layout, "amounts[3]").unwrap();
+        assert_eq!((slot.slot, slot.offset), (start + 1, 16));
+
+        let slot = compute_storage_slot(&layout, "positions[2].amount").unwrap();
+        assert_eq!((slot.slot, slot.offset), (U256::from(7), 0));
+        assert_eq!(slot.type_label, "uint256");
+
+        assert!(compute_storage_slot(&layout, "missing").is_err());
+        assert!(compute_storage_slot(&layout, "balances.owner").is_err());
+    }
+}
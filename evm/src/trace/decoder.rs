@@ -49,6 +49,15 @@ impl CallTraceDecoderBuilder {
         self
     }
 
+    /// Add a signature identifier, shared with other decoders, to the decoder.
+    pub fn with_signature_identifier(
+        mut self,
+        identifier: Option<Arc<RwLock<SignaturesIdentifier>>>,
+    ) -> Self {
+        self.decoder.signature_identifier = identifier;
+        self
+    }
+
     /// Build the decoder.
     pub fn build(self) -> CallTraceDecoder {
         self.decoder
@@ -249,6 +258,11 @@ impl CallTraceDecoder {
                 node.trace.label = Some(label);
             }
 
+            // Set the label of the implementation of a proxy
+            if let Some(proxy) = &mut node.trace.proxy {
+                proxy.implementation_label = self.labels.get(&proxy.implementation).cloned();
+            }
+
             // Decode call
             if let Some(precompile_fn) = self.precompiles.get(&node.trace.address) {
                 node.decode_precompile(precompile_fn, &self.labels);
@@ -272,20 +286,20 @@ impl CallTraceDecoder {
                         String::new(),
                         Vec::new(),
                     );
+                }
+            }
 
-                    if let RawOrDecodedReturnData::Raw(bytes) = &node.trace.output {
-                        if !node.trace.success {
-                            if let Ok(decoded_error) = decode::decode_revert(
-                                &bytes[..],
-                                Some(&self.errors),
-                                Some(node.trace.status),
-                            ) {
-                                node.trace.output = RawOrDecodedReturnData::Decoded(format!(
-                                    r#""{}""#,
-                                    decoded_error
-                                ));
-                            }
-                        }
+            // Decode the revert data of every failed frame, including the frames of unknown
+            // functions and failed creations
+            if let RawOrDecodedReturnData::Raw(bytes) = &node.trace.output {
+                if !node.trace.success {
+                    if let Ok(decoded_error) = decode::decode_revert(
+                        &bytes[..],
+                        Some(&self.errors),
+                        Some(node.trace.status),
+                    ) {
+                        node.trace.output =
+                            RawOrDecodedReturnData::Decoded(format!(r#""{}""#, decoded_error));
                     }
                 }
             }
@@ -2,23 +2,51 @@ use super::{AddressIdentity, TraceIdentifier};
 use ethers::{
     abi::{Abi, Address, Event},
     prelude::ArtifactId,
+    types::H256,
+    utils::keccak256,
 };
 use foundry_utils::diff_score;
-use std::{borrow::Cow, collections::BTreeMap};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+};
 
 /// A trace identifier that tries to identify addresses using local contracts.
+///
+/// Deployed code is matched exactly by its hash first, and otherwise against the most similar
+/// local contract, since immutables and linked libraries change the deployed code.
 pub struct LocalTraceIdentifier {
     local_contracts: BTreeMap<Vec<u8>, (ArtifactId, Abi)>,
+    /// The runtime code of the local contracts by code hash
+    code_hashes: HashMap<H256, Vec<u8>>,
 }
 
 impl LocalTraceIdentifier {
     pub fn new(known_contracts: &BTreeMap<ArtifactId, (Abi, Vec<u8>)>) -> Self {
-        Self {
-            local_contracts: known_contracts
-                .iter()
-                .map(|(id, (abi, runtime_code))| (runtime_code.clone(), (id.clone(), abi.clone())))
-                .collect(),
-        }
+        let local_contracts: BTreeMap<_, _> = known_contracts
+            .iter()
+            .map(|(id, (abi, runtime_code))| (runtime_code.clone(), (id.clone(), abi.clone())))
+            .collect();
+        let code_hashes = local_contracts
+            .keys()
+            .map(|runtime_code| (H256::from(keccak256(runtime_code)), runtime_code.clone()))
+            .collect();
+        Self { local_contracts, code_hashes }
+    }
+
+    /// Returns the local contract with the given deployed code, or the most similar one
+    fn find(&self, code: &[u8]) -> Option<&(ArtifactId, Abi)> {
+        self.code_hashes
+            .get(&H256::from(keccak256(code)))
+            .and_then(|runtime_code| self.local_contracts.get(runtime_code))
+            .or_else(|| {
+                self.local_contracts
+                    .iter()
+                    .map(|(known_code, contract)| (diff_score(known_code, code), contract))
+                    .filter(|(score, _)| *score < 0.1)
+                    .min_by(|(a, _), (b, _)| a.total_cmp(b))
+                    .map(|(_, contract)| contract)
+            })
     }
 
     /// Get all the events of the local contracts.
@@ -35,11 +63,7 @@ impl TraceIdentifier for LocalTraceIdentifier {
         addresses
             .into_iter()
             .filter_map(|(address, code)| {
-                let code = code?;
-                let (_, (id, abi)) = self
-                    .local_contracts
-                    .iter()
-                    .find(|(known_code, _)| diff_score(known_code, code) < 0.1)?;
+                let (id, abi) = self.find(code?)?;
 
                 Some(AddressIdentity {
                     address: *address,
@@ -52,3 +76,45 @@ impl TraceIdentifier for LocalTraceIdentifier {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use semver::Version;
+    use std::path::PathBuf;
+
+    fn artifact_id(name: &str) -> ArtifactId {
+        ArtifactId {
+            path: PathBuf::from(format!("out/{name}.sol/{name}.json")),
+            name: name.to_string(),
+            source: PathBuf::from(format!("src/{name}.sol")),
+            version: Version::new(0, 8, 15),
+        }
+    }
+
+    #[test]
+    fn can_find_local_contracts() {
+        let short = vec![0x60; 40];
+        let long = [vec![0x60; 40], vec![0x61; 40]].concat();
+        let other = vec![0x62; 40];
+        let known_contracts = [("Short", &short), ("Long", &long), ("Other", &other)]
+            .into_iter()
+            .map(|(name, code)| (artifact_id(name), (Abi::default(), code.clone())))
+            .collect();
+        let identifier = LocalTraceIdentifier::new(&known_contracts);
+        let find = |code: &[u8]| identifier.find(code).map(|(id, _)| id.name.as_str());
+
+        // exact matches win over contracts that are just as similar
+        assert_eq!(find(&short), Some("Short"));
+        assert_eq!(find(&long), Some("Long"));
+        assert_eq!(find(&other), Some("Other"));
+
+        // near matches, e.g. with immutables, are matched by similarity
+        let mut immutable = other.clone();
+        immutable[20] = 0xff;
+        assert_eq!(find(&immutable), Some("Other"));
+
+        assert_eq!(find(&[0xfe; 40]), None);
+        assert_eq!(find(&[]), None);
+    }
+}
@@ -1,15 +1,24 @@
 use ethers::abi::{Event, Function};
 use foundry_utils::{decode_selector, get_event, get_func, selectors::SelectorType};
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, io::BufWriter, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::BufWriter,
+    path::PathBuf,
+};
 use tracing::warn;
 
 /// An identifier that tries to identify functions and events using signatures found at
-/// `sig.eth.samczsun.com`.
+/// `openchain.xyz` or `4byte.directory`.
+///
+/// Identified signatures are cached in `<cache>/signatures`, selectors that could not be identified
+/// are not queried again by the same identifier.
 #[derive(Debug, Default)]
 pub struct SignaturesIdentifier {
     cached: CachedSignatures,
     cached_path: Option<PathBuf>,
+    /// The selectors that could not be identified
+    unknown: HashSet<String>,
 }
 
 impl SignaturesIdentifier {
@@ -24,7 +33,7 @@ impl SignaturesIdentifier {
                 }
                 CachedSignatures::default()
             };
-            return Ok(Self { cached, cached_path: Some(path) })
+            return Ok(Self { cached, cached_path: Some(path), unknown: Default::default() })
         }
         Ok(Self::default())
     }
@@ -56,10 +65,17 @@ impl SignaturesIdentifier {
 
         let identifier = format!("0x{}", hex::encode(identifier));
 
-        if !map.contains_key(&identifier) {
-            if let Ok(signatures) = decode_selector(&identifier, selector_type).await {
-                if let Some(signature) = signatures.into_iter().next() {
-                    map.insert(identifier.to_string(), signature);
+        if !map.contains_key(&identifier) && !self.unknown.contains(&identifier) {
+            match decode_selector(&identifier, selector_type)
+                .await
+                .ok()
+                .and_then(|signatures| signatures.into_iter().next())
+            {
+                Some(signature) => {
+                    map.insert(identifier.clone(), signature);
+                }
+                None => {
+                    self.unknown.insert(identifier.clone());
                 }
             }
         }
@@ -70,12 +86,12 @@ impl SignaturesIdentifier {
         None
     }
 
-    /// Identifies `Function` from its cache or `sig.eth.samczsun.com`
+    /// Identifies `Function` from its cache, `openchain.xyz` or `4byte.directory`
     pub async fn identify_function(&mut self, identifier: &[u8]) -> Option<Function> {
         self.identify(SelectorType::Function, identifier, get_func).await
     }
 
-    /// Identifies `Event` from its cache or `sig.eth.samczsun.com`
+    /// Identifies `Event` from its cache, `openchain.xyz` or `4byte.directory`
     pub async fn identify_event(&mut self, identifier: &[u8]) -> Option<Event> {
         self.identify(SelectorType::Event, identifier, get_event).await
     }
@@ -118,6 +134,10 @@ mod tests {
             assert!(func == get_func("transferFrom(address,address,uint256)").unwrap());
             assert!(event == get_event("Transfer(address,address,uint128)").unwrap());
 
+            // unknown selectors are not queried again
+            assert!(sigs.identify_function(&[0x5a, 0x7e, 0x31, 0x9c]).await.is_none());
+            assert!(sigs.unknown.contains("0x5a7e319c"));
+
             // dropping saves the cache
         }
 
@@ -5,17 +5,19 @@ pub mod identifier;
 
 mod decoder;
 pub mod node;
+pub mod proxy;
 mod utils;
 
 pub use decoder::{CallTraceDecoder, CallTraceDecoderBuilder};
 
-use crate::{abi::CHEATCODE_ADDRESS, CallKind};
+use crate::{abi::CHEATCODE_ADDRESS, decode, CallKind};
 use ethers::{
-    abi::{Address, RawLog},
+    abi::{Abi, Address, RawLog},
     types::U256,
 };
 use node::CallTraceNode;
-use revm::{CallContext, Return};
+use proxy::ProxyInfo;
+use revm::{return_ok, CallContext, Return};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashSet,
@@ -67,6 +69,42 @@ impl CallTraceArena {
         }
     }
 
+    /// Returns the chain of revert reasons of a reverted call, starting at the outermost call.
+    ///
+    /// The chain is reconstructed by following the reverted subcall that caused the revert of
+    /// every reverted call down to the innermost one. A subcall caused the revert if its revert
+    /// data was bubbled up or re-thrown wrapped in a custom error, i.e. if the revert data of the
+    /// caller contains it. Reverts that were caught and not re-thrown, e.g. by a try/catch or a
+    /// low-level call, are not part of the chain. Consecutive identical reasons, i.e. reverts that
+    /// were simply bubbled up, are collapsed.
+    pub fn revert_chain(&self, abi: Option<&Abi>) -> Vec<String> {
+        let mut chain: Vec<String> = Vec::new();
+        let mut idx = 0;
+        loop {
+            let node = &self.arena[idx];
+            if node.trace.success || matches!(node.trace.status, return_ok!()) {
+                break
+            }
+            let output = node.trace.output.to_raw();
+            if node.trace.address != CHEATCODE_ADDRESS {
+                let reason = decode::decode_revert(&output, abi, Some(node.trace.status))
+                    .unwrap_or_else(|_| format!("{:?}", node.trace.status));
+                if chain.last() != Some(&reason) {
+                    chain.push(reason);
+                }
+            }
+            let cause = node.children.iter().rev().find(|child| {
+                let child = &self.arena[**child].trace;
+                !child.success && contains_revert_data(&output, &child.output.to_raw())
+            });
+            match cause {
+                Some(child) => idx = *child,
+                None => break,
+            }
+        }
+        chain
+    }
+
     pub fn addresses(&self) -> HashSet<(&Address, Option<&Vec<u8>>)> {
         self.arena
             .iter()
@@ -79,10 +117,23 @@ impl CallTraceArena {
 
                 (&node.trace.address, None)
             })
+            // the implementations of proxies are identified by their code, since they are usually
+            // not created in the same trace
+            .chain(self.arena.iter().filter_map(|node| {
+                let proxy = node.trace.proxy.as_ref()?;
+                (!proxy.implementation_code.is_empty())
+                    .then(|| (&proxy.implementation, Some(&proxy.implementation_code)))
+            }))
             .collect()
     }
 }
 
+/// Returns `true` if the revert data of a call contains the revert data of its subcall, i.e. the
+/// subcall's revert was bubbled up or re-thrown wrapped in another error
+fn contains_revert_data(data: &[u8], nested: &[u8]) -> bool {
+    !nested.is_empty() && data.windows(nested.len()).any(|window| window == nested)
+}
+
 const PIPE: &str = "  │ ";
 const EDGE: &str = "  └─ ";
 const BRANCH: &str = "  ├─ ";
@@ -320,6 +371,9 @@ pub struct CallTrace {
     pub status: Return,
     /// call context of the runtime
     pub call_context: Option<CallContext>,
+    /// The proxy pattern of the called contract, if the call was delegated to an implementation
+    #[serde(default)]
+    pub proxy: Option<ProxyInfo>,
 }
 
 // === impl CallTrace ===
@@ -359,6 +413,7 @@ impl Default for CallTrace {
             gas_cost: Default::default(),
             status: Return::Continue,
             call_context: Default::default(),
+            proxy: Default::default(),
         }
     }
 }
@@ -412,6 +467,10 @@ impl fmt::Display for CallTrace {
                 inputs,
                 Paint::yellow(action),
             )?;
+            if let Some(proxy) = &self.proxy {
+                let separator = if action.is_empty() { "" } else { " " };
+                write!(f, "{}{}", separator, Paint::yellow(proxy))?;
+            }
         }
 
         Ok(())
@@ -0,0 +1,132 @@
+//! Detection of common proxy patterns in call traces
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The EIP-1967 slot of the implementation, `keccak256("eip1967.proxy.implementation") - 1`
+pub const EIP1967_IMPLEMENTATION_SLOT: [u8; 32] = [
+    0x36, 0x08, 0x94, 0xa1, 0x3b, 0xa1, 0xa3, 0x21, 0x06, 0x67, 0xc8, 0x28, 0x49, 0x2d, 0xb9, 0x8d,
+    0xca, 0x3e, 0x20, 0x76, 0xcc, 0x37, 0x35, 0xa9, 0x20, 0xa3, 0xca, 0x50, 0x5d, 0x38, 0x2b, 0xbc,
+];
+
+/// The EIP-1967 slot of the admin, `keccak256("eip1967.proxy.admin") - 1`
+pub const EIP1967_ADMIN_SLOT: [u8; 32] = [
+    0xb5, 0x31, 0x27, 0x68, 0x4a, 0x56, 0x8b, 0x31, 0x73, 0xae, 0x13, 0xb9, 0xf8, 0xa6, 0x01, 0x6e,
+    0x24, 0x3e, 0x63, 0xb6, 0xe8, 0xee, 0x11, 0x78, 0xd6, 0xa7, 0x17, 0x85, 0x0b, 0x5d, 0x61, 0x03,
+];
+
+/// The EIP-1967 slot of the beacon, `keccak256("eip1967.proxy.beacon") - 1`
+pub const EIP1967_BEACON_SLOT: [u8; 32] = [
+    0xa3, 0xf0, 0xad, 0x74, 0xe5, 0x42, 0x3a, 0xeb, 0xfd, 0x80, 0xd3, 0xef, 0x43, 0x46, 0x57, 0x83,
+    0x35, 0xa9, 0xa7, 0x2a, 0xea, 0xee, 0x59, 0xff, 0x6c, 0xb3, 0x58, 0x2c, 0xfb, 0x8a, 0x2d, 0x50,
+];
+
+/// The runtime code of an EIP-1167 minimal proxy before the address of the implementation
+const MINIMAL_PROXY_PREFIX: [u8; 10] = [0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d, 0x73];
+
+/// The runtime code of an EIP-1167 minimal proxy after the address of the implementation
+const MINIMAL_PROXY_SUFFIX: [u8; 15] =
+    [0x5a, 0xf4, 0x3d, 0x82, 0x80, 0x3e, 0x90, 0x3d, 0x91, 0x60, 0x2b, 0x57, 0xfd, 0x5b, 0xf3];
+
+/// The kind of a proxy
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProxyKind {
+    /// An EIP-1967 proxy with an admin, e.g. a transparent proxy
+    Eip1967,
+    /// An EIP-1967 proxy without an admin, which is upgraded by its implementation
+    Uups,
+    /// An EIP-1967 beacon proxy, which gets its implementation from the beacon
+    Beacon,
+    /// An EIP-1167 minimal proxy
+    Minimal,
+}
+
+impl fmt::Display for ProxyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyKind::Eip1967 => f.write_str("EIP-1967"),
+            ProxyKind::Uups => f.write_str("UUPS"),
+            ProxyKind::Beacon => f.write_str("beacon"),
+            ProxyKind::Minimal => f.write_str("minimal"),
+        }
+    }
+}
+
+impl ProxyKind {
+    /// Returns the kind of the proxy with the given runtime code, reading its EIP-1967 slots with
+    /// `storage`, or `None` if it doesn't look like a proxy
+    pub fn detect(code: &[u8], mut storage: impl FnMut(U256) -> U256) -> Option<Self> {
+        if minimal_proxy_implementation(code).is_some() {
+            return Some(ProxyKind::Minimal)
+        }
+        if !storage(U256::from_big_endian(&EIP1967_BEACON_SLOT)).is_zero() {
+            return Some(ProxyKind::Beacon)
+        }
+        if !storage(U256::from_big_endian(&EIP1967_IMPLEMENTATION_SLOT)).is_zero() {
+            return if storage(U256::from_big_endian(&EIP1967_ADMIN_SLOT)).is_zero() {
+                Some(ProxyKind::Uups)
+            } else {
+                Some(ProxyKind::Eip1967)
+            }
+        }
+        None
+    }
+}
+
+/// Returns the implementation of an EIP-1167 minimal proxy with the given runtime code
+pub fn minimal_proxy_implementation(code: &[u8]) -> Option<Address> {
+    let implementation =
+        code.strip_prefix(&MINIMAL_PROXY_PREFIX[..])?.strip_suffix(&MINIMAL_PROXY_SUFFIX[..])?;
+    (implementation.len() == 20).then(|| Address::from_slice(implementation))
+}
+
+/// A proxy call, i.e. a call that delegates to the implementation of the called contract
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyInfo {
+    pub kind: ProxyKind,
+    /// The implementation the call was delegated to
+    pub implementation: Address,
+    /// The runtime code of the implementation, which identifies it
+    #[serde(skip)]
+    pub implementation_code: Vec<u8>,
+    /// The label of the implementation, if it was identified
+    pub implementation_label: Option<String>,
+}
+
+impl fmt::Display for ProxyInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.implementation_label {
+            Some(label) => write!(f, "[{} proxy → {}]", self.kind, label),
+            None => write!(f, "[{} proxy → {}]", self.kind, self.implementation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn can_detect_proxies() {
+        let implementation = Address::from_low_u64_be(0xbeef);
+        let code =
+            [&MINIMAL_PROXY_PREFIX[..], implementation.as_bytes(), &MINIMAL_PROXY_SUFFIX[..]]
+                .concat();
+        assert_eq!(minimal_proxy_implementation(&code), Some(implementation));
+        assert_eq!(ProxyKind::detect(&code, |_| U256::zero()), Some(ProxyKind::Minimal));
+        assert_eq!(minimal_proxy_implementation(&code[1..]), None);
+
+        let mut slots = HashMap::new();
+        let detect = |slots: &HashMap<U256, U256>| {
+            ProxyKind::detect(&[0x00], |slot| slots.get(&slot).copied().unwrap_or_default())
+        };
+        assert_eq!(detect(&slots), None);
+        slots.insert(U256::from_big_endian(&EIP1967_IMPLEMENTATION_SLOT), U256::one());
+        assert_eq!(detect(&slots), Some(ProxyKind::Uups));
+        slots.insert(U256::from_big_endian(&EIP1967_ADMIN_SLOT), U256::one());
+        assert_eq!(detect(&slots), Some(ProxyKind::Eip1967));
+        slots.insert(U256::from_big_endian(&EIP1967_BEACON_SLOT), U256::one());
+        assert_eq!(detect(&slots), Some(ProxyKind::Beacon));
+    }
+}
@@ -1,6 +1,10 @@
 use bytes::Bytes;
-use ethers::prelude::{H256, U256};
-use revm::{opcode, spec_opcode_gas, SpecId};
+use ethers::{
+    abi::{encode, Token},
+    prelude::{H256, U256},
+    utils::keccak256,
+};
+use revm::{opcode, spec_opcode_gas, BlockEnv, SpecId};
 use std::collections::BTreeMap;
 
 /// Small helper function to convert [U256] into [H256].
@@ -27,6 +31,22 @@ pub fn h256_to_u256_le(storage: H256) -> U256 {
     U256::from_little_endian(storage.as_bytes())
 }
 
+/// Advances the block environment by `blocks` blocks, each mined `interval` seconds after the
+/// previous one.
+///
+/// The difficulty is replaced by a hash of the previous difficulty and the new block number, so it
+/// changes between blocks like the post-merge `PREVRANDAO` value.
+pub fn mine_blocks(block: &mut BlockEnv, blocks: U256, interval: U256) {
+    block.number = block.number.saturating_add(blocks);
+    block.timestamp = block.timestamp.saturating_add(blocks.saturating_mul(interval));
+    if !blocks.is_zero() {
+        block.difficulty = U256::from(keccak256(encode(&[
+            Token::Uint(block.difficulty),
+            Token::Uint(block.number),
+        ])));
+    }
+}
+
 /// Builds the instruction counter map for the given bytecode.
 // TODO: Some of the same logic is performed in REVM, but then later discarded. We should
 // investigate if we can reuse it
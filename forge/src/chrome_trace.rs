@@ -0,0 +1,156 @@
+use crate::{
+    flamegraph::frame_name,
+    trace::{CallTraceArena, TraceKind},
+};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Collects call traces into the Chrome trace event format, which can be viewed as a timeline in
+/// `chrome://tracing` or Perfetto.
+///
+/// Every test is shown as a separate thread and every call as a slice whose duration is the gas
+/// used by the call, where 1 gas is displayed as 1µs. The traces do not record at which point of
+/// their parent subcalls were made, so subcalls are laid out back to back from the start of their
+/// parent.
+#[derive(Debug, Default)]
+pub struct ChromeTrace {
+    events: Vec<Value>,
+    threads: u64,
+}
+
+impl ChromeTrace {
+    /// Adds the traces of a test as a new thread with the given name.
+    ///
+    /// The traces should be decoded first, otherwise calls are named after addresses and
+    /// selectors.
+    pub fn add(&mut self, name: &str, traces: &[(TraceKind, CallTraceArena)]) {
+        self.threads += 1;
+        let tid = self.threads;
+        self.events.push(json!({
+            "name": "thread_name",
+            "ph": "M",
+            "pid": 1,
+            "tid": tid,
+            "args": { "name": name },
+        }));
+
+        // The deployment, setup and execution of a test follow each other
+        let mut start = 0;
+        for (kind, arena) in traces {
+            if let Some(root) = arena.arena.first() {
+                self.add_node(arena, 0, tid, start, kind);
+                start += root.trace.gas_cost;
+            }
+        }
+    }
+
+    fn add_node(
+        &mut self,
+        arena: &CallTraceArena,
+        idx: usize,
+        tid: u64,
+        start: u64,
+        kind: &TraceKind,
+    ) {
+        let node = &arena.arena[idx];
+        let trace = &node.trace;
+        self.events.push(json!({
+            "name": frame_name(trace),
+            "cat": format!("{:?}", kind),
+            "ph": "X",
+            "ts": start,
+            "dur": trace.gas_cost,
+            "pid": 1,
+            "tid": tid,
+            "args": {
+                "address": trace.address,
+                "kind": format!("{:?}", trace.kind),
+                "value": trace.value,
+                "gas": trace.gas_cost,
+                "success": trace.success,
+            },
+        }));
+
+        let mut child_start = start;
+        for child in &node.children {
+            self.add_node(arena, *child, tid, child_start, kind);
+            child_start += arena.arena[*child].trace.gas_cost;
+        }
+    }
+
+    /// Returns `true` if no test was added
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Returns the trace in the Chrome trace event format
+    pub fn to_json(&self) -> Value {
+        json!({ "traceEvents": self.events })
+    }
+
+    /// Writes the trace in the Chrome trace event format to the given file
+    pub fn write(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        std::fs::write(path, serde_json::to_string(&self.to_json())?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::{node::CallTraceNode, CallTrace, RawOrDecodedCall};
+
+    fn node(contract: &str, function: &str, gas_cost: u64, children: Vec<usize>) -> CallTraceNode {
+        CallTraceNode {
+            children,
+            trace: CallTrace {
+                contract: Some(format!("src/{contract}.sol:{contract}")),
+                data: RawOrDecodedCall::Decoded(function.to_string(), String::new(), vec![]),
+                gas_cost,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_export_traces() {
+        let setup = CallTraceArena { arena: vec![node("CounterTest", "setUp", 100, vec![])] };
+        let execution = CallTraceArena {
+            arena: vec![
+                node("CounterTest", "testIncrement", 1000, vec![1, 2]),
+                node("Counter", "increment", 300, vec![]),
+                node("Counter", "increment", 200, vec![]),
+            ],
+        };
+
+        let mut chrome_trace = ChromeTrace::default();
+        chrome_trace.add(
+            "CounterTest::testIncrement",
+            &[(TraceKind::Setup, setup), (TraceKind::Execution, execution)],
+        );
+        let json = chrome_trace.to_json();
+        let events = json["traceEvents"].as_array().unwrap();
+        assert_eq!(events[0]["args"]["name"], "CounterTest::testIncrement");
+
+        let slices = events[1..]
+            .iter()
+            .map(|event| {
+                (
+                    event["name"].as_str().unwrap(),
+                    event["ts"].as_u64().unwrap(),
+                    event["dur"].as_u64().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            slices,
+            vec![
+                ("CounterTest::setUp", 0, 100),
+                ("CounterTest::testIncrement", 100, 1000),
+                ("Counter::increment", 100, 300),
+                ("Counter::increment", 400, 200),
+            ]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{
+    executor::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
+    trace::{CallTrace, CallTraceArena, RawOrDecodedCall, TraceKind},
+};
+use std::{collections::BTreeMap, io::Write, path::Path};
+
+/// Collects call traces into the folded stack format used by flamegraph tools, weighted by gas.
+///
+/// Every stack has the form `frame;frame;frame <gas>`, where the gas of a stack is the gas used by
+/// the innermost call excluding the gas used by its subcalls. Identical stacks of different tests
+/// are merged.
+#[derive(Debug, Default)]
+pub struct Flamegraph {
+    stacks: BTreeMap<String, u64>,
+}
+
+impl Flamegraph {
+    /// Adds the execution traces of a test.
+    ///
+    /// The traces should be decoded first, otherwise frames are named after addresses and
+    /// selectors.
+    pub fn add(&mut self, traces: &[(TraceKind, CallTraceArena)]) {
+        for (_, arena) in traces.iter().filter(|(kind, _)| *kind == TraceKind::Execution) {
+            self.add_node(arena, 0, &mut Vec::new());
+        }
+    }
+
+    fn add_node(&mut self, arena: &CallTraceArena, idx: usize, stack: &mut Vec<String>) {
+        let node = &arena.arena[idx];
+        let trace = &node.trace;
+        if trace.address == CHEATCODE_ADDRESS || trace.address == HARDHAT_CONSOLE_ADDRESS {
+            return
+        }
+
+        // `;` separates frames and the last space separates the gas
+        stack.push(frame_name(trace).replace(&[';', ' '][..], "_"));
+
+        let children_gas: u64 =
+            node.children.iter().map(|child| arena.arena[*child].trace.gas_cost).sum();
+        let self_gas = trace.gas_cost.saturating_sub(children_gas);
+        if self_gas > 0 {
+            *self.stacks.entry(stack.join(";")).or_default() += self_gas;
+        }
+
+        for child in &node.children {
+            self.add_node(arena, *child, stack);
+        }
+        stack.pop();
+    }
+
+    /// Returns `true` if no gas was recorded
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    /// Returns the stacks in the folded stack format, one line per stack
+    pub fn folded(&self) -> Vec<String> {
+        self.stacks.iter().map(|(stack, gas)| format!("{stack} {gas}")).collect()
+    }
+
+    /// Writes the stacks in the folded stack format to the given file
+    pub fn write_folded(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        for line in self.folded() {
+            writeln!(file, "{line}")?;
+        }
+        Ok(())
+    }
+
+    /// Renders the stacks as an SVG flamegraph to the given file
+    pub fn write_svg(&self, path: impl AsRef<Path>, title: &str) -> eyre::Result<()> {
+        let mut options = inferno::flamegraph::Options::default();
+        options.title = title.to_string();
+        options.count_name = "gas".to_string();
+
+        let file = std::fs::File::create(path)?;
+        let lines = self.folded();
+        inferno::flamegraph::from_lines(&mut options, lines.iter().map(String::as_str), file)
+            .map_err(|err| eyre::eyre!("Failed to render flamegraph: {}", err))
+    }
+}
+
+/// Returns the name of a call in the form `<contract>::<function>`, falling back to the label or
+/// address of the callee and the selector of the call
+pub(crate) fn frame_name(trace: &CallTrace) -> String {
+    let contract = trace
+        .contract
+        .as_deref()
+        .map(|name| name.rsplit(':').next().unwrap_or(name).to_string())
+        .or_else(|| trace.label.clone())
+        .unwrap_or_else(|| format!("{:?}", trace.address));
+    let function = match &trace.data {
+        RawOrDecodedCall::Decoded(name, _, _) => name.clone(),
+        RawOrDecodedCall::Raw(_) if trace.created() => "constructor".to_string(),
+        RawOrDecodedCall::Raw(data) if data.len() >= 4 => {
+            format!("0x{}", hex::encode(&data[..4]))
+        }
+        RawOrDecodedCall::Raw(_) => "fallback".to_string(),
+    };
+    format!("{contract}::{function}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::node::CallTraceNode;
+
+    fn node(contract: &str, function: &str, gas_cost: u64, children: Vec<usize>) -> CallTraceNode {
+        CallTraceNode {
+            children,
+            trace: CallTrace {
+                contract: Some(format!("src/{contract}.sol:{contract}")),
+                data: RawOrDecodedCall::Decoded(function.to_string(), String::new(), vec![]),
+                gas_cost,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_fold_traces() {
+        let arena = CallTraceArena {
+            arena: vec![
+                node("CounterTest", "testIncrement", 1000, vec![1, 2]),
+                node("Counter", "increment", 300, vec![]),
+                node("Counter", "increment", 200, vec![]),
+            ],
+        };
+
+        let mut flamegraph = Flamegraph::default();
+        flamegraph.add(&[(TraceKind::Setup, arena.clone()), (TraceKind::Execution, arena)]);
+        assert_eq!(
+            flamegraph.folded(),
+            vec![
+                "CounterTest::testIncrement 500".to_string(),
+                "CounterTest::testIncrement;Counter::increment 500".to_string(),
+            ]
+        );
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{
-    executor::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
+    executor::{
+        inspector::{GasDeltas, GasSchedule},
+        CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS,
+    },
+    result::{TestKind, TestResult},
     trace::{CallTraceArena, RawOrDecodedCall, TraceKind},
 };
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
-use ethers::types::U256;
+use ethers::{abi::Abi, types::U256};
 use serde::{Deserialize, Serialize};
 use std::{collections::BTreeMap, fmt::Display};
 
@@ -161,3 +165,92 @@ impl Display for GasReport {
         Ok(())
     }
 }
+
+/// A report of the gas differences of tests and functions under a hypothetical [GasSchedule]
+/// compared to the standard schedule
+///
+/// Only standard tests are measured under the schedule, fuzz tests are not part of the report.
+#[derive(Debug)]
+pub struct GasScheduleReport {
+    pub schedule: GasSchedule,
+    /// Function signatures of the contracts that are not test contracts by selector, used to name
+    /// the functions in the report
+    signatures: BTreeMap<[u8; 4], String>,
+    /// The name, standard gas usage and gas difference of every standard test
+    pub tests: Vec<(String, u64, i64)>,
+    /// The accumulated gas differences of all tests
+    pub deltas: GasDeltas,
+}
+
+impl GasScheduleReport {
+    /// Creates an empty report, the functions of the given contracts are named in the report
+    /// unless they are test contracts, i.e. contracts with test functions
+    pub fn new<'a>(schedule: GasSchedule, abis: impl IntoIterator<Item = &'a Abi>) -> Self {
+        let signatures = abis
+            .into_iter()
+            .filter(|abi| !abi.functions().any(|func| func.name.starts_with("test")))
+            .flat_map(|abi| abi.functions())
+            .map(|func| (func.short_signature(), func.signature()))
+            .collect();
+        Self { schedule, signatures, tests: Vec::new(), deltas: GasDeltas::default() }
+    }
+
+    /// Adds the gas differences of a test, fuzz tests are ignored since they are not measured
+    /// under the schedule
+    pub fn add(&mut self, name: String, result: &TestResult) {
+        if let (TestKind::Standard(gas), Some(deltas)) = (&result.kind, &result.gas_deltas) {
+            self.tests.push((name, *gas, deltas.total));
+            self.deltas.merge(deltas);
+        }
+    }
+}
+
+impl Display for GasScheduleReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let header = |name: &str| Cell::new(name).add_attribute(Attribute::Bold).fg(Color::Cyan);
+        let delta_cell = |delta: i64| {
+            let color = match delta {
+                d if d > 0 => Color::Red,
+                d if d < 0 => Color::Green,
+                _ => Color::Reset,
+            };
+            Cell::new(format!("{:+}", delta)).fg(color)
+        };
+
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+        table.set_header(vec![Cell::new(format!("Gas schedule: {}", self.schedule))
+            .add_attribute(Attribute::Bold)
+            .fg(Color::Green)]);
+        table.add_row(vec![header("Test"), header("Standard"), header("Overridden"), header("Δ")]);
+        for (name, gas, delta) in &self.tests {
+            table.add_row(vec![
+                Cell::new(name).add_attribute(Attribute::Bold),
+                Cell::new(gas.to_string()),
+                Cell::new((*gas as i64 + delta).to_string()),
+                delta_cell(*delta),
+            ]);
+        }
+        writeln!(f, "{}", table)?;
+
+        let functions = self
+            .deltas
+            .functions
+            .iter()
+            .filter_map(|(selector, delta)| Some((self.signatures.get(selector)?, delta)))
+            .collect::<Vec<_>>();
+        if !functions.is_empty() {
+            let mut table = Table::new();
+            table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+            table.set_header(vec![header("Function"), header("Total Δ across all tests")]);
+            for (sig, delta) in functions {
+                table.add_row(vec![
+                    Cell::new(sig).add_attribute(Attribute::Bold),
+                    delta_cell(*delta),
+                ]);
+            }
+            writeln!(f, "{}", table)?;
+        }
+        Ok(())
+    }
+}
@@ -4,6 +4,12 @@ pub mod gas_report;
 /// Coverage reports
 pub mod coverage;
 
+/// Gas flamegraphs
+pub mod flamegraph;
+
+/// Call timelines in the Chrome trace event format
+pub mod chrome_trace;
+
 /// The Forge test runner
 mod runner;
 pub use runner::ContractRunner;
@@ -17,6 +23,87 @@ pub use traits::*;
 
 pub mod result;
 
+/// The changes of tests to the state
+pub mod state_diff;
+
+/// Replaying blocks with contract overrides
+pub mod replay;
+
+/// Checking tests against a reference EVM
+pub mod reference;
+
+/// Per-test configuration with natspec comments
+pub mod inline_config;
+
+/// Upgrade-safety checks of implementation contracts
+pub mod upgrade_check;
+use inline_config::{InlineConfig, TestConfig};
+
+use foundry_config::Config;
+use foundry_evm::fuzz::FuzzDictionaryConfig;
+
+/// Test configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestOptions {
+    /// Whether to include the storage of all accounts in the fuzz dictionary
+    pub include_storage: bool,
+    /// Whether to include the PUSH values of all bytecode in the fuzz dictionary
+    pub include_push_bytes: bool,
+    /// The percentage of fuzz inputs that are drawn from the fuzz dictionary
+    pub dictionary_weight: u32,
+    /// The number of most recent successful fuzz cases whose logs are kept
+    pub keep_logs: usize,
+    /// The maximum amount of gas a standard test may use
+    pub gas_limit_per_test: Option<u64>,
+    /// The settings of single test contracts and tests, overridden with natspec comments
+    pub inline_config: InlineConfig,
+}
+
+impl TestOptions {
+    /// Returns the configuration of the fuzz dictionary
+    pub fn fuzz_dictionary(&self) -> FuzzDictionaryConfig {
+        FuzzDictionaryConfig {
+            include_storage: self.include_storage,
+            include_push_bytes: self.include_push_bytes,
+            dictionary_weight: self.dictionary_weight,
+        }
+    }
+
+    /// Returns the settings of the `test` of the `contract`, given by its identifier, that are
+    /// overridden with natspec comments
+    pub fn test_config(&self, contract: &str, test: &str) -> TestConfig {
+        self.inline_config.get(contract, test)
+    }
+}
+
+impl Default for TestOptions {
+    fn default() -> Self {
+        let FuzzDictionaryConfig { include_storage, include_push_bytes, dictionary_weight } =
+            FuzzDictionaryConfig::default();
+        Self {
+            include_storage,
+            include_push_bytes,
+            dictionary_weight,
+            keep_logs: 0,
+            gas_limit_per_test: None,
+            inline_config: Default::default(),
+        }
+    }
+}
+
+impl From<&Config> for TestOptions {
+    fn from(config: &Config) -> Self {
+        Self {
+            include_storage: config.fuzz_include_storage,
+            include_push_bytes: config.fuzz_include_push_bytes,
+            dictionary_weight: config.fuzz_dictionary_weight,
+            keep_logs: config.fuzz_keep_logs,
+            gas_limit_per_test: config.gas_limit_per_test,
+            inline_config: Default::default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test_helpers;
 
@@ -1,22 +1,43 @@
-use crate::{result::SuiteResult, ContractRunner, TestFilter};
+use crate::{
+    inline_config::ForkConfig,
+    reference::ReferenceEvm,
+    result::{ArtifactInfo, BuildInfo, CompilerSettings, SuiteResult, TestResult, TestSetup},
+    ContractRunner, TestFilter, TestOptions,
+};
 use ethers::{
     abi::Abi,
-    prelude::{artifacts::CompactContractBytecode, ArtifactId, ArtifactOutput},
-    solc::{Artifact, ProjectCompileOutput},
-    types::{Address, Bytes, U256},
+    prelude::{
+        artifacts::{CompactContractBytecode, Libraries},
+        ArtifactId, ArtifactOutput,
+    },
+    solc::{utils::RuntimeOrHandle, Artifact, Graph, ProjectCompileOutput},
+    types::{Address, Bytes, H256, U256},
+    utils::keccak256,
 };
-use eyre::Result;
+use eyre::{Result, WrapErr};
 use foundry_evm::{
     executor::{
-        backend::Backend, fork::CreateFork, inspector::CheatsConfig, opts::EvmOpts, Executor,
-        ExecutorBuilder, SpecId,
+        backend::Backend,
+        fork::CreateFork,
+        inspector::{CheatsConfig, CustomInspectorFactory, CustomPrecompiles, GasSchedule},
+        opts::EvmOpts,
+        Executor, ExecutorBuilder, SpecId,
     },
     revm,
 };
 use foundry_utils::PostLinkInput;
 use proptest::test_runner::TestRunner;
 use rayon::prelude::*;
-use std::{collections::BTreeMap, path::Path, sync::mpsc::Sender};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+};
 
 pub type DeployableContracts = BTreeMap<ArtifactId, (Abi, Bytes, Vec<Bytes>)>;
 
@@ -48,9 +69,59 @@ pub struct MultiContractRunner {
     pub cheats_config: CheatsConfig,
     /// Whether to collect coverage info
     pub coverage: bool,
+    /// The hypothetical gas schedule to measure gas differences against, if any
+    pub gas_schedule: Option<GasSchedule>,
+    /// The test configuration
+    pub test_options: TestOptions,
+    /// The compiler settings, attached to the [BuildInfo] of every suite if set
+    pub compiler_settings: Option<CompilerSettings>,
+    /// The artifacts involved in every test contract, see [BuildInfo::artifacts]
+    pub artifacts: BTreeMap<ArtifactId, Vec<ArtifactInfo>>,
+    /// The reference EVM standard tests are checked against, if any
+    pub reference_evm: Option<ReferenceEvm>,
+    /// Set when the run was cancelled, in which case the remaining tests are skipped
+    pub cancelled: Arc<AtomicBool>,
+    /// Whether to cancel the run as soon as a test fails
+    pub fail_fast: bool,
+    /// Whether the calls made by the tests are executed as transactions of their own
+    pub isolate: bool,
+    /// Whether to run the tests without tracing, and re-run only the failing tests with tracing
+    pub trace_failures: bool,
+    /// Whether to record the state after every standard test
+    pub dump_state: bool,
+    /// Whether to record the changes of every standard test to the state
+    pub state_diff: bool,
+    /// The identifiers of the test contracts that are skipped, e.g. because their results are
+    /// cached
+    pub skipped_suites: BTreeSet<String>,
+    /// The only tests to run, as signatures keyed by the identifiers of their test contracts,
+    /// e.g. the tests that failed in the previous run
+    pub selected_tests: Option<BTreeMap<String, BTreeSet<String>>>,
+    /// The factories of the custom inspectors that run alongside the built-in inspectors
+    pub custom_inspectors: Vec<Arc<dyn CustomInspectorFactory>>,
+    /// The precompiles at custom addresses
+    pub precompiles: CustomPrecompiles,
 }
 
 impl MultiContractRunner {
+    /// Returns the hash of the linked creation code and the library code of every test contract,
+    /// keyed by the identifier of the contract.
+    ///
+    /// The hash changes if the code of the test contract, any contract it creates, or any library
+    /// it links against changes.
+    pub fn code_hashes(&self) -> BTreeMap<String, H256> {
+        self.contracts
+            .iter()
+            .map(|(id, (_, deploy_code, libs))| {
+                let code = std::iter::once(deploy_code)
+                    .chain(libs)
+                    .flat_map(|code| code.iter().copied())
+                    .collect::<Vec<_>>();
+                (id.identifier(), H256::from(keccak256(code)))
+            })
+            .collect()
+    }
+
     pub fn count_filtered_tests(&self, filter: &impl TestFilter) -> usize {
         self.contracts
             .iter()
@@ -123,6 +194,25 @@ impl MultiContractRunner {
 
         let db = Backend::spawn(self.fork.take());
 
+        // the tests that run on their own forks, every fork is created once and shared by all of
+        // its tests
+        let mut forks = BTreeMap::new();
+        for (id, (abi, _, _)) in self.contracts.iter().filter(|(id, _)| {
+            filter.matches_path(id.source.to_string_lossy()) &&
+                filter.matches_contract(&id.name) &&
+                !self.skipped_suites.contains(&id.identifier()) &&
+                self.is_selected_suite(&id.identifier())
+        }) {
+            let tests = self.tests_by_fork(&id.identifier(), abi, filter, include_fuzz_tests);
+            for fork in tests.into_keys().flatten() {
+                if !forks.contains_key(&fork) {
+                    let create_fork = self.create_fork(&fork)?;
+                    let env = create_fork.env.clone();
+                    forks.insert(fork, (Backend::spawn(Some(create_fork)), env));
+                }
+            }
+        }
+
         let results =
             // the db backend that serves all the data, each contract gets its own instance
 
@@ -136,26 +226,64 @@ impl MultiContractRunner {
                 .filter(|(_, (abi, _, _))| {
                     abi.functions().any(|func| filter.matches_test(&func.name))
                 })
+                .filter(|(id, _)| !self.skipped_suites.contains(&id.identifier()))
+                .filter(|(id, _)| self.is_selected_suite(&id.identifier()))
+                // Suites that did not start before the run was cancelled are skipped
+                .filter(|_| !self.cancelled.load(Ordering::Relaxed))
                 .map(|(id, (abi, deploy_code, libs))| {
-                    let executor = ExecutorBuilder::default()
-                        .with_cheatcodes(self.cheats_config.clone())
-                        .with_config(self.env.clone())
-                        .with_spec(self.evm_spec)
-                        .with_gas_limit(self.evm_opts.gas_limit())
-                        .set_tracing(self.evm_opts.verbosity >= 3)
-                        .set_coverage(self.coverage)
-                        .build(db.clone());
+                    let executor = |db: Backend, env: revm::Env| {
+                        ExecutorBuilder::default()
+                            .with_cheatcodes(self.cheats_config.clone())
+                            .with_config(env)
+                            .with_spec(self.evm_spec)
+                            .with_gas_limit(self.evm_opts.gas_limit())
+                            .set_tracing(self.evm_opts.verbosity >= 3 && !self.trace_failures)
+                            .set_coverage(self.coverage)
+                            .set_gas_schedule(self.gas_schedule.clone())
+                            .with_custom_inspectors(self.custom_inspectors.clone())
+                            .with_precompiles(self.precompiles.clone())
+                            .build(db)
+                    };
                     let identifier = id.identifier();
                     tracing::trace!(contract= ?identifier, "start executing all tests in contract");
 
-                    let result = self.run_tests(
-                        &identifier,
-                        abi,
-                        executor,
-                        deploy_code.clone(),
-                        libs,
-                        (filter, include_fuzz_tests),
-                    )?;
+                    let tests = self.tests_by_fork(&identifier, abi, filter, include_fuzz_tests);
+                    let mut result = if self.selected_tests.is_none() &&
+                        tests.keys().all(Option::is_none)
+                    {
+                        self.run_tests(
+                            &identifier,
+                            abi,
+                            executor(db.clone(), self.env.clone()),
+                            deploy_code.clone(),
+                            libs,
+                            (filter, include_fuzz_tests),
+                        )?
+                    } else {
+                        // every fork runs `setUp` and its tests on its own state, the selected
+                        // tests are already filtered by `tests_by_fork`
+                        let mut result =
+                            SuiteResult::new(Default::default(), Default::default(), Vec::new());
+                        for (fork, tests) in tests {
+                            let (db, env) = match fork {
+                                Some(fork) => forks[&fork].clone(),
+                                None => (db.clone(), self.env.clone()),
+                            };
+                            result.merge(self.run_tests(
+                                &identifier,
+                                abi,
+                                executor(db, env),
+                                deploy_code.clone(),
+                                libs,
+                                (&TestSetFilter(&tests), include_fuzz_tests),
+                            )?);
+                        }
+                        result
+                    };
+                    result.build_info = Some(BuildInfo {
+                        settings: self.compiler_settings.clone(),
+                        artifacts: self.artifacts.get(id).cloned().unwrap_or_default(),
+                    });
 
                     tracing::trace!(contract= ?identifier, "executed all tests in contract");
                     Ok((identifier, result))
@@ -174,23 +302,188 @@ impl MultiContractRunner {
         Ok(results)
     }
 
-    // The _name field is unused because we only want it for tracing
+    /// Deploys the test contract matching the `filter` and runs its `setUp` function.
+    ///
+    /// Returns the identifier of the contract, the [TestSetup] and the executor holding the state
+    /// after `setUp`. Fails if the filter does not match exactly one contract.
+    pub fn setup(&mut self, filter: &impl TestFilter) -> Result<(String, TestSetup, Executor)> {
+        let mut matching = self.contracts.iter().filter(|(id, _)| {
+            filter.matches_path(id.source.to_string_lossy()) && filter.matches_contract(&id.name)
+        });
+        let (id, (abi, deploy_code, libs)) =
+            matching.next().ok_or_else(|| eyre::eyre!("No test contract matches the filter"))?;
+        if matching.next().is_some() {
+            eyre::bail!("More than one test contract matches the filter, but exactly 1 must match")
+        }
+
+        let executor = ExecutorBuilder::default()
+            .with_cheatcodes(self.cheats_config.clone())
+            .with_config(self.env.clone())
+            .with_spec(self.evm_spec)
+            .with_gas_limit(self.evm_opts.gas_limit())
+            .set_tracing(self.evm_opts.verbosity >= 3)
+            .with_custom_inspectors(self.custom_inspectors.clone())
+            .with_precompiles(self.precompiles.clone())
+            .build(Backend::spawn(self.fork.take()));
+
+        let mut runner = ContractRunner::new(
+            executor,
+            abi,
+            deploy_code.clone(),
+            self.evm_opts.initial_balance,
+            self.sender,
+            self.errors.as_ref(),
+            libs,
+        );
+        let setup = runner.setup(abi.functions().any(|func| func.name == "setUp"))?;
+        Ok((id.identifier(), setup, runner.executor))
+    }
+
+    /// Runs a single test of a test contract and returns its full [TestResult], including its
+    /// traces and, if enabled, its coverage.
+    ///
+    /// The contract is identified by its name or its `<path>:<name>` identifier, the test by its
+    /// name or, if it is overloaded, its signature. Fails if either does not match exactly one
+    /// contract or test. If the `setUp` function of the contract fails, its result is returned.
+    pub fn run_test(&self, contract: &str, test: &str) -> Result<TestResult> {
+        let mut matching = self
+            .contracts
+            .iter()
+            .filter(|(id, _)| id.name == contract || id.identifier() == contract);
+        let (id, (abi, deploy_code, libs)) = matching
+            .next()
+            .ok_or_else(|| eyre::eyre!("No test contract named `{}` found", contract))?;
+        if matching.next().is_some() {
+            eyre::bail!(
+                "More than one test contract is named `{}`, use its `<path>:<name>` identifier",
+                contract
+            )
+        }
+
+        let mut tests = abi.functions().filter(|func| {
+            func.name.starts_with("test") && (func.name == test || func.signature() == test)
+        });
+        let signature = tests
+            .next()
+            .ok_or_else(|| eyre::eyre!("No test `{}` found in `{}`", test, contract))?
+            .signature();
+        if tests.next().is_some() {
+            eyre::bail!("Test `{}` in `{}` is overloaded, use its signature", test, contract)
+        }
+
+        let name = signature.split('(').next().unwrap_or(&signature);
+        let (fork, env) = match self.test_options.test_config(&id.identifier(), name).fork {
+            Some(fork) => {
+                let fork = self.create_fork(&fork)?;
+                let env = fork.env.clone();
+                (Some(fork), env)
+            }
+            None => (self.fork.clone(), self.env.clone()),
+        };
+        let executor = ExecutorBuilder::default()
+            .with_cheatcodes(self.cheats_config.clone())
+            .with_config(env)
+            .with_spec(self.evm_spec)
+            .with_gas_limit(self.evm_opts.gas_limit())
+            .set_tracing(true)
+            .set_coverage(self.coverage)
+            .set_gas_schedule(self.gas_schedule.clone())
+            .with_custom_inspectors(self.custom_inspectors.clone())
+            .with_precompiles(self.precompiles.clone())
+            .build(Backend::spawn(fork));
+
+        let mut result = self.run_tests(
+            &id.identifier(),
+            abi,
+            executor,
+            deploy_code.clone(),
+            libs,
+            (&SingleTestFilter(&signature), true),
+        )?;
+        result
+            .test_results
+            .remove(&signature)
+            .or_else(|| result.test_results.remove("setUp()"))
+            .ok_or_else(|| eyre::eyre!("Test `{}` in `{}` was not run", signature, contract))
+    }
+
+    /// Returns the signatures of the tests of the contract that match the `filter`, grouped by the
+    /// fork they run on, which is `None` for the tests that run on the fork of the run, if any
+    fn tests_by_fork(
+        &self,
+        identifier: &str,
+        contract: &Abi,
+        filter: &impl TestFilter,
+        include_fuzz_tests: bool,
+    ) -> BTreeMap<Option<ForkConfig>, BTreeSet<String>> {
+        let mut tests: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+        for func in contract.functions().filter(|func| {
+            func.name.starts_with("test") &&
+                filter.matches_test(func.signature()) &&
+                (include_fuzz_tests || func.inputs.is_empty())
+        }) {
+            let signature = func.signature();
+            if !self.is_selected_test(identifier, &signature) {
+                continue
+            }
+            let fork = self.test_options.test_config(identifier, &func.name).fork;
+            tests.entry(fork).or_default().insert(signature);
+        }
+        tests
+    }
+
+    /// Returns `true` if any test of the test contract with the given identifier is selected, see
+    /// [MultiContractRunner::selected_tests]
+    fn is_selected_suite(&self, identifier: &str) -> bool {
+        self.selected_tests.as_ref().map_or(true, |tests| tests.contains_key(identifier))
+    }
+
+    /// Returns `true` if the test is selected, see [MultiContractRunner::selected_tests]
+    fn is_selected_test(&self, identifier: &str, signature: &str) -> bool {
+        self.selected_tests.as_ref().map_or(true, |tests| {
+            tests.get(identifier).map_or(false, |signatures| signatures.contains(signature))
+        })
+    }
+
+    /// Returns the fork of a test, with the rpc aliases and their pinned block numbers resolved
+    /// like the ones of the fork cheatcodes
+    fn create_fork(&self, fork: &ForkConfig) -> Result<CreateFork> {
+        let config = &self.cheats_config;
+        let endpoint = config.get_rpc_endpoint(&fork.url_or_alias).map_err(|_| {
+            eyre::eyre!("Failed to resolve the rpc url or alias of the fork `{}`", fork)
+        })?;
+        let mut evm_opts = self.evm_opts.clone();
+        evm_opts.fork_url = Some(endpoint.url.clone());
+        evm_opts.fork_block_number =
+            fork.block.or_else(|| config.fork_block_numbers.get(&fork.url_or_alias).copied());
+        let env = RuntimeOrHandle::new()
+            .block_on(evm_opts.fork_evm_env(&endpoint.url))
+            .wrap_err_with(|| format!("Failed to create the fork `{fork}`"))?;
+        Ok(CreateFork {
+            enable_caching: config.rpc_storage_caching.enable_for_endpoint(&endpoint.url),
+            endpoint,
+            env,
+            evm_opts,
+            prefetch_dir: config.fork_prefetch_dir.clone(),
+        })
+    }
+
     #[tracing::instrument(
         name = "contract",
         skip_all,
         err,
-        fields(name = %_name)
+        fields(name = %name)
     )]
     fn run_tests(
         &self,
-        _name: &str,
+        name: &str,
         contract: &Abi,
         executor: Executor,
         deploy_code: Bytes,
         libs: &[Bytes],
         (filter, include_fuzz_tests): (&impl TestFilter, bool),
     ) -> Result<SuiteResult> {
-        let runner = ContractRunner::new(
+        let mut runner = ContractRunner::new(
             executor,
             contract,
             deploy_code,
@@ -199,7 +492,50 @@ impl MultiContractRunner {
             self.errors.as_ref(),
             libs,
         );
-        runner.run_tests(filter, self.fuzzer.clone(), include_fuzz_tests)
+        runner.name = name;
+        runner.reference_evm = self.reference_evm.as_ref();
+        runner.cancelled = self.cancelled.clone();
+        runner.fail_fast = self.fail_fast;
+        runner.isolate = self.isolate;
+        runner.trace_failures = self.trace_failures && self.evm_opts.verbosity >= 3;
+        runner.gas_limit_per_test = self.test_options.gas_limit_per_test;
+        runner.dump_state = self.dump_state;
+        runner.state_diff = self.state_diff;
+        runner.run_tests(filter, self.fuzzer.clone(), &self.test_options, include_fuzz_tests)
+    }
+}
+
+/// A filter that only matches the test with the given signature
+struct SingleTestFilter<'a>(&'a str);
+
+impl TestFilter for SingleTestFilter<'_> {
+    fn matches_test(&self, test_name: impl AsRef<str>) -> bool {
+        test_name.as_ref() == self.0
+    }
+
+    fn matches_contract(&self, _: impl AsRef<str>) -> bool {
+        true
+    }
+
+    fn matches_path(&self, _: impl AsRef<str>) -> bool {
+        true
+    }
+}
+
+/// A filter that only matches the tests with the given signatures
+struct TestSetFilter<'a>(&'a BTreeSet<String>);
+
+impl TestFilter for TestSetFilter<'_> {
+    fn matches_test(&self, test_name: impl AsRef<str>) -> bool {
+        self.0.contains(test_name.as_ref())
+    }
+
+    fn matches_contract(&self, _: impl AsRef<str>) -> bool {
+        true
+    }
+
+    fn matches_path(&self, _: impl AsRef<str>) -> bool {
+        true
     }
 }
 
@@ -221,6 +557,34 @@ pub struct MultiContractRunnerBuilder {
     pub cheats_config: Option<CheatsConfig>,
     /// Whether or not to collect coverage info
     pub coverage: bool,
+    /// The hypothetical gas schedule to measure gas differences against, if any
+    pub gas_schedule: Option<GasSchedule>,
+    /// The test configuration
+    pub test_options: TestOptions,
+    /// The compiler settings the contracts were built with
+    pub compiler_settings: Option<CompilerSettings>,
+    /// The hash of the sources every source imports, keyed by the absolute path of the source
+    pub imports_hashes: BTreeMap<PathBuf, H256>,
+    /// The reference EVM standard tests are checked against, if any
+    pub reference_evm: Option<ReferenceEvm>,
+    /// The flag that cancels the run when set
+    pub cancelled: Arc<AtomicBool>,
+    /// Whether to cancel the run as soon as a test fails
+    pub fail_fast: bool,
+    /// Whether the calls made by the tests are executed as transactions of their own
+    pub isolate: bool,
+    /// Whether to run the tests without tracing, and re-run only the failing tests with tracing
+    pub trace_failures: bool,
+    /// Whether to record the state after every standard test
+    pub dump_state: bool,
+    /// Whether to record the changes of every standard test to the state
+    pub state_diff: bool,
+    /// The libraries that are already deployed, these are linked instead of deployed by the tests
+    pub libraries: Libraries,
+    /// The factories of the custom inspectors that run alongside the built-in inspectors
+    pub custom_inspectors: Vec<Arc<dyn CustomInspectorFactory>>,
+    /// The precompiles at custom addresses
+    pub precompiles: CustomPrecompiles,
 }
 
 impl MultiContractRunnerBuilder {
@@ -239,34 +603,71 @@ impl MultiContractRunnerBuilder {
         // This is just the contracts compiled, but we need to merge this with the read cached
         // artifacts
         let contracts = output
-            .with_stripped_file_prefixes(root)
+            .with_stripped_file_prefixes(root.as_ref())
             .into_artifacts()
             .map(|(i, c)| (i, c.into_contract_bytecode()))
             .collect::<Vec<(ArtifactId, CompactContractBytecode)>>();
 
+        self.build_with_artifacts(root, contracts, env, evm_opts)
+    }
+
+    /// Returns a runner for the given artifacts, e.g. artifacts read from the cache without
+    /// compiling.
+    ///
+    /// The source paths of the artifacts are expected to be relative to `root`.
+    pub fn build_with_artifacts(
+        self,
+        root: impl AsRef<Path>,
+        contracts: Vec<(ArtifactId, CompactContractBytecode)>,
+        env: revm::Env,
+        evm_opts: EvmOpts,
+    ) -> Result<MultiContractRunner> {
+        let root = root.as_ref().to_path_buf();
+
         let mut known_contracts: BTreeMap<ArtifactId, (Abi, Vec<u8>)> = Default::default();
         let source_paths = contracts
             .iter()
             .map(|(i, _)| (i.identifier(), i.source.to_string_lossy().into()))
             .collect::<BTreeMap<String, String>>();
+        let ids = contracts.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>();
+        let imports_hashes = self.imports_hashes;
 
         // create a mapping of name => (abi, deployment code, Vec<library deployment code>)
         let mut deployable_contracts = DeployableContracts::default();
+        // and a mapping of name => artifacts involved in the contract
+        let mut artifacts: BTreeMap<ArtifactId, Vec<ArtifactInfo>> = Default::default();
+
+        // the link references use the source paths relative to `root`
+        let libraries = Libraries {
+            libs: self
+                .libraries
+                .libs
+                .into_iter()
+                .map(|(file, libs)| {
+                    let file = file
+                        .strip_prefix(&root)
+                        .or_else(|_| file.strip_prefix("./"))
+                        .map(Path::to_path_buf)
+                        .unwrap_or(file);
+                    (file, libs)
+                })
+                .collect(),
+        };
 
         foundry_utils::link_with_nonce_or_address(
             BTreeMap::from_iter(contracts),
             &mut known_contracts,
-            Default::default(),
+            libraries,
             evm_opts.sender,
             U256::one(),
-            &mut deployable_contracts,
+            &mut (&mut deployable_contracts, &mut artifacts),
             |file, key| (format!("{key}.json:{key}"), file, key),
             |post_link_input| {
                 let PostLinkInput {
                     contract,
                     known_contracts,
                     id,
-                    extra: deployable_contracts,
+                    extra: (deployable_contracts, artifacts),
                     dependencies,
                 } = post_link_input;
 
@@ -283,6 +684,26 @@ impl MultiContractRunnerBuilder {
                 if abi.constructor.as_ref().map(|c| c.inputs.is_empty()).unwrap_or(true) &&
                     abi.functions().any(|func| func.name.starts_with("test"))
                 {
+                    // the dependencies are identified by `<file>:<contract>:<address>`
+                    let libraries = dependencies.iter().filter_map(|(dependency, _)| {
+                        let mut parts = dependency.rsplitn(3, ':').skip(1);
+                        let (name, file) = (parts.next()?, Path::new(parts.next()?));
+                        let mut candidates =
+                            ids.iter().filter(|lib| lib.name == name && lib.source == file);
+                        // prefer the library compiled with the same version as the test contract
+                        candidates
+                            .clone()
+                            .find(|lib| lib.version == id.version)
+                            .or_else(|| candidates.next())
+                    });
+                    artifacts.insert(
+                        id.clone(),
+                        std::iter::once(&id)
+                            .chain(libraries)
+                            .map(|id| artifact_info(&root, id, &imports_hashes))
+                            .collect(),
+                    );
+
                     deployable_contracts.insert(
                         id.clone(),
                         (
@@ -319,6 +740,21 @@ impl MultiContractRunnerBuilder {
             fork: self.fork,
             cheats_config: self.cheats_config.unwrap_or_default(),
             coverage: self.coverage,
+            gas_schedule: self.gas_schedule,
+            test_options: self.test_options,
+            compiler_settings: self.compiler_settings,
+            artifacts,
+            reference_evm: self.reference_evm,
+            cancelled: self.cancelled,
+            fail_fast: self.fail_fast,
+            isolate: self.isolate,
+            trace_failures: self.trace_failures,
+            dump_state: self.dump_state,
+            state_diff: self.state_diff,
+            skipped_suites: Default::default(),
+            selected_tests: None,
+            custom_inspectors: self.custom_inspectors,
+            precompiles: self.precompiles,
         })
     }
 
@@ -363,6 +799,129 @@ impl MultiContractRunnerBuilder {
         self.coverage = enable;
         self
     }
+
+    #[must_use]
+    pub fn set_gas_schedule(mut self, schedule: Option<GasSchedule>) -> Self {
+        self.gas_schedule = schedule;
+        self
+    }
+
+    #[must_use]
+    pub fn with_test_options(mut self, test_options: TestOptions) -> Self {
+        self.test_options = test_options;
+        self
+    }
+
+    #[must_use]
+    pub fn with_compiler_settings(mut self, settings: CompilerSettings) -> Self {
+        self.compiler_settings = Some(settings);
+        self
+    }
+
+    /// Hashes the sources every source of the `graph` imports, directly or transitively, see
+    /// [ArtifactInfo::imports_hash]
+    #[must_use]
+    pub fn with_source_graph(mut self, graph: &Graph) -> Self {
+        self.imports_hashes = graph
+            .files()
+            .iter()
+            .map(|(path, index)| {
+                let imports = graph
+                    .all_imported_nodes(*index)
+                    .map(|import| graph.node(import).unpack())
+                    .collect::<BTreeMap<_, _>>();
+                let hashes = imports
+                    .values()
+                    .flat_map(|source| keccak256(source.content.as_bytes()))
+                    .collect::<Vec<_>>();
+                (path.clone(), H256::from(keccak256(hashes)))
+            })
+            .collect();
+        self
+    }
+
+    #[must_use]
+    pub fn set_dump_state(mut self, enable: bool) -> Self {
+        self.dump_state = enable;
+        self
+    }
+
+    #[must_use]
+    pub fn set_state_diff(mut self, enable: bool) -> Self {
+        self.state_diff = enable;
+        self
+    }
+
+    #[must_use]
+    pub fn with_libraries(mut self, libraries: Libraries) -> Self {
+        self.libraries = libraries;
+        self
+    }
+
+    #[must_use]
+    pub fn with_reference_evm(mut self, reference_evm: Option<ReferenceEvm>) -> Self {
+        self.reference_evm = reference_evm;
+        self
+    }
+
+    #[must_use]
+    pub fn with_cancellation(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancelled = cancelled;
+        self
+    }
+
+    #[must_use]
+    pub fn set_fail_fast(mut self, enable: bool) -> Self {
+        self.fail_fast = enable;
+        self
+    }
+
+    #[must_use]
+    pub fn set_isolation(mut self, enable: bool) -> Self {
+        self.isolate = enable;
+        self
+    }
+
+    #[must_use]
+    pub fn set_trace_failures(mut self, enable: bool) -> Self {
+        self.trace_failures = enable;
+        self
+    }
+
+    /// Registers a custom inspector that runs alongside the built-in inspectors in every test.
+    ///
+    /// The data collected by the inspectors is attached to the
+    /// [TestResult](crate::result::TestResult) of every standard test, see
+    /// [TestResult::extensions](crate::result::TestResult::extensions).
+    #[must_use]
+    pub fn with_inspector(mut self, factory: Arc<dyn CustomInspectorFactory>) -> Self {
+        self.custom_inspectors.push(factory);
+        self
+    }
+
+    /// Registers precompiles at custom addresses in every test
+    #[must_use]
+    pub fn with_precompiles(mut self, precompiles: CustomPrecompiles) -> Self {
+        self.precompiles.extend(precompiles);
+        self
+    }
+}
+
+/// Returns the [ArtifactInfo] of the artifact with the given id, hashing its source file
+fn artifact_info(
+    root: &Path,
+    id: &ArtifactId,
+    imports_hashes: &BTreeMap<PathBuf, H256>,
+) -> ArtifactInfo {
+    let path = root.join(&id.source);
+    let source_hash = fs::read(&path).ok().map(|content| H256::from(keccak256(content)));
+    ArtifactInfo {
+        identifier: id.identifier(),
+        compiler_version: id.version.to_string(),
+        source: id.source.clone(),
+        source_hash,
+        imports_hash: imports_hashes.get(&path).copied(),
+    }
 }
 
 #[cfg(test)]
@@ -376,7 +935,7 @@ mod tests {
         },
     };
     use foundry_config::{Config, RpcEndpoint, RpcEndpoints};
-    use foundry_evm::trace::TraceKind;
+    use foundry_evm::{executor::CHEATCODE_ADDRESS, trace::TraceKind};
     use std::env;
 
     /// Builds a base runner
@@ -514,6 +1073,277 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_build_info() {
+        let mut runner = runner();
+        let results = runner.test(&Filter::new(".*", ".*", ".*core"), None, true).unwrap();
+        assert!(!results.is_empty());
+
+        for (name, suite) in results {
+            let build_info = suite.build_info.expect("suite has no build info");
+            assert_eq!(build_info.settings, None);
+            let artifact = &build_info.artifacts[0];
+            assert_eq!(artifact.identifier, name);
+            assert!(artifact.source_hash.is_some(), "Source of {} was not hashed", name);
+        }
+    }
+
+    #[test]
+    fn test_build_info_imports() {
+        let graph = Graph::resolve(&PROJECT.paths).unwrap();
+        let mut runner = base_runner()
+            .with_source_graph(&graph)
+            .build(
+                &PROJECT.paths.root,
+                (*COMPILED).clone(),
+                EVM_OPTS.evm_env_blocking(),
+                EVM_OPTS.clone(),
+            )
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", "RollTest", ".*cheats"), None, true).unwrap();
+        let suite = results.into_values().next().unwrap();
+        let artifact = &suite.build_info.unwrap().artifacts[0];
+
+        // the test imports `ds-test/test.sol` and `Cheats.sol`
+        let imports = graph
+            .all_imported_nodes(graph.files()[&PROJECT.paths.root.join(&artifact.source)])
+            .map(|import| graph.node(import).unpack())
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(imports.len(), 2);
+        let hashes = imports
+            .values()
+            .flat_map(|source| keccak256(source.content.as_bytes()))
+            .collect::<Vec<_>>();
+        assert_eq!(artifact.imports_hash, Some(H256::from(keccak256(hashes))));
+    }
+
+    #[test]
+    fn test_build_info_libraries() {
+        let mut runner = runner();
+        let results =
+            runner.test(&Filter::new(".*", "LibraryLinkingTest", ".*core"), None, true).unwrap();
+        let suite = results.into_values().next().unwrap();
+
+        // the linked libraries are identified by their full artifact id
+        let source = format!("core{}LibraryLinking.t.sol", std::path::MAIN_SEPARATOR);
+        let identifiers = suite
+            .build_info
+            .unwrap()
+            .artifacts
+            .into_iter()
+            .map(|artifact| artifact.identifier)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            identifiers,
+            BTreeSet::from([
+                format!("{source}:LibraryLinkingTest"),
+                format!("{source}:Lib"),
+                format!("{source}:NestedLib"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_cancelled() {
+        let mut runner = runner();
+        runner.cancelled.store(true, Ordering::Relaxed);
+        let results = runner.test(&Filter::new(".*", ".*", ".*core"), None, true).unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_fail_fast() {
+        let mut runner = runner();
+        runner.fail_fast = true;
+        let results = runner.test(&Filter::new(".*", ".*", ".*core"), None, true).unwrap();
+
+        // the first failure cancels the run
+        assert!(runner.cancelled.load(Ordering::Relaxed));
+        assert!(results
+            .values()
+            .flat_map(|suite| suite.test_results.values())
+            .any(|result| !result.success));
+    }
+
+    #[test]
+    fn test_gas_limit_per_test() {
+        let mut runner = runner();
+        runner.test_options.gas_limit_per_test = Some(1000);
+        let results = runner
+            .test(&Filter::new("testExpectGasUsed$", "ExpectGasUsedTest", ".*cheats"), None, true)
+            .unwrap();
+        let suite = results.into_values().next().unwrap();
+        let result = &suite.test_results["testExpectGasUsed()"];
+        assert!(!result.success);
+        assert!(result.reason.as_ref().unwrap().contains("exceeds the limit of 1000 per test"));
+    }
+
+    #[test]
+    fn test_reference_evm() {
+        let mut runner = runner();
+        // a reference EVM that always fails, to tell the skipped tests apart from the checked ones
+        runner.reference_evm = Some(ReferenceEvm::new("false"));
+        let results =
+            runner.test(&Filter::new(".*", "ReferenceEvmTest", ".*cheats"), None, true).unwrap();
+        let suite = results.into_values().next().unwrap();
+
+        // the contract contains the cheatcode address, but only the test that calls it is skipped
+        assert!(suite.test_results["testWithCheatcodes()"].success);
+        let result = &suite.test_results["testWithoutCheatcodes()"];
+        assert!(!result.success);
+        assert!(result.reason.as_ref().unwrap().starts_with("Failed to run the reference EVM"));
+    }
+
+    #[test]
+    fn test_gas_schedule_report() {
+        use crate::gas_report::GasScheduleReport;
+
+        let mut runner = runner();
+        let schedule: GasSchedule = "SLOAD=800".parse().unwrap();
+        runner.gas_schedule = Some(schedule.clone());
+        let results = runner.test(&Filter::new(".*", "RollTest", ".*cheats"), None, true).unwrap();
+        let suite = results.into_values().next().unwrap();
+        assert!(suite.test_results["testRollFuzzed(uint128)"].gas_deltas.is_none());
+
+        let mut report =
+            GasScheduleReport::new(schedule, runner.known_contracts.values().map(|(abi, _)| abi));
+        for (name, result) in &suite.test_results {
+            report.add(name.clone(), result);
+        }
+
+        // fuzz tests are not measured under the schedule, and test contracts are not reported
+        let tests = report.tests.iter().map(|(name, _, _)| name.as_str()).collect::<Vec<_>>();
+        assert_eq!(tests, vec!["testRoll()", "testRollHash()"]);
+        assert_eq!(report.to_string().matches("testRoll()").count(), 1);
+    }
+
+    #[test]
+    fn test_trace_failures() {
+        let mut runner = tracing_runner();
+        runner.trace_failures = true;
+        let results = runner.test(&Filter::new(".*", ".*", ".*core"), None, true).unwrap();
+
+        // only the failing tests were re-run with tracing
+        let tests = results.values().flat_map(|suite| suite.test_results.values());
+        for result in tests.filter(|result| result.success) {
+            assert!(result.traces.is_empty());
+        }
+        let suite = results
+            .iter()
+            .find(|(id, _)| id.ends_with("PaymentFailure.t.sol:PaymentFailureTest"))
+            .map(|(_, suite)| suite)
+            .unwrap();
+        let result = &suite.test_results["testCantPay()"];
+        assert!(!result.success);
+        assert!(result.traces.iter().any(|(kind, _)| *kind == TraceKind::Execution));
+    }
+
+    #[test]
+    fn test_dump_state() {
+        let mut runner = runner();
+        runner.dump_state = true;
+        let results =
+            runner.test(&Filter::new("testLoadAllocs$", "LoadAllocsTest", ".*cheats"), None, true);
+        let suite = results.unwrap().into_values().next().unwrap();
+        let state = suite.test_results["testLoadAllocs()"].state.clone().unwrap();
+
+        let allocated = state[&Address::from_low_u64_be(0xA110C)].clone();
+        assert_eq!(allocated.balance, U256::exp10(18));
+        assert_eq!(allocated.nonce, 5);
+        assert_eq!(allocated.storage[&U256::one()], U256::from(1337));
+        assert!(!state.contains_key(&CHEATCODE_ADDRESS));
+    }
+
+    #[test]
+    fn test_state_diff() {
+        use crate::state_diff::Change;
+
+        let mut runner = runner();
+        runner.state_diff = true;
+        let results = runner.test(&Filter::new("testStore$", "StoreTest", ".*cheats"), None, true);
+        let suite = results.unwrap().into_values().next().unwrap();
+        let diff = suite.test_results["testStore()"].state_diff.clone().unwrap();
+
+        // only the stored slot changed, the slots that were only read are not part of the diff
+        let (_, store) = diff.0.iter().find(|(_, account)| !account.storage.is_empty()).unwrap();
+        assert_eq!(
+            store.storage,
+            BTreeMap::from([(U256::zero(), Change { from: U256::from(10), to: U256::one() })])
+        );
+        assert!(store.deployed_code.is_none());
+        assert!(!diff.0.contains_key(&CHEATCODE_ADDRESS));
+    }
+
+    #[test]
+    fn test_custom_inspector() {
+        use foundry_evm::{
+            executor::inspector::CustomInspector,
+            revm::{Interpreter, Return},
+        };
+
+        #[derive(Debug)]
+        struct StepCounterFactory;
+
+        struct StepCounter(u64);
+
+        impl CustomInspector for StepCounter {
+            fn step(&mut self, _: &mut Interpreter, _: bool) -> Return {
+                self.0 += 1;
+                Return::Continue
+            }
+
+            fn finish(self: Box<Self>) -> Option<serde_json::Value> {
+                Some(self.0.into())
+            }
+        }
+
+        impl CustomInspectorFactory for StepCounterFactory {
+            fn name(&self) -> &str {
+                "steps"
+            }
+
+            fn create(&self) -> Box<dyn CustomInspector> {
+                Box::new(StepCounter(0))
+            }
+        }
+
+        let mut runner = base_runner()
+            .with_inspector(Arc::new(StepCounterFactory))
+            .build(
+                &PROJECT.paths.root,
+                (*COMPILED).clone(),
+                EVM_OPTS.evm_env_blocking(),
+                EVM_OPTS.clone(),
+            )
+            .unwrap();
+        let results =
+            runner.test(&Filter::new("testAddresses", ".*", ".*core"), None, true).unwrap();
+        assert!(!results.is_empty());
+
+        for suite in results.into_values() {
+            for result in suite.test_results.into_values() {
+                let steps = result.extensions["steps"].as_u64().unwrap();
+                assert!(steps > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_run_single_test() {
+        let runner = runner();
+
+        let result = runner.run_test("SetupConsistencyCheck", "testAdd").unwrap();
+        assert!(result.success);
+        assert!(!result.traces.is_empty());
+
+        let result =
+            runner.run_test("FailingSetupTest", "testFailShouldBeMarkedAsFailedBecauseOfSetup");
+        assert!(!result.unwrap().success);
+
+        assert!(runner.run_test("SetupConsistencyCheck", "testMissing").is_err());
+        assert!(runner.run_test("MissingTest", "testAdd").is_err());
+    }
+
     #[test]
     fn test_core() {
         let mut runner = runner();
@@ -560,6 +1390,35 @@ mod tests {
                         ("testMultiply()", true, None, None, None),
                     ],
                 ),
+                (
+                    format!("core{}TestSetup.t.sol:TestSpecificSetup", std::path::MAIN_SEPARATOR)
+                        .as_str(),
+                    vec![
+                        ("testWithdraw()", true, None, None, None),
+                        ("testDeposit()", true, None, None, None),
+                        (
+                            "testFailingSetup()",
+                            false,
+                            Some("Setup failed: test setup failed predictably".to_string()),
+                            None,
+                            None,
+                        ),
+                    ],
+                ),
+                (
+                    format!("core{}Hooks.t.sol:HooksTest", std::path::MAIN_SEPARATOR).as_str(),
+                    vec![
+                        ("testBeforeEach()", true, None, None, None),
+                        ("testBeforeEachIsolated()", true, None, None, None),
+                        (
+                            "testAfterEachFails()",
+                            false,
+                            Some("afterEach() failed: assertion failed".to_string()),
+                            None,
+                            None,
+                        ),
+                    ],
+                ),
                 (
                     format!("core{}DSStyle.t.sol:DSStyleTest", std::path::MAIN_SEPARATOR).as_str(),
                     vec![("testFailingAssertions()", true, None, None, None)],
@@ -1252,7 +2111,10 @@ Reason: `setEnv` failed to set an environment variable `{}={}`",
                 let logs = decode_console_logs(&result.logs);
 
                 match test_name.as_ref() {
-                    "testPositive(uint256)" | "testSuccessfulFuzz(uint128,uint128)" => assert!(
+                    "testPositive(uint256)" |
+                    "testSuccessfulFuzz(uint128,uint128)" |
+                    "testFuzzBeforeEach(uint256)" |
+                    "testFuzzSetupOrder(uint256)" => assert!(
                         result.success,
                         "Test {} did not pass as expected.\nReason: {:?}\nLogs:\n{}",
                         test_name,
@@ -1271,6 +2133,31 @@ Reason: `setEnv` failed to set an environment variable `{}={}`",
         }
     }
 
+    #[test]
+    fn test_fuzz_hooks() {
+        let mut runner = runner();
+        let results =
+            runner.test(&Filter::new(".*", "FuzzHooksTest", ".*fuzz"), None, true).unwrap();
+
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                format!("fuzz{}FuzzHooks.t.sol:FuzzHooksTest", std::path::MAIN_SEPARATOR).as_str(),
+                vec![
+                    ("testFuzzBeforeEach(uint256)", true, None, None, None),
+                    ("testFuzzSetupOrder(uint256)", true, None, None, None),
+                    (
+                        "testFuzzAfterEachFails(uint256)",
+                        false,
+                        Some("afterEach() failed: assertion failed".to_string()),
+                        None,
+                        None,
+                    ),
+                ],
+            )]),
+        );
+    }
+
     #[test]
     fn test_trace() {
         let mut runner = tracing_runner();
@@ -1308,6 +2195,70 @@ Reason: `setEnv` failed to set an environment variable `{}={}`",
         }
     }
 
+    #[test]
+    fn test_revert_chain() {
+        let mut runner = tracing_runner();
+        let results =
+            runner.test(&Filter::new(".*", "RevertChainTest", ".*trace"), None, true).unwrap();
+
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                format!("trace{}RevertChain.t.sol:RevertChainTest", std::path::MAIN_SEPARATOR)
+                    .as_str(),
+                vec![
+                    (
+                        "testRevertChain()",
+                        false,
+                        Some(
+                            r#"OuterFailure("inner failure"); caused by: inner failure"#
+                                .to_string(),
+                        ),
+                        None,
+                        None,
+                    ),
+                    // the caught revert did not cause the revert
+                    ("testSwallowedRevert()", false, Some("outer failure".to_string()), None, None),
+                ],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_cheats_fork_pinned_block_number() {
+        let mut config = Config::with_root(PROJECT.root());
+        config.rpc_endpoints = rpc_endpoints();
+        config.fork_block_numbers = BTreeMap::from([("rpcAlias".to_string(), 15_000_000)]);
+        let mut runner = base_runner()
+            .with_cheats_config(CheatsConfig::new(&config, &EVM_OPTS))
+            .build(
+                &PROJECT.paths.root,
+                (*COMPILED).clone(),
+                EVM_OPTS.evm_env_blocking(),
+                EVM_OPTS.clone(),
+            )
+            .unwrap();
+
+        let results = runner
+            .test(
+                &Filter::new(
+                    "testLogForkBlockNumber",
+                    "ForkTest",
+                    &format!(".*cheats{}Fork2", RE_PATH_SEPARATOR),
+                ),
+                None,
+                true,
+            )
+            .unwrap();
+        let result = results
+            .values()
+            .flat_map(|suite| suite.test_results.values())
+            .next()
+            .expect("test was run");
+        assert!(result.success, "{:?}", result.reason);
+        assert_eq!(decode_console_logs(&result.logs), vec!["15000000".to_string()]);
+    }
+
     #[test]
     fn test_fork() {
         let rpc_url = foundry_utils::rpc::next_http_archive_rpc_endpoint();
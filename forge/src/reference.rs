@@ -0,0 +1,335 @@
+use crate::executor::{backend::DatabaseExt, Executor, CHEATCODE_ADDRESS};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Address, Bytes, H256, U256, U64},
+};
+use eyre::WrapErr;
+use foundry_evm::revm::SpecId;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::BTreeMap, fmt, fs, path::Path, process::Command};
+
+/// The gas limit of the transaction executed by both EVMs
+const GAS_LIMIT: u64 = 30_000_000;
+
+/// The key of the account that sends the transaction executed by both EVMs, since the reference
+/// EVM can only execute signed transactions
+const SENDER_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// A reference EVM implementation that tests are executed against in addition to revm, to check
+/// that both implementations agree.
+///
+/// The reference EVM is invoked via the `t8n` (state transition) interface, e.g. `evm t8n` of
+/// geth or `evmone-t8n`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceEvm {
+    /// The program and its leading arguments, e.g. `["evm", "t8n"]`
+    command: Vec<String>,
+}
+
+impl ReferenceEvm {
+    /// Creates a reference EVM invoked by the given whitespace separated command, e.g. `evm t8n`
+    pub fn new(command: impl AsRef<str>) -> Self {
+        Self { command: command.as_ref().split_whitespace().map(str::to_string).collect() }
+    }
+
+    /// Executes a call to `to` with the given calldata on top of the state of the executor in both
+    /// revm and the reference EVM, and compares the status, gas used and resulting state.
+    pub fn check(&self, executor: &Executor, to: Address, calldata: Bytes) -> eyre::Result<Check> {
+        if executor.backend().active_fork().is_some() {
+            return Ok(Check::Skipped("forked state can not be exported".to_string()))
+        }
+
+        let wallet: LocalWallet = SENDER_KEY.parse()?;
+        let sender = wallet.address();
+
+        let mut executor = executor.clone();
+        executor
+            .set_balance(sender, U256::from(u128::MAX))
+            .set_gas_limit(GAS_LIMIT.into())
+            .set_tracing(true);
+
+        let pre = executor.backend().local_accounts();
+        let call = executor.call_raw(sender, to, calldata.0.clone(), 0.into())?;
+
+        // the reference EVM can only execute calls that don't use any cheatcodes
+        if call
+            .traces
+            .iter()
+            .flat_map(|traces| traces.arena.iter())
+            .any(|node| node.trace.address == CHEATCODE_ADDRESS)
+        {
+            return Ok(Check::Skipped("cheatcodes are not supported".to_string()))
+        }
+        let changeset = call.state_changeset.unwrap_or_default();
+
+        let env = executor.env();
+        let nonce = pre.get(&sender).map(|(info, _)| info.nonce).unwrap_or_default();
+        let dir = tempfile::tempdir()?;
+        let alloc = pre
+            .iter()
+            .map(|(address, (info, storage))| {
+                let account = T8nAccount {
+                    balance: info.balance,
+                    nonce: Some(info.nonce.into()),
+                    code: info.code.clone().map(Bytes::from),
+                    storage: storage
+                        .iter()
+                        .map(|(slot, value)| (u256_to_h256(*slot), *value))
+                        .collect(),
+                };
+                (*address, account)
+            })
+            .collect::<BTreeMap<_, _>>();
+        fs::write(dir.path().join("alloc.json"), serde_json::to_string(&alloc)?)?;
+        fs::write(
+            dir.path().join("env.json"),
+            json!({
+                "currentCoinbase": env.block.coinbase,
+                "currentDifficulty": env.block.difficulty,
+                "currentGasLimit": U256::from(GAS_LIMIT),
+                "currentNumber": env.block.number,
+                "currentTimestamp": env.block.timestamp,
+                "currentBaseFee": U256::zero(),
+            })
+            .to_string(),
+        )?;
+        fs::write(
+            dir.path().join("txs.json"),
+            json!([{
+                "gas": U256::from(GAS_LIMIT),
+                "gasPrice": U256::zero(),
+                "nonce": U256::from(nonce),
+                "to": to,
+                "value": U256::zero(),
+                "input": calldata,
+                "v": U256::zero(),
+                "r": U256::zero(),
+                "s": U256::zero(),
+                "secretKey": format!("0x{SENDER_KEY}"),
+            }])
+            .to_string(),
+        )?;
+
+        let (result, post) = self.run(dir.path(), env.cfg.spec_id, env.cfg.chain_id)?;
+
+        let mut divergences = Vec::new();
+        match result.receipts.first() {
+            Some(receipt) => {
+                let success = receipt.status == U64::one();
+                if success == call.reverted {
+                    divergences.push(format!(
+                        "status: revm {}, reference {}",
+                        status(!call.reverted),
+                        status(success)
+                    ));
+                }
+                if receipt.gas_used.as_u64() != call.gas {
+                    divergences.push(format!(
+                        "gas used: revm {}, reference {}",
+                        call.gas, receipt.gas_used
+                    ));
+                }
+            }
+            None => {
+                let error = result.rejected.first().map(|tx| tx.error.as_str()).unwrap_or_default();
+                eyre::bail!("Reference EVM rejected the transaction: {}", error)
+            }
+        }
+
+        let empty = T8nAccount::default();
+        for (address, account) in changeset {
+            let reference = post.get(&address).unwrap_or(&empty);
+            // the fees are charged by the reference EVM only
+            if address != sender &&
+                address != env.block.coinbase &&
+                account.info.balance != reference.balance
+            {
+                divergences.push(format!(
+                    "balance of {:?}: revm {}, reference {}",
+                    address, account.info.balance, reference.balance
+                ));
+            }
+            let reference_nonce = reference.nonce.unwrap_or_default().as_u64();
+            if address != sender && account.info.nonce != reference_nonce {
+                divergences.push(format!(
+                    "nonce of {:?}: revm {}, reference {}",
+                    address, account.info.nonce, reference_nonce
+                ));
+            }
+            for (slot, value) in account.storage {
+                let reference =
+                    reference.storage.get(&u256_to_h256(slot)).copied().unwrap_or_default();
+                if value != reference {
+                    divergences.push(format!(
+                        "slot {:#x} of {:?}: revm {:#x}, reference {:#x}",
+                        slot, address, value, reference
+                    ));
+                }
+            }
+        }
+
+        Ok(if divergences.is_empty() { Check::Matched } else { Check::Diverged(divergences) })
+    }
+
+    /// Runs the reference EVM on the inputs in `dir`, returning its result and post state
+    fn run(
+        &self,
+        dir: &Path,
+        spec: SpecId,
+        chain_id: U256,
+    ) -> eyre::Result<(T8nResult, BTreeMap<Address, T8nAccount>)> {
+        let (program, args) =
+            self.command.split_first().ok_or_else(|| eyre::eyre!("No reference EVM command"))?;
+        let output = Command::new(program)
+            .args(args)
+            .arg("--input.alloc")
+            .arg(dir.join("alloc.json"))
+            .arg("--input.env")
+            .arg(dir.join("env.json"))
+            .arg("--input.txs")
+            .arg(dir.join("txs.json"))
+            .arg("--output.basedir")
+            .arg(dir)
+            .args(["--output.result", "result.json", "--output.alloc", "post.json"])
+            .args(["--state.fork", fork_name(spec)?])
+            .args(["--state.chainid", &chain_id.to_string()])
+            .output()
+            .wrap_err_with(|| format!("Failed to execute `{}`", self.command.join(" ")))?;
+        if !output.status.success() {
+            eyre::bail!(
+                "Reference EVM failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+
+        let result = serde_json::from_str(&fs::read_to_string(dir.join("result.json"))?)?;
+        let post = serde_json::from_str(&fs::read_to_string(dir.join("post.json"))?)?;
+        Ok((result, post))
+    }
+}
+
+/// The outcome of checking a test against the [ReferenceEvm]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Check {
+    /// Both EVMs agree
+    Matched,
+    /// The EVMs disagree, with a description of every difference
+    Diverged(Vec<String>),
+    /// The test can not be executed by the reference EVM
+    Skipped(String),
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Matched => write!(f, "matched the reference EVM"),
+            Check::Diverged(divergences) => {
+                write!(f, "diverged from the reference EVM:")?;
+                for divergence in divergences {
+                    write!(f, "\n  {}", divergence)?;
+                }
+                Ok(())
+            }
+            Check::Skipped(reason) => write!(f, "skipped the reference EVM: {}", reason),
+        }
+    }
+}
+
+/// An account in the `t8n` alloc format
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct T8nAccount {
+    #[serde(default)]
+    balance: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<U64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    code: Option<Bytes>,
+    #[serde(default)]
+    storage: BTreeMap<H256, U256>,
+}
+
+/// The result written by `t8n`
+#[derive(Debug, Deserialize)]
+struct T8nResult {
+    #[serde(default)]
+    receipts: Vec<T8nReceipt>,
+    #[serde(default)]
+    rejected: Vec<T8nRejected>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct T8nReceipt {
+    status: U64,
+    gas_used: U64,
+}
+
+#[derive(Debug, Deserialize)]
+struct T8nRejected {
+    error: String,
+}
+
+/// Returns the name `t8n` uses for the given spec
+fn fork_name(spec: SpecId) -> eyre::Result<&'static str> {
+    Ok(match spec {
+        SpecId::FRONTIER => "Frontier",
+        SpecId::HOMESTEAD => "Homestead",
+        SpecId::TANGERINE => "EIP150",
+        SpecId::SPURIOUS_DRAGON => "EIP158",
+        SpecId::BYZANTIUM => "Byzantium",
+        SpecId::CONSTANTINOPLE => "Constantinople",
+        SpecId::PETERSBURG => "ConstantinopleFix",
+        SpecId::ISTANBUL => "Istanbul",
+        SpecId::BERLIN => "Berlin",
+        SpecId::LONDON => "London",
+        SpecId::MERGE => "Merge",
+        spec => eyre::bail!("The reference EVM does not support {:?}", spec),
+    })
+}
+
+fn u256_to_h256(value: U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    H256::from(bytes)
+}
+
+fn status(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "revert"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_t8n_output() {
+        let result: T8nResult = serde_json::from_str(
+            r#"{"stateRoot":"0x00","receipts":[{"status":"0x1","gasUsed":"0x5208"}],"rejected":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(result.receipts[0].status, U64::one());
+        assert_eq!(result.receipts[0].gas_used, U64::from(21000));
+
+        let post: BTreeMap<Address, T8nAccount> = serde_json::from_str(
+            r#"{"0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266":{"balance":"0x10","nonce":"0x1","storage":{"0x0000000000000000000000000000000000000000000000000000000000000001":"0x02"}}}"#,
+        )
+        .unwrap();
+        let account = &post[&"0xf39fd6e51aad88f6f4ce6ab8827279cfffb92266".parse().unwrap()];
+        assert_eq!(account.balance, U256::from(16));
+        assert_eq!(account.storage[&u256_to_h256(1.into())], U256::from(2));
+    }
+
+    #[test]
+    fn can_split_command() {
+        assert_eq!(
+            ReferenceEvm::new(" evm  t8n ").command,
+            vec!["evm".to_string(), "t8n".to_string()]
+        );
+    }
+}
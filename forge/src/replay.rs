@@ -0,0 +1,406 @@
+use ethers::{
+    prelude::Middleware,
+    types::{Address, Bytes, Log, Transaction, H256, U256},
+    utils::keccak256,
+};
+use eyre::WrapErr;
+use foundry_evm::{
+    executor::{DatabaseRef, EvmError, Executor, StateChangeset},
+    revm::{db::DatabaseCommit, BlockEnv},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// Replays the transactions of a block on top of the state of its parent block with the code of
+/// some contracts replaced, e.g. with a patched implementation behind a proxy, and compares the
+/// results with the canonical ones.
+///
+/// The status, gas and logs of every transaction are compared with its on-chain receipt. Since
+/// the state after every transaction is not available on-chain, the state is compared with a
+/// replay of the block without any overrides instead.
+#[derive(Debug, Clone)]
+pub struct BlockReplay {
+    /// The executor, forked at the parent block of the replayed block
+    executor: Executor,
+    /// The runtime code to use for contracts
+    overrides: BTreeMap<Address, Bytes>,
+}
+
+impl BlockReplay {
+    /// Creates a new replay on top of the state of the given executor, which is expected to fork
+    /// the parent block of the replayed block
+    pub fn new(executor: Executor) -> Self {
+        Self { executor, overrides: Default::default() }
+    }
+
+    /// Replaces the runtime code of the contract at `address`
+    #[must_use]
+    pub fn with_override(mut self, address: Address, code: Bytes) -> Self {
+        self.overrides.insert(address, code);
+        self
+    }
+
+    /// Replays all transactions of the given block and compares them with the canonical results
+    pub async fn replay<M: Middleware>(
+        self,
+        provider: &M,
+        number: u64,
+    ) -> eyre::Result<ReplayReport>
+    where
+        M::Error: 'static,
+    {
+        let block = provider
+            .get_block_with_txs(number)
+            .await
+            .wrap_err_with(|| format!("Failed to get block {number}"))?
+            .ok_or_else(|| eyre::eyre!("Block {} not found", number))?;
+        let block_env = BlockEnv {
+            number: number.into(),
+            timestamp: block.timestamp,
+            coinbase: block.author.unwrap_or_default(),
+            difficulty: block.difficulty,
+            basefee: block.base_fee_per_gas.unwrap_or_default(),
+            gas_limit: block.gas_limit,
+        };
+
+        // the transactions pay their fees like on-chain, so `BASEFEE`, `GASPRICE` and the balances
+        // of the senders and the coinbase match
+        let mut baseline = self.executor.clone();
+        baseline.set_block_env(block_env.clone()).set_charge_fees(true);
+        let mut patched = self.executor;
+        patched.set_block_env(block_env).set_charge_fees(true);
+        for (address, code) in self.overrides {
+            let mut info = patched.backend_mut().basic(address);
+            info.code_hash = H256::from_slice(&keccak256(&code));
+            info.code = Some(code.0);
+            patched.backend_mut().insert_account_info(address, info);
+        }
+
+        let mut transactions = Vec::with_capacity(block.transactions.len());
+        for tx in block.transactions {
+            let receipt = provider
+                .get_transaction_receipt(tx.hash)
+                .await
+                .wrap_err_with(|| format!("Failed to get receipt of {:?}", tx.hash))?
+                .ok_or_else(|| eyre::eyre!("Receipt of {:?} not found", tx.hash))?;
+            let canonical = TxOutcome {
+                success: receipt.status.map(|status| status.as_u64() == 1).unwrap_or_default(),
+                gas_used: receipt.gas_used.unwrap_or_default().as_u64(),
+                logs: receipt.logs.iter().map(LogEntry::from).collect(),
+            };
+
+            let (_, expected_state) = execute(&mut baseline, &tx)?;
+            let (replayed, actual_state) = execute(&mut patched, &tx)?;
+            let state_diffs = diff_state(&baseline, &expected_state, &patched, &actual_state);
+
+            transactions.push(TxReplay { hash: tx.hash, canonical, replayed, state_diffs });
+        }
+
+        Ok(ReplayReport { block: number, transactions })
+    }
+}
+
+/// Executes the transaction with its effective gas price and commits its changes, returning its
+/// outcome and changeset.
+///
+/// The changeset of contract creations is not available, so it is always empty.
+fn execute(executor: &mut Executor, tx: &Transaction) -> eyre::Result<(TxOutcome, StateChangeset)> {
+    let base_fee = executor.env().block.basefee;
+    executor.set_gas_limit(tx.gas).set_gas_price(effective_gas_price(tx, base_fee));
+    match tx.to {
+        Some(to) => {
+            let call = executor.call_raw(tx.from, to, tx.input.0.clone(), tx.value)?;
+            let changeset = call.state_changeset.unwrap_or_default();
+            executor.backend_mut().commit(changeset.clone());
+            let outcome = TxOutcome {
+                success: !call.reverted,
+                gas_used: call.gas,
+                logs: call.logs.iter().map(LogEntry::from).collect(),
+            };
+            Ok((outcome, changeset))
+        }
+        None => {
+            let outcome = match executor.deploy(tx.from, tx.input.0.clone(), tx.value, None) {
+                Ok(deployment) => TxOutcome {
+                    success: true,
+                    gas_used: deployment.gas,
+                    logs: deployment.logs.iter().map(LogEntry::from).collect(),
+                },
+                Err(EvmError::Execution { gas, logs, .. }) => TxOutcome {
+                    success: false,
+                    gas_used: gas,
+                    logs: logs.iter().map(LogEntry::from).collect(),
+                },
+                Err(err) => return Err(err.into()),
+            };
+            Ok((outcome, Default::default()))
+        }
+    }
+}
+
+/// Returns the gas price the transaction paid in a block with the given base fee
+fn effective_gas_price(tx: &Transaction, base_fee: U256) -> U256 {
+    match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) {
+        (Some(max_fee), Some(priority_fee)) => max_fee.min(base_fee + priority_fee),
+        _ => tx.gas_price.unwrap_or_default(),
+    }
+}
+
+/// Compares the balances and storage slots touched by either changeset after they were committed
+/// to the respective executors
+fn diff_state(
+    expected: &Executor,
+    expected_changes: &StateChangeset,
+    actual: &Executor,
+    actual_changes: &StateChangeset,
+) -> Vec<StateDiff> {
+    let mut touched: BTreeMap<Address, BTreeSet<U256>> = BTreeMap::new();
+    for (address, account) in expected_changes.iter().chain(actual_changes.iter()) {
+        touched.entry(*address).or_default().extend(account.storage.keys().copied());
+    }
+
+    let mut diffs = Vec::new();
+    for (address, slots) in touched {
+        let expected_balance = expected.backend().basic(address).balance;
+        let actual_balance = actual.backend().basic(address).balance;
+        if expected_balance != actual_balance {
+            diffs.push(StateDiff::Balance {
+                address,
+                expected: expected_balance,
+                actual: actual_balance,
+            });
+        }
+        for slot in slots {
+            let expected = expected.backend().storage(address, slot);
+            let actual = actual.backend().storage(address, slot);
+            if expected != actual {
+                diffs.push(StateDiff::Storage { address, slot, expected, actual });
+            }
+        }
+    }
+    diffs
+}
+
+/// A log emitted by a transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+}
+
+impl From<&Log> for LogEntry {
+    fn from(log: &Log) -> Self {
+        Self { address: log.address, topics: log.topics.clone(), data: log.data.clone() }
+    }
+}
+
+/// The outcome of executing a transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TxOutcome {
+    /// Whether the transaction succeeded
+    pub success: bool,
+    /// The gas used by the transaction
+    pub gas_used: u64,
+    /// The logs emitted by the transaction
+    pub logs: Vec<LogEntry>,
+}
+
+/// A difference in the state after a transaction compared to the replay without overrides
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateDiff {
+    Balance { address: Address, expected: U256, actual: U256 },
+    Storage { address: Address, slot: U256, expected: U256, actual: U256 },
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateDiff::Balance { address, expected, actual } => {
+                write!(f, "balance of {:?}: expected {}, got {}", address, expected, actual)
+            }
+            StateDiff::Storage { address, slot, expected, actual } => write!(
+                f,
+                "slot {:#x} of {:?}: expected {:#x}, got {:#x}",
+                slot, address, expected, actual
+            ),
+        }
+    }
+}
+
+/// The replay of a single transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxReplay {
+    /// The hash of the transaction
+    pub hash: H256,
+    /// The on-chain outcome of the transaction
+    pub canonical: TxOutcome,
+    /// The outcome of the transaction with the overrides applied
+    pub replayed: TxOutcome,
+    /// The differences in state after the transaction
+    pub state_diffs: Vec<StateDiff>,
+}
+
+impl TxReplay {
+    /// Returns a description of every difference to the canonical results
+    pub fn differences(&self) -> Vec<String> {
+        let mut differences = Vec::new();
+        if self.canonical.success != self.replayed.success {
+            let status = |success| if success { "success" } else { "revert" };
+            differences.push(format!(
+                "status: expected {}, got {}",
+                status(self.canonical.success),
+                status(self.replayed.success)
+            ));
+        }
+        if self.canonical.gas_used != self.replayed.gas_used {
+            differences.push(format!(
+                "gas: expected {}, got {} ({:+})",
+                self.canonical.gas_used,
+                self.replayed.gas_used,
+                self.replayed.gas_used as i64 - self.canonical.gas_used as i64
+            ));
+        }
+        if self.canonical.logs != self.replayed.logs {
+            differences.push(format!(
+                "logs: expected {} logs, got {} logs{}",
+                self.canonical.logs.len(),
+                self.replayed.logs.len(),
+                self.canonical
+                    .logs
+                    .iter()
+                    .zip(&self.replayed.logs)
+                    .position(|(expected, actual)| expected != actual)
+                    .map(|idx| format!(", first difference at log {}", idx))
+                    .unwrap_or_default()
+            ));
+        }
+        differences.extend(self.state_diffs.iter().map(|diff| diff.to_string()));
+        differences
+    }
+
+    /// Returns `true` if the replay matches the canonical results
+    pub fn matches(&self) -> bool {
+        self.differences().is_empty()
+    }
+}
+
+/// The result of a [BlockReplay]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// The number of the replayed block
+    pub block: u64,
+    /// The replayed transactions, in block order
+    pub transactions: Vec<TxReplay>,
+}
+
+impl ReplayReport {
+    /// Returns `true` if all transactions match the canonical results
+    pub fn matches(&self) -> bool {
+        self.transactions.iter().all(TxReplay::matches)
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for tx in &self.transactions {
+            let differences = tx.differences();
+            if differences.is_empty() {
+                writeln!(f, "[MATCH] {:?}", tx.hash)?;
+            } else {
+                writeln!(f, "[DIFF] {:?}", tx.hash)?;
+                for difference in differences {
+                    writeln!(f, "    {}", difference)?;
+                }
+            }
+        }
+        let mismatched = self.transactions.iter().filter(|tx| !tx.matches()).count();
+        write!(
+            f,
+            "Replayed {} transactions of block {}: {} matched, {} differed",
+            self.transactions.len(),
+            self.block,
+            self.transactions.len() - mismatched,
+            mismatched
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundry_evm::executor::{Backend, ExecutorBuilder, SpecId};
+
+    #[test]
+    fn can_execute_london_transactions() {
+        let base_fee = U256::from(10_000_000_000u64);
+        let coinbase = Address::from_low_u64_be(0xc0ffee);
+        let mut executor =
+            ExecutorBuilder::default().with_spec(SpecId::LONDON).build(Backend::spawn(None));
+        executor.set_block_env(BlockEnv {
+            number: 15_000_000.into(),
+            coinbase,
+            basefee: base_fee,
+            gas_limit: 30_000_000.into(),
+            ..Default::default()
+        });
+        executor.set_charge_fees(true);
+
+        let sender = Address::from_low_u64_be(0x1000);
+        let balance = U256::exp10(18);
+        executor.set_balance(sender, balance);
+
+        let tx = Transaction {
+            from: sender,
+            to: Some(Address::from_low_u64_be(0x2000)),
+            value: 1.into(),
+            gas: 21_000.into(),
+            max_fee_per_gas: Some(base_fee * 2),
+            max_priority_fee_per_gas: Some(1_000_000_000u64.into()),
+            ..Default::default()
+        };
+        let gas_price = effective_gas_price(&tx, base_fee);
+        assert_eq!(gas_price, base_fee + 1_000_000_000u64);
+
+        let (outcome, _) = execute(&mut executor, &tx).unwrap();
+        assert!(outcome.success);
+        assert_eq!(
+            executor.get_balance(sender),
+            balance - tx.value - gas_price * 21_000u64,
+            "the sender pays the effective gas price"
+        );
+        assert_eq!(executor.get_balance(coinbase), U256::from(1_000_000_000u64 * 21_000));
+    }
+
+    #[test]
+    fn can_report_differences() {
+        let log = LogEntry { address: Address::zero(), topics: vec![], data: Bytes::default() };
+        let canonical = TxOutcome { success: true, gas_used: 50_000, logs: vec![log.clone()] };
+        let mut tx = TxReplay {
+            hash: H256::zero(),
+            canonical: canonical.clone(),
+            replayed: canonical,
+            state_diffs: vec![],
+        };
+        assert!(tx.matches());
+
+        tx.replayed.gas_used = 45_000;
+        tx.replayed.logs.clear();
+        tx.state_diffs.push(StateDiff::Storage {
+            address: Address::zero(),
+            slot: 1.into(),
+            expected: 2.into(),
+            actual: 3.into(),
+        });
+        assert_eq!(
+            tx.differences(),
+            vec![
+                "gas: expected 50000, got 45000 (-5000)".to_string(),
+                "logs: expected 1 logs, got 0 logs".to_string(),
+                format!("slot 0x1 of {:?}: expected 0x2, got 0x3", Address::zero()),
+            ]
+        );
+    }
+}
@@ -1,14 +1,16 @@
 //! test outcomes
 
-use crate::Address;
-use ethers::prelude::Log;
+use crate::{state_diff::StateDiff, Address};
+use ethers::{prelude::Log, types::H256};
+use foundry_config::Config;
 use foundry_evm::{
     coverage::HitMaps,
-    fuzz::{CounterExample, FuzzedCases},
+    executor::{genesis::GenesisAlloc, inspector::GasDeltas},
+    fuzz::{CounterExample, FuzzStats, FuzzedCases},
     trace::{CallTraceArena, TraceKind},
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fmt, time::Duration};
+use std::{collections::BTreeMap, fmt, path::PathBuf, time::Duration};
 
 /// Results and duration for a set of tests included in the same test contract
 #[derive(Clone, Serialize)]
@@ -19,6 +21,9 @@ pub struct SuiteResult {
     pub test_results: BTreeMap<String, TestResult>,
     // Warnings
     pub warnings: Vec<String>,
+    /// Describes the code the results were produced by
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_info: Option<BuildInfo>,
 }
 
 impl SuiteResult {
@@ -27,7 +32,7 @@ impl SuiteResult {
         test_results: BTreeMap<String, TestResult>,
         warnings: Vec<String>,
     ) -> Self {
-        Self { duration, test_results, warnings }
+        Self { duration, test_results, warnings, build_info: None }
     }
 
     pub fn is_empty(&self) -> bool {
@@ -37,6 +42,63 @@ impl SuiteResult {
     pub fn len(&self) -> usize {
         self.test_results.len()
     }
+
+    /// Adds the results of other tests of the same contract, e.g. of the tests that ran on another
+    /// fork
+    pub fn merge(&mut self, other: SuiteResult) {
+        self.duration += other.duration;
+        self.test_results.extend(other.test_results);
+        for warning in other.warnings {
+            if !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
+        }
+    }
+}
+
+/// The provenance of the results of a test suite, i.e. exactly what code produced them
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// The settings the contracts were compiled with, if known
+    pub settings: Option<CompilerSettings>,
+    /// The test contract, followed by the libraries it links against
+    pub artifacts: Vec<ArtifactInfo>,
+}
+
+/// The compiler settings that apply to all artifacts of a project
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompilerSettings {
+    pub optimizer: bool,
+    pub optimizer_runs: usize,
+    pub via_ir: bool,
+    pub evm_version: String,
+}
+
+impl From<&Config> for CompilerSettings {
+    fn from(config: &Config) -> Self {
+        Self {
+            optimizer: config.optimizer,
+            optimizer_runs: config.optimizer_runs,
+            via_ir: config.via_ir,
+            evm_version: config.evm_version.to_string(),
+        }
+    }
+}
+
+/// Identifies a compiled artifact and the source it was compiled from
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactInfo {
+    /// The identifier of the artifact, e.g. `src/Counter.sol:Counter`
+    pub identifier: String,
+    /// The version of the compiler that produced the artifact
+    pub compiler_version: String,
+    /// The source file, relative to the project root
+    pub source: PathBuf,
+    /// The keccak256 hash of the content of the source file, if it could be read
+    pub source_hash: Option<H256>,
+    /// The keccak256 hash of the content hashes of all sources the source file imports, directly
+    /// or transitively, in the order of their paths, if the imports are known
+    pub imports_hash: Option<H256>,
 }
 
 /// The result of an executed solidity test
@@ -68,8 +130,28 @@ pub struct TestResult {
     #[serde(skip)]
     pub coverage: Option<HitMaps>,
 
+    /// The gas differences under the configured gas schedule, if any
+    #[serde(skip)]
+    pub gas_deltas: Option<GasDeltas>,
+
     /// Labeled addresses
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// The state after a standard test, if state dumping is enabled
+    #[serde(skip)]
+    pub state: Option<GenesisAlloc>,
+
+    /// The changes a standard test made to the state, if state diffs are enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_diff: Option<StateDiff>,
+
+    /// The data collected by the custom inspectors of a standard test, keyed by their names
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extensions: BTreeMap<String, serde_json::Value>,
+
+    /// The distribution of the inputs of a fuzz test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzz_stats: Option<FuzzStats>,
 }
 
 impl TestResult {
@@ -1,28 +1,40 @@
 use crate::{
+    reference::{Check, ReferenceEvm},
     result::{SuiteResult, TestKind, TestResult, TestSetup},
-    TestFilter,
+    state_diff::StateDiff,
+    TestFilter, TestOptions,
 };
 use ethers::{
-    abi::{Abi, Function},
-    types::{Address, Bytes, U256},
+    abi::{Abi, Function, Token},
+    types::{Address, Bytes, Log, U256},
 };
 use eyre::Result;
 use foundry_evm::{
-    executor::{CallResult, DeployResult, EvmError, Executor},
-    fuzz::FuzzedExecutor,
-    trace::TraceKind,
+    executor::{CallResult, DeployResult, EvmError, Executor, StateChangeset},
+    fuzz::{is_fixture_output, FuzzFixtures, FuzzedExecutor, FIXTURE_PREFIX},
+    revm::db::DatabaseCommit,
+    trace::{CallTraceArena, TraceKind},
     CALLER,
 };
 use proptest::test_runner::TestRunner;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::{collections::BTreeMap, time::Instant};
-use tracing::{error, trace};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+use tracing::{error, trace, warn};
 
 /// A type that executes all tests of a contract
 #[derive(Debug, Clone)]
 pub struct ContractRunner<'a> {
     /// The executor used by the runner.
     pub executor: Executor,
+    /// The identifier of the test contract, which its settings in [TestOptions] are looked up by
+    pub name: &'a str,
 
     /// Library contracts to be deployed before the test contract
     pub predeploy_libs: &'a [Bytes],
@@ -37,6 +49,25 @@ pub struct ContractRunner<'a> {
     pub initial_balance: U256,
     /// The address which will be used as the `from` field in all EVM calls
     pub sender: Address,
+    /// The reference EVM standard tests are checked against, if any
+    pub reference_evm: Option<&'a ReferenceEvm>,
+    /// Set when the run was cancelled, in which case tests that did not complete are skipped
+    pub cancelled: Arc<AtomicBool>,
+    /// Whether to cancel the run as soon as a test fails
+    pub fail_fast: bool,
+    /// Whether the calls made by the tests are executed as transactions of their own, unless
+    /// overridden by the `isolate` setting of a test
+    pub isolate: bool,
+    /// Whether to re-run the failing tests with tracing enabled, so their results include traces
+    /// even though the tests run without tracing
+    pub trace_failures: bool,
+    /// Whether to record the state after every standard test in [TestResult::state]
+    pub dump_state: bool,
+    /// Whether to record the changes of every standard test to the state in
+    /// [TestResult::state_diff]
+    pub state_diff: bool,
+    /// The maximum amount of gas a standard test may use
+    pub gas_limit_per_test: Option<u64>,
 }
 
 impl<'a> ContractRunner<'a> {
@@ -52,12 +83,21 @@ impl<'a> ContractRunner<'a> {
     ) -> Self {
         Self {
             executor,
+            name: "",
             contract,
             code,
             initial_balance,
             sender: sender.unwrap_or_default(),
             errors,
             predeploy_libs,
+            reference_evm: None,
+            cancelled: Default::default(),
+            fail_fast: false,
+            isolate: false,
+            trace_failures: false,
+            dump_state: false,
+            state_diff: false,
+            gas_limit_per_test: None,
         }
     }
 }
@@ -169,10 +209,61 @@ impl<'a> ContractRunner<'a> {
     }
 
     /// Runs all tests for a contract whose names match the provided regular expression
+    ///
+    /// If [Self::trace_failures] is set, the failing tests are run again with tracing enabled and
+    /// their results are replaced by the traced results.
     pub fn run_tests(
+        self,
+        filter: &impl TestFilter,
+        fuzzer: Option<TestRunner>,
+        test_options: &TestOptions,
+        include_fuzz_tests: bool,
+    ) -> Result<SuiteResult> {
+        // the failing tests are re-run from the state before `setUp`
+        let tracing_runner = self.trace_failures.then(|| {
+            let mut runner = self.clone();
+            runner.executor.set_tracing(true);
+            runner.trace_failures = false;
+            // failures may have cancelled the run with `--fail-fast`, but are re-run regardless
+            runner.cancelled = Default::default();
+            runner.fail_fast = false;
+            runner
+        });
+
+        let mut result =
+            self.run_suite(filter, fuzzer.clone(), test_options, include_fuzz_tests)?;
+        if let Some(runner) = tracing_runner {
+            let failed = result
+                .test_results
+                .iter()
+                .filter(|(_, result)| !result.success)
+                .map(|(signature, _)| signature.clone())
+                .collect::<BTreeSet<_>>();
+            if !failed.is_empty() {
+                trace!(failed = failed.len(), "re-running failed tests with tracing");
+                let traced = runner.run_suite(
+                    &FailedTestFilter(&failed),
+                    fuzzer,
+                    test_options,
+                    include_fuzz_tests,
+                )?;
+                // a test that passes when it's re-run keeps its original result
+                for (signature, traced) in traced.test_results {
+                    if !traced.success && failed.contains(&signature) {
+                        result.test_results.insert(signature, traced);
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Runs all tests for a contract whose names match the provided regular expression
+    fn run_suite(
         mut self,
         filter: &impl TestFilter,
         fuzzer: Option<TestRunner>,
+        test_options: &TestOptions,
         include_fuzz_tests: bool,
     ) -> Result<SuiteResult> {
         tracing::info!("starting tests");
@@ -196,6 +287,7 @@ impl<'a> ContractRunner<'a> {
 
         // There are multiple setUp function, so we return a single test result for `setUp`
         if setup_fns.len() > 1 {
+            self.cancel_if_fail_fast();
             return Ok(SuiteResult::new(
                 start.elapsed(),
                 [(
@@ -208,7 +300,12 @@ impl<'a> ContractRunner<'a> {
                         kind: TestKind::Standard(0),
                         traces: vec![],
                         coverage: None,
+                        gas_deltas: None,
                         labeled_addresses: BTreeMap::new(),
+                        state: None,
+                        state_diff: None,
+                        extensions: BTreeMap::new(),
+                        fuzz_stats: None,
                     },
                 )]
                 .into(),
@@ -219,6 +316,7 @@ impl<'a> ContractRunner<'a> {
         let setup = self.setup(needs_setup)?;
         if setup.setup_failed {
             // The setup failed, so we return a single test result for `setUp`
+            self.cancel_if_fail_fast();
             return Ok(SuiteResult::new(
                 start.elapsed(),
                 [(
@@ -231,7 +329,12 @@ impl<'a> ContractRunner<'a> {
                         kind: TestKind::Standard(0),
                         traces: setup.traces,
                         coverage: None,
+                        gas_deltas: None,
                         labeled_addresses: setup.labeled_addresses,
+                        state: None,
+                        state_diff: None,
+                        extensions: BTreeMap::new(),
+                        fuzz_stats: None,
                     },
                 )]
                 .into(),
@@ -255,15 +358,72 @@ impl<'a> ContractRunner<'a> {
         let test_results = tests
             .par_iter()
             .filter_map(|(func, should_fail)| {
+                if self.cancelled.load(Ordering::Relaxed) {
+                    return None
+                }
+
+                // Run the test specific setup function on top of the `setUp` state, if any. Fuzz
+                // tests run them before every case instead
+                let mut setup = setup.clone();
+                let mut test_runner =
+                    if func.inputs.is_empty() { self.setup_test(func, &mut setup) } else { None };
+                let config = test_options.test_config(self.name, &func.name);
+                if config.isolate.unwrap_or(self.isolate) {
+                    // `setUp` and the test specific setup functions are not isolated
+                    test_runner.get_or_insert_with(|| self.clone()).executor.set_isolation(true);
+                }
+                let runner = test_runner.as_ref().unwrap_or(&self);
+                if setup.setup_failed {
+                    self.cancel_if_fail_fast();
+                    return Some(Ok((
+                        func.signature(),
+                        TestResult {
+                            success: false,
+                            reason: setup.reason,
+                            counterexample: None,
+                            logs: setup.logs,
+                            kind: TestKind::Standard(0),
+                            traces: setup.traces,
+                            coverage: None,
+                            gas_deltas: None,
+                            labeled_addresses: setup.labeled_addresses,
+                            state: None,
+                            state_diff: None,
+                            extensions: BTreeMap::new(),
+                            fuzz_stats: None,
+                        },
+                    )))
+                }
+
                 let result = if func.inputs.is_empty() {
-                    Some(self.clone().run_test(func, *should_fail, setup.clone()))
+                    Some(runner.run_test(func, *should_fail, setup))
                 } else {
                     fuzzer.as_ref().map(|fuzzer| {
-                        self.run_fuzz_test(func, *should_fail, fuzzer.clone(), setup.clone())
+                        runner.run_fuzz_test(
+                            func,
+                            *should_fail,
+                            config.fuzzer(fuzzer),
+                            test_options,
+                            setup,
+                        )
                     })
                 };
 
-                result.map(|result| Ok((func.signature(), result?)))
+                let result = result?;
+                if !matches!(&result, Ok(result) if result.success) {
+                    self.cancel_if_fail_fast();
+                }
+                match result {
+                    // A passing fuzz test that was cancelled did not run all of its cases
+                    Ok(result)
+                        if result.success &&
+                            matches!(result.kind, TestKind::Fuzz(_)) &&
+                            self.cancelled.load(Ordering::Relaxed) =>
+                    {
+                        None
+                    }
+                    result => Some(result.map(|result| (func.signature(), result))),
+                }
             })
             .collect::<Result<BTreeMap<_, _>>>()?;
 
@@ -280,15 +440,152 @@ impl<'a> ContractRunner<'a> {
         Ok(SuiteResult::new(duration, test_results, warnings))
     }
 
+    /// Returns the function of the test contract with the given name that takes no arguments, if
+    /// any
+    fn hook(&self, name: &str) -> Option<&'a Function> {
+        self.contract.functions().find(|func| func.name == name && func.inputs.is_empty())
+    }
+
+    /// Cancels the run if it should stop at the first failure
+    fn cancel_if_fail_fast(&self) {
+        if self.fail_fast {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the `beforeEach()` hook and the test specific setup function of the given test, e.g.
+    /// `setUp_testWithdraw()` for `testWithdraw()`, in the order they are run
+    fn setup_fns(&self, func: &Function) -> Vec<&'a Function> {
+        [self.hook("beforeEach"), self.hook(&format!("setUp_{}", func.name))]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Runs the `beforeEach()` hook and the optional test specific setup function of the given
+    /// test, e.g. `setUp_testWithdraw()` for `testWithdraw()`, on top of the state after `setUp()`.
+    ///
+    /// Returns `None` if there are no such functions, otherwise a new runner whose state includes
+    /// the changes made by the setup functions. The logs, traces and labels of the calls are added
+    /// to the `setup`.
+    fn setup_test(&self, func: &Function, setup: &mut TestSetup) -> Option<Self> {
+        let setup_fns = self.setup_fns(func);
+        if setup_fns.is_empty() {
+            return None
+        }
+
+        let mut runner = self.clone();
+        for setup_fn in setup_fns {
+            trace!(test = %func.name, setup = %setup_fn.name, "running test specific setup");
+            match runner.executor.call_committing::<(), _, _>(
+                CALLER,
+                setup.address,
+                setup_fn.clone(),
+                (),
+                0.into(),
+                self.errors,
+            ) {
+                Ok(CallResult { traces, labels, logs, .. }) => {
+                    setup.logs.extend(logs);
+                    setup.traces.extend(traces.map(|traces| (TraceKind::Setup, traces)));
+                    setup.labeled_addresses.extend(labels);
+                }
+                Err(EvmError::Execution { traces, labels, logs, reason, .. }) => {
+                    error!(reason=?reason, test=%func.name, "test specific setup failed");
+                    setup.logs.extend(logs);
+                    setup.traces.extend(traces.map(|traces| (TraceKind::Setup, traces)));
+                    setup.labeled_addresses.extend(labels);
+                    setup.setup_failed = true;
+                    setup.reason = Some(format!("Setup failed: {reason}"));
+                    break
+                }
+                Err(err) => {
+                    error!(reason=?err, test=%func.name, "test specific setup failed");
+                    setup.setup_failed = true;
+                    setup.reason = Some(format!("Setup failed: {}", &err.to_string()));
+                    break
+                }
+            }
+        }
+
+        Some(runner)
+    }
+
+    /// Runs the `afterEach()` hook, if any, on top of the state after the test.
+    ///
+    /// The logs, traces and labels of the call are added to the given collections. Returns the
+    /// reason if the hook reverted or a DSTest assertion in the hook failed.
+    fn after_each(
+        &self,
+        address: Address,
+        state_changeset: StateChangeset,
+        logs: &mut Vec<Log>,
+        traces: &mut Vec<(TraceKind, CallTraceArena)>,
+        labeled_addresses: &mut BTreeMap<Address, String>,
+    ) -> Option<String> {
+        let after_each = self.hook("afterEach")?;
+
+        let mut executor = self.executor.clone();
+        executor.backend_mut().commit(state_changeset);
+
+        let (reverted, reason, state_changeset) = match executor.call_committing::<(), _, _>(
+            CALLER,
+            address,
+            after_each.clone(),
+            (),
+            0.into(),
+            self.errors,
+        ) {
+            Ok(CallResult {
+                traces: call_traces,
+                labels,
+                logs: call_logs,
+                state_changeset,
+                ..
+            }) => {
+                logs.extend(call_logs);
+                traces.extend(call_traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
+                labeled_addresses.extend(labels);
+                (false, None, state_changeset)
+            }
+            Err(EvmError::Execution {
+                traces: call_traces,
+                labels,
+                logs: call_logs,
+                reason,
+                state_changeset,
+                ..
+            }) => {
+                logs.extend(call_logs);
+                traces.extend(call_traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
+                labeled_addresses.extend(labels);
+                (true, Some(reason), state_changeset)
+            }
+            Err(err) => return Some(format!("afterEach() failed: {err}")),
+        };
+
+        let success =
+            executor.is_success(address, reverted, state_changeset.unwrap_or_default(), false);
+        if success {
+            None
+        } else {
+            Some(format!(
+                "afterEach() failed: {}",
+                reason.unwrap_or_else(|| "assertion failed".to_string())
+            ))
+        }
+    }
+
     /// Runs a single test
     ///
     /// Calls the given functions and returns the `TestResult`.
     ///
     /// State modifications are not committed to the evm database but discarded after the call,
-    /// similar to `eth_call`.
+    /// similar to `eth_call`. Since the test is executed against a copy-on-write view of the
+    /// state after `setUp()`, all tests of a contract share the same setup state.
     #[tracing::instrument(name = "test", skip_all, fields(name = %func.signature(), %should_fail))]
     pub fn run_test(
-        mut self,
+        &self,
         func: &Function,
         should_fail: bool,
         setup: TestSetup,
@@ -297,7 +594,9 @@ impl<'a> ContractRunner<'a> {
 
         // Run unit test
         let start = Instant::now();
-        let (reverted, reason, gas, stipend, execution_traces, coverage, state_changeset) =
+        let mut gas_deltas = None;
+        let mut extensions = BTreeMap::new();
+        let (reverted, mut reason, gas, stipend, execution_traces, coverage, state_changeset) =
             match self.executor.execute_test::<(), _, _>(
                 self.sender,
                 address,
@@ -313,12 +612,16 @@ impl<'a> ContractRunner<'a> {
                     logs: execution_logs,
                     traces: execution_trace,
                     coverage,
+                    gas_deltas: execution_gas_deltas,
                     labels: new_labels,
                     state_changeset,
+                    extensions: execution_extensions,
                     ..
                 }) => {
                     labeled_addresses.extend(new_labels);
                     logs.extend(execution_logs);
+                    gas_deltas = execution_gas_deltas;
+                    extensions = execution_extensions;
                     (reverted, None, gas, stipend, execution_trace, coverage, state_changeset)
                 }
                 Err(EvmError::Execution {
@@ -343,12 +646,58 @@ impl<'a> ContractRunner<'a> {
             };
         traces.extend(execution_traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
 
-        let success = self.executor.is_success(
+        let state_changeset = state_changeset.expect("we should have a state changeset");
+        let mut success =
+            self.executor.is_success(setup.address, reverted, state_changeset.clone(), should_fail);
+
+        let gas_used = gas.overflowing_sub(stipend).0;
+        if let Some(limit) = self.gas_limit_per_test.filter(|limit| success && gas_used > *limit) {
+            success = false;
+            reason = Some(format!("Gas used {gas_used} exceeds the limit of {limit} per test"));
+        }
+
+        // Execute the test in the reference EVM as well, on top of the state after `setUp`
+        if let Some(reference_evm) = self.reference_evm {
+            match reference_evm.check(&self.executor, address, func.encode_input(&[])?.into()) {
+                Ok(Check::Diverged(divergences)) => {
+                    success = false;
+                    reason = Some(format!(
+                        "Diverged from the reference EVM: {}",
+                        divergences.join(", ")
+                    ));
+                }
+                Ok(Check::Skipped(skip_reason)) => {
+                    warn!(test = %func.name, reason = %skip_reason, "skipped reference EVM");
+                }
+                Ok(Check::Matched) => {}
+                Err(err) => {
+                    success = false;
+                    reason = Some(format!("Failed to run the reference EVM: {err}"));
+                }
+            }
+        }
+
+        // Record the state after the test, on top of the state after `setUp`
+        let state = self.dump_state.then(|| {
+            let mut executor = self.executor.clone();
+            executor.backend_mut().commit(state_changeset.clone());
+            executor.dump_allocs()
+        });
+
+        let state_diff =
+            self.state_diff.then(|| StateDiff::new(self.executor.backend(), &state_changeset));
+
+        // Run the `afterEach` hook on top of the state after the test
+        if let Some(after_each_reason) = self.after_each(
             setup.address,
-            reverted,
-            state_changeset.expect("we should have a state changeset"),
-            should_fail,
-        );
+            state_changeset,
+            &mut logs,
+            &mut traces,
+            &mut labeled_addresses,
+        ) {
+            success = false;
+            reason = Some(after_each_reason);
+        }
 
         // Record test execution time
         tracing::debug!(
@@ -362,33 +711,73 @@ impl<'a> ContractRunner<'a> {
             reason,
             counterexample: None,
             logs,
-            kind: TestKind::Standard(gas.overflowing_sub(stipend).0),
+            kind: TestKind::Standard(gas_used),
             traces,
             coverage,
+            gas_deltas,
             labeled_addresses,
+            state,
+            state_diff,
+            extensions,
+            fuzz_stats: None,
         })
     }
 
+    /// Collects the values returned by the `fixture*()` functions of the test contract deployed at
+    /// `address`, see [FuzzFixtures]
+    fn fuzz_fixtures(&self, address: Address) -> FuzzFixtures {
+        let mut fixtures = FuzzFixtures::default();
+        let fixture_fns = self.contract.functions().filter(|func| {
+            func.name.starts_with(FIXTURE_PREFIX) &&
+                func.inputs.is_empty() &&
+                is_fixture_output(&func.outputs)
+        });
+        for func in fixture_fns {
+            let values = self
+                .executor
+                .call_raw(self.sender, address, func.short_signature().to_vec().into(), 0.into())
+                .ok()
+                .filter(|call| !call.reverted)
+                .and_then(|call| func.decode_output(&call.result).ok())
+                .and_then(|mut tokens| tokens.pop());
+            match values {
+                Some(Token::Array(values) | Token::FixedArray(values)) => {
+                    fixtures.insert(&func.name, values);
+                }
+                _ => warn!(name = ?func.name, "failed to collect fuzz fixtures"),
+            }
+        }
+        fixtures
+    }
+
     #[tracing::instrument(name = "fuzz-test", skip_all, fields(name = %func.signature(), %should_fail))]
     pub fn run_fuzz_test(
         &self,
         func: &Function,
         should_fail: bool,
         runner: TestRunner,
+        test_options: &TestOptions,
         setup: TestSetup,
     ) -> Result<TestResult> {
         let TestSetup { address, mut logs, mut traces, mut labeled_addresses, .. } = setup;
 
         // Run fuzz test
         let start = Instant::now();
-        let mut result = FuzzedExecutor::new(&self.executor, runner, self.sender).fuzz(
-            func,
-            address,
-            should_fail,
-            self.errors,
-        );
+        let mut result = FuzzedExecutor::new(&self.executor, runner, self.sender)
+            .with_dictionary(test_options.fuzz_dictionary())
+            .with_fixtures(self.fuzz_fixtures(address))
+            .with_keep_logs(test_options.keep_logs)
+            .with_cancellation(self.cancelled.clone())
+            .with_hooks(
+                self.setup_fns(func).into_iter().cloned().collect(),
+                self.hook("afterEach").cloned(),
+            )
+            .fuzz(func, address, should_fail, self.errors);
 
         // Record logs, labels and traces
+        for mut case_logs in result.case_logs.drain(..) {
+            logs.append(&mut case_logs);
+        }
         logs.append(&mut result.logs);
         labeled_addresses.append(&mut result.labeled_addresses);
         traces.extend(result.traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
@@ -408,7 +797,29 @@ impl<'a> ContractRunner<'a> {
             traces,
             // TODO: Maybe support coverage for fuzz tests
             coverage: None,
+            gas_deltas: None,
             labeled_addresses,
+            state: None,
+            state_diff: None,
+            extensions: BTreeMap::new(),
+            fuzz_stats: Some(result.stats),
         })
     }
 }
+
+/// A filter that only matches the tests that failed, given by their signatures
+struct FailedTestFilter<'a>(&'a BTreeSet<String>);
+
+impl TestFilter for FailedTestFilter<'_> {
+    fn matches_test(&self, test_name: impl AsRef<str>) -> bool {
+        self.0.contains(test_name.as_ref())
+    }
+
+    fn matches_contract(&self, _: impl AsRef<str>) -> bool {
+        true
+    }
+
+    fn matches_path(&self, _: impl AsRef<str>) -> bool {
+        true
+    }
+}
@@ -0,0 +1,222 @@
+use ethers::types::{Address, Bytes, U256};
+use foundry_evm::{
+    executor::{DatabaseRef, StateChangeset, CHEATCODE_ADDRESS},
+    revm::{AccountInfo, Filth, KECCAK_EMPTY},
+    storage_layout::StorageVariable,
+};
+use foundry_utils::diff_score;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+};
+
+/// A value before and after a test
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change<T> {
+    pub from: T,
+    pub to: T,
+}
+
+impl<T: PartialEq> Change<T> {
+    /// Returns the change, or `None` if the value did not change
+    fn new(from: T, to: T) -> Option<Self> {
+        (from != to).then(|| Self { from, to })
+    }
+}
+
+/// The changes a test made to a single account
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDiff {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<Change<U256>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<Change<u64>>,
+    /// The code deployed to the account, if it had no code before the test
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployed_code: Option<Bytes>,
+    /// Whether the account self-destructed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub destroyed: bool,
+    /// The changed storage slots
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<U256, Change<U256>>,
+    /// The code of the account, used to identify its contract
+    #[serde(skip)]
+    pub code: Option<Bytes>,
+}
+
+impl AccountDiff {
+    /// Returns `true` if the account did not change
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_none() &&
+            self.nonce.is_none() &&
+            self.deployed_code.is_none() &&
+            !self.destroyed &&
+            self.storage.is_empty()
+    }
+}
+
+/// The changes a test made to the state, by account
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StateDiff(pub BTreeMap<Address, AccountDiff>);
+
+impl StateDiff {
+    /// Computes the changes of the `changeset` of a test to the state in `db` it was executed on
+    pub fn new<DB: DatabaseRef>(db: &DB, changeset: &StateChangeset) -> Self {
+        let code = |info: &AccountInfo| {
+            info.code.clone().filter(|code| !code.is_empty()).or_else(|| {
+                (!info.code_hash.is_zero() && info.code_hash != KECCAK_EMPTY)
+                    .then(|| db.code_by_hash(info.code_hash))
+            })
+        };
+
+        let mut diff = BTreeMap::new();
+        for (address, account) in changeset {
+            if *address == CHEATCODE_ADDRESS {
+                continue
+            }
+
+            let before = db.basic(*address);
+            let code_before = code(&before);
+            let destroyed = matches!(account.filth, Filth::Destroyed);
+            let after = if destroyed { AccountInfo::default() } else { account.info.clone() };
+            let code_after = code(&after).or_else(|| code_before.clone());
+
+            let storage = account
+                .storage
+                .iter()
+                .filter_map(|(slot, value)| {
+                    let old = if account.filth.abandon_old_storage() {
+                        U256::zero()
+                    } else {
+                        DatabaseRef::storage(db, *address, *slot)
+                    };
+                    Some((*slot, Change::new(old, *value)?))
+                })
+                .collect();
+
+            let account_diff = AccountDiff {
+                balance: Change::new(before.balance, after.balance),
+                nonce: Change::new(before.nonce, after.nonce),
+                deployed_code: if code_before.is_none() && !destroyed {
+                    code_after.clone().map(Into::into)
+                } else {
+                    None
+                },
+                destroyed: destroyed && (code_before.is_some() || !before.balance.is_zero()),
+                storage,
+                code: code_after.or(code_before).map(Into::into),
+            };
+            if !account_diff.is_empty() {
+                diff.insert(*address, account_diff);
+            }
+        }
+        Self(diff)
+    }
+
+    /// Returns `true` if the test did not change the state
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Renders the changes, naming accounts by their labels and the contracts of the
+    /// `layouts`, and storage slots by the variables of the storage layouts of the contracts
+    pub fn render(&self, labels: &BTreeMap<Address, String>, layouts: &StorageLayouts) -> String {
+        let mut out = String::new();
+        self.write(&mut out, labels, layouts).expect("writing to a string");
+        out
+    }
+
+    fn write(
+        &self,
+        out: &mut String,
+        labels: &BTreeMap<Address, String>,
+        layouts: &StorageLayouts,
+    ) -> fmt::Result {
+        for (address, account) in &self.0 {
+            let contract = account.code.as_ref().and_then(|code| layouts.find(code));
+            let name = labels
+                .get(address)
+                .map(String::as_str)
+                .or_else(|| contract.map(|(name, _)| name.as_str()));
+            if let Some(name) = name {
+                writeln!(out, "{:?} ({})", address, name)?;
+            } else {
+                writeln!(out, "{:?}", address)?;
+            }
+
+            if let Some(Change { from, to }) = &account.balance {
+                writeln!(out, "  balance: {} → {}", from, to)?;
+            }
+            if let Some(Change { from, to }) = &account.nonce {
+                writeln!(out, "  nonce: {} → {}", from, to)?;
+            }
+            if let Some(code) = &account.deployed_code {
+                writeln!(out, "  code: deployed {} bytes", code.len())?;
+            }
+            if account.destroyed {
+                writeln!(out, "  destroyed")?;
+            }
+            for (slot, Change { from, to }) in &account.storage {
+                let variables = contract.map(|(_, variables)| variables_at(variables, *slot));
+                if let Some(variables) = variables.filter(|variables| !variables.is_empty()) {
+                    writeln!(
+                        out,
+                        "  storage {:#x} ({}): {:#x} → {:#x}",
+                        slot, variables, from, to
+                    )?;
+                } else {
+                    writeln!(out, "  storage {:#x}: {:#x} → {:#x}", slot, from, to)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the names of the variables stored in `slot`, separated by commas
+fn variables_at(variables: &[StorageVariable], slot: U256) -> String {
+    variables
+        .iter()
+        .filter(|var| {
+            // static arrays and structs span multiple slots
+            let slots = (var.bytes + var.offset + 31) / 32;
+            var.slot <= slot && slot < var.slot + slots.max(U256::one())
+        })
+        .map(|var| var.label.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The storage layouts of the local contracts, by their deployed code
+#[derive(Debug, Clone, Default)]
+pub struct StorageLayouts {
+    layouts: BTreeMap<Vec<u8>, (String, Vec<StorageVariable>)>,
+}
+
+impl StorageLayouts {
+    /// Creates the layouts from `(deployed code, contract name, variables)` entries
+    pub fn new(layouts: impl IntoIterator<Item = (Vec<u8>, String, Vec<StorageVariable>)>) -> Self {
+        Self {
+            layouts: layouts
+                .into_iter()
+                .map(|(code, name, variables)| (code, (name, variables)))
+                .collect(),
+        }
+    }
+
+    /// Returns the name and variables of the contract with the given deployed code, or of the
+    /// most similar one, since immutables and linked libraries change the deployed code
+    pub fn find(&self, code: &[u8]) -> Option<&(String, Vec<StorageVariable>)> {
+        self.layouts.get(code).or_else(|| {
+            self.layouts
+                .iter()
+                .map(|(known_code, contract)| (diff_score(known_code, code), contract))
+                .filter(|(score, _)| *score < 0.1)
+                .min_by(|(a, _), (b, _)| a.total_cmp(b))
+                .map(|(_, contract)| contract)
+        })
+    }
+}
@@ -71,6 +71,7 @@ pub static EVM_OPTS: Lazy<EvmOpts> = Lazy::new(|| EvmOpts {
     sender: Address::from_str("00a329c0648769a73afac7f9381e08fb43dbea72").unwrap(),
     initial_balance: U256::MAX,
     ffi: true,
+    allow_wait: true,
     memory_limit: 2u64.pow(24),
     ..Default::default()
 });
@@ -0,0 +1,530 @@
+//! Upgrade-safety checks of implementation contracts behind proxies
+//!
+//! The checks read the ASTs of an implementation and its base contracts, and compare its storage
+//! layout with the layout of the implementation it replaces. Findings can be allowed with the
+//! annotations of the OpenZeppelin upgrades plugins, e.g.
+//!
+//! ```solidity
+//! /// @custom:oz-upgrades-unsafe-allow constructor state-variable-immutable
+//! constructor(address token) { TOKEN = token; }
+//! ```
+//!
+//! An annotation of a contract allows the findings of all of its members.
+use crate::storage_layout::{diff_storage_layouts, StorageLayoutChange};
+use ethers::solc::artifacts::{ast::Ast, StorageLayout};
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, fmt, path::Path};
+
+/// The natspec tag that allows findings, followed by the kinds of the allowed findings
+pub const UNSAFE_ALLOW_TAG: &str = "@custom:oz-upgrades-unsafe-allow";
+
+/// The modifiers that guard initializers from being called more than once
+const INITIALIZER_MODIFIERS: [&str; 3] = ["initializer", "reinitializer", "onlyInitializing"];
+
+/// The severity of a finding, only errors fail the check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// The kind of a finding, named like the kinds of the `@custom:oz-upgrades-unsafe-allow`
+/// annotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FindingKind {
+    /// The storage layout is incompatible with the layout of the replaced implementation
+    StorageLayout,
+    /// A constructor with code, which never runs in the context of the proxy
+    Constructor,
+    /// A state variable with an initial value, which is assigned in the constructor
+    StateVariableAssignment,
+    /// An immutable variable, which is shared by all proxies of the implementation
+    StateVariableImmutable,
+    /// A `selfdestruct`, which can destroy the implementation of all proxies
+    Selfdestruct,
+    /// A `delegatecall`, which can run a `selfdestruct` in the context of the implementation
+    Delegatecall,
+    /// A public initializer without an initializer modifier, which can be called again
+    MissingInitializerGuard,
+    /// The constructor does not disable the initializers of the implementation itself
+    InitializersNotDisabled,
+}
+
+impl fmt::Display for FindingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            FindingKind::StorageLayout => "storage-layout",
+            FindingKind::Constructor => "constructor",
+            FindingKind::StateVariableAssignment => "state-variable-assignment",
+            FindingKind::StateVariableImmutable => "state-variable-immutable",
+            FindingKind::Selfdestruct => "selfdestruct",
+            FindingKind::Delegatecall => "delegatecall",
+            FindingKind::MissingInitializerGuard => "missing-initializer-guard",
+            FindingKind::InitializersNotDisabled => "initializers-not-disabled",
+        };
+        f.write_str(kind)
+    }
+}
+
+/// A single issue of an implementation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub kind: FindingKind,
+    pub message: String,
+}
+
+impl Finding {
+    fn error(kind: FindingKind, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, kind, message: message.into() }
+    }
+
+    fn warning(kind: FindingKind, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, kind, message: message.into() }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.kind)
+    }
+}
+
+/// The result of checking an implementation, which is printed as JSON with `--json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UpgradeCheckReport {
+    /// The checked implementation
+    pub contract: String,
+    /// The implementation it replaces, if its storage layout was compared
+    pub reference: Option<String>,
+    pub findings: Vec<Finding>,
+    /// Whether there are no errors
+    pub passed: bool,
+}
+
+impl UpgradeCheckReport {
+    pub fn new(contract: String, reference: Option<String>, findings: Vec<Finding>) -> Self {
+        let passed = findings.iter().all(|finding| finding.severity != Severity::Error);
+        Self { contract, reference, findings, passed }
+    }
+
+    /// Returns the number of errors
+    pub fn errors(&self) -> usize {
+        self.findings.iter().filter(|finding| finding.severity == Severity::Error).count()
+    }
+}
+
+/// Returns the storage layout changes of a new implementation as findings: added variables are
+/// safe, removed variables are warnings and all other changes, e.g. renamed variables, are errors
+pub fn check_storage_layout(
+    old: &StorageLayout,
+    new: &StorageLayout,
+) -> eyre::Result<Vec<Finding>> {
+    Ok(diff_storage_layouts(old, new)?
+        .into_iter()
+        .filter_map(|change| match change {
+            StorageLayoutChange::Added { .. } => None,
+            StorageLayoutChange::Removed { .. } => {
+                Some(Finding::warning(FindingKind::StorageLayout, change.to_string()))
+            }
+            change => Some(Finding::error(FindingKind::StorageLayout, change.to_string())),
+        })
+        .collect())
+}
+
+/// The contract definitions of the ASTs of a compilation, along with the paths of their sources,
+/// by their ids
+#[derive(Debug, Clone, Default)]
+pub struct ContractDefinitions {
+    contracts: BTreeMap<u64, (String, Value)>,
+}
+
+impl ContractDefinitions {
+    pub fn new<'a>(asts: impl IntoIterator<Item = &'a Ast>) -> eyre::Result<Self> {
+        let mut contracts = BTreeMap::new();
+        for ast in asts {
+            for node in &ast.nodes {
+                let node = serde_json::to_value(node)?;
+                if node["nodeType"] == "ContractDefinition" {
+                    if let Some(id) = node["id"].as_u64() {
+                        contracts.insert(id, (ast.absolute_path.clone(), node));
+                    }
+                }
+            }
+        }
+        Ok(Self { contracts })
+    }
+
+    /// Returns the contract with the given name, declared in the source at `path` if given
+    fn find(&self, name: &str, path: Option<&str>) -> Option<&Value> {
+        self.contracts
+            .values()
+            .find(|(source, contract)| {
+                contract["name"] == name &&
+                    path.map_or(true, |path| {
+                        Path::new(path).ends_with(source) || Path::new(source).ends_with(path)
+                    })
+            })
+            .map(|(_, contract)| contract)
+    }
+
+    /// Checks the contract with the given name, and the path of its source if given, along with
+    /// its base contracts
+    pub fn check(&self, name: &str, path: Option<&str>) -> eyre::Result<Vec<Finding>> {
+        let contract = self
+            .find(name, path)
+            .ok_or_else(|| eyre::eyre!("Could not find the contract `{}` in the ASTs", name))?;
+        if contract["contractKind"] != "contract" {
+            eyre::bail!("`{}` is not a contract", name)
+        }
+
+        // the contract itself is the first of its linearized base contracts
+        let bases = contract["linearizedBaseContracts"]
+            .as_array()
+            .map(|bases| bases.iter().filter_map(Value::as_u64).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let mut checker = Checker::default();
+        for (_, base) in bases.iter().filter_map(|id| self.contracts.get(id)).rev() {
+            checker.check_contract(base);
+        }
+        if checker.has_initializers && !checker.disables_initializers {
+            checker.findings.push(Finding::warning(
+                FindingKind::InitializersNotDisabled,
+                format!(
+                    "`{name}` does not call `_disableInitializers()` in its constructor, so its \
+                     implementation can be initialized by anyone"
+                ),
+            ));
+        }
+        Ok(checker.findings)
+    }
+}
+
+/// Collects the findings of the contracts of a linearization
+#[derive(Debug, Default)]
+struct Checker {
+    findings: Vec<Finding>,
+    /// Whether any contract has an initializer
+    has_initializers: bool,
+    /// Whether any constructor calls `_disableInitializers()`
+    disables_initializers: bool,
+}
+
+impl Checker {
+    fn check_contract(&mut self, contract: &Value) {
+        let name = contract["name"].as_str().unwrap_or_default();
+        let contract_allowed = allowed_kinds(contract);
+        let nodes = contract["nodes"].as_array().map(Vec::as_slice).unwrap_or_default();
+        for node in nodes {
+            let mut allowed = allowed_kinds(node);
+            allowed.extend(contract_allowed.iter().cloned());
+            let mut report = |finding: Finding| {
+                if !allowed.contains(&finding.kind.to_string()) {
+                    self.findings.push(finding);
+                }
+            };
+
+            match node["nodeType"].as_str() {
+                Some("VariableDeclaration") => {
+                    let variable = node["name"].as_str().unwrap_or_default();
+                    match node["mutability"].as_str() {
+                        Some("immutable") => report(Finding::error(
+                            FindingKind::StateVariableImmutable,
+                            format!("`{name}.{variable}` is immutable, so all proxies share it"),
+                        )),
+                        Some("constant") => {}
+                        _ if !node["value"].is_null() => report(Finding::error(
+                            FindingKind::StateVariableAssignment,
+                            format!(
+                                "`{name}.{variable}` has an initial value, which is only \
+                                 assigned in the storage of the implementation"
+                            ),
+                        )),
+                        _ => {}
+                    }
+                }
+                Some("FunctionDefinition") => {
+                    let function = node["name"].as_str().unwrap_or_default();
+                    let kind = node["kind"].as_str().unwrap_or_default();
+                    let mut calls = Vec::new();
+                    collect_calls(&node["body"], &mut calls);
+
+                    if kind == "constructor" {
+                        let disables = calls.contains(&"_disableInitializers");
+                        self.disables_initializers |= disables;
+                        let statements = node["body"]["statements"].as_array().map_or(0, Vec::len);
+                        if statements > usize::from(disables) {
+                            report(Finding::error(
+                                FindingKind::Constructor,
+                                format!(
+                                    "`{name}` has a constructor, which never runs in the \
+                                     context of the proxy"
+                                ),
+                            ));
+                        }
+                    }
+
+                    let location = if function.is_empty() {
+                        format!("the {kind} of `{name}`")
+                    } else {
+                        format!("`{name}.{function}`")
+                    };
+                    if calls.iter().any(|call| *call == "selfdestruct" || *call == "suicide") {
+                        report(Finding::error(
+                            FindingKind::Selfdestruct,
+                            format!("{location} can selfdestruct the implementation"),
+                        ));
+                    }
+                    if calls.contains(&"delegatecall") {
+                        report(Finding::error(
+                            FindingKind::Delegatecall,
+                            format!(
+                                "{location} makes a delegatecall, which could selfdestruct the \
+                                 implementation"
+                            ),
+                        ));
+                    }
+
+                    let guarded = node["modifiers"].as_array().map_or(false, |modifiers| {
+                        modifiers.iter().any(|modifier| {
+                            modifier["modifierName"]["name"]
+                                .as_str()
+                                .map_or(false, |name| INITIALIZER_MODIFIERS.contains(&name))
+                        })
+                    });
+                    let public = matches!(node["visibility"].as_str(), Some("public" | "external"));
+                    let initializer = kind == "function" && function.starts_with("initialize");
+                    self.has_initializers |= guarded || (public && initializer);
+                    if public && initializer && !guarded {
+                        report(Finding::error(
+                            FindingKind::MissingInitializerGuard,
+                            format!(
+                                "`{name}.{function}` has no `initializer` modifier, so it can \
+                                 be called again"
+                            ),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns the kinds of findings allowed by the natspec comment of the node
+fn allowed_kinds(node: &Value) -> Vec<String> {
+    // solc < 0.6.3 emits the text of the comment instead of a `StructuredDocumentation` node
+    let docs = match &node["documentation"] {
+        Value::String(text) => text.as_str(),
+        docs => docs["text"].as_str().unwrap_or_default(),
+    };
+    docs.lines()
+        .filter_map(|line| line.split_once(UNSAFE_ALLOW_TAG))
+        .flat_map(|(_, kinds)| kinds.split_whitespace().map(str::to_string))
+        .collect()
+}
+
+/// Collects the names of the functions called in a Solidity or Yul AST, including the members
+/// of the called member accesses, e.g. `delegatecall` of `target.delegatecall(data)`
+fn collect_calls<'a>(node: &'a Value, calls: &mut Vec<&'a str>) {
+    match node {
+        Value::Object(object) => {
+            let called = match object.get("nodeType").and_then(Value::as_str) {
+                Some("FunctionCall") => match object["expression"]["nodeType"].as_str() {
+                    Some("Identifier") => object["expression"]["name"].as_str(),
+                    Some("MemberAccess") => object["expression"]["memberName"].as_str(),
+                    // calls with options, e.g. `target.delegatecall{gas: 1000}(data)`
+                    Some("FunctionCallOptions") => {
+                        object["expression"]["expression"]["memberName"].as_str()
+                    }
+                    _ => None,
+                },
+                Some("YulFunctionCall") => object["functionName"]["name"].as_str(),
+                _ => None,
+            };
+            calls.extend(called);
+            object.values().for_each(|value| collect_calls(value, calls));
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_calls(value, calls)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(expression: Value) -> Value {
+        json!({
+            "nodeType": "ExpressionStatement",
+            "expression": { "nodeType": "FunctionCall", "expression": expression, "arguments": [] }
+        })
+    }
+
+    fn function(
+        id: u64,
+        name: &str,
+        kind: &str,
+        modifiers: &[&str],
+        statements: Vec<Value>,
+    ) -> Value {
+        json!({
+            "id": id,
+            "nodeType": "FunctionDefinition",
+            "src": "0:0:0",
+            "name": name,
+            "kind": kind,
+            "visibility": if kind == "constructor" { "public" } else { "external" },
+            "modifiers": modifiers
+                .iter()
+                .map(|name| json!({ "nodeType": "ModifierInvocation", "modifierName": { "nodeType": "IdentifierPath", "name": name } }))
+                .collect::<Vec<_>>(),
+            "body": { "id": id + 100, "nodeType": "Block", "src": "0:0:0", "statements": statements }
+        })
+    }
+
+    fn contract(id: u64, name: &str, bases: &[u64], docs: &str, nodes: Vec<Value>) -> Value {
+        json!({
+            "id": id,
+            "nodeType": "ContractDefinition",
+            "src": "0:0:0",
+            "name": name,
+            "contractKind": "contract",
+            "linearizedBaseContracts": bases,
+            "documentation": docs,
+            "nodes": nodes
+        })
+    }
+
+    fn ast(contracts: Vec<Value>) -> Ast {
+        serde_json::from_value(json!({
+            "absolutePath": "/project/src/Vault.sol",
+            "id": 0,
+            "exportedSymbols": {},
+            "nodeType": "SourceUnit",
+            "src": "0:0:0",
+            "nodes": contracts
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn can_check_implementations() {
+        let ast = ast(vec![
+            contract(
+                1,
+                "Base",
+                &[1],
+                "",
+                vec![function(
+                    10,
+                    "",
+                    "constructor",
+                    &[],
+                    vec![call(json!({ "nodeType": "Identifier", "name": "_disableInitializers" }))],
+                )],
+            ),
+            contract(
+                2,
+                "Vault",
+                &[2, 1],
+                " @custom:oz-upgrades-unsafe-allow delegatecall",
+                vec![
+                    json!({ "id": 20, "nodeType": "VariableDeclaration", "src": "0:0:0", "name": "fee", "mutability": "mutable", "value": { "nodeType": "Literal", "value": "1" } }),
+                    json!({ "id": 21, "nodeType": "VariableDeclaration", "src": "0:0:0", "name": "TOKEN", "mutability": "immutable", "value": null }),
+                    json!({ "id": 22, "nodeType": "VariableDeclaration", "src": "0:0:0", "name": "MAX", "mutability": "constant", "value": { "nodeType": "Literal", "value": "1" } }),
+                    function(23, "initialize", "function", &[], vec![]),
+                    function(24, "initializeV2", "function", &["reinitializer"], vec![]),
+                    function(
+                        25,
+                        "execute",
+                        "function",
+                        &[],
+                        vec![call(
+                            json!({ "nodeType": "MemberAccess", "memberName": "delegatecall", "expression": { "nodeType": "Identifier", "name": "target" } }),
+                        )],
+                    ),
+                    function(
+                        26,
+                        "destroy",
+                        "function",
+                        &[],
+                        vec![call(json!({ "nodeType": "Identifier", "name": "selfdestruct" }))],
+                    ),
+                ],
+            ),
+        ]);
+
+        let contracts = ContractDefinitions::new([&ast]).unwrap();
+        let findings = contracts.check("Vault", Some("src/Vault.sol")).unwrap();
+        let kinds = findings.iter().map(|finding| finding.kind).collect::<Vec<_>>();
+        // the constructor of the base only disables the initializers, the delegatecall is allowed
+        assert_eq!(
+            kinds,
+            vec![
+                FindingKind::StateVariableAssignment,
+                FindingKind::StateVariableImmutable,
+                FindingKind::MissingInitializerGuard,
+                FindingKind::Selfdestruct,
+            ]
+        );
+        assert_eq!(
+            findings[2].to_string(),
+            "`Vault.initialize` has no `initializer` modifier, so it can be called again (missing-initializer-guard)"
+        );
+        assert!(!UpgradeCheckReport::new("Vault".to_string(), None, findings).passed);
+
+        // without disabling the initializers in a constructor
+        let findings = contracts.check("Base", None).unwrap();
+        assert!(findings.is_empty());
+        let ast = ast_without_constructor();
+        let contracts = ContractDefinitions::new([&ast]).unwrap();
+        let findings = contracts.check("Vault", None).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, FindingKind::InitializersNotDisabled);
+        assert_eq!(findings[0].severity, Severity::Warning);
+        assert!(UpgradeCheckReport::new("Vault".to_string(), None, findings).passed);
+
+        assert!(contracts.check("Missing", None).is_err());
+    }
+
+    fn ast_without_constructor() -> Ast {
+        ast(vec![contract(
+            1,
+            "Vault",
+            &[1],
+            "",
+            vec![function(10, "initialize", "function", &["initializer"], vec![])],
+        )])
+    }
+
+    #[test]
+    fn can_check_storage_layouts() {
+        let layout = |vars: &[(&str, u64)]| -> StorageLayout {
+            let storage = vars
+                .iter()
+                .map(|(label, slot)| {
+                    json!({ "astId": 1, "contract": "src/Vault.sol:Vault", "label": label, "offset": 0, "slot": slot.to_string(), "type": "t_uint256" })
+                })
+                .collect::<Vec<_>>();
+            serde_json::from_value(json!({
+                "storage": storage,
+                "types": { "t_uint256": { "encoding": "inplace", "label": "uint256", "numberOfBytes": "32" } }
+            }))
+            .unwrap()
+        };
+
+        let old = layout(&[("total", 0), ("legacy", 1)]);
+        let findings = check_storage_layout(&old, &layout(&[("total", 0), ("fee", 2)])).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Warning);
+
+        let findings = check_storage_layout(&old, &layout(&[("fee", 0), ("total", 1)])).unwrap();
+        assert!(findings.iter().all(|finding| finding.severity == Severity::Error));
+        assert!(!findings.is_empty());
+    }
+}
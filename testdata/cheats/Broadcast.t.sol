@@ -246,6 +246,25 @@ contract BroadcastMix is DSTest {
     }
 }
 
+contract BroadcastTestKeys is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    // private key of the 1st anvil account
+    uint256 constant KEY_A = 0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80;
+    // private key of the 2nd anvil account
+    uint256 constant KEY_B = 0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d;
+
+    function deployWithKeys() public {
+        cheats.broadcast(KEY_A);
+        Test test = new Test();
+
+        cheats.startBroadcast(KEY_B);
+        test.inc();
+        test.inc();
+        cheats.stopBroadcast();
+    }
+}
+
 contract BroadcastTestSetup is DSTest {
     Cheats constant cheats = Cheats(HEVM_ADDRESS);
 
@@ -4,10 +4,20 @@ pragma solidity >=0.8.0;
 interface Cheats {
     // This allows us to getRecordedLogs()
     struct Log {bytes32[] topics; bytes data;}
+    // This allows us to getRecordedAccesses()
+    struct StorageAccess {address account; address caller; bytes32 slot; bool isWrite; uint64 depth;}
+    // This allows us to lastCallGas()
+    struct Gas {uint64 gasLimit; uint64 gasTotalUsed; uint64 gasMemoryUsed; int64 gasRefunded;}
+    // This allows us to stopAndReturnStateDiff()
+    enum AccountAccessKind {Call, DelegateCall, CallCode, StaticCall, Create, SelfDestruct, Resume, Balance, Extcodesize, Extcodehash, Extcodecopy}
+    struct AccountStorageAccess {address account; bytes32 slot; bool isWrite; bytes32 previousValue; bytes32 newValue; bool reverted;}
+    struct AccountAccess {AccountAccessKind kind; address account; address accessor; bool initialized; uint256 oldBalance; uint256 newBalance; bytes deployedCode; uint256 value; bytes data; bool reverted; AccountStorageAccess[] storageAccesses; uint64 depth;}
     // Set block.timestamp (newTimestamp)
     function warp(uint256) external;
     // Set block.height (newHeight)
     function roll(uint256) external;
+    // Advance block.height by a number of blocks mined an interval of seconds apart, updating block.timestamp and block.difficulty (blocks, interval)
+    function rollBlocks(uint256,uint256) external;
     // Set block.basefee (newBasefee)
     function fee(uint256) external;
     // Set block.coinbase (who)
@@ -18,8 +28,14 @@ interface Cheats {
     function store(address,bytes32,bytes32) external;
     // Signs data, (privateKey, digest) => (v, r, s)
     function sign(uint256,bytes32) external returns (uint8,bytes32,bytes32);
+    // Signs EIP-712 typed data given as JSON, (privateKey, typedData) => (v, r, s)
+    function signTypedData(uint256,string) external returns (uint8,bytes32,bytes32);
     // Gets address for a given private key, (privateKey) => (address)
     function addr(uint256) external returns (address);
+    // Computes the address of a contract deployed through the CREATE2 deployer, (salt, initCodeHash) => (address)
+    function computeCreate2Address(bytes32,bytes32) external returns (address);
+    // Computes the address of a contract deployed with CREATE2 by the deployer, (salt, initCodeHash, deployer) => (address)
+    function computeCreate2Address(bytes32,bytes32,address) external returns (address);
     // Derive a private key from a provided mnenomic string (or mnenomic file path) at the derivation path m/44'/60'/0'/0/{index}
     function deriveKey(string calldata, uint32) external returns (uint256);
     // Derive a private key from a provided mnenomic string (or mnenomic file path) at the derivation path {path}{index}
@@ -54,18 +70,47 @@ interface Cheats {
     function startPrank(address,address) external;
     // Resets subsequent calls' msg.sender to be `address(this)`
     function stopPrank() external;
+    // Starts a prank scope that sets all subsequent calls' msg.sender to be the input address until it is stopped by
+    // `stopPrankScope`. Scopes can be nested within other pranks of the same call frame, returns the id of the scope
+    function startPrankScope(address) external returns (uint256);
+    // Starts a prank scope that also sets the tx.origin to be the second input, returns the id of the scope
+    function startPrankScope(address,address) external returns (uint256);
+    // Stops the prank scope with the given id, after the pranks nested within it
+    function stopPrankScope(uint256) external;
     // Sets an address' balance, (who, newBalance)
     function deal(address, uint256) external;
     // Sets an address' code, (who, newCode)
     function etch(address, bytes calldata) external;
+    // Sets the balances, nonces, code and storage of the accounts of a JSON file, relative to the
+    // project root. Accepts genesis files, plain allocs and states dumped by anvil, (path)
+    function loadAllocs(string calldata) external;
     // Expects an error on next call
     function expectRevert() external;
     function expectRevert(bytes calldata) external;
     function expectRevert(bytes4) external;
+    // Expects a revert whose data starts with the given bytes, e.g. the selector of a custom error
+    // with any arguments, or the selector followed by the first arguments of the error
+    function expectPartialRevert(bytes4) external;
+    function expectPartialRevert(bytes calldata) external;
+    // Expects a revert whose reason string contains the given string
+    function expectRevertSubstring(string calldata) external;
+    // Expects the next call not to revert, a revert fails the test with its decoded reason even if
+    // the revert is caught, e.g. by a low-level call
+    function expectNoRevert() external;
+    // Expects the next call to use at most the given amount of gas (maxGas)
+    function expectGasUsed(uint64) external;
+    // Gets the gas limit, the total gas used, the gas used for memory expansion and the refunded gas of the last call
+    function lastCallGas() external returns (Gas memory);
     // Record all storage reads and writes
     function record() external;
     // Gets all accessed reads and write slot from a recording session, for a given address
     function accesses(address) external returns (bytes32[] memory reads, bytes32[] memory writes);
+    // Gets all storage accesses of a recording session in execution order, with the account, caller, slot, kind and call depth of each access
+    function getRecordedAccesses() external returns (StorageAccess[] memory);
+    // Starts recording all account accesses: calls, creates, selfdestructs, balance and code queries, and storage accesses
+    function startStateDiffRecording() external;
+    // Stops recording account accesses and returns the accesses since startStateDiffRecording in execution order
+    function stopAndReturnStateDiff() external returns (AccountAccess[] memory);
     // Record all the transaction logs
     function recordLogs() external;
     // Gets all the recorded logs
@@ -76,6 +121,9 @@ interface Cheats {
     // Second form also checks supplied address against emitting contract.
     function expectEmit(bool,bool,bool,bool) external;
     function expectEmit(bool,bool,bool,bool,address) external;
+    // Expects the next emitted event not to be emitted by the next call, with the same checks as expectEmit
+    function expectNoEmit(bool,bool,bool,bool) external;
+    function expectNoEmit(bool,bool,bool,bool,address) external;
     // Mocks a call to an address, returning specified data.
     // Calldata can either be strict or a partial match, e.g. if you only
     // pass a Solidity selector to the expected calldata, then the entire Solidity
@@ -84,6 +132,11 @@ interface Cheats {
     // Mocks a call to an address with a specific msg.value, returning specified data.
     // Calldata match takes precedence over msg.value in case of ambiguity.
     function mockCall(address,uint256,bytes calldata,bytes calldata) external;
+    // Mocks the functions of a JSON ABI at an address with the return values of a JSON object of function names or signatures to return values,
+    // other functions revert (where, abi, returns)
+    function mockContract(address,string calldata,string calldata) external;
+    // Reverts on calls to an address that are not mocked with mockCall (where)
+    function strictMock(address) external;
     // Clears all mocked calls
     function clearMockedCalls() external;
     // Expect a call to an address with the specified calldata.
@@ -91,12 +144,29 @@ interface Cheats {
     function expectCall(address,bytes calldata) external;
     // Expect a call to an address with the specified msg.value and calldata
     function expectCall(address,uint256,bytes calldata) external;
+    // Expect a call to an address with the specified calldata an exact number of times
+    function expectCall(address,bytes calldata,uint64) external;
+    // Expect a call to an address with the specified msg.value and calldata an exact number of times
+    function expectCall(address,uint256,bytes calldata,uint64) external;
+    // Expect the calls expected after this cheatcode to be made in the order they were expected
+    function expectOrderedCalls() external;
     // Gets the code from an artifact file. Takes in the relative path to the json file
     function getCode(string calldata) external returns (bytes memory);
+    // Deploys a contract from an artifact file, specified like in getCode, optionally with abi-encoded constructor args
+    function deployCode(string calldata) external returns (address);
+    function deployCode(string calldata, bytes calldata) external returns (address);
     // Labels an address in call traces
     function label(address, string calldata) external;
     // If the condition is false, discard this run's fuzz inputs and generate new ones
     function assume(bool) external;
+    // Returns a random value, derived from the seed of the run, which is reported on failures and can
+    // be set with `--fuzz-seed` to replay the values
+    function randomUint() external returns (uint256);
+    // Returns a random uint256 in the inclusive range [min, max], (min, max) => (value)
+    function randomUint(uint256, uint256) external returns (uint256);
+    function randomAddress() external returns (address);
+    // Returns the given number of random bytes
+    function randomBytes(uint256) external returns (bytes memory);
     // Set nonce for an account
     function setNonce(address,uint64) external;
     // Get nonce for an account
@@ -107,10 +177,14 @@ interface Cheats {
     function broadcast() external;
     // Has the next call (at this call depth only) create a transaction with the address provided as the sender that can later be signed and sent onchain
     function broadcast(address) external;
+    // Has the next call (at this call depth only) create a transaction with the address of the private key provided as the sender that can later be signed and sent onchain
+    function broadcast(uint256) external;
     // Using the address that calls the test contract, has the all subsequent calls (at this call depth only) create transactions that can later be signed and sent onchain
     function startBroadcast() external;
     // Has the all subsequent calls (at this call depth only) create transactions that can later be signed and sent onchain
     function startBroadcast(address) external;
+    // Has the all subsequent calls (at this call depth only) create transactions with the address of the private key provided as the sender that can later be signed and sent onchain
+    function startBroadcast(uint256) external;
     // Stops collecting onchain transactions
     function stopBroadcast() external;
     // Reads the entire content of file to string. Path is relative to the project root. (path) => (data)
@@ -132,6 +206,48 @@ interface Cheats {
     // - The user lacks permissions to remove the file.
     // Path is relative to the project root. (path) => ()
     function removeFile(string calldata) external;
+    // Parses the value at the key of the JSON string, e.g. `.deployments[0].address`, ABI-encoded with
+    // its type inferred from the JSON: 0x prefixed hex strings are addresses, bytes32 or bytes by their
+    // length, integers are uint256, or int256 if negative, and objects are tuples of their values in the
+    // alphabetical order of their keys. The key `.` selects the whole JSON. (json, key) => (abiEncodedData)
+    function parseJson(string calldata, string calldata) external returns (bytes memory);
+    function parseJson(string calldata) external returns (bytes memory);
+    // Parses the value at the key of the JSON string as the given type. Numbers that don't fit into
+    // 64 bits have to be given as decimal or 0x prefixed hex strings. (json, key) => (value)
+    function parseJsonUint(string calldata, string calldata) external returns (uint256);
+    function parseJsonUintArray(string calldata, string calldata) external returns (uint256[] memory);
+    function parseJsonInt(string calldata, string calldata) external returns (int256);
+    function parseJsonIntArray(string calldata, string calldata) external returns (int256[] memory);
+    function parseJsonBool(string calldata, string calldata) external returns (bool);
+    function parseJsonBoolArray(string calldata, string calldata) external returns (bool[] memory);
+    function parseJsonAddress(string calldata, string calldata) external returns (address);
+    function parseJsonAddressArray(string calldata, string calldata) external returns (address[] memory);
+    function parseJsonBytes32(string calldata, string calldata) external returns (bytes32);
+    function parseJsonBytes32Array(string calldata, string calldata) external returns (bytes32[] memory);
+    function parseJsonString(string calldata, string calldata) external returns (string memory);
+    function parseJsonStringArray(string calldata, string calldata) external returns (string[] memory);
+    function parseJsonBytes(string calldata, string calldata) external returns (bytes memory);
+    function parseJsonBytesArray(string calldata, string calldata) external returns (bytes[] memory);
+    // Adds the value at the value key to the JSON object with the object key, and returns the object
+    // serialized. Serialized objects can be nested by serializing them as strings. (objectKey, valueKey, value) => (json)
+    function serializeBool(string calldata, string calldata, bool) external returns (string memory);
+    function serializeBool(string calldata, string calldata, bool[] calldata) external returns (string memory);
+    function serializeUint(string calldata, string calldata, uint256) external returns (string memory);
+    function serializeUint(string calldata, string calldata, uint256[] calldata) external returns (string memory);
+    function serializeInt(string calldata, string calldata, int256) external returns (string memory);
+    function serializeInt(string calldata, string calldata, int256[] calldata) external returns (string memory);
+    function serializeAddress(string calldata, string calldata, address) external returns (string memory);
+    function serializeAddress(string calldata, string calldata, address[] calldata) external returns (string memory);
+    function serializeBytes32(string calldata, string calldata, bytes32) external returns (string memory);
+    function serializeBytes32(string calldata, string calldata, bytes32[] calldata) external returns (string memory);
+    function serializeString(string calldata, string calldata, string calldata) external returns (string memory);
+    function serializeString(string calldata, string calldata, string[] calldata) external returns (string memory);
+    function serializeBytes(string calldata, string calldata, bytes calldata) external returns (string memory);
+    function serializeBytes(string calldata, string calldata, bytes[] calldata) external returns (string memory);
+    // Writes the JSON to a file, or replaces the value at the key of the JSON file with it.
+    // Path is relative to the project root. (json, path) => () and (json, path, valueKey) => ()
+    function writeJson(string calldata, string calldata) external;
+    function writeJson(string calldata, string calldata, string calldata) external;
 
     function toString(address)        external returns(string memory);
     function toString(bytes calldata) external returns(string memory);
@@ -139,6 +255,27 @@ interface Cheats {
     function toString(bool)           external returns(string memory);
     function toString(uint256)        external returns(string memory);
     function toString(int256)         external returns(string memory);
+    // Parses the string as a value of the given type, the inverse of toString. Integers can be decimal
+    // or 0x prefixed hex, bools are case insensitive and bytes are hex encoded.
+    function parseBytes(string calldata)   external returns(bytes memory);
+    function parseAddress(string calldata) external returns(address);
+    function parseUint(string calldata)    external returns(uint256);
+    function parseInt(string calldata)     external returns(int256);
+    function parseBytes32(string calldata) external returns(bytes32);
+    function parseBool(string calldata)    external returns(bool);
+    // Converts the string to upper/lower case
+    function toUppercase(string calldata) external returns(string memory);
+    function toLowercase(string calldata) external returns(string memory);
+    // Removes the leading and trailing whitespace of the string
+    function trim(string calldata) external returns(string memory);
+    // Replaces all occurrences of `from` in the string with `to`, (input, from, to) => (output)
+    function replace(string calldata, string calldata, string calldata) external returns(string memory);
+    // Splits the string by the delimiter, (input, delimiter) => (parts)
+    function split(string calldata, string calldata) external returns(string[] memory);
+    // Returns the index of the first occurrence of the key in the string, or type(uint256).max if there is none
+    function indexOf(string calldata, string calldata) external returns(uint256);
+    // Concatenates the strings
+    function concat(string[] calldata) external returns(string memory);
     // Snapshot the current state of the evm.
     // Returns the id of the snapshot that was created.
     // To revert a snapshot use `revertTo`
@@ -165,8 +302,28 @@ interface Cheats {
     function rollFork(uint256) external;
     // Updates the given fork to given block number
     function rollFork(uint256 forkId, uint256 blockNumber) external;
+    // Fetches the given transaction from the active fork and executes it on the current state
+    function transact(bytes32 txHash) external;
     /// Returns the RPC url for the given alias
     function rpcUrl(string calldata) external returns(string memory);
     /// Returns all rpc urls and their aliases `[alias, url][]`
     function rpcUrls() external returns(string[2][] memory);
+    // Pauses the execution for the given number of milliseconds, requires `allow_wait`
+    function sleep(uint256) external;
+    // Waits until the file at the given path exists, reverts after the timeout in milliseconds, requires `allow_wait`
+    // Path is relative to the project root. (path, timeout) => ()
+    function waitForFile(string calldata, uint256) external;
+    // Waits until the given URL responds successfully to a GET request and returns the response body,
+    // reverts after the timeout in milliseconds, requires `allow_wait`. (url, timeout) => (body)
+    function waitForHttp(string calldata, uint256) external returns(string memory);
+    // Waits until the given URL responds to a GET request with the expected body (ignoring surrounding whitespace),
+    // reverts after the timeout in milliseconds, requires `allow_wait`. (url, expected, timeout) => (body)
+    function waitForHttp(string calldata, string calldata, uint256) external returns(string memory);
+    // Waits until the storage slot of an account has the expected value on the given RPC endpoint or alias,
+    // reverts after the timeout in milliseconds, requires `allow_wait`. (urlOrAlias, account, slot, expected, timeout) => ()
+    function waitForStorage(string calldata, address, bytes32, bytes32, uint256) external;
+    // Reverts if the storage layout of the new artifact is incompatible with the layout of the old artifact,
+    // e.g. because variables were moved, retyped or overwritten. Artifacts are specified like in getCode and
+    // must include the storage layout, see `extra_output`. (oldArtifact, newArtifact) => ()
+    function assertStorageLayoutCompatible(string calldata, string calldata) external;
 }
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract Deployed {
+    uint256 public value;
+
+    constructor(uint256 _value) {
+        value = _value;
+    }
+}
+
+contract ComputeCreate2AddressTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testComputeCreate2Address() public {
+        bytes32 salt = bytes32(uint256(1337));
+        bytes32 initCodeHash = keccak256(abi.encodePacked(type(Deployed).creationCode, uint256(42)));
+
+        address expected = cheats.computeCreate2Address(salt, initCodeHash, address(this));
+        Deployed deployed = new Deployed{salt: salt}(42);
+        assertEq(address(deployed), expected, "expected address did not match");
+    }
+
+    function testComputeCreate2AddressOfDeployer() public {
+        // EIP-1014 example 5
+        assertEq(
+            cheats.computeCreate2Address(
+                bytes32(uint256(0xcafebabe)), keccak256(hex"deadbeef"), address(uint160(0xdeadbeef))
+            ),
+            0x60f3f640a8508fC6a86d45DF051962668E1e8AC7
+        );
+        assertEq(
+            cheats.computeCreate2Address(bytes32(0), keccak256(hex"00")),
+            cheats.computeCreate2Address(bytes32(0), keccak256(hex"00"), 0x4e59b44847b379578588920cA78FbF26c0B4956C)
+        );
+    }
+}
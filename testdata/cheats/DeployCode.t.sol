@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+interface Secret {
+    function secret() external view returns (uint256);
+}
+
+contract DeployCodeTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testDeployCode() public {
+        uint64 nonce = cheats.getNonce(address(this));
+        address deployed = cheats.deployCode("../testdata/fixtures/GetCode/WorkingContract.json");
+
+        assertEq(Secret(deployed).secret(), 42);
+        // the contract is created by the test contract
+        assertEq(cheats.getNonce(address(this)), nonce + 1);
+    }
+
+    function testDeployCodeWithArgs() public {
+        address deployed = cheats.deployCode(
+            "../testdata/fixtures/GetCode/WorkingContract.json",
+            abi.encode(uint256(1))
+        );
+        assertEq(Secret(deployed).secret(), 42);
+    }
+
+    function testFailDeployCodeMissingArtifact() public {
+        cheats.deployCode("../testdata/fixtures/GetCode/MissingContract.json");
+    }
+}
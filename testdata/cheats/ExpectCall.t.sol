@@ -137,4 +137,89 @@ contract ExpectCallTest is DSTest {
         );
         target.pay{value: 3}(100);
     }
+
+    function testExpectCallCount() public {
+        Contract target = new Contract();
+        cheats.expectCall(
+            address(target),
+            abi.encodeWithSelector(target.add.selector, 1, 2),
+            2
+        );
+        target.add(1, 2);
+        target.add(1, 2);
+    }
+
+    function testFailExpectCallCountTooFew() public {
+        Contract target = new Contract();
+        cheats.expectCall(
+            address(target),
+            abi.encodeWithSelector(target.add.selector, 1, 2),
+            2
+        );
+        target.add(1, 2);
+    }
+
+    function testFailExpectCallCountTooMany() public {
+        Contract target = new Contract();
+        cheats.expectCall(
+            address(target),
+            abi.encodeWithSelector(target.add.selector, 1, 2),
+            1
+        );
+        target.add(1, 2);
+        target.add(1, 2);
+    }
+
+    function testExpectNoCall() public {
+        Contract target = new Contract();
+        cheats.expectCall(
+            address(target),
+            abi.encodeWithSelector(target.add.selector),
+            0
+        );
+        target.numberA();
+    }
+
+    function testExpectCallCountWithValue() public {
+        Contract target = new Contract();
+        cheats.expectCall(
+            address(target),
+            1,
+            abi.encodeWithSelector(target.pay.selector),
+            2
+        );
+        target.pay{value: 1}(2);
+        target.pay{value: 1}(3);
+        target.pay{value: 2}(3);
+    }
+
+    function testExpectOrderedCalls() public {
+        Contract target = new Contract();
+        cheats.expectOrderedCalls();
+        cheats.expectCall(
+            address(target),
+            abi.encodeWithSelector(target.numberA.selector)
+        );
+        cheats.expectCall(
+            address(target),
+            abi.encodeWithSelector(target.numberB.selector)
+        );
+        target.numberA();
+        target.numberB();
+    }
+
+    function testFailExpectOrderedCalls() public {
+        Contract target = new Contract();
+        cheats.expectOrderedCalls();
+        cheats.expectCall(
+            address(target),
+            abi.encodeWithSelector(target.numberA.selector)
+        );
+        cheats.expectCall(
+            address(target),
+            abi.encodeWithSelector(target.numberB.selector)
+        );
+        target.numberB();
+        target.numberA();
+    }
 }
@@ -97,6 +97,31 @@ contract ExpectEmitTest is DSTest {
         emit Something(1, 2, 3, 4);
     }
 
+    function testExpectNoEmit() public {
+        cheats.expectNoEmit(true, true, true, true);
+        emit Something(1, 2, 3, 4);
+        emitter.emitEvent(1, 2, 3, 5);
+    }
+
+    function testExpectNoEmitUncheckedData() public {
+        cheats.expectNoEmit(true, true, true, false);
+        emit Something(1, 2, 3, 4);
+        emitter.emitEvent(1, 2, 4, 4);
+    }
+
+    function testFailExpectNoEmit() public {
+        cheats.expectNoEmit(true, true, true, false);
+        emit Something(1, 2, 3, 4);
+        emitter.emitEvent(1, 2, 3, 5);
+    }
+
+    function testFailExpectNoEmitNested() public {
+        Emitter inner = new Emitter();
+        cheats.expectNoEmit(true, true, true, true, address(inner));
+        emit Something(1, 2, 3, 4);
+        emitter.emitNested(inner, 1, 2, 3, 4);
+    }
+
     /// The topics that are not checked are altered to be incorrect
     /// compared to the reference.
     function testExpectEmit(
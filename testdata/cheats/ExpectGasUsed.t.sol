@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract Store {
+    uint256 public value;
+
+    function set(uint256 _value) public {
+        value = _value;
+    }
+}
+
+contract ExpectGasUsedTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+    Store store;
+
+    function setUp() public {
+        store = new Store();
+    }
+
+    function testExpectGasUsed() public {
+        cheats.expectGasUsed(50000);
+        store.set(1);
+    }
+
+    function testExpectGasUsedOnlyAppliesToNextCall() public {
+        cheats.expectGasUsed(50000);
+        store.value();
+        store.set(1);
+    }
+
+    function testExpectGasUsedCreate() public {
+        cheats.expectGasUsed(500000);
+        new Store();
+    }
+
+    function testFailExpectGasUsed() public {
+        // a fresh storage write costs more than 20000 gas
+        cheats.expectGasUsed(20000);
+        store.set(1);
+    }
+
+    function testFailExpectGasUsedCreate() public {
+        cheats.expectGasUsed(1000);
+        new Store();
+    }
+}
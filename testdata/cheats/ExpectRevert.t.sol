@@ -6,6 +6,7 @@ import "./Cheats.sol";
 
 contract Reverter {
     error CustomError();
+    error CustomErrorWithArgs(uint256 code, address account);
 
     function revertWithMessage(string memory message) public pure {
         require(false, message);
@@ -21,6 +22,10 @@ contract Reverter {
         revert CustomError();
     }
 
+    function revertWithCustomErrorArgs(uint256 code, address account) public pure {
+        revert CustomErrorWithArgs(code, account);
+    }
+
     function nestedRevert(Reverter inner, string memory message) public pure {
         inner.revertWithMessage(message);
     }
@@ -107,6 +112,67 @@ contract ExpectRevertTest is DSTest {
         reverter.revertWithoutReason();
     }
 
+    function testExpectPartialRevert() public {
+        Reverter reverter = new Reverter();
+        // the selector only
+        cheats.expectPartialRevert(Reverter.CustomErrorWithArgs.selector);
+        reverter.revertWithCustomErrorArgs(1, address(this));
+
+        // the selector and the first argument
+        cheats.expectPartialRevert(abi.encodeWithSelector(Reverter.CustomErrorWithArgs.selector, 1));
+        reverter.revertWithCustomErrorArgs(1, address(this));
+    }
+
+    function testFailExpectPartialRevertArgsDoNotMatch() public {
+        Reverter reverter = new Reverter();
+        cheats.expectPartialRevert(abi.encodeWithSelector(Reverter.CustomErrorWithArgs.selector, 2));
+        reverter.revertWithCustomErrorArgs(1, address(this));
+    }
+
+    function testFailExpectPartialRevertSelectorDoesNotMatch() public {
+        Reverter reverter = new Reverter();
+        cheats.expectPartialRevert(Reverter.CustomError.selector);
+        reverter.revertWithCustomErrorArgs(1, address(this));
+    }
+
+    function testExpectRevertSubstring() public {
+        Reverter reverter = new Reverter();
+        cheats.expectRevertSubstring("insufficient");
+        reverter.revertWithMessage("ERC20: insufficient balance");
+    }
+
+    function testFailExpectRevertSubstringDoesNotMatch() public {
+        Reverter reverter = new Reverter();
+        cheats.expectRevertSubstring("allowance");
+        reverter.revertWithMessage("ERC20: insufficient balance");
+    }
+
+    function testFailExpectRevertSubstringCustomError() public {
+        Reverter reverter = new Reverter();
+        cheats.expectRevertSubstring("Custom");
+        reverter.revertWithCustomError();
+    }
+
+    function testExpectNoRevert() public {
+        Reverter reverter = new Reverter();
+        cheats.expectNoRevert();
+        reverter.doNotRevert();
+    }
+
+    function testFailExpectNoRevert() public {
+        Reverter reverter = new Reverter();
+        cheats.expectNoRevert();
+        reverter.revertWithMessage("revert");
+    }
+
+    function testFailExpectNoRevertCaught() public {
+        Reverter reverter = new Reverter();
+        cheats.expectNoRevert();
+        (bool success, ) =
+            address(reverter).call(abi.encodeWithSelector(Reverter.revertWithMessage.selector, "revert"));
+        assertTrue(!success);
+    }
+
     function testFailExpectRevertDangling() public {
         cheats.expectRevert("dangling");
     }
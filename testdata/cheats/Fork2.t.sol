@@ -65,6 +65,12 @@ contract ForkTest is DSTest {
         assertEq(anotherFork, cheats.activeFork());
     }
 
+    // logs the block of a fork of an alias, which is pinned if the alias is in `fork_block_numbers`
+    function testLogForkBlockNumber() public {
+        cheats.createSelectFork("rpcAlias");
+        emit log_uint(block.number);
+    }
+
     // ensures forks have different block hashes
     function testBlockNumbersMimatch() public {
         cheats.selectFork(mainnetFork);
@@ -116,4 +122,22 @@ contract ForkTest is DSTest {
         cheats.selectFork(otherMain);
         assertEq(block.number, mainBlock + 1);
     }
+
+    // test that a historical transaction can be replayed on the fork
+    function testCanTransact() public {
+        // the first ether transfer, included in block 46147
+        cheats.createSelectFork("rpcAlias", 46_146);
+        bytes32 txHash = 0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060;
+        address sender = 0xA1E4380A3B1f749673E270229993eE55F35663b4;
+        address recipient = 0x5DF9B87991262F6BA471F09758CDE1c0FC1De734;
+
+        uint256 senderBalance = sender.balance;
+        uint256 recipientBalance = recipient.balance;
+
+        cheats.transact(txHash);
+
+        assertEq(recipient.balance, recipientBalance + 31337);
+        // the sender also paid for gas
+        assertLt(sender.balance, senderBalance - 31337);
+    }
 }
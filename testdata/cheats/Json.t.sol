@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract JsonTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    string json;
+
+    // the fields are in the alphabetical order of the keys
+    struct Deployment {
+        address addr;
+        uint256 number;
+    }
+
+    function setUp() public {
+        json = cheats.readFile("../testdata/fixtures/Json/test.json");
+    }
+
+    function testParseJsonInferred() public {
+        assertEq(abi.decode(cheats.parseJson(json, ".chainId"), (uint256)), 1);
+        assertEq(abi.decode(cheats.parseJson(json, ".delta"), (int256)), -42);
+        assertTrue(abi.decode(cheats.parseJson(json, ".enabled"), (bool)));
+        assertEq(abi.decode(cheats.parseJson(json, ".name"), (string)), "deployment");
+        assertEq(
+            abi.decode(cheats.parseJson(json, ".owner"), (address)),
+            0x00a329c0648769A73afAc7F9381E08FB43dBEA72
+        );
+        assertEq(abi.decode(cheats.parseJson(json, ".salt"), (bytes32)), bytes32(uint256(1)));
+        assertEq0(abi.decode(cheats.parseJson(json, ".code"), (bytes)), hex"6001");
+
+        uint256[] memory values = abi.decode(cheats.parseJson(json, "$.values"), (uint256[]));
+        assertEq(values.length, 3);
+        assertEq(values[2], 3);
+    }
+
+    function testParseJsonObjects() public {
+        Deployment memory deployment =
+            abi.decode(cheats.parseJson(json, ".deployments[1]"), (Deployment));
+        assertEq(deployment.addr, 0x5FbDB2315678afecb367f032d93F642f64180aa3);
+        assertEq(deployment.number, 15000001);
+
+        Deployment[] memory deployments =
+            abi.decode(cheats.parseJson(json, ".deployments"), (Deployment[]));
+        assertEq(deployments.length, 2);
+        assertEq(deployments[0].addr, 0x00a329c0648769A73afAc7F9381E08FB43dBEA72);
+    }
+
+    function testParseJsonTyped() public {
+        assertEq(cheats.parseJsonUint(json, ".supply"), type(uint256).max);
+        assertEq(cheats.parseJsonUint(json, ".deployments[0].block"), 15000000);
+        assertEq(cheats.parseJsonInt(json, ".delta"), -42);
+        assertTrue(cheats.parseJsonBool(json, ".enabled"));
+        assertEq(cheats.parseJsonAddress(json, ".owner"), 0x00a329c0648769A73afAc7F9381E08FB43dBEA72);
+        assertEq(cheats.parseJsonBytes32(json, ".salt"), bytes32(uint256(1)));
+        assertEq(cheats.parseJsonString(json, ".name"), "deployment");
+        assertEq0(cheats.parseJsonBytes(json, ".code"), hex"6001");
+
+        uint256[] memory values = cheats.parseJsonUintArray(json, ".values");
+        assertEq(values.length, 3);
+        assertEq(values[0], 1);
+    }
+
+    function testParseJsonErrors() public {
+        cheats.expectRevert("Key `.missing` not found in JSON");
+        cheats.parseJson(json, ".missing");
+
+        cheats.expectRevert("Cannot parse \"deployment\" as uint256");
+        cheats.parseJsonUint(json, ".name");
+    }
+
+    function testSerializeJson() public {
+        cheats.serializeUint("deployment", "block", 15000000);
+        cheats.serializeBool("deployment", "verified", true);
+        string memory deployment =
+            cheats.serializeAddress("deployment", "address", 0x00a329c0648769A73afAc7F9381E08FB43dBEA72);
+        assertEq(
+            deployment,
+            '{"address":"0x00a329c0648769A73afAc7F9381E08FB43dBEA72","block":15000000,"verified":true}'
+        );
+
+        uint256[] memory values = new uint256[](2);
+        values[0] = 1;
+        values[1] = type(uint256).max;
+        cheats.serializeUint("output", "values", values);
+        string memory output = cheats.serializeString("output", "deployment", deployment);
+        assertEq(
+            output,
+            '{"deployment":{"address":"0x00a329c0648769A73afAc7F9381E08FB43dBEA72","block":15000000,"verified":true},"values":[1,"115792089237316195423570985008687907853269984665640564039457584007913129639935"]}'
+        );
+        assertEq(cheats.parseJsonUint(output, ".deployment.block"), 15000000);
+    }
+
+    function testWriteJson() public {
+        string memory path = "../testdata/fixtures/Json/write_test.json";
+        string memory written = cheats.serializeBytes32("written", "salt", bytes32(uint256(1)));
+        cheats.writeJson(written, path);
+        assertEq(cheats.parseJsonBytes32(cheats.readFile(path), ".salt"), bytes32(uint256(1)));
+
+        cheats.writeJson("0x02", path, ".salt");
+        assertEq(cheats.parseJsonBytes32(cheats.readFile(path), ".salt"), bytes32(uint256(2)) << 248);
+
+        cheats.removeFile(path);
+    }
+}
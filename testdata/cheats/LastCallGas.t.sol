@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract Target {
+    uint256 public value;
+    uint256[] public values;
+
+    function set(uint256 _value) public {
+        value = _value;
+    }
+
+    function push(uint256 count) public {
+        uint256[] memory buffer = new uint256[](count);
+        for (uint256 i; i < count; i++) {
+            values.push(buffer[i]);
+        }
+    }
+}
+
+contract LastCallGasTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+    Target target;
+
+    function setUp() public {
+        target = new Target();
+    }
+
+    function testLastCallGas() public {
+        target.set(1);
+        Cheats.Gas memory gas = cheats.lastCallGas();
+        // a fresh storage write costs more than 20000 gas
+        assertGt(gas.gasTotalUsed, 20000);
+        assertLt(gas.gasTotalUsed, gas.gasLimit);
+        assertEq(gas.gasRefunded, 0);
+    }
+
+    function testLastCallGasRefund() public {
+        target.set(1);
+        target.set(0);
+        Cheats.Gas memory gas = cheats.lastCallGas();
+        // clearing a storage slot is refunded
+        assertGt(gas.gasRefunded, 0);
+    }
+
+    function testLastCallGasMemory() public {
+        target.push(1);
+        uint64 small = cheats.lastCallGas().gasMemoryUsed;
+        target.push(100);
+        uint64 large = cheats.lastCallGas().gasMemoryUsed;
+        assertGt(large, small);
+    }
+
+    function testLastCallGasIsOfLastCall() public {
+        target.set(1);
+        target.value();
+        Cheats.Gas memory gas = cheats.lastCallGas();
+        assertLt(gas.gasTotalUsed, 20000);
+    }
+
+    function testFailLastCallGasWithoutCall() public {
+        cheats.lastCallGas();
+    }
+}
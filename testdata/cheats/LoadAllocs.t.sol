@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract LoadAllocsTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    address constant ALLOCATED = address(0xA110C);
+    address constant FUNDED = address(0xBEEF);
+
+    function testLoadAllocs() public {
+        cheats.loadAllocs("../testdata/fixtures/Allocs/genesis.json");
+
+        assertEq(ALLOCATED.balance, 1 ether);
+        assertEq(FUNDED.balance, 1000);
+        assertEq(cheats.getNonce(ALLOCATED), 5);
+        assertEq(uint256(cheats.load(ALLOCATED, bytes32(uint256(1)))), 1337);
+
+        (bool success, bytes memory data) = ALLOCATED.call("");
+        assertTrue(success);
+        assertEq(abi.decode(data, (uint256)), 42);
+    }
+
+    function testLoadAllocsOverwritesAccount() public {
+        cheats.deal(FUNDED, 1 ether);
+        cheats.loadAllocs("../testdata/fixtures/Allocs/genesis.json");
+        assertEq(FUNDED.balance, 1000);
+    }
+
+    function testLoadAllocsDisallowedPath() public {
+        cheats.expectRevert("Path is not allowed.");
+        cheats.loadAllocs("/etc/hosts");
+    }
+}
@@ -188,4 +188,24 @@ contract MockCallTest is DSTest {
         assertEq(mock.pay{value: 10}(2), 2);
         assertEq(mock.pay(2), 2);
     }
+
+    function testStrictMock() public {
+        Mock mock = new Mock();
+
+        cheats.strictMock(address(mock));
+        cheats.mockCall(
+            address(mock),
+            abi.encodeWithSelector(mock.numberA.selector),
+            abi.encode(10)
+        );
+
+        assertEq(mock.numberA(), 10);
+    }
+
+    function testFailStrictMockUnmockedCall() public {
+        Mock mock = new Mock();
+
+        cheats.strictMock(address(mock));
+        mock.add(1, 2);
+    }
 }
@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+interface IToken {
+    function balanceOf(address) external view returns (uint256);
+    function decimals() external view returns (uint8);
+    function name() external view returns (string memory);
+    function totalSupply() external view returns (uint256);
+    function holders() external view returns (address[] memory);
+}
+
+contract MockContractTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    string constant ABI =
+        "[{\"type\":\"function\",\"name\":\"balanceOf\",\"inputs\":[{\"name\":\"\",\"type\":\"address\"}],\"outputs\":[{\"name\":\"\",\"type\":\"uint256\"}],\"stateMutability\":\"view\"},"
+        "{\"type\":\"function\",\"name\":\"decimals\",\"inputs\":[],\"outputs\":[{\"name\":\"\",\"type\":\"uint8\"}],\"stateMutability\":\"view\"},"
+        "{\"type\":\"function\",\"name\":\"name\",\"inputs\":[],\"outputs\":[{\"name\":\"\",\"type\":\"string\"}],\"stateMutability\":\"view\"},"
+        "{\"type\":\"function\",\"name\":\"totalSupply\",\"inputs\":[],\"outputs\":[{\"name\":\"\",\"type\":\"uint256\"}],\"stateMutability\":\"view\"},"
+        "{\"type\":\"function\",\"name\":\"holders\",\"inputs\":[],\"outputs\":[{\"name\":\"\",\"type\":\"address[]\"}],\"stateMutability\":\"view\"}]";
+
+    IToken token = IToken(address(0x1337));
+
+    function setUp() public {
+        cheats.mockContract(
+            address(token),
+            ABI,
+            "{\"balanceOf(address)\":[\"100\"],\"decimals\":18,\"name\":\"Mock\",\"holders\":[\"0x0000000000000000000000000000000000000001\"]}"
+        );
+    }
+
+    function testMockContract() public {
+        assertEq(token.balanceOf(address(this)), 100);
+        assertEq(token.decimals(), 18);
+        assertEq(token.name(), "Mock");
+
+        address[] memory holders = token.holders();
+        assertEq(holders.length, 1);
+        assertEq(holders[0], address(1));
+    }
+
+    function testFailUnmockedFunction() public view {
+        token.totalSupply();
+    }
+
+    function testFailUnknownFunction() public {
+        cheats.mockContract(address(token), ABI, "{\"transfer\":true}");
+    }
+
+    function testFailWrongReturnCount() public {
+        cheats.mockContract(address(token), ABI, "{\"decimals\":[18,18]}");
+    }
+}
@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract ParseStringTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testParseBytes() public {
+        assertEq0(cheats.parseBytes("0x7109709ECfa91a80626fF3989D68f67F5b1DD12D"), hex"7109709ECfa91a80626fF3989D68f67F5b1DD12D");
+        assertEq0(cheats.parseBytes("6001"), hex"6001");
+    }
+
+    function testParseAddress() public {
+        address testAddress = 0x7109709ECfa91a80626fF3989D68f67F5b1DD12D;
+        assertEq(cheats.parseAddress(cheats.toString(testAddress)), testAddress);
+    }
+
+    function testParseUint() public {
+        assertEq(cheats.parseUint("115792089237316195423570985008687907853269984665640564039457584007913129639935"), type(uint256).max);
+        assertEq(cheats.parseUint(cheats.toString(uint256(42))), 42);
+        assertEq(cheats.parseUint("0x0100"), 256);
+    }
+
+    function testParseInt() public {
+        assertEq(cheats.parseInt(cheats.toString(int256(-42))), -42);
+        assertEq(cheats.parseInt("-0x10"), -16);
+    }
+
+    function testParseBytes32() public {
+        bytes32 testBytes = "test";
+        assertEq(cheats.parseBytes32(cheats.toString(testBytes)), testBytes);
+        assertEq(cheats.parseBytes32("0x74657374"), testBytes);
+    }
+
+    function testParseBool() public {
+        assertTrue(cheats.parseBool(cheats.toString(true)));
+        assertTrue(!cheats.parseBool("FALSE"));
+    }
+
+    function testParseInvalid() public {
+        cheats.expectRevert("Failed to parse `yes` as type `bool`: provided string was not `true` or `false`");
+        cheats.parseBool("yes");
+    }
+
+    function testStringManipulation() public {
+        assertEq(cheats.toUppercase("Hello"), "HELLO");
+        assertEq(cheats.toLowercase("Hello"), "hello");
+        assertEq(cheats.trim("  hello \n"), "hello");
+        assertEq(cheats.replace("hello world", "o", "0"), "hell0 w0rld");
+        assertEq(cheats.indexOf("hello world", "world"), 6);
+        assertEq(cheats.indexOf("hello world", "foundry"), type(uint256).max);
+
+        string[] memory parts = cheats.split("a,b,,c", ",");
+        assertEq(parts.length, 4);
+        assertEq(parts[0], "a");
+        assertEq(parts[2], "");
+        assertEq(cheats.concat(parts), "abc");
+    }
+}
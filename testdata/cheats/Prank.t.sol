@@ -93,6 +93,44 @@ contract NestedPranker {
     }
 }
 
+contract ScopedPranker {
+    Cheats constant cheats = Cheats(
+        address(bytes20(uint160(uint256(keccak256('hevm cheat code')))))
+    );
+
+    function prankWithin(Victim victim, address sender) public {
+        cheats.startPrank(sender);
+        victim.assertCallerAndOrigin(
+            sender,
+            "msg.sender was not set in scoped prank",
+            tx.origin,
+            "tx.origin invariant failed"
+        );
+        cheats.stopPrank();
+
+        victim.assertCallerAndOrigin(
+            address(this),
+            "msg.sender was not cleaned up in scoped prank",
+            tx.origin,
+            "tx.origin invariant failed"
+        );
+    }
+}
+
+contract PrankStopper {
+    Cheats constant cheats = Cheats(
+        address(bytes20(uint160(uint256(keccak256('hevm cheat code')))))
+    );
+
+    function stopPrank() public {
+        cheats.stopPrank();
+    }
+
+    function stopPrankScope(uint256 scope) public {
+        cheats.stopPrankScope(scope);
+    }
+}
+
 contract PrankTest is DSTest {
     Cheats constant cheats = Cheats(HEVM_ADDRESS);
 
@@ -305,6 +343,130 @@ contract PrankTest is DSTest {
         pranker.completePrank(victim);
     }
 
+    function testPrankNested(address outer, address inner) public {
+        Victim victim = new Victim();
+        ScopedPranker pranker = new ScopedPranker();
+
+        cheats.startPrank(outer);
+        pranker.prankWithin(victim, inner);
+        victim.assertCallerAndOrigin(
+            outer,
+            "msg.sender was not restored to the outer prank",
+            tx.origin,
+            "tx.origin invariant failed"
+        );
+        cheats.stopPrank();
+
+        victim.assertCallerAndOrigin(
+            address(this),
+            "msg.sender was not cleaned up",
+            tx.origin,
+            "tx.origin invariant failed"
+        );
+    }
+
+    function testPrankTwiceInSameFrame(address sender) public {
+        cheats.startPrank(sender);
+        try cheats.startPrank(sender) {
+            fail();
+        } catch Error(string memory reason) {
+            assertEq(reason, "You have an active prank already.");
+        }
+        cheats.stopPrank();
+    }
+
+    function testStopPrankFromOtherFrame(address sender) public {
+        Victim victim = new Victim();
+        PrankStopper stopper = new PrankStopper();
+
+        cheats.startPrank(sender);
+        stopper.stopPrank();
+        victim.assertCallerAndOrigin(
+            address(this),
+            "msg.sender was not cleaned up",
+            tx.origin,
+            "tx.origin invariant failed"
+        );
+    }
+
+    function testPrankScopesNested(address outer, address inner, address origin) public {
+        Victim victim = new Victim();
+        address oldOrigin = tx.origin;
+
+        uint256 outerScope = cheats.startPrankScope(outer);
+        uint256 innerScope = cheats.startPrankScope(inner, origin);
+        victim.assertCallerAndOrigin(
+            inner,
+            "msg.sender was not set in the inner scope",
+            origin,
+            "tx.origin was not set in the inner scope"
+        );
+
+        cheats.stopPrankScope(innerScope);
+        victim.assertCallerAndOrigin(
+            outer,
+            "msg.sender was not restored to the outer scope",
+            oldOrigin,
+            "tx.origin was not restored to the outer scope"
+        );
+
+        cheats.stopPrankScope(outerScope);
+        victim.assertCallerAndOrigin(
+            address(this),
+            "msg.sender was not cleaned up",
+            oldOrigin,
+            "tx.origin was not cleaned up"
+        );
+    }
+
+    function testPrankScopeWithinPrank(address outer, address inner) public {
+        Victim victim = new Victim();
+
+        cheats.startPrank(outer);
+        uint256 scope = cheats.startPrankScope(inner);
+        victim.assertCallerAndOrigin(
+            inner,
+            "msg.sender was not set in the scope",
+            tx.origin,
+            "tx.origin invariant failed"
+        );
+
+        cheats.stopPrankScope(scope);
+        victim.assertCallerAndOrigin(
+            outer,
+            "msg.sender was not restored to the outer prank",
+            tx.origin,
+            "tx.origin invariant failed"
+        );
+        cheats.stopPrank();
+    }
+
+    function testStopPrankScopeMisuse(address outer, address inner) public {
+        uint256 outerScope = cheats.startPrankScope(outer);
+        uint256 innerScope = cheats.startPrankScope(inner);
+
+        try cheats.stopPrankScope(outerScope) {
+            fail();
+        } catch Error(string memory reason) {
+            assertEq(reason, "Prank scope has nested pranks that are still active");
+        }
+
+        cheats.stopPrankScope(innerScope);
+        try cheats.stopPrankScope(innerScope) {
+            fail();
+        } catch Error(string memory reason) {
+            assertEq(reason, "Prank scope is not active");
+        }
+
+        PrankStopper stopper = new PrankStopper();
+        try stopper.stopPrankScope(outerScope) {
+            fail();
+        } catch Error(string memory reason) {
+            assertEq(reason, "Prank scope was started in another call frame");
+        }
+        cheats.stopPrankScope(outerScope);
+    }
+
     /// Checks that `tx.origin` is set for all subcalls of a `prank`.
     ///
     /// Ref: issue #1210
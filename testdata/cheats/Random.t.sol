@@ -0,0 +1,35 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract RandomTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testRandomUint() public {
+        // consecutive values differ
+        assertTrue(cheats.randomUint() != cheats.randomUint());
+    }
+
+    function testRandomUintInRange() public {
+        for (uint256 i = 0; i < 10; i++) {
+            uint256 value = cheats.randomUint(100, 200);
+            assertTrue(value >= 100 && value <= 200);
+        }
+        assertEq(cheats.randomUint(42, 42), 42);
+        cheats.randomUint(0, type(uint256).max);
+
+        cheats.expectRevert("min (2) is greater than max (1)");
+        cheats.randomUint(2, 1);
+    }
+
+    function testRandomAddress() public {
+        assertTrue(cheats.randomAddress() != cheats.randomAddress());
+    }
+
+    function testRandomBytes() public {
+        assertEq(cheats.randomBytes(7).length, 7);
+        assertEq(cheats.randomBytes(0).length, 0);
+    }
+}
@@ -53,4 +53,32 @@ contract RecordTest is DSTest {
         assertEq(innerWrites.length, 1, "number of nested writes is incorrect");
         assertEq(innerWrites[0], bytes32(uint256(2)), "key for nested write is incorrect");
     }
+
+    function testGetRecordedAccesses() public {
+        RecordAccess target = new RecordAccess();
+
+        cheats.record();
+        NestedRecordAccess inner = target.record();
+
+        Cheats.StorageAccess[] memory entries = cheats.getRecordedAccesses();
+        assertEq(entries.length, 4, "number of accesses is incorrect");
+
+        // `sstore(1, add(sload(1), 1))` in the target
+        assertEq(entries[0].account, address(target), "account of access 0 is incorrect");
+        assertEq(entries[0].caller, address(this), "caller of access 0 is incorrect");
+        assertEq(entries[0].slot, bytes32(uint256(1)), "slot of access 0 is incorrect");
+        assertTrue(!entries[0].isWrite, "access 0 is not a read");
+        assertEq(entries[1].account, address(target), "account of access 1 is incorrect");
+        assertTrue(entries[1].isWrite, "access 1 is not a write");
+
+        // `sstore(2, add(sload(2), 1))` in the nested call
+        assertEq(entries[2].account, address(inner), "account of access 2 is incorrect");
+        assertEq(entries[2].caller, address(target), "caller of access 2 is incorrect");
+        assertEq(entries[2].slot, bytes32(uint256(2)), "slot of access 2 is incorrect");
+        assertEq(entries[2].depth, entries[0].depth + 1, "depth of access 2 is incorrect");
+        assertTrue(entries[3].isWrite, "access 3 is not a write");
+
+        // the entries are consumed
+        assertEq(cheats.getRecordedAccesses().length, 0, "accesses were not consumed");
+    }
 }
@@ -0,0 +1,18 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract ReferenceEvmTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testWithCheatcodes() public {
+        cheats.roll(10);
+        assertEq(block.number, 10);
+    }
+
+    function testWithoutCheatcodes() public {
+        assertEq(uint256(1) + 1, 2);
+    }
+}
@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract RollBlocksTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testRollBlocks() public {
+        uint256 number = block.number;
+        uint256 timestamp = block.timestamp;
+        uint256 difficulty = block.difficulty;
+
+        cheats.rollBlocks(10, 12);
+        assertEq(block.number, number + 10, "rollBlocks did not advance the block number");
+        assertEq(block.timestamp, timestamp + 120, "rollBlocks did not advance the timestamp");
+        assertTrue(block.difficulty != difficulty, "rollBlocks did not change the difficulty");
+    }
+
+    function testRollBlocksFuzzed(uint64 blocks, uint32 interval) public {
+        uint256 number = block.number;
+        uint256 timestamp = block.timestamp;
+
+        cheats.rollBlocks(blocks, interval);
+        assertEq(block.number, number + blocks, "rollBlocks did not advance the block number");
+        assertEq(
+            block.timestamp,
+            timestamp + uint256(blocks) * interval,
+            "rollBlocks did not advance the timestamp"
+        );
+    }
+
+    function testRollBlocksZero() public {
+        uint256 difficulty = block.difficulty;
+
+        cheats.rollBlocks(0, 12);
+        assertEq(block.difficulty, difficulty, "rollBlocks changed the difficulty");
+    }
+}
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract SignTypedDataTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    // The `Mail` example of EIP-712
+    string constant MAIL =
+        "{\"types\":{\"EIP712Domain\":[{\"name\":\"name\",\"type\":\"string\"},{\"name\":\"version\",\"type\":\"string\"},{\"name\":\"chainId\",\"type\":\"uint256\"},{\"name\":\"verifyingContract\",\"type\":\"address\"}],\"Person\":[{\"name\":\"name\",\"type\":\"string\"},{\"name\":\"wallet\",\"type\":\"address\"}],\"Mail\":[{\"name\":\"from\",\"type\":\"Person\"},{\"name\":\"to\",\"type\":\"Person\"},{\"name\":\"contents\",\"type\":\"string\"}]},\"primaryType\":\"Mail\",\"domain\":{\"name\":\"Ether Mail\",\"version\":\"1\",\"chainId\":1,\"verifyingContract\":\"0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC\"},\"message\":{\"from\":{\"name\":\"Cow\",\"wallet\":\"0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826\"},\"to\":{\"name\":\"Bob\",\"wallet\":\"0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB\"},\"contents\":\"Hello, Bob!\"}}";
+
+    function testSignTypedData(uint248 pk) public {
+        cheats.assume(pk != 0);
+
+        (uint8 v, bytes32 r, bytes32 s) = cheats.signTypedData(pk, MAIL);
+        bytes32 digest = 0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2;
+
+        assertEq(ecrecover(digest, v, r, s), cheats.addr(pk), "typed data signer did not match");
+    }
+
+    function testSignTypedDataMatchesDigest() public {
+        (uint8 v, bytes32 r, bytes32 s) = cheats.signTypedData(1, MAIL);
+        (uint8 v2, bytes32 r2, bytes32 s2) =
+            cheats.sign(1, 0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2);
+
+        assertEq(v, v2);
+        assertEq(r, r2);
+        assertEq(s, s2);
+    }
+
+    function testFailInvalidTypedData() public {
+        cheats.signTypedData(1, "{}");
+    }
+}
@@ -51,4 +51,34 @@ contract SnapshotTest is DSTest {
         assertEq(block.timestamp, time, "snapshot revert for block.timestamp unsuccessful");
     }
 
-}
\ No newline at end of file
+}
+
+// tests that a snapshot taken in `setUp()` can be reverted to in every test
+contract SetupSnapshotTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    Storage store;
+    uint256 snapshot;
+
+    function setUp() public {
+        store.slot0 = 10;
+        store.slot1 = 20;
+        snapshot = cheats.snapshot();
+    }
+
+    function testRevertToSetupSnapshot() public {
+        store.slot0 = 300;
+        store.slot1 = 400;
+
+        assert(cheats.revertTo(snapshot));
+        assertEq(store.slot0, 10, "snapshot revert for slot 0 unsuccessful");
+        assertEq(store.slot1, 20, "snapshot revert for slot 1 unsuccessful");
+    }
+
+    function testRevertToSetupSnapshotAgain() public {
+        store.slot0 = 500;
+
+        assert(cheats.revertTo(snapshot));
+        assertEq(store.slot0, 10, "snapshot revert for slot 0 unsuccessful");
+    }
+}
@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract Counter {
+    uint256 public count;
+
+    function increment() public payable {
+        count += 1;
+    }
+
+    function incrementAndRevert() public {
+        count += 1;
+        revert("reverted");
+    }
+}
+
+contract Factory {
+    function deploy() public returns (Counter) {
+        return new Counter();
+    }
+}
+
+contract StateDiffTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+    Counter counter;
+
+    function setUp() public {
+        counter = new Counter();
+    }
+
+    function testRecordsCalls() public {
+        cheats.startStateDiffRecording();
+        counter.increment{value: 1}();
+        Cheats.AccountAccess[] memory accesses = cheats.stopAndReturnStateDiff();
+
+        assertEq(accesses.length, 1);
+        Cheats.AccountAccess memory access = accesses[0];
+        assertEq(uint256(access.kind), uint256(Cheats.AccountAccessKind.Call));
+        assertEq(access.account, address(counter));
+        assertEq(access.accessor, address(this));
+        assertTrue(access.initialized);
+        assertEq(access.value, 1);
+        assertEq(access.newBalance, access.oldBalance + 1);
+        assertEq(access.data, abi.encodeWithSelector(Counter.increment.selector));
+        assertTrue(!access.reverted);
+
+        // `count += 1` reads and then writes the slot
+        assertEq(access.storageAccesses.length, 2);
+        assertTrue(!access.storageAccesses[0].isWrite);
+        assertTrue(access.storageAccesses[1].isWrite);
+        assertEq(access.storageAccesses[1].previousValue, bytes32(uint256(0)));
+        assertEq(access.storageAccesses[1].newValue, bytes32(uint256(1)));
+    }
+
+    function testRecordsRevertedCalls() public {
+        cheats.startStateDiffRecording();
+        try counter.incrementAndRevert() {} catch {}
+        Cheats.AccountAccess[] memory accesses = cheats.stopAndReturnStateDiff();
+
+        assertEq(accesses.length, 1);
+        assertTrue(accesses[0].reverted);
+        assertTrue(accesses[0].storageAccesses[1].reverted);
+    }
+
+    function testRecordsCreates() public {
+        Factory factory = new Factory();
+        cheats.startStateDiffRecording();
+        Counter created = factory.deploy();
+        Cheats.AccountAccess[] memory accesses = cheats.stopAndReturnStateDiff();
+
+        assertEq(accesses.length, 2);
+        assertEq(uint256(accesses[0].kind), uint256(Cheats.AccountAccessKind.Call));
+        assertEq(uint256(accesses[1].kind), uint256(Cheats.AccountAccessKind.Create));
+        assertEq(accesses[1].account, address(created));
+        assertEq(accesses[1].accessor, address(factory));
+        assertTrue(!accesses[1].initialized);
+        assertEq(accesses[1].deployedCode, address(created).code);
+        assertEq(accesses[1].depth, accesses[0].depth + 1);
+    }
+
+    function testRecordsBalanceQueries() public {
+        cheats.startStateDiffRecording();
+        uint256 balance = address(counter).balance;
+        Cheats.AccountAccess[] memory accesses = cheats.stopAndReturnStateDiff();
+
+        assertEq(accesses.length, 1);
+        assertEq(uint256(accesses[0].kind), uint256(Cheats.AccountAccessKind.Balance));
+        assertEq(accesses[0].account, address(counter));
+        assertEq(accesses[0].accessor, address(this));
+        assertEq(accesses[0].oldBalance, balance);
+    }
+
+    function testStopsRecording() public {
+        cheats.startStateDiffRecording();
+        cheats.stopAndReturnStateDiff();
+        counter.increment();
+        assertEq(cheats.stopAndReturnStateDiff().length, 0);
+    }
+}
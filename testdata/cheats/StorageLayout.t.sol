@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract StorageLayoutTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testAppendedVariableIsCompatible() public {
+        cheats.assertStorageLayoutCompatible(
+            "../testdata/fixtures/StorageLayout/VaultV1.json",
+            "../testdata/fixtures/StorageLayout/VaultV2.json"
+        );
+    }
+
+    function testInsertedVariableIsIncompatible() public {
+        cheats.expectRevert(
+            "Incompatible storage layout: `fee` (uint256) at slot 1 collides with `balance` (uint256) of the old layout; moved `balance` from slot 1 (offset 0) to slot 2 (offset 0)"
+        );
+        cheats.assertStorageLayoutCompatible(
+            "../testdata/fixtures/StorageLayout/VaultV1.json",
+            "../testdata/fixtures/StorageLayout/VaultV2Broken.json"
+        );
+    }
+}
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract WaitTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testSleep() public {
+        cheats.sleep(10);
+    }
+
+    function testWaitForFile() public {
+        string memory path = "../testdata/fixtures/File/wait_for_file.txt";
+        cheats.writeFile(path, "ready");
+
+        cheats.waitForFile(path, 1000);
+
+        cheats.removeFile(path);
+    }
+
+    function testWaitForFileTimeout() public {
+        string memory path = "../testdata/fixtures/File/missing.txt";
+
+        cheats.expectRevert(
+            "Timed out after 200ms waiting for file ../testdata/fixtures/File/missing.txt"
+        );
+        cheats.waitForFile(path, 200);
+    }
+
+    function testWaitForFileNotAllowed() public {
+        cheats.expectRevert("Path is not allowed.");
+        cheats.waitForFile("/etc/hosts", 100);
+    }
+}
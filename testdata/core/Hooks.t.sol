@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract HooksTest is DSTest {
+    uint256 counter;
+
+    function setUp() public {
+        counter = 1;
+    }
+
+    function beforeEach() public {
+        counter += 1;
+    }
+
+    function afterEach() public {
+        assertTrue(counter < 10);
+    }
+
+    function testBeforeEach() public {
+        assertEq(counter, 2);
+    }
+
+    function testBeforeEachIsolated() public {
+        assertEq(counter, 2);
+        counter += 1;
+    }
+
+    function testAfterEachFails() public {
+        counter = 10;
+    }
+}
@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract TestSpecificSetup is DSTest {
+    uint256 balance;
+    uint256 withdrawn;
+
+    function setUp() public {
+        balance = 100;
+    }
+
+    function setUp_testWithdraw() public {
+        balance -= 40;
+        withdrawn = 40;
+    }
+
+    function testWithdraw() public {
+        assertEq(balance, 60);
+        assertEq(withdrawn, 40);
+    }
+
+    function testDeposit() public {
+        assertEq(balance, 100);
+        assertEq(withdrawn, 0);
+    }
+
+    function setUp_testFailingSetup() public {
+        require(false, "test setup failed predictably");
+    }
+
+    function testFailingSetup() public {}
+}
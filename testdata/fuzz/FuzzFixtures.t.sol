@@ -0,0 +1,16 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract FuzzFixturesTest is DSTest {
+    function fixtureAmount() public returns (uint256[] memory amounts) {
+        amounts = new uint256[](2);
+        amounts[0] = 1e18 - 1;
+        amounts[1] = 123456789;
+    }
+
+    function testFixtureAmount(uint256 amount) public {
+        assertTrue(amount != 123456789);
+    }
+}
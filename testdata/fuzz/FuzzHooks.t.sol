@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract FuzzHooksTest is DSTest {
+    uint256 counter;
+
+    function setUp() public {
+        counter = 1;
+    }
+
+    function beforeEach() public {
+        counter += 1;
+    }
+
+    function afterEach() public {
+        assertTrue(counter < 10);
+    }
+
+    function testFuzzBeforeEach(uint256 x) public {
+        assertEq(counter, 2);
+        counter += x % 5;
+    }
+
+    function setUp_testFuzzSetupOrder() public {
+        require(counter == 2, "beforeEach did not run first");
+        counter = 5;
+    }
+
+    function testFuzzSetupOrder(uint256 x) public {
+        assertEq(counter, 5);
+        counter += x % 5;
+    }
+
+    function testFuzzAfterEachFails(uint256 x) public {
+        counter = 10 + x % 5;
+    }
+}
@@ -0,0 +1,38 @@
+pragma solidity >=0.8.4;
+
+import "ds-test/test.sol";
+
+error OuterFailure(bytes reason);
+
+contract Inner {
+    function fail() external pure {
+        revert("inner failure");
+    }
+}
+
+contract Outer {
+    Inner inner = new Inner();
+
+    function call() external view {
+        try inner.fail() {} catch (bytes memory reason) {
+            revert OuterFailure(reason);
+        }
+    }
+
+    function swallow() external view {
+        try inner.fail() {} catch {}
+        revert("outer failure");
+    }
+}
+
+contract RevertChainTest is DSTest {
+    function testRevertChain() public {
+        Outer outer = new Outer();
+        outer.call();
+    }
+
+    function testSwallowedRevert() public {
+        Outer outer = new Outer();
+        outer.swallow();
+    }
+}
@@ -201,17 +201,15 @@ pub fn recurse_link<'a>(
                 }
             }
 
-            let mut deployed_address = None;
-
-            if let Some(library_file) = deployed_library_addresses
-                .libs
-                .get(&PathBuf::from_str(file).expect("Invalid library path."))
-            {
-                if let Some(address) = library_file.get(key) {
-                    deployed_address =
-                        Some(Address::from_str(address).expect("Invalid library address passed."));
-                }
-            }
+            // libraries given without a file (`<lib>:<addr>`) are stored under an empty path and
+            // are linked regardless of the file they are declared in
+            let library_file = PathBuf::from_str(file).expect("Invalid library path.");
+            let deployed_address = [library_file, PathBuf::new()]
+                .iter()
+                .find_map(|file| deployed_library_addresses.libs.get(file)?.get(key))
+                .map(|address| {
+                    Address::from_str(address).expect("Invalid library address passed.")
+                });
 
             let address = deployed_address.unwrap_or_else(|| {
                 ethers_core::utils::get_contract_address(sender, init_nonce + deployment.len())
@@ -935,6 +933,64 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn test_linking_deployed_libraries() {
+        let paths = ProjectPathsConfig::builder()
+            .root("../testdata")
+            .sources("../testdata/core")
+            .build()
+            .unwrap();
+
+        let project = Project::builder().paths(paths).ephemeral().no_artifacts().build().unwrap();
+
+        let output = project.compile().unwrap();
+        let contract_names =
+            ["Lib.json:Lib", "LibraryLinkingTest.json:LibraryLinkingTest", "NestedLib.json:NestedLib"];
+        let contracts = output
+            .into_artifacts()
+            .filter(|(i, _)| contract_names.contains(&i.slug().as_str()))
+            .map(|(id, c)| (id, c.into_contract_bytecode()))
+            .collect::<BTreeMap<ArtifactId, CompactContractBytecode>>();
+
+        let mut known_contracts: BTreeMap<ArtifactId, (Abi, Vec<u8>)> = Default::default();
+
+        // `Lib` is linked by name only, so only `NestedLib` has to be deployed
+        let lib_address = "0x1000000000000000000000000000000000000001";
+        let libraries = Libraries {
+            libs: BTreeMap::from([(
+                PathBuf::new(),
+                BTreeMap::from([("Lib".to_string(), lib_address.to_string())]),
+            )]),
+        };
+
+        link_with_nonce_or_address(
+            contracts,
+            &mut known_contracts,
+            libraries,
+            Address::default(),
+            U256::one(),
+            &mut (),
+            |file, key| (format!("{key}.json:{key}"), file, key),
+            |post_link_input| {
+                match post_link_input.id.slug().as_str() {
+                    "LibraryLinkingTest.json:LibraryLinkingTest" => {
+                        assert_eq!(post_link_input.dependencies.len(), 1);
+                        assert!(post_link_input.dependencies[0].0.contains(":NestedLib:"));
+                    }
+                    "NestedLib.json:NestedLib" => {
+                        assert!(post_link_input.dependencies.is_empty());
+                        let bytecode = post_link_input.contract.bytecode.unwrap().object;
+                        assert!(hex::encode(bytecode.into_bytes().unwrap())
+                            .contains(lib_address.trim_start_matches("0x")));
+                    }
+                    _ => {}
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+    }
+
     #[test]
     fn test_resolve_addr() {
         use std::str::FromStr;
@@ -4,8 +4,10 @@ use eyre::Result;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt};
 
-static SELECTOR_DATABASE_URL: &str = "https://sig.eth.samczsun.com/api/v1/signatures";
-static SELECTOR_IMPORT_URL: &str = "https://sig.eth.samczsun.com/api/v1/import";
+static SELECTOR_DATABASE_URL: &str = "https://api.openchain.xyz/signature-database/v1/lookup";
+static SELECTOR_IMPORT_URL: &str = "https://api.openchain.xyz/signature-database/v1/import";
+static FOURBYTE_FUNCTION_URL: &str = "https://www.4byte.directory/api/v1/signatures/";
+static FOURBYTE_EVENT_URL: &str = "https://www.4byte.directory/api/v1/event-signatures/";
 
 pub enum SelectorOrSig {
     Selector(String),
@@ -50,18 +52,40 @@ pub enum SelectorType {
     Event,
 }
 
-/// Decodes the given function or event selector using sig.eth.samczsun.com
+/// Decodes the given function or event selector using openchain.xyz, falling back to
+/// 4byte.directory if openchain does not know the selector
 pub async fn decode_selector(selector: &str, selector_type: SelectorType) -> Result<Vec<String>> {
+    let openchain = decode_selector_openchain(selector, selector_type).await;
+    if matches!(&openchain, Ok(sigs) if !sigs.is_empty()) {
+        return openchain
+    }
+
+    match decode_selector_fourbyte(selector, selector_type).await {
+        Ok(sigs) if !sigs.is_empty() => Ok(sigs),
+        // report the error of the primary database, if any
+        _ => match openchain {
+            Ok(_) => eyre::bail!("No signature found"),
+            Err(err) => Err(err),
+        },
+    }
+}
+
+/// Decodes the given function or event selector using openchain.xyz
+async fn decode_selector_openchain(
+    selector: &str,
+    selector_type: SelectorType,
+) -> Result<Vec<String>> {
     #[derive(Deserialize)]
     struct Decoded {
         name: String,
         filtered: bool,
     }
 
+    // unknown selectors are mapped to `null`
     #[derive(Deserialize)]
     struct ApiResult {
-        event: HashMap<String, Vec<Decoded>>,
-        function: HashMap<String, Vec<Decoded>>,
+        event: HashMap<String, Option<Vec<Decoded>>>,
+        function: HashMap<String, Option<Vec<Decoded>>>,
     }
 
     #[derive(Deserialize)]
@@ -70,7 +94,7 @@ pub async fn decode_selector(selector: &str, selector_type: SelectorType) -> Res
         result: ApiResult,
     }
 
-    // using samczsun signature database over 4byte
+    // using the openchain signature database (formerly sig.eth.samczsun.com) over 4byte
     // see https://github.com/foundry-rs/foundry/issues/1672
     let url = match selector_type {
         SelectorType::Function => format!("{SELECTOR_DATABASE_URL}?function={selector}"),
@@ -96,13 +120,49 @@ pub async fn decode_selector(selector: &str, selector_type: SelectorType) -> Res
 
     Ok(decoded
         .get(selector)
-        .ok_or(eyre::eyre!("No signature found"))?
-        .iter()
-        .filter_map(|d| (!d.filtered).then(|| d.name.clone()))
-        .collect::<Vec<String>>())
+        .and_then(Option::as_ref)
+        .map(|decoded| {
+            decoded.iter().filter_map(|d| (!d.filtered).then(|| d.name.clone())).collect()
+        })
+        .unwrap_or_default())
 }
 
-/// Fetches a function signature given the selector using sig.eth.samczsun.com
+/// Decodes the given function or event selector using 4byte.directory
+async fn decode_selector_fourbyte(
+    selector: &str,
+    selector_type: SelectorType,
+) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    struct Decoded {
+        id: u64,
+        text_signature: String,
+    }
+
+    #[derive(Deserialize)]
+    struct ApiResponse {
+        results: Vec<Decoded>,
+    }
+
+    let url = match selector_type {
+        SelectorType::Function => format!("{FOURBYTE_FUNCTION_URL}?hex_signature={selector}"),
+        SelectorType::Event => format!("{FOURBYTE_EVENT_URL}?hex_signature={selector}"),
+    };
+
+    let res = reqwest::get(url).await?.text().await?;
+    let mut api_response = match serde_json::from_str::<ApiResponse>(&res) {
+        Ok(inner) => inner,
+        Err(err) => {
+            eyre::bail!("Could not decode response:\n {res}.\nError: {err}")
+        }
+    };
+
+    // the oldest submission is the most likely to be the original signature, the others are
+    // usually collisions
+    api_response.results.sort_by_key(|decoded| decoded.id);
+    Ok(api_response.results.into_iter().map(|decoded| decoded.text_signature).collect())
+}
+
+/// Fetches a function signature given the selector using openchain.xyz or 4byte.directory
 pub async fn decode_function_selector(selector: &str) -> Result<Vec<String>> {
     let prefixed_selector = format!("0x{}", selector.strip_prefix("0x").unwrap_or(selector));
     if prefixed_selector.len() != 10 {
@@ -132,7 +192,7 @@ pub async fn decode_calldata(calldata: &str) -> Result<Vec<String>> {
         .collect::<Vec<String>>())
 }
 
-/// Fetches a event signature given the 32 byte topic using sig.eth.samczsun.com
+/// Fetches a event signature given the 32 byte topic using openchain.xyz or 4byte.directory
 pub async fn decode_event_topic(topic: &str) -> Result<Vec<String>> {
     let prefixed_topic = format!("0x{}", topic.strip_prefix("0x").unwrap_or(topic));
     if prefixed_topic.len() != 66 {
@@ -249,11 +309,11 @@ impl SelectorImportResponse {
             .iter()
             .for_each(|(k, v)| println!("Duplicated: Event {k}: {v}"));
 
-        println!("Selectors successfully uploaded to https://sig.eth.samczsun.com");
+        println!("Selectors successfully uploaded to https://openchain.xyz");
     }
 }
 
-/// uploads selectors to sig.eth.samczsun.com using the given data
+/// uploads selectors to openchain.xyz using the given data
 pub async fn import_selectors(data: SelectorImportData) -> Result<SelectorImportResponse> {
     let request = match &data {
         SelectorImportData::Abi(_) => {
@@ -451,3 +511,16 @@ async fn test_decode_event_topic() {
             .await;
     assert_eq!(decoded.unwrap()[0], "canCall(address,address,bytes4)".to_string());
 }
+
+#[tokio::test]
+async fn test_decode_selector_fourbyte() {
+    let sigs = decode_selector_fourbyte("0xa9059cbb", SelectorType::Function).await;
+    assert_eq!(sigs.unwrap()[0], "transfer(address,uint256)".to_string());
+
+    let sigs = decode_selector_fourbyte(
+        "0x0000000000000000000000000000000000000000000000000000000000000000",
+        SelectorType::Event,
+    )
+    .await;
+    assert!(sigs.unwrap().is_empty());
+}